@@ -2,7 +2,9 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Direction, Identifier, LineStyle, Note, Stereotype};
+use crate::common::{
+    Color, DiagramMetadata, Direction, Identifier, LineStyle, Link, Note, Span, Stereotype,
+};
 
 /// Диаграмма классов
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -86,6 +88,10 @@ pub struct Classifier {
     pub border_color: Option<Color>,
     /// Обобщённые параметры (generics)
     pub generics: Option<String>,
+    /// Гиперссылка `[[url]]`
+    pub link: Option<Link>,
+    /// Позиция объявления в исходном коде
+    pub span: Span,
 }
 
 impl Classifier {
@@ -100,6 +106,8 @@ impl Classifier {
             background_color: None,
             border_color: None,
             generics: None,
+            link: None,
+            span: Span::empty(),
         }
     }
 
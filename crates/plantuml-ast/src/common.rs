@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::sprite::SpriteRegistry;
+
 /// Позиция в исходном коде
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Span {
@@ -180,6 +182,83 @@ impl Stereotype {
             background_color: None,
         }
     }
+
+    /// Спрайт-иконка `<<$name>>` (имя без `$`)
+    pub fn sprite(&self) -> Option<&str> {
+        self.names
+            .iter()
+            .find_map(|name| name.trim().strip_prefix('$'))
+    }
+}
+
+/// Гиперссылка элемента: `[[url]]`, `[[url подсказка]]`, `[[url{подсказка}]]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    /// Адрес ссылки
+    pub url: String,
+    /// Всплывающая подсказка
+    pub tooltip: Option<String>,
+}
+
+impl Link {
+    /// Создаёт ссылку без подсказки
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            tooltip: None,
+        }
+    }
+
+    /// Добавляет подсказку
+    pub fn with_tooltip(mut self, tooltip: impl Into<String>) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// Разбирает ссылку `[[...]]` (скобки необязательны)
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text
+            .strip_prefix("[[")
+            .and_then(|t| t.strip_suffix("]]"))
+            .unwrap_or(text)
+            .trim();
+
+        let (url, tooltip) = match text.find('{') {
+            // [[url{подсказка}]] или [[url{подсказка} подпись]]
+            Some(open) => {
+                let rest = &text[open + 1..];
+                let tooltip = rest.find('}').map_or(rest, |close| &rest[..close]);
+                (&text[..open], tooltip)
+            }
+            // [[url подсказка]]
+            None => text.split_once(char::is_whitespace).unwrap_or((text, "")),
+        };
+
+        let url = url.trim();
+        if url.is_empty() {
+            return None;
+        }
+        let tooltip = tooltip.trim();
+        Some(Self {
+            url: url.to_string(),
+            tooltip: (!tooltip.is_empty()).then(|| tooltip.to_string()),
+        })
+    }
+
+    /// Извлекает первую ссылку из текста; возвращает текст без неё
+    pub fn extract(text: &str) -> (String, Option<Self>) {
+        let Some(start) = text.find("[[") else {
+            return (text.to_string(), None);
+        };
+        let Some(len) = text[start..].find("]]") else {
+            return (text.to_string(), None);
+        };
+        let end = start + len + 2;
+        let link = Self::parse(&text[start..end]);
+        let rest = format!("{} {}", text[..start].trim_end(), text[end..].trim_start());
+        (rest.trim().to_string(), link)
+    }
 }
 
 /// Заметка (note)
@@ -240,12 +319,37 @@ pub struct DiagramMetadata {
     pub footer: Option<String>,
     /// Масштаб
     pub scale: Option<f64>,
+    /// Спрайты, определённые в диаграмме (`sprite $name ...`)
+    #[serde(default, skip_serializing_if = "SpriteRegistry::is_empty")]
+    pub sprites: SpriteRegistry,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_link_parse() {
+        assert_eq!(
+            Link::parse("[[https://example.com]]"),
+            Some(Link::new("https://example.com"))
+        );
+        assert_eq!(
+            Link::parse("[[https://example.com Документация API]]"),
+            Some(Link::new("https://example.com").with_tooltip("Документация API"))
+        );
+        assert_eq!(
+            Link::parse("[[https://example.com{подсказка} подпись]]"),
+            Some(Link::new("https://example.com").with_tooltip("подсказка"))
+        );
+        assert_eq!(Link::parse("[[ ]]"), None);
+
+        let (text, link) = Link::extract("запрос [[/api/login вход]] к API");
+        assert_eq!(text, "запрос к API");
+        assert_eq!(link.unwrap().url, "/api/login");
+        assert_eq!(Link::extract("без ссылки").1, None);
+    }
+
     #[test]
     fn test_identifier_display_name() {
         let id = Identifier::new("Alice");
@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Direction, Note, Stereotype};
 
 /// Диаграмма компонентов/развёртывания
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -238,6 +238,12 @@ pub struct Connection {
     pub color: Option<Color>,
     /// Пунктирная линия
     pub dashed: bool,
+    /// Скрытая связь (`-[hidden]->`): влияет на раскладку, но не рисуется
+    #[serde(default)]
+    pub hidden: bool,
+    /// Направление из стрелки (`-up->`, `-[hidden]right-`)
+    #[serde(default)]
+    pub direction: Option<Direction>,
 }
 
 impl Connection {
@@ -250,6 +256,8 @@ impl Connection {
             label: None,
             color: None,
             dashed: false,
+            hidden: false,
+            direction: None,
         }
     }
 
@@ -264,6 +272,18 @@ impl Connection {
         self.dashed = true;
         self
     }
+
+    /// Делает скрытой
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Задаёт направление
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
 }
 
 /// Пакет/контейнер для компонентов
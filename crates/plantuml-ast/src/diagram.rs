@@ -6,6 +6,7 @@ use crate::activity::ActivityDiagram;
 use crate::class::ClassDiagram;
 use crate::common::DiagramMetadata;
 use crate::component::ComponentDiagram;
+use crate::dot::DotDiagram;
 use crate::gantt::GanttDiagram;
 use crate::json::JsonDiagram;
 use crate::mindmap::MindMapDiagram;
@@ -42,6 +43,7 @@ pub enum DiagramType {
     Salt,
     Er,
     Archimate,
+    Dot,
 }
 
 /// Корневой enum для всех типов диаграмм
@@ -83,6 +85,8 @@ pub enum Diagram {
     Salt(SaltDiagram),
     /// Archimate диаграмма (использует ComponentDiagram)
     Archimate(ComponentDiagram),
+    /// Graphviz DOT граф (`@startdot`)
+    Dot(DotDiagram),
 }
 
 impl Diagram {
@@ -107,6 +111,7 @@ impl Diagram {
             Diagram::Network(_) => DiagramType::Network,
             Diagram::Salt(_) => DiagramType::Salt,
             Diagram::Archimate(_) => DiagramType::Archimate,
+            Diagram::Dot(_) => DiagramType::Dot,
         }
     }
 
@@ -131,6 +136,32 @@ impl Diagram {
             Diagram::Network(d) => &d.metadata,
             Diagram::Salt(d) => &d.metadata,
             Diagram::Archimate(d) => &d.metadata,
+            Diagram::Dot(d) => &d.metadata,
+        }
+    }
+
+    /// Возвращает изменяемые метаданные диаграммы
+    pub fn metadata_mut(&mut self) -> &mut DiagramMetadata {
+        match self {
+            Diagram::Sequence(d) => &mut d.metadata,
+            Diagram::Class(d) => &mut d.metadata,
+            Diagram::Activity(d) => &mut d.metadata,
+            Diagram::State(d) => &mut d.metadata,
+            Diagram::Component(d) => &mut d.metadata,
+            Diagram::Deployment(d) => &mut d.metadata,
+            Diagram::UseCase(d) => &mut d.metadata,
+            Diagram::Object(d) => &mut d.metadata,
+            Diagram::Timing(d) => &mut d.metadata,
+            Diagram::Gantt(d) => &mut d.metadata,
+            Diagram::MindMap(d) => &mut d.metadata,
+            Diagram::Wbs(d) => &mut d.metadata,
+            Diagram::Json(d) => &mut d.metadata,
+            Diagram::Yaml(d) => &mut d.metadata,
+            Diagram::Er(d) => &mut d.metadata,
+            Diagram::Network(d) => &mut d.metadata,
+            Diagram::Salt(d) => &mut d.metadata,
+            Diagram::Archimate(d) => &mut d.metadata,
+            Diagram::Dot(d) => &mut d.metadata,
         }
     }
 }
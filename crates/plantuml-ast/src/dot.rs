@@ -0,0 +1,273 @@
+//! AST типы для Graphviz DOT диаграмм
+//!
+//! PlantUML поддерживает DOT-графы внутри `@startdot`:
+//!
+//! ```text
+//! @startdot
+//! digraph G {
+//!   rankdir=LR
+//!   node [shape=box]
+//!   subgraph cluster_0 {
+//!     label="Frontend"
+//!     a -> b
+//!   }
+//!   b -> c [label="calls", style=dashed]
+//! }
+//! @enddot
+//! ```
+//!
+//! Атрибуты хранятся как есть (строки), их интерпретация — задача layout.
+//! Значения по умолчанию (`node [...]`, `edge [...]`) уже применены парсером
+//! к каждому узлу и ребру.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{DiagramMetadata, Direction};
+
+/// Атрибуты графа, узла или ребра (`имя → значение`)
+pub type DotAttributes = BTreeMap<String, String>;
+
+/// DOT диаграмма
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DotDiagram {
+    /// Метаданные диаграммы
+    pub metadata: DiagramMetadata,
+    /// `strict` граф (без кратных рёбер)
+    pub strict: bool,
+    /// Ориентированный граф (`digraph`)
+    pub directed: bool,
+    /// Имя графа
+    pub name: Option<String>,
+    /// Атрибуты графа (`rankdir`, `label`, `splines`, ...)
+    pub attributes: DotAttributes,
+    /// Узлы в порядке первого упоминания
+    pub nodes: Vec<DotNode>,
+    /// Рёбра (цепочки `a -> b -> c` разворачиваются в пары)
+    pub edges: Vec<DotEdge>,
+    /// Подграфы верхнего уровня
+    pub subgraphs: Vec<DotSubgraph>,
+}
+
+impl DotDiagram {
+    /// Создаёт пустой граф
+    pub fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            ..Self::default()
+        }
+    }
+
+    /// Находит узел по идентификатору
+    pub fn find_node(&self, id: &str) -> Option<&DotNode> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    /// Направление рангов из атрибута `rankdir`
+    pub fn direction(&self) -> Direction {
+        match self
+            .attributes
+            .get("rankdir")
+            .map(|s| s.to_ascii_uppercase())
+        {
+            Some(dir) if dir == "LR" => Direction::LeftToRight,
+            Some(dir) if dir == "RL" => Direction::RightToLeft,
+            Some(dir) if dir == "BT" => Direction::BottomToTop,
+            _ => Direction::TopToBottom,
+        }
+    }
+}
+
+/// Узел графа
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DotNode {
+    /// Идентификатор узла
+    pub id: String,
+    /// Атрибуты (`shape`, `label`, `color`, `fillcolor`, `style`, ...)
+    pub attributes: DotAttributes,
+}
+
+impl DotNode {
+    /// Создаёт узел без атрибутов
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            attributes: DotAttributes::new(),
+        }
+    }
+
+    /// Устанавливает атрибут
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Текст узла: `label` (с подстановкой `\N`) или идентификатор
+    pub fn label(&self) -> String {
+        match self.attributes.get("label") {
+            Some(label) => label.replace("\\N", &self.id),
+            None => self.id.clone(),
+        }
+    }
+
+    /// Форма узла из атрибута `shape`
+    pub fn shape(&self) -> DotShape {
+        self.attributes
+            .get("shape")
+            .map(|s| DotShape::parse(s))
+            .unwrap_or_default()
+    }
+
+    /// Проверяет наличие стиля в атрибуте `style` (`"filled,rounded"`)
+    pub fn has_style(&self, style: &str) -> bool {
+        has_style(&self.attributes, style)
+    }
+}
+
+/// Форма узла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DotShape {
+    /// Эллипс (по умолчанию в Graphviz)
+    #[default]
+    Ellipse,
+    /// Круг (`circle`, `doublecircle`, `Mcircle`)
+    Circle,
+    /// Прямоугольник (`box`, `rect`, `record`, `note`, `component`, многоугольники)
+    Box,
+    /// Ромб (`diamond`, `Mdiamond`)
+    Diamond,
+    /// Точка (`point`)
+    Point,
+    /// Только текст (`plaintext`, `plain`, `none`)
+    PlainText,
+}
+
+impl DotShape {
+    /// Разбирает значение атрибута `shape`
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "ellipse" | "oval" | "egg" => Self::Ellipse,
+            "circle" | "doublecircle" | "mcircle" => Self::Circle,
+            "diamond" | "mdiamond" => Self::Diamond,
+            "point" => Self::Point,
+            "plaintext" | "plain" | "none" => Self::PlainText,
+            _ => Self::Box,
+        }
+    }
+}
+
+/// Ребро графа
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DotEdge {
+    /// Исходный узел
+    pub from: String,
+    /// Целевой узел
+    pub to: String,
+    /// Атрибуты (`label`, `color`, `style`, `dir`, `tailport`, `headport`, ...)
+    pub attributes: DotAttributes,
+}
+
+impl DotEdge {
+    /// Создаёт ребро без атрибутов
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+            attributes: DotAttributes::new(),
+        }
+    }
+
+    /// Устанавливает атрибут
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Метка ребра
+    pub fn label(&self) -> Option<&str> {
+        self.attributes.get("label").map(String::as_str)
+    }
+
+    /// Проверяет наличие стиля в атрибуте `style`
+    pub fn has_style(&self, style: &str) -> bool {
+        has_style(&self.attributes, style)
+    }
+}
+
+/// Подграф (`subgraph name { ... }` или анонимный `{ ... }`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DotSubgraph {
+    /// Имя подграфа
+    pub name: Option<String>,
+    /// Атрибуты подграфа (`label`, `color`, `style`, ...)
+    pub attributes: DotAttributes,
+    /// Узлы, упомянутые непосредственно в подграфе
+    pub nodes: Vec<String>,
+    /// Вложенные подграфы
+    pub subgraphs: Vec<DotSubgraph>,
+}
+
+impl DotSubgraph {
+    /// Создаёт подграф
+    pub fn new(name: Option<String>) -> Self {
+        Self {
+            name,
+            ..Self::default()
+        }
+    }
+
+    /// Кластер — подграф с именем `cluster*`, рисуется рамкой
+    pub fn is_cluster(&self) -> bool {
+        self.name
+            .as_deref()
+            .is_some_and(|name| name.starts_with("cluster"))
+    }
+
+    /// Заголовок кластера
+    pub fn label(&self) -> Option<&str> {
+        self.attributes.get("label").map(String::as_str)
+    }
+}
+
+/// Проверяет наличие элемента в списке стилей через запятую
+fn has_style(attributes: &DotAttributes, style: &str) -> bool {
+    attributes
+        .get("style")
+        .is_some_and(|s| s.split(',').any(|part| part.trim() == style))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_label_and_shape() {
+        let node = DotNode::new("a")
+            .with_attribute("label", "Node \\N")
+            .with_attribute("shape", "Mdiamond");
+        assert_eq!(node.label(), "Node a");
+        assert_eq!(node.shape(), DotShape::Diamond);
+        assert_eq!(DotNode::new("b").shape(), DotShape::Ellipse);
+    }
+
+    #[test]
+    fn test_direction_and_cluster() {
+        let mut diagram = DotDiagram::new(true);
+        diagram
+            .attributes
+            .insert("rankdir".to_string(), "lr".to_string());
+        assert_eq!(diagram.direction(), Direction::LeftToRight);
+
+        assert!(DotSubgraph::new(Some("cluster_db".to_string())).is_cluster());
+        assert!(!DotSubgraph::new(Some("same_rank".to_string())).is_cluster());
+        assert!(!DotSubgraph::new(None).is_cluster());
+    }
+
+    #[test]
+    fn test_edge_style() {
+        let edge = DotEdge::new("a", "b").with_attribute("style", "dashed, bold");
+        assert!(edge.has_style("dashed"));
+        assert!(!edge.has_style("dotted"));
+    }
+}
@@ -10,6 +10,7 @@ pub mod class;
 pub mod common;
 pub mod component;
 pub mod diagram;
+pub mod dot;
 pub mod er;
 pub mod gantt;
 pub mod json;
@@ -21,9 +22,11 @@ pub mod timing;
 pub mod usecase;
 pub mod network;
 pub mod salt;
+pub mod sprite;
 pub mod wbs;
 pub mod yaml;
 
 // Re-exports
 pub use common::*;
 pub use diagram::Diagram;
+pub use sprite::{Sprite, SpriteRegistry};
@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Identifier, LineStyle, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Identifier, LineStyle, Link, Note, Span, Stereotype};
 
 /// Диаграмма последовательностей
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -83,6 +83,10 @@ pub struct Participant {
     pub color: Option<Color>,
     /// Порядок (для явного указания позиции)
     pub order: Option<i32>,
+    /// Гиперссылка `[[url]]`
+    pub link: Option<Link>,
+    /// Позиция объявления в исходном коде
+    pub span: Span,
 }
 
 impl Participant {
@@ -94,6 +98,8 @@ impl Participant {
             stereotype: None,
             color: None,
             order: None,
+            link: None,
+            span: Span::empty(),
         }
     }
 
@@ -176,6 +182,10 @@ pub struct Message {
     pub create: bool,
     /// Уничтожить участника
     pub destroy: bool,
+    /// Гиперссылка `[[url]]`
+    pub link: Option<Link>,
+    /// Позиция сообщения в исходном коде
+    pub span: Span,
 }
 
 impl Message {
@@ -193,6 +203,8 @@ impl Message {
             deactivate: false,
             create: false,
             destroy: false,
+            link: None,
+            span: Span::empty(),
         }
     }
 
@@ -0,0 +1,322 @@
+//! Спрайты PlantUML: `sprite $name [WxH/16] {...}` и `sprite $name <svg ...>`.
+//!
+//! Определения спрайтов собираются парсером в [`SpriteRegistry`] диаграммы,
+//! а в подписях на них ссылаются `<$name>`, `<$name{scale=2}>` или
+//! `<$name,scale=0.5,color=red>`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Наибольшее число пикселей монохромного спрайта
+pub const MAX_SPRITE_PIXELS: usize = 4096 * 4096;
+
+/// Содержимое спрайта
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteData {
+    /// Монохромный спрайт: уровни серого по строкам (`0` — прозрачный
+    /// пиксель, `levels - 1` — цвет текста)
+    Gray { levels: u8, pixels: Vec<u8> },
+    /// Встроенное SVG изображение
+    Svg(String),
+}
+
+/// Спрайт
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sprite {
+    /// Ширина в пикселях
+    pub width: u32,
+    /// Высота в пикселях
+    pub height: u32,
+    /// Содержимое
+    pub data: SpriteData,
+}
+
+impl Sprite {
+    /// Создаёт монохромный спрайт; недостающие пиксели прозрачные,
+    /// уровни выше `levels - 1` ограничиваются. `None`, если пикселей больше
+    /// [`MAX_SPRITE_PIXELS`]
+    pub fn gray(width: u32, height: u32, levels: u8, mut pixels: Vec<u8>) -> Option<Self> {
+        let max = levels.saturating_sub(1);
+        pixels.resize(Self::pixel_count(width, height)?, 0);
+        for pixel in &mut pixels {
+            *pixel = (*pixel).min(max);
+        }
+        Some(Self {
+            width,
+            height,
+            data: SpriteData::Gray { levels, pixels },
+        })
+    }
+
+    /// Число пикселей `width × height`; `None` при переполнении или больше
+    /// [`MAX_SPRITE_PIXELS`]
+    pub fn pixel_count(width: u32, height: u32) -> Option<usize> {
+        (width as usize)
+            .checked_mul(height as usize)
+            .filter(|count| *count <= MAX_SPRITE_PIXELS)
+    }
+
+    /// Создаёт SVG спрайт; размер берётся из `width`/`height` или `viewBox`
+    /// корневого `<svg>`, по умолчанию 16×16
+    pub fn svg(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let (width, height) = svg_size(&source).unwrap_or((16, 16));
+        Self {
+            width,
+            height,
+            data: SpriteData::Svg(source),
+        }
+    }
+
+    /// Уровень серого пикселя (для SVG спрайтов — `None`)
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u8> {
+        match &self.data {
+            SpriteData::Gray { pixels, .. } if x < self.width && y < self.height => {
+                pixels.get(y as usize * self.width as usize + x as usize).copied()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Размер корневого `<svg>`: атрибуты `width`/`height`, иначе `viewBox`
+fn svg_size(source: &str) -> Option<(u32, u32)> {
+    let start = source.find("<svg")?;
+    let tag = &source[start..start + source[start..].find('>')?];
+    let number = |value: &str| -> Option<u32> {
+        let digits: String = value
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        digits.parse::<f64>().ok().map(|v| v.round() as u32)
+    };
+
+    if let (Some(w), Some(h)) = (attribute(tag, "width"), attribute(tag, "height")) {
+        if let (Some(w), Some(h)) = (number(w), number(h)) {
+            return Some((w, h));
+        }
+    }
+    let view_box: Vec<u32> = attribute(tag, "viewBox")?
+        .split([' ', ','])
+        .filter(|part| !part.is_empty())
+        .filter_map(number)
+        .collect();
+    match view_box.as_slice() {
+        [_, _, w, h] => Some((*w, *h)),
+        _ => None,
+    }
+}
+
+/// Значение атрибута тега в одинарных или двойных кавычках
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let preceded = rest[..pos].ends_with(char::is_whitespace);
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        if !preceded {
+            continue;
+        }
+        let after = after.trim_start();
+        let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &after[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+    None
+}
+
+/// Спрайты диаграммы по имени (без `$`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpriteRegistry {
+    sprites: BTreeMap<String, Sprite>,
+}
+
+impl SpriteRegistry {
+    /// Создаёт пустой реестр
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет спрайт; повторное определение заменяет предыдущее
+    pub fn insert(&mut self, name: impl Into<String>, sprite: Sprite) {
+        let name = name.into();
+        let name = name.strip_prefix('$').map(str::to_string).unwrap_or(name);
+        self.sprites.insert(name, sprite);
+    }
+
+    /// Спрайт по имени (`$` в начале необязателен)
+    pub fn get(&self, name: &str) -> Option<&Sprite> {
+        self.sprites.get(name.strip_prefix('$').unwrap_or(name))
+    }
+
+    /// Количество спрайтов
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    /// Реестр пуст
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Спрайты в порядке имён
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Sprite)> {
+        self.sprites
+            .iter()
+            .map(|(name, sprite)| (name.as_str(), sprite))
+    }
+
+    /// Подпись ссылается хотя бы на один известный спрайт
+    pub fn is_used_in(&self, text: &str) -> bool {
+        !self.is_empty()
+            && LabelPart::split(text).iter().any(|part| {
+                matches!(part, LabelPart::Sprite(sprite) if self.get(&sprite.name).is_some())
+            })
+    }
+}
+
+/// Ссылка на спрайт в тексте: `<$name>`, `<$name{scale=2,color=red}>`,
+/// `<$name,scale=2>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteRef {
+    /// Имя спрайта без `$`
+    pub name: String,
+    /// Масштаб
+    pub scale: f64,
+    /// Цвет монохромного спрайта (по умолчанию — цвет текста)
+    pub color: Option<String>,
+}
+
+impl SpriteRef {
+    /// Создаёт ссылку с масштабом 1
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            scale: 1.0,
+            color: None,
+        }
+    }
+
+    /// Разбирает ссылку без угловых скобок: `$name{scale=2}`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().strip_prefix('$')?;
+        let name_end = text
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(text.len());
+        if name_end == 0 {
+            return None;
+        }
+        let mut sprite = Self::new(&text[..name_end]);
+
+        let params = text[name_end..].trim();
+        let params = match params.strip_prefix('{') {
+            Some(inner) => inner.strip_suffix('}')?,
+            None if params.is_empty() => "",
+            None => params.strip_prefix(',')?,
+        };
+        for param in params.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=')?;
+            match key.trim().to_lowercase().as_str() {
+                "scale" => sprite.scale = value.trim().parse().ok().filter(|s: &f64| *s > 0.0)?,
+                "color" => sprite.color = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+        Some(sprite)
+    }
+}
+
+/// Фрагмент строки подписи: текст или спрайт
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelPart<'a> {
+    /// Обычный текст
+    Text(&'a str),
+    /// Ссылка `<$name>`
+    Sprite(SpriteRef),
+}
+
+impl<'a> LabelPart<'a> {
+    /// Делит строку на текст и ссылки на спрайты; некорректные ссылки
+    /// остаются текстом
+    pub fn split(line: &'a str) -> Vec<Self> {
+        let mut parts = Vec::new();
+        let mut text_start = 0;
+        let mut search = 0;
+        while let Some(offset) = line[search..].find("<$") {
+            let start = search + offset;
+            let Some(len) = line[start..].find('>') else {
+                break;
+            };
+            let end = start + len;
+            if let Some(sprite) = SpriteRef::parse(&line[start + 1..end]) {
+                if start > text_start {
+                    parts.push(Self::Text(&line[text_start..start]));
+                }
+                parts.push(Self::Sprite(sprite));
+                text_start = end + 1;
+            }
+            search = end + 1;
+        }
+        if text_start < line.len() {
+            parts.push(Self::Text(&line[text_start..]));
+        }
+        parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sprite_ref_parse() {
+        assert_eq!(SpriteRef::parse("$server"), Some(SpriteRef::new("server")));
+        let sprite = SpriteRef::parse("$db{scale=0.5,color=red}").unwrap();
+        assert_eq!(sprite.scale, 0.5);
+        assert_eq!(sprite.color.as_deref(), Some("red"));
+        assert_eq!(SpriteRef::parse("$db,scale=2").unwrap().scale, 2.0);
+        assert!(SpriteRef::parse("server").is_none());
+        assert!(SpriteRef::parse("$db{scale=x}").is_none());
+    }
+
+    #[test]
+    fn test_label_split() {
+        let parts = LabelPart::split("<$server> Web <b>x</b> <$db{scale=2}>");
+        assert_eq!(
+            parts,
+            vec![
+                LabelPart::Sprite(SpriteRef::new("server")),
+                LabelPart::Text(" Web <b>x</b> "),
+                LabelPart::Sprite(SpriteRef {
+                    name: "db".to_string(),
+                    scale: 2.0,
+                    color: None
+                }),
+            ]
+        );
+        assert_eq!(LabelPart::split("a <$ b"), vec![LabelPart::Text("a <$ b")]);
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = SpriteRegistry::new();
+        registry.insert("$dot", Sprite::gray(2, 2, 4, vec![0, 3, 9]).unwrap());
+        let sprite = registry.get("dot").unwrap();
+        assert_eq!(sprite.pixel(1, 0), Some(3));
+        // Уровень ограничен, недостающие пиксели прозрачные
+        assert_eq!(sprite.pixel(0, 1), Some(3));
+        assert_eq!(sprite.pixel(1, 1), Some(0));
+        assert!(registry.is_used_in("x <$dot>"));
+        assert!(!registry.is_used_in("x <$other>"));
+
+        let svg = Sprite::svg(r#"<svg viewBox="0 0 24 32"><path d="M0 0"/></svg>"#);
+        assert_eq!((svg.width, svg.height), (24, 32));
+        let svg = Sprite::svg(r#"<svg xmlns="x" width='10px' height="12"/>"#);
+        assert_eq!((svg.width, svg.height), (10, 12));
+    }
+}
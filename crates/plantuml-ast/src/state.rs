@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Link, Note, Span, Stereotype};
 
 /// Диаграмма состояний
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -61,6 +61,10 @@ pub struct State {
     pub exit_action: Option<String>,
     /// Действие во время нахождения
     pub do_action: Option<String>,
+    /// Гиперссылка `[[url]]`
+    pub link: Option<Link>,
+    /// Позиция объявления в исходном коде
+    pub span: Span,
 }
 
 impl State {
@@ -79,6 +83,8 @@ impl State {
             entry_action: None,
             exit_action: None,
             do_action: None,
+            link: None,
+            span: Span::empty(),
         }
     }
 
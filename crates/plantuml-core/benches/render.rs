@@ -0,0 +1,56 @@
+//! Сравнение PNG рендеринга: через SVG (usvg + resvg) и через display list
+//!
+//! Запуск: `cargo bench -p plantuml-core --features png`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use plantuml_core::{render, render_png, PngOptions, PngRenderer, RenderOptions};
+
+const SEQUENCE: &str = r#"@startuml
+participant Alice
+participant Bob
+participant Carol
+Alice -> Bob: Запрос авторизации
+Bob -> Carol: Проверка токена
+Carol --> Bob: Токен валиден
+alt успех
+    Bob --> Alice: 200 OK
+else ошибка
+    Bob --> Alice: 401 Unauthorized
+end
+@enduml"#;
+
+const CLASS: &str = r#"@startuml
+class Repository {
+  - items: Vec<Item>
+  + find(id: u64): Option<Item>
+  + save(item: Item)
+}
+interface Storage {
+  + load(): Vec<Item>
+}
+class Item {
+  + id: u64
+  + name: String
+}
+Repository ..|> Storage
+Repository "1" *-- "many" Item : содержит
+@enduml"#;
+
+fn png_paths(c: &mut Criterion) {
+    let options = RenderOptions::default();
+    let png_options = PngOptions::default();
+    let mut group = c.benchmark_group("png");
+    for (name, source) in [("sequence", SEQUENCE), ("class", CLASS)] {
+        group.bench_with_input(BenchmarkId::new("svg", name), source, |b, source| {
+            let renderer = PngRenderer::new(png_options.clone());
+            b.iter(|| renderer.render_svg(&render(source, &options).unwrap()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("direct", name), source, |b, source| {
+            b.iter(|| render_png(source, &options, &png_options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, png_paths);
+criterion_main!(benches);
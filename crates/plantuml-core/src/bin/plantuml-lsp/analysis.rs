@@ -0,0 +1,480 @@
+//! Анализ документа: препроцессинг, парсинг, символы и ошибки
+//!
+//! Строки текста после препроцессора переводятся в строки документа через
+//! `SourceMap`: строки, развёрнутые макросом, относятся к строке его вызова,
+//! строки из `!include` — к включённому файлу.
+
+use std::path::Path;
+
+use plantuml_ast::class::ClassifierType;
+use plantuml_ast::component::Component;
+use plantuml_ast::sequence::SequenceElement;
+use plantuml_ast::state::State;
+use plantuml_ast::Diagram;
+use plantuml_parser::DiagramKind;
+use plantuml_preprocessor::{
+    FileResolver, FsFileResolver, PreprocessContext, Preprocessor, SourceLocation, SourceMap,
+    UserCallable,
+};
+use plantuml_stdlib::StdlibResolver;
+
+/// Вид элемента диаграммы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Participant,
+    Class,
+    Interface,
+    Enum,
+    State,
+    Component,
+    Actor,
+    UseCase,
+}
+
+impl SymbolKind {
+    /// Название для подсказок
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Participant => "участник",
+            Self::Class => "класс",
+            Self::Interface => "интерфейс",
+            Self::Enum => "перечисление",
+            Self::State => "состояние",
+            Self::Component => "компонент",
+            Self::Actor => "актёр",
+            Self::UseCase => "прецедент",
+        }
+    }
+}
+
+/// Элемент диаграммы с положением объявления
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// Имя элемента
+    pub name: String,
+    /// Алиас (`as A`)
+    pub alias: Option<String>,
+    /// Вид элемента
+    pub kind: SymbolKind,
+    /// Положение объявления в исходниках (`None` — не найдено)
+    pub location: Option<SourceLocation>,
+    /// Вложенные элементы (составные состояния, компоненты)
+    pub children: Vec<Symbol>,
+}
+
+impl Symbol {
+    /// Совпадает ли имя или алиас со словом
+    pub fn is_named(&self, word: &str) -> bool {
+        self.name == word || self.alias.as_deref() == Some(word)
+    }
+}
+
+/// Ошибка препроцессора или парсера
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// Строка документа (с 0)
+    pub line: u32,
+    /// Текст ошибки
+    pub message: String,
+}
+
+/// Результат анализа документа
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// Тип диаграммы
+    pub kind: DiagramKind,
+    /// Строки текста после препроцессора
+    pub lines: Vec<String>,
+    /// Положения строк `lines` в исходниках
+    pub source_map: SourceMap,
+    /// Ошибки
+    pub problems: Vec<Problem>,
+    /// Элементы диаграммы в порядке объявления
+    pub symbols: Vec<Symbol>,
+    /// Функции и процедуры препроцессора
+    pub callables: Vec<UserCallable>,
+}
+
+impl Analysis {
+    /// Анализирует текст документа; `base_dir` — директория для `!include`
+    /// (без неё доступна только стандартная библиотека)
+    pub fn new(text: &str, base_dir: Option<&Path>) -> Self {
+        match base_dir {
+            Some(dir) => Self::with_resolver(
+                text,
+                StdlibResolver::with_fallback(FsFileResolver::new(dir)),
+            ),
+            None => Self::with_resolver(text, StdlibResolver::new()),
+        }
+    }
+
+    fn with_resolver<R: FileResolver>(text: &str, resolver: R) -> Self {
+        let mut ctx = PreprocessContext::new();
+        let result = Preprocessor::with_resolver(resolver).process_with_context(text, &mut ctx);
+
+        let mut analysis = Self {
+            kind: DiagramKind::Unknown,
+            lines: Vec::new(),
+            source_map: ctx.source_map,
+            problems: Vec::new(),
+            symbols: Vec::new(),
+            callables: ctx.callables.into_values().collect(),
+        };
+
+        let processed = match result {
+            Ok(processed) => processed,
+            Err(error) => {
+                let line = ctx
+                    .error_location
+                    .map_or(0, |location| problem_line(&location, text));
+                analysis.problems.push(Problem {
+                    line,
+                    message: error.to_string(),
+                });
+                analysis.kind = plantuml_parser::detect_diagram_type(text).unwrap_or(DiagramKind::Unknown);
+                return analysis;
+            }
+        };
+
+        analysis.kind =
+            plantuml_parser::detect_diagram_type(&processed).unwrap_or(DiagramKind::Unknown);
+        analysis.lines = processed.lines().map(str::to_string).collect();
+        match plantuml_parser::parse(&processed) {
+            Ok(diagram) => analysis.symbols = analysis.collect_symbols(&diagram),
+            Err(error) => {
+                let line = error
+                    .line()
+                    .and_then(|line| analysis.source_map.get(line))
+                    .map_or(0, |location| problem_line(location, text));
+                analysis.problems.push(Problem {
+                    line,
+                    message: error.to_string(),
+                });
+            }
+        }
+        analysis
+    }
+
+    /// Элемент с именем или алиасом `word`, включая вложенные
+    pub fn find_symbol(&self, word: &str) -> Option<&Symbol> {
+        fn find<'a>(symbols: &'a [Symbol], word: &str) -> Option<&'a Symbol> {
+            symbols.iter().find_map(|symbol| {
+                if symbol.is_named(word) {
+                    Some(symbol)
+                } else {
+                    find(&symbol.children, word)
+                }
+            })
+        }
+        find(&self.symbols, word)
+    }
+
+    /// Функция или процедура препроцессора по имени
+    pub fn find_callable(&self, name: &str) -> Option<&UserCallable> {
+        self.callables.iter().find(|callable| callable.name == name)
+    }
+
+    /// Текст, в который препроцессор развернул строку документа `line` (с 0)
+    pub fn expansion(&self, line: u32) -> Vec<&str> {
+        self.source_map
+            .iter()
+            .zip(&self.lines)
+            .filter(|(location, _)| line_in_document(location) == Some(line))
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+
+    /// Элементы диаграммы с положениями объявлений
+    fn collect_symbols(&self, diagram: &Diagram) -> Vec<Symbol> {
+        match diagram {
+            Diagram::Sequence(sequence) => {
+                let mut symbols: Vec<Symbol> = sequence
+                    .participants
+                    .iter()
+                    .map(|participant| {
+                        let id = &participant.id;
+                        let line = participant.span.line;
+                        self.symbol(&id.name, id.alias.as_deref(), SymbolKind::Participant, line)
+                    })
+                    .collect();
+                self.implicit_participants(&sequence.elements, &mut symbols);
+                symbols
+            }
+            Diagram::Class(class) => class
+                .classifiers
+                .iter()
+                .map(|classifier| {
+                    let kind = match classifier.classifier_type {
+                        ClassifierType::Interface => SymbolKind::Interface,
+                        ClassifierType::Enum => SymbolKind::Enum,
+                        _ => SymbolKind::Class,
+                    };
+                    let id = &classifier.id;
+                    self.symbol(&id.name, id.alias.as_deref(), kind, classifier.span.line)
+                })
+                .collect(),
+            Diagram::State(state) => self.state_symbols(&state.states),
+            Diagram::Component(component)
+            | Diagram::Deployment(component)
+            | Diagram::Archimate(component) => self.component_symbols(&component.components),
+            Diagram::UseCase(usecase) => {
+                let actors = usecase.actors.iter().map(|actor| {
+                    self.symbol(&actor.name, actor.alias.as_deref(), SymbolKind::Actor, 0)
+                });
+                let use_cases = usecase.use_cases.iter().map(|use_case| {
+                    self.symbol(&use_case.name, use_case.alias.as_deref(), SymbolKind::UseCase, 0)
+                });
+                actors.chain(use_cases).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Участники без объявления: положение — первое сообщение с ними
+    fn implicit_participants(&self, elements: &[SequenceElement], symbols: &mut Vec<Symbol>) {
+        for element in elements {
+            match element {
+                SequenceElement::Message(message) => {
+                    for name in [&message.from, &message.to] {
+                        let boundary = name.is_empty() || name == "[" || name == "]";
+                        if !boundary && !symbols.iter().any(|symbol| symbol.is_named(name)) {
+                            let line = message.span.line;
+                            symbols.push(self.symbol(name, None, SymbolKind::Participant, line));
+                        }
+                    }
+                }
+                SequenceElement::Fragment(fragment) => {
+                    for section in &fragment.sections {
+                        self.implicit_participants(&section.elements, symbols);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn state_symbols(&self, states: &[State]) -> Vec<Symbol> {
+        states
+            .iter()
+            .map(|state| {
+                let mut symbol =
+                    self.symbol(&state.name, state.alias.as_deref(), SymbolKind::State, state.span.line);
+                let regions = state.regions.iter().flatten().cloned();
+                let substates: Vec<State> = state.substates.iter().cloned().chain(regions).collect();
+                symbol.children = self.state_symbols(&substates);
+                symbol
+            })
+            .collect()
+    }
+
+    fn component_symbols(&self, components: &[Component]) -> Vec<Symbol> {
+        components
+            .iter()
+            .map(|component| {
+                let mut symbol =
+                    self.symbol(&component.name, component.alias.as_deref(), SymbolKind::Component, 0);
+                symbol.children = self.component_symbols(&component.children);
+                symbol
+            })
+            .collect()
+    }
+
+    /// Символ с положением: по строке из AST (`line` > 0) или поиском
+    /// объявления в тексте после препроцессора
+    fn symbol(&self, name: &str, alias: Option<&str>, kind: SymbolKind, line: usize) -> Symbol {
+        let line = match line {
+            0 => declaration_line(&self.lines, name, alias),
+            line => Some(line),
+        };
+        Symbol {
+            name: name.to_string(),
+            alias: alias.map(str::to_string),
+            kind,
+            location: line.and_then(|line| self.source_map.get(line)).cloned(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Строка документа (с 0), к которой относится положение: строка внешнего
+/// вызова макроса из документа, иначе сама строка основного исходника
+pub fn line_in_document(location: &SourceLocation) -> Option<u32> {
+    let call = location.macro_stack.iter().find(|call| call.file.is_none());
+    match call {
+        Some(call) => Some(call.line),
+        None => location.file.is_none().then_some(location.line),
+    }
+    .map(|line| line.saturating_sub(1) as u32)
+}
+
+/// Строка документа для ошибки: как [`line_in_document`], а для ошибки во
+/// включённом файле — строка `!include` этого файла
+fn problem_line(location: &SourceLocation, text: &str) -> u32 {
+    if let Some(line) = line_in_document(location) {
+        return line;
+    }
+    let Some(file) = location.file.as_deref() else {
+        return 0;
+    };
+    let name = Path::new(file)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(file);
+    text.lines()
+        .position(|line| include_target(line).is_some_and(|target| target.ends_with(name)))
+        .map_or(0, |line| line as u32)
+}
+
+/// Путь файла директивы `!include`/`!include_once`/`!includesub`/
+/// `!includedef`/`!import` без селектора блока; `None` для стандартной
+/// библиотеки `<...>` и прочих строк
+pub fn include_target(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix('!')?;
+    let (directive, spec) = rest.split_once(char::is_whitespace)?;
+    if !matches!(
+        directive,
+        "include" | "include_once" | "include_many" | "includesub" | "includedef" | "import"
+    ) {
+        return None;
+    }
+
+    let spec = spec.trim();
+    if spec.starts_with('<') {
+        return None;
+    }
+    let path = spec.trim_matches('"');
+    let path = path.rsplit_once('!').map_or(path, |(path, _)| path);
+    (!path.is_empty()).then_some(path)
+}
+
+/// Номер (с 1) первой строки, объявляющей элемент: с `as ALIAS`, иначе
+/// первой строки с его именем
+fn declaration_line(lines: &[String], name: &str, alias: Option<&str>) -> Option<usize> {
+    let code = |line: &&String| !line.trim_start().starts_with('\'');
+    alias
+        .and_then(|alias| {
+            lines
+                .iter()
+                .filter(code)
+                .position(|line| {
+                    line.split_whitespace()
+                        .collect::<Vec<_>>()
+                        .windows(2)
+                        .any(|pair| pair[0] == "as" && pair[1] == alias)
+                })
+                .and_then(|index| nth_code_line(lines, index))
+        })
+        .or_else(|| {
+            let position = lines
+                .iter()
+                .filter(code)
+                .position(|line| contains_word(line, name))?;
+            nth_code_line(lines, position)
+        })
+}
+
+/// Номер (с 1) `index`-й строки, не являющейся комментарием
+fn nth_code_line(lines: &[String], index: usize) -> Option<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim_start().starts_with('\''))
+        .nth(index)
+        .map(|(number, _)| number + 1)
+}
+
+/// Есть ли в строке `word` целым словом
+fn contains_word(line: &str, word: &str) -> bool {
+    !word.is_empty()
+        && line.match_indices(word).any(|(start, _)| {
+            let before = line[..start].chars().next_back();
+            let after = line[start + word.len()..].chars().next();
+            !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+        })
+}
+
+/// Символ идентификатора PlantUML (включая `$` переменных и макросов)
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Слово под курсором: `column` — смещение в символах UTF-16
+pub fn word_at(line: &str, column: u32) -> Option<&str> {
+    let mut offset = line.len();
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= column as usize {
+            offset = index;
+            break;
+        }
+        units += c.len_utf16();
+    }
+
+    let start = line[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(offset, |(index, _)| index);
+    let end = line[offset..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(line.len(), |(index, _)| offset + index);
+    (start < end).then(|| &line[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_preprocessor::MemoryFileResolver;
+
+    #[test]
+    fn test_symbols_and_expansion() {
+        let text = "@startuml\n!procedure $pair($a, $b)\n$a -> $b : ping\n$b -> $a : pong\n!endprocedure\nparticipant \"Web App\" as Web\n$pair(Web, Api)\n@enduml\n";
+        let analysis = Analysis::new(text, None);
+
+        assert_eq!(analysis.kind, DiagramKind::Sequence);
+        assert!(analysis.problems.is_empty());
+        let web = analysis.find_symbol("Web").expect("участник Web");
+        assert_eq!(web.name, "Web App");
+        assert_eq!(web.location.as_ref().and_then(line_in_document), Some(5));
+        // Участник, появившийся в развёрнутом макросе, указывает на строку вызова
+        let api = analysis.find_symbol("Api").expect("участник Api");
+        assert_eq!(api.location.as_ref().and_then(line_in_document), Some(6));
+
+        assert_eq!(analysis.expansion(6), ["Web -> Api : ping", "Api -> Web : pong"]);
+        assert_eq!(analysis.find_callable("$pair").map(|c| c.line), Some(3));
+    }
+
+    #[test]
+    fn test_problems() {
+        let files = MemoryFileResolver::new().with_file("lib/common.puml", "class A\nclass B {{{\n");
+        let text = "@startuml\n' общие классы\n!include lib/common.puml\n@enduml\n";
+        let analysis = Analysis::with_resolver(text, files);
+        assert_eq!(analysis.problems.len(), 1);
+        assert_eq!(analysis.problems[0].line, 2);
+
+        let text = "@startuml\nclass A\n!assert 1 == 2 : сломано\n@enduml\n";
+        let analysis = Analysis::new(text, None);
+        assert_eq!(analysis.problems[0].line, 2);
+        assert!(analysis.problems[0].message.contains("сломано"));
+
+        let analysis = Analysis::new("@startuml\nclass A\nclass B {{{\n@enduml\n", None);
+        assert_eq!(analysis.problems[0].line, 2);
+        assert_eq!(analysis.kind, DiagramKind::Class);
+    }
+
+    #[test]
+    fn test_include_target_and_word_at() {
+        assert_eq!(include_target("!include common.puml"), Some("common.puml"));
+        assert_eq!(include_target("  !includesub \"parts.puml!NODES\""), Some("parts.puml"));
+        assert_eq!(include_target("!include <C4/C4_Container>"), None);
+        assert_eq!(include_target("!define A B"), None);
+
+        assert_eq!(word_at("Alice -> Bob", 1), Some("Alice"));
+        assert_eq!(word_at("Alice -> Bob", 12), Some("Bob"));
+        assert_eq!(word_at("Alice -> Bob", 6), None);
+        assert_eq!(word_at("$pair(Web, Api)", 2), Some("$pair"));
+        assert_eq!(word_at("Иван -> Пётр", 9), Some("Пётр"));
+    }
+}
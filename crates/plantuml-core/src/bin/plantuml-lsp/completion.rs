@@ -0,0 +1,206 @@
+//! Автодополнение: ключевые слова типа диаграммы, директивы препроцессора,
+//! имена элементов и макросы документа
+
+use lsp_types::{CompletionItem, CompletionItemKind};
+use plantuml_parser::DiagramKind;
+use plantuml_preprocessor::CallableKind;
+
+use crate::analysis::{Analysis, Symbol, SymbolKind};
+
+/// Ключевые слова, общие для всех диаграмм
+const COMMON_KEYWORDS: &[&str] = &[
+    "@startuml", "@enduml", "title", "caption", "header", "footer", "legend", "endlegend",
+    "skinparam", "hide", "show", "scale", "note", "end note", "left to right direction",
+    "top to bottom direction",
+];
+
+const SEQUENCE_KEYWORDS: &[&str] = &[
+    "participant", "actor", "boundary", "control", "entity", "database", "collections", "queue",
+    "activate", "deactivate", "destroy", "create", "return", "autonumber", "alt", "else", "opt",
+    "loop", "par", "break", "critical", "group", "end", "ref over", "box", "end box",
+    "note left of", "note right of", "note over", "newpage", "hnote", "rnote",
+];
+
+const CLASS_KEYWORDS: &[&str] = &[
+    "class", "abstract class", "interface", "enum", "annotation", "entity", "package",
+    "namespace", "extends", "implements", "together", "note left of", "note right of",
+    "note top of", "note bottom of",
+];
+
+const ACTIVITY_KEYWORDS: &[&str] = &[
+    "start", "stop", "end", "if", "then", "elseif", "else", "endif", "while", "endwhile",
+    "repeat", "repeat while", "backward", "fork", "fork again", "end fork", "split",
+    "split again", "end split", "switch", "case", "endswitch", "partition", "detach", "kill",
+    "break",
+];
+
+const STATE_KEYWORDS: &[&str] = &["state", "[*]", "<<choice>>", "<<fork>>", "<<join>>", "<<end>>"];
+
+const COMPONENT_KEYWORDS: &[&str] = &[
+    "component", "interface", "package", "node", "folder", "frame", "cloud", "database",
+    "artifact", "storage", "card", "rectangle", "queue", "stack", "file", "port", "portin",
+    "portout",
+];
+
+const USECASE_KEYWORDS: &[&str] = &["actor", "usecase", "rectangle", "package", "extends", "include"];
+
+const OBJECT_KEYWORDS: &[&str] = &["object", "map", "package"];
+
+const TIMING_KEYWORDS: &[&str] = &["robust", "concise", "clock", "binary", "highlight"];
+
+const GANTT_KEYWORDS: &[&str] = &[
+    "project starts", "requires", "starts", "ends", "lasts", "happens", "is colored in",
+    "saturday are closed", "sunday are closed",
+];
+
+/// Директивы препроцессора (без `!`)
+const DIRECTIVES: &[&str] = &[
+    "include", "include_once", "include_many", "includesub", "includedef", "includeurl",
+    "import", "define", "undef", "ifdef", "ifndef", "if", "elseif", "else", "endif", "while",
+    "endwhile", "foreach", "endfor", "function", "endfunction", "procedure", "endprocedure",
+    "unquoted",
+    "return", "local", "global", "theme", "pragma", "assert", "log", "dump_memory",
+    "startsub", "endsub",
+];
+
+/// Ключевые слова типа диаграммы
+fn keywords(kind: DiagramKind) -> &'static [&'static str] {
+    match kind {
+        DiagramKind::Sequence => SEQUENCE_KEYWORDS,
+        DiagramKind::Class => CLASS_KEYWORDS,
+        DiagramKind::Activity => ACTIVITY_KEYWORDS,
+        DiagramKind::State => STATE_KEYWORDS,
+        DiagramKind::Component | DiagramKind::Deployment | DiagramKind::Archimate => {
+            COMPONENT_KEYWORDS
+        }
+        DiagramKind::UseCase => USECASE_KEYWORDS,
+        DiagramKind::Object => OBJECT_KEYWORDS,
+        DiagramKind::Timing => TIMING_KEYWORDS,
+        DiagramKind::Gantt => GANTT_KEYWORDS,
+        _ => &[],
+    }
+}
+
+/// Варианты для строки, набранной до курсора
+pub fn completions(analysis: &Analysis, prefix: &str) -> Vec<CompletionItem> {
+    let token = prefix
+        .rsplit(|c: char| c.is_whitespace() || "(),".contains(c))
+        .next()
+        .unwrap_or_default();
+
+    // Директива: `!` уже набран, подставляется только имя
+    if prefix.trim_start().starts_with('!') && !prefix.trim_start().contains(char::is_whitespace) {
+        return DIRECTIVES
+            .iter()
+            .map(|directive| CompletionItem {
+                label: format!("!{}", directive),
+                kind: Some(CompletionItemKind::KEYWORD),
+                insert_text: Some(directive.to_string()),
+                filter_text: Some(directive.to_string()),
+                ..Default::default()
+            })
+            .collect();
+    }
+
+    let mut items = Vec::new();
+    let macros_only = token.starts_with('$');
+    if !macros_only {
+        let words = keywords(analysis.kind).iter().chain(COMMON_KEYWORDS);
+        items.extend(words.map(|keyword| CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        }));
+        symbol_items(&analysis.symbols, &mut items);
+    }
+
+    items.extend(analysis.callables.iter().map(|callable| {
+        let (kind, detail) = match callable.kind {
+            CallableKind::Function => (CompletionItemKind::FUNCTION, "функция"),
+            CallableKind::Procedure => (CompletionItemKind::METHOD, "процедура"),
+        };
+        CompletionItem {
+            label: callable.name.clone(),
+            kind: Some(kind),
+            detail: Some(format!("{} {}({})", detail, callable.name, callable.parameters.join(", "))),
+            // `$` уже набран и не входит в слово редактора
+            insert_text: macros_only.then(|| callable.name.trim_start_matches('$').to_string()),
+            ..Default::default()
+        }
+    }));
+    items
+}
+
+/// Имена и алиасы элементов, включая вложенные
+fn symbol_items(symbols: &[Symbol], items: &mut Vec<CompletionItem>) {
+    for symbol in symbols {
+        let kind = match symbol.kind {
+            SymbolKind::Class => CompletionItemKind::CLASS,
+            SymbolKind::Interface => CompletionItemKind::INTERFACE,
+            SymbolKind::Enum => CompletionItemKind::ENUM,
+            SymbolKind::Component => CompletionItemKind::MODULE,
+            _ => CompletionItemKind::VARIABLE,
+        };
+        let names = std::iter::once(&symbol.name).chain(&symbol.alias);
+        for name in names {
+            if items.iter().any(|item| &item.label == name) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: name.clone(),
+                kind: Some(kind),
+                detail: Some(symbol.kind.description().to_string()),
+                ..Default::default()
+            });
+        }
+        symbol_items(&symbol.children, items);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(items: &[CompletionItem]) -> Vec<&str> {
+        items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_keywords_and_names() {
+        let analysis = Analysis::new(
+            "@startuml\n!function $greet($name)\n!return \"Hi \" + $name\n!endfunction\nparticipant Alice as A\nA -> Bob\n@enduml\n",
+            None,
+        );
+        let items = completions(&analysis, "Al");
+        let sequence = labels(&items);
+        assert!(sequence.contains(&"participant"));
+        assert!(sequence.contains(&"autonumber"));
+        assert!(!sequence.contains(&"endwhile"));
+        assert!(sequence.contains(&"Alice"));
+        assert!(sequence.contains(&"A"));
+        assert!(sequence.contains(&"Bob"));
+        assert!(sequence.contains(&"$greet"));
+
+        let analysis = Analysis::new("@startuml\nclass User\n@enduml\n", None);
+        let items = completions(&analysis, "");
+        let class = labels(&items);
+        assert!(class.contains(&"abstract class"));
+        assert!(class.contains(&"User"));
+        assert!(!class.contains(&"participant"));
+    }
+
+    #[test]
+    fn test_directives_and_macros() {
+        let analysis = Analysis::new(
+            "@startuml\n!procedure $box($name)\nrectangle $name\n!endprocedure\n@enduml\n",
+            None,
+        );
+        let items = completions(&analysis, "!inc");
+        assert!(items.iter().any(|item| item.label == "!include"
+            && item.insert_text.as_deref() == Some("include")));
+
+        let items = completions(&analysis, "  $b");
+        assert_eq!(labels(&items), ["$box"]);
+        assert_eq!(items[0].insert_text.as_deref(), Some("box"));
+    }
+}
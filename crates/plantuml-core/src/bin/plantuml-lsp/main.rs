@@ -0,0 +1,57 @@
+//! # plantuml-lsp
+//!
+//! Language Server Protocol для файлов `.puml`: диагностика препроцессора и
+//! парсера, автодополнение ключевых слов и имён элементов, переход к
+//! объявлениям алиасов, макросов и файлов `!include`, раскрытие макросов при
+//! наведении, структура документа и живой предпросмотр SVG.
+//!
+//! Сервер общается с редактором через stdin/stdout. Требует feature `lsp`:
+//!
+//! ```bash
+//! cargo install --path crates/plantuml-core --features lsp --bin plantuml-lsp
+//! ```
+
+mod analysis;
+mod completion;
+mod server;
+
+use std::process::ExitCode;
+
+use lsp_server::Connection;
+
+/// Справка по запуску
+const USAGE: &str = "\
+Использование: plantuml-lsp
+
+Language Server для PlantUML: запускается редактором и общается с ним через
+stdin/stdout по протоколу LSP.
+
+Команды workspace/executeCommand:
+  plantuml.preview <uri>      SVG документа; далее уведомления plantuml/preview
+                              при каждом изменении
+  plantuml.stopPreview <uri>  отключает уведомления предпросмотра";
+
+fn main() -> ExitCode {
+    if std::env::args().skip(1).any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let (connection, io_threads) = Connection::stdio();
+    let result = server::run(&connection);
+    // Поток записи завершается, когда закрыт канал отправки
+    drop(connection);
+    let joined = io_threads.join();
+
+    match (result, joined) {
+        (Ok(()), Ok(())) => ExitCode::SUCCESS,
+        (Err(e), _) => {
+            eprintln!("plantuml-lsp: {}", e);
+            ExitCode::FAILURE
+        }
+        (_, Err(e)) => {
+            eprintln!("plantuml-lsp: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
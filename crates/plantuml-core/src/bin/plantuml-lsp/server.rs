@@ -0,0 +1,585 @@
+//! Обработка сообщений LSP
+//!
+//! Документы синхронизируются целиком (`TextDocumentSyncKind::FULL`) и
+//! анализируются заново при каждом изменении и сохранении. Предпросмотр
+//! включается командой `plantuml.preview`: она возвращает SVG документа, а
+//! после каждого изменения сервер отправляет уведомление `plantuml/preview`
+//! с тем же содержимым.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    CompletionOptions, CompletionParams, Diagnostic, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DidSaveTextDocumentParams, DocumentSymbol, DocumentSymbolParams, ExecuteCommandOptions,
+    ExecuteCommandParams, GotoDefinitionParams, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, SymbolKind, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use plantuml_core::{FsFileResolver, RenderOptions};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::analysis::{self, Analysis, Symbol};
+use crate::completion;
+
+/// Команда предпросмотра: SVG документа и включение уведомлений
+pub const PREVIEW_COMMAND: &str = "plantuml.preview";
+/// Команда отключения уведомлений предпросмотра
+pub const STOP_PREVIEW_COMMAND: &str = "plantuml.stopPreview";
+/// Уведомление с SVG документа после изменения
+pub const PREVIEW_NOTIFICATION: &str = "plantuml/preview";
+
+/// Возможности сервера для ответа на `initialize`
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["!".to_string(), "$".to_string()]),
+            ..Default::default()
+        }),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![PREVIEW_COMMAND.to_string(), STOP_PREVIEW_COMMAND.to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Выполняет `initialize` и обрабатывает сообщения до `shutdown`
+pub fn run(connection: &Connection) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (id, _params) = connection.initialize_start()?;
+    connection.initialize_finish(
+        id,
+        json!({
+            "capabilities": capabilities(),
+            "serverInfo": { "name": "plantuml-lsp", "version": plantuml_core::version() },
+        }),
+    )?;
+
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        let outgoing = match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                vec![Message::Response(server.handle_request(request))]
+            }
+            Message::Notification(notification) => server
+                .handle_notification(notification)
+                .into_iter()
+                .map(Message::Notification)
+                .collect(),
+            Message::Response(_) => Vec::new(),
+        };
+        for message in outgoing {
+            connection.sender.send(message)?;
+        }
+    }
+    Ok(())
+}
+
+/// Открытый документ
+struct Document {
+    /// Текущий текст
+    text: String,
+    /// Результат анализа текста
+    analysis: Analysis,
+}
+
+/// Состояние сервера: открытые документы и документы с предпросмотром
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<Url, Document>,
+    previews: HashSet<Url>,
+}
+
+impl Server {
+    /// Отвечает на запрос
+    pub fn handle_request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            "textDocument/completion" => respond(request, |params| self.completion(params)),
+            "textDocument/definition" => respond(request, |params| self.definition(params)),
+            "textDocument/hover" => respond(request, |params| self.hover(params)),
+            "textDocument/documentSymbol" => {
+                respond(request, |params| self.document_symbols(params))
+            }
+            "workspace/executeCommand" => {
+                let id = request.id.clone();
+                match serde_json::from_value(request.params) {
+                    Ok(params) => match self.execute_command(params) {
+                        Ok(result) => Response::new_ok(id, result),
+                        Err(message) => {
+                            Response::new_err(id, ErrorCode::InvalidParams as i32, message)
+                        }
+                    },
+                    Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+                }
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("метод не поддерживается: {}", method),
+            ),
+        }
+    }
+
+    /// Обрабатывает уведомление; возвращает уведомления для клиента
+    pub fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let params = notification.params;
+        let uri = match notification.method.as_str() {
+            "textDocument/didOpen" => {
+                let Ok(params) = serde_json::from_value::<DidOpenTextDocumentParams>(params) else {
+                    return Vec::new();
+                };
+                let document = params.text_document;
+                self.update(document.uri.clone(), document.text);
+                document.uri
+            }
+            "textDocument/didChange" => {
+                let Ok(params) = serde_json::from_value::<DidChangeTextDocumentParams>(params)
+                else {
+                    return Vec::new();
+                };
+                // При полной синхронизации последнее изменение содержит весь текст
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return Vec::new();
+                };
+                self.update(params.text_document.uri.clone(), change.text);
+                params.text_document.uri
+            }
+            "textDocument/didSave" => {
+                // Включённые файлы могли измениться: документ анализируется заново
+                let Ok(params) = serde_json::from_value::<DidSaveTextDocumentParams>(params) else {
+                    return Vec::new();
+                };
+                let uri = params.text_document.uri;
+                let Some(text) = self.documents.get(&uri).map(|document| document.text.clone())
+                else {
+                    return Vec::new();
+                };
+                self.update(uri.clone(), text);
+                uri
+            }
+            "textDocument/didClose" => {
+                let Ok(params) = serde_json::from_value::<DidCloseTextDocumentParams>(params) else {
+                    return Vec::new();
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.previews.remove(&uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        };
+
+        let mut outgoing = vec![publish_diagnostics(uri.clone(), self.diagnostics(&uri))];
+        if self.previews.contains(&uri) {
+            outgoing.push(Notification::new(PREVIEW_NOTIFICATION.to_string(), self.preview(&uri)));
+        }
+        outgoing
+    }
+
+    /// Сохраняет текст документа и анализирует его
+    fn update(&mut self, uri: Url, text: String) {
+        let base_dir = base_dir(&uri);
+        let analysis = Analysis::new(&text, base_dir.as_deref());
+        self.documents.insert(uri, Document { text, analysis });
+    }
+
+    /// Ошибки препроцессора и парсера документа
+    fn diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        document
+            .analysis
+            .problems
+            .iter()
+            .map(|problem| Diagnostic {
+                range: line_range(&document.text, problem.line),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("plantuml".to_string()),
+                message: problem.message.clone(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Документ и строка под курсором
+    fn line_at(&self, position: &TextDocumentPositionParams) -> Option<(&Document, &str)> {
+        let document = self.documents.get(&position.text_document.uri)?;
+        let line = document.text.lines().nth(position.position.line as usize)?;
+        Some((document, line))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<Vec<lsp_types::CompletionItem>> {
+        let position = &params.text_document_position;
+        let (document, line) = self.line_at(position)?;
+        let prefix = utf16_prefix(line, position.position.character);
+        Some(completion::completions(&document.analysis, prefix))
+    }
+
+    /// Объявление алиаса, элемента или макроса под курсором, либо файл `!include`
+    fn definition(&self, params: GotoDefinitionParams) -> Option<Location> {
+        let position = &params.text_document_position_params;
+        let uri = &position.text_document.uri;
+        let (document, line) = self.line_at(position)?;
+
+        if let Some(target) = analysis::include_target(line) {
+            let path = base_dir(uri)?.join(target);
+            return Some(Location::new(Url::from_file_path(path).ok()?, Range::default()));
+        }
+
+        let word = analysis::word_at(line, position.position.character)?;
+        let analysis = &document.analysis;
+        let (file, line) = match analysis.find_callable(word) {
+            // Строка `!procedure`/`!function` — перед первой строкой тела
+            Some(callable) => (callable.file.clone(), callable.line.saturating_sub(2) as u32),
+            None => {
+                let location = analysis.find_symbol(word)?.location.as_ref()?;
+                match analysis::line_in_document(location) {
+                    Some(line) => (None, line),
+                    None => (location.file.clone(), location.line.saturating_sub(1) as u32),
+                }
+            }
+        };
+
+        let uri = match file {
+            None => uri.clone(),
+            Some(file) => Url::from_file_path(base_dir(uri)?.join(file)).ok()?,
+        };
+        let start = Position::new(line, 0);
+        Some(Location::new(uri, Range::new(start, start)))
+    }
+
+    /// Раскрытие макросов в строке под курсором и сигнатура макроса
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = &params.text_document_position_params;
+        let (document, line) = self.line_at(position)?;
+        let analysis = &document.analysis;
+
+        let mut sections = Vec::new();
+        let word = analysis::word_at(line, position.position.character);
+        if let Some(callable) = word.and_then(|word| analysis.find_callable(word)) {
+            let parameters: Vec<String> = callable
+                .parameters
+                .iter()
+                .zip(&callable.defaults)
+                .map(|(name, default)| match default {
+                    Some(default) => format!("{} = {}", name, default),
+                    None => name.clone(),
+                })
+                .collect();
+            let kind = match callable.kind {
+                plantuml_preprocessor::CallableKind::Function => "функция",
+                plantuml_preprocessor::CallableKind::Procedure => "процедура",
+            };
+            sections.push(format!("`{}({})` — {}", callable.name, parameters.join(", "), kind));
+        }
+
+        let expansion = analysis.expansion(position.position.line);
+        let unchanged = matches!(expansion.as_slice(), [single] if single.trim() == line.trim());
+        if !expansion.is_empty() && !unchanged {
+            sections.push(format!("```plantuml\n{}\n```", expansion.join("\n")));
+        }
+
+        (!sections.is_empty()).then(|| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: sections.join("\n\n"),
+            }),
+            range: None,
+        })
+    }
+
+    /// Участники, классы, состояния и другие элементы, объявленные в документе
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<Vec<DocumentSymbol>> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        Some(document_symbols(&document.analysis.symbols, &document.text))
+    }
+
+    fn execute_command(&mut self, params: ExecuteCommandParams) -> Result<Value, String> {
+        let uri = params
+            .arguments
+            .first()
+            .and_then(Value::as_str)
+            .and_then(|uri| Url::parse(uri).ok())
+            .ok_or_else(|| format!("{}: первый аргумент — URI документа", params.command))?;
+        if !self.documents.contains_key(&uri) {
+            return Err(format!("документ не открыт: {}", uri));
+        }
+
+        match params.command.as_str() {
+            PREVIEW_COMMAND => {
+                self.previews.insert(uri.clone());
+                Ok(self.preview(&uri))
+            }
+            STOP_PREVIEW_COMMAND => {
+                self.previews.remove(&uri);
+                Ok(Value::Null)
+            }
+            command => Err(format!("неизвестная команда: {}", command)),
+        }
+    }
+
+    /// SVG документа (`svg`) или текст ошибки рендеринга (`error`)
+    fn preview(&self, uri: &Url) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return json!({ "uri": uri, "error": "документ не открыт" });
+        };
+        let options = RenderOptions::default();
+        let rendered = match base_dir(uri) {
+            Some(dir) => {
+                plantuml_core::render_with_resolver(&document.text, FsFileResolver::new(dir), &options)
+            }
+            None => plantuml_core::render(&document.text, &options),
+        };
+        match rendered {
+            Ok(svg) => json!({ "uri": uri, "svg": svg }),
+            Err(e) => json!({ "uri": uri, "error": e.to_string() }),
+        }
+    }
+}
+
+/// Разбирает параметры запроса и отвечает результатом обработчика
+fn respond<P, R>(request: Request, handler: impl FnOnce(P) -> R) -> Response
+where
+    P: DeserializeOwned,
+    R: Serialize,
+{
+    match serde_json::from_value(request.params) {
+        Ok(params) => Response::new_ok(request.id, handler(params)),
+        Err(e) => Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string()),
+    }
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<Diagnostic>) -> Notification {
+    Notification::new(
+        "textDocument/publishDiagnostics".to_string(),
+        PublishDiagnosticsParams::new(uri, diagnostics, None),
+    )
+}
+
+/// Директория файла документа (`None` для несохранённых документов)
+fn base_dir(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok()?;
+    path.parent().map(Path::to_path_buf)
+}
+
+/// Диапазон строки документа целиком
+fn line_range(text: &str, line: u32) -> Range {
+    let length = text
+        .lines()
+        .nth(line as usize)
+        .map_or(0, |text| text.encode_utf16().count() as u32);
+    Range::new(Position::new(line, 0), Position::new(line, length))
+}
+
+/// Часть строки до позиции `column` в символах UTF-16
+fn utf16_prefix(line: &str, column: u32) -> &str {
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= column as usize {
+            return &line[..index];
+        }
+        units += c.len_utf16();
+    }
+    line
+}
+
+/// Элементы, объявленные в самом документе (не во включённых файлах)
+#[allow(deprecated)] // поле `deprecated` обязательно в lsp-types
+fn document_symbols(symbols: &[Symbol], text: &str) -> Vec<DocumentSymbol> {
+    symbols
+        .iter()
+        .filter_map(|symbol| {
+            let line = symbol.location.as_ref().and_then(analysis::line_in_document)?;
+            let range = line_range(text, line);
+            let kind = match symbol.kind {
+                analysis::SymbolKind::Participant | analysis::SymbolKind::Actor => SymbolKind::OBJECT,
+                analysis::SymbolKind::Class => SymbolKind::CLASS,
+                analysis::SymbolKind::Interface => SymbolKind::INTERFACE,
+                analysis::SymbolKind::Enum => SymbolKind::ENUM,
+                analysis::SymbolKind::State => SymbolKind::STRUCT,
+                analysis::SymbolKind::Component => SymbolKind::MODULE,
+                analysis::SymbolKind::UseCase => SymbolKind::EVENT,
+            };
+            let children = document_symbols(&symbol.children, text);
+            Some(DocumentSymbol {
+                name: symbol.name.clone(),
+                detail: Some(match &symbol.alias {
+                    Some(alias) => format!("{} {}", symbol.kind.description(), alias),
+                    None => symbol.kind.description().to_string(),
+                }),
+                kind,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: (!children.is_empty()).then_some(children),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_server::RequestId;
+
+    const SOURCE: &str = "@startuml\n!procedure $call($from, $to)\n$from -> $to : запрос\n!endprocedure\nparticipant \"Web App\" as Web\nparticipant Api\n$call(Web, Api)\n@enduml\n";
+
+    fn open(server: &mut Server, uri: &Url, text: &str) -> Vec<Notification> {
+        server.handle_notification(Notification::new(
+            "textDocument/didOpen".to_string(),
+            json!({
+                "textDocument": { "uri": uri, "languageId": "plantuml", "version": 1, "text": text }
+            }),
+        ))
+    }
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let response = server.handle_request(Request::new(RequestId::from(1), method.to_string(), params));
+        assert!(response.error.is_none(), "{:?}", response.error);
+        response.result.unwrap_or(Value::Null)
+    }
+
+    fn position(uri: &Url, line: u32, character: u32) -> Value {
+        json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } })
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = Server::default();
+        let uri = Url::parse("file:///project/broken.puml").unwrap();
+        let outgoing = open(&mut server, &uri, "@startuml\nclass A\nclass B {{{\n@enduml\n");
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].method, "textDocument/publishDiagnostics");
+        let diagnostics = &outgoing[0].params["diagnostics"];
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+
+        let outgoing = server.handle_notification(Notification::new(
+            "textDocument/didChange".to_string(),
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "@startuml\nclass A\n@enduml\n" }],
+            }),
+        ));
+        assert_eq!(outgoing[0].params["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_recursive_include() {
+        // Файл включает сам себя: диагностика вместо переполнения стека
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("self.puml");
+        let text = "@startuml\n!include self.puml\n@enduml\n";
+        std::fs::write(&path, text).unwrap();
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let mut server = Server::default();
+        let outgoing = open(&mut server, &uri, text);
+        let diagnostics = &outgoing[0].params["diagnostics"];
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+        assert!(diagnostics[0]["message"].as_str().unwrap().contains("self.puml -> self.puml"));
+
+        let outgoing = server.handle_notification(Notification::new(
+            "textDocument/didSave".to_string(),
+            json!({ "textDocument": { "uri": uri } }),
+        ));
+        assert_eq!(outgoing[0].params["diagnostics"].as_array().unwrap().len(), 1);
+
+        let preview = request(
+            &mut server,
+            "workspace/executeCommand",
+            json!({ "command": PREVIEW_COMMAND, "arguments": [uri] }),
+        );
+        assert!(preview["error"].as_str().unwrap().contains("рекурсивное включение"));
+    }
+
+    #[test]
+    fn test_definition_hover_and_symbols() {
+        let mut server = Server::default();
+        let uri = Url::parse("file:///project/main.puml").unwrap();
+        open(&mut server, &uri, SOURCE);
+
+        // Алиас Web в вызове макроса ведёт к объявлению участника
+        let location = request(&mut server, "textDocument/definition", position(&uri, 6, 7));
+        assert_eq!(location["range"]["start"]["line"], 4);
+        let location = request(&mut server, "textDocument/definition", position(&uri, 6, 2));
+        assert_eq!(location["range"]["start"]["line"], 1);
+
+        let hover = request(&mut server, "textDocument/hover", position(&uri, 6, 2));
+        let value = hover["contents"]["value"].as_str().unwrap();
+        assert!(value.contains("`$call($from, $to)` — процедура"));
+        assert!(value.contains("Web -> Api : запрос"));
+        assert_eq!(request(&mut server, "textDocument/hover", position(&uri, 5, 2)), Value::Null);
+
+        let symbols = request(
+            &mut server,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": uri } }),
+        );
+        let names: Vec<&str> = symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["Web App", "Api"]);
+    }
+
+    #[test]
+    fn test_include_definition() {
+        let mut server = Server::default();
+        let uri = Url::parse("file:///project/main.puml").unwrap();
+        open(&mut server, &uri, "@startuml\n!include styles/common.puml!1\n@enduml\n");
+
+        let location = request(&mut server, "textDocument/definition", position(&uri, 1, 12));
+        assert_eq!(location["uri"], "file:///project/styles/common.puml");
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut server = Server::default();
+        let uri = Url::parse("untitled:Untitled-1").unwrap();
+        open(&mut server, &uri, "@startuml\nAlice -> Bob\n@enduml\n");
+
+        let preview = request(
+            &mut server,
+            "workspace/executeCommand",
+            json!({ "command": PREVIEW_COMMAND, "arguments": [uri] }),
+        );
+        assert!(preview["svg"].as_str().unwrap().contains("<svg"));
+
+        // После включения предпросмотра изменения присылают новый SVG
+        let outgoing = server.handle_notification(Notification::new(
+            "textDocument/didChange".to_string(),
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "@startuml\nAlice -> Carol\n@enduml\n" }],
+            }),
+        ));
+        assert_eq!(outgoing.len(), 2);
+        assert_eq!(outgoing[1].method, PREVIEW_NOTIFICATION);
+        assert!(outgoing[1].params["svg"].as_str().unwrap().contains("Carol"));
+
+        request(
+            &mut server,
+            "workspace/executeCommand",
+            json!({ "command": STOP_PREVIEW_COMMAND, "arguments": [uri] }),
+        );
+        let outgoing = open(&mut server, &uri, "@startuml\nA -> B\n@enduml\n");
+        assert_eq!(outgoing.len(), 1);
+    }
+}
@@ -0,0 +1,123 @@
+//! LRU кэш готовых диаграмм
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Ключ кэша: формат вывода и исходный код целиком — при совпадении хэшей
+/// разных исходников ключи всё равно различаются
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    format: &'static str,
+    source: Arc<str>,
+}
+
+impl CacheKey {
+    pub fn new(format: &'static str, source: &str) -> Self {
+        Self {
+            format,
+            source: Arc::from(source),
+        }
+    }
+}
+
+/// LRU кэш фиксированной ёмкости; ёмкость 0 отключает кэширование
+pub struct LruCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, (Arc<Vec<u8>>, u64)>,
+    /// Момент последнего обращения → ключ, первым вытесняется самый старый
+    order: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl LruCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<Arc<Vec<u8>>> {
+        self.tick += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(Arc::clone(value))
+    }
+
+    pub fn insert(&mut self, key: CacheKey, value: Arc<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, key);
+
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(byte: u8) -> Arc<Vec<u8>> {
+        Arc::new(vec![byte])
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        let a = CacheKey::new("svg", "a");
+        let b = CacheKey::new("svg", "b");
+        let c = CacheKey::new("svg", "c");
+
+        cache.insert(a.clone(), value(1));
+        cache.insert(b.clone(), value(2));
+        // Обращение к `a` делает вытесняемым `b`
+        assert_eq!(cache.get(&a).as_deref(), Some(&vec![1]));
+        cache.insert(c.clone(), value(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&c).is_some());
+    }
+
+    #[test]
+    fn test_key_includes_format() {
+        let mut cache = LruCache::new(4);
+        cache.insert(CacheKey::new("svg", "a"), value(1));
+        assert!(cache.get(&CacheKey::new("png", "a")).is_none());
+
+        // Повторная вставка заменяет значение без роста кэша
+        cache.insert(CacheKey::new("svg", "a"), value(2));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.get(&CacheKey::new("svg", "a")).as_deref(),
+            Some(&vec![2])
+        );
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut cache = LruCache::new(0);
+        cache.insert(CacheKey::new("svg", "a"), value(1));
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -0,0 +1,118 @@
+//! Настройки сервера из аргументов командной строки
+
+use std::time::Duration;
+
+/// Справка по аргументам
+pub const USAGE: &str = "\
+Использование: plantuml-server [опции]
+
+Опции:
+  --bind <адрес>      адрес и порт (по умолчанию 127.0.0.1:8080)
+  --port <порт>       только порт, адрес 127.0.0.1
+  --threads <n>       число потоков обработки запросов
+  --cache <n>         ёмкость LRU кэша диаграмм, 0 — без кэша (256)
+  --max-size <байт>   максимальный размер исходного кода (1048576)
+  --timeout <сек>     ограничение времени рендеринга (10)
+  --renders <n>       одновременных рендерингов, включая превысившие
+                      время; сверх этого — 503 (по числу потоков)
+  -h, --help          эта справка";
+
+/// Настройки сервера
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    /// Адрес для прослушивания
+    pub bind: String,
+    /// Число потоков, принимающих запросы
+    pub threads: usize,
+    /// Ёмкость кэша готовых диаграмм
+    pub cache_size: usize,
+    /// Максимальный размер исходного кода (тело POST или декодированный URL)
+    pub max_source_size: usize,
+    /// Ограничение времени рендеринга одной диаграммы
+    pub timeout: Duration,
+    /// Максимум одновременно выполняющихся рендерингов
+    pub max_renders: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            threads,
+            cache_size: 256,
+            max_source_size: 1024 * 1024,
+            timeout: Duration::from_secs(10),
+            max_renders: threads,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Разбирает аргументы командной строки (без имени программы).
+    ///
+    /// `Ok(None)` — запрошена справка.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(None);
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("не указано значение для {}", arg))?;
+            match arg.as_str() {
+                "--bind" => config.bind = value,
+                "--port" => config.bind = format!("127.0.0.1:{}", number::<u16>(&arg, &value)?),
+                "--threads" => config.threads = number::<usize>(&arg, &value)?.max(1),
+                "--cache" => config.cache_size = number(&arg, &value)?,
+                "--max-size" => config.max_source_size = number(&arg, &value)?,
+                "--timeout" => config.timeout = Duration::from_secs(number(&arg, &value)?),
+                "--renders" => config.max_renders = number::<usize>(&arg, &value)?.max(1),
+                _ => return Err(format!("неизвестный аргумент {}", arg)),
+            }
+        }
+        Ok(Some(config))
+    }
+}
+
+fn number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("некорректное значение {} для {}", value, arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<ServerConfig>, String> {
+        ServerConfig::from_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let config = parse(&["--port", "9000", "--cache", "0", "--timeout", "3"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.bind, "127.0.0.1:9000");
+        assert_eq!(config.cache_size, 0);
+        assert_eq!(config.timeout, Duration::from_secs(3));
+
+        let config = parse(&["--bind", "0.0.0.0:80", "--threads", "0", "--renders", "2"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.bind, "0.0.0.0:80");
+        assert_eq!(config.threads, 1);
+        assert_eq!(config.max_renders, 2);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&["--help"]), Ok(None));
+        assert!(parse(&["--port"]).is_err());
+        assert!(parse(&["--port", "x"]).is_err());
+        assert!(parse(&["--unknown", "1"]).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+//! Маршруты сервера, совместимые с API сервера PlantUML
+//!
+//! - `GET /svg/{encoded}`, `/png/{encoded}`, `/txt/{encoded}` — текст в
+//!   кодировке `plantuml_core::encode` (префиксы `~1` и `~h` тоже принимаются)
+//! - `POST /svg`, `/png`, `/txt` — исходный код в теле запроса
+//! - `GET /health` — проверка работоспособности
+//!
+//! Префикс `/plantuml` (как у Java сервера) отбрасывается, поэтому в
+//! настройках клиентов достаточно заменить адрес сервера.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use plantuml_core::{OutputFormat, PngOptions, RenderOptions};
+
+use crate::cache::{CacheKey, LruCache};
+use crate::config::ServerConfig;
+
+/// Максимальная длина закодированного текста в URL
+const MAX_ENCODED_LEN: usize = 64 * 1024;
+
+/// Функция рендеринга диаграммы в байты ответа
+type Renderer = dyn Fn(Format, &str) -> plantuml_core::Result<Vec<u8>> + Send + Sync;
+
+/// Формат ответа
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Svg,
+    Png,
+    Txt,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "svg" => Some(Self::Svg),
+            "png" => Some(Self::Png),
+            "txt" => Some(Self::Txt),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Png => "png",
+            Self::Txt => "txt",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Svg => "image/svg+xml",
+            Self::Png => "image/png",
+            Self::Txt => "text/plain; charset=utf-8",
+        }
+    }
+
+    fn render(self, source: &str) -> plantuml_core::Result<Vec<u8>> {
+        match self {
+            Self::Svg => {
+                plantuml_core::render(source, &RenderOptions::default()).map(String::into_bytes)
+            }
+            Self::Png => {
+                let options = RenderOptions::new().with_format(OutputFormat::Png);
+                plantuml_core::render_image(source, &options, &PngOptions::default())
+            }
+            Self::Txt => {
+                let options = RenderOptions::new().with_format(OutputFormat::Txt);
+                plantuml_core::render(source, &options).map(String::into_bytes)
+            }
+        }
+    }
+}
+
+/// HTTP ответ
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Arc<Vec<u8>>,
+    /// Ответ зависит только от URL и может кэшироваться клиентом
+    pub immutable: bool,
+}
+
+impl Reply {
+    fn ok(format: Format, body: Arc<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            content_type: format.content_type(),
+            body,
+            immutable: false,
+        }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: Arc::new(message.into().into_bytes()),
+            immutable: false,
+        }
+    }
+}
+
+/// Общее состояние обработчиков запросов
+pub struct Handler {
+    config: ServerConfig,
+    cache: Mutex<LruCache>,
+    renderer: Arc<Renderer>,
+    /// Число выполняющихся рендерингов, включая превысившие время
+    active: Arc<AtomicUsize>,
+}
+
+impl Handler {
+    pub fn new(config: ServerConfig) -> Self {
+        Self::with_renderer(config, Arc::new(|format: Format, source: &str| format.render(source)))
+    }
+
+    fn with_renderer(config: ServerConfig, renderer: Arc<Renderer>) -> Self {
+        let cache = Mutex::new(LruCache::new(config.cache_size));
+        Self {
+            config,
+            cache,
+            renderer,
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// Обрабатывает запрос; тело уже прочитано не более чем на
+    /// `max_source_size + 1` байт
+    pub fn handle(&self, method: &str, url: &str, body: &[u8]) -> Reply {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let path = path.strip_prefix("/plantuml").unwrap_or(path);
+        let segments: Vec<&str> = path.trim_matches('/').splitn(2, '/').collect();
+
+        match (method, segments.as_slice()) {
+            ("GET" | "HEAD", ["health"]) => Reply {
+                status: 200,
+                content_type: "application/json",
+                body: Arc::new(
+                    format!(
+                        "{{\"status\":\"ok\",\"version\":\"{}\"}}",
+                        plantuml_core::version()
+                    )
+                    .into_bytes(),
+                ),
+                immutable: false,
+            },
+            ("GET" | "HEAD", [format, encoded]) => {
+                let Some(format) = Format::from_name(format) else {
+                    return Reply::error(404, "не найдено");
+                };
+                if encoded.len() > MAX_ENCODED_LEN {
+                    return Reply::error(414, "слишком длинный URL");
+                }
+                let source = match plantuml_core::decode_with_limit(
+                    encoded,
+                    self.config.max_source_size,
+                ) {
+                    Ok(source) => source,
+                    Err(plantuml_core::Error::SourceTooLarge(_)) => {
+                        return Reply::error(413, "исходный код превышает допустимый размер")
+                    }
+                    Err(e) => return Reply::error(400, e.to_string()),
+                };
+                let mut reply = self.render(format, source);
+                reply.immutable = reply.status == 200;
+                reply
+            }
+            ("POST", [format]) => {
+                let Some(format) = Format::from_name(format) else {
+                    return Reply::error(404, "не найдено");
+                };
+                match std::str::from_utf8(body) {
+                    Ok(source) => self.render(format, source.to_string()),
+                    Err(_) => Reply::error(400, "тело запроса не в UTF-8"),
+                }
+            }
+            (_, ["health"] | ["svg" | "png" | "txt", ..]) => {
+                Reply::error(405, "метод не поддерживается")
+            }
+            _ => Reply::error(404, "не найдено"),
+        }
+    }
+
+    /// Рендерит диаграмму с учётом кэша и ограничений размера и времени
+    fn render(&self, format: Format, source: String) -> Reply {
+        if source.len() > self.config.max_source_size {
+            return Reply::error(413, "исходный код превышает допустимый размер");
+        }
+        // Как и сервер PlantUML, принимаем текст без @startuml
+        let source = if source.contains("@start") {
+            source
+        } else {
+            format!("@startuml\n{}\n@enduml", source)
+        };
+
+        let key = CacheKey::new(format.name(), &source);
+        if let Some(body) = self.lock_cache().get(&key) {
+            return Reply::ok(format, body);
+        }
+
+        // Рендеринг в отдельном потоке: по истечении времени отвечаем 503,
+        // поток завершится сам, а результат будет отброшен. Незавершённые
+        // потоки занимают место, пока не закончат работу: при `max_renders`
+        // выполняющихся рендерингах новые запросы отклоняются
+        let Some(slot) = RenderSlot::acquire(&self.active, self.config.max_renders) else {
+            return Reply::error(503, "сервер перегружен, повторите запрос позже");
+        };
+        let renderer = Arc::clone(&self.renderer);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = renderer(format, &source);
+            // Место освобождается до ответа, чтобы следующий запрос его застал
+            drop(slot);
+            let _ = sender.send(result);
+        });
+        match receiver.recv_timeout(self.config.timeout) {
+            Ok(Ok(bytes)) => {
+                let body = Arc::new(bytes);
+                self.lock_cache().insert(key, Arc::clone(&body));
+                Reply::ok(format, body)
+            }
+            Ok(Err(e)) => Reply::error(400, e.to_string()),
+            Err(RecvTimeoutError::Timeout) => Reply::error(503, "превышено время рендеринга"),
+            Err(RecvTimeoutError::Disconnected) => {
+                Reply::error(500, "внутренняя ошибка рендеринга")
+            }
+        }
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, LruCache> {
+        // Кэш остаётся согласованным даже после паники в другом потоке
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Место в пуле рендеринга; освобождается при завершении потока, в том
+/// числе после паники
+struct RenderSlot(Arc<AtomicUsize>);
+
+impl RenderSlot {
+    fn acquire(active: &Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < limit).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(Arc::clone(active)))
+    }
+}
+
+impl Drop for RenderSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const SOURCE: &str = "@startuml\nAlice -> Bob: Hello\n@enduml";
+
+    fn handler() -> Handler {
+        Handler::new(ServerConfig::default())
+    }
+
+    #[test]
+    fn test_get_svg() {
+        let handler = handler();
+        let url = format!("/svg/{}", plantuml_core::encode(SOURCE));
+        let reply = handler.handle("GET", &url, &[]);
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, "image/svg+xml");
+        assert!(reply.immutable);
+        assert!(String::from_utf8_lossy(&reply.body).contains("<svg"));
+
+        // Повторный запрос берётся из кэша, префикс /plantuml допустим
+        let url = format!("/plantuml/svg/~1{}?v=1", plantuml_core::encode(SOURCE));
+        let cached = handler.handle("GET", &url, &[]);
+        assert!(Arc::ptr_eq(&reply.body, &cached.body));
+        assert_eq!(handler.lock_cache().len(), 1);
+    }
+
+    #[test]
+    fn test_png_and_txt() {
+        let handler = handler();
+        let encoded = plantuml_core::encode(SOURCE);
+
+        let png = handler.handle("GET", &format!("/png/{}", encoded), &[]);
+        assert_eq!(png.status, 200);
+        assert!(png.body.starts_with(b"\x89PNG"));
+
+        let txt = handler.handle("GET", &format!("/txt/{}", encoded), &[]);
+        assert_eq!(txt.status, 200);
+        assert!(String::from_utf8_lossy(&txt.body).contains("Alice"));
+    }
+
+    #[test]
+    fn test_getenv_disabled() {
+        let path = std::env::var("PATH").unwrap();
+        let source = "@startuml\nAlice -> Bob: [%getenv(\"PATH\")]\n@enduml";
+        let url = format!("/txt/{}", plantuml_core::encode(source));
+        let reply = handler().handle("GET", &url, &[]);
+        assert_eq!(reply.status, 200);
+        let text = String::from_utf8_lossy(&reply.body);
+        assert!(text.contains("[]"));
+        assert!(!text.contains(&path));
+    }
+
+    #[test]
+    fn test_post_source() {
+        let handler = handler();
+        // Текст без @startuml оборачивается автоматически
+        let reply = handler.handle("POST", "/svg", b"Alice -> Bob");
+        assert_eq!(reply.status, 200);
+        assert!(!reply.immutable);
+
+        assert_eq!(handler.handle("POST", "/svg", &[0xFF]).status, 400);
+        assert_eq!(handler.handle("POST", "/pdf", b"").status, 404);
+    }
+
+    #[test]
+    fn test_health_and_errors() {
+        let handler = handler();
+        let health = handler.handle("GET", "/health", &[]);
+        assert_eq!(health.status, 200);
+        assert!(String::from_utf8_lossy(&health.body).contains("\"status\":\"ok\""));
+
+        assert_eq!(handler.handle("GET", "/svg/abc$", &[]).status, 400);
+        assert_eq!(handler.handle("GET", "/gif/abc", &[]).status, 404);
+        assert_eq!(handler.handle("GET", "/", &[]).status, 404);
+        assert_eq!(handler.handle("DELETE", "/svg/abc", &[]).status, 405);
+        assert_eq!(handler.handle("PUT", "/health", &[]).status, 405);
+    }
+
+    #[test]
+    fn test_limits() {
+        let handler = Handler::new(ServerConfig {
+            max_source_size: 16,
+            ..ServerConfig::default()
+        });
+        assert_eq!(
+            handler.handle("POST", "/svg", SOURCE.as_bytes()).status,
+            413
+        );
+        let url = format!("/svg/{}", plantuml_core::encode(SOURCE));
+        assert_eq!(handler.handle("GET", &url, &[]).status, 413);
+        let url = format!("/svg/{}", "0".repeat(MAX_ENCODED_LEN + 1));
+        assert_eq!(handler.handle("GET", &url, &[]).status, 414);
+
+        // Распаковка ограничена размером исходника, а не только длиной URL
+        let url = format!("/svg/{}", plantuml_core::encode(&"A -> B\n".repeat(100_000)));
+        assert!(url.len() < MAX_ENCODED_LEN);
+        assert_eq!(handler.handle("GET", &url, &[]).status, 413);
+    }
+
+    #[test]
+    fn test_timeout_and_saturation() {
+        // Рендеринг блокируется до сигнала теста, поэтому исход не зависит
+        // от скорости машины
+        let (release, blocked) = mpsc::channel::<()>();
+        let blocked = Mutex::new(blocked);
+        let handler = Handler::with_renderer(
+            ServerConfig {
+                timeout: Duration::from_millis(10),
+                max_renders: 1,
+                ..ServerConfig::default()
+            },
+            Arc::new(move |_, _| {
+                let _ = blocked.lock().unwrap().recv();
+                Ok(Vec::new())
+            }),
+        );
+
+        let reply = handler.handle("POST", "/svg", SOURCE.as_bytes());
+        assert_eq!(reply.status, 503);
+        assert!(String::from_utf8_lossy(&reply.body).contains("время"));
+        // Превысивший время рендеринг всё ещё занимает единственное место
+        let reply = handler.handle("POST", "/svg", b"A -> C");
+        assert_eq!(reply.status, 503);
+        assert!(String::from_utf8_lossy(&reply.body).contains("перегружен"));
+        assert_eq!(handler.active.load(Ordering::Acquire), 1);
+
+        drop(release);
+        while handler.active.load(Ordering::Acquire) > 0 {
+            thread::yield_now();
+        }
+        assert_eq!(handler.handle("POST", "/svg", b"A -> C").status, 200);
+    }
+}
@@ -0,0 +1,110 @@
+//! # plantuml-server
+//!
+//! Локальный HTTP сервер рендеринга, совместимый с API сервера PlantUML
+//! (`/svg/{encoded}`, `/png/{encoded}`, `/txt/{encoded}`, `POST /svg`).
+//! Диаграммы рендерятся `plantuml-core` в том же процессе, готовые
+//! результаты хранятся в LRU кэше по исходному коду. `%getenv` в диаграммах
+//! всегда возвращает пустую строку: переменные окружения сервера не
+//! раскрываются клиентам.
+//!
+//! Требует feature `server`:
+//!
+//! ```bash
+//! cargo run -p plantuml-core --features server --bin plantuml-server -- --port 8080
+//! ```
+
+mod cache;
+mod config;
+mod handler;
+
+use std::io::Read;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+
+use tiny_http::{Header, Request, Response, Server};
+
+use config::{ServerConfig, USAGE};
+use handler::{Handler, Reply};
+
+fn main() -> ExitCode {
+    let config = match ServerConfig::from_args(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let server = match Server::http(&config.bind) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("не удалось открыть {}: {}", config.bind, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "plantuml-server {} слушает http://{}",
+        plantuml_core::version(),
+        config.bind
+    );
+
+    let handler = Arc::new(Handler::new(config));
+    let workers: Vec<_> = (0..handler.config().threads)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let handler = Arc::clone(&handler);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve(&handler, request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    ExitCode::SUCCESS
+}
+
+/// Читает тело с ограничением размера и отправляет ответ обработчика
+fn serve(handler: &Handler, mut request: Request) {
+    let limit = handler.config().max_source_size;
+    let reply = if request.body_length().is_some_and(|len| len > limit) {
+        Reply::error(413, "исходный код превышает допустимый размер")
+    } else {
+        let mut body = Vec::new();
+        let read = request
+            .as_reader()
+            .take(limit as u64 + 1)
+            .read_to_end(&mut body);
+        match read {
+            Ok(_) if body.len() > limit => {
+                Reply::error(413, "исходный код превышает допустимый размер")
+            }
+            Ok(_) => handler.handle(request.method().as_str(), request.url(), &body),
+            Err(e) => Reply::error(400, e.to_string()),
+        }
+    };
+
+    let mut response = Response::from_data(reply.body.as_slice())
+        .with_status_code(reply.status)
+        .with_header(header("Content-Type", reply.content_type))
+        .with_header(header("Access-Control-Allow-Origin", "*"));
+    if reply.immutable {
+        // Результат однозначно определяется закодированным исходником в URL
+        response = response.with_header(header(
+            "Cache-Control",
+            "public, max-age=31536000, immutable",
+        ));
+    }
+    let _ = request.respond(response);
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("корректный заголовок")
+}
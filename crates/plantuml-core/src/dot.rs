@@ -0,0 +1,1121 @@
+//! Экспорт диаграмм в Graphviz DOT
+//!
+//! Графовые диаграммы (классы, компоненты, состояния, прецеденты, объекты,
+//! ER и сами DOT графы) переводятся в `digraph`, который можно открыть в
+//! Graphviz или передать другим инструментам:
+//!
+//! - классификаторы, объекты и сущности — узлы `shape=record` с членами;
+//! - пакеты, composite состояния и контейнеры — кластеры `subgraph cluster_N`;
+//! - стереотипы и тип элемента — атрибуты `stereotype` и `kind`;
+//! - тип связи — стрелки (`arrowhead`, `arrowtail`) и стиль линии.
+//!
+//! Рёбра к кластеру (composite состояние, компонент с вложенными) идут к
+//! невидимому якорю внутри кластера с `lhead`/`ltail` (`compound=true`).
+
+use std::collections::HashMap;
+
+use plantuml_ast::class::{ClassDiagram, Classifier, Member, Package, RelationshipType};
+use plantuml_ast::common::{Color, DiagramMetadata, Direction, LineStyle, Stereotype};
+use plantuml_ast::component::{Component, ComponentDiagram, ComponentPackage, ConnectionType};
+use plantuml_ast::dot::{DotDiagram, DotSubgraph};
+use plantuml_ast::er::{Cardinality, ErDiagram};
+use plantuml_ast::object::{ObjectDiagram, ObjectLinkType};
+use plantuml_ast::state::{State, StateDiagram, StateType, Transition};
+use plantuml_ast::usecase::{UseCaseDiagram, UseCaseRelationType};
+use plantuml_ast::Diagram;
+
+use crate::{Error, Result};
+
+/// Атрибуты узла, ребра или графа
+type Attributes = Vec<(String, String)>;
+
+/// Экспортирует диаграмму в Graphviz DOT.
+///
+/// Поддерживаются графовые диаграммы: классы, объекты, компоненты
+/// (включая deployment и archimate), состояния, прецеденты, ER и DOT.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{export_dot, parse_diagram};
+///
+/// let diagram = parse_diagram("@startuml\nclass A\nclass B\nA --> B\n@enduml").unwrap();
+/// let dot = export_dot(&diagram).unwrap();
+/// assert!(dot.starts_with("digraph"));
+/// assert!(dot.contains("\"A\" -> \"B\""));
+/// ```
+pub fn export_dot(diagram: &Diagram) -> Result<String> {
+    match diagram {
+        Diagram::Class(d) => Ok(export_class(d)),
+        Diagram::Object(d) => Ok(export_object(d)),
+        Diagram::Component(d) | Diagram::Deployment(d) | Diagram::Archimate(d) => {
+            Ok(export_component(d))
+        }
+        Diagram::State(d) => Ok(export_state(d)),
+        Diagram::UseCase(d) => Ok(export_usecase(d)),
+        Diagram::Er(d) => Ok(export_er(d)),
+        Diagram::Dot(d) => Ok(export_graph(d)),
+        other => Err(Error::UnsupportedDiagram(format!(
+            "DOT экспорт недоступен для {:?}",
+            other.diagram_type()
+        ))),
+    }
+}
+
+/// Диаграмма классов
+fn export_class(diagram: &ClassDiagram) -> String {
+    let mut w = DotWriter::new(true, &diagram.metadata);
+    w.attribute("node", attrs([("shape", "record")]));
+
+    let mut ids = Ids::default();
+    for classifier in &diagram.classifiers {
+        class_node(&mut w, &mut ids, classifier);
+    }
+    for package in &diagram.packages {
+        class_package(&mut w, &mut ids, package);
+    }
+
+    for rel in &diagram.relationships {
+        let mut a = Attributes::new();
+        // Родитель выше потомка: ребро наследования идёт от родителя (`to`)
+        let (from, to, tail, head) = match rel.relationship_type {
+            RelationshipType::Inheritance | RelationshipType::Realization => {
+                push(&mut a, "dir", "back");
+                push(&mut a, "arrowtail", "empty");
+                (
+                    &rel.to,
+                    &rel.from,
+                    &rel.to_cardinality,
+                    &rel.from_cardinality,
+                )
+            }
+            _ => (
+                &rel.from,
+                &rel.to,
+                &rel.from_cardinality,
+                &rel.to_cardinality,
+            ),
+        };
+        match rel.relationship_type {
+            RelationshipType::Composition | RelationshipType::Aggregation => {
+                push(&mut a, "dir", "both");
+                let diamond = if rel.relationship_type == RelationshipType::Composition {
+                    "diamond"
+                } else {
+                    "odiamond"
+                };
+                push(&mut a, "arrowtail", diamond);
+                push(&mut a, "arrowhead", "none");
+            }
+            RelationshipType::Link => push(&mut a, "arrowhead", "none"),
+            RelationshipType::Dependency => push(&mut a, "arrowhead", "vee"),
+            _ => {}
+        }
+        let style = match rel.relationship_type {
+            RelationshipType::Realization | RelationshipType::Dependency => Some("dashed"),
+            _ => line_style(rel.line_style),
+        };
+        if let Some(style) = style {
+            push(&mut a, "style", style);
+        }
+        push_opt(&mut a, "label", rel.label.as_deref());
+        push_opt(&mut a, "taillabel", tail.as_deref());
+        push_opt(&mut a, "headlabel", head.as_deref());
+        w.edge(&ids.get(from), &ids.get(to), &a);
+    }
+
+    w.finish()
+}
+
+/// Пакет диаграммы классов — кластер
+fn class_package(w: &mut DotWriter, ids: &mut Ids, package: &Package) {
+    let mut a = attrs([("label", package.name.as_str())]);
+    push_stereotype(&mut a, package.stereotype.as_ref());
+    push_color(&mut a, "bgcolor", package.background_color.as_ref());
+    w.open_cluster(&a);
+    for classifier in &package.classifiers {
+        class_node(w, ids, classifier);
+    }
+    for nested in &package.packages {
+        class_package(w, ids, nested);
+    }
+    w.close();
+}
+
+/// Классификатор — record `{Имя|поля|методы}`
+fn class_node(w: &mut DotWriter, ids: &mut Ids, classifier: &Classifier) {
+    let id = ids.declare(&classifier.id.name, classifier.id.alias.as_deref());
+
+    let mut name = classifier.id.name.clone();
+    if let Some(generics) = &classifier.generics {
+        name = format!("{}<{}>", name, generics);
+    }
+    let fields: Vec<String> = classifier
+        .fields
+        .iter()
+        .map(|m| format_member(m, false))
+        .collect();
+    let methods: Vec<String> = classifier
+        .methods
+        .iter()
+        .map(|m| format_member(m, true))
+        .collect();
+
+    let mut a = attrs([
+        ("label", record(&name, &[&fields, &methods])),
+        (
+            "kind",
+            format!("{:?}", classifier.classifier_type).to_lowercase(),
+        ),
+    ]);
+    push_stereotype(&mut a, classifier.stereotype.as_ref());
+    push_fill(&mut a, classifier.background_color.as_ref());
+    push_color(&mut a, "color", classifier.border_color.as_ref());
+    w.node(&id, &a);
+}
+
+/// Поле или метод: `+name(a : T) : R`
+fn format_member(member: &Member, method: bool) -> String {
+    let mut text = format!("{}{}", member.visibility.to_char(), member.name);
+    if method && !member.name.ends_with(')') {
+        let params: Vec<String> = member
+            .parameters
+            .iter()
+            .map(|p| {
+                if p.name.is_empty() {
+                    p.param_type.clone()
+                } else {
+                    format!("{} : {}", p.name, p.param_type)
+                }
+            })
+            .collect();
+        text.push_str(&format!("({})", params.join(", ")));
+    }
+    if let Some(member_type) = &member.member_type {
+        text.push_str(&format!(" : {}", member_type));
+    }
+    if member.is_static {
+        text = format!("{{static}} {}", text);
+    }
+    if member.is_abstract {
+        text = format!("{{abstract}} {}", text);
+    }
+    text
+}
+
+/// Диаграмма объектов
+fn export_object(diagram: &ObjectDiagram) -> String {
+    let mut w = DotWriter::new(true, &diagram.metadata);
+    w.attribute("node", attrs([("shape", "record")]));
+
+    let mut ids = Ids::default();
+    for object in &diagram.objects {
+        let id = ids.declare(&object.name, None);
+        let fields: Vec<String> = object
+            .fields
+            .iter()
+            .map(|f| format!("{} = {}", f.name, f.value))
+            .collect();
+        let mut a = attrs([
+            ("label", record(&object.display_name(), &[&fields])),
+            ("kind", "object".to_string()),
+        ]);
+        push_stereotype(&mut a, object.stereotype.as_ref());
+        push_fill(&mut a, object.background_color.as_ref());
+        w.node(&id, &a);
+    }
+
+    for link in &diagram.links {
+        let mut a = Attributes::new();
+        match link.link_type {
+            ObjectLinkType::Association => {}
+            ObjectLinkType::Link => push(&mut a, "arrowhead", "none"),
+            ObjectLinkType::Dependency => {
+                push(&mut a, "arrowhead", "vee");
+                push(&mut a, "style", "dashed");
+            }
+            ObjectLinkType::Composition | ObjectLinkType::Aggregation => {
+                push(&mut a, "dir", "both");
+                let diamond = if link.link_type == ObjectLinkType::Composition {
+                    "diamond"
+                } else {
+                    "odiamond"
+                };
+                push(&mut a, "arrowtail", diamond);
+                push(&mut a, "arrowhead", "none");
+            }
+        }
+        push_opt(&mut a, "label", link.label.as_deref());
+        w.edge(&ids.get(&link.from), &ids.get(&link.to), &a);
+    }
+
+    w.finish()
+}
+
+/// Диаграмма компонентов (а также deployment и archimate)
+fn export_component(diagram: &ComponentDiagram) -> String {
+    let mut w = DotWriter::new(true, &diagram.metadata);
+    w.attribute("graph", attrs([("compound", "true")]));
+
+    let mut ids = Ids::default();
+    for component in &diagram.components {
+        component_node(&mut w, &mut ids, component);
+    }
+    for package in &diagram.packages {
+        component_package(&mut w, &mut ids, package);
+    }
+
+    for conn in &diagram.connections {
+        let mut a = Attributes::new();
+        if conn.dashed
+            || matches!(
+                conn.connection_type,
+                ConnectionType::Dependency | ConnectionType::Use | ConnectionType::Include
+            )
+        {
+            push(&mut a, "style", "dashed");
+        }
+        if conn.connection_type == ConnectionType::Dependency {
+            push(&mut a, "arrowhead", "vee");
+        }
+        push_opt(&mut a, "label", conn.label.as_deref());
+        push_color(&mut a, "color", conn.color.as_ref());
+        ids.edge(&mut w, &conn.from, &conn.to, a);
+    }
+
+    w.finish()
+}
+
+/// Контейнер диаграммы компонентов (package, node, cloud, ...) — кластер
+fn component_package(w: &mut DotWriter, ids: &mut Ids, package: &ComponentPackage) {
+    let mut a = attrs([
+        ("label", package.name.clone()),
+        ("kind", format!("{:?}", package.package_type).to_lowercase()),
+    ]);
+    push_stereotype(&mut a, package.stereotype.as_ref());
+    push_color(&mut a, "bgcolor", package.color.as_ref());
+    w.open_cluster(&a);
+    for component in &package.components {
+        component_node(w, ids, component);
+    }
+    for nested in &package.packages {
+        component_package(w, ids, nested);
+    }
+    w.close();
+}
+
+/// Компонент; с вложенными компонентами — кластер с якорем
+fn component_node(w: &mut DotWriter, ids: &mut Ids, component: &Component) {
+    let id = ids.declare(&component.name, component.alias.as_deref());
+    let kind = format!("{:?}", component.component_type).to_lowercase();
+    let shape = match kind.as_str() {
+        "component" => "component",
+        "interface" => "circle",
+        "database" | "storage" => "cylinder",
+        "queue" | "collections" | "stack" => "box3d",
+        "folder" => "folder",
+        "node" | "device" => "box3d",
+        "artifact" | "file" => "note",
+        "cloud" | "usecase" => "ellipse",
+        "card" | "frame" => "tab",
+        "hexagon" => "hexagon",
+        "actor" | "agent" => "plaintext",
+        "port" => "square",
+        _ => "box",
+    };
+    let mut a = attrs([
+        ("label", component.name.clone()),
+        ("shape", shape.to_string()),
+        ("kind", kind),
+    ]);
+    push_stereotype(&mut a, component.stereotype.as_ref());
+    push_fill(&mut a, component.color.as_ref());
+
+    let container = !component.children.is_empty();
+    if container {
+        let mut cluster = attrs([("label", component.name.as_str())]);
+        push_stereotype(&mut cluster, component.stereotype.as_ref());
+        let name = w.open_cluster(&cluster);
+        ids.anchor(w, &id, name);
+        for child in &component.children {
+            component_node(w, ids, child);
+        }
+    } else {
+        w.node(&id, &a);
+    }
+
+    // Интерфейсы (`()`) и порты рисуются отдельными узлами рядом с компонентом
+    for interface in &component.interfaces {
+        let iface = ids.declare(&interface.name, interface.alias.as_deref());
+        w.node(
+            &iface,
+            &attrs([
+                ("label", interface.name.as_str()),
+                ("shape", "circle"),
+                ("kind", "interface"),
+            ]),
+        );
+        let mut link = attrs([("arrowhead", "none")]);
+        if !interface.provided {
+            push(&mut link, "style", "dashed");
+        }
+        ids.edge(w, &component.name, &interface.name, link);
+    }
+    for port in &component.ports {
+        let port_id = format!("{}.{}", id, port.name);
+        w.node(
+            &port_id,
+            &attrs([
+                ("xlabel", port.name.as_str()),
+                ("shape", "square"),
+                ("label", ""),
+            ]),
+        );
+        ids.edge(w, &component.name, &port_id, attrs([("arrowhead", "none")]));
+    }
+
+    if container {
+        w.close();
+    }
+}
+
+/// Диаграмма состояний
+fn export_state(diagram: &StateDiagram) -> String {
+    let mut w = DotWriter::new(true, &diagram.metadata);
+    w.attribute("graph", attrs([("compound", "true")]));
+    w.attribute("node", attrs([("shape", "box"), ("style", "rounded")]));
+
+    let mut ids = Ids::default();
+    state_scope(&mut w, &mut ids, "", &diagram.states, &diagram.transitions);
+    w.finish()
+}
+
+/// Состояния и переходы одного уровня; у каждого уровня свои `[*]`
+fn state_scope(
+    w: &mut DotWriter,
+    ids: &mut Ids,
+    prefix: &str,
+    states: &[State],
+    transitions: &[Transition],
+) {
+    for state in states {
+        if state.name == "[*]" {
+            continue;
+        }
+        let id = ids.declare(&state.name, state.alias.as_deref());
+        let mut a = Attributes::new();
+        push_stereotype(&mut a, state.stereotype.as_ref());
+        push_fill(&mut a, state.color.as_ref());
+
+        let composite = state.state_type == StateType::Composite
+            || !state.substates.is_empty()
+            || !state.regions.is_empty();
+        if composite {
+            a.insert(0, ("label".to_string(), state.name.clone()));
+            let name = w.open_cluster(&a);
+            ids.anchor(w, &id, name);
+            let inner = format!("{}{}/", prefix, id);
+            state_scope(
+                w,
+                ids,
+                &inner,
+                &state.substates,
+                &state.internal_transitions,
+            );
+            // Параллельные регионы — пунктирные кластеры
+            for (i, region) in state.regions.iter().enumerate() {
+                w.open_cluster(&attrs([("label", ""), ("style", "dashed")]));
+                let region_prefix = format!("{}{}/{}/", prefix, id, i);
+                state_scope(w, ids, &region_prefix, region, &[]);
+                w.close();
+            }
+            w.close();
+            continue;
+        }
+
+        let mut label = state.name.clone();
+        if let Some(description) = &state.description {
+            label.push_str(&format!("\\n{}", description));
+        }
+        for (name, action) in [
+            ("entry", &state.entry_action),
+            ("exit", &state.exit_action),
+            ("do", &state.do_action),
+        ] {
+            if let Some(action) = action {
+                label.push_str(&format!("\\n{} / {}", name, action));
+            }
+        }
+        a.splice(0..0, state_shape(state.state_type, &label));
+        w.node(&id, &a);
+    }
+
+    // Псевдосостояния уровня и состояния, упомянутые только в переходах
+    let initial = format!("{}[*]_initial", prefix);
+    let last = format!("{}[*]_final", prefix);
+    if transitions.iter().any(|t| t.from == "[*]") {
+        w.node(&initial, &state_shape(StateType::Initial, ""));
+    }
+    if transitions.iter().any(|t| t.to == "[*]") {
+        w.node(&last, &state_shape(StateType::Final, ""));
+    }
+    for t in transitions {
+        for name in [&t.from, &t.to] {
+            if name != "[*]" && !ids.contains(name) {
+                let state_type = match name.as_str() {
+                    "[H]" => StateType::History,
+                    "[H*]" => StateType::DeepHistory,
+                    _ => StateType::Simple,
+                };
+                let id = ids.declare(name, None);
+                w.node(&id, &state_shape(state_type, name));
+            }
+        }
+    }
+
+    for t in transitions {
+        let from = if t.from == "[*]" { &initial } else { &t.from };
+        let to = if t.to == "[*]" { &last } else { &t.to };
+        let mut a = Attributes::new();
+        let label = t.label();
+        if !label.is_empty() {
+            push(&mut a, "label", label);
+        }
+        push_color(&mut a, "color", t.color.as_ref());
+        ids.edge(w, from, to, a);
+    }
+}
+
+/// Форма узла по типу состояния
+fn state_shape(state_type: StateType, label: &str) -> Attributes {
+    let shape = |shape: &str, label: &str| attrs([("shape", shape), ("label", label)]);
+    let mut a = match state_type {
+        StateType::Initial => attrs([
+            ("shape", "circle"),
+            ("style", "filled"),
+            ("fillcolor", "black"),
+            ("width", "0.2"),
+            ("label", ""),
+        ]),
+        StateType::Final => attrs([
+            ("shape", "doublecircle"),
+            ("style", "filled"),
+            ("fillcolor", "black"),
+            ("width", "0.15"),
+            ("label", ""),
+        ]),
+        StateType::History => shape("circle", "H"),
+        StateType::DeepHistory => shape("circle", "H*"),
+        StateType::Choice => shape("diamond", ""),
+        StateType::Fork | StateType::Join => attrs([
+            ("shape", "box"),
+            ("style", "filled"),
+            ("fillcolor", "black"),
+            ("height", "0.1"),
+            ("label", ""),
+        ]),
+        StateType::EntryPoint | StateType::ExitPoint => shape("circle", ""),
+        StateType::Simple | StateType::Composite => attrs([("label", label)]),
+    };
+    if !matches!(state_type, StateType::Simple | StateType::Composite) {
+        push(&mut a, "kind", format!("{:?}", state_type).to_lowercase());
+    }
+    a
+}
+
+/// Диаграмма прецедентов
+fn export_usecase(diagram: &UseCaseDiagram) -> String {
+    let mut w = DotWriter::new(true, &diagram.metadata);
+    if let Some(rankdir) = rankdir(diagram.direction) {
+        w.attribute("graph", attrs([("rankdir", rankdir)]));
+    }
+
+    let mut ids = Ids::default();
+    for actor in &diagram.actors {
+        let id = ids.declare(&actor.name, actor.alias.as_deref());
+        let mut a = attrs([
+            ("label", actor.name.as_str()),
+            ("shape", "plaintext"),
+            ("kind", "actor"),
+        ]);
+        push_stereotype(&mut a, actor.stereotype.as_ref());
+        push_color(&mut a, "fontcolor", actor.color.as_ref());
+        w.node(&id, &a);
+    }
+
+    let usecase = |w: &mut DotWriter, ids: &mut Ids, uc: &plantuml_ast::usecase::UseCase| {
+        let id = ids.declare(&uc.name, uc.alias.as_deref());
+        let mut a = attrs([
+            ("label", uc.name.as_str()),
+            ("shape", "ellipse"),
+            ("kind", "usecase"),
+        ]);
+        push_stereotype(&mut a, uc.stereotype.as_ref());
+        push_fill(&mut a, uc.color.as_ref());
+        w.node(&id, &a);
+    };
+    for uc in &diagram.use_cases {
+        usecase(&mut w, &mut ids, uc);
+    }
+    for package in &diagram.packages {
+        let mut a = attrs([("label", package.name.as_str())]);
+        push_color(&mut a, "bgcolor", package.color.as_ref());
+        w.open_cluster(&a);
+        for uc in &package.use_cases {
+            usecase(&mut w, &mut ids, uc);
+        }
+        w.close();
+    }
+
+    for rel in &diagram.relationships {
+        let mut a = Attributes::new();
+        let stereotype = match rel.relation_type {
+            UseCaseRelationType::Association => None,
+            UseCaseRelationType::Generalization => {
+                push(&mut a, "arrowhead", "empty");
+                None
+            }
+            UseCaseRelationType::Include => Some("include"),
+            UseCaseRelationType::Extend => Some("extend"),
+        };
+        if let Some(stereotype) = stereotype {
+            push(&mut a, "arrowhead", "vee");
+            push(&mut a, "style", "dashed");
+            push(&mut a, "stereotype", stereotype);
+        }
+        let label = rel
+            .label
+            .clone()
+            .or_else(|| stereotype.map(|s| format!("<<{}>>", s)));
+        push_opt(&mut a, "label", label.as_deref());
+        w.edge(&ids.get(&rel.from), &ids.get(&rel.to), &a);
+    }
+
+    w.finish()
+}
+
+/// ER диаграмма
+fn export_er(diagram: &ErDiagram) -> String {
+    let mut w = DotWriter::new(true, &diagram.metadata);
+    w.attribute("node", attrs([("shape", "record")]));
+
+    let mut ids = Ids::default();
+    for entity in &diagram.entities {
+        let id = ids.declare(&entity.id.name, entity.id.alias.as_deref());
+        let (keys, columns): (Vec<_>, Vec<_>) =
+            entity.attributes.iter().partition(|a| a.is_primary_key);
+        let column = |attribute: &&plantuml_ast::er::Attribute| {
+            let mut text = String::new();
+            if attribute.is_required {
+                text.push('*');
+            }
+            text.push_str(&attribute.name);
+            if let Some(data_type) = &attribute.data_type {
+                text.push_str(&format!(" : {}", data_type));
+            }
+            if let Some(stereotype) = &attribute.stereotype {
+                text.push_str(&format!(" <<{}>>", stereotype));
+            }
+            text
+        };
+        let keys: Vec<String> = keys.iter().map(column).collect();
+        let columns: Vec<String> = columns.iter().map(column).collect();
+
+        let mut a = attrs([
+            ("label", record(&entity.id.name, &[&keys, &columns])),
+            ("kind", "entity".to_string()),
+        ]);
+        if entity.is_weak {
+            push(&mut a, "peripheries", "2");
+        }
+        push_stereotype(&mut a, entity.stereotype.as_ref());
+        push_fill(&mut a, entity.background_color.as_ref());
+        w.node(&id, &a);
+    }
+
+    // Crow's foot: кардинальности — формы стрелок на концах
+    let arrow = |cardinality: Cardinality| match cardinality {
+        Cardinality::One => "teetee",
+        Cardinality::ZeroOrOne => "teeodot",
+        Cardinality::Many => "crow",
+        Cardinality::ZeroOrMany => "crowodot",
+        Cardinality::OneOrMany => "crowtee",
+    };
+    for rel in &diagram.relationships {
+        let mut a = attrs([
+            ("dir", "both"),
+            ("arrowtail", arrow(rel.from_cardinality)),
+            ("arrowhead", arrow(rel.to_cardinality)),
+        ]);
+        if !rel.is_identifying {
+            push(&mut a, "style", "dashed");
+        }
+        push_opt(&mut a, "label", rel.label.as_deref());
+        w.edge(&ids.get(&rel.from), &ids.get(&rel.to), &a);
+    }
+
+    w.finish()
+}
+
+/// DOT граф `@startdot` — обратно в DOT
+fn export_graph(diagram: &DotDiagram) -> String {
+    let mut w = DotWriter::new(diagram.directed, &diagram.metadata);
+    w.strict = diagram.strict;
+    w.name = diagram.name.clone();
+    for (name, value) in &diagram.attributes {
+        w.assign(name, value);
+    }
+    for node in &diagram.nodes {
+        w.node(&node.id, &to_attributes(&node.attributes));
+    }
+    for subgraph in &diagram.subgraphs {
+        graph_subgraph(&mut w, subgraph);
+    }
+    for edge in &diagram.edges {
+        w.edge(&edge.from, &edge.to, &to_attributes(&edge.attributes));
+    }
+    w.finish()
+}
+
+/// Подграф DOT графа с исходным именем
+fn graph_subgraph(w: &mut DotWriter, subgraph: &DotSubgraph) {
+    w.open_subgraph(subgraph.name.as_deref());
+    for (name, value) in &subgraph.attributes {
+        w.assign(name, value);
+    }
+    for node in &subgraph.nodes {
+        w.node(node, &[]);
+    }
+    for child in &subgraph.subgraphs {
+        graph_subgraph(w, child);
+    }
+    w.close();
+}
+
+/// Канонические идентификаторы узлов: ссылка по имени или алиасу → id
+#[derive(Default)]
+struct Ids {
+    ids: HashMap<String, String>,
+    /// Кластеры с якорями: id якоря → имя кластера
+    clusters: HashMap<String, String>,
+}
+
+impl Ids {
+    /// Регистрирует элемент; id — алиас (как в `class "Long Name" as L`) или имя
+    fn declare(&mut self, name: &str, alias: Option<&str>) -> String {
+        let id = alias.unwrap_or(name).to_string();
+        self.ids.insert(name.to_string(), id.clone());
+        self.ids.insert(id.clone(), id.clone());
+        id
+    }
+
+    /// Объявлен ли элемент
+    fn contains(&self, reference: &str) -> bool {
+        self.ids.contains_key(reference)
+    }
+
+    /// Id по имени или алиасу (необъявленные ссылки остаются как есть)
+    fn get(&self, reference: &str) -> String {
+        self.ids
+            .get(reference)
+            .cloned()
+            .unwrap_or_else(|| reference.to_string())
+    }
+
+    /// Невидимый якорь кластера — конец рёбер к кластеру целиком
+    fn anchor(&mut self, w: &mut DotWriter, id: &str, cluster: String) {
+        w.node(
+            id,
+            &attrs([
+                ("shape", "point"),
+                ("style", "invis"),
+                ("width", "0"),
+                ("label", ""),
+            ]),
+        );
+        self.clusters.insert(id.to_string(), cluster);
+    }
+
+    /// Ребро; концы-кластеры обрезаются по границе кластера (`ltail`/`lhead`)
+    fn edge(&self, w: &mut DotWriter, from: &str, to: &str, mut a: Attributes) {
+        let (from, to) = (self.get(from), self.get(to));
+        if let Some(cluster) = self.clusters.get(&from) {
+            push(&mut a, "ltail", cluster.clone());
+        }
+        if let Some(cluster) = self.clusters.get(&to) {
+            push(&mut a, "lhead", cluster.clone());
+        }
+        w.edge(&from, &to, &a);
+    }
+}
+
+/// Построитель текста DOT с отступами
+struct DotWriter {
+    directed: bool,
+    strict: bool,
+    name: Option<String>,
+    body: String,
+    depth: usize,
+    clusters: usize,
+}
+
+impl DotWriter {
+    /// Граф; заголовок диаграммы становится подписью графа
+    fn new(directed: bool, metadata: &DiagramMetadata) -> Self {
+        let mut writer = Self {
+            directed,
+            strict: false,
+            name: None,
+            body: String::new(),
+            depth: 1,
+            clusters: 0,
+        };
+        if let Some(title) = &metadata.title {
+            writer.assign("label", title);
+            writer.assign("labelloc", "t");
+        }
+        writer
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.body.push_str("  ");
+        }
+        self.body.push_str(text);
+        self.body.push('\n');
+    }
+
+    /// Атрибут текущего графа: `name="value"`
+    fn assign(&mut self, name: &str, value: &str) {
+        self.line(&format!("{}={}", key(name), quote(value)));
+    }
+
+    /// Значения по умолчанию: `node [...]`, `edge [...]`, `graph [...]`
+    fn attribute(&mut self, target: &str, a: Attributes) {
+        self.line(&format!("{}{}", target, attribute_list(&a)));
+    }
+
+    fn node(&mut self, id: &str, a: &[(String, String)]) {
+        self.line(&format!("{}{}", quote(id), attribute_list(a)));
+    }
+
+    fn edge(&mut self, from: &str, to: &str, a: &[(String, String)]) {
+        let op = if self.directed { "->" } else { "--" };
+        self.line(&format!(
+            "{} {} {}{}",
+            quote(from),
+            op,
+            quote(to),
+            attribute_list(a)
+        ));
+    }
+
+    /// Открывает кластер `cluster_N` и возвращает его имя
+    fn open_cluster(&mut self, a: &[(String, String)]) -> String {
+        let name = format!("cluster_{}", self.clusters);
+        self.clusters += 1;
+        self.open_subgraph(Some(&name));
+        for (key, value) in a {
+            self.assign(key, value);
+        }
+        name
+    }
+
+    fn open_subgraph(&mut self, name: Option<&str>) {
+        match name {
+            Some(name) => self.line(&format!("subgraph {} {{", quote(name))),
+            None => self.line("{"),
+        }
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn finish(self) -> String {
+        let mut out = String::new();
+        if self.strict {
+            out.push_str("strict ");
+        }
+        out.push_str(if self.directed { "digraph" } else { "graph" });
+        if let Some(name) = &self.name {
+            out.push(' ');
+            out.push_str(&quote(name));
+        }
+        out.push_str(" {\n");
+        out.push_str(&self.body);
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Строка в кавычках DOT; `\n` и прочие escape-последовательности сохраняются
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            // Escape-последовательности DOT (`\n`, `\l`, `\N`, `\\`, `\|` в записях...)
+            // сохраняются, прочие `\` экранируются: иначе `\"` закрыл бы строку
+            '\\' => match chars.peek() {
+                Some('n' | 'l' | 'r' | 'N' | 'G' | 'E' | 'T' | 'H' | 'L')
+                | Some('{' | '}' | '|' | '<' | '>') => out.push('\\'),
+                Some('\\') => {
+                    chars.next();
+                    out.push_str("\\\\");
+                }
+                _ => out.push_str("\\\\"),
+            },
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Имя атрибута: идентификатор без кавычек, остальное — в кавычках
+fn key(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+/// `[a="b", c="d"]` (пусто без атрибутов)
+fn attribute_list(a: &[(String, String)]) -> String {
+    if a.is_empty() {
+        return String::new();
+    }
+    let items: Vec<String> = a
+        .iter()
+        .map(|(k, v)| format!("{}={}", key(k), quote(v)))
+        .collect();
+    format!(" [{}]", items.join(", "))
+}
+
+/// Метка record: `{Заголовок|строка\l...|...}`
+fn record(title: &str, sections: &[&[String]]) -> String {
+    let mut parts = vec![escape_record(title)];
+    for section in sections {
+        let lines: String = section
+            .iter()
+            .map(|line| format!("{}\\l", escape_record(line)))
+            .collect();
+        parts.push(lines);
+    }
+    format!("{{{}}}", parts.join("|"))
+}
+
+/// Экранирует служебные символы record-меток
+fn escape_record(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Направление диаграммы → `rankdir` (`None` для TB по умолчанию)
+fn rankdir(direction: Direction) -> Option<&'static str> {
+    match direction {
+        Direction::TopToBottom => None,
+        Direction::BottomToTop => Some("BT"),
+        Direction::LeftToRight => Some("LR"),
+        Direction::RightToLeft => Some("RL"),
+    }
+}
+
+/// Стиль линии → `style`
+fn line_style(style: LineStyle) -> Option<&'static str> {
+    match style {
+        LineStyle::Solid => None,
+        LineStyle::Dashed => Some("dashed"),
+        LineStyle::Dotted => Some("dotted"),
+        LineStyle::Bold => Some("bold"),
+    }
+}
+
+/// Цвет в формате DOT (`#rrggbb`, `#rrggbbaa` или имя)
+fn dot_color(color: &Color) -> String {
+    match color {
+        Color::Named(name) => name.to_lowercase(),
+        Color::Hex(_) => color.to_css(),
+        Color::Rgb { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Rgba { r, g, b, a } => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+    }
+}
+
+fn attrs<K: Into<String>, V: Into<String>, const N: usize>(items: [(K, V); N]) -> Attributes {
+    items
+        .into_iter()
+        .map(|(k, v)| (k.into(), v.into()))
+        .collect()
+}
+
+fn to_attributes(map: &plantuml_ast::dot::DotAttributes) -> Attributes {
+    map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+fn push(a: &mut Attributes, name: &str, value: impl Into<String>) {
+    a.push((name.to_string(), value.into()));
+}
+
+fn push_opt(a: &mut Attributes, name: &str, value: Option<&str>) {
+    if let Some(value) = value {
+        push(a, name, value);
+    }
+}
+
+fn push_color(a: &mut Attributes, name: &str, color: Option<&Color>) {
+    if let Some(color) = color {
+        push(a, name, dot_color(color));
+    }
+}
+
+/// Заливка: `fillcolor` + `style=filled`
+fn push_fill(a: &mut Attributes, color: Option<&Color>) {
+    if let Some(color) = color {
+        push(a, "fillcolor", dot_color(color));
+        match a.iter_mut().find(|(k, _)| k == "style") {
+            Some((_, style)) => style.push_str(",filled"),
+            None => push(a, "style", "filled"),
+        }
+    }
+}
+
+/// Стереотип — атрибут `stereotype` (несколько имён через запятую)
+fn push_stereotype(a: &mut Attributes, stereotype: Option<&Stereotype>) {
+    if let Some(stereotype) = stereotype {
+        push(a, "stereotype", stereotype.names.join(","));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_diagram;
+
+    fn export(source: &str) -> String {
+        export_dot(&parse_diagram(source).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_export_class() {
+        let dot = export(
+            r#"@startuml
+package model {
+  class User <<Entity>> {
+    - id : Long
+    + getName() : String
+  }
+}
+interface Named
+User ..|> Named
+User "1" *-- "many" Order : owns
+@enduml"#,
+        );
+
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("subgraph \"cluster_0\" {"));
+        assert!(dot.contains("label=\"model\""));
+        assert!(dot.contains("\"User\" [label=\"{User|-id : Long\\l|+getName() : String\\l}\""));
+        assert!(dot.contains("stereotype=\"Entity\""));
+        assert!(dot.contains("kind=\"interface\""));
+        // Реализация: от интерфейса к классу, треугольник у интерфейса
+        assert!(dot.contains(
+            "\"Named\" -> \"User\" [dir=\"back\", arrowtail=\"empty\", style=\"dashed\"]"
+        ));
+        assert!(dot.contains("arrowtail=\"diamond\""));
+        assert!(dot.contains("taillabel=\"1\""));
+        assert!(dot.contains("headlabel=\"many\""));
+    }
+
+    #[test]
+    fn test_export_state() {
+        let dot = export(
+            r#"@startuml
+[*] --> Active
+state Active {
+  [*] --> Idle
+  Idle --> Busy : job [ready]
+}
+Active --> [*]
+@enduml"#,
+        );
+
+        assert!(dot.contains("compound=\"true\""));
+        assert!(dot.contains("\"[*]_initial\""));
+        assert!(dot.contains("\"Active/[*]_initial\" -> \"Idle\""));
+        assert!(dot.contains("\"[*]_initial\" -> \"Active\" [lhead=\"cluster_0\"]"));
+        assert!(dot.contains("\"Active\" -> \"[*]_final\" [ltail=\"cluster_0\"]"));
+        assert!(dot.contains("label=\"job [ready]\""));
+    }
+
+    #[test]
+    fn test_export_component() {
+        let dot = export(
+            "@startuml\npackage Backend {\n  [API] as api\n  database DB\n}\napi ..> DB : SQL\n@enduml",
+        );
+        assert!(dot.contains("label=\"Backend\""));
+        assert!(dot.contains("\"api\" [label=\"API\", shape=\"component\""));
+        assert!(dot.contains("shape=\"cylinder\""));
+        assert!(dot.contains("\"api\" -> \"DB\" [style=\"dashed\""));
+    }
+
+    #[test]
+    fn test_export_er_and_usecase() {
+        let er = export(
+            "@startuml\nentity User {\n  * id : int <<PK>>\n  name : text\n}\nentity Post {\n  * id : int <<PK>>\n}\nUser ||--o{ Post : writes\n@enduml",
+        );
+        assert!(er.contains("label=\"{User|*id : int \\<\\<PK\\>\\>\\l|name : text\\l}\""));
+        assert!(er.contains("arrowtail=\"teetee\""));
+        assert!(er.contains("arrowhead=\"crowodot\""));
+
+        let usecase = export(
+            "@startuml\nleft to right direction\nactor User\nUser --> (Login)\n(Login) .> (Auth) : <<include>>\n@enduml",
+        );
+        assert!(usecase.contains("rankdir=\"LR\""));
+        assert!(usecase.contains("kind=\"actor\""));
+    }
+
+    #[test]
+    fn test_export_dot_roundtrip() {
+        let source = "@startdot\nstrict graph G {\n  rankdir=LR\n  subgraph cluster_a { x }\n  x -- y [label=\"say \\\"hi\\\"\"]\n}\n@enddot";
+        let dot = export(source);
+        assert!(dot.starts_with("strict graph \"G\" {"));
+
+        let reparsed = plantuml_parser::parse_dot(&dot).unwrap();
+        assert!(reparsed.strict && !reparsed.directed);
+        assert_eq!(reparsed.subgraphs[0].nodes, vec!["x"]);
+        assert_eq!(reparsed.edges[0].label(), Some("say \"hi\""));
+    }
+
+    #[test]
+    fn test_export_unsupported() {
+        let diagram = parse_diagram("@startuml\nAlice -> Bob\n@enduml").unwrap();
+        assert!(matches!(
+            export_dot(&diagram),
+            Err(Error::UnsupportedDiagram(_))
+        ));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a\"b"), "\"a\\\"b\"");
+        assert_eq!(quote("line\\nnext"), "\"line\\nnext\"");
+        assert_eq!(quote("end\\"), "\"end\\\\\"");
+        assert_eq!(quote("la\\\"b"), "\"la\\\\\\\"b\"");
+        assert_eq!(quote("a\\\\b\\x"), "\"a\\\\b\\\\x\"");
+    }
+}
@@ -0,0 +1,218 @@
+//! Текстовое кодирование диаграмм для URL сервера PlantUML
+//!
+//! Исходный код сжимается raw deflate (без заголовка zlib) и записывается
+//! base64 с алфавитом PlantUML `0-9A-Za-z-_`, как в адресах вида
+//! `/svg/SoWkIImgAStDuNBAJrBGjLDmpCbCJbMmKiX8pSd9vt98pKi1IW80`.
+//!
+//! При декодировании учитываются префиксы сервера: `~1` — тот же deflate,
+//! `~h` — шестнадцатеричная запись UTF-8 байтов без сжатия.
+
+use std::io::{Read, Write};
+
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::{Error, Result};
+
+/// Алфавит base64 PlantUML (отличается от RFC 4648 порядком символов)
+const ALPHABET: &[u8; 64] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+
+/// Кодирует исходный код для URL сервера PlantUML (deflate + base64 PlantUML).
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{decode, encode};
+///
+/// let source = "@startuml\nAlice -> Bob\n@enduml";
+/// let encoded = encode(source);
+/// assert_eq!(decode(&encoded).unwrap(), source);
+/// ```
+pub fn encode(source: &str) -> String {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    // Запись в Vec не завершается ошибкой
+    let _ = encoder.write_all(source.as_bytes());
+    let compressed = encoder.finish().unwrap_or_default();
+    encode64(&compressed)
+}
+
+/// Кодирует исходный код в шестнадцатеричный вариант `~h` (без сжатия)
+pub fn encode_hex(source: &str) -> String {
+    let mut out = String::with_capacity(2 + source.len() * 2);
+    out.push_str("~h");
+    for byte in source.bytes() {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out
+}
+
+/// Декодирует строку из URL сервера PlantUML в исходный код.
+///
+/// Поддерживаются варианты без префикса и `~1` (deflate + base64 PlantUML;
+/// поток с заголовком zlib тоже принимается) и `~h` (шестнадцатеричный).
+pub fn decode(encoded: &str) -> Result<String> {
+    decode_with_limit(encoded, usize::MAX)
+}
+
+/// Декодирует строку из URL, как [`decode`], но распаковывает не более
+/// `max_len` байт: более длинный текст — [`Error::SourceTooLarge`] без
+/// распаковки остатка потока.
+pub fn decode_with_limit(encoded: &str, max_len: usize) -> Result<String> {
+    let encoded = encoded.trim();
+    if let Some(hex) = encoded.strip_prefix("~h") {
+        if hex.len() / 2 > max_len {
+            return Err(Error::SourceTooLarge(max_len));
+        }
+        return utf8(decode_hex(hex)?);
+    }
+    let encoded = encoded.strip_prefix("~1").unwrap_or(encoded);
+    let compressed = decode64(encoded)?;
+
+    let bytes = match inflate(DeflateDecoder::new(compressed.as_slice()), max_len) {
+        Ok(bytes) => bytes,
+        Err(_) => inflate(ZlibDecoder::new(compressed.as_slice()), max_len)
+            .map_err(|e| Error::Decode(format!("поток deflate повреждён: {}", e)))?,
+    };
+    if bytes.len() > max_len {
+        return Err(Error::SourceTooLarge(max_len));
+    }
+    utf8(bytes)
+}
+
+/// Распаковывает не более `max_len + 1` байт: лишний байт означает превышение
+fn inflate(decoder: impl Read, max_len: usize) -> std::io::Result<Vec<u8>> {
+    let limit = u64::try_from(max_len).unwrap_or(u64::MAX).saturating_add(1);
+    let mut bytes = Vec::new();
+    decoder.take(limit).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Base64 PlantUML: каждые 3 байта — 4 символа, неполная группа дополняется нулями
+fn encode64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let b0 = group[0];
+        let b1 = group.get(1).copied().unwrap_or(0);
+        let b2 = group.get(2).copied().unwrap_or(0);
+        for index in [
+            b0 >> 2,
+            ((b0 & 0x3) << 4) | (b1 >> 4),
+            ((b1 & 0xF) << 2) | (b2 >> 6),
+            b2 & 0x3F,
+        ] {
+            out.push(ALPHABET[usize::from(index)] as char);
+        }
+    }
+    out
+}
+
+fn decode64(encoded: &str) -> Result<Vec<u8>> {
+    let values = encoded
+        .bytes()
+        .map(|c| {
+            ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .map(|v| v as u8)
+                .ok_or_else(|| Error::Decode(format!("недопустимый символ '{}'", c as char)))
+        })
+        .collect::<Result<Vec<u8>>>()?;
+
+    let mut out = Vec::with_capacity(values.len() * 3 / 4);
+    for group in values.chunks(4) {
+        let v = |i: usize| group.get(i).copied().unwrap_or(0);
+        out.push((v(0) << 2) | (v(1) >> 4));
+        if group.len() > 2 {
+            out.push((v(1) << 4) | (v(2) >> 2));
+        }
+        if group.len() > 3 {
+            out.push((v(2) << 6) | v(3));
+        }
+    }
+    Ok(out)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return Err(Error::Decode(
+            "нечётная длина шестнадцатеричной строки".to_string(),
+        ));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::Decode(format!("недопустимая пара '{}'", &hex[i..])))
+        })
+        .collect()
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).map_err(|_| Error::Decode("текст не в UTF-8".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for source in [
+            "@startuml\nAlice -> Bob: Hello\n@enduml",
+            "@startuml\nАлиса -> Боб: Привет 👋\n@enduml",
+            "",
+        ] {
+            let encoded = encode(source);
+            assert!(encoded
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+            assert_eq!(decode(&encoded).unwrap(), source);
+            assert_eq!(decode(&format!("~1{}", encoded)).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn test_decode_server_url() {
+        // Пример из документации сервера PlantUML
+        let source = decode("SoWkIImgAStDuNBAJrBGjLDmpCbCJbMmKiX8pSd9vt98pKi1IW80").unwrap();
+        assert_eq!(source, "@startuml\nBob -> Alice : hello\n@enduml");
+    }
+
+    #[test]
+    fn test_hex_variant() {
+        let encoded = encode_hex("A -> B");
+        assert_eq!(encoded, "~h41202d3e2042");
+        assert_eq!(decode(&encoded).unwrap(), "A -> B");
+        assert!(matches!(decode("~h4"), Err(Error::Decode(_))));
+        assert!(matches!(decode("~hzz"), Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(matches!(decode("abc$"), Err(Error::Decode(_))));
+        assert!(matches!(decode("0000"), Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn test_decode_with_limit() {
+        let source = "A -> B\n".repeat(1000);
+        assert_eq!(decode_with_limit(&encode(&source), source.len()).unwrap(), source);
+        assert!(matches!(
+            decode_with_limit(&encode(&source), 100),
+            Err(Error::SourceTooLarge(100))
+        ));
+        assert!(matches!(
+            decode_with_limit(&encode_hex(&source), 100),
+            Err(Error::SourceTooLarge(100))
+        ));
+    }
+
+    #[test]
+    fn test_encode64_alphabet() {
+        assert_eq!(encode64(&[0, 0, 0]), "0000");
+        assert_eq!(encode64(&[0xFF, 0xFF, 0xFF]), "____");
+        assert_eq!(decode64("____").unwrap(), vec![0xFF, 0xFF, 0xFF]);
+    }
+}
@@ -1,5 +1,6 @@
 //! Типы ошибок для plantuml-core
 
+use plantuml_preprocessor::{SourceLocation, SourceMap};
 use thiserror::Error;
 
 /// Тип результата для plantuml-core
@@ -12,10 +13,28 @@ pub enum Error {
     #[error("ошибка препроцессора: {0}")]
     Preprocess(String),
 
+    /// Ошибка препроцессора с положением строки, на которой он остановился
+    #[error("ошибка препроцессора {location}: {message}")]
+    PreprocessAt {
+        /// Текст ошибки препроцессора
+        message: String,
+        /// Исходное положение строки с ошибкой
+        location: SourceLocation,
+    },
+
     /// Ошибка парсинга
     #[error("ошибка парсинга: {0}")]
     Parse(String),
 
+    /// Ошибка парсинга с положением в исходных файлах (после `!include` и макросов)
+    #[error("ошибка парсинга {location}: {message}")]
+    ParseAt {
+        /// Текст ошибки парсера без номера строки разобранного текста
+        message: String,
+        /// Исходное положение строки с ошибкой
+        location: SourceLocation,
+    },
+
     /// Ошибка layout
     #[error("ошибка layout: {0}")]
     Layout(String),
@@ -35,4 +54,49 @@ pub enum Error {
     /// Пустой исходный код
     #[error("пустой исходный код")]
     EmptySource,
+
+    /// Ошибка декодирования текста из URL сервера PlantUML
+    #[error("ошибка декодирования: {0}")]
+    Decode(String),
+
+    /// Декодированный текст длиннее допустимого (в байтах)
+    #[error("исходный код превышает {0} байт")]
+    SourceTooLarge(usize),
+}
+
+impl Error {
+    /// Ошибка препроцессора с положением из `PreprocessContext::error_location`
+    pub fn from_preprocess_error(
+        error: plantuml_preprocessor::PreprocessError,
+        location: Option<SourceLocation>,
+    ) -> Self {
+        match location {
+            Some(location) => Error::PreprocessAt {
+                message: error.to_string(),
+                location,
+            },
+            None => Error::Preprocess(error.to_string()),
+        }
+    }
+
+    /// Ошибка парсера с номером строки, переведённым через карту препроцессора
+    pub fn from_parse_error(error: plantuml_parser::ParseError, source_map: &SourceMap) -> Self {
+        match error.line().and_then(|line| source_map.get(line)) {
+            Some(location) => Error::ParseAt {
+                message: error.message(),
+                location: location.clone(),
+            },
+            None => Error::Parse(error.to_string()),
+        }
+    }
+
+    /// Исходное положение ошибки, если оно известно
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            Error::ParseAt { location, .. } | Error::PreprocessAt { location, .. } => {
+                Some(location)
+            }
+            _ => None,
+        }
+    }
 }
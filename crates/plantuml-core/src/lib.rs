@@ -4,7 +4,13 @@
 //!
 //! Это главный фасад библиотеки, предоставляющий простой API для:
 //! - Парсинга PlantUML исходного кода
-//! - Рендеринга диаграмм в SVG
+//! - Рендеринга диаграмм в SVG, EPS и LaTeX/TikZ (PNG, JPEG, WebP и PDF —
+//!   features `png`, `jpeg`, `webp` и `pdf`; растр рисуется напрямую из layout)
+//! - Экспорта графовых диаграмм в Graphviz DOT
+//! - Построения display list команд рисования для canvas-подобных backend'ов
+//! - Кодирования исходного кода для URL сервера PlantUML (`encode`, `decode`)
+//! - Встраивания исходного кода в SVG и PNG и его извлечения (`extract_source`)
+//! - `!include <...>` из встроенной стандартной библиотеки (`StdlibResolver`)
 //! - Настройки тем и стилей
 //!
 //! ## Быстрый старт
@@ -33,23 +39,46 @@
 //! - `plantuml-layout` — алгоритмы размещения элементов
 //! - `plantuml-renderer` — SVG/PNG рендеринг
 //! - `plantuml-themes` — темы и skinparam
+//! - `plantuml-stdlib` — встроенная стандартная библиотека (C4, AWS, ...)
 
+mod dot;
+mod encoding;
 mod error;
+mod metadata;
 mod options;
 mod pipeline;
+mod text;
 
+pub use dot::export_dot;
+pub use encoding::{decode, decode_with_limit, encode, encode_hex};
 pub use error::{Error, Result};
-pub use options::RenderOptions;
+pub use metadata::{extract_source, EmbeddedSource};
+pub use options::{OutputFormat, RenderOptions};
+pub use text::{export_text, TextCharset};
 
 // Re-exports для удобства
 pub use plantuml_ast::Diagram;
 pub use plantuml_parser::parse;
-pub use plantuml_preprocessor::FsFileResolver;
+pub use plantuml_renderer::display_list::{self, DisplayList, DrawCommand};
+pub use plantuml_renderer::{EpsRenderer, TikzRenderer};
+pub use plantuml_preprocessor::{ChainResolver, FileResolver, FsFileResolver, MemoryFileResolver};
+pub use plantuml_preprocessor::{MacroCall, SourceLocation, SourceMap};
+#[cfg(feature = "url")]
+pub use plantuml_preprocessor::{Fetcher, UrlResolver};
+pub use plantuml_stdlib::StdlibResolver;
 pub use plantuml_themes::Theme;
 
 // PNG рендеринг (требует feature "png")
 #[cfg(feature = "png")]
-pub use plantuml_renderer::{PngError, PngOptions, PngRenderer};
+pub use plantuml_renderer::{PngError, PngOptions, PngRenderer, RasterRenderer};
+
+// PDF рендеринг (требует feature "pdf")
+#[cfg(feature = "pdf")]
+pub use plantuml_renderer::{PageSize, PdfError, PdfOptions, PdfRenderer};
+
+// Шрифты для PNG и PDF
+#[cfg(any(feature = "png", feature = "pdf"))]
+pub use plantuml_renderer::{FontOptions, FontSet};
 
 /// Рендерит PlantUML диаграмму в SVG.
 ///
@@ -59,6 +88,11 @@ pub use plantuml_renderer::{PngError, PngOptions, PngRenderer};
 /// 3. Layout (расчёт позиций элементов)
 /// 4. Рендеринг (генерация SVG)
 ///
+/// С [`OutputFormat::Dot`] вместо layout и SVG возвращается Graphviz DOT
+/// (см. [`export_dot`]), с [`OutputFormat::Eps`] и [`OutputFormat::Latex`] —
+/// EPS и LaTeX/TikZ того же layout. Двоичные форматы возвращаются
+/// `render_image` и `render_pdf`.
+///
 /// # Аргументы
 ///
 /// * `source` - исходный код PlantUML
@@ -116,14 +150,41 @@ pub fn render_with_includes(
     pipeline::render_pipeline_with_includes(source, base_path, options)
 }
 
+/// Рендерит PlantUML диаграмму, читая `!include` через заданный resolver.
+///
+/// Подходит для окружений без файловой системы (WASM) и для тестов:
+/// файлы можно передать через [`MemoryFileResolver`], несколько источников
+/// объединить [`ChainResolver`]. Стандартная библиотека (`!include <...>`)
+/// доступна всегда.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{render_with_resolver, MemoryFileResolver, RenderOptions};
+///
+/// let files = MemoryFileResolver::new().with_file("common.puml", "Alice -> Bob");
+/// let source = "@startuml\n!include common.puml\n@enduml";
+/// let svg = render_with_resolver(source, &files, &RenderOptions::default()).unwrap();
+/// assert!(svg.contains("Alice"));
+/// ```
+pub fn render_with_resolver<R: FileResolver>(
+    source: &str,
+    resolver: R,
+    options: &RenderOptions,
+) -> Result<String> {
+    pipeline::render_pipeline_with_resolver(source, resolver, options)
+}
+
 /// Рендерит PlantUML диаграмму в PNG.
 ///
-/// Требует feature `png`.
+/// Требует feature `png`. Layout переводится в [`DisplayList`] и рисуется
+/// [`RasterRenderer`] без промежуточного SVG, как EPS, LaTeX и display list
+/// для WASM; рукописный режим поддерживается.
 ///
 /// # Аргументы
 ///
 /// * `source` - исходный код PlantUML
-/// * `options` - опции рендеринга SVG
+/// * `options` - опции рендеринга (формат вывода игнорируется)
 /// * `png_options` - опции рендеринга PNG
 ///
 /// # Пример
@@ -141,16 +202,114 @@ pub fn render_png(
     options: &RenderOptions,
     png_options: &PngOptions,
 ) -> Result<Vec<u8>> {
-    // Сначала рендерим в SVG
-    let svg = render(source, options)?;
+    pipeline::raster_pipeline(source, options, png_options, OutputFormat::Png)
+}
+
+/// Рендерит PlantUML диаграмму в PNG без промежуточного SVG.
+///
+/// Требует feature `png`. То же, что [`render_png`]: layout рисуется
+/// [`RasterRenderer`] прямо на `tiny_skia::Pixmap` (текст — ab_glyph).
+///
+/// # Пример
+///
+/// ```rust,ignore
+/// use plantuml_core::{render_png_direct, PngOptions, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob\n@enduml";
+/// let png_bytes = render_png_direct(source, &RenderOptions::default(), &PngOptions::default())?;
+/// ```
+#[cfg(feature = "png")]
+pub fn render_png_direct(
+    source: &str,
+    options: &RenderOptions,
+    png_options: &PngOptions,
+) -> Result<Vec<u8>> {
+    pipeline::raster_pipeline(source, options, png_options, OutputFormat::Png)
+}
+
+/// Рендерит PlantUML диаграмму в растровый формат из `options.format`.
+///
+/// Требует feature `png`; [`OutputFormat::Png`], [`OutputFormat::Jpeg`]
+/// (feature `jpeg`, качество — [`PngOptions::quality`]) и
+/// [`OutputFormat::WebP`] (feature `webp`, без потерь) рисуются напрямую из
+/// layout, как в [`render_png_direct`]. Для остальных форматов возвращается
+/// [`Error::Render`].
+///
+/// # Пример
+///
+/// ```rust,ignore
+/// use plantuml_core::{render_image, OutputFormat, PngOptions, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob\n@enduml";
+/// let options = RenderOptions::new().with_format(OutputFormat::Jpeg);
+/// let jpeg = render_image(source, &options, &PngOptions::default().with_quality(85))?;
+/// std::fs::write("diagram.jpg", jpeg)?;
+/// ```
+#[cfg(feature = "png")]
+pub fn render_image(
+    source: &str,
+    options: &RenderOptions,
+    png_options: &PngOptions,
+) -> Result<Vec<u8>> {
+    pipeline::raster_pipeline(source, options, png_options, options.format)
+}
+
+/// Рендерит PlantUML диаграмму в векторный PDF.
+///
+/// Требует feature `pdf`. Текст выводится встроенными шрифтами, размер
+/// страницы и разбиение больших диаграмм на страницы задаются в [`PdfOptions`].
+///
+/// # Аргументы
+///
+/// * `source` - исходный код PlantUML
+/// * `options` - опции рендеринга SVG (формат вывода игнорируется)
+/// * `pdf_options` - опции PDF
+///
+/// # Пример
+///
+/// ```rust,ignore
+/// use plantuml_core::{render_pdf, PageSize, PdfOptions, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob\n@enduml";
+/// let pdf_options = PdfOptions::default().with_page_size(PageSize::A4).paginated();
+/// let pdf_bytes = render_pdf(source, &RenderOptions::default(), &pdf_options)?;
+/// std::fs::write("diagram.pdf", pdf_bytes)?;
+/// ```
+#[cfg(feature = "pdf")]
+pub fn render_pdf(
+    source: &str,
+    options: &RenderOptions,
+    pdf_options: &PdfOptions,
+) -> Result<Vec<u8>> {
+    // PDF строится из SVG той же диаграммы
+    let svg_options = options.clone().with_format(OutputFormat::Svg);
+    let svg = render(source, &svg_options)?;
 
-    // Затем конвертируем в PNG
-    let png_renderer = PngRenderer::new(png_options.clone());
-    png_renderer
+    let pdf_renderer = PdfRenderer::new(pdf_options.clone());
+    pdf_renderer
         .render_svg(&svg)
         .map_err(|e| Error::Render(e.to_string()))
 }
 
+/// Строит display list — независимый от backend список команд рисования.
+///
+/// Команды (контуры, текст, группы с `id` и ссылками) сериализуются в JSON
+/// и рисуются на canvas в браузере или в нативном GUI; PNG растеризуется
+/// из них через `PngRenderer::render_display_list`.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{render_display_list, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob: Hello\n@enduml";
+/// let list = render_display_list(source, &RenderOptions::default()).unwrap();
+/// assert!(!list.commands.is_empty());
+/// ```
+pub fn render_display_list(source: &str, options: &RenderOptions) -> Result<DisplayList> {
+    pipeline::display_list_pipeline(source, options)
+}
+
 /// Парсит PlantUML и возвращает AST без рендеринга.
 ///
 /// Полезно для анализа структуры диаграммы или для собственного рендеринга.
@@ -165,12 +324,14 @@ pub fn render_png(
 /// ```
 pub fn parse_diagram(source: &str) -> Result<Diagram> {
     // Препроцессинг
-    let processed = plantuml_preprocessor::preprocess(source)
-        .map_err(|e: plantuml_preprocessor::PreprocessError| Error::Preprocess(e.to_string()))?;
+    let mut ctx = plantuml_preprocessor::PreprocessContext::new();
+    let processed = plantuml_preprocessor::Preprocessor::new()
+        .process_with_context(source, &mut ctx)
+        .map_err(|e| Error::from_preprocess_error(e, ctx.error_location.take()))?;
 
     // Парсинг
     let diagram = plantuml_parser::parse(&processed)
-        .map_err(|e: plantuml_parser::ParseError| Error::Parse(e.to_string()))?;
+        .map_err(|e| Error::from_parse_error(e, &ctx.source_map))?;
 
     Ok(diagram)
 }
@@ -217,4 +378,114 @@ mod tests {
         let v = version();
         assert!(!v.is_empty());
     }
+
+    #[test]
+    fn test_stdlib_include() {
+        // Стандартная библиотека доступна без файловой системы
+        let source = "@startuml\n!include <C4/Unknown>\nAlice -> Bob\n@enduml";
+        let err = render(source, &RenderOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("C4/Unknown"));
+
+        let source = "@startuml\n!include common.puml\nAlice -> Bob\n@enduml";
+        let err = render(source, &RenderOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("!include не поддерживается"));
+    }
+
+    #[test]
+    fn test_render_c4_container() {
+        // Макросы C4 из встроенной stdlib: `!unquoted procedure` и вызовы без `$`
+        let source = r#"@startuml
+!include <C4/C4_Container>
+LAYOUT_LEFT_RIGHT()
+Person(user, "Пользователь", "Покупатель магазина")
+System_Boundary(shop, "Магазин") {
+    Container(web, "Веб-приложение", "Rust, axum", "Каталог и корзина")
+    ContainerDb(db, "База данных", "PostgreSQL")
+}
+System_Ext(pay, "Платёжный шлюз")
+Rel(user, web, "Покупает", "HTTPS")
+Rel(web, db, "Читает и пишет", "SQL, TCP")
+Rel_R(web, pay, "Оплата")
+@enduml"#;
+        let svg = render(source, &RenderOptions::default()).unwrap();
+        for text in [
+            "Пользователь",
+            "Веб-приложение",
+            "[Rust, axum]",
+            "База данных",
+            "Магазин",
+            "Платёжный шлюз",
+            "Покупает",
+            "[SQL, TCP]",
+        ] {
+            assert!(svg.contains(text), "нет {:?} в SVG", text);
+        }
+        assert!(!svg.contains("$label"));
+        assert!(svg.contains("id=\"conn_web_db\""));
+    }
+
+    #[test]
+    fn test_render_c4_layout_and_legend() {
+        // Lay_* — скрытые связи, SHOW_LEGEND — только встретившиеся виды элементов
+        let source = r#"@startuml
+!include <C4/C4_Container>
+Person(user, "Пользователь")
+System(shop, "Магазин")
+System_Ext(pay, "Платёжный шлюз")
+Lay_D(user, shop)
+Lay_R(shop, pay)
+SHOW_LEGEND()
+@enduml"#;
+        let svg = render(source, &RenderOptions::default()).unwrap();
+        assert!(!svg.contains("id=\"conn_"));
+        for text in ["person", "system", "external system"] {
+            assert!(svg.contains(&format!("\n{}\n", text)), "нет {:?} в легенде", text);
+        }
+        assert!(svg.contains("id=\"legend_line_2\""));
+        assert!(!svg.contains("id=\"legend_line_3\""));
+    }
+
+    #[test]
+    fn test_render_sprites() {
+        let source = r#"@startuml
+sprite $dot [3x3/4] {
+GGG
+}
+sprite $logo <svg viewBox="0 0 20 20"><circle cx="10" cy="10" r="8"/></svg>
+participant "<$dot>\nAlice" as A <<$logo>>
+A -> Bob: <$logo{scale=0.5}> готово
+@enduml"#;
+        let svg = render(source, &RenderOptions::default()).unwrap();
+        assert!(svg.contains("class=\"sprite sprite-dot\""));
+        assert_eq!(svg.matches("<image").count(), 2);
+        assert!(!svg.contains("&lt;$"));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn test_render_pdf() {
+        let source = "@startuml\nAlice -> Bob: Привет\n@enduml";
+        let options = RenderOptions::new().with_format(OutputFormat::Pdf);
+        let pdf = render_pdf(source, &options, &PdfOptions::default()).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.windows(10).any(|w| w == b"/FontFile2"));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png_embedded_source() {
+        let source = "@startuml\nAlice -> Bob: Привет\n@enduml";
+        let options = RenderOptions::new().with_embedded_source();
+        for png in [
+            render_png(source, &options, &PngOptions::default()).unwrap(),
+            render_png_direct(source, &options, &PngOptions::default()).unwrap(),
+        ] {
+            let embedded = extract_source(&png).unwrap();
+            assert_eq!(embedded.source, source);
+            assert_eq!(embedded.version.as_deref(), Some(version()));
+        }
+
+        let png = render_png(source, &RenderOptions::default(), &PngOptions::default()).unwrap();
+        assert_eq!(extract_source(&png), None);
+    }
 }
@@ -0,0 +1,253 @@
+//! Исходный код диаграммы внутри SVG и PNG
+//!
+//! С [`RenderOptions::embed_source`](crate::RenderOptions::embed_source)
+//! исходный текст (до препроцессинга) и версия библиотеки сохраняются
+//! в элементе `<metadata>` SVG и в чанках PNG: `iTXt` с ключом `plantuml`
+//! (UTF-8) и `tEXt` с ключом `Software`. [`extract_source`] восстанавливает
+//! их из готового файла, чтобы картинку можно было снова открыть для правки.
+
+use crate::{version, Error, Result};
+
+/// Пространство имён элемента с исходным кодом в SVG
+const SVG_NAMESPACE: &str = "https://plantuml.com/source";
+
+/// Ключ чанка PNG с исходным кодом
+const PNG_KEYWORD: &[u8] = b"plantuml";
+
+/// Подпись PNG файла
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Исходный код, извлечённый из SVG или PNG
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedSource {
+    /// Исходный код PlantUML
+    pub source: String,
+    /// Версия библиотеки, создавшей файл
+    pub version: Option<String>,
+}
+
+/// Извлекает исходный код, встроенный при рендеринге в SVG или PNG.
+///
+/// Возвращает `None`, если файл не содержит исходного кода.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{extract_source, render, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob\n@enduml";
+/// let svg = render(source, &RenderOptions::new().with_embedded_source()).unwrap();
+/// let embedded = extract_source(svg.as_bytes()).unwrap();
+/// assert_eq!(embedded.source, source);
+/// ```
+pub fn extract_source(bytes: &[u8]) -> Option<EmbeddedSource> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        extract_png(bytes)
+    } else {
+        extract_svg(std::str::from_utf8(bytes).ok()?)
+    }
+}
+
+/// Вставляет `<metadata>` с исходным кодом сразу после открывающего `<svg>`
+pub(crate) fn embed_svg(svg: &str, source: &str) -> String {
+    let Some(start) = svg.find("<svg") else {
+        return svg.to_string();
+    };
+    let Some(end) = svg[start..].find('>').map(|i| start + i + 1) else {
+        return svg.to_string();
+    };
+    // `]]>` внутри исходника разбивается на две секции CDATA
+    let cdata = source.replace("]]>", "]]]]><![CDATA[>");
+    format!(
+        "{}<metadata><plantuml xmlns=\"{}\" version=\"{}\"><![CDATA[{}]]></plantuml></metadata>{}",
+        &svg[..end],
+        SVG_NAMESPACE,
+        version(),
+        cdata,
+        &svg[end..]
+    )
+}
+
+fn extract_svg(svg: &str) -> Option<EmbeddedSource> {
+    let metadata = &svg[svg.find("<metadata>")?..];
+    let element = &metadata[metadata.find("<plantuml")?..];
+    let open_end = element.find('>')?;
+    let version = attribute(&element[..open_end], "version");
+    let body = &element[open_end + 1..element.find("</plantuml>")?];
+
+    let mut source = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("<![CDATA[") {
+        let content = &rest[start + "<![CDATA[".len()..];
+        let end = content.find("]]>")?;
+        source.push_str(&content[..end]);
+        rest = &content[end + "]]>".len()..];
+    }
+    Some(EmbeddedSource { source, version })
+}
+
+/// Значение атрибута в открывающем теге
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!("{}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = tag[start..].find('"')?;
+    Some(tag[start..start + end].to_string())
+}
+
+/// Добавляет чанки `iTXt` (исходный код) и `tEXt` (версия) перед `IEND`
+pub(crate) fn embed_png(png: &[u8], source: &str) -> Result<Vec<u8>> {
+    let iend = png_chunks(png)
+        .find(|chunk| chunk.kind == *b"IEND")
+        .map(|chunk| chunk.offset)
+        .ok_or_else(|| Error::Render("PNG без чанка IEND".to_string()))?;
+
+    // keyword, флаг и метод сжатия, пустые язык и переведённый ключ, текст
+    let mut itxt = PNG_KEYWORD.to_vec();
+    itxt.extend_from_slice(&[0, 0, 0, 0, 0]);
+    itxt.extend_from_slice(source.as_bytes());
+    let mut text = b"Software\0".to_vec();
+    text.extend_from_slice(format!("plantuml-rs {}", version()).as_bytes());
+
+    let mut out = Vec::with_capacity(png.len() + itxt.len() + text.len() + 24);
+    out.extend_from_slice(&png[..iend]);
+    write_chunk(&mut out, b"iTXt", &itxt);
+    write_chunk(&mut out, b"tEXt", &text);
+    out.extend_from_slice(&png[iend..]);
+    Ok(out)
+}
+
+fn extract_png(png: &[u8]) -> Option<EmbeddedSource> {
+    let mut source = None;
+    let mut version = None;
+    for chunk in png_chunks(png) {
+        match &chunk.kind {
+            b"iTXt" => {
+                let Some(rest) = chunk
+                    .data
+                    .strip_prefix(PNG_KEYWORD)
+                    .and_then(|rest| rest.strip_prefix(b"\0"))
+                else {
+                    continue;
+                };
+                // Сжатый текст (флаг 1) рендерер не записывает
+                let [0, _, rest @ ..] = rest else {
+                    continue;
+                };
+                // Пропускаем язык и переведённый ключ
+                let Some(text) = skip_nul_terminated(rest).and_then(skip_nul_terminated) else {
+                    continue;
+                };
+                source = Some(String::from_utf8_lossy(text).into_owned());
+            }
+            b"tEXt" => {
+                if let Some(software) = chunk.data.strip_prefix(b"Software\0") {
+                    let software = String::from_utf8_lossy(software);
+                    if let Some(v) = software.strip_prefix("plantuml-rs ") {
+                        version = Some(v.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    source.map(|source| EmbeddedSource { source, version })
+}
+
+/// Остаток после строки, завершённой нулевым байтом
+fn skip_nul_terminated(bytes: &[u8]) -> Option<&[u8]> {
+    let end = bytes.iter().position(|&b| b == 0)?;
+    Some(&bytes[end + 1..])
+}
+
+/// Чанк PNG: тип, данные и смещение начала чанка в файле
+struct PngChunk<'a> {
+    kind: [u8; 4],
+    data: &'a [u8],
+    offset: usize,
+}
+
+/// Чанки PNG по порядку; обход останавливается на повреждённом чанке
+fn png_chunks(png: &[u8]) -> impl Iterator<Item = PngChunk<'_>> {
+    let mut offset = PNG_SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let header = png.get(offset..offset + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let data = png.get(offset + 8..offset + 8 + length)?;
+        let chunk = PngChunk { kind, data, offset };
+        // Длина, тип, данные и CRC
+        offset += 12 + length;
+        Some(chunk)
+    })
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(kind.iter().chain(data)).to_be_bytes());
+}
+
+/// CRC-32 (ISO 3309), как требует формат PNG
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_round_trip() {
+        let source = "@startuml\nAlice -> Bob: a]]>b & <c>\n@enduml";
+        let svg = embed_svg(
+            "<?xml version=\"1.0\"?>\n<svg width=\"1\"><g/></svg>",
+            source,
+        );
+        assert!(svg.contains("<svg width=\"1\"><metadata><plantuml"));
+
+        let embedded = extract_source(svg.as_bytes()).unwrap();
+        assert_eq!(embedded.source, source);
+        assert_eq!(embedded.version.as_deref(), Some(version()));
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        // Минимальный PNG: подпись, IHDR и IEND
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        write_chunk(&mut png, b"IEND", &[]);
+
+        let source = "@startuml\nАлиса -> Боб\n@enduml";
+        let embedded = embed_png(&png, source).unwrap();
+        assert!(embedded.ends_with(&png[png.len() - 12..]));
+
+        let extracted = extract_source(&embedded).unwrap();
+        assert_eq!(extracted.source, source);
+        assert_eq!(extracted.version.as_deref(), Some(version()));
+    }
+
+    #[test]
+    fn test_crc32() {
+        // CRC пустого чанка IEND из спецификации PNG
+        assert_eq!(crc32(b"IEND".iter()), 0xAE42_6082);
+    }
+
+    #[test]
+    fn test_extract_without_source() {
+        assert_eq!(extract_source(b"<svg></svg>"), None);
+        assert_eq!(extract_source(PNG_SIGNATURE), None);
+        assert!(embed_png(PNG_SIGNATURE, "x").is_err());
+    }
+}
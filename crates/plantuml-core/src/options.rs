@@ -25,6 +25,10 @@ pub struct RenderOptions {
 
     /// Максимальная высота (None = без ограничений)
     pub max_height: Option<f64>,
+
+    /// Встроить исходный код и версию в SVG (`<metadata>`) и PNG (`iTXt`),
+    /// см. `extract_source`
+    pub embed_source: bool,
 }
 
 /// Формат вывода
@@ -33,7 +37,33 @@ pub enum OutputFormat {
     /// SVG (по умолчанию)
     #[default]
     Svg,
-    // PNG будет добавлен позже через resvg
+    /// Graphviz DOT (для графовых диаграмм, без layout)
+    Dot,
+    /// Векторный PDF — двоичный формат, см. `render_pdf` (feature `pdf`)
+    Pdf,
+    /// Текст ASCII (`-ttxt`, только диаграммы последовательностей)
+    Txt,
+    /// Текст с псевдографикой Unicode (`-tutxt`)
+    Utxt,
+    /// PNG без промежуточного SVG — двоичный формат, см. `render_image` (feature `png`)
+    Png,
+    /// JPEG — двоичный формат, см. `render_image` (feature `jpeg`)
+    Jpeg,
+    /// WebP без потерь — двоичный формат, см. `render_image` (feature `webp`)
+    WebP,
+    /// Encapsulated PostScript (`-teps`)
+    Eps,
+    /// Документ LaTeX с рисунком TikZ (`-tlatex`)
+    Latex,
+    /// Только окружение `tikzpicture` (`-tlatex:nopreamble`)
+    LatexNoPreamble,
+}
+
+impl OutputFormat {
+    /// Двоичный формат: результат не возвращается строкой из `render`
+    pub fn is_binary(self) -> bool {
+        matches!(self, Self::Pdf | Self::Png | Self::Jpeg | Self::WebP)
+    }
 }
 
 impl Default for RenderOptions {
@@ -46,6 +76,7 @@ impl Default for RenderOptions {
             background_color: None,
             max_width: None,
             max_height: None,
+            embed_source: false,
         }
     }
 }
@@ -70,6 +101,12 @@ impl RenderOptions {
         self
     }
 
+    /// Устанавливает формат вывода
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     /// Устанавливает масштаб
     pub fn with_scale(mut self, scale: f64) -> Self {
         self.scale = scale;
@@ -105,6 +142,12 @@ impl RenderOptions {
         self.max_height = Some(height);
         self
     }
+
+    /// Встраивает исходный код диаграммы в SVG и PNG
+    pub fn with_embedded_source(mut self) -> Self {
+        self.embed_source = true;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -116,6 +159,7 @@ mod tests {
         let opts = RenderOptions::default();
         assert_eq!(opts.scale, 1.0);
         assert!(opts.xml_header);
+        assert!(!opts.embed_source);
     }
 
     #[test]
@@ -129,4 +173,13 @@ mod tests {
         assert_eq!(opts.scale, 2.0);
         assert!(!opts.xml_header);
     }
+
+    #[test]
+    fn test_with_format() {
+        assert_eq!(RenderOptions::default().format, OutputFormat::Svg);
+        let opts = RenderOptions::new().with_format(OutputFormat::Dot);
+        assert_eq!(opts.format, OutputFormat::Dot);
+        assert!(!OutputFormat::Eps.is_binary());
+        assert!(OutputFormat::WebP.is_binary());
+    }
 }
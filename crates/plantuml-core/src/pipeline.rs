@@ -1,39 +1,51 @@
 //! Pipeline рендеринга диаграмм
 
 use std::path::Path;
+use std::sync::Arc;
 
-use crate::{Error, RenderOptions, Result};
+use crate::{
+    export_dot, export_text, metadata, Error, OutputFormat, RenderOptions, Result, TextCharset,
+};
 use plantuml_ast::Diagram;
+use plantuml_layout::graph::LineType;
 use plantuml_layout::{
-    ActivityLayoutEngine, ClassLayoutEngine, ComponentLayoutEngine, ErLayoutEngine,
+    ActivityLayoutEngine, ClassLayoutConfig, ClassLayoutEngine, ComponentLayoutConfig,
+    ComponentLayoutEngine, DotLayoutConfig, DotLayoutEngine, ErLayoutConfig, ErLayoutEngine,
     GanttLayoutEngine, JsonLayoutEngine, LayoutConfig, LayoutResult, MindMapLayoutEngine,
-    NetworkLayoutEngine, ObjectLayoutEngine, SaltLayoutEngine, SequenceLayoutEngine,
-    StateLayoutEngine, TimingLayoutEngine, UseCaseLayoutEngine, WbsLayoutEngine, YamlLayoutEngine,
+    NetworkLayoutEngine, ObjectLayoutConfig, ObjectLayoutEngine, SaltLayoutEngine,
+    SequenceLayoutEngine, StateLayoutConfig, StateLayoutEngine, TextMetrics, TimingLayoutEngine,
+    UseCaseLayoutConfig, UseCaseLayoutEngine, WbsLayoutEngine, YamlLayoutEngine,
+};
+use plantuml_preprocessor::{
+    FileResolver, FsFileResolver, PreprocessContext, Preprocessor, SkinParams, SourceMap,
+};
+use plantuml_renderer::{
+    DisplayList, DisplayListRenderer, EpsRenderer, Renderer, SvgRenderer, TikzRenderer,
 };
-use plantuml_preprocessor::{FsFileResolver, Preprocessor};
-use plantuml_renderer::{Renderer, SvgRenderer};
+use plantuml_stdlib::StdlibResolver;
+use plantuml_themes::Theme;
+
+#[cfg(any(feature = "png", feature = "pdf"))]
+use plantuml_renderer::FontSet;
+#[cfg(feature = "png")]
+use plantuml_renderer::{PngOptions, RasterRenderer};
+#[cfg(any(feature = "png", feature = "pdf"))]
+use std::sync::OnceLock;
 
 /// Выполняет полный pipeline рендеринга
 pub fn render_pipeline(source: &str, options: &RenderOptions) -> Result<String> {
     // Проверка на пустой исходник
-    let source = source.trim();
-    if source.is_empty() {
-        return Err(Error::EmptySource);
-    }
+    let (source, skipped) = trim_source(source)?;
 
     // 1. Препроцессинг
-    let processed = preprocess(source)?;
+    let (processed, settings) = preprocess(source, skipped)?;
 
     // 2. Парсинг
-    let diagram = parse(&processed)?;
-
-    // 3. Layout
-    let layout = layout(&diagram, options)?;
+    let diagram = parse(&processed, &settings)?;
 
-    // 4. Рендеринг
-    let svg = render_svg(&layout, options)?;
-
-    Ok(svg)
+    // 3-4. Layout и рендеринг (или экспорт в DOT)
+    let output = render_diagram(&diagram, &settings, options)?;
+    Ok(embed_source(output, source, options))
 }
 
 /// Выполняет полный pipeline с поддержкой !include
@@ -41,170 +53,429 @@ pub fn render_pipeline_with_includes(
     source: &str,
     base_path: &Path,
     options: &RenderOptions,
+) -> Result<String> {
+    render_pipeline_with_resolver(source, FsFileResolver::new(base_path), options)
+}
+
+/// Выполняет полный pipeline, читая `!include` через `resolver`
+/// (стандартная библиотека остаётся доступной)
+pub fn render_pipeline_with_resolver<R: FileResolver>(
+    source: &str,
+    resolver: R,
+    options: &RenderOptions,
 ) -> Result<String> {
     // Проверка на пустой исходник
-    let source = source.trim();
-    if source.is_empty() {
-        return Err(Error::EmptySource);
-    }
+    let (source, skipped) = trim_source(source)?;
 
     // 1. Препроцессинг с поддержкой файлов
-    let processed = preprocess_with_includes(source, base_path)?;
+    let (processed, settings) = preprocess_with_resolver(source, skipped, resolver)?;
 
     // 2. Парсинг
-    let diagram = parse(&processed)?;
+    let diagram = parse(&processed, &settings)?;
+
+    // 3-4. Layout и рендеринг (или экспорт в DOT)
+    let output = render_diagram(&diagram, &settings, options)?;
+    Ok(embed_source(output, source, options))
+}
+
+/// Pipeline до display list: препроцессинг, парсинг, layout и команды рисования
+pub fn display_list_pipeline(source: &str, options: &RenderOptions) -> Result<DisplayList> {
+    let (source, skipped) = trim_source(source)?;
+    let (processed, settings) = preprocess(source, skipped)?;
+    let diagram = parse(&processed, &settings)?;
+    let layout = layout(&diagram, &settings, default_metrics())?;
 
-    // 3. Layout
-    let layout = layout(&diagram, options)?;
+    let renderer = DisplayListRenderer::with_options(renderer_options(options))
+        .with_sprites(diagram.metadata().sprites.clone());
+    Ok(renderer.render(&layout, &diagram_theme(&settings.skin_params, options)))
+}
+
+/// Pipeline до растрового изображения без SVG: layout рисуется
+/// [`RasterRenderer`] напрямую и кодируется в `format` (PNG, JPEG или WebP)
+#[cfg(feature = "png")]
+pub fn raster_pipeline(
+    source: &str,
+    options: &RenderOptions,
+    png_options: &PngOptions,
+    format: OutputFormat,
+) -> Result<Vec<u8>> {
+    let (source, skipped) = trim_source(source)?;
+    let (processed, settings) = preprocess(source, skipped)?;
+    let diagram = parse(&processed, &settings)?;
+
+    let renderer = RasterRenderer::new(png_options.clone())
+        .with_render_options(renderer_options(options))
+        .with_sprites(diagram.metadata().sprites.clone());
+    // Размеры подписей — по шрифтам, которыми рендерер рисует текст
+    let fonts = renderer.fonts().map_err(|e| Error::Render(e.to_string()))?;
+    let layout = layout(&diagram, &settings, Some(Arc::new(fonts.clone())))?;
+    let theme = diagram_theme(&settings.skin_params, options);
+    let encoded = match format {
+        OutputFormat::Png => renderer.render_png(&layout, &theme),
+        #[cfg(feature = "jpeg")]
+        OutputFormat::Jpeg => renderer.render_jpeg(&layout, &theme),
+        #[cfg(feature = "webp")]
+        OutputFormat::WebP => renderer.render_webp(&layout, &theme),
+        other => {
+            return Err(Error::Render(format!(
+                "{:?} не поддерживается растровым выводом (JPEG и WebP — features `jpeg` и `webp`)",
+                other
+            )))
+        }
+    };
+    let encoded = encoded.map_err(|e| Error::Render(e.to_string()))?;
+    if options.embed_source && format == OutputFormat::Png {
+        return metadata::embed_png(&encoded, source);
+    }
+    Ok(encoded)
+}
+
+/// Обрезает пробельные символы вокруг исходника; возвращает его и число
+/// отброшенных строк в начале, чтобы ошибки указывали строки исходника
+fn trim_source(source: &str) -> Result<(&str, usize)> {
+    let trimmed = source.trim();
+    if trimmed.is_empty() {
+        return Err(Error::EmptySource);
+    }
+    let skipped = source[..source.len() - source.trim_start().len()]
+        .matches('\n')
+        .count();
+    Ok((trimmed, skipped))
+}
+
+/// Исходный код в `<metadata>` SVG, если включён `embed_source`
+fn embed_source(output: String, source: &str, options: &RenderOptions) -> String {
+    if options.embed_source && options.format == OutputFormat::Svg {
+        metadata::embed_svg(&output, source)
+    } else {
+        output
+    }
+}
+
+/// Этапы после парсинга: layout и SVG, либо экспорт в DOT или текст
+fn render_diagram(
+    diagram: &Diagram,
+    settings: &DiagramSettings,
+    options: &RenderOptions,
+) -> Result<String> {
+    let skin_params = &settings.skin_params;
+    match options.format {
+        OutputFormat::Svg => {
+            let layout = layout(diagram, settings, default_metrics())?;
+            render_svg(&layout, diagram, skin_params, options)
+        }
+        OutputFormat::Dot => export_dot(diagram),
+        OutputFormat::Pdf => Err(Error::Render(
+            "PDF — двоичный формат, используйте render_pdf".to_string(),
+        )),
+        OutputFormat::Png | OutputFormat::Jpeg | OutputFormat::WebP => Err(Error::Render(
+            format!("{:?} — двоичный формат, используйте render_image", options.format),
+        )),
+        OutputFormat::Txt => export_text(diagram, TextCharset::Ascii),
+        OutputFormat::Utxt => export_text(diagram, TextCharset::Unicode),
+        OutputFormat::Eps => {
+            let layout = layout(diagram, settings, default_metrics())?;
+            let renderer = EpsRenderer::with_options(renderer_options(options))
+                .with_sprites(diagram.metadata().sprites.clone());
+            Ok(renderer.render(&layout, &diagram_theme(skin_params, options)))
+        }
+        OutputFormat::Latex | OutputFormat::LatexNoPreamble => {
+            let layout = layout(diagram, settings, default_metrics())?;
+            let mut renderer = TikzRenderer::with_options(renderer_options(options))
+                .with_sprites(diagram.metadata().sprites.clone());
+            if options.format == OutputFormat::LatexNoPreamble {
+                renderer = renderer.without_preamble();
+            }
+            Ok(renderer.render(&layout, &diagram_theme(skin_params, options)))
+        }
+    }
+}
 
-    // 4. Рендеринг
-    let svg = render_svg(&layout, options)?;
+/// Настройки диаграммы, собранные препроцессором
+struct DiagramSettings {
+    /// `skinparam` диаграммы
+    skin_params: SkinParams,
+    /// Значения `!pragma` (ключи в нижнем регистре)
+    pragmas: Vec<(String, String)>,
+    /// Положения строк текста диаграммы в исходных файлах
+    source_map: SourceMap,
+}
 
-    Ok(svg)
+impl DiagramSettings {
+    /// Значение `!pragma` по имени
+    fn pragma(&self, name: &str) -> Option<&str> {
+        self.pragmas
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
 }
 
 /// Этап препроцессинга
-fn preprocess(source: &str) -> Result<String> {
-    plantuml_preprocessor::preprocess(source)
-        .map_err(|e: plantuml_preprocessor::PreprocessError| Error::Preprocess(e.to_string()))
+///
+/// Возвращает текст диаграммы и собранные `skinparam`, `!pragma` и карту строк.
+/// `skipped` — число строк, отброшенных перед `source`. Доступны только
+/// `!include <...>` из встроенной стандартной библиотеки.
+fn preprocess(source: &str, skipped: usize) -> Result<(String, DiagramSettings)> {
+    run_preprocessor(&Preprocessor::with_resolver(StdlibResolver::new()), source, skipped)
+}
+
+/// Этап препроцессинга с поддержкой !include через `resolver`
+fn preprocess_with_resolver<R: FileResolver>(
+    source: &str,
+    skipped: usize,
+    resolver: R,
+) -> Result<(String, DiagramSettings)> {
+    let resolver = StdlibResolver::with_fallback(resolver);
+    run_preprocessor(&Preprocessor::with_resolver(resolver), source, skipped)
 }
 
-/// Этап препроцессинга с поддержкой !include
-fn preprocess_with_includes(source: &str, base_path: &Path) -> Result<String> {
-    let resolver = FsFileResolver::new(base_path);
-    let preprocessor = Preprocessor::with_resolver(resolver);
-    preprocessor
-        .process(source)
-        .map_err(|e: plantuml_preprocessor::PreprocessError| Error::Preprocess(e.to_string()))
+/// Запускает препроцессор и забирает `skinparam`, `!pragma` и карту строк из контекста
+fn run_preprocessor<R: FileResolver>(
+    preprocessor: &Preprocessor<R>,
+    source: &str,
+    skipped: usize,
+) -> Result<(String, DiagramSettings)> {
+    let mut ctx = PreprocessContext::new();
+    let processed = match preprocessor.process_with_context(source, &mut ctx) {
+        Ok(processed) => processed,
+        Err(e) => {
+            let mut location = ctx.error_location.take();
+            if let Some(location) = &mut location {
+                location.shift_main_lines(skipped);
+            }
+            return Err(Error::from_preprocess_error(e, location));
+        }
+    };
+    ctx.source_map.shift_main_lines(skipped);
+    let settings = DiagramSettings {
+        skin_params: ctx.skin_params,
+        pragmas: ctx.pragmas.into_iter().collect(),
+        source_map: ctx.source_map,
+    };
+    Ok((processed, settings))
 }
 
-/// Этап парсинга
-fn parse(source: &str) -> Result<Diagram> {
+/// Этап парсинга; номера строк ошибок переводятся в исходные файлы
+fn parse(source: &str, settings: &DiagramSettings) -> Result<Diagram> {
     plantuml_parser::parse(source)
-        .map_err(|e: plantuml_parser::ParseError| Error::Parse(e.to_string()))
+        .map_err(|e| Error::from_parse_error(e, &settings.source_map))
+}
+
+/// Метрики текста для layout векторных форматов: встроенный шрифт растровых
+/// рендереров (features `png`, `pdf`), чтобы SVG и PNG раскладывались
+/// одинаково; без шрифтов движки оценивают ширину по числу символов
+fn default_metrics() -> Option<Arc<dyn TextMetrics>> {
+    #[cfg(any(feature = "png", feature = "pdf"))]
+    {
+        static METRICS: OnceLock<Arc<dyn TextMetrics>> = OnceLock::new();
+        Some(
+            METRICS
+                .get_or_init(|| Arc::new(FontSet::embedded()))
+                .clone(),
+        )
+    }
+    #[cfg(not(any(feature = "png", feature = "pdf")))]
+    None
 }
 
-/// Этап layout
-fn layout(diagram: &Diagram, _options: &RenderOptions) -> Result<LayoutResult> {
+/// Этап layout; размеры подписей измеряются `metrics`, если они заданы
+fn layout(
+    diagram: &Diagram,
+    settings: &DiagramSettings,
+    metrics: Option<Arc<dyn TextMetrics>>,
+) -> Result<LayoutResult> {
+    // Подключает метрики к движку; без них остаётся его собственная оценка
+    macro_rules! measured {
+        ($engine:expr) => {
+            match metrics {
+                Some(metrics) => $engine.with_metrics(metrics),
+                None => $engine,
+            }
+        };
+    }
+
     let _config = LayoutConfig::default();
+    // skinparam linetype ortho|polyline (по умолчанию — сплайны); `!pragma layout elk`
+    // без явного linetype даёт ортогональные рёбра, как раскладка ELK
+    let line_type = settings
+        .skin_params
+        .get_ignore_case("linetype")
+        .and_then(|v| LineType::parse(v))
+        .or_else(|| {
+            settings
+                .pragma("layout")
+                .filter(|engine| engine.eq_ignore_ascii_case("elk"))
+                .map(|_| LineType::Ortho)
+        })
+        .unwrap_or_default();
 
     // Выбираем layout engine в зависимости от типа диаграммы
     match diagram {
         Diagram::Sequence(seq) => {
             // Используем SequenceLayoutEngine для sequence diagrams
-            let engine = SequenceLayoutEngine::new();
+            let engine = measured!(SequenceLayoutEngine::new());
             Ok(engine.layout(seq))
         }
         Diagram::Class(class) => {
             // Используем ClassLayoutEngine для class diagrams (Sugiyama algorithm)
-            let engine = ClassLayoutEngine::new();
+            let engine = measured!(ClassLayoutEngine::with_config(ClassLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout_diagram(class))
         }
         Diagram::Activity(act) => {
             // Используем ActivityLayoutEngine для activity diagrams
-            let engine = ActivityLayoutEngine::new();
+            let engine = measured!(ActivityLayoutEngine::new());
             Ok(engine.layout(act))
         }
         Diagram::State(state) => {
             // Используем StateLayoutEngine для state diagrams
-            let engine = StateLayoutEngine::new();
+            let engine = measured!(StateLayoutEngine::with_config(StateLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(state))
         }
         Diagram::Component(comp) => {
             // Используем ComponentLayoutEngine для component diagrams
-            let engine = ComponentLayoutEngine::new();
+            let engine = measured!(ComponentLayoutEngine::with_config(ComponentLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(comp))
         }
         Diagram::UseCase(uc) => {
             // Используем UseCaseLayoutEngine для use case diagrams
-            let engine = UseCaseLayoutEngine::new();
+            let engine = measured!(UseCaseLayoutEngine::with_config(UseCaseLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(uc))
         }
         Diagram::Deployment(dep) => {
             // Deployment использует ComponentLayoutEngine (та же структура)
-            let engine = ComponentLayoutEngine::new();
+            let engine = measured!(ComponentLayoutEngine::with_config(ComponentLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(dep))
         }
         Diagram::Object(obj) => {
             // Используем ObjectLayoutEngine для object diagrams
-            let engine = ObjectLayoutEngine::new();
+            let engine = measured!(ObjectLayoutEngine::with_config(ObjectLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(obj))
         }
         Diagram::Timing(timing) => {
             // Используем TimingLayoutEngine для timing diagrams
-            let engine = TimingLayoutEngine::new();
+            let engine = measured!(TimingLayoutEngine::new());
             Ok(engine.layout(timing))
         }
         Diagram::Gantt(gantt) => {
             // Используем GanttLayoutEngine для gantt diagrams
-            let engine = GanttLayoutEngine::new();
+            let engine = measured!(GanttLayoutEngine::new());
             Ok(engine.layout(gantt))
         }
         Diagram::MindMap(mindmap) => {
             // Используем MindMapLayoutEngine для mindmap diagrams
-            let engine = MindMapLayoutEngine::new();
+            let engine = measured!(MindMapLayoutEngine::new());
             Ok(engine.layout(mindmap))
         }
         Diagram::Wbs(wbs) => {
             // Используем WbsLayoutEngine для wbs diagrams
-            let engine = WbsLayoutEngine::new();
+            let engine = measured!(WbsLayoutEngine::new());
             Ok(engine.layout(wbs))
         }
         Diagram::Json(json) => {
             // Используем JsonLayoutEngine для json diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = JsonLayoutEngine::new();
+            let engine = measured!(JsonLayoutEngine::new());
             Ok(engine.layout(json, &_config))
         }
         Diagram::Yaml(yaml) => {
             // Используем YamlLayoutEngine для yaml diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = YamlLayoutEngine::new();
+            let engine = measured!(YamlLayoutEngine::new());
             Ok(engine.layout(yaml, &_config))
         }
         Diagram::Er(er) => {
             // Используем ErLayoutEngine для ER diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = ErLayoutEngine::new();
+            let engine = measured!(ErLayoutEngine::with_config(ErLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(er, &_config))
         }
         Diagram::Network(net) => {
             // Используем NetworkLayoutEngine для network diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = NetworkLayoutEngine::new();
+            let engine = measured!(NetworkLayoutEngine::new());
             Ok(engine.layout(net, &_config))
         }
         Diagram::Salt(salt) => {
             // Используем SaltLayoutEngine для salt diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = SaltLayoutEngine::new();
+            let engine = measured!(SaltLayoutEngine::new());
             Ok(engine.layout(salt, &_config))
         }
         Diagram::Archimate(arch) => {
             // Archimate использует ComponentLayoutEngine
-            let engine = ComponentLayoutEngine::new();
+            let engine = measured!(ComponentLayoutEngine::with_config(ComponentLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
             Ok(engine.layout(arch))
         }
+        Diagram::Dot(dot) => {
+            // DOT граф раскладывается общим Sugiyama layout; `splines` графа
+            // имеет приоритет над `skinparam linetype`
+            let engine = measured!(DotLayoutEngine::with_config(DotLayoutConfig {
+                line_type,
+                ..Default::default()
+            }));
+            Ok(engine.layout(dot))
+        }
     }
 }
 
-/// Этап SVG рендеринга
-fn render_svg(layout: &LayoutResult, options: &RenderOptions) -> Result<String> {
-    let render_options = plantuml_renderer::RenderOptions {
+/// Этап SVG рендеринга (со спрайтами диаграммы)
+fn render_svg(
+    layout: &LayoutResult,
+    diagram: &Diagram,
+    skin_params: &SkinParams,
+    options: &RenderOptions,
+) -> Result<String> {
+    let renderer = SvgRenderer::with_options(renderer_options(options))
+        .with_sprites(diagram.metadata().sprites.clone());
+    Ok(renderer.render(layout, &diagram_theme(skin_params, options)))
+}
+
+/// Опции рендерера из опций фасада
+fn renderer_options(options: &RenderOptions) -> plantuml_renderer::RenderOptions {
+    plantuml_renderer::RenderOptions {
         xml_header: options.xml_header,
         scale: options.scale,
         // None означает использовать PlantUML default (#FEFECE)
         background_color: options.background_color.clone(),
-    };
-
-    let renderer = SvgRenderer::with_options(render_options);
+    }
+}
 
-    Ok(renderer.render(layout, &options.theme))
+/// skinparam диаграммы (handwritten, shadowing, шрифт) поверх выбранной темы
+fn diagram_theme(skin_params: &SkinParams, options: &RenderOptions) -> Theme {
+    let mut theme = options.theme.clone();
+    skin_params.apply_to(&mut theme);
+    theme
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plantuml_preprocessor::MemoryFileResolver;
 
     #[test]
     fn test_pipeline_basic() {
@@ -251,4 +522,344 @@ Redux --> React: state updated
         let result = render_pipeline(source, &RenderOptions::default());
         assert!(result.is_ok(), "Pipeline error: {:?}", result.err());
     }
+
+    #[test]
+    fn test_pipeline_linetype() {
+        let has_curves = |svg: &str| {
+            svg.split("d=\"")
+                .skip(1)
+                .any(|d| d.split('"').next().unwrap_or("").contains('C'))
+        };
+        let diagram = "class Foo\nclass Bar\nclass Baz\nFoo --> Bar\nFoo --> Baz\n@enduml";
+
+        let spline = render_pipeline(&format!("@startuml\n{diagram}"), &RenderOptions::default())
+            .unwrap();
+        assert!(has_curves(&spline));
+
+        let ortho = render_pipeline(
+            &format!("@startuml\nskinparam linetype ortho\n{diagram}"),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert!(!has_curves(&ortho));
+
+        // !pragma layout elk даёт ортогональные рёбра, явный linetype важнее
+        let elk = render_pipeline(
+            &format!("@startuml\n!pragma layout elk\n{diagram}"),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(elk, ortho);
+        let elk_spline = render_pipeline(
+            &format!("@startuml\n!pragma layout elk\nskinparam linetype spline\n{diagram}"),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert!(has_curves(&elk_spline));
+    }
+
+    #[test]
+    fn test_pipeline_with_resolver() {
+        let resolver = MemoryFileResolver::new()
+            .with_file("common.puml", "!$target = \"Bob\"")
+            .with_file("other.puml", "Alice -> Carol");
+        let source = "@startuml\n!include common.puml\n!include other.puml\nAlice -> $target\n@enduml";
+
+        let svg = render_pipeline_with_resolver(source, &resolver, &RenderOptions::default())
+            .unwrap();
+        assert!(svg.contains("Bob"));
+        assert!(svg.contains("Carol"));
+
+        let missing = "\n@startuml\n!include missing.puml\n@enduml";
+        let error = render_pipeline_with_resolver(missing, &resolver, &RenderOptions::default())
+            .unwrap_err();
+        assert!(matches!(error, Error::PreprocessAt { .. }));
+        let location = error.location().expect("положение ошибки");
+        assert_eq!((location.file.as_deref(), location.line), (None, 3));
+        assert_eq!(
+            error.to_string(),
+            "ошибка препроцессора в строке 3: файл не найден: missing.puml (в памяти: 2 файлов)"
+        );
+
+        // Ошибка во включённом файле указывает на этот файл
+        let resolver = resolver.with_file("broken.puml", "A\n!$x = 1 / 0");
+        let source = "@startuml\n!include broken.puml\n@enduml";
+        let error = render_pipeline_with_resolver(source, &resolver, &RenderOptions::default())
+            .unwrap_err();
+        let location = error.location().expect("положение ошибки");
+        assert_eq!((location.file.as_deref(), location.line), (Some("broken.puml"), 2));
+    }
+
+    #[test]
+    fn test_pipeline_parse_error_location() {
+        let resolver = MemoryFileResolver::new().with_file("common.puml", "' общий файл\nclass A\nclass B {{{");
+        let source = "\n\n@startuml\n!include common.puml\n@enduml";
+        let error = render_pipeline_with_resolver(source, &resolver, &RenderOptions::default())
+            .unwrap_err();
+        let location = error.location().expect("положение ошибки");
+        assert_eq!(location.file.as_deref(), Some("common.puml"));
+        assert_eq!(location.line, 3);
+        // Номер строки только из карты исходников, без строки текста после препроцессора
+        assert_eq!(
+            error.to_string(),
+            "ошибка парсинга в common.puml:3: синтаксическая ошибка: expected member, позиция 10"
+        );
+
+        // Пустые строки перед @startuml учитываются в номерах строк основного исходника
+        let error = render_pipeline("\n@startuml\nclass A\nclass B {{{\n@enduml", &RenderOptions::default())
+            .unwrap_err();
+        let location = error.location().expect("положение ошибки");
+        assert_eq!((location.file.as_deref(), location.line), (None, 4));
+        assert_eq!(
+            error.to_string(),
+            "ошибка парсинга в строке 4: синтаксическая ошибка: expected member, позиция 10"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_dot() {
+        let source = r#"@startdot
+digraph G {
+  rankdir=LR
+  node [shape=box]
+  subgraph cluster_app { label="App"; ui -> api }
+  api -> db [label="SQL"]
+}
+@enddot"#;
+
+        let svg = render_pipeline(source, &RenderOptions::default()).unwrap();
+        assert!(svg.contains("node_api"));
+        assert!(svg.contains("cluster_cluster_app"));
+        assert!(svg.contains("SQL"));
+    }
+
+    #[test]
+    fn test_pipeline_dot_output() {
+        let options = RenderOptions::new().with_format(OutputFormat::Dot);
+        let dot = render_pipeline("@startuml\nclass A\nclass B\nA --> B\n@enduml", &options)
+            .unwrap();
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("\"A\" -> \"B\""));
+
+        let sequence = render_pipeline("@startuml\nAlice -> Bob\n@enduml", &options);
+        assert!(matches!(sequence, Err(Error::UnsupportedDiagram(_))));
+    }
+
+    #[test]
+    fn test_pipeline_pdf_is_binary() {
+        // PDF не возвращается строкой — только через render_pdf
+        let options = RenderOptions::new().with_format(OutputFormat::Pdf);
+        let result = render_pipeline("@startuml\nAlice -> Bob\n@enduml", &options);
+        assert!(matches!(result, Err(Error::Render(_))));
+    }
+
+    #[test]
+    fn test_pipeline_text_output() {
+        let source = "@startuml\nAlice -> Bob: Hello\n@enduml";
+        let options = RenderOptions::new().with_format(OutputFormat::Txt);
+        let text = render_pipeline(source, &options).unwrap();
+        assert!(text.contains("|Alice|"));
+        assert!(text.contains("Hello"));
+
+        let options = RenderOptions::new().with_format(OutputFormat::Utxt);
+        let text = render_pipeline(source, &options).unwrap();
+        assert!(text.contains("│Alice│"));
+
+        let class = render_pipeline("@startuml\nclass A\n@enduml", &options);
+        assert!(matches!(class, Err(Error::UnsupportedDiagram(_))));
+    }
+
+    #[test]
+    fn test_pipeline_embed_source() {
+        let source = "@startuml\nAlice -> Bob\n@enduml";
+        let svg = render_pipeline(source, &RenderOptions::default()).unwrap();
+        assert!(!svg.contains("<metadata>"));
+
+        let options = RenderOptions::new().with_embedded_source();
+        let svg = render_pipeline(source, &options).unwrap();
+        let embedded = metadata::extract_source(svg.as_bytes()).unwrap();
+        assert_eq!(embedded.source, source);
+
+        // Текстовые форматы не меняются
+        let options = options.with_format(OutputFormat::Txt);
+        assert!(!render_pipeline(source, &options).unwrap().contains("<metadata>"));
+    }
+
+    #[test]
+    fn test_pipeline_vector_text_formats() {
+        let source = "@startuml\nAlice -> Bob: Hello\n@enduml";
+        let eps = render_pipeline(source, &RenderOptions::new().with_format(OutputFormat::Eps))
+            .unwrap();
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0"));
+        assert!(eps.contains("(Hello)"));
+
+        let options = RenderOptions::new().with_format(OutputFormat::Latex);
+        let latex = render_pipeline(source, &options).unwrap();
+        assert!(latex.contains("\\begin{document}"));
+        assert!(latex.contains("{Hello};"));
+
+        let options = RenderOptions::new().with_format(OutputFormat::LatexNoPreamble);
+        let tikz = render_pipeline(source, &options).unwrap();
+        assert!(tikz.starts_with("\\begin{tikzpicture}"));
+
+        let options = RenderOptions::new().with_format(OutputFormat::Jpeg);
+        assert!(matches!(
+            render_pipeline(source, &options),
+            Err(Error::Render(_))
+        ));
+    }
+
+    #[test]
+    fn test_pipeline_display_list() {
+        let source = "@startuml\nAlice -> Bob: Hello\n@enduml";
+        let list = display_list_pipeline(source, &RenderOptions::default()).unwrap();
+        assert!(list.width > 0.0 && list.height > 0.0);
+
+        let mut texts = Vec::new();
+        list.visit(&mut |command| {
+            if let plantuml_renderer::DrawCommand::Text { text, .. } = command {
+                texts.push(text.clone());
+            }
+        });
+        assert!(texts.iter().any(|t| t == "Alice"));
+        assert!(texts.iter().any(|t| t == "Hello"));
+
+        assert!(matches!(
+            display_list_pipeline("  ", &RenderOptions::default()),
+            Err(Error::EmptySource)
+        ));
+    }
+
+    const SPRITE_SOURCE: &str = "@startuml\nsprite $dot [3x3/4] {\nFFF\nF0F\nFFF\n}\nparticipant \"<$dot>\\nAlice\" as A\nA -> Bob: <$dot> готово\n@enduml";
+
+    #[test]
+    fn test_pipeline_sprites_in_vector_backends() {
+        let list = display_list_pipeline(SPRITE_SOURCE, &RenderOptions::default()).unwrap();
+        let mut images = 0;
+        list.visit(&mut |command| {
+            if let plantuml_renderer::DrawCommand::Image { image, .. } = command {
+                assert!(matches!(image, plantuml_renderer::ImageData::Gray { .. }));
+                images += 1;
+            }
+        });
+        // Заголовок участника рисуется сверху и снизу, плюс подпись сообщения
+        assert!(images >= 2);
+
+        let options = RenderOptions::new().with_format(OutputFormat::Eps);
+        let eps = render_pipeline(SPRITE_SOURCE, &options).unwrap();
+        assert!(eps.contains("rectfill"));
+        assert!(!eps.contains("<$dot>"));
+
+        let options = RenderOptions::new().with_format(OutputFormat::LatexNoPreamble);
+        let tikz = render_pipeline(SPRITE_SOURCE, &options).unwrap();
+        assert!(tikz.contains(") rectangle ("));
+        assert!(!tikz.contains("<$dot>"));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_pipeline_raster_sprites() {
+        let options = RenderOptions::default();
+        let png_options = PngOptions::default();
+        let with_sprite =
+            raster_pipeline(SPRITE_SOURCE, &options, &png_options, OutputFormat::Png).unwrap();
+        let source = SPRITE_SOURCE.replace("<$dot>", "");
+        let without = raster_pipeline(&source, &options, &png_options, OutputFormat::Png).unwrap();
+        assert_eq!(&with_sprite[1..4], b"PNG");
+        assert_ne!(with_sprite, without);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_pipeline_raster() {
+        let source = "@startuml\nAlice -> Bob: Hello\n@enduml";
+        let options = RenderOptions::default();
+        let png = raster_pipeline(source, &options, &PngOptions::default(), OutputFormat::Png)
+            .unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        let result = raster_pipeline("", &options, &PngOptions::default(), OutputFormat::Png);
+        assert!(matches!(result, Err(Error::EmptySource)));
+
+        let svg = raster_pipeline(source, &options, &PngOptions::default(), OutputFormat::Svg);
+        assert!(matches!(svg, Err(Error::Render(_))));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_svg_and_png_same_sizes() {
+        let source = "@startuml\nclass VeryLongClassNameForTheWidthCheck {\n  +description: String\n}\nclass B\nVeryLongClassNameForTheWidthCheck --> B : подпись связи\n@enduml";
+        let (processed, settings) = preprocess(source, 0).unwrap();
+        let diagram = parse(&processed, &settings).unwrap();
+
+        // Узлы SVG раскладываются по тем же шрифтам, что рисует PNG
+        let svg_layout = layout(&diagram, &settings, default_metrics()).unwrap();
+        let renderer = RasterRenderer::new(PngOptions::default());
+        let fonts = Arc::new(renderer.fonts().unwrap().clone());
+        let png_layout = layout(&diagram, &settings, Some(fonts)).unwrap();
+        let bounds = |layout: &LayoutResult| {
+            layout
+                .elements
+                .iter()
+                .map(|e| (e.id.clone(), e.bounds))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bounds(&svg_layout), bounds(&png_layout));
+
+        // Ширина класса — по шрифту, а не по оценке `char_width`
+        let Diagram::Class(class) = &diagram else {
+            panic!("ожидалась диаграмма классов");
+        };
+        let estimated = ClassLayoutEngine::new().layout_diagram(class);
+        let width = |layout: &LayoutResult| {
+            layout
+                .elements
+                .iter()
+                .find(|e| e.id.contains("VeryLong"))
+                .map(|e| e.bounds.width)
+                .unwrap()
+        };
+        assert_ne!(width(&svg_layout), width(&estimated));
+
+        // Размер изображения совпадает
+        let options = RenderOptions::default();
+        let svg = render_pipeline(source, &options).unwrap();
+        let png = raster_pipeline(source, &options, &PngOptions::default(), OutputFormat::Png)
+            .unwrap();
+        let svg_size = |name: &str| {
+            let start = svg.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+            let end = start + svg[start..].find('"').unwrap();
+            svg[start..end].parse::<f64>().unwrap().ceil() as u32
+        };
+        let png_width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let png_height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        assert_eq!((svg_size("width"), svg_size("height")), (png_width, png_height));
+    }
+
+    #[test]
+    fn test_pipeline_handwritten_and_shadowing() {
+        let source = "@startuml\nskinparam handwritten true\nskinparam shadowing true\nclass A\nclass B\nA --> B\n@enduml";
+        let svg = render_pipeline(source, &RenderOptions::default()).unwrap();
+        assert!(svg.contains("url(#shadow)"));
+        assert!(!svg.contains("<rect"));
+
+        let plain =
+            render_pipeline("@startuml\nclass A\n@enduml", &RenderOptions::default()).unwrap();
+        assert!(!plain.contains("url(#shadow)"));
+        assert!(plain.contains("<rect"));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_raster_pipeline_handwritten() {
+        let render = |source: &str| {
+            raster_pipeline(source, &RenderOptions::default(), &PngOptions::default(), OutputFormat::Png)
+                .unwrap()
+        };
+        let sketched = render("@startuml\nskinparam handwritten true\nclass A\n@enduml");
+        let plain = render("@startuml\nclass A\n@enduml");
+        assert!(sketched.starts_with(b"\x89PNG"));
+        assert_ne!(sketched, plain);
+    }
 }
+
@@ -0,0 +1,1223 @@
+//! Текстовый вывод диаграмм последовательностей (`-ttxt` / `-tutxt`)
+//!
+//! Диаграмма рисуется моноширинными символами — для комментариев в коде,
+//! сообщений коммитов и терминала. [`TextCharset::Ascii`] использует только
+//! ASCII (`+`, `-`, `|`), [`TextCharset::Unicode`] — символы псевдографики
+//! (`┌`, `─`, `│`).
+//!
+//! Вывод строится по AST, а не по layout: позиции участников подбираются
+//! заново в символах так, чтобы подписи сообщений, заметки и заголовки
+//! фрагментов помещались между линиями жизни. Широкие символы (CJK)
+//! занимают две клетки.
+
+use plantuml_ast::common::{LineStyle, Note, NotePosition};
+use plantuml_ast::sequence::{
+    ActivationType, ArrowType, AutonumberCommand, Fragment, FragmentType, Message, ParticipantType,
+    SequenceDiagram, SequenceElement,
+};
+use plantuml_ast::Diagram;
+use plantuml_layout::text::is_wide_char;
+
+use crate::{Error, Result};
+
+/// Набор символов текстового вывода
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextCharset {
+    /// Только ASCII (`-ttxt`)
+    #[default]
+    Ascii,
+    /// Псевдографика Unicode (`-tutxt`)
+    Unicode,
+}
+
+/// Экспортирует диаграмму последовательностей в текст.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{export_text, parse_diagram, TextCharset};
+///
+/// let diagram = parse_diagram("@startuml\nAlice -> Bob: Hello\n@enduml").unwrap();
+/// let text = export_text(&diagram, TextCharset::Ascii).unwrap();
+/// assert!(text.contains("|Alice|"));
+/// assert!(text.contains("Hello"));
+/// ```
+pub fn export_text(diagram: &Diagram, charset: TextCharset) -> Result<String> {
+    match diagram {
+        Diagram::Sequence(d) => Ok(export_sequence(d, charset)),
+        other => Err(Error::UnsupportedDiagram(format!(
+            "текстовый вывод доступен только для диаграмм последовательностей, а не {:?}",
+            other.diagram_type()
+        ))),
+    }
+}
+
+/// Символы рамок и линий
+struct Glyphs {
+    horizontal: char,
+    vertical: char,
+    top_left: char,
+    top_right: char,
+    bottom_left: char,
+    bottom_right: char,
+    /// Линия жизни выходит из нижней границы блока участника
+    tee_down: char,
+    /// Линия жизни входит в верхнюю границу блока участника
+    tee_up: char,
+    /// Разделитель секций у левой и правой границ фрагмента
+    tee_right: char,
+    tee_left: char,
+    /// Пунктир: символы чередуются
+    dashed: [char; 2],
+    divider: char,
+    delay: char,
+}
+
+const ASCII: Glyphs = Glyphs {
+    horizontal: '-',
+    vertical: '|',
+    top_left: '+',
+    top_right: '+',
+    bottom_left: '+',
+    bottom_right: '+',
+    tee_down: '+',
+    tee_up: '+',
+    tee_right: '+',
+    tee_left: '+',
+    dashed: ['-', ' '],
+    divider: '=',
+    delay: ':',
+};
+
+const UNICODE: Glyphs = Glyphs {
+    horizontal: '─',
+    vertical: '│',
+    top_left: '┌',
+    top_right: '┐',
+    bottom_left: '└',
+    bottom_right: '┘',
+    tee_down: '┬',
+    tee_up: '┴',
+    tee_right: '├',
+    tee_left: '┤',
+    dashed: ['╌', '╌'],
+    divider: '═',
+    delay: '┊',
+};
+
+/// Вторая клетка широкого символа (не выводится)
+const WIDE_PAD: char = '\0';
+
+/// Участник: колонка с линией жизни
+struct Column {
+    key: String,
+    lines: Vec<String>,
+    actor: bool,
+    /// Ширина блока в заголовке
+    width: usize,
+    /// Координата линии жизни
+    x: usize,
+    destroyed: bool,
+}
+
+impl Column {
+    /// Клеток слева от линии жизни
+    fn half_left(&self) -> usize {
+        (self.width - 1) / 2
+    }
+
+    /// Клеток справа от линии жизни
+    fn half_right(&self) -> usize {
+        self.width - 1 - self.half_left()
+    }
+
+    /// Высота блока в заголовке
+    fn height(&self) -> usize {
+        if self.actor {
+            self.lines.len() + 3
+        } else {
+            self.lines.len() + 2
+        }
+    }
+}
+
+/// Фрагмент (`alt`, `loop`, ...)
+struct Frame {
+    header: String,
+    /// Крайние колонки содержимого (None — все участники)
+    range: Option<(usize, usize)>,
+    /// Отступы рамки от крайних линий жизни слева и справа
+    pad: (usize, usize),
+    /// Ширина самой длинной подписи (заголовок, условия секций)
+    label_width: usize,
+}
+
+/// Элемент вывода с разрешёнными колонками
+enum Item {
+    Message {
+        from: usize,
+        to: usize,
+        lines: Vec<String>,
+        dashed: bool,
+        arrow_type: ArrowType,
+    },
+    Note {
+        lo: usize,
+        hi: usize,
+        position: NotePosition,
+        lines: Vec<String>,
+    },
+    Reference {
+        lo: usize,
+        hi: usize,
+        lines: Vec<String>,
+    },
+    Divider(String),
+    Delay(Option<String>),
+    Space(usize),
+    Destroy(usize),
+    FrameStart(usize),
+    FrameElse(usize, String),
+    FrameEnd(usize),
+}
+
+/// Требование к расстоянию между линиями жизни
+enum Need {
+    /// x[b] - x[a] >= d
+    Span(usize, usize, usize),
+    /// d клеток слева от линии жизни
+    Left(usize, usize),
+    /// d клеток справа от линии жизни
+    Right(usize, usize),
+}
+
+fn export_sequence(diagram: &SequenceDiagram, charset: TextCharset) -> String {
+    let mut builder = Builder::default();
+    for participant in &diagram.participants {
+        let key = participant
+            .id
+            .alias
+            .clone()
+            .unwrap_or_else(|| participant.id.name.clone());
+        let index = builder.column(&key);
+        let column = &mut builder.columns[index];
+        column.lines = text_lines(&participant.id.name);
+        column.actor = participant.participant_type == ParticipantType::Actor;
+    }
+    builder.elements(&diagram.elements);
+
+    let Builder {
+        mut columns,
+        items,
+        mut frames,
+        ..
+    } = builder;
+    if columns.is_empty() {
+        return String::new();
+    }
+    for column in &mut columns {
+        let text_width = column.lines.iter().map(|l| width(l)).max().unwrap_or(0);
+        column.width = if column.actor {
+            text_width.max(3)
+        } else {
+            text_width + 2
+        };
+    }
+
+    resolve_frames(&items, &mut frames, columns.len());
+    let needs = collect_needs(&items, &frames);
+    place_columns(&mut columns, &needs);
+
+    let glyphs = match charset {
+        TextCharset::Ascii => &ASCII,
+        TextCharset::Unicode => &UNICODE,
+    };
+    let mut drawer = Drawer {
+        canvas: Canvas::default(),
+        glyphs,
+        columns,
+        frames,
+        open: Vec::new(),
+        centered: Vec::new(),
+    };
+    drawer.draw(&items);
+
+    let mut title_lines = diagram
+        .metadata
+        .title
+        .as_deref()
+        .map(text_lines)
+        .unwrap_or_default();
+    if !title_lines.is_empty() {
+        title_lines.push(String::new());
+    }
+    drawer.finish(&title_lines)
+}
+
+/// Первый проход: участники, элементы и фрагменты
+#[derive(Default)]
+struct Builder {
+    columns: Vec<Column>,
+    items: Vec<Item>,
+    frames: Vec<Frame>,
+    /// Номер сообщения autonumber
+    autonumber: Option<Autonumber>,
+    /// Счётчик после `autonumber stop`
+    paused: Option<Autonumber>,
+    /// Вызовы для `return`: (вызывающий, вызванный)
+    call_stack: Vec<(usize, usize)>,
+    last_message: Option<(usize, usize)>,
+}
+
+struct Autonumber {
+    current: u32,
+    step: u32,
+    format: Option<String>,
+}
+
+impl Autonumber {
+    /// Следующий номер (формат как в SVG: `0` и `00` заменяются числом)
+    fn next(&mut self) -> String {
+        let number = self.current;
+        self.current += self.step;
+        match &self.format {
+            Some(format) if format.contains("00") => {
+                strip_tags(&format.replace("00", &format!("{:02}", number)))
+            }
+            Some(format) if format.contains('0') => {
+                strip_tags(&format.replace('0', &number.to_string()))
+            }
+            Some(format) => format!("{}{}", number, strip_tags(format)),
+            None => number.to_string(),
+        }
+    }
+}
+
+impl Builder {
+    /// Индекс колонки участника; неизвестный участник добавляется справа
+    fn column(&mut self, key: &str) -> usize {
+        if let Some(index) = self.columns.iter().position(|c| c.key == key) {
+            return index;
+        }
+        self.columns.push(Column {
+            key: key.to_string(),
+            lines: text_lines(key),
+            actor: false,
+            width: 0,
+            x: 0,
+            destroyed: false,
+        });
+        self.columns.len() - 1
+    }
+
+    fn elements(&mut self, elements: &[SequenceElement]) {
+        for element in elements {
+            self.element(element);
+        }
+    }
+
+    fn element(&mut self, element: &SequenceElement) {
+        match element {
+            SequenceElement::Message(message) => self.message(message),
+            SequenceElement::Fragment(fragment) => self.fragment(fragment),
+            SequenceElement::Note(note) => self.note(note),
+            SequenceElement::Activation(activation) => {
+                let index = self.column(&activation.participant);
+                match activation.activation_type {
+                    ActivationType::Activate => {
+                        if let Some((from, to)) = self.last_message {
+                            if to == index {
+                                self.call_stack.push((from, to));
+                            }
+                        }
+                    }
+                    ActivationType::Deactivate => {}
+                    ActivationType::Destroy => self.items.push(Item::Destroy(index)),
+                }
+            }
+            SequenceElement::Divider(divider) => {
+                self.items.push(Item::Divider(strip_tags(&divider.text)))
+            }
+            SequenceElement::Delay(delay) => self
+                .items
+                .push(Item::Delay(delay.text.as_deref().map(strip_tags))),
+            SequenceElement::Space(height) => {
+                self.items.push(Item::Space((*height as usize / 10).max(1)))
+            }
+            SequenceElement::Reference(reference) => {
+                let indices: Vec<usize> = reference
+                    .participants
+                    .iter()
+                    .map(|p| self.column(p))
+                    .collect();
+                let (lo, hi) = bounds(&indices).unwrap_or((0, 0));
+                let mut lines = vec!["ref".to_string()];
+                lines.extend(text_lines(&reference.text));
+                self.items.push(Item::Reference { lo, hi, lines });
+            }
+            SequenceElement::Autonumber(command) => self.autonumber_command(command),
+            SequenceElement::Return(ret) => {
+                if let Some((caller, callee)) = self.call_stack.pop() {
+                    self.items.push(Item::Message {
+                        from: callee,
+                        to: caller,
+                        lines: ret.label.as_deref().map(text_lines).unwrap_or_default(),
+                        dashed: true,
+                        arrow_type: ArrowType::Normal,
+                    });
+                }
+            }
+        }
+    }
+
+    fn message(&mut self, message: &Message) {
+        let from = self.column(&message.from);
+        let to = self.column(&message.to);
+        let mut lines = text_lines(&message.label);
+        if let Some(autonumber) = &mut self.autonumber {
+            let number = autonumber.next();
+            match lines.first_mut() {
+                Some(first) => *first = format!("{} {}", number, first),
+                None => lines.push(number),
+            }
+        }
+        self.items.push(Item::Message {
+            from,
+            to,
+            lines,
+            dashed: matches!(message.line_style, LineStyle::Dashed | LineStyle::Dotted),
+            arrow_type: message.arrow_type,
+        });
+
+        self.last_message = Some((from, to));
+        if message.activate {
+            self.call_stack.push((from, to));
+        }
+        if message.destroy {
+            self.items.push(Item::Destroy(to));
+        }
+    }
+
+    fn note(&mut self, note: &Note) {
+        let indices: Vec<usize> = note.anchors.iter().map(|a| self.column(a)).collect();
+        // Заметка без привязки — над последним сообщением
+        let (lo, hi) = bounds(&indices)
+            .or_else(|| self.last_message.map(|(a, b)| (a.min(b), a.max(b))))
+            .unwrap_or((0, 0));
+        self.items.push(Item::Note {
+            lo,
+            hi,
+            position: note.position,
+            lines: text_lines(&note.text),
+        });
+    }
+
+    fn fragment(&mut self, fragment: &Fragment) {
+        let kind = format!("{:?}", fragment.fragment_type).to_lowercase();
+        let header = match (fragment.fragment_type, fragment.condition.as_deref()) {
+            (FragmentType::Group, Some(condition)) => strip_tags(condition),
+            (_, Some(condition)) => format!("{} [{}]", kind, strip_tags(condition)),
+            (_, None) => kind,
+        };
+        let frame = self.frames.len();
+        let mut label_width = width(&header);
+        self.frames.push(Frame {
+            header,
+            range: None,
+            pad: (2, 2),
+            label_width: 0,
+        });
+
+        self.items.push(Item::FrameStart(frame));
+        for (index, section) in fragment.sections.iter().enumerate() {
+            // Условие первой секции уже в заголовке
+            if index > 0 {
+                let label = section
+                    .condition
+                    .as_deref()
+                    .map(|c| format!("[{}]", strip_tags(c)))
+                    .unwrap_or_default();
+                label_width = label_width.max(width(&label));
+                self.items.push(Item::FrameElse(frame, label));
+            }
+            self.elements(&section.elements);
+        }
+        self.frames[frame].label_width = label_width;
+        self.items.push(Item::FrameEnd(frame));
+    }
+
+    fn autonumber_command(&mut self, command: &AutonumberCommand) {
+        match command {
+            AutonumberCommand::Start(params) => {
+                self.autonumber = Some(Autonumber {
+                    current: params.start.unwrap_or(1),
+                    step: params.step.unwrap_or(1),
+                    format: params.format.clone(),
+                });
+            }
+            AutonumberCommand::Stop => {
+                // Остановленный счётчик сохраняет значение для resume
+                if let Some(autonumber) = self.autonumber.take() {
+                    self.paused = Some(autonumber);
+                }
+            }
+            AutonumberCommand::Resume(params) => {
+                let mut autonumber = self.paused.take().unwrap_or(Autonumber {
+                    current: 1,
+                    step: 1,
+                    format: None,
+                });
+                if let Some(params) = params {
+                    if let Some(start) = params.start {
+                        autonumber.current = start;
+                    }
+                    if let Some(step) = params.step {
+                        autonumber.step = step;
+                    }
+                    if params.format.is_some() {
+                        autonumber.format = params.format.clone();
+                    }
+                }
+                self.autonumber = Some(autonumber);
+            }
+            AutonumberCommand::Inc(_) => {}
+        }
+    }
+}
+
+/// Охват элемента: крайние колонки и число клеток левее первой и правее
+/// последней линии жизни
+type Extent = (usize, usize, usize, usize);
+
+fn item_extent(item: &Item) -> Option<Extent> {
+    match item {
+        Item::Message {
+            from, to, lines, ..
+        } => {
+            if from == to {
+                // Петля на 5 клеток, подпись через пробел справа от неё
+                let label = max_width(lines);
+                Some((*from, *to, 0, if label > 0 { label + 6 } else { 5 }))
+            } else {
+                Some((*from.min(to), *from.max(to), 0, 0))
+            }
+        }
+        Item::Note {
+            lo,
+            hi,
+            position,
+            lines,
+        } => {
+            let box_width = max_width(lines) + 4;
+            Some(match position {
+                NotePosition::Right => (*lo, *hi, 0, box_width + 1),
+                NotePosition::Left => (*lo, *hi, box_width + 1, 0),
+                _ => box_extent(*lo, *hi, box_width),
+            })
+        }
+        Item::Reference { lo, hi, lines } => Some(box_extent(*lo, *hi, max_width(lines) + 4)),
+        Item::Destroy(index) => Some((*index, *index, 0, 0)),
+        _ => None,
+    }
+}
+
+/// Охват рамки над участниками `lo..=hi` (заметка `over`, `ref`)
+fn box_extent(lo: usize, hi: usize, box_width: usize) -> Extent {
+    if lo == hi {
+        let left = (box_width - 1) / 2;
+        (lo, hi, left, box_width - 1 - left)
+    } else {
+        (lo, hi, 2, 2)
+    }
+}
+
+/// Объединение охватов: выступ берётся у крайней колонки
+fn merge_extent(acc: Option<Extent>, extent: Extent) -> Extent {
+    let Some((lo, hi, left, right)) = acc else {
+        return extent;
+    };
+    let (new_lo, new_hi, new_left, new_right) = extent;
+    let left = match new_lo.cmp(&lo) {
+        std::cmp::Ordering::Less => new_left,
+        std::cmp::Ordering::Equal => left.max(new_left),
+        std::cmp::Ordering::Greater => left,
+    };
+    let right = match new_hi.cmp(&hi) {
+        std::cmp::Ordering::Greater => new_right,
+        std::cmp::Ordering::Equal => right.max(new_right),
+        std::cmp::Ordering::Less => right,
+    };
+    (lo.min(new_lo), hi.max(new_hi), left, right)
+}
+
+/// Диапазоны колонок фрагментов и отступы рамок: рамка охватывает
+/// содержимое (петли, заметки, вложенные фрагменты) с зазором в 2 клетки
+fn resolve_frames(items: &[Item], frames: &mut [Frame], column_count: usize) {
+    // Открытые фрагменты и охват их содержимого
+    let mut open: Vec<(usize, Option<Extent>)> = Vec::new();
+    for item in items {
+        match *item {
+            Item::FrameStart(frame) => open.push((frame, None)),
+            Item::FrameEnd(frame) => {
+                let Some((_, extent)) = open.pop() else {
+                    continue;
+                };
+                let (lo, hi, left, right) = extent.unwrap_or((0, column_count - 1, 0, 0));
+                frames[frame].range = Some((lo, hi));
+                frames[frame].pad = (left + 2, right + 2);
+                // Вложенный фрагмент расширяет внешний
+                if let Some((_, parent)) = open.last_mut() {
+                    *parent = Some(merge_extent(*parent, (lo, hi, left + 2, right + 2)));
+                }
+            }
+            _ => {
+                if let Some(extent) = item_extent(item) {
+                    for (_, acc) in &mut open {
+                        *acc = Some(merge_extent(*acc, extent));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Требования к расстояниям между линиями жизни
+fn collect_needs(items: &[Item], frames: &[Frame]) -> Vec<Need> {
+    let mut needs = Vec::new();
+    for item in items {
+        if let Some((lo, hi, left, right)) = item_extent(item) {
+            if left > 0 {
+                needs.push(Need::Left(lo, left));
+            }
+            if right > 0 {
+                needs.push(Need::Right(hi, right));
+            }
+        }
+        match item {
+            Item::Message {
+                from, to, lines, ..
+            } if from != to => {
+                needs.push(Need::Span(
+                    *from.min(to),
+                    *from.max(to),
+                    max_width(lines) + 4,
+                ));
+            }
+            Item::Note { lo, hi, lines, .. } | Item::Reference { lo, hi, lines } => {
+                needs.push(Need::Span(
+                    *lo,
+                    *hi,
+                    (max_width(lines) + 4).saturating_sub(5),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    for frame in frames {
+        let Some((lo, hi)) = frame.range else {
+            continue;
+        };
+        let (left, right) = frame.pad;
+        needs.push(Need::Left(lo, left));
+        needs.push(Need::Right(hi, right));
+        // Подпись: угол, линия, пробел, текст, пробел, ..., угол
+        let label = (frame.label_width + 4).saturating_sub(left + right);
+        if lo < hi {
+            needs.push(Need::Span(lo, hi, label));
+        } else {
+            needs.push(Need::Right(hi, right + label));
+        }
+    }
+    needs
+}
+
+/// Расставляет линии жизни: минимальные промежутки между блоками участников,
+/// затем промежутки растягиваются под подписи и заметки
+fn place_columns(columns: &mut [Column], needs: &[Need]) {
+    let count = columns.len();
+    let mut gaps: Vec<usize> = columns
+        .windows(2)
+        .map(|pair| pair[0].half_right() + pair[1].half_left() + 3)
+        .collect();
+    let mut left_margin = columns[0].half_left();
+
+    // Содержимое не должно касаться соседней линии жизни: +1 клетка зазора
+    let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+    for need in needs {
+        match *need {
+            Need::Span(a, b, d) if a < b => spans.push((a, b, d)),
+            Need::Span(..) => {}
+            Need::Left(0, d) => left_margin = left_margin.max(d),
+            Need::Left(a, d) => spans.push((a - 1, a, d + 2)),
+            Need::Right(a, d) if a + 1 < count => spans.push((a, a + 1, d + 2)),
+            Need::Right(..) => {}
+        }
+    }
+
+    // Сначала короткие пролёты, потом охватывающие их
+    spans.sort_by_key(|&(a, b, _)| b - a);
+    for (a, b, d) in spans {
+        let current: usize = gaps[a..b].iter().sum();
+        if current < d {
+            let deficit = d - current;
+            let n = b - a;
+            for (i, gap) in gaps[a..b].iter_mut().enumerate() {
+                *gap += deficit / n + usize::from(i < deficit % n);
+            }
+        }
+    }
+
+    let mut x = left_margin;
+    for (index, column) in columns.iter_mut().enumerate() {
+        column.x = x;
+        if index < gaps.len() {
+            x += gaps[index];
+        }
+    }
+}
+
+/// Второй проход: рисование на сетке
+struct Drawer<'a> {
+    canvas: Canvas,
+    glyphs: &'a Glyphs,
+    columns: Vec<Column>,
+    frames: Vec<Frame>,
+    /// Открытые фрагменты (их боковые границы продолжаются на новых строках)
+    open: Vec<usize>,
+    /// Строки, заполняемые по всей ширине в конце: (строка, текст, символ заполнения)
+    centered: Vec<(usize, String, Option<char>)>,
+}
+
+impl Drawer<'_> {
+    fn draw(&mut self, items: &[Item]) {
+        self.participants(true);
+        self.row();
+
+        for item in items {
+            match item {
+                Item::Message {
+                    from,
+                    to,
+                    lines,
+                    dashed,
+                    arrow_type,
+                } => {
+                    if from == to {
+                        self.self_message(*from, lines, *dashed);
+                    } else {
+                        self.message(*from, *to, lines, *dashed, *arrow_type);
+                    }
+                    self.row();
+                }
+                Item::Note {
+                    lo,
+                    hi,
+                    position,
+                    lines,
+                } => {
+                    let box_width = max_width(lines) + 4;
+                    let (left, right) = (self.columns[*lo].x, self.columns[*hi].x);
+                    let (x, box_width) = match position {
+                        NotePosition::Right => (right + 2, box_width),
+                        NotePosition::Left => (left.saturating_sub(box_width + 1), box_width),
+                        _ if lo == hi => (left.saturating_sub((box_width - 1) / 2), box_width),
+                        _ => (left - 2, box_width.max(right - left + 5)),
+                    };
+                    self.text_box(x, box_width, lines);
+                    self.row();
+                }
+                Item::Reference { lo, hi, lines } => {
+                    let (left, right) = (self.columns[*lo].x, self.columns[*hi].x);
+                    let box_width = max_width(lines) + 4;
+                    if lo == hi {
+                        self.text_box(left.saturating_sub((box_width - 1) / 2), box_width, lines);
+                    } else {
+                        self.text_box(left - 2, box_width.max(right - left + 5), lines);
+                    }
+                    self.row();
+                }
+                Item::Divider(text) => {
+                    let y = self.row();
+                    self.centered
+                        .push((y, text.clone(), Some(self.glyphs.divider)));
+                    self.row();
+                }
+                Item::Delay(text) => {
+                    self.delay_row();
+                    if let Some(text) = text {
+                        let y = self.delay_row();
+                        self.centered.push((y, text.clone(), None));
+                    }
+                    self.delay_row();
+                    self.row();
+                }
+                Item::Space(rows) => {
+                    for _ in 0..*rows {
+                        self.row();
+                    }
+                }
+                Item::Destroy(index) => {
+                    let y = self.row();
+                    self.canvas.put(self.columns[*index].x, y, 'X');
+                    self.columns[*index].destroyed = true;
+                    self.row();
+                }
+                Item::FrameStart(frame) => {
+                    let y = self.row();
+                    let (left, right) = self.frame_edges(*frame);
+                    let header = self.frames[*frame].header.clone();
+                    self.border(y, left, right, self.glyphs.top_left, self.glyphs.top_right);
+                    self.canvas.text(left + 2, y, &format!(" {} ", header));
+                    self.open.push(*frame);
+                }
+                Item::FrameElse(frame, label) => {
+                    let y = self.row();
+                    let (left, right) = self.frame_edges(*frame);
+                    self.canvas.put(left, y, self.glyphs.tee_right);
+                    for (i, x) in (left + 1..right).enumerate() {
+                        self.canvas.put(x, y, self.glyphs.dashed[i % 2]);
+                    }
+                    self.canvas.put(right, y, self.glyphs.tee_left);
+                    if !label.is_empty() {
+                        self.canvas.text(left + 2, y, &format!(" {} ", label));
+                    }
+                }
+                Item::FrameEnd(frame) => {
+                    self.open.retain(|open| open != frame);
+                    let y = self.row();
+                    let (left, right) = self.frame_edges(*frame);
+                    self.border(
+                        y,
+                        left,
+                        right,
+                        self.glyphs.bottom_left,
+                        self.glyphs.bottom_right,
+                    );
+                    self.row();
+                }
+            }
+        }
+
+        self.participants(false);
+    }
+
+    /// Новая строка с линиями жизни и границами открытых фрагментов
+    fn row(&mut self) -> usize {
+        let y = self.canvas.push_row();
+        for column in self.columns.iter().filter(|c| !c.destroyed) {
+            self.canvas.put(column.x, y, self.glyphs.vertical);
+        }
+        for index in 0..self.open.len() {
+            let (left, right) = self.frame_edges(self.open[index]);
+            self.canvas.put(left, y, self.glyphs.vertical);
+            self.canvas.put(right, y, self.glyphs.vertical);
+        }
+        y
+    }
+
+    /// Строка задержки: линии жизни пунктиром
+    fn delay_row(&mut self) -> usize {
+        let y = self.row();
+        for column in self.columns.iter().filter(|c| !c.destroyed) {
+            self.canvas.put(column.x, y, self.glyphs.delay);
+        }
+        y
+    }
+
+    /// Границы рамки фрагмента
+    fn frame_edges(&self, frame: usize) -> (usize, usize) {
+        let frame = &self.frames[frame];
+        let (lo, hi) = frame.range.unwrap_or((0, self.columns.len() - 1));
+        let left = self.columns[lo].x.saturating_sub(frame.pad.0);
+        let right = (self.columns[hi].x + frame.pad.1).max(left + frame.label_width + 4);
+        (left, right)
+    }
+
+    /// Горизонтальная граница с углами
+    fn border(&mut self, y: usize, left: usize, right: usize, first: char, last: char) {
+        self.canvas.put(left, y, first);
+        for x in left + 1..right {
+            self.canvas.put(x, y, self.glyphs.horizontal);
+        }
+        self.canvas.put(right, y, last);
+    }
+
+    /// Рамка с текстом (заметка, ref)
+    fn text_box(&mut self, left: usize, box_width: usize, lines: &[String]) {
+        let right = left + box_width - 1;
+        let y = self.row();
+        self.border(y, left, right, self.glyphs.top_left, self.glyphs.top_right);
+        for line in lines {
+            let y = self.row();
+            for x in left..=right {
+                self.canvas.put(x, y, ' ');
+            }
+            self.canvas.put(left, y, self.glyphs.vertical);
+            self.canvas.text(left + 2, y, line);
+            self.canvas.put(right, y, self.glyphs.vertical);
+        }
+        let y = self.row();
+        self.border(
+            y,
+            left,
+            right,
+            self.glyphs.bottom_left,
+            self.glyphs.bottom_right,
+        );
+    }
+
+    fn message(
+        &mut self,
+        from: usize,
+        to: usize,
+        lines: &[String],
+        dashed: bool,
+        arrow_type: ArrowType,
+    ) {
+        let (a, b) = (self.columns[from].x, self.columns[to].x);
+        let (lo, hi) = (a.min(b), a.max(b));
+        for line in lines {
+            let y = self.row();
+            let start = lo + 1 + (hi - lo - 1).saturating_sub(width(line)) / 2;
+            self.canvas.text(start, y, line);
+        }
+
+        let y = self.row();
+        self.line(y, lo + 1, hi, dashed, b < a);
+        let x = if b > a { hi - 1 } else { lo + 1 };
+        let head = match arrow_type {
+            ArrowType::Cross => 'x',
+            ArrowType::Circle => 'o',
+            _ if b > a => '>',
+            _ => '<',
+        };
+        self.canvas.put(x, y, head);
+    }
+
+    /// Сообщение самому себе: петля справа от линии жизни
+    fn self_message(&mut self, index: usize, lines: &[String], dashed: bool) {
+        let x = self.columns[index].x;
+        let y = self.row();
+        self.line(y, x + 1, x + 5, dashed, false);
+        self.canvas.put(x + 5, y, self.glyphs.top_right);
+        for k in 0..lines.len().max(1) {
+            let y = self.row();
+            self.canvas.put(x + 5, y, self.glyphs.vertical);
+            if let Some(line) = lines.get(k) {
+                self.canvas.text(x + 7, y, line);
+            }
+        }
+        let y = self.row();
+        self.line(y, x + 1, x + 5, dashed, true);
+        self.canvas.put(x + 1, y, '<');
+        self.canvas.put(x + 5, y, self.glyphs.bottom_right);
+    }
+
+    /// Линия сообщения в клетках `from..to`; пунктир начинается от хвоста
+    /// (`reverse` — хвост справа), чтобы линия не отрывалась от линии жизни
+    fn line(&mut self, y: usize, from: usize, to: usize, dashed: bool, reverse: bool) {
+        for x in from..to {
+            let i = if reverse { to - 1 - x } else { x - from };
+            let c = if dashed {
+                self.glyphs.dashed[i % 2]
+            } else {
+                self.glyphs.horizontal
+            };
+            self.canvas.put(x, y, c);
+        }
+    }
+
+    /// Блоки участников: в заголовке выровнены по низу, в подвале — по верху
+    fn participants(&mut self, head: bool) {
+        let columns: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| head || !self.columns[i].destroyed)
+            .collect();
+        let Some(height) = columns.iter().map(|&i| self.columns[i].height()).max() else {
+            return;
+        };
+        let top = self.canvas.rows.len();
+        for _ in 0..height {
+            self.canvas.push_row();
+        }
+
+        for index in columns {
+            let column = &self.columns[index];
+            let (x, lines) = (column.x, column.lines.clone());
+            let mut y = if head {
+                top + height - column.height()
+            } else {
+                top
+            };
+            if column.actor {
+                self.canvas.put(x, y, 'O');
+                self.canvas.text(x - 1, y + 1, "/|\\");
+                self.canvas.text(x - 1, y + 2, "/ \\");
+                for (k, line) in lines.iter().enumerate() {
+                    let start = x - (width(line).max(1) - 1) / 2;
+                    self.canvas.text(start, y + 3 + k, line);
+                }
+                continue;
+            }
+
+            let left = x - column.half_left();
+            let right = left + column.width - 1;
+            let inner = column.width - 2;
+            self.border(y, left, right, self.glyphs.top_left, self.glyphs.top_right);
+            if !head {
+                self.canvas.put(x, y, self.glyphs.tee_up);
+            }
+            for line in &lines {
+                y += 1;
+                self.canvas.put(left, y, self.glyphs.vertical);
+                self.canvas
+                    .text(left + 1 + (inner - width(line)) / 2, y, line);
+                self.canvas.put(right, y, self.glyphs.vertical);
+            }
+            y += 1;
+            self.border(
+                y,
+                left,
+                right,
+                self.glyphs.bottom_left,
+                self.glyphs.bottom_right,
+            );
+            if head {
+                self.canvas.put(x, y, self.glyphs.tee_down);
+            }
+        }
+    }
+
+    /// Разделители и подписи задержек по всей ширине, заголовок сверху
+    fn finish(mut self, title: &[String]) -> String {
+        let total = self.canvas.width();
+        for (y, text, fill) in std::mem::take(&mut self.centered) {
+            let label = format!(" {} ", text);
+            let start = total.saturating_sub(width(&label)) / 2;
+            if let Some(fill) = fill {
+                for x in 0..total {
+                    self.canvas.put(x, y, fill);
+                }
+            }
+            self.canvas.text(start, y, &label);
+        }
+
+        let mut out = String::new();
+        for line in title {
+            if !line.is_empty() {
+                let start = total.saturating_sub(width(line)) / 2;
+                out.push_str(&" ".repeat(start));
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.canvas.render());
+        out
+    }
+}
+
+/// Сетка символов
+#[derive(Default)]
+struct Canvas {
+    rows: Vec<Vec<char>>,
+}
+
+impl Canvas {
+    fn push_row(&mut self) -> usize {
+        self.rows.push(Vec::new());
+        self.rows.len() - 1
+    }
+
+    fn put(&mut self, x: usize, y: usize, c: char) {
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, ' ');
+        }
+        row[x] = c;
+    }
+
+    /// Текст; широкий символ занимает две клетки
+    fn text(&mut self, x: usize, y: usize, text: &str) {
+        let mut x = x;
+        for c in text.chars() {
+            self.put(x, y, c);
+            x += 1;
+            if is_wide_char(c) {
+                self.put(x, y, WIDE_PAD);
+                x += 1;
+            }
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for row in &self.rows {
+            let line: String = row.iter().filter(|&&c| c != WIDE_PAD).collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Строки подписи: `\n` и перевод строки, без HTML-тегов
+fn text_lines(text: &str) -> Vec<String> {
+    let text = text.replace("\\n", "\n");
+    text.lines()
+        .map(|line| strip_tags(line).trim().to_string())
+        .collect()
+}
+
+/// Удаляет HTML-теги (`<b>`, `</color>`, ...)
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let is_tag = after
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '/');
+        match after.find('>') {
+            Some(end) if is_tag => {
+                out.push_str(&rest[..start]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Ширина текста в клетках
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if is_wide_char(c) { 2 } else { 1 })
+        .sum()
+}
+
+fn max_width(lines: &[String]) -> usize {
+    lines.iter().map(|line| width(line)).max().unwrap_or(0)
+}
+
+fn bounds(indices: &[usize]) -> Option<(usize, usize)> {
+    Some((*indices.iter().min()?, *indices.iter().max()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_diagram;
+
+    fn export(source: &str, charset: TextCharset) -> String {
+        export_text(&parse_diagram(source).unwrap(), charset).unwrap()
+    }
+
+    #[test]
+    fn test_export_message() {
+        let text = export(
+            "@startuml\nAlice -> Bob: Hello\n@enduml",
+            TextCharset::Ascii,
+        );
+        let expected = "\
++-----+   +---+
+|Alice|   |Bob|
++--+--+   +-+-+
+   |        |
+   | Hello  |
+   |------->|
+   |        |
++--+--+   +-+-+
+|Alice|   |Bob|
++-----+   +---+
+";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn test_export_unicode_and_dashed_return() {
+        let text = export(
+            "@startuml\nAlice -> Bob: ping\nBob --> Alice: pong\n@enduml",
+            TextCharset::Unicode,
+        );
+        assert!(text.contains("│Alice│"));
+        assert!(text.contains("└──┬──┘"));
+        assert!(text.contains("│──────>│"));
+        // Пунктир примыкает к линии жизни со стороны хвоста
+        assert!(text.contains("│<╌╌╌╌╌╌│"));
+    }
+
+    #[test]
+    fn test_export_self_message_and_notes() {
+        let text = export(
+            "@startuml\nA -> A: retry\nnote right of A: edge\nnote left of A: left\n@enduml",
+            TextCharset::Ascii,
+        );
+        let lines: Vec<&str> = text.lines().collect();
+        let x = lines[0].find('+').unwrap() + 1;
+        assert!(lines.iter().any(|l| l[x..].starts_with("|----+")));
+        assert!(lines.iter().any(|l| l[x..].starts_with("|    | retry")));
+        assert!(lines.iter().any(|l| l[x..].starts_with("|<---+")));
+        assert!(lines.iter().any(|l| l[x..].starts_with("| | edge |")));
+        assert!(lines.iter().any(|l| l.starts_with("| left | |")));
+    }
+
+    #[test]
+    fn test_export_fragment_divider_and_delay() {
+        let text = export(
+            "@startuml\nA -> B: go\nalt ok\n  B -> A: yes\nelse fail\n  B -> A: no\nend\n== Phase ==\n...later...\n@enduml",
+            TextCharset::Ascii,
+        );
+        assert!(text.contains("+- alt [ok] "));
+        assert!(text.contains("+- [fail] "));
+        assert!(text.contains(" Phase "));
+        assert!(text.lines().any(|l| l.starts_with("===")));
+        assert!(text.contains("later"));
+        assert!(text.lines().any(|l| l.trim_start().starts_with(':')));
+    }
+
+    #[test]
+    fn test_export_autonumber_title_and_wide_chars() {
+        let text = export(
+            "@startuml\ntitle Заказ\nautonumber\nA -> B: 日本\nA -> B: next\n@enduml",
+            TextCharset::Ascii,
+        );
+        assert!(text.starts_with(' '));
+        assert!(text.lines().next().unwrap().trim() == "Заказ");
+        assert!(text.contains("1 日本"));
+        assert!(text.contains("2 next"));
+        // Широкие символы не сдвигают линии жизни
+        let column = |line: &str| {
+            line.chars()
+                .map(|c| width(&c.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let label = text.lines().find(|l| l.contains("日本")).unwrap();
+        let arrow = text.lines().find(|l| l.contains('>')).unwrap();
+        assert_eq!(
+            column(label).iter().sum::<usize>(),
+            column(arrow).iter().sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_export_unsupported_diagram() {
+        let diagram = parse_diagram("@startuml\nclass A\n@enduml").unwrap();
+        let result = export_text(&diagram, TextCharset::Ascii);
+        assert!(matches!(result, Err(Error::UnsupportedDiagram(_))));
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        assert_eq!(strip_tags("<b>bold</b> a < b"), "bold a < b");
+        assert_eq!(text_lines("one\\ntwo"), vec!["one", "two"]);
+    }
+}
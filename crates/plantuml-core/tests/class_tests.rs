@@ -2,6 +2,9 @@
 //!
 //! Используем insta для snapshot тестирования SVG вывода.
 
+mod common;
+
+use common::assert_svg_snapshot;
 use plantuml_core::{render, RenderOptions};
 
 /// Тест простой class diagram
@@ -24,7 +27,7 @@ class User {
     assert!(svg.contains("User"));
 
     // Snapshot тест
-    insta::assert_snapshot!("simple_class", svg);
+    assert_svg_snapshot!("simple_class", svg);
 }
 
 /// Тест наследования
@@ -53,7 +56,7 @@ Cat --|> Animal
     assert!(svg.contains("Dog"));
     assert!(svg.contains("Cat"));
 
-    insta::assert_snapshot!("inheritance", svg);
+    assert_svg_snapshot!("inheritance", svg);
 }
 
 /// Тест интерфейса и реализации
@@ -77,7 +80,7 @@ User ..|> Serializable
     assert!(svg.contains("Serializable"));
     assert!(svg.contains("User"));
 
-    insta::assert_snapshot!("interface", svg);
+    assert_svg_snapshot!("interface", svg);
 }
 
 /// Тест композиции и агрегации
@@ -107,7 +110,7 @@ Car o-- Wheel : has
     assert!(svg.contains("Engine"));
     assert!(svg.contains("Wheel"));
 
-    insta::assert_snapshot!("composition_aggregation", svg);
+    assert_svg_snapshot!("composition_aggregation", svg);
 }
 
 /// Тест сложной иерархии
@@ -144,5 +147,5 @@ ProductRepository --|> AbstractRepository
     assert!(svg.contains("UserRepository"));
     assert!(svg.contains("ProductRepository"));
 
-    insta::assert_snapshot!("complex_hierarchy", svg);
+    assert_svg_snapshot!("complex_hierarchy", svg);
 }
@@ -0,0 +1,18 @@
+//! Общие помощники визуальных тестов
+
+/// Snapshot SVG. Со шрифтами растровых рендереров (features `png`, `pdf`)
+/// layout измеряет подписи их метриками, поэтому снимки хранятся отдельно
+/// с суффиксом `fonts`.
+macro_rules! assert_svg_snapshot {
+    ($name:expr, $svg:expr) => {
+        if cfg!(any(feature = "png", feature = "pdf")) {
+            insta::with_settings!({ snapshot_suffix => "fonts" }, {
+                insta::assert_snapshot!($name, $svg);
+            });
+        } else {
+            insta::assert_snapshot!($name, $svg);
+        }
+    };
+}
+
+pub(crate) use assert_svg_snapshot;
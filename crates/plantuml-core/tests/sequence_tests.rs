@@ -2,6 +2,9 @@
 //!
 //! Используем insta для snapshot тестирования SVG вывода.
 
+mod common;
+
+use common::assert_svg_snapshot;
 use plantuml_core::{render, RenderOptions};
 
 /// Тест простой sequence diagram
@@ -22,7 +25,7 @@ Bob --> Alice: Hi
     assert!(svg.contains("Hello"));
 
     // Snapshot тест
-    insta::assert_snapshot!("simple_sequence", svg);
+    assert_svg_snapshot!("simple_sequence", svg);
 }
 
 /// Тест sequence diagram с участниками разных типов
@@ -46,7 +49,7 @@ Admin --> User: Response
     assert!(svg.contains("<svg"));
     assert!(svg.contains("participant_"));
 
-    insta::assert_snapshot!("participant_types", svg);
+    assert_svg_snapshot!("participant_types", svg);
 }
 
 /// Тест self-message
@@ -64,7 +67,7 @@ Server -> Server: Validate
     assert!(svg.contains("Server"));
     assert!(svg.contains("Process"));
 
-    insta::assert_snapshot!("self_message", svg);
+    assert_svg_snapshot!("self_message", svg);
 }
 
 /// Тест fragment (alt)
@@ -85,5 +88,27 @@ end
     assert!(svg.contains("Alice"));
     assert!(svg.contains("Bob"));
 
-    insta::assert_snapshot!("alt_fragment", svg);
+    assert_svg_snapshot!("alt_fragment", svg);
+}
+
+/// Тест интерактивного SVG: id, классы, строки исходника и ссылки
+#[test]
+fn test_interactive_sequence_svg() {
+    let source = r#"@startuml
+participant Alice [[https://example.com/alice Профиль]]
+Alice -> Bob: ping
+Alice -> Bob: [[https://example.com/pong]] pong
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains("xmlns:xlink=\"http://www.w3.org/1999/xlink\""));
+    assert!(svg.contains("class=\"participant\" data-line=\"2\" id=\"participant_Alice\""));
+    assert!(svg.contains("xlink:href=\"https://example.com/alice\""));
+    assert!(svg.contains("<title>Профиль</title>"));
+    // Повторяющиеся id получают суффикс
+    assert!(svg.contains("class=\"message\" data-line=\"3\" id=\"msg_Alice_Bob\""));
+    assert!(svg.contains("class=\"message\" data-line=\"4\" id=\"msg_Alice_Bob-2\""));
+    assert!(svg.contains("xlink:href=\"https://example.com/pong\""));
+    assert!(!svg.contains("[["));
 }
@@ -2,6 +2,9 @@
 //!
 //! Используем insta для snapshot тестирования SVG вывода.
 
+mod common;
+
+use common::assert_svg_snapshot;
 use plantuml_core::{render, RenderOptions};
 
 /// Тест простой state diagram
@@ -23,7 +26,7 @@ Active --> [*] : close
     assert!(svg.contains("Inactive"));
 
     // Snapshot тест
-    insta::assert_snapshot!("simple_state", svg);
+    assert_svg_snapshot!("simple_state", svg);
 }
 
 /// Тест state diagram с определениями состояний
@@ -47,7 +50,7 @@ Active --> [*]
     assert!(svg.contains("<svg"));
     assert!(svg.contains("Active"));
 
-    insta::assert_snapshot!("state_with_definitions", svg);
+    assert_svg_snapshot!("state_with_definitions", svg);
 }
 
 /// Тест state diagram с choice point
@@ -67,5 +70,5 @@ Invalid --> [*]
     assert!(svg.contains("<?xml"));
     assert!(svg.contains("<svg"));
 
-    insta::assert_snapshot!("state_with_choice", svg);
+    assert_svg_snapshot!("state_with_choice", svg);
 }
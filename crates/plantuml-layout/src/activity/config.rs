@@ -27,6 +27,10 @@ pub struct ActivityLayoutConfig {
     pub action_corner_radius: f64,
     /// Размер стрелки
     pub arrow_size: f64,
+    /// Размер шрифта действий
+    pub font_size: f64,
+    /// Отступ текста внутри действия
+    pub text_padding: f64,
 }
 
 impl Default for ActivityLayoutConfig {
@@ -44,6 +48,8 @@ impl Default for ActivityLayoutConfig {
             bar_width: 50.0,
             action_corner_radius: 10.0,
             arrow_size: 8.0,
+            font_size: 14.0,
+            text_padding: 10.0,
         }
     }
 }
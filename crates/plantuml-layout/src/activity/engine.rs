@@ -2,30 +2,41 @@
 //!
 //! Flowchart-based layout algorithm для activity diagrams.
 
+use std::sync::Arc;
+
 use plantuml_ast::activity::{
     Action, ActivityDiagram, ActivityElement, Condition, Fork, RepeatLoop, WhileLoop,
 };
-use plantuml_model::{Point, Rect};
+use plantuml_model::{Point, Rect, Size};
 
 use super::config::ActivityLayoutConfig;
-use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+use crate::text::{EstimatedMetrics, TextMetrics};
+use crate::{EdgeLabelPositions, EdgeType, ElementType, LayoutElement, LayoutResult};
 
 /// Layout engine для activity diagrams
 pub struct ActivityLayoutEngine {
     config: ActivityLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl ActivityLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: ActivityLayoutConfig::default(),
-        }
+        Self::with_config(ActivityLayoutConfig::default())
     }
 
     /// Создаёт engine с заданной конфигурацией
     pub fn with_config(config: ActivityLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics::default()),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout диаграммы
@@ -33,8 +44,9 @@ impl ActivityLayoutEngine {
         let mut elements = Vec::new();
         let mut current_y = self.config.margin;
         
-        // Центр диаграммы по X
-        let center_x = self.config.margin + self.config.action_width / 2.0;
+        // Центр диаграммы по X: самое широкое действие не выходит за отступ
+        let max_width = self.max_action_width(&diagram.elements);
+        let center_x = self.config.margin + max_width / 2.0;
 
         // Обрабатываем элементы последовательно
         for element in &diagram.elements {
@@ -157,6 +169,44 @@ impl ActivityLayoutEngine {
         self.layout_stop(center_x, current_y, elements)
     }
 
+    /// Размер действия: не меньше конфигурации, подпись помещается с отступами
+    fn action_size(&self, label: &str) -> Size {
+        let text = self.metrics.text_size(label, self.config.font_size);
+        let padding = self.config.text_padding * 2.0;
+        Size::new(
+            self.config.action_width.max(text.width + padding),
+            self.config.action_height.max(text.height + padding),
+        )
+    }
+
+    /// Наибольшая ширина действия среди элементов (включая вложенные ветки)
+    fn max_action_width(&self, elements: &[ActivityElement]) -> f64 {
+        elements
+            .iter()
+            .map(|element| match element {
+                ActivityElement::Action(action) => self.action_size(&action.label).width,
+                ActivityElement::Condition(cond) => {
+                    let mut width = self.max_action_width(&cond.then_branch);
+                    for branch in &cond.elseif_branches {
+                        width = width.max(self.max_action_width(&branch.elements));
+                    }
+                    if let Some(branch) = &cond.else_branch {
+                        width = width.max(self.max_action_width(branch));
+                    }
+                    width
+                }
+                ActivityElement::While(w) => self.max_action_width(&w.body),
+                ActivityElement::Repeat(r) => self.max_action_width(&r.body),
+                ActivityElement::Fork(fork) => fork
+                    .branches
+                    .iter()
+                    .map(|branch| self.max_action_width(branch))
+                    .fold(0.0, f64::max),
+                _ => 0.0,
+            })
+            .fold(self.config.action_width, f64::max)
+    }
+
     /// Располагает действие (rounded rectangle)
     fn layout_action(
         &self,
@@ -165,8 +215,7 @@ impl ActivityLayoutEngine {
         current_y: f64,
         elements: &mut Vec<LayoutElement>,
     ) -> f64 {
-        let w = self.config.action_width;
-        let h = self.config.action_height;
+        let Size { width: w, height: h } = self.action_size(&action.label);
 
         elements.push(LayoutElement {
             id: format!("action_{}", elements.len()),
@@ -310,6 +359,7 @@ impl ActivityLayoutEngine {
                 arrow_end: true,
                 dashed: false,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
         });
 
@@ -374,6 +424,7 @@ impl ActivityLayoutEngine {
                 arrow_end: true,
                 dashed: false,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
         });
 
@@ -510,6 +561,7 @@ impl ActivityLayoutEngine {
                 arrow_end: true,
                 dashed: false,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
         });
     }
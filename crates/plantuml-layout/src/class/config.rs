@@ -1,5 +1,8 @@
 //! Конфигурация для Class Layout Engine
 
+use crate::graph::LineType;
+use crate::text::FixedMetrics;
+
 /// Конфигурация layout'а class diagrams
 #[derive(Debug, Clone)]
 pub struct ClassLayoutConfig {
@@ -19,8 +22,16 @@ pub struct ClassLayoutConfig {
     pub class_padding: f64,
     /// Отступ от границ диаграммы
     pub margin: f64,
-    /// Ширина символа (приблизительно)
+    /// Ширина символа (приблизительно, если не заданы метрики шрифтов)
     pub char_width: f64,
+    /// Размер шрифта подписей
+    pub font_size: f64,
+    /// Отступ внутри пакета
+    pub package_padding: f64,
+    /// Высота заголовка пакета
+    pub package_header_height: f64,
+    /// Форма линий рёбер (`skinparam linetype`)
+    pub line_type: LineType,
 }
 
 impl Default for ClassLayoutConfig {
@@ -35,6 +46,10 @@ impl Default for ClassLayoutConfig {
             class_padding: 10.0,
             margin: 20.0,
             char_width: 8.0,
+            font_size: 14.0,
+            package_padding: 15.0,
+            package_header_height: 25.0,
+            line_type: LineType::Spline,
         }
     }
 }
@@ -45,6 +60,11 @@ impl ClassLayoutConfig {
         Self::default()
     }
 
+    /// Оценка метрик текста по `char_width`
+    pub fn estimated_metrics(&self) -> FixedMetrics {
+        FixedMetrics::new(self.char_width, self.font_size)
+    }
+
     /// Устанавливает расстояние между узлами
     pub fn with_node_spacing(mut self, horizontal: f64, vertical: f64) -> Self {
         self.node_horizontal_spacing = horizontal;
@@ -1,8 +1,11 @@
 //! ClassLayoutEngine - layout engine для диаграмм классов.
 
+use std::sync::Arc;
+
 use plantuml_ast::class::{ClassDiagram, ClassifierType, RelationshipType};
-use plantuml_model::{Point, Rect};
+use plantuml_model::Rect;
 
+use crate::text::TextMetrics;
 use crate::traits::LayoutEngine;
 use crate::{ClassMember, ClassifierKind, EdgeType, ElementType, LayoutConfig, LayoutElement, LayoutResult, MemberVisibility};
 
@@ -11,23 +14,32 @@ use super::graph::Graph;
 use super::sugiyama::SugiyamaLayout;
 
 /// Layout engine для Class Diagrams
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ClassLayoutEngine {
     /// Конфигурация layout
     config: ClassLayoutConfig,
+    /// Метрики шрифтов; без них размеры оцениваются по `char_width`
+    metrics: Option<Arc<dyn TextMetrics>>,
 }
 
 impl ClassLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: ClassLayoutConfig::default(),
-        }
+        Self::with_config(ClassLayoutConfig::default())
     }
 
     /// Создаёт engine с заданной конфигурацией
     pub fn with_config(config: ClassLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: None,
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
     }
 
     /// Выполняет layout диаграммы классов
@@ -37,13 +49,30 @@ impl ClassLayoutEngine {
         }
 
         // Строим граф и выполняем Sugiyama layout
-        let mut graph = Graph::from_diagram(diagram, &self.config);
-        let mut sugiyama = SugiyamaLayout::new(&mut graph, &self.config);
+        let estimated = self.config.estimated_metrics();
+        let metrics = self.metrics.as_deref();
+        let mut graph =
+            Graph::from_diagram(diagram, &self.config, metrics.unwrap_or(&estimated));
+        let mut sugiyama = SugiyamaLayout::new(&mut graph, &self.config, metrics);
         sugiyama.run();
 
         // Преобразуем результат в LayoutElements
         let mut elements = Vec::new();
 
+        // Пакеты (до классов, чтобы рендериться под ними)
+        for cluster in &graph.clusters {
+            elements.push(LayoutElement {
+                id: format!("package_{}", cluster.name),
+                bounds: cluster.bounds,
+                text: None,
+                properties: std::collections::HashMap::new(),
+                element_type: ElementType::Group {
+                    label: Some(cluster.name.clone()),
+                    children: Vec::new(),
+                },
+            });
+        }
+
         // Добавляем узлы (классы)
         for node in &graph.nodes {
             // Ищем оригинальный classifier для получения деталей
@@ -189,6 +218,8 @@ impl ClassLayoutEngine {
                 methods,
             },
         }
+        .with_link(classifier.and_then(|c| c.link.as_ref()))
+        .with_span(&classifier.map(|c| c.span).unwrap_or_default())
     }
 
     /// Конвертирует Visibility из AST в MemberVisibility
@@ -208,27 +239,16 @@ impl ClassLayoutEngine {
         from_node: &super::graph::Node,
         to_node: &super::graph::Node,
     ) -> LayoutElement {
-        // Определяем визуальное направление стрелки
         // В графе: from_node = родитель (слой 0, вверху), to_node = потомок (ниже)
         // Для наследования стрелка должна идти ОТ потомка К родителю (снизу вверх)
         // Для композиции/агрегации стрелка идёт ОТ владельца К части
-        let (visual_from, visual_to) = match edge.relationship_type {
-            RelationshipType::Inheritance | RelationshipType::Realization => {
-                // Стрелка от потомка к родителю
-                (to_node, from_node)
-            }
-            _ => (from_node, to_node),
-        };
-
-        // Вычисляем точки соединения (передаём тип связи для правильного выбора грани)
-        let (start_point, end_point) = self.calculate_connection_points(
-            visual_from, 
-            visual_to, 
-            edge.relationship_type
-        );
-
-        // Создаём путь с ортогональными линиями
-        let points = self.create_orthogonal_path(start_point, end_point, visual_from, visual_to);
+        let mut route = edge.route.clone();
+        if matches!(
+            edge.relationship_type,
+            RelationshipType::Inheritance | RelationshipType::Realization
+        ) {
+            route.reverse();
+        }
 
         // Определяем стрелки и тип линии на основе типа отношения
         // arrow_end = маркер на конце линии (у целевого узла)
@@ -260,11 +280,11 @@ impl ClassLayoutEngine {
 
         LayoutElement {
             id: format!("edge_{}_{}", from_node.id, to_node.id),
-            bounds: self.calculate_edge_bounds(&points),
+            bounds: route.bounds(),
             text: None,
             properties: std::collections::HashMap::new(),
             element_type: ElementType::Edge {
-                points,
+                points: route.points.clone(),
                 label: edge.label.clone(),
                 arrow_start,
                 arrow_end,
@@ -272,121 +292,11 @@ impl ClassLayoutEngine {
                 edge_type,
                 from_cardinality: from_card,
                 to_cardinality: to_card,
+                curve: route.curve.clone(),
+                label_positions: route.label_positions(),
             },
         }
     }
-
-    /// Вычисляет точки соединения между двумя узлами
-    fn calculate_connection_points(
-        &self,
-        from: &super::graph::Node,
-        to: &super::graph::Node,
-        relationship_type: RelationshipType,
-    ) -> (Point, Point) {
-        let from_center_x = from.x + from.size.width / 2.0;
-        let to_center_x = to.x + to.size.width / 2.0;
-
-        // Для наследования и реализации ВСЕГДА используем верхнюю/нижнюю грань
-        // независимо от горизонтального расположения узлов
-        match relationship_type {
-            RelationshipType::Inheritance | RelationshipType::Realization => {
-                // from = потомок (снизу), to = родитель (сверху)
-                // Стрелка выходит из верхней грани потомка, входит в нижнюю грань родителя
-                let start = Point::new(from_center_x, from.y); // верх потомка
-                let end = Point::new(to_center_x, to.y + to.size.height); // низ родителя
-                (start, end)
-            }
-            _ => {
-                // Для других типов связей - автоопределение направления
-                let from_center_y = from.y + from.size.height / 2.0;
-                let to_center_y = to.y + to.size.height / 2.0;
-                let dx = to_center_x - from_center_x;
-                let dy = to_center_y - from_center_y;
-
-                if dy.abs() > dx.abs() {
-                    // Вертикальное соединение
-                    if dy > 0.0 {
-                        (
-                            Point::new(from_center_x, from.y + from.size.height),
-                            Point::new(to_center_x, to.y),
-                        )
-                    } else {
-                        (
-                            Point::new(from_center_x, from.y),
-                            Point::new(to_center_x, to.y + to.size.height),
-                        )
-                    }
-                } else {
-                    // Горизонтальное соединение
-                    if dx > 0.0 {
-                        (
-                            Point::new(from.x + from.size.width, from_center_y),
-                            Point::new(to.x, to_center_y),
-                        )
-                    } else {
-                        (
-                            Point::new(from.x, from_center_y),
-                            Point::new(to.x + to.size.width, to_center_y),
-                        )
-                    }
-                }
-            }
-        }
-    }
-
-    /// Создаёт ортогональный путь между точками (с коленом)
-    fn create_orthogonal_path(
-        &self,
-        start: Point,
-        end: Point,
-        _from: &super::graph::Node,
-        _to: &super::graph::Node,
-    ) -> Vec<Point> {
-        let dx = end.x - start.x;
-        let dy = end.y - start.y;
-
-        // Если точки почти на одной линии - прямая
-        if dx.abs() < 1.0 || dy.abs() < 1.0 {
-            return vec![start, end];
-        }
-
-        // Ортогональный путь с коленом
-        // Для вертикального наследования (потомок снизу, родитель сверху):
-        // start = верх потомка, end = низ родителя
-        // Линия: вверх от потомка → горизонтально → вниз к родителю
-        
-        // Вычисляем Y для горизонтального сегмента
-        // Это должно быть между нижней гранью родителя и верхней гранью потомка
-        let mid_y = if dy < 0.0 {
-            // end выше start (типичное наследование: потомок внизу)
-            // mid_y = середина между end.y и start.y
-            end.y + (start.y - end.y) / 2.0
-        } else {
-            // end ниже start
-            start.y + (end.y - start.y) / 2.0
-        };
-
-        vec![
-            start,
-            Point::new(start.x, mid_y),
-            Point::new(end.x, mid_y),
-            end,
-        ]
-    }
-
-    /// Вычисляет bounds для ребра
-    fn calculate_edge_bounds(&self, points: &[Point]) -> Rect {
-        if points.is_empty() {
-            return Rect::new(0.0, 0.0, 0.0, 0.0);
-        }
-
-        let min_x = points.iter().map(|p| p.x).fold(f64::MAX, f64::min);
-        let min_y = points.iter().map(|p| p.y).fold(f64::MAX, f64::min);
-        let max_x = points.iter().map(|p| p.x).fold(f64::MIN, f64::max);
-        let max_y = points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
-
-        Rect::new(min_x, min_y, max_x - min_x, max_y - min_y)
-    }
 }
 
 impl Default for ClassLayoutEngine {
@@ -522,4 +432,34 @@ mod tests {
         assert!(result.bounds.width > 0.0);
         assert!(result.bounds.height > 0.0);
     }
+
+    #[test]
+    fn test_package_encloses_classes() {
+        let engine = ClassLayoutEngine::new();
+        let mut diagram = ClassDiagram::new();
+
+        let mut package = plantuml_ast::class::Package::new("model");
+        package.classifiers.push(Classifier::new("User"));
+        package.classifiers.push(Classifier::new("Order"));
+        diagram.packages.push(package);
+        diagram.add_class(Classifier::new("Service"));
+        diagram.add_relationship(Relationship::new(
+            "Service",
+            "User",
+            RelationshipType::Association,
+        ));
+
+        let result = engine.layout_diagram(&diagram);
+
+        let package = result.elements.iter().find(|e| e.id == "package_model").unwrap();
+        for name in ["User", "Order"] {
+            let class = result.elements.iter().find(|e| e.id == name).unwrap();
+            assert!(class.bounds.x >= package.bounds.x);
+            assert!(class.bounds.y >= package.bounds.y);
+            assert!(class.bounds.x + class.bounds.width <= package.bounds.x + package.bounds.width);
+            assert!(class.bounds.y + class.bounds.height <= package.bounds.y + package.bounds.height);
+        }
+        let service = result.elements.iter().find(|e| e.id == "Service").unwrap();
+        assert!(service.bounds.y + service.bounds.height <= package.bounds.y);
+    }
 }
@@ -5,9 +5,11 @@
 use std::collections::HashMap;
 
 use plantuml_ast::class::{ClassDiagram, Classifier, Relationship, RelationshipType};
-use plantuml_model::Size;
+use plantuml_model::{Rect, Size};
 
 use super::config::ClassLayoutConfig;
+use crate::graph::RoutedEdge;
+use crate::text::TextMetrics;
 
 /// Узел графа (класс/интерфейс)
 #[derive(Debug, Clone)]
@@ -28,6 +30,8 @@ pub struct Node {
     pub x: f64,
     /// Y координата (после layout)
     pub y: f64,
+    /// Пакет, непосредственно содержащий класс
+    pub cluster: Option<usize>,
 }
 
 impl Node {
@@ -37,8 +41,9 @@ impl Node {
         index: usize,
         classifier: &Classifier,
         config: &ClassLayoutConfig,
+        metrics: &dyn TextMetrics,
     ) -> Self {
-        let size = Self::calculate_size(classifier, config);
+        let size = Self::calculate_size(classifier, config, metrics);
         Self {
             id: id.clone(),
             index,
@@ -48,16 +53,21 @@ impl Node {
             position: 0,
             x: 0.0,
             y: 0.0,
+            cluster: None,
         }
     }
 
     /// Вычисляет размер узла на основе содержимого класса
-    fn calculate_size(classifier: &Classifier, config: &ClassLayoutConfig) -> Size {
+    fn calculate_size(
+        classifier: &Classifier,
+        config: &ClassLayoutConfig,
+        metrics: &dyn TextMetrics,
+    ) -> Size {
         // Ширина: max(имя класса, поля, методы)
         // Добавляем место для иконки класса (~30px)
         let icon_width = 30.0;
         let name_width =
-            classifier.id.name.len() as f64 * config.char_width + icon_width + config.class_padding * 2.0;
+            metrics.text_width(&classifier.id.name, config.font_size) + icon_width + config.class_padding * 2.0;
 
         let field_max_width = classifier
             .fields
@@ -69,7 +79,7 @@ impl Node {
                 } else {
                     format!("{}{}", f.visibility.to_char(), f.name)
                 };
-                text.len() as f64 * config.char_width
+                metrics.text_width(&text, config.font_size)
             })
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap_or(0.0);
@@ -84,7 +94,7 @@ impl Node {
                 } else {
                     format!("{}{}()", m.visibility.to_char(), m.name)
                 };
-                text.len() as f64 * config.char_width
+                metrics.text_width(&text, config.font_size)
             })
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap_or(0.0);
@@ -142,6 +152,8 @@ pub struct Edge {
     pub to_cardinality: Option<String>,
     /// Обратное ребро (для удаления циклов)
     pub reversed: bool,
+    /// Маршрут ребра после layout (в направлении from → to)
+    pub route: RoutedEdge,
 }
 
 impl Edge {
@@ -155,8 +167,28 @@ impl Edge {
             from_cardinality: rel.from_cardinality.clone(),
             to_cardinality: rel.to_cardinality.clone(),
             reversed: false,
+            route: RoutedEdge::default(),
         }
     }
+
+    /// Обращает ребро: концы, кардинальности и маршрут меняются местами
+    pub fn reverse(&mut self) {
+        self.reversed = true;
+        std::mem::swap(&mut self.from, &mut self.to);
+        std::mem::swap(&mut self.from_cardinality, &mut self.to_cardinality);
+        self.route.reverse();
+    }
+}
+
+/// Пакет (кластер узлов)
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    /// Имя пакета
+    pub name: String,
+    /// Родительский пакет
+    pub parent: Option<usize>,
+    /// Bounding box (после layout)
+    pub bounds: Rect,
 }
 
 /// Граф для алгоритма Sugiyama
@@ -164,6 +196,8 @@ impl Edge {
 pub struct Graph {
     /// Все узлы
     pub nodes: Vec<Node>,
+    /// Пакеты
+    pub clusters: Vec<Cluster>,
     /// Все рёбра
     pub edges: Vec<Edge>,
     /// Индекс узлов по имени
@@ -176,8 +210,13 @@ pub struct Graph {
 
 impl Graph {
     /// Создаёт граф из ClassDiagram
-    pub fn from_diagram(diagram: &ClassDiagram, config: &ClassLayoutConfig) -> Self {
+    pub fn from_diagram(
+        diagram: &ClassDiagram,
+        config: &ClassLayoutConfig,
+        metrics: &dyn TextMetrics,
+    ) -> Self {
         let mut nodes = Vec::new();
+        let mut clusters = Vec::new();
         let mut node_index = HashMap::new();
 
         // Создаём узлы из классификаторов
@@ -186,16 +225,19 @@ impl Graph {
             if !node_index.contains_key(&id) {
                 let index = nodes.len();
                 node_index.insert(id.clone(), index);
-                nodes.push(Node::new(id, index, classifier, config));
+                nodes.push(Node::new(id, index, classifier, config, metrics));
             }
         }
 
         // Также добавляем узлы из пакетов (рекурсивно)
         Self::collect_classifiers_from_packages(
             &diagram.packages,
+            None,
             &mut nodes,
+            &mut clusters,
             &mut node_index,
             config,
+            metrics,
         );
 
         // Создаём фиктивные узлы для классов, упомянутых в отношениях, но не объявленных
@@ -214,6 +256,7 @@ impl Graph {
                         position: 0,
                         x: 0.0,
                         y: 0.0,
+                        cluster: None,
                     });
                 }
             }
@@ -237,7 +280,12 @@ impl Graph {
                     }
                     _ => (from_idx, to_idx),
                 };
-                edges.push(Edge::new(graph_from, graph_to, rel));
+                let mut edge = Edge::new(graph_from, graph_to, rel);
+                if graph_from != from_idx {
+                    // Кардинальности следуют за концами ребра
+                    std::mem::swap(&mut edge.from_cardinality, &mut edge.to_cardinality);
+                }
+                edges.push(edge);
             }
         }
 
@@ -253,6 +301,7 @@ impl Graph {
 
         Self {
             nodes,
+            clusters,
             edges,
             node_index,
             adjacency,
@@ -263,21 +312,41 @@ impl Graph {
     /// Собирает классификаторы из пакетов рекурсивно
     fn collect_classifiers_from_packages(
         packages: &[plantuml_ast::class::Package],
+        parent: Option<usize>,
         nodes: &mut Vec<Node>,
+        clusters: &mut Vec<Cluster>,
         node_index: &mut HashMap<String, usize>,
         config: &ClassLayoutConfig,
+        metrics: &dyn TextMetrics,
     ) {
         for package in packages {
+            let cluster = clusters.len();
+            clusters.push(Cluster {
+                name: package.name.clone(),
+                parent,
+                bounds: Rect::default(),
+            });
+
             for classifier in &package.classifiers {
                 let id = classifier.id.name.clone();
                 if !node_index.contains_key(&id) {
                     let index = nodes.len();
                     node_index.insert(id.clone(), index);
-                    nodes.push(Node::new(id, index, classifier, config));
+                    let mut node = Node::new(id, index, classifier, config, metrics);
+                    node.cluster = Some(cluster);
+                    nodes.push(node);
                 }
             }
             // Рекурсивно обрабатываем вложенные пакеты
-            Self::collect_classifiers_from_packages(&package.packages, nodes, node_index, config);
+            Self::collect_classifiers_from_packages(
+                &package.packages,
+                Some(cluster),
+                nodes,
+                clusters,
+                node_index,
+                config,
+                metrics,
+            );
         }
     }
 
@@ -342,7 +411,7 @@ mod tests {
         diagram.add_relationship(Relationship::inheritance("Dog", "Animal"));
 
         let config = ClassLayoutConfig::default();
-        let graph = Graph::from_diagram(&diagram, &config);
+        let graph = Graph::from_diagram(&diagram, &config, &config.estimated_metrics());
 
         assert_eq!(graph.node_count(), 2);
         assert_eq!(graph.edge_count(), 1);
@@ -357,7 +426,13 @@ mod tests {
         classifier.add_field(Member::field("name", "String").with_visibility(Visibility::Private));
         classifier.add_method(Member::method("getId").with_visibility(Visibility::Public));
 
-        let node = Node::new("TestClass".to_string(), 0, &classifier, &config);
+        let node = Node::new(
+            "TestClass".to_string(),
+            0,
+            &classifier,
+            &config,
+            &config.estimated_metrics(),
+        );
 
         assert!(node.size.width >= config.min_class_width);
         assert!(node.size.height >= config.min_class_height);
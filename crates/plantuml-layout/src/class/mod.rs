@@ -1,15 +1,17 @@
 //! # Class Diagram Layout
 //!
-//! Layout engine для диаграмм классов с использованием алгоритма Sugiyama.
+//! Layout engine для диаграмм классов с использованием алгоритма Sugiyama
+//! (общая реализация — [`crate::graph`]).
 //!
 //! ## Алгоритм Sugiyama
 //!
 //! Иерархический layout для направленных графов:
 //! 1. **Удаление циклов** - обращение обратных рёбер
 //! 2. **Присвоение слоёв** - каждому узлу назначается слой (Y-координата)
-//! 3. **Минимизация пересечений** - переупорядочивание узлов внутри слоёв
-//! 4. **Присвоение координат** - X/Y координаты для узлов
-//! 5. **Маршрутизация рёбер** - ортогональные линии между узлами
+//! 3. **Фиктивные узлы** - длинные рёбра разбиваются по слоям
+//! 4. **Минимизация пересечений** - медиана/барицентр + transpose
+//! 5. **Присвоение координат** - Brandes-Köpf
+//! 6. **Маршрутизация рёбер** - ломаные через фиктивные узлы
 
 pub mod config;
 pub mod engine;
@@ -1,12 +1,13 @@
-//! Алгоритм Sugiyama для иерархического layout графов.
+//! Алгоритм Sugiyama для иерархического layout графов классов.
 //!
-//! Реализует 4 основных шага:
-//! 1. Удаление циклов (cycle removal)
-//! 2. Присвоение слоёв (layer assignment)
-//! 3. Минимизация пересечений (crossing minimization)
-//! 4. Присвоение координат (coordinate assignment)
+//! Адаптер над [`crate::graph`]: граф классов (с пакетами-кластерами)
+//! преобразуется в [`GraphLayout`], результат (координаты узлов и пакетов,
+//! маршруты рёбер) записывается обратно.
 
-use std::collections::VecDeque;
+use plantuml_model::Size;
+
+use crate::graph::{self, GraphCluster, GraphEdge, GraphLayout, SugiyamaConfig};
+use crate::text::TextMetrics;
 
 use super::config::ClassLayoutConfig;
 use super::graph::Graph;
@@ -15,12 +16,22 @@ use super::graph::Graph;
 pub struct SugiyamaLayout<'a> {
     graph: &'a mut Graph,
     config: &'a ClassLayoutConfig,
+    /// Метрики шрифтов; без них подписи рёбер оцениваются [`graph::label_size`]
+    metrics: Option<&'a dyn TextMetrics>,
 }
 
 impl<'a> SugiyamaLayout<'a> {
     /// Создаёт новый экземпляр алгоритма
-    pub fn new(graph: &'a mut Graph, config: &'a ClassLayoutConfig) -> Self {
-        Self { graph, config }
+    pub fn new(
+        graph: &'a mut Graph,
+        config: &'a ClassLayoutConfig,
+        metrics: Option<&'a dyn TextMetrics>,
+    ) -> Self {
+        Self {
+            graph,
+            config,
+            metrics,
+        }
     }
 
     /// Выполняет полный layout
@@ -29,341 +40,124 @@ impl<'a> SugiyamaLayout<'a> {
             return;
         }
 
-        // Шаг 1: Удаление циклов
-        self.remove_cycles();
-
-        // Шаг 2: Присвоение слоёв (longest path)
-        self.assign_layers();
-
-        // Шаг 3: Минимизация пересечений (barycenter)
-        self.minimize_crossings();
-
-        // Шаг 4: Присвоение координат
-        self.assign_coordinates();
-    }
-
-    // =========================================================================
-    // Шаг 1: Удаление циклов (Cycle Removal)
-    // =========================================================================
-
-    /// Удаляет циклы путём обращения обратных рёбер (greedy algorithm)
-    fn remove_cycles(&mut self) {
-        let n = self.graph.node_count();
-        if n == 0 {
-            return;
-        }
-
-        // Используем DFS для поиска обратных рёбер
-        let mut visited = vec![false; n];
-        let mut in_stack = vec![false; n];
-        let mut back_edges = Vec::new();
+        let result = self.to_graph_layout().layout();
 
-        for start in 0..n {
-            if !visited[start] {
-                self.dfs_find_back_edges(start, &mut visited, &mut in_stack, &mut back_edges);
-            }
+        // Узлы и пакеты: координаты
+        for (node, bounds) in self.graph.nodes.iter_mut().zip(&result.nodes) {
+            node.x = bounds.x;
+            node.y = bounds.y;
         }
-
-        // Обращаем обратные рёбра
-        for edge_idx in back_edges {
-            self.graph.edges[edge_idx].reversed = true;
-            let edge = &mut self.graph.edges[edge_idx];
-            std::mem::swap(&mut edge.from, &mut edge.to);
+        for (cluster, bounds) in self.graph.clusters.iter_mut().zip(&result.clusters) {
+            cluster.bounds = *bounds;
         }
 
-        // Перестраиваем списки смежности
-        self.rebuild_adjacency();
-    }
-
-    /// DFS для поиска обратных рёбер
-    fn dfs_find_back_edges(
-        &self,
-        node: usize,
-        visited: &mut [bool],
-        in_stack: &mut [bool],
-        back_edges: &mut Vec<usize>,
-    ) {
-        visited[node] = true;
-        in_stack[node] = true;
-
-        for &edge_idx in &self.graph.adjacency[node] {
-            let edge = &self.graph.edges[edge_idx];
-            let target = edge.to;
-
-            if !visited[target] {
-                self.dfs_find_back_edges(target, visited, in_stack, back_edges);
-            } else if in_stack[target] {
-                // Нашли обратное ребро
-                back_edges.push(edge_idx);
+        // Рёбра: обращённые рёбра разворачиваем, маршрут храним в направлении графа
+        for (edge, route) in self.graph.edges.iter_mut().zip(result.edges) {
+            let reversed = route.reversed;
+            edge.route = route;
+            if reversed {
+                edge.reverse();
             }
         }
 
-        in_stack[node] = false;
-    }
-
-    /// Перестраивает списки смежности после обращения рёбер
-    fn rebuild_adjacency(&mut self) {
-        let n = self.graph.node_count();
-        self.graph.adjacency = vec![Vec::new(); n];
-        self.graph.reverse_adjacency = vec![Vec::new(); n];
+        self.rebuild_adjacency();
 
-        for (idx, edge) in self.graph.edges.iter().enumerate() {
-            self.graph.adjacency[edge.from].push(idx);
-            self.graph.reverse_adjacency[edge.to].push(idx);
+        // Слои и порядок внутри слоя — для навигации по графу
+        self.assign_layers();
+        let mut order: Vec<usize> = (0..self.graph.node_count()).collect();
+        order.sort_by(|&a, &b| self.graph.nodes[a].x.total_cmp(&self.graph.nodes[b].x));
+        let mut next = vec![0usize; self.graph.max_layer() + 1];
+        for idx in order {
+            let node = &mut self.graph.nodes[idx];
+            node.position = next[node.layer];
+            next[node.layer] += 1;
         }
     }
 
-    // =========================================================================
-    // Шаг 2: Присвоение слоёв (Layer Assignment)
-    // =========================================================================
-
-    /// Присваивает слои методом longest path
-    fn assign_layers(&mut self) {
-        let n = self.graph.node_count();
-        if n == 0 {
-            return;
-        }
-
-        // Топологическая сортировка (Kahn's algorithm)
-        let topo_order = self.topological_sort();
-
-        // Longest path: для каждого узла layer = max(layer предшественников) + 1
-        let mut layers = vec![0usize; n];
-
-        for &node in &topo_order {
-            let mut max_pred_layer = 0;
-            for &edge_idx in &self.graph.reverse_adjacency[node] {
-                let edge = &self.graph.edges[edge_idx];
-                max_pred_layer = max_pred_layer.max(layers[edge.from] + 1);
-            }
-            layers[node] = max_pred_layer;
+    /// Преобразует граф классов во входной граф layout
+    fn to_graph_layout(&self) -> GraphLayout {
+        let config = SugiyamaConfig::default()
+            .with_spacing(
+                self.config.node_horizontal_spacing,
+                self.config.layer_vertical_spacing,
+            )
+            .with_margin(self.config.margin)
+            .with_line_type(self.config.line_type);
+        let mut layout = GraphLayout::new(config);
+
+        for cluster in &self.graph.clusters {
+            let name_width = match self.metrics {
+                Some(metrics) => metrics.text_width(&cluster.name, self.config.font_size),
+                None => self.config.estimated_metrics().text_width(&cluster.name, self.config.font_size),
+            };
+            let min_width = name_width + self.config.package_padding * 2.0;
+            layout.add_cluster(
+                GraphCluster::new(cluster.parent)
+                    .with_padding(self.config.package_padding)
+                    .with_header(self.config.package_header_height)
+                    .with_min_size(Size::new(min_width, 0.0)),
+            );
         }
-
-        // Записываем слои в узлы
-        for (idx, &layer) in layers.iter().enumerate() {
-            self.graph.nodes[idx].layer = layer;
+        for node in &self.graph.nodes {
+            match node.cluster {
+                Some(cluster) => layout.add_node_in(node.size, cluster),
+                None => layout.add_node(node.size),
+            };
         }
-    }
-
-    /// Топологическая сортировка (Kahn's algorithm)
-    fn topological_sort(&self) -> Vec<usize> {
-        let n = self.graph.node_count();
-        let mut in_degree = vec![0usize; n];
-
-        // Подсчитываем входящие степени
+        let label_size = |text: &str| match self.metrics {
+            Some(metrics) => metrics.text_size(text, self.config.font_size),
+            None => graph::label_size(text),
+        };
         for edge in &self.graph.edges {
-            in_degree[edge.to] += 1;
-        }
-
-        // Начинаем с узлов без входящих рёбер
-        let mut queue: VecDeque<usize> = in_degree
-            .iter()
-            .enumerate()
-            .filter(|(_, &deg)| deg == 0)
-            .map(|(idx, _)| idx)
-            .collect();
-
-        let mut result = Vec::with_capacity(n);
-
-        while let Some(node) = queue.pop_front() {
-            result.push(node);
-
-            for &edge_idx in &self.graph.adjacency[node] {
-                let target = self.graph.edges[edge_idx].to;
-                in_degree[target] -= 1;
-                if in_degree[target] == 0 {
-                    queue.push_back(target);
-                }
-            }
-        }
-
-        // Если не все узлы обработаны, значит есть цикл (не должно случиться после remove_cycles)
-        if result.len() < n {
-            // Добавляем оставшиеся узлы
-            for i in 0..n {
-                if !result.contains(&i) {
-                    result.push(i);
-                }
-            }
-        }
-
-        result
-    }
-
-    // =========================================================================
-    // Шаг 3: Минимизация пересечений (Crossing Minimization)
-    // =========================================================================
-
-    /// Минимизирует пересечения методом барицентра
-    fn minimize_crossings(&mut self) {
-        let max_layer = self.graph.max_layer();
-        if max_layer == 0 {
-            return;
-        }
-
-        // Начальная позиция: порядок по индексу
-        for node in &mut self.graph.nodes {
-            node.position = node.index;
-        }
-
-        // Итерируем сверху-вниз и снизу-вверх несколько раз
-        const MAX_ITERATIONS: usize = 10;
-
-        for _ in 0..MAX_ITERATIONS {
-            // Сверху вниз
-            for layer in 1..=max_layer {
-                self.order_layer_by_barycenter(layer, true);
-            }
-
-            // Снизу вверх
-            for layer in (0..max_layer).rev() {
-                self.order_layer_by_barycenter(layer, false);
+            let mut graph_edge = GraphEdge::new(edge.from, edge.to);
+            if let Some(label) = &edge.label {
+                graph_edge = graph_edge.with_label(label_size(label));
             }
+            graph_edge = graph_edge.with_end_labels(
+                edge.from_cardinality.as_deref().map(label_size),
+                edge.to_cardinality.as_deref().map(label_size),
+            );
+            layout.add_edge(graph_edge);
         }
+        layout
     }
 
-    /// Упорядочивает узлы слоя методом барицентра
-    fn order_layer_by_barycenter(&mut self, layer: usize, use_upper: bool) {
-        let nodes_in_layer: Vec<usize> = self.graph.nodes_on_layer(layer);
-
-        if nodes_in_layer.is_empty() {
-            return;
-        }
-
-        // Вычисляем барицентр для каждого узла
-        let mut barycenters: Vec<(usize, f64)> = nodes_in_layer
-            .iter()
-            .map(|&node| {
-                let bc = self.calculate_barycenter(node, use_upper);
-                (node, bc)
-            })
-            .collect();
-
-        // Сортируем по барицентру
-        barycenters.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Обновляем позиции
-        for (pos, (node, _)) in barycenters.iter().enumerate() {
-            self.graph.nodes[*node].position = pos;
-        }
+    /// Текущие рёбра в виде пар индексов
+    fn edge_pairs(&self) -> Vec<(usize, usize)> {
+        self.graph.edges.iter().map(|e| (e.from, e.to)).collect()
     }
 
-    /// Вычисляет барицентр узла
-    fn calculate_barycenter(&self, node: usize, use_upper: bool) -> f64 {
-        let edges = if use_upper {
-            // Смотрим на предшественников (верхний слой)
-            &self.graph.reverse_adjacency[node]
-        } else {
-            // Смотрим на потомков (нижний слой)
-            &self.graph.adjacency[node]
-        };
+    /// Удаляет циклы путём обращения обратных рёбер
+    pub fn remove_cycles(&mut self) {
+        let reversed = graph::sugiyama::remove_cycles(self.graph.node_count(), &self.edge_pairs());
 
-        if edges.is_empty() {
-            // Нет связей - возвращаем текущую позицию
-            return self.graph.nodes[node].position as f64;
+        for (edge, rev) in self.graph.edges.iter_mut().zip(reversed) {
+            if rev {
+                edge.reverse();
+            }
         }
 
-        let sum: f64 = edges
-            .iter()
-            .map(|&edge_idx| {
-                let edge = &self.graph.edges[edge_idx];
-                let neighbor = if use_upper { edge.from } else { edge.to };
-                self.graph.nodes[neighbor].position as f64
-            })
-            .sum();
-
-        sum / edges.len() as f64
+        self.rebuild_adjacency();
     }
 
-    // =========================================================================
-    // Шаг 4: Присвоение координат (Coordinate Assignment)
-    // =========================================================================
-
-    /// Присваивает X и Y координаты узлам
-    fn assign_coordinates(&mut self) {
-        let max_layer = self.graph.max_layer();
-
-        // Y координаты: PlantUML располагает родителей ВВЕРХУ, детей ВНИЗУ
-        // Слой 0 = верх (корень/родитель), слой N = низ (потомки)
-        // Вычисляем Y на основе фактических высот узлов предыдущих слоёв
-        let mut layer_y = vec![0.0f64; max_layer + 1];
-        layer_y[0] = self.config.margin;
-        
-        for layer in 1..=max_layer {
-            // Находим максимальную высоту узлов на предыдущем слое
-            let prev_layer_max_height = self.graph.nodes_on_layer(layer - 1)
-                .iter()
-                .map(|&n| self.graph.nodes[n].size.height)
-                .max_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap_or(self.config.min_class_height);
-            
-            layer_y[layer] = layer_y[layer - 1] + prev_layer_max_height + self.config.layer_vertical_spacing;
-        }
-        
-        for node in &mut self.graph.nodes {
-            node.y = layer_y[node.layer];
-        }
-
-        // X координаты: позиционируем узлы внутри каждого слоя
-        for layer in 0..=max_layer {
-            self.position_layer_x(layer);
+    /// Присваивает слои (граф должен быть ацикличным)
+    pub fn assign_layers(&mut self) {
+        let layers = graph::sugiyama::assign_layers(self.graph.node_count(), &self.edge_pairs());
+        for (node, layer) in self.graph.nodes.iter_mut().zip(layers) {
+            node.layer = layer;
         }
     }
 
-    /// Позиционирует узлы слоя по оси X
-    fn position_layer_x(&mut self, layer: usize) {
-        let mut nodes_in_layer: Vec<usize> = self.graph.nodes_on_layer(layer);
-
-        if nodes_in_layer.is_empty() {
-            return;
-        }
-
-        // Сортируем по позиции (результат минимизации пересечений)
-        nodes_in_layer.sort_by_key(|&n| self.graph.nodes[n].position);
-
-        // Вычисляем общую ширину слоя
-        let total_width: f64 = nodes_in_layer
-            .iter()
-            .map(|&n| self.graph.nodes[n].size.width)
-            .sum();
-
-        let spacing = (nodes_in_layer.len() - 1) as f64 * self.config.node_horizontal_spacing;
-        let layer_width = total_width + spacing;
-
-        // Начинаем с центрирования (можно улучшить)
-        let mut x = self.config.margin;
-
-        for &node_idx in &nodes_in_layer {
-            self.graph.nodes[node_idx].x = x;
-            x += self.graph.nodes[node_idx].size.width + self.config.node_horizontal_spacing;
-        }
-
-        // Центрируем слой относительно максимальной ширины
-        let max_layer_width = self.calculate_max_layer_width();
-        if layer_width < max_layer_width {
-            let offset = (max_layer_width - layer_width) / 2.0;
-            for &node_idx in &nodes_in_layer {
-                self.graph.nodes[node_idx].x += offset;
-            }
-        }
-    }
+    /// Перестраивает списки смежности после обращения рёбер
+    fn rebuild_adjacency(&mut self) {
+        let n = self.graph.node_count();
+        self.graph.adjacency = vec![Vec::new(); n];
+        self.graph.reverse_adjacency = vec![Vec::new(); n];
 
-    /// Вычисляет максимальную ширину слоя
-    fn calculate_max_layer_width(&self) -> f64 {
-        let max_layer = self.graph.max_layer();
-        let mut max_width = 0.0f64;
-
-        for layer in 0..=max_layer {
-            let nodes = self.graph.nodes_on_layer(layer);
-            let width: f64 = nodes
-                .iter()
-                .map(|&n| self.graph.nodes[n].size.width)
-                .sum::<f64>()
-                + (nodes.len().saturating_sub(1)) as f64 * self.config.node_horizontal_spacing;
-            max_width = max_width.max(width);
+        for (idx, edge) in self.graph.edges.iter().enumerate() {
+            self.graph.adjacency[edge.from].push(idx);
+            self.graph.reverse_adjacency[edge.to].push(idx);
         }
-
-        max_width
     }
 }
 
@@ -384,7 +178,7 @@ mod tests {
         diagram.add_relationship(Relationship::inheritance("Cat", "Animal"));
 
         let config = ClassLayoutConfig::default();
-        let graph = Graph::from_diagram(&diagram, &config);
+        let graph = Graph::from_diagram(&diagram, &config, &config.estimated_metrics());
 
         (graph, config)
     }
@@ -392,7 +186,7 @@ mod tests {
     #[test]
     fn test_layer_assignment() {
         let (mut graph, config) = create_test_graph();
-        let mut sugiyama = SugiyamaLayout::new(&mut graph, &config);
+        let mut sugiyama = SugiyamaLayout::new(&mut graph, &config, None);
 
         sugiyama.assign_layers();
 
@@ -413,7 +207,7 @@ mod tests {
     #[test]
     fn test_full_layout() {
         let (mut graph, config) = create_test_graph();
-        let mut sugiyama = SugiyamaLayout::new(&mut graph, &config);
+        let mut sugiyama = SugiyamaLayout::new(&mut graph, &config, None);
 
         sugiyama.run();
 
@@ -424,6 +218,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_long_edge_routed_through_dummy() {
+        // A -> B -> C и A -> C: ребро A -> C пересекает слой B
+        let mut diagram = ClassDiagram::new();
+        diagram.add_class(Classifier::new("A"));
+        diagram.add_class(Classifier::new("B"));
+        diagram.add_class(Classifier::new("C"));
+        for (from, to) in [("A", "B"), ("B", "C"), ("A", "C")] {
+            diagram.add_relationship(Relationship::new(
+                from,
+                to,
+                plantuml_ast::class::RelationshipType::Association,
+            ));
+        }
+
+        let config = ClassLayoutConfig::default();
+        let mut graph = Graph::from_diagram(&diagram, &config, &config.estimated_metrics());
+        SugiyamaLayout::new(&mut graph, &config, None).run();
+
+        let long = &graph.edges[2];
+        let points = &long.route.points;
+        assert!(points.len() > 2);
+
+        let b = graph.get_node_by_name("B").unwrap();
+        for p in &points[1..points.len() - 1] {
+            assert!(p.x <= b.x || p.x >= b.x + b.size.width);
+        }
+    }
+
     #[test]
     fn test_cycle_removal() {
         let mut diagram = ClassDiagram::new();
@@ -451,8 +274,8 @@ mod tests {
         ));
 
         let config = ClassLayoutConfig::default();
-        let mut graph = Graph::from_diagram(&diagram, &config);
-        let mut sugiyama = SugiyamaLayout::new(&mut graph, &config);
+        let mut graph = Graph::from_diagram(&diagram, &config, &config.estimated_metrics());
+        let mut sugiyama = SugiyamaLayout::new(&mut graph, &config, None);
 
         sugiyama.remove_cycles();
 
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Component Diagrams
 
+use crate::graph::LineType;
+
 /// Конфигурация Component Layout Engine
 #[derive(Debug, Clone)]
 pub struct ComponentLayoutConfig {
@@ -23,6 +25,8 @@ pub struct ComponentLayoutConfig {
     pub corner_radius: f64,
     /// Размер иконки компонента
     pub icon_size: f64,
+    /// Форма линий рёбер (`skinparam linetype`)
+    pub line_type: LineType,
 }
 
 impl Default for ComponentLayoutConfig {
@@ -38,6 +42,7 @@ impl Default for ComponentLayoutConfig {
             package_header_height: 25.0,
             corner_radius: 5.0,
             icon_size: 16.0,
+            line_type: LineType::Spline,
         }
     }
 }
@@ -1,88 +1,138 @@
 //! Component Diagram Layout Engine
 //!
-//! Алгоритм layout для диаграмм компонентов.
+//! Алгоритм layout для диаграмм компонентов: компоненты — узлы,
+//! пакеты — кластеры общего [`GraphLayout`].
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use plantuml_ast::component::{Component, ComponentDiagram, ComponentType, Connection};
-use plantuml_model::{Point, Rect};
+use plantuml_ast::component::{
+    Component, ComponentDiagram, ComponentPackage, ComponentType, Connection,
+};
+use plantuml_ast::common::Direction;
+use plantuml_ast::SpriteRegistry;
+use plantuml_model::{Rect, Size};
 
 use super::config::ComponentLayoutConfig;
-use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+use crate::graph::{GraphCluster, GraphEdge, GraphLayout, RoutedEdge, SugiyamaConfig};
+use crate::text::{EstimatedMetrics, TextMetrics};
+use crate::{sprites, EdgeType, ElementType, LayoutElement, LayoutResult};
+
+/// Кегль подписей компонентов (как у темы по умолчанию)
+const LABEL_FONT_SIZE: f64 = 14.0;
+
+/// Кегль текста легенды
+const LEGEND_FONT_SIZE: f64 = 12.0;
+
+/// Отступ текста легенды от рамки
+const LEGEND_PADDING: f64 = 8.0;
 
 /// Layout engine для component diagrams
 pub struct ComponentLayoutEngine {
     config: ComponentLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl ComponentLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: ComponentLayoutConfig::default(),
-        }
+        Self::with_config(ComponentLayoutConfig::default())
     }
 
     /// Создаёт engine с заданной конфигурацией
     pub fn with_config(config: ComponentLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics::default()),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &ComponentDiagram) -> LayoutResult {
-        let mut elements = Vec::new();
-        let mut component_positions: HashMap<String, Rect> = HashMap::new();
-
-        // Сначала располагаем компоненты в grid-layout
-        let components: Vec<&Component> = diagram.components.iter().collect();
-        let num_cols = ((components.len() as f64).sqrt().ceil() as usize).max(1);
-
-        for (i, comp) in components.iter().enumerate() {
-            let row = i / num_cols;
-            let col = i % num_cols;
-
-            let x = self.config.margin
-                + col as f64 * (self.config.component_width + self.config.horizontal_spacing);
-            let y = self.config.margin
-                + row as f64 * (self.config.component_height + self.config.vertical_spacing);
-
-            let (elem, bounds) = self.create_component_element(comp, x, y);
-            
-            // Сохраняем позицию по имени и алиасу
-            component_positions.insert(comp.name.clone(), bounds.clone());
-            if let Some(alias) = &comp.alias {
-                component_positions.insert(alias.clone(), bounds.clone());
-            }
-            
-            elements.push(elem);
+        let config = SugiyamaConfig::default()
+            .with_spacing(self.config.horizontal_spacing, self.config.vertical_spacing)
+            .with_margin(self.config.margin)
+            .with_line_type(self.config.line_type);
+        let mut graph = GraphLayout::new(config);
+        let mut components: Vec<&Component> = Vec::new();
+        let mut packages: Vec<&ComponentPackage> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let sprites = &diagram.metadata.sprites;
+
+        for comp in &diagram.components {
+            self.add_component(&mut graph, comp, None, sprites, &mut components, &mut index);
         }
-
-        // Располагаем пакеты
-        let mut package_y = self.config.margin
-            + ((components.len() / num_cols.max(1) + 1) as f64)
-                * (self.config.component_height + self.config.vertical_spacing);
-
         for pkg in &diagram.packages {
-            let (pkg_elements, pkg_bounds, inner_positions) =
-                self.layout_package(pkg, self.config.margin, package_y);
+            self.add_package(
+                &mut graph,
+                pkg,
+                None,
+                sprites,
+                &mut components,
+                &mut packages,
+                &mut index,
+            );
+        }
 
-            for elem in pkg_elements {
-                elements.push(elem);
+        // Связи между известными компонентами; скрытые только влияют на раскладку
+        let mut connections: Vec<(&Connection, usize)> = Vec::new();
+        for conn in &diagram.connections {
+            let (Some(&from), Some(&to)) = (index.get(&conn.from), index.get(&conn.to)) else {
+                continue;
+            };
+            if conn.hidden {
+                match conn.direction {
+                    Some(Direction::LeftToRight) => graph.add_same_rank(from, to),
+                    Some(Direction::RightToLeft) => graph.add_same_rank(to, from),
+                    Some(Direction::BottomToTop) => {
+                        graph.add_edge(GraphEdge::new(to, from));
+                    }
+                    _ => {
+                        graph.add_edge(GraphEdge::new(from, to));
+                    }
+                }
+                continue;
             }
-
-            // Добавляем позиции вложенных компонентов
-            for (name, rect) in inner_positions {
-                component_positions.insert(name, rect);
+            let mut edge = GraphEdge::new(from, to);
+            if let Some(label) = &conn.label {
+                edge = edge.with_label(self.metrics.text_size(label, LABEL_FONT_SIZE));
             }
+            connections.push((conn, graph.add_edge(edge)));
+        }
 
-            package_y = pkg_bounds.y + pkg_bounds.height + self.config.vertical_spacing;
+        let placed = graph.layout();
+        let mut elements = Vec::new();
+
+        // Пакеты первыми (под компонентами)
+        for (pkg, bounds) in packages.iter().zip(&placed.clusters) {
+            elements.push(LayoutElement {
+                id: format!("package_{}", pkg.name.replace(' ', "_")),
+                bounds: *bounds,
+                text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Group {
+                    label: Some(pkg.name.clone()),
+                    children: Vec::new(),
+                },
+            });
         }
 
-        // Создаём связи
-        for conn in &diagram.connections {
-            if let Some(edge) = self.create_connection_element(conn, &component_positions) {
-                elements.push(edge);
+        for (comp, bounds) in components.iter().zip(&placed.nodes) {
+            let mut element = self.create_component_element(comp, bounds.x, bounds.y);
+            // Размер узла может быть увеличен под спрайты подписи
+            element.bounds = *bounds;
+            if let Some(sprite) = comp.stereotype.as_ref().and_then(|s| s.sprite()) {
+                element = element.with_property("sprite", sprite);
             }
+            elements.push(element);
+        }
+
+        for (conn, edge) in connections {
+            elements.push(self.create_connection_element(conn, &placed.edges[edge]));
         }
 
         // Вычисляем bounds
@@ -92,6 +142,12 @@ impl ComponentLayoutEngine {
         };
         result.calculate_bounds();
 
+        if let Some(legend) = &diagram.metadata.legend {
+            let elements = self.create_legend_elements(legend, &result.bounds);
+            result.elements.extend(elements);
+            result.calculate_bounds();
+        }
+
         // Добавляем отступы
         result.bounds.width += self.config.margin * 2.0;
         result.bounds.height += self.config.margin * 2.0;
@@ -99,11 +155,99 @@ impl ComponentLayoutEngine {
         result
     }
 
-    /// Создаёт элемент компонента
-    fn create_component_element(&self, comp: &Component, x: f64, y: f64) -> (LayoutElement, Rect) {
-        let bounds = Rect::new(x, y, self.config.component_width, self.config.component_height);
+    /// Добавляет компонент в граф и запоминает его индекс по имени и алиасу
+    fn add_component<'a>(
+        &self,
+        graph: &mut GraphLayout,
+        comp: &'a Component,
+        cluster: Option<usize>,
+        sprites: &SpriteRegistry,
+        components: &mut Vec<&'a Component>,
+        index: &mut HashMap<String, usize>,
+    ) {
+        let size = self.component_size(comp, sprites);
+        let node = match cluster {
+            Some(cluster) => graph.add_node_in(size, cluster),
+            None => graph.add_node(size),
+        };
+        components.push(comp);
+
+        index.insert(comp.name.clone(), node);
+        if let Some(alias) = &comp.alias {
+            index.insert(alias.clone(), node);
+        }
+    }
+
+    /// Добавляет пакет (кластер) со всем содержимым
+    #[allow(clippy::too_many_arguments)]
+    fn add_package<'a>(
+        &self,
+        graph: &mut GraphLayout,
+        pkg: &'a ComponentPackage,
+        parent: Option<usize>,
+        sprites: &SpriteRegistry,
+        components: &mut Vec<&'a Component>,
+        packages: &mut Vec<&'a ComponentPackage>,
+        index: &mut HashMap<String, usize>,
+    ) {
+        let cluster = graph.add_cluster(
+            GraphCluster::new(parent)
+                .with_padding(self.config.package_padding)
+                .with_header(self.config.package_header_height)
+                .with_min_size(Size::new(150.0, 100.0)),
+        );
+        packages.push(pkg);
+
+        for comp in &pkg.components {
+            self.add_component(graph, comp, Some(cluster), sprites, components, index);
+        }
+        for nested in &pkg.packages {
+            self.add_package(
+                graph,
+                nested,
+                Some(cluster),
+                sprites,
+                components,
+                packages,
+                index,
+            );
+        }
+    }
+
+    /// Размер элемента компонента в зависимости от типа
+    ///
+    /// Длинная подпись (в том числе со спрайтами `<$name>`) увеличивает
+    /// элемент до размера блока подписи с отступами; подпись актора без
+    /// спрайтов рисуется под фигурой и размер не меняет.
+    fn component_size(&self, comp: &Component, sprites: &SpriteRegistry) -> Size {
+        let (w, h) = (self.config.component_width, self.config.component_height);
+        let size = match comp.component_type {
+            ComponentType::Cloud => Size::new(w * 1.2, h),
+            ComponentType::Interface => {
+                let d = self.config.interface_radius * 2.0;
+                return Size::new(d, d);
+            }
+            ComponentType::Actor => Size::new(w * 0.6, h),
+            _ => Size::new(w, h),
+        };
+        if comp.component_type == ComponentType::Actor && !sprites.is_used_in(&comp.name) {
+            return size;
+        }
+        let label = sprites::label_size(
+            &comp.name,
+            sprites,
+            LABEL_FONT_SIZE,
+            self.metrics.as_ref(),
+        );
+        Size::new(
+            size.width.max(label.width + 2.0 * self.config.package_padding),
+            size.height.max(label.height + self.config.package_padding),
+        )
+    }
 
-        let elem = match comp.component_type {
+    /// Создаёт элемент компонента
+    fn create_component_element(&self, comp: &Component, x: f64, y: f64) -> LayoutElement {
+        match comp.component_type {
             ComponentType::Database => self.create_database_element(&comp.name, x, y),
             ComponentType::Cloud => self.create_cloud_element(&comp.name, x, y),
             ComponentType::Interface => self.create_interface_element(&comp.name, x, y),
@@ -112,9 +256,7 @@ impl ComponentLayoutEngine {
             ComponentType::Folder => self.create_folder_element(&comp.name, x, y),
             ComponentType::Actor => self.create_actor_element(&comp.name, x, y),
             _ => self.create_standard_component_element(&comp.name, x, y),
-        };
-
-        (elem, bounds)
+        }
     }
 
     /// Создаёт стандартный компонент
@@ -218,136 +360,71 @@ impl ComponentLayoutEngine {
         }
     }
 
-    /// Располагает пакет и возвращает элементы, bounds и позиции вложенных компонентов
-    fn layout_package(
-        &self,
-        pkg: &plantuml_ast::component::ComponentPackage,
-        x: f64,
-        y: f64,
-    ) -> (Vec<LayoutElement>, Rect, HashMap<String, Rect>) {
-        let mut elements = Vec::new();
-        let mut positions = HashMap::new();
-
-        // Располагаем вложенные компоненты
-        let num_cols = ((pkg.components.len() as f64).sqrt().ceil() as usize).max(1);
-        let mut max_row = 0;
-        let mut max_col = 0;
-
-        for (i, comp) in pkg.components.iter().enumerate() {
-            let row = i / num_cols;
-            let col = i % num_cols;
-            max_row = max_row.max(row);
-            max_col = max_col.max(col);
-
-            let comp_x = x + self.config.package_padding
-                + col as f64 * (self.config.component_width + self.config.horizontal_spacing / 2.0);
-            let comp_y = y + self.config.package_header_height + self.config.package_padding
-                + row as f64 * (self.config.component_height + self.config.vertical_spacing / 2.0);
-
-            let (elem, bounds) = self.create_component_element(comp, comp_x, comp_y);
-            positions.insert(comp.name.clone(), bounds.clone());
-            if let Some(alias) = &comp.alias {
-                positions.insert(alias.clone(), bounds);
-            }
-            elements.push(elem);
-        }
-
-        // Вычисляем размер пакета
-        let inner_width = (max_col + 1) as f64
-            * (self.config.component_width + self.config.horizontal_spacing / 2.0)
-            - self.config.horizontal_spacing / 2.0;
-        let inner_height = (max_row + 1) as f64
-            * (self.config.component_height + self.config.vertical_spacing / 2.0)
-            - self.config.vertical_spacing / 2.0;
-
-        let pkg_width = inner_width + self.config.package_padding * 2.0;
-        let pkg_height =
-            inner_height + self.config.package_header_height + self.config.package_padding * 2.0;
-
-        let pkg_bounds = Rect::new(x, y, pkg_width.max(150.0), pkg_height.max(100.0));
-
-        // Создаём элемент пакета (group)
-        let pkg_elem = LayoutElement {
-            id: format!("package_{}", pkg.name.replace(' ', "_")),
-            bounds: pkg_bounds.clone(),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Group {
-                label: Some(pkg.name.clone()),
-                children: Vec::new(),
+    /// Создаёт легенду: рамку и строки текста под диаграммой, по правому краю
+    fn create_legend_elements(&self, legend: &str, diagram: &Rect) -> Vec<LayoutElement> {
+        let lines: Vec<Size> = legend
+            .lines()
+            .map(|line| self.metrics.text_size(line, LEGEND_FONT_SIZE))
+            .collect();
+        let line_height = lines
+            .iter()
+            .map(|s| s.height)
+            .fold(LEGEND_FONT_SIZE, f64::max);
+        let width = lines.iter().map(|s| s.width).fold(0.0, f64::max) + LEGEND_PADDING * 2.0;
+        let height = line_height * lines.len() as f64 + LEGEND_PADDING * 2.0;
+        let x = (diagram.x + diagram.width - width).max(diagram.x);
+        let y = diagram.y + diagram.height + self.config.vertical_spacing / 2.0;
+
+        let mut elements = vec![LayoutElement {
+            id: "legend".to_string(),
+            bounds: Rect::new(x, y, width, height),
+            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
+                label: String::new(),
+                corner_radius: 0.0,
             },
-        };
-
-        // Вставляем пакет первым (под компонентами)
-        elements.insert(0, pkg_elem);
-
-        (elements, pkg_bounds, positions)
+        }];
+        for (i, (line, size)) in legend.lines().zip(&lines).enumerate() {
+            elements.push(LayoutElement {
+                id: format!("legend_line_{}", i),
+                bounds: Rect::new(
+                    x + LEGEND_PADDING,
+                    y + LEGEND_PADDING + line_height * i as f64,
+                    size.width,
+                    line_height,
+                ),
+                text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
+                    text: line.to_string(),
+                    font_size: LEGEND_FONT_SIZE,
+                },
+            });
+        }
+        elements
     }
 
     /// Создаёт элемент связи
-    fn create_connection_element(
-        &self,
-        conn: &Connection,
-        positions: &HashMap<String, Rect>,
-    ) -> Option<LayoutElement> {
-        let from_rect = positions.get(&conn.from)?;
-        let to_rect = positions.get(&conn.to)?;
-
-        let (start, end) = self.calculate_connection_points(from_rect, to_rect);
+    fn create_connection_element(&self, conn: &Connection, route: &RoutedEdge) -> LayoutElement {
+        let mut bounds = route.bounds();
+        bounds.width = bounds.width.max(1.0);
+        bounds.height = bounds.height.max(1.0);
 
-        let min_x = start.x.min(end.x);
-        let min_y = start.y.min(end.y);
-        let max_x = start.x.max(end.x);
-        let max_y = start.y.max(end.y);
-
-        Some(LayoutElement {
+        LayoutElement {
             id: format!(
                 "conn_{}_{}",
                 conn.from.replace(' ', "_"),
                 conn.to.replace(' ', "_")
             ),
-            bounds: Rect::new(min_x, min_y, (max_x - min_x).max(1.0), (max_y - min_y).max(1.0)),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
-                points: vec![start, end],
+                points: route.points.clone(),
                 label: conn.label.clone(),
                 arrow_start: false,
                 arrow_end: true,
                 dashed: conn.dashed,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
+                curve: route.curve.clone(),
+                label_positions: route.label_positions(),
             },
-        })
-    }
-
-    /// Вычисляет точки соединения для связи
-    fn calculate_connection_points(&self, from: &Rect, to: &Rect) -> (Point, Point) {
-        let from_center_x = from.x + from.width / 2.0;
-        let from_center_y = from.y + from.height / 2.0;
-        let to_center_x = to.x + to.width / 2.0;
-        let to_center_y = to.y + to.height / 2.0;
-
-        let dx = to_center_x - from_center_x;
-        let dy = to_center_y - from_center_y;
-
-        let start;
-        let end;
-
-        if dy.abs() > dx.abs() {
-            if dy > 0.0 {
-                start = Point::new(from_center_x, from.y + from.height);
-                end = Point::new(to_center_x, to.y);
-            } else {
-                start = Point::new(from_center_x, from.y);
-                end = Point::new(to_center_x, to.y + to.height);
-            }
-        } else {
-            if dx > 0.0 {
-                start = Point::new(from.x + from.width, from_center_y);
-                end = Point::new(to.x, to_center_y);
-            } else {
-                start = Point::new(from.x, from_center_y);
-                end = Point::new(to.x + to.width, to_center_y);
-            }
         }
-
-        (start, end)
     }
 }
 
@@ -392,6 +469,37 @@ mod tests {
         assert!(result.elements.len() >= 3);
     }
 
+    #[test]
+    fn test_connection_into_nested_package() {
+        use plantuml_ast::component::ComponentPackage;
+
+        let mut diagram = ComponentDiagram::new();
+        let mut inner = ComponentPackage::new("Services");
+        inner.components.push(Component::new("API"));
+        let mut outer = ComponentPackage::new("Backend");
+        outer.packages.push(inner);
+        diagram.packages.push(outer);
+        diagram.components.push(Component::new("Client"));
+        diagram.connections.push(Connection::new("Client", "API"));
+
+        let engine = ComponentLayoutEngine::new();
+        let result = engine.layout(&diagram);
+
+        let find = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+        let (outer, inner, api) = (find("package_Backend"), find("package_Services"), find("component_API"));
+        assert!(inner.x >= outer.x && inner.y >= outer.y);
+        assert!(api.x >= inner.x && api.y >= inner.y);
+        assert!(api.x + api.width <= inner.x + inner.width);
+
+        // Связь заканчивается на границе компонента, а не пакета
+        let conn = result.elements.iter().find(|e| e.id == "conn_Client_API").unwrap();
+        let ElementType::Edge { points, .. } = &conn.element_type else {
+            panic!("expected edge");
+        };
+        let end = points[points.len() - 1];
+        assert!((end.y - api.y).abs() < 1e-6);
+    }
+
     #[test]
     fn test_layout_various_types() {
         let mut diagram = ComponentDiagram::new();
@@ -405,4 +513,60 @@ mod tests {
 
         assert_eq!(result.elements.len(), 4);
     }
+
+    #[test]
+    fn test_hidden_connections_place_without_drawing() {
+        let mut diagram = ComponentDiagram::new();
+        for name in ["A", "B", "C"] {
+            diagram.components.push(Component::new(name));
+        }
+        diagram.connections.push(Connection::new("A", "B").hidden());
+        diagram
+            .connections
+            .push(Connection::new("B", "C").hidden().with_direction(Direction::LeftToRight));
+
+        let result = ComponentLayoutEngine::new().layout(&diagram);
+        assert!(!result
+            .elements
+            .iter()
+            .any(|e| matches!(e.element_type, ElementType::Edge { .. })));
+
+        let find = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+        let (a, b, c) = (find("component_A"), find("component_B"), find("component_C"));
+        assert!(b.y > a.y + a.height);
+        assert_eq!(b.y, c.y);
+        assert!(c.x > b.x + b.width);
+    }
+
+    #[test]
+    fn test_layout_legend_below_diagram() {
+        let mut diagram = ComponentDiagram::new();
+        diagram.components.push(Component::new("API"));
+        diagram.metadata.legend = Some("Легенда\nвторая строка".to_string());
+
+        let result = ComponentLayoutEngine::new().layout(&diagram);
+        let find = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+        let (api, frame) = (find("component_API"), find("legend"));
+        assert!(frame.y > api.y + api.height);
+        let second = find("legend_line_1");
+        assert!(second.y + second.height <= frame.y + frame.height);
+        assert!(result.bounds.height >= frame.y + frame.height);
+    }
+
+    #[test]
+    fn test_layout_sprite_label() {
+        let mut diagram = ComponentDiagram::new();
+        diagram
+            .metadata
+            .sprites
+            .insert("server", plantuml_ast::Sprite::gray(48, 80, 16, Vec::new()).unwrap());
+        diagram.components.push(Component::new("<$server>\\nWeb"));
+        diagram.components.push(Component::new("<$unknown> DB"));
+
+        let result = ComponentLayoutEngine::new().layout(&diagram);
+        // Спрайт выше стандартной высоты компонента увеличивает узел
+        let web = &result.elements[0];
+        assert!(web.bounds.height >= 80.0 + 17.0);
+        assert_eq!(result.elements[1].bounds.height, 60.0);
+    }
 }
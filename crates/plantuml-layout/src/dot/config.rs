@@ -0,0 +1,48 @@
+//! Конфигурация layout для DOT графов
+
+use crate::graph::LineType;
+
+/// Конфигурация DOT Layout Engine
+#[derive(Debug, Clone)]
+pub struct DotLayoutConfig {
+    /// Отступ от края диаграммы
+    pub margin: f64,
+    /// Минимальная ширина узла (0.75 дюйма в Graphviz)
+    pub node_width: f64,
+    /// Минимальная высота узла (0.5 дюйма в Graphviz)
+    pub node_height: f64,
+    /// Отступ от текста до границы узла
+    pub node_padding: f64,
+    /// Диаметр узла `shape=point`
+    pub point_size: f64,
+    /// Расстояние между узлами одного ранга (`nodesep`)
+    pub node_spacing: f64,
+    /// Расстояние между рангами (`ranksep`)
+    pub rank_spacing: f64,
+    /// Отступ внутри кластера
+    pub cluster_padding: f64,
+    /// Высота заголовка кластера
+    pub cluster_header_height: f64,
+    /// Размер шрифта подписей
+    pub font_size: f64,
+    /// Форма линий рёбер, если граф не задаёт `splines`
+    pub line_type: LineType,
+}
+
+impl Default for DotLayoutConfig {
+    fn default() -> Self {
+        Self {
+            margin: 20.0,
+            node_width: 54.0,
+            node_height: 36.0,
+            node_padding: 12.0,
+            point_size: 8.0,
+            node_spacing: 25.0,
+            rank_spacing: 40.0,
+            cluster_padding: 15.0,
+            cluster_header_height: 24.0,
+            font_size: 14.0,
+            line_type: LineType::Spline,
+        }
+    }
+}
@@ -0,0 +1,650 @@
+//! DOT Graph Layout Engine
+//!
+//! Узлы DOT — узлы общего [`GraphLayout`], кластеры (`subgraph cluster*`) —
+//! его кластеры. Учитываются `rankdir`, `splines`, `nodesep`, `ranksep`,
+//! порты рёбер (`a:s -> b:n`) и подписи `label`/`taillabel`/`headlabel`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use plantuml_ast::dot::{DotAttributes, DotDiagram, DotEdge, DotNode, DotShape, DotSubgraph};
+use plantuml_model::{Point, Rect, Size};
+
+use super::config::DotLayoutConfig;
+use crate::graph::{
+    self, GraphCluster, GraphEdge, GraphLayout, LineType, Port, RankDir, RoutedEdge, SugiyamaConfig,
+};
+use crate::text::TextMetrics;
+use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+
+/// Точек в дюйме (единица `nodesep`, `ranksep`, `width`, `height` в DOT)
+const POINTS_PER_INCH: f64 = 72.0;
+
+/// Layout engine для DOT графов
+pub struct DotLayoutEngine {
+    config: DotLayoutConfig,
+    metrics: Option<Arc<dyn TextMetrics>>,
+}
+
+impl DotLayoutEngine {
+    /// Создаёт новый engine с конфигурацией по умолчанию
+    pub fn new() -> Self {
+        Self::with_config(DotLayoutConfig::default())
+    }
+
+    /// Создаёт engine с заданной конфигурацией
+    pub fn with_config(config: DotLayoutConfig) -> Self {
+        Self {
+            config,
+            metrics: None,
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Выполняет layout графа
+    pub fn layout(&self, diagram: &DotDiagram) -> LayoutResult {
+        let attributes = &diagram.attributes;
+        let line_type = attributes
+            .get("splines")
+            .map(|s| splines_line_type(s))
+            .unwrap_or(self.config.line_type);
+        let node_spacing = inches(attributes.get("nodesep")).unwrap_or(self.config.node_spacing);
+        let rank_spacing = inches(attributes.get("ranksep")).unwrap_or(self.config.rank_spacing);
+
+        let config = SugiyamaConfig::default()
+            .with_spacing(node_spacing, rank_spacing)
+            .with_margin(self.config.margin)
+            .with_rank_dir(RankDir::from(diagram.direction()))
+            .with_line_type(line_type);
+        let mut graph = GraphLayout::new(config);
+
+        // Кластеры; узел попадает в самый вложенный кластер, где он упомянут
+        let mut clusters: Vec<&DotSubgraph> = Vec::new();
+        let mut membership: HashMap<&str, (usize, usize)> = HashMap::new();
+        for subgraph in &diagram.subgraphs {
+            self.add_subgraph(
+                &mut graph,
+                subgraph,
+                None,
+                0,
+                &mut clusters,
+                &mut membership,
+            );
+        }
+
+        // Узлы
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for node in &diagram.nodes {
+            let size = self.node_size(node);
+            let id = match membership.get(node.id.as_str()) {
+                Some(&(_, cluster)) => graph.add_node_in(size, cluster),
+                None => graph.add_node(size),
+            };
+            index.insert(node.id.as_str(), id);
+        }
+
+        // Рёбра
+        let mut edges: Vec<&DotEdge> = Vec::new();
+        for edge in &diagram.edges {
+            let (Some(&from), Some(&to)) =
+                (index.get(edge.from.as_str()), index.get(edge.to.as_str()))
+            else {
+                continue;
+            };
+            let mut graph_edge = GraphEdge::new(from, to).with_ports(
+                port(edge.attributes.get("tailport")),
+                port(edge.attributes.get("headport")),
+            );
+            if let Some(label) = edge.label() {
+                graph_edge = graph_edge.with_label(self.label_size(label));
+            }
+            let tail = edge.attributes.get("taillabel");
+            let head = edge.attributes.get("headlabel");
+            if tail.is_some() || head.is_some() {
+                graph_edge = graph_edge.with_end_labels(
+                    tail.map(|l| self.label_size(l)),
+                    head.map(|l| self.label_size(l)),
+                );
+            }
+            graph.add_edge(graph_edge);
+            edges.push(edge);
+        }
+
+        let placed = graph.layout();
+        let mut elements = Vec::new();
+
+        for (i, (subgraph, bounds)) in clusters.iter().zip(&placed.clusters).enumerate() {
+            let name = subgraph.name.clone().unwrap_or_else(|| i.to_string());
+            elements.push(LayoutElement {
+                id: format!("cluster_{}", sanitize(&name)),
+                bounds: *bounds,
+                text: None,
+                properties: colors(&subgraph.attributes),
+                element_type: ElementType::Group {
+                    label: subgraph.label().map(str::to_string),
+                    children: Vec::new(),
+                },
+            });
+        }
+
+        // Невидимые узлы и рёбра (`style=invis`) участвуют в layout, но не рисуются
+        for node in &diagram.nodes {
+            if node.has_style("invis") {
+                continue;
+            }
+            let bounds = placed.nodes[index[node.id.as_str()]];
+            elements.extend(self.create_node_elements(node, bounds));
+        }
+        for (i, (edge, route)) in edges.into_iter().zip(&placed.edges).enumerate() {
+            if edge.has_style("invis") {
+                continue;
+            }
+            elements.push(self.create_edge_element(i, edge, route, diagram.directed));
+        }
+
+        // Подпись графа — по центру под диаграммой
+        if let Some(label) = attributes.get("label") {
+            let center = Point::new(
+                placed.width / 2.0,
+                placed.height + self.config.font_size / 2.0,
+            );
+            elements.push(self.create_text_element("graph_label".to_string(), label, center));
+        }
+
+        let mut result = LayoutResult {
+            elements,
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+        };
+        result.calculate_bounds();
+
+        result.bounds.width += self.config.margin;
+        result.bounds.height += self.config.margin;
+
+        result
+    }
+
+    /// Добавляет кластеры подграфа (рекурсивно) и запоминает принадлежность узлов
+    fn add_subgraph<'a>(
+        &self,
+        graph: &mut GraphLayout,
+        subgraph: &'a DotSubgraph,
+        parent: Option<usize>,
+        depth: usize,
+        clusters: &mut Vec<&'a DotSubgraph>,
+        membership: &mut HashMap<&'a str, (usize, usize)>,
+    ) {
+        // Обычный подграф (`{ rank=same; a b }`) не рисуется и не создаёт кластер
+        let (cluster, depth) = if subgraph.is_cluster() {
+            let (header, min_width) = match subgraph.label() {
+                Some(label) => (
+                    self.config.cluster_header_height,
+                    self.label_size(label).width + self.config.cluster_padding * 2.0,
+                ),
+                None => (0.0, 0.0),
+            };
+            let cluster = graph.add_cluster(
+                GraphCluster::new(parent)
+                    .with_padding(self.config.cluster_padding)
+                    .with_header(header)
+                    .with_min_size(Size::new(min_width, 0.0)),
+            );
+            clusters.push(subgraph);
+            (Some(cluster), depth + 1)
+        } else {
+            (parent, depth)
+        };
+
+        if let Some(cluster) = cluster {
+            for id in &subgraph.nodes {
+                let deeper = membership
+                    .get(id.as_str())
+                    .map_or(true, |&(known, _)| depth > known);
+                if deeper {
+                    membership.insert(id.as_str(), (depth, cluster));
+                }
+            }
+        }
+
+        for child in &subgraph.subgraphs {
+            self.add_subgraph(graph, child, cluster, depth, clusters, membership);
+        }
+    }
+
+    /// Размер узла по форме и тексту (`width`/`height` задают минимум в дюймах)
+    fn node_size(&self, node: &DotNode) -> Size {
+        let text = self.label_size(&node.label());
+        let padding = self.config.node_padding;
+        let (min_width, min_height) = (
+            inches(node.attributes.get("width")).unwrap_or(self.config.node_width),
+            inches(node.attributes.get("height")).unwrap_or(self.config.node_height),
+        );
+        let fit = |scale: f64| {
+            Size::new(
+                ((text.width + padding * 2.0) * scale).max(min_width),
+                ((text.height + padding) * scale).max(min_height),
+            )
+        };
+
+        match node.shape() {
+            DotShape::Point => Size::new(self.config.point_size, self.config.point_size),
+            DotShape::PlainText => Size::new(text.width + padding, text.height + padding / 2.0),
+            DotShape::Box => fit(1.0),
+            // Текст вписан в эллипс/ромб, поэтому фигура больше прямоугольника текста
+            DotShape::Ellipse => fit(1.3),
+            DotShape::Diamond => fit(1.6),
+            DotShape::Circle => {
+                let size = fit(1.3);
+                let diameter = size.width.max(size.height);
+                Size::new(diameter, diameter)
+            }
+        }
+    }
+
+    /// Размер подписи: по метрикам шрифтов, если они заданы, иначе оценка
+    fn label_size(&self, text: &str) -> Size {
+        match &self.metrics {
+            Some(metrics) => metrics.text_size(text, self.config.font_size),
+            None => graph::label_size(text),
+        }
+    }
+
+    /// Создаёт элементы узла (фигура и, для ромба и текста, подпись)
+    fn create_node_elements(&self, node: &DotNode, bounds: Rect) -> Vec<LayoutElement> {
+        let id = format!("node_{}", sanitize(&node.id));
+        let label = node.label();
+        let properties = colors(&node.attributes);
+
+        let element_type = match node.shape() {
+            DotShape::Box => ElementType::Rectangle {
+                label,
+                corner_radius: if node.has_style("rounded") { 8.0 } else { 0.0 },
+            },
+            DotShape::Ellipse | DotShape::Circle => ElementType::Ellipse { label: Some(label) },
+            DotShape::Point => ElementType::Ellipse { label: None },
+            DotShape::PlainText => {
+                let mut text = self.create_text_element(id, &label, bounds.center());
+                text.properties = properties;
+                return vec![text];
+            }
+            DotShape::Diamond => {
+                let center = bounds.center();
+                let mut properties = properties;
+                properties.insert(
+                    "path".to_string(),
+                    format!(
+                        "M{},{} L{},{} L{},{} L{},{} Z",
+                        center.x,
+                        bounds.y,
+                        bounds.x + bounds.width,
+                        center.y,
+                        center.x,
+                        bounds.y + bounds.height,
+                        bounds.x,
+                        center.y
+                    ),
+                );
+                let shape = LayoutElement {
+                    id: id.clone(),
+                    bounds,
+                    text: None,
+                    properties,
+                    element_type: ElementType::Path,
+                };
+                let text = self.create_text_element(format!("{}_label", id), &label, center);
+                return vec![shape, text];
+            }
+        };
+
+        vec![LayoutElement {
+            id,
+            bounds,
+            text: None,
+            properties,
+            element_type,
+        }]
+    }
+
+    /// Создаёт однострочный текст с центром в `center`
+    fn create_text_element(&self, id: String, text: &str, center: Point) -> LayoutElement {
+        let text = text.replace("\\n", " ");
+        let size = self.label_size(&text);
+        let font_size = self.config.font_size;
+        LayoutElement {
+            id,
+            // Текст рендерится от базовой линии bounds.y + font_size
+            bounds: Rect::new(
+                center.x - size.width / 2.0,
+                center.y - font_size * 0.65,
+                size.width,
+                font_size,
+            ),
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Text { text, font_size },
+        }
+    }
+
+    /// Создаёт элемент ребра
+    fn create_edge_element(
+        &self,
+        index: usize,
+        edge: &DotEdge,
+        route: &RoutedEdge,
+        directed: bool,
+    ) -> LayoutElement {
+        let mut bounds = route.bounds();
+        bounds.width = bounds.width.max(1.0);
+        bounds.height = bounds.height.max(1.0);
+
+        let (arrow_start, arrow_end) = arrows(&edge.attributes, directed);
+
+        LayoutElement {
+            id: format!(
+                "edge_{}_{}_{}",
+                index,
+                sanitize(&edge.from),
+                sanitize(&edge.to)
+            ),
+            bounds,
+            text: None,
+            properties: colors(&edge.attributes),
+            element_type: ElementType::Edge {
+                points: route.points.clone(),
+                label: edge.label().map(str::to_string),
+                arrow_start,
+                arrow_end,
+                dashed: edge.has_style("dashed") || edge.has_style("dotted"),
+                edge_type: if arrow_start || arrow_end {
+                    EdgeType::Association
+                } else {
+                    EdgeType::Link
+                },
+                from_cardinality: edge.attributes.get("taillabel").cloned(),
+                to_cardinality: edge.attributes.get("headlabel").cloned(),
+                curve: route.curve.clone(),
+                label_positions: route.label_positions(),
+            },
+        }
+    }
+}
+
+impl Default for DotLayoutEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Форма линий по атрибуту графа `splines`
+fn splines_line_type(value: &str) -> LineType {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "ortho" => LineType::Ortho,
+        "polyline" | "line" | "false" | "none" => LineType::Polyline,
+        _ => LineType::Spline,
+    }
+}
+
+/// Значение в дюймах, переведённое в пиксели
+fn inches(value: Option<&String>) -> Option<f64> {
+    value
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| *v > 0.0)
+        .map(|v| v * POINTS_PER_INCH)
+}
+
+/// Сторона узла по compass point порта (`n`, `se`, `w`, ...)
+fn port(value: Option<&String>) -> Port {
+    match value.and_then(|v| v.trim().chars().next()) {
+        Some('n') => Port::Top,
+        Some('s') => Port::Bottom,
+        Some('e') => Port::Right,
+        Some('w') => Port::Left,
+        _ => Port::Auto,
+    }
+}
+
+/// Стрелки ребра `(в начале, в конце)` с учётом `dir`, `arrowhead` и `arrowtail`
+fn arrows(attributes: &DotAttributes, directed: bool) -> (bool, bool) {
+    let dir = attributes.get("dir").map(|d| d.to_ascii_lowercase());
+    let (mut start, mut end) = match dir.as_deref() {
+        Some("back") => (true, false),
+        Some("both") => (true, true),
+        Some("none") => (false, false),
+        Some(_) => (false, true),
+        None => (false, directed),
+    };
+    if attributes.get("arrowhead").is_some_and(|a| a == "none") {
+        end = false;
+    }
+    if attributes.get("arrowtail").is_some_and(|a| a == "none") {
+        start = false;
+    }
+    (start, end)
+}
+
+/// Цвета элемента: `color` → `stroke`, `fillcolor`/`bgcolor` → `fill`,
+/// `fontcolor` → `font_color`
+fn colors(attributes: &DotAttributes) -> HashMap<String, String> {
+    // Список цветов `red:blue` — берём первый
+    let first = |name: &str| {
+        attributes
+            .get(name)
+            .and_then(|c| c.split(':').next())
+            .map(str::to_string)
+    };
+
+    let mut properties = HashMap::new();
+    if let Some(color) = first("color") {
+        properties.insert("stroke".to_string(), color);
+    }
+    let filled = has_filled_style(attributes);
+    let fill = first("fillcolor").or_else(|| first("bgcolor")).or_else(|| {
+        if filled {
+            first("color")
+        } else {
+            None
+        }
+    });
+    if let Some(fill) = fill {
+        properties.insert("fill".to_string(), fill);
+    }
+    if let Some(color) = first("fontcolor") {
+        properties.insert("font_color".to_string(), color);
+    }
+    properties
+}
+
+/// Проверяет `style=filled`
+fn has_filled_style(attributes: &DotAttributes) -> bool {
+    attributes
+        .get("style")
+        .is_some_and(|s| s.split(',').any(|part| part.trim() == "filled"))
+}
+
+/// Идентификатор элемента без пробелов и спецсимволов
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(result: &'a LayoutResult, id: &str) -> &'a LayoutElement {
+        result
+            .elements
+            .iter()
+            .find(|e| e.id == id)
+            .unwrap_or_else(|| panic!("элемент {} не найден", id))
+    }
+
+    fn digraph(edges: &[(&str, &str)]) -> DotDiagram {
+        let mut diagram = DotDiagram::new(true);
+        for (from, to) in edges {
+            for id in [from, to] {
+                if diagram.find_node(id).is_none() {
+                    diagram.nodes.push(DotNode::new(*id));
+                }
+            }
+            diagram.edges.push(DotEdge::new(*from, *to));
+        }
+        diagram
+    }
+
+    #[test]
+    fn test_layout_simple() {
+        let diagram = digraph(&[("a", "b"), ("b", "c")]);
+        let result = DotLayoutEngine::new().layout(&diagram);
+
+        // 3 узла + 2 ребра
+        assert_eq!(result.elements.len(), 5);
+        let (a, b) = (
+            find(&result, "node_a").bounds,
+            find(&result, "node_b").bounds,
+        );
+        assert!(a.y + a.height <= b.y);
+
+        match &find(&result, "edge_0_a_b").element_type {
+            ElementType::Edge {
+                arrow_end,
+                arrow_start,
+                ..
+            } => assert!(*arrow_end && !*arrow_start),
+            other => panic!("ожидалось ребро, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_layout_rankdir_lr() {
+        let mut diagram = digraph(&[("a", "b")]);
+        diagram
+            .attributes
+            .insert("rankdir".to_string(), "LR".to_string());
+        let result = DotLayoutEngine::new().layout(&diagram);
+
+        let (a, b) = (
+            find(&result, "node_a").bounds,
+            find(&result, "node_b").bounds,
+        );
+        assert!(a.x + a.width <= b.x);
+    }
+
+    #[test]
+    fn test_layout_shapes() {
+        let mut diagram = DotDiagram::new(false);
+        diagram
+            .nodes
+            .push(DotNode::new("box").with_attribute("shape", "box"));
+        diagram
+            .nodes
+            .push(DotNode::new("choice").with_attribute("shape", "diamond"));
+        diagram
+            .nodes
+            .push(DotNode::new("hidden").with_attribute("style", "invis"));
+        diagram.edges.push(DotEdge::new("box", "choice"));
+
+        let result = DotLayoutEngine::new().layout(&diagram);
+        assert!(matches!(
+            find(&result, "node_box").element_type,
+            ElementType::Rectangle { .. }
+        ));
+        let choice = find(&result, "node_choice");
+        assert!(matches!(choice.element_type, ElementType::Path));
+        assert!(choice.properties.contains_key("path"));
+        assert!(find(&result, "node_choice_label").bounds.width > 0.0);
+        assert!(result.elements.iter().all(|e| e.id != "node_hidden"));
+
+        // Неориентированное ребро без стрелок
+        match &find(&result, "edge_0_box_choice").element_type {
+            ElementType::Edge {
+                arrow_end,
+                edge_type,
+                ..
+            } => {
+                assert!(!*arrow_end);
+                assert_eq!(*edge_type, EdgeType::Link);
+            }
+            other => panic!("ожидалось ребро, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_layout_clusters() {
+        let mut diagram = digraph(&[("ui", "api"), ("api", "db")]);
+        let mut inner = DotSubgraph::new(Some("cluster_inner".to_string()));
+        inner.nodes.push("api".to_string());
+        let mut outer = DotSubgraph::new(Some("cluster_outer".to_string()));
+        outer
+            .attributes
+            .insert("label".to_string(), "Backend".to_string());
+        outer.nodes = vec!["api".to_string(), "db".to_string()];
+        outer.subgraphs.push(inner);
+        diagram.subgraphs.push(outer);
+
+        let result = DotLayoutEngine::new().layout(&diagram);
+        let outer = find(&result, "cluster_cluster_outer").bounds;
+        let inner = find(&result, "cluster_cluster_inner").bounds;
+        let (api, db, ui) = (
+            find(&result, "node_api").bounds,
+            find(&result, "node_db").bounds,
+            find(&result, "node_ui").bounds,
+        );
+
+        let contains = |outer: Rect, inner: Rect| {
+            inner.x >= outer.x
+                && inner.y >= outer.y
+                && inner.x + inner.width <= outer.x + outer.width
+                && inner.y + inner.height <= outer.y + outer.height
+        };
+        assert!(contains(inner, api));
+        assert!(contains(outer, inner));
+        assert!(contains(outer, db));
+        assert!(!contains(outer, ui));
+    }
+
+    #[test]
+    fn test_arrows_and_splines() {
+        let mut attributes = DotAttributes::new();
+        assert_eq!(arrows(&attributes, true), (false, true));
+        assert_eq!(arrows(&attributes, false), (false, false));
+        attributes.insert("dir".to_string(), "both".to_string());
+        attributes.insert("arrowhead".to_string(), "none".to_string());
+        assert_eq!(arrows(&attributes, false), (true, false));
+
+        assert_eq!(splines_line_type("ortho"), LineType::Ortho);
+        assert_eq!(splines_line_type("false"), LineType::Polyline);
+        assert_eq!(splines_line_type("true"), LineType::Spline);
+    }
+
+    #[test]
+    fn test_layout_with_metrics() {
+        use crate::text::EstimatedMetrics;
+
+        let mut diagram = digraph(&[("a", "b")]);
+        diagram.nodes[0]
+            .attributes
+            .insert("label".to_string(), "a rather long node label".to_string());
+
+        let estimated = DotLayoutEngine::new().layout(&diagram);
+        let wide = DotLayoutEngine::new()
+            .with_metrics(Arc::new(EstimatedMetrics {
+                char_width: 1.0,
+                wide_char_width: 1.0,
+            }))
+            .layout(&diagram);
+
+        assert!(find(&wide, "node_a").bounds.width > find(&estimated, "node_a").bounds.width);
+    }
+}
@@ -0,0 +1,10 @@
+//! Layout engine для Graphviz DOT графов
+//!
+//! Узлы и кластеры DOT отображаются в общий [`GraphLayout`](crate::graph::GraphLayout),
+//! поэтому `@startdot` рендерится без установленного Graphviz.
+
+pub mod config;
+pub mod engine;
+
+pub use config::DotLayoutConfig;
+pub use engine::DotLayoutEngine;
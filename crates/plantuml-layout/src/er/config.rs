@@ -1,5 +1,8 @@
 //! Конфигурация layout для ER диаграмм
 
+use crate::graph::LineType;
+use crate::text::FixedMetrics;
+
 /// Конфигурация для ER layout engine
 #[derive(Debug, Clone)]
 pub struct ErLayoutConfig {
@@ -19,10 +22,14 @@ pub struct ErLayoutConfig {
     pub entity_padding: f64,
     /// Размер шрифта
     pub font_size: f64,
+    /// Ширина символа (приблизительно, если не заданы метрики шрифтов)
+    pub char_width: f64,
     /// Цвет фона сущности
     pub entity_bg_color: &'static str,
     /// Цвет заголовка
     pub header_bg_color: &'static str,
+    /// Форма линий рёбер (`skinparam linetype`)
+    pub line_type: LineType,
 }
 
 impl Default for ErLayoutConfig {
@@ -36,8 +43,17 @@ impl Default for ErLayoutConfig {
             vertical_spacing: 60.0,
             entity_padding: 10.0,
             font_size: 13.0,
+            char_width: 9.0,
             entity_bg_color: "#FEFECE",
             header_bg_color: "#E2E2F0",
+            line_type: LineType::Spline,
         }
     }
 }
+
+impl ErLayoutConfig {
+    /// Оценка метрик текста по `char_width`
+    pub fn estimated_metrics(&self) -> FixedMetrics {
+        FixedMetrics::new(self.char_width, self.font_size)
+    }
+}
@@ -1,49 +1,60 @@
 //! Layout engine для ER диаграмм
 //!
 //! Размещает сущности в виде таблиц с атрибутами.
-//! Использует простой grid layout с оптимизацией для связей.
+//! Сущности и связи отображаются в общий [`GraphLayout`].
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use plantuml_ast::er::{ErDiagram, Entity};
-use plantuml_model::{Point, Rect, Size};
+use plantuml_ast::er::{Attribute, ErDiagram, Entity};
+use plantuml_model::{Rect, Size};
 
 use crate::er::config::ErLayoutConfig;
+use crate::graph::{GraphEdge, GraphLayout, GraphLayoutResult, SugiyamaConfig};
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::{EdgeType, ElementType, LayoutConfig, LayoutElement};
 
 /// Layout engine для ER диаграмм
 pub struct ErLayoutEngine {
     config: ErLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl ErLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: ErLayoutConfig::default(),
-        }
+        Self::with_config(ErLayoutConfig::default())
     }
 
-    /// Создаёт engine с указанной конфигурацией
+    /// Создаёт engine с указанной конфигурацией; ширина текста оценивается
+    /// по `char_width`, пока не заданы метрики
     pub fn with_config(config: ErLayoutConfig) -> Self {
-        Self { config }
+        let metrics = Arc::new(config.estimated_metrics());
+        Self { config, metrics }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Вычисляет размер сущности
     fn calculate_entity_size(&self, entity: &Entity) -> Size {
-        let width = self.config.min_entity_width.max(
-            entity.id.name.len() as f64 * 9.0 + self.config.entity_padding * 2.0,
-        );
+        let name_width = self.metrics.text_width(&entity.id.name, self.config.font_size);
+        let width = self
+            .config
+            .min_entity_width
+            .max(name_width + self.config.entity_padding * 2.0);
 
         // Находим максимальную ширину атрибута
         let max_attr_width = entity
             .attributes
             .iter()
             .map(|a| {
-                let type_str = a.data_type.as_deref().unwrap_or("");
-                let stereo_str = a.stereotype.as_deref().map(|s| format!(" <<{}>>", s)).unwrap_or_default();
-                (a.name.len() + type_str.len() + stereo_str.len() + 3) as f64 * 7.5
+                self.metrics
+                    .text_width(&attribute_text(a), self.config.font_size - 1.0)
             })
             .fold(0.0, f64::max);
 
@@ -56,43 +67,38 @@ impl ErLayoutEngine {
         Size::new(final_width, height)
     }
 
-    /// Размещает сущности в grid
-    fn layout_entities(
-        &self,
-        diagram: &ErDiagram,
-        elements: &mut Vec<LayoutElement>,
-    ) -> HashMap<String, Rect> {
-        let mut positions: HashMap<String, Rect> = HashMap::new();
-
-        // Простой grid layout: размещаем сущности в ряд
-        let entities_per_row = 3;
-        let mut x = self.config.padding;
-        let mut y = self.config.padding;
-        let mut row_height = 0.0_f64;
-        let mut col = 0;
-
+    /// Размещает сущности и связи через общий граф
+    ///
+    /// Возвращает результат layout и для каждой связи диаграммы индекс ребра
+    /// графа (`None`, если одна из сущностей не объявлена).
+    fn place(&self, diagram: &ErDiagram) -> (GraphLayoutResult, Vec<Option<usize>>) {
+        let config = SugiyamaConfig::default()
+            .with_spacing(self.config.horizontal_spacing, self.config.vertical_spacing)
+            .with_margin(self.config.padding)
+            .with_line_type(self.config.line_type);
+        let mut graph = GraphLayout::new(config);
+
+        let mut index: HashMap<&str, usize> = HashMap::new();
         for entity in &diagram.entities {
-            let size = self.calculate_entity_size(entity);
-
-            if col >= entities_per_row {
-                col = 0;
-                x = self.config.padding;
-                y += row_height + self.config.vertical_spacing;
-                row_height = 0.0;
-            }
-
-            let bounds = Rect::new(x, y, size.width, size.height);
-            positions.insert(entity.id.name.clone(), bounds);
-
-            // Рисуем сущность
-            self.render_entity(entity, &bounds, elements);
-
-            x += size.width + self.config.horizontal_spacing;
-            row_height = row_height.max(size.height);
-            col += 1;
+            let node = graph.add_node(self.calculate_entity_size(entity));
+            index.insert(&entity.id.name, node);
         }
 
-        positions
+        let edges = diagram
+            .relationships
+            .iter()
+            .map(|rel| {
+                let from = *index.get(rel.from.as_str())?;
+                let to = *index.get(rel.to.as_str())?;
+                let mut edge = GraphEdge::new(from, to);
+                if let Some(label) = &rel.label {
+                    edge = edge.with_label(self.metrics.text_size(label, self.config.font_size));
+                }
+                Some(graph.add_edge(edge))
+            })
+            .collect();
+
+        (graph.layout(), edges)
     }
 
     /// Рендерит одну сущность
@@ -174,19 +180,7 @@ impl ErLayoutEngine {
         // Атрибуты
         let mut attr_y = bounds.y + self.config.entity_header_height;
         for (i, attr) in entity.attributes.iter().enumerate() {
-            let prefix = if attr.is_required { "* " } else { "  " };
-            let type_str = attr
-                .data_type
-                .as_ref()
-                .map(|t| format!(" : {}", t))
-                .unwrap_or_default();
-            let stereo_str = attr
-                .stereotype
-                .as_ref()
-                .map(|s| format!(" <<{}>>", s))
-                .unwrap_or_default();
-
-            let attr_text = format!("{}{}{}{}", prefix, attr.name, type_str, stereo_str);
+            let attr_text = attribute_text(attr);
 
             let attr_element = LayoutElement {
                 id: format!("entity_{}_attr_{}", entity_id, i),
@@ -215,45 +209,33 @@ impl ErLayoutEngine {
     fn render_relationships(
         &self,
         diagram: &ErDiagram,
-        positions: &HashMap<String, Rect>,
+        placed: &GraphLayoutResult,
+        edges: &[Option<usize>],
         elements: &mut Vec<LayoutElement>,
     ) {
-        for (i, rel) in diagram.relationships.iter().enumerate() {
-            let from_bounds = match positions.get(&rel.from) {
-                Some(b) => b,
-                None => continue,
-            };
-            let to_bounds = match positions.get(&rel.to) {
-                Some(b) => b,
-                None => continue,
+        for (i, (rel, edge)) in diagram.relationships.iter().zip(edges).enumerate() {
+            let Some(edge) = edge else {
+                continue;
             };
-
-            // Вычисляем точки соединения
-            let from_center = Point::new(
-                from_bounds.x + from_bounds.width / 2.0,
-                from_bounds.y + from_bounds.height / 2.0,
-            );
-            let to_center = Point::new(
-                to_bounds.x + to_bounds.width / 2.0,
-                to_bounds.y + to_bounds.height / 2.0,
-            );
-
-            // Определяем точки на границах
-            let (from_point, to_point) =
-                self.calculate_connection_points(from_bounds, to_bounds, from_center, to_center);
+            let route = &placed.edges[*edge];
+            if route.points.is_empty() {
+                continue;
+            }
 
             // Линия связи
             let edge = LayoutElement {
                 id: format!("rel_{}", i),
                 element_type: ElementType::Edge {
-                    points: vec![from_point, to_point],
+                    points: route.points.clone(),
                     label: rel.label.clone(),
                     arrow_start: false,
                     arrow_end: false,
                     dashed: false,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                    curve: route.curve.clone(),
+                    label_positions: route.label_positions(),
                 },
-                bounds: Rect::from_points(from_point, to_point),
+                bounds: route.bounds(),
                 text: rel.label.clone(),
                 properties: [
                     ("stroke".to_string(), "#181818".to_string()),
@@ -272,51 +254,22 @@ impl ErLayoutEngine {
             elements.push(edge);
         }
     }
+}
 
-    /// Вычисляет точки соединения на границах прямоугольников
-    fn calculate_connection_points(
-        &self,
-        from: &Rect,
-        to: &Rect,
-        from_center: Point,
-        to_center: Point,
-    ) -> (Point, Point) {
-        // Простой расчёт: соединяем ближайшие стороны
-        let dx = to_center.x - from_center.x;
-        let dy = to_center.y - from_center.y;
-
-        let from_point = if dx.abs() > dy.abs() {
-            // Горизонтальное соединение
-            if dx > 0.0 {
-                Point::new(from.x + from.width, from_center.y)
-            } else {
-                Point::new(from.x, from_center.y)
-            }
-        } else {
-            // Вертикальное соединение
-            if dy > 0.0 {
-                Point::new(from_center.x, from.y + from.height)
-            } else {
-                Point::new(from_center.x, from.y)
-            }
-        };
-
-        let to_point = if dx.abs() > dy.abs() {
-            if dx > 0.0 {
-                Point::new(to.x, to_center.y)
-            } else {
-                Point::new(to.x + to.width, to_center.y)
-            }
-        } else {
-            if dy > 0.0 {
-                Point::new(to_center.x, to.y)
-            } else {
-                Point::new(to_center.x, to.y + to.height)
-            }
-        };
-
-        (from_point, to_point)
-    }
+/// Строка атрибута: `* name : type <<stereotype>>`
+fn attribute_text(attr: &Attribute) -> String {
+    let prefix = if attr.is_required { "* " } else { "  " };
+    let type_str = attr
+        .data_type
+        .as_ref()
+        .map(|t| format!(" : {}", t))
+        .unwrap_or_default();
+    let stereo_str = attr
+        .stereotype
+        .as_ref()
+        .map(|s| format!(" <<{}>>", s))
+        .unwrap_or_default();
+    format!("{}{}{}{}", prefix, attr.name, type_str, stereo_str)
 }
 
 impl Default for ErLayoutEngine {
@@ -331,11 +284,14 @@ impl LayoutEngine for ErLayoutEngine {
     fn layout(&self, diagram: &Self::Input, _config: &LayoutConfig) -> LayoutResult {
         let mut elements = Vec::new();
 
-        // Размещаем сущности
-        let positions = self.layout_entities(diagram, &mut elements);
+        // Размещаем сущности и связи
+        let (placed, edges) = self.place(diagram);
+        for (entity, bounds) in diagram.entities.iter().zip(&placed.nodes) {
+            self.render_entity(entity, bounds, &mut elements);
+        }
 
         // Рисуем связи
-        self.render_relationships(diagram, &positions, &mut elements);
+        self.render_relationships(diagram, &placed, &edges, &mut elements);
 
         let mut result = LayoutResult {
             elements,
@@ -389,4 +345,17 @@ mod tests {
         // Должны быть элементы для обеих сущностей и связи
         assert!(result.elements.len() >= 5);
     }
+
+    #[test]
+    fn test_relationship_to_unknown_entity_skipped() {
+        let mut diagram = ErDiagram::new();
+
+        diagram.add_entity(Entity::new("User"));
+        diagram.add_relationship(ErRelationship::new("User", "Ghost"));
+
+        let engine = ErLayoutEngine::new();
+        let result = engine.layout(&diagram, &LayoutConfig::default());
+
+        assert!(result.elements.iter().all(|e| !e.id.starts_with("rel_")));
+    }
 }
@@ -3,36 +3,51 @@
 //! Создаёт горизонтальную временную шкалу с задачами.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use plantuml_ast::gantt::{GanttDate, GanttDiagram, TaskDuration, TaskStart, Weekday};
 use plantuml_model::{Point, Rect};
 
 use super::GanttLayoutConfig;
+use crate::text::{EstimatedMetrics, TextMetrics};
 use crate::traits::LayoutResult;
-use crate::{EdgeType, ElementType, LayoutElement};
+use crate::{EdgeLabelPositions, EdgeType, ElementType, LayoutElement};
 
 /// Layout engine для Gantt Diagrams
 pub struct GanttLayoutEngine {
     config: GanttLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl GanttLayoutEngine {
     /// Создаёт новый layout engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: GanttLayoutConfig::default(),
-        }
+        Self::with_config(GanttLayoutConfig::default())
     }
 
     /// Создаёт layout engine с заданной конфигурацией
     pub fn with_config(config: GanttLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics::default()),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &GanttDiagram) -> LayoutResult {
         let mut elements = Vec::new();
 
+        // Колонка подписей задач шире конфигурации, если подпись не помещается
+        let label_width = diagram.tasks.iter().map(|task| task.name.as_str())
+            .map(|label| self.metrics.text_width(label, self.config.label_font_size) + 10.0)
+            .fold(self.config.task_label_width, f64::max);
+
         // Определяем даты начала и окончания проекта
         let project_start = diagram
             .project_start
@@ -50,7 +65,7 @@ impl GanttLayoutEngine {
             .unwrap_or(30);
 
         let timeline_width = (total_days as f64) * self.config.day_width;
-        let timeline_start_x = self.config.padding + self.config.task_label_width;
+        let timeline_start_x = self.config.padding + label_width;
 
         // 1. Рисуем заголовок с датами
         self.draw_header(
@@ -83,7 +98,7 @@ impl GanttLayoutEngine {
                 bounds: Rect::new(
                     self.config.padding,
                     row_y,
-                    self.config.task_label_width - 10.0,
+                    label_width - 10.0,
                     self.config.row_height,
                 ),
                 text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
@@ -158,6 +173,7 @@ impl GanttLayoutEngine {
                     arrow_end: false,
                     dashed: true,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                    curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                 },
             });
         }
@@ -319,6 +335,7 @@ impl GanttLayoutEngine {
                     arrow_end: false,
                     dashed: false,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                    curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                 },
             });
         }
@@ -341,6 +358,7 @@ impl GanttLayoutEngine {
                     arrow_end: false,
                     dashed: true,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                    curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                 },
             });
         }
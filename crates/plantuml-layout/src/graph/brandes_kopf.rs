@@ -0,0 +1,377 @@
+//! Присвоение горизонтальных координат по алгоритму Brandes-Köpf.
+//!
+//! 1. Пометка конфликтов типа 1 (внутренние сегменты между фиктивными узлами
+//!    имеют приоритет над пересекающими их обычными сегментами)
+//! 2. Вертикальное выравнивание узлов в блоки по медианным соседям —
+//!    четыре варианта: верх/низ × лево/право
+//! 3. Горизонтальное сжатие блоков с учётом ширины узлов
+//! 4. Балансировка: среднее двух медианных из четырёх координат
+//!
+//! Сжатие выполняется через граф блоков (как в dagre), что обходит известную
+//! ошибку в оригинальной процедуре сдвига классов.
+
+use std::collections::HashSet;
+
+use super::layered::ProperGraph;
+
+/// Параметры разнесения узлов
+#[derive(Debug, Clone, Copy)]
+pub struct Separation {
+    /// Расстояние между обычными узлами
+    pub node_spacing: f64,
+    /// Расстояние между фиктивными узлами
+    pub edge_spacing: f64,
+}
+
+/// Вычисляет X-координаты центров всех узлов (включая фиктивные)
+pub fn assign_x(graph: &ProperGraph, separation: Separation) -> Vec<f64> {
+    let n = graph.node_count();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let conflicts = find_type1_conflicts(graph);
+    let mut candidates: Vec<(Vec<f64>, bool)> = Vec::with_capacity(4);
+
+    for upward in [true, false] {
+        for leftward in [true, false] {
+            let mut layering: Vec<Vec<usize>> = if upward {
+                graph.layers.clone()
+            } else {
+                graph.layers.iter().rev().cloned().collect()
+            };
+            if !leftward {
+                for layer in &mut layering {
+                    layer.reverse();
+                }
+            }
+
+            let neighbors = if upward { &graph.preds } else { &graph.succs };
+            let (root, align) = vertical_alignment(&layering, neighbors, &conflicts, n);
+            let mut xs = horizontal_compaction(graph, &layering, &root, &align, separation);
+            if !leftward {
+                for x in &mut xs {
+                    *x = -*x;
+                }
+            }
+            candidates.push((xs, leftward));
+        }
+    }
+
+    align_to_smallest(graph, &mut candidates);
+    balance(&candidates, n)
+}
+
+/// Ключ неупорядоченной пары узлов
+fn conflict_key(a: usize, b: usize) -> (usize, usize) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Находит конфликты типа 1: обычный сегмент пересекает внутренний
+fn find_type1_conflicts(graph: &ProperGraph) -> HashSet<(usize, usize)> {
+    let mut conflicts = HashSet::new();
+    let pos = graph.positions();
+
+    for i in 1..graph.layers.len() {
+        let prev_len = graph.layers[i - 1].len();
+        let layer = &graph.layers[i];
+        let Some(&last) = layer.last() else {
+            continue;
+        };
+
+        let mut k0 = 0usize;
+        let mut scan_pos = 0usize;
+
+        for (l1, &v) in layer.iter().enumerate() {
+            // Внутренний сегмент: оба конца фиктивные
+            let inner = if graph.dummy[v] {
+                graph.preds[v].iter().copied().find(|&u| graph.dummy[u])
+            } else {
+                None
+            };
+            let k1 = inner.map_or(prev_len, |u| pos[u]);
+
+            if inner.is_some() || v == last {
+                for &scan in &layer[scan_pos..=l1] {
+                    for &u in &graph.preds[scan] {
+                        let u_pos = pos[u];
+                        if (u_pos < k0 || k1 < u_pos) && !(graph.dummy[u] && graph.dummy[scan]) {
+                            conflicts.insert(conflict_key(u, scan));
+                        }
+                    }
+                }
+                scan_pos = l1 + 1;
+                k0 = k1;
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Выравнивает узлы в вертикальные блоки по медианным соседям
+///
+/// Возвращает `(root, align)`: корень блока каждого узла и циклический
+/// список узлов блока.
+fn vertical_alignment(
+    layering: &[Vec<usize>],
+    neighbors: &[Vec<usize>],
+    conflicts: &HashSet<(usize, usize)>,
+    n: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut root: Vec<usize> = (0..n).collect();
+    let mut align: Vec<usize> = (0..n).collect();
+    let mut pos = vec![0usize; n];
+    for layer in layering {
+        for (i, &v) in layer.iter().enumerate() {
+            pos[v] = i;
+        }
+    }
+
+    for layer in layering {
+        let mut prev_idx: Option<usize> = None;
+        for &v in layer {
+            let mut ws: Vec<usize> = neighbors[v].clone();
+            if ws.is_empty() {
+                continue;
+            }
+            ws.sort_by_key(|&w| pos[w]);
+
+            // Одна медиана для нечётного числа соседей, две — для чётного
+            let lo = (ws.len() - 1) / 2;
+            let hi = ws.len() / 2;
+            for &w in &ws[lo..=hi] {
+                if align[v] == v
+                    && prev_idx.map_or(true, |p| p < pos[w])
+                    && !conflicts.contains(&conflict_key(v, w))
+                {
+                    align[w] = v;
+                    root[v] = root[w];
+                    align[v] = root[v];
+                    prev_idx = Some(pos[w]);
+                }
+            }
+        }
+    }
+
+    (root, align)
+}
+
+/// Минимальное расстояние между центрами соседних узлов
+fn separation_between(graph: &ProperGraph, a: usize, b: usize, sep: Separation) -> f64 {
+    let spacing = match (graph.dummy[a], graph.dummy[b]) {
+        (true, true) => sep.edge_spacing,
+        (false, false) => sep.node_spacing,
+        _ => (sep.node_spacing + sep.edge_spacing) / 2.0,
+    };
+    (graph.widths[a] + graph.widths[b]) / 2.0 + spacing
+}
+
+/// Горизонтальное сжатие блоков
+fn horizontal_compaction(
+    graph: &ProperGraph,
+    layering: &[Vec<usize>],
+    root: &[usize],
+    align: &[usize],
+    sep: Separation,
+) -> Vec<f64> {
+    let n = graph.node_count();
+
+    // Граф блоков: ребро от блока левого соседа к блоку правого
+    let mut in_edges: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    let mut out_edges: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for layer in layering {
+        for pair in layer.windows(2) {
+            let (u, v) = (pair[0], pair[1]);
+            let (ru, rv) = (root[u], root[v]);
+            let weight = separation_between(graph, u, v, sep);
+
+            match out_edges[ru].iter_mut().find(|(t, _)| *t == rv) {
+                Some(edge) => edge.1 = edge.1.max(weight),
+                None => out_edges[ru].push((rv, weight)),
+            }
+            match in_edges[rv].iter_mut().find(|(s, _)| *s == ru) {
+                Some(edge) => edge.1 = edge.1.max(weight),
+                None => in_edges[rv].push((ru, weight)),
+            }
+        }
+    }
+
+    let roots: Vec<usize> = (0..n).filter(|&v| root[v] == v).collect();
+    let order = topological_blocks(&roots, &in_edges, n);
+
+    // Проход 1: минимально возможные координаты (прижимаем влево)
+    let mut xs = vec![0.0f64; n];
+    for &b in &order {
+        xs[b] = in_edges[b]
+            .iter()
+            .map(|&(u, w)| xs[u] + w)
+            .fold(0.0, f64::max);
+    }
+
+    // Проход 2: сдвигаем блоки вправо, если есть свободное место
+    for &b in order.iter().rev() {
+        let min = out_edges[b]
+            .iter()
+            .map(|&(v, w)| xs[v] - w)
+            .fold(f64::INFINITY, f64::min);
+        if min.is_finite() && min > xs[b] {
+            xs[b] = min;
+        }
+    }
+
+    // Все узлы блока получают координату корня
+    let mut result = vec![0.0f64; n];
+    for &r in &roots {
+        let mut v = r;
+        loop {
+            result[v] = xs[r];
+            v = align[v];
+            if v == r {
+                break;
+            }
+        }
+    }
+    result
+}
+
+/// Топологический порядок блоков (граф блоков ацикличен)
+fn topological_blocks(roots: &[usize], in_edges: &[Vec<(usize, f64)>], n: usize) -> Vec<usize> {
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(roots.len());
+
+    for &start in roots {
+        if visited[start] {
+            continue;
+        }
+        // Итеративный DFS по входящим рёбрам (post-order)
+        let mut stack: Vec<(usize, usize)> = vec![(start, 0)];
+        visited[start] = true;
+        while let Some(&mut (v, ref mut next)) = stack.last_mut() {
+            if let Some(&(u, _)) = in_edges[v].get(*next) {
+                *next += 1;
+                if !visited[u] {
+                    visited[u] = true;
+                    stack.push((u, 0));
+                }
+            } else {
+                order.push(v);
+                stack.pop();
+            }
+        }
+    }
+
+    order
+}
+
+/// Выравнивает все варианты по варианту с наименьшей шириной
+fn align_to_smallest(graph: &ProperGraph, candidates: &mut [(Vec<f64>, bool)]) {
+    let extent = |xs: &[f64]| {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for (v, &x) in xs.iter().enumerate() {
+            let half = graph.widths[v] / 2.0;
+            min = min.min(x - half);
+            max = max.max(x + half);
+        }
+        (min, max)
+    };
+
+    let Some((smallest_min, smallest_max)) = candidates
+        .iter()
+        .map(|(xs, _)| extent(xs))
+        .min_by(|a, b| {
+            (a.1 - a.0)
+                .partial_cmp(&(b.1 - b.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    else {
+        return;
+    };
+
+    for (xs, leftward) in candidates.iter_mut() {
+        let (min, max) = extent(xs);
+        let delta = if *leftward { smallest_min - min } else { smallest_max - max };
+        if delta != 0.0 {
+            for x in xs.iter_mut() {
+                *x += delta;
+            }
+        }
+    }
+}
+
+/// Балансировка: среднее двух медианных значений из четырёх
+fn balance(candidates: &[(Vec<f64>, bool)], n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|v| {
+            let mut values: Vec<f64> = candidates.iter().map(|(xs, _)| xs[v]).collect();
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            (values[1] + values[2]) / 2.0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEP: Separation = Separation {
+        node_spacing: 10.0,
+        edge_spacing: 5.0,
+    };
+
+    #[test]
+    fn test_no_overlap_within_layer() {
+        let mut graph = ProperGraph::with_layers(2);
+        let a = graph.add_node(0, 100.0, 40.0, false);
+        let b = graph.add_node(1, 80.0, 40.0, false);
+        let c = graph.add_node(1, 60.0, 40.0, false);
+        let d = graph.add_node(1, 40.0, 40.0, false);
+        graph.connect(a, b);
+        graph.connect(a, c);
+        graph.connect(a, d);
+
+        let xs = assign_x(&graph, SEP);
+        for pair in graph.layers[1].windows(2) {
+            let (u, v) = (pair[0], pair[1]);
+            let gap = (xs[v] - graph.widths[v] / 2.0) - (xs[u] + graph.widths[u] / 2.0);
+            assert!(gap >= SEP.node_spacing - 1e-6, "gap {gap}");
+        }
+    }
+
+    #[test]
+    fn test_parent_centered_over_single_child() {
+        let mut graph = ProperGraph::with_layers(2);
+        let a = graph.add_node(0, 100.0, 40.0, false);
+        let b = graph.add_node(1, 60.0, 40.0, false);
+        graph.connect(a, b);
+
+        let xs = assign_x(&graph, SEP);
+        assert!((xs[a] - xs[b]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_dummy_chain_is_straight() {
+        // a -> d1 -> d2 -> b: цепочка фиктивных узлов должна быть вертикальной
+        let mut graph = ProperGraph::with_layers(4);
+        let a = graph.add_node(0, 80.0, 40.0, false);
+        let x = graph.add_node(0, 80.0, 40.0, false);
+        let d1 = graph.add_node(1, 0.0, 0.0, true);
+        let y = graph.add_node(1, 80.0, 40.0, false);
+        let d2 = graph.add_node(2, 0.0, 0.0, true);
+        let z = graph.add_node(2, 80.0, 40.0, false);
+        let b = graph.add_node(3, 80.0, 40.0, false);
+        graph.connect(a, d1);
+        graph.connect(d1, d2);
+        graph.connect(d2, b);
+        graph.connect(x, y);
+        graph.connect(y, z);
+
+        let xs = assign_x(&graph, SEP);
+        assert!((xs[d1] - xs[d2]).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,127 @@
+//! Конфигурация для иерархического layout графов
+
+use plantuml_ast::common::Direction;
+
+/// Направление рангов (аналог `rankdir` в Graphviz)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankDir {
+    /// Сверху вниз (по умолчанию)
+    #[default]
+    TopToBottom,
+    /// Снизу вверх
+    BottomToTop,
+    /// Слева направо
+    LeftToRight,
+    /// Справа налево
+    RightToLeft,
+}
+
+impl RankDir {
+    /// Горизонтальное направление (слои идут по оси X)
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Self::LeftToRight | Self::RightToLeft)
+    }
+}
+
+impl From<Direction> for RankDir {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::TopToBottom => Self::TopToBottom,
+            Direction::BottomToTop => Self::BottomToTop,
+            Direction::LeftToRight => Self::LeftToRight,
+            Direction::RightToLeft => Self::RightToLeft,
+        }
+    }
+}
+
+/// Форма линий рёбер (`skinparam linetype`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineType {
+    /// Сглаженные кривые Безье (по умолчанию)
+    #[default]
+    Spline,
+    /// Ломаные
+    Polyline,
+    /// Ортогональные линии с обходом узлов
+    Ortho,
+}
+
+impl LineType {
+    /// Разбирает значение `skinparam linetype`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ortho" => Some(Self::Ortho),
+            "polyline" => Some(Self::Polyline),
+            "spline" | "splines" => Some(Self::Spline),
+            _ => None,
+        }
+    }
+}
+
+/// Конфигурация алгоритма Sugiyama
+#[derive(Debug, Clone)]
+pub struct SugiyamaConfig {
+    /// Расстояние между соседними узлами в слое
+    pub node_spacing: f64,
+    /// Расстояние между соседними фиктивными узлами (параллельными рёбрами)
+    pub edge_spacing: f64,
+    /// Расстояние между слоями
+    pub layer_spacing: f64,
+    /// Отступ от границ диаграммы
+    pub margin: f64,
+    /// Максимальное количество проходов минимизации пересечений
+    pub max_sweeps: usize,
+    /// Локальные перестановки соседних узлов (transpose) после каждого прохода
+    pub transpose: bool,
+    /// Направление рангов
+    pub rank_dir: RankDir,
+    /// Форма линий рёбер
+    pub line_type: LineType,
+}
+
+impl Default for SugiyamaConfig {
+    fn default() -> Self {
+        Self {
+            node_spacing: 50.0,
+            edge_spacing: 20.0,
+            layer_spacing: 80.0,
+            margin: 20.0,
+            max_sweeps: 24,
+            transpose: true,
+            rank_dir: RankDir::TopToBottom,
+            line_type: LineType::Spline,
+        }
+    }
+}
+
+impl SugiyamaConfig {
+    /// Создаёт новую конфигурацию
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Устанавливает расстояния между узлами и слоями
+    pub fn with_spacing(mut self, node: f64, layer: f64) -> Self {
+        self.node_spacing = node;
+        self.layer_spacing = layer;
+        self
+    }
+
+    /// Устанавливает направление рангов
+    pub fn with_rank_dir(mut self, rank_dir: RankDir) -> Self {
+        self.rank_dir = rank_dir;
+        self
+    }
+
+    /// Устанавливает форму линий рёбер
+    pub fn with_line_type(mut self, line_type: LineType) -> Self {
+        self.line_type = line_type;
+        self
+    }
+
+    /// Устанавливает отступ от границ
+    pub fn with_margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        self
+    }
+}
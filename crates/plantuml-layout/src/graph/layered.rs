@@ -0,0 +1,153 @@
+//! Графы для иерархического layout.
+//!
+//! [`LayeredGraph`] — входной граф: узлы задаются только размерами, рёбра — парами
+//! индексов. Индексы узлов и рёбер сохраняются в результате layout.
+//!
+//! [`ProperGraph`] — внутреннее представление после присвоения слоёв: каждое ребро
+//! соединяет соседние слои, длинные рёбра разбиты фиктивными узлами.
+
+use plantuml_model::Size;
+
+/// Направленный граф с размерами узлов
+#[derive(Debug, Clone, Default)]
+pub struct LayeredGraph {
+    /// Размеры узлов
+    pub sizes: Vec<Size>,
+    /// Рёбра (from, to)
+    pub edges: Vec<(usize, usize)>,
+    /// Пары узлов одного слоя (левый, правый)
+    pub same_rank: Vec<(usize, usize)>,
+}
+
+impl LayeredGraph {
+    /// Создаёт пустой граф
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет узел и возвращает его индекс
+    pub fn add_node(&mut self, size: Size) -> usize {
+        self.sizes.push(size);
+        self.sizes.len() - 1
+    }
+
+    /// Добавляет ребро и возвращает его индекс
+    pub fn add_edge(&mut self, from: usize, to: usize) -> usize {
+        self.edges.push((from, to));
+        self.edges.len() - 1
+    }
+
+    /// Требует поставить `left` и `right` в один слой, `left` левее
+    pub fn add_same_rank(&mut self, left: usize, right: usize) {
+        self.same_rank.push((left, right));
+    }
+
+    /// Возвращает количество узлов
+    pub fn node_count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Возвращает количество рёбер
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+}
+
+/// Граф, в котором все рёбра соединяют соседние слои
+#[derive(Debug, Clone, Default)]
+pub struct ProperGraph {
+    /// Ширина узлов (0 для фиктивных)
+    pub widths: Vec<f64>,
+    /// Высота узлов (0 для фиктивных)
+    pub heights: Vec<f64>,
+    /// Фиктивный узел (точка излома длинного ребра)
+    pub dummy: Vec<bool>,
+    /// Слой каждого узла
+    pub layer: Vec<usize>,
+    /// Узлы каждого слоя в порядке слева направо
+    pub layers: Vec<Vec<usize>>,
+    /// Соседи в верхнем слое
+    pub preds: Vec<Vec<usize>>,
+    /// Соседи в нижнем слое
+    pub succs: Vec<Vec<usize>>,
+}
+
+impl ProperGraph {
+    /// Создаёт граф с заданным количеством слоёв
+    pub fn with_layers(layer_count: usize) -> Self {
+        Self {
+            layers: vec![Vec::new(); layer_count],
+            ..Self::default()
+        }
+    }
+
+    /// Добавляет узел в конец слоя и возвращает его индекс
+    pub fn add_node(&mut self, layer: usize, width: f64, height: f64, dummy: bool) -> usize {
+        let idx = self.widths.len();
+        self.widths.push(width);
+        self.heights.push(height);
+        self.dummy.push(dummy);
+        self.layer.push(layer);
+        self.preds.push(Vec::new());
+        self.succs.push(Vec::new());
+        if self.layers.len() <= layer {
+            self.layers.resize(layer + 1, Vec::new());
+        }
+        self.layers[layer].push(idx);
+        idx
+    }
+
+    /// Соединяет узел верхнего слоя с узлом следующего слоя
+    pub fn connect(&mut self, upper: usize, lower: usize) {
+        self.succs[upper].push(lower);
+        self.preds[lower].push(upper);
+    }
+
+    /// Возвращает количество узлов (включая фиктивные)
+    pub fn node_count(&self) -> usize {
+        self.widths.len()
+    }
+
+    /// Позиции узлов внутри их слоёв
+    pub fn positions(&self) -> Vec<usize> {
+        let mut pos = vec![0; self.node_count()];
+        for layer in &self.layers {
+            for (i, &v) in layer.iter().enumerate() {
+                pos[v] = i;
+            }
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_nodes_and_edges() {
+        let mut graph = LayeredGraph::new();
+        let a = graph.add_node(Size::new(100.0, 40.0));
+        let b = graph.add_node(Size::new(80.0, 40.0));
+        let e = graph.add_edge(a, b);
+
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.edges[e], (0, 1));
+    }
+
+    #[test]
+    fn test_proper_graph_positions() {
+        let mut proper = ProperGraph::with_layers(2);
+        let a = proper.add_node(0, 10.0, 10.0, false);
+        let b = proper.add_node(1, 10.0, 10.0, false);
+        let c = proper.add_node(1, 0.0, 0.0, true);
+        proper.connect(a, b);
+        proper.connect(a, c);
+
+        let pos = proper.positions();
+        assert_eq!(pos[b], 0);
+        assert_eq!(pos[c], 1);
+        assert_eq!(proper.succs[a], vec![b, c]);
+    }
+}
@@ -0,0 +1,809 @@
+//! Общий layout графовых диаграмм: узлы, кластеры, порты и метки рёбер.
+//!
+//! [`GraphLayout`] — модель, в которую отображаются диаграммы «узлы и связи»
+//! (классы, компоненты, прецеденты, объекты, ER, состояния).
+//!
+//! Кластеры раскладываются рекурсивно снизу вверх: содержимое кластера
+//! размещается отдельным проходом Sugiyama, после чего кластер участвует в
+//! layout родителя как один узел своего размера. Ребро между узлами разных
+//! кластеров учитывается на уровне их ближайшего общего предка, а его концы
+//! затем обрезаются по границам настоящих узлов.
+//!
+//! Метка ребра становится отдельным узлом между концами ребра, поэтому место
+//! под неё резервируется, а линия проходит вдоль её края. Окончательную
+//! геометрию рёбер (форма линий, обход узлов, подписи концов) строит
+//! [`EdgeRouter`].
+
+use std::collections::HashMap;
+
+use plantuml_model::{Point, Rect, Size};
+
+use super::config::SugiyamaConfig;
+use super::layered::LayeredGraph;
+use super::router::{EdgeRouter, RouteRequest, LABEL_GAP};
+use super::routing::EdgeRoute;
+use super::sugiyama::SugiyamaLayout;
+use crate::EdgeLabelPositions;
+
+/// Средняя ширина символа метки ребра (шрифт 13px)
+const LABEL_CHAR_WIDTH: f64 = 7.0;
+
+/// Высота строки метки ребра
+const LABEL_LINE_HEIGHT: f64 = 16.0;
+
+/// Приблизительный размер текстовой метки ребра
+pub fn label_size(text: &str) -> Size {
+    let text = text.replace("\\n", "\n");
+    let lines = text.lines().count().max(1);
+    let chars = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+    Size::new(
+        chars as f64 * LABEL_CHAR_WIDTH,
+        lines as f64 * LABEL_LINE_HEIGHT,
+    )
+}
+
+/// Сторона узла, к которой крепится конец ребра
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Port {
+    /// Точка определяется маршрутом ребра
+    #[default]
+    Auto,
+    /// Середина верхней стороны
+    Top,
+    /// Середина нижней стороны
+    Bottom,
+    /// Середина левой стороны
+    Left,
+    /// Середина правой стороны
+    Right,
+}
+
+impl Port {
+    /// Точка крепления на границе прямоугольника (`None` для [`Port::Auto`])
+    pub fn anchor(self, rect: &Rect) -> Option<Point> {
+        match self {
+            Self::Auto => None,
+            Self::Top => Some(rect.top_center()),
+            Self::Bottom => Some(rect.bottom_center()),
+            Self::Left => Some(rect.left_center()),
+            Self::Right => Some(rect.right_center()),
+        }
+    }
+}
+
+/// Узел графа
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    /// Размер узла
+    pub size: Size,
+    /// Кластер, непосредственно содержащий узел
+    pub cluster: Option<usize>,
+}
+
+/// Кластер — составной узел (пакет, составное состояние, граница системы)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphCluster {
+    /// Родительский кластер
+    pub parent: Option<usize>,
+    /// Отступ от границы кластера до содержимого
+    pub padding: f64,
+    /// Высота заголовка над содержимым
+    pub header_height: f64,
+    /// Минимальный размер (например, по ширине заголовка)
+    pub min_size: Size,
+}
+
+impl GraphCluster {
+    /// Создаёт кластер внутри `parent` (или на верхнем уровне)
+    pub fn new(parent: Option<usize>) -> Self {
+        Self {
+            parent,
+            padding: 20.0,
+            header_height: 0.0,
+            min_size: Size::zero(),
+        }
+    }
+
+    /// Устанавливает отступ до содержимого
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Устанавливает высоту заголовка
+    pub fn with_header(mut self, header_height: f64) -> Self {
+        self.header_height = header_height;
+        self
+    }
+
+    /// Устанавливает минимальный размер
+    pub fn with_min_size(mut self, min_size: Size) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
+
+/// Конец ребра: узел или кластер целиком (например, составное состояние)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// Узел
+    Node(usize),
+    /// Кластер
+    Cluster(usize),
+}
+
+/// Ребро графа
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphEdge {
+    /// Исходный конец
+    pub from: Endpoint,
+    /// Целевой конец
+    pub to: Endpoint,
+    /// Размер метки (под неё резервируется место)
+    pub label: Option<Size>,
+    /// Размер подписи у исходного конца (кардинальность)
+    pub from_label: Option<Size>,
+    /// Размер подписи у целевого конца
+    pub to_label: Option<Size>,
+    /// Порт исходного узла
+    pub from_port: Port,
+    /// Порт целевого узла
+    pub to_port: Port,
+}
+
+impl GraphEdge {
+    /// Создаёт ребро между узлами
+    pub fn new(from: usize, to: usize) -> Self {
+        Self::between(Endpoint::Node(from), Endpoint::Node(to))
+    }
+
+    /// Создаёт ребро между произвольными концами
+    pub fn between(from: Endpoint, to: Endpoint) -> Self {
+        Self {
+            from,
+            to,
+            label: None,
+            from_label: None,
+            to_label: None,
+            from_port: Port::Auto,
+            to_port: Port::Auto,
+        }
+    }
+
+    /// Устанавливает размер метки
+    pub fn with_label(mut self, size: Size) -> Self {
+        self.label = Some(size);
+        self
+    }
+
+    /// Устанавливает размеры подписей у концов ребра
+    pub fn with_end_labels(mut self, from: Option<Size>, to: Option<Size>) -> Self {
+        self.from_label = from;
+        self.to_label = to;
+        self
+    }
+
+    /// Устанавливает порты концов ребра
+    pub fn with_ports(mut self, from_port: Port, to_port: Port) -> Self {
+        self.from_port = from_port;
+        self.to_port = to_port;
+        self
+    }
+}
+
+/// Маршрут ребра после layout
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RoutedEdge {
+    /// Точки ломаной от исходного узла к целевому
+    pub points: Vec<Point>,
+    /// Контрольные точки кривых Безье `[p0, c1, c2, p1, ...]` (пусто — ломаная)
+    pub curve: Vec<Point>,
+    /// Область метки
+    pub label: Option<Rect>,
+    /// Область подписи у исходного конца
+    pub from_label: Option<Rect>,
+    /// Область подписи у целевого конца
+    pub to_label: Option<Rect>,
+    /// Ребро было обращено при удалении циклов
+    pub reversed: bool,
+}
+
+impl RoutedEdge {
+    /// Меняет направление маршрута (подписи концов меняются местами)
+    pub fn reverse(&mut self) {
+        self.points.reverse();
+        self.curve.reverse();
+        std::mem::swap(&mut self.from_label, &mut self.to_label);
+    }
+
+    /// Bounding box линии и всех подписей
+    pub fn bounds(&self) -> Rect {
+        let mut rects: Vec<Rect> = self
+            .points
+            .iter()
+            .chain(&self.curve)
+            .map(|p| Rect::new(p.x, p.y, 0.0, 0.0))
+            .collect();
+        rects.extend(self.label.iter().chain(&self.from_label).chain(&self.to_label));
+        let Some(first) = rects.first().copied() else {
+            return Rect::default();
+        };
+        rects.iter().fold(first, |acc, r| {
+            let x = acc.x.min(r.x);
+            let y = acc.y.min(r.y);
+            let right = (acc.x + acc.width).max(r.x + r.width);
+            let bottom = (acc.y + acc.height).max(r.y + r.height);
+            Rect::new(x, y, right - x, bottom - y)
+        })
+    }
+
+    /// Центры подписей для элемента [`crate::ElementType::Edge`]
+    pub fn label_positions(&self) -> EdgeLabelPositions {
+        EdgeLabelPositions {
+            label: self.label.map(|r| r.center()),
+            from_cardinality: self.from_label.map(|r| r.center()),
+            to_cardinality: self.to_label.map(|r| r.center()),
+        }
+    }
+}
+
+/// Результат layout графа
+#[derive(Debug, Clone, Default)]
+pub struct GraphLayoutResult {
+    /// Bounding box узлов (в порядке добавления)
+    pub nodes: Vec<Rect>,
+    /// Bounding box кластеров (в порядке добавления)
+    pub clusters: Vec<Rect>,
+    /// Маршруты рёбер (в порядке добавления)
+    pub edges: Vec<RoutedEdge>,
+    /// Ширина диаграммы (с отступами)
+    pub width: f64,
+    /// Высота диаграммы (с отступами)
+    pub height: f64,
+}
+
+/// Граф с кластерами, портами и метками рёбер
+///
+/// ```
+/// use plantuml_layout::graph::{GraphCluster, GraphEdge, GraphLayout, SugiyamaConfig};
+/// use plantuml_layout::Size;
+///
+/// let mut graph = GraphLayout::new(SugiyamaConfig::default());
+/// let package = graph.add_cluster(GraphCluster::new(None).with_header(20.0));
+/// let a = graph.add_node_in(Size::new(100.0, 40.0), package);
+/// let b = graph.add_node(Size::new(100.0, 40.0));
+/// graph.add_edge(GraphEdge::new(a, b).with_label(Size::new(60.0, 14.0)));
+///
+/// let result = graph.layout();
+/// let cluster = result.clusters[package];
+/// assert!(result.nodes[a].y >= cluster.y + 20.0);
+/// assert!(result.edges[0].label.is_some());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GraphLayout {
+    config: SugiyamaConfig,
+    nodes: Vec<GraphNode>,
+    clusters: Vec<GraphCluster>,
+    edges: Vec<GraphEdge>,
+    same_rank: Vec<(usize, usize)>,
+}
+
+/// Элемент одного уровня вложенности: узел или кластер целиком
+type Item = Endpoint;
+
+/// Как ребро представлено во входном графе уровня
+enum Owned {
+    /// Одно ребро
+    Direct(usize),
+    /// Ребро через узел метки: (узел метки, первая половина, вторая половина)
+    Labeled(usize, usize, usize),
+}
+
+/// Layout одного уровня вложенности (координаты относительно его начала)
+#[derive(Debug, Default)]
+struct Scope {
+    size: Size,
+    nodes: Vec<(usize, Rect)>,
+    clusters: Vec<(usize, Rect)>,
+    edges: Vec<(usize, RoutedEdge)>,
+}
+
+impl Scope {
+    /// Сдвигает все элементы уровня
+    fn translate(&mut self, dx: f64, dy: f64) {
+        let shift = |r: &mut Rect| {
+            r.x += dx;
+            r.y += dy;
+        };
+        self.nodes.iter_mut().for_each(|(_, r)| shift(r));
+        self.clusters.iter_mut().for_each(|(_, r)| shift(r));
+        for (_, edge) in &mut self.edges {
+            for p in &mut edge.points {
+                p.x += dx;
+                p.y += dy;
+            }
+            if let Some(label) = &mut edge.label {
+                shift(label);
+            }
+        }
+    }
+
+    /// Переносит элементы вложенного уровня
+    fn absorb(&mut self, other: Scope) {
+        self.nodes.extend(other.nodes);
+        self.clusters.extend(other.clusters);
+        self.edges.extend(other.edges);
+    }
+}
+
+impl GraphLayout {
+    /// Создаёт пустой граф
+    pub fn new(config: SugiyamaConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Добавляет узел верхнего уровня
+    pub fn add_node(&mut self, size: Size) -> usize {
+        self.nodes.push(GraphNode {
+            size,
+            cluster: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Добавляет узел в кластер
+    pub fn add_node_in(&mut self, size: Size, cluster: usize) -> usize {
+        self.nodes.push(GraphNode {
+            size,
+            cluster: Some(cluster),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Добавляет кластер
+    pub fn add_cluster(&mut self, cluster: GraphCluster) -> usize {
+        self.clusters.push(cluster);
+        self.clusters.len() - 1
+    }
+
+    /// Добавляет ребро
+    pub fn add_edge(&mut self, edge: GraphEdge) -> usize {
+        self.edges.push(edge);
+        self.edges.len() - 1
+    }
+
+    /// Ставит узлы `left` и `right` в один слой, `left` левее
+    ///
+    /// Для узлов разных кластеров действует на содержащие их кластеры.
+    pub fn add_same_rank(&mut self, left: usize, right: usize) {
+        self.same_rank.push((left, right));
+    }
+
+    /// Количество узлов
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Количество кластеров
+    pub fn cluster_count(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Количество рёбер
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Выполняет layout
+    pub fn layout(&self) -> GraphLayoutResult {
+        let scope = self.layout_scope(None, self.config.margin);
+
+        let mut result = GraphLayoutResult {
+            nodes: vec![Rect::default(); self.nodes.len()],
+            clusters: vec![Rect::default(); self.clusters.len()],
+            edges: vec![RoutedEdge::default(); self.edges.len()],
+            width: scope.size.width,
+            height: scope.size.height,
+        };
+        for (id, rect) in scope.nodes {
+            result.nodes[id] = rect;
+        }
+        for (id, rect) in scope.clusters {
+            result.clusters[id] = rect;
+        }
+        for (id, edge) in scope.edges {
+            result.edges[id] = edge;
+        }
+
+        // Окончательная геометрия: концы по настоящим узлам (а не по
+        // кластерам-представителям), обход узлов, подписи
+        let rect = |end: Endpoint| match end {
+            Endpoint::Node(v) => result.nodes[v],
+            Endpoint::Cluster(c) => result.clusters[c],
+        };
+        let (ids, requests): (Vec<usize>, Vec<RouteRequest>) = self
+            .edges
+            .iter()
+            .zip(&result.edges)
+            .enumerate()
+            .filter(|(_, (_, routed))| routed.points.len() >= 2)
+            .map(|(id, (edge, routed))| {
+                let (from, to) = (rect(edge.from), rect(edge.to));
+                let waypoints = if edge.from == edge.to {
+                    Vec::new()
+                } else {
+                    routed.points[1..routed.points.len() - 1].to_vec()
+                };
+                let request = RouteRequest {
+                    from,
+                    to,
+                    waypoints,
+                    from_anchor: edge.from_port.anchor(&from),
+                    to_anchor: edge.to_port.anchor(&to),
+                    label: edge.label,
+                    reserved_label: routed.label,
+                    from_label: edge.from_label,
+                    to_label: edge.to_label,
+                };
+                (id, request)
+            })
+            .unzip();
+
+        let router = EdgeRouter::new(self.config.line_type).with_spacing(self.config.edge_spacing);
+        for (id, route) in ids.into_iter().zip(router.route(&result.nodes, &requests)) {
+            let routed = &mut result.edges[id];
+            routed.points = route.points;
+            routed.curve = route.curve;
+            routed.label = route.label;
+            routed.from_label = route.from_label;
+            routed.to_label = route.to_label;
+        }
+
+        result
+    }
+
+    /// Представитель конца ребра на уровне `scope`: сам конец или содержащий его кластер
+    fn representative(&self, end: Endpoint, scope: Option<usize>) -> Option<Item> {
+        let mut cluster = match end {
+            Endpoint::Node(v) if self.nodes[v].cluster == scope => return Some(end),
+            Endpoint::Node(v) => self.nodes[v].cluster,
+            Endpoint::Cluster(c) if self.clusters[c].parent == scope => return Some(end),
+            Endpoint::Cluster(c) => self.clusters[c].parent,
+        };
+        while let Some(c) = cluster {
+            if self.clusters[c].parent == scope {
+                return Some(Item::Cluster(c));
+            }
+            cluster = self.clusters[c].parent;
+        }
+        None
+    }
+
+    /// Раскладывает содержимое уровня `scope` (рекурсивно)
+    fn layout_scope(&self, scope: Option<usize>, margin: f64) -> Scope {
+        let items: Vec<Item> = (0..self.nodes.len())
+            .filter(|&v| self.nodes[v].cluster == scope)
+            .map(Item::Node)
+            .chain(
+                (0..self.clusters.len())
+                    .filter(|&c| self.clusters[c].parent == scope)
+                    .map(Item::Cluster),
+            )
+            .collect();
+        let index: HashMap<Item, usize> =
+            items.iter().enumerate().map(|(i, &it)| (it, i)).collect();
+
+        let mut layered = LayeredGraph::new();
+        let mut inner: HashMap<usize, Scope> = HashMap::new();
+        for &item in &items {
+            let size = match item {
+                Item::Node(v) => self.nodes[v].size,
+                Item::Cluster(c) => {
+                    let content = self.layout_scope(Some(c), 0.0);
+                    let cluster = &self.clusters[c];
+                    let size = Size::new(
+                        (content.size.width + cluster.padding * 2.0).max(cluster.min_size.width),
+                        (content.size.height + cluster.padding * 2.0 + cluster.header_height)
+                            .max(cluster.min_size.height),
+                    );
+                    inner.insert(c, content);
+                    size
+                }
+            };
+            layered.add_node(size);
+        }
+
+        // Рёбра уровня: концы в разных элементах уровня или петли узлов уровня
+        let horizontal = self.config.rank_dir.is_horizontal();
+        let mut owned: Vec<(usize, Owned)> = Vec::new();
+        for (id, edge) in self.edges.iter().enumerate() {
+            let (Some(from), Some(to)) = (
+                self.representative(edge.from, scope),
+                self.representative(edge.to, scope),
+            ) else {
+                continue;
+            };
+            // Петля — только на уровне самого конца, а не содержащего его кластера
+            if from == to && (edge.from != edge.to || from != edge.from) {
+                continue;
+            }
+            let (from, to) = (index[&from], index[&to]);
+
+            match edge.label {
+                Some(label) if from != to => {
+                    let size = if horizontal {
+                        Size::new(label.width, label.height + LABEL_GAP)
+                    } else {
+                        Size::new(label.width + LABEL_GAP, label.height)
+                    };
+                    let node = layered.add_node(size);
+                    let first = layered.add_edge(from, node);
+                    let second = layered.add_edge(node, to);
+                    owned.push((id, Owned::Labeled(node, first, second)));
+                }
+                _ => owned.push((id, Owned::Direct(layered.add_edge(from, to)))),
+            }
+        }
+
+        for &(left, right) in &self.same_rank {
+            let (Some(left), Some(right)) = (
+                self.representative(Endpoint::Node(left), scope),
+                self.representative(Endpoint::Node(right), scope),
+            ) else {
+                continue;
+            };
+            if left != right {
+                layered.add_same_rank(index[&left], index[&right]);
+            }
+        }
+
+        let mut config = self.config.clone();
+        config.margin = margin;
+        if owned.iter().any(|(_, o)| matches!(o, Owned::Labeled(..))) {
+            // Узлы меток занимают отдельные слои
+            config.layer_spacing /= 2.0;
+        }
+        let placed = SugiyamaLayout::new(config).layout(&layered);
+
+        let mut result = Scope {
+            size: Size::new(placed.width, placed.height),
+            ..Scope::default()
+        };
+
+        for (i, &item) in items.iter().enumerate() {
+            let rect = placed.nodes[i].bounds;
+            match item {
+                Item::Node(v) => result.nodes.push((v, rect)),
+                Item::Cluster(c) => {
+                    result.clusters.push((c, rect));
+                    let Some(mut content) = inner.remove(&c) else {
+                        continue;
+                    };
+                    let cluster = &self.clusters[c];
+                    content.translate(
+                        rect.x + (rect.width - content.size.width) / 2.0,
+                        rect.y + cluster.header_height + cluster.padding,
+                    );
+                    result.absorb(content);
+                }
+            }
+        }
+
+        for (id, o) in owned {
+            let routed = match o {
+                Owned::Direct(e) => {
+                    let route = &placed.edges[e];
+                    let label = self.edges[id].label.map(|size| {
+                        // Петля: метка справа от неё
+                        let right = route.points.iter().map(|p| p.x).fold(f64::MIN, f64::max);
+                        let center = route.points.iter().map(|p| p.y).sum::<f64>()
+                            / route.points.len().max(1) as f64;
+                        Rect::new(
+                            right + LABEL_GAP,
+                            center - size.height / 2.0,
+                            size.width,
+                            size.height,
+                        )
+                    });
+                    if let Some(label) = label {
+                        result.size.width = result.size.width.max(label.x + label.width + margin);
+                    }
+                    RoutedEdge {
+                        points: route.points.clone(),
+                        label,
+                        reversed: route.reversed,
+                        ..RoutedEdge::default()
+                    }
+                }
+                Owned::Labeled(node, first, second) => {
+                    let bounds = placed.nodes[node].bounds;
+                    join_through_label(
+                        &placed.edges[first],
+                        &placed.edges[second],
+                        bounds,
+                        horizontal,
+                    )
+                }
+            };
+            result.edges.push((id, routed));
+        }
+
+        result
+    }
+}
+
+/// Склеивает две половины ребра, проводя линию вдоль края узла метки
+///
+/// При вертикальных слоях линия идёт по левому краю узла, метка справа от неё;
+/// при горизонтальных — по верхнему краю, метка под линией.
+fn join_through_label(
+    first: &EdgeRoute,
+    second: &EdgeRoute,
+    bounds: Rect,
+    horizontal: bool,
+) -> RoutedEdge {
+    let (mut pass, label) = if horizontal {
+        (
+            [
+                Point::new(bounds.x, bounds.y),
+                Point::new(bounds.x + bounds.width, bounds.y),
+            ],
+            Rect::new(
+                bounds.x,
+                bounds.y + LABEL_GAP,
+                bounds.width,
+                bounds.height - LABEL_GAP,
+            ),
+        )
+    } else {
+        (
+            [
+                Point::new(bounds.x, bounds.y),
+                Point::new(bounds.x, bounds.y + bounds.height),
+            ],
+            Rect::new(
+                bounds.x + LABEL_GAP,
+                bounds.y,
+                bounds.width - LABEL_GAP,
+                bounds.height,
+            ),
+        )
+    };
+
+    let entry = first
+        .points
+        .last()
+        .copied()
+        .unwrap_or_else(|| bounds.center());
+    let distance = |p: Point| (p.x - entry.x).powi(2) + (p.y - entry.y).powi(2);
+    if distance(pass[1]) < distance(pass[0]) {
+        pass.swap(0, 1);
+    }
+
+    let mut points = Vec::with_capacity(first.points.len() + second.points.len() + 2);
+    points.extend_from_slice(&first.points[..first.points.len().saturating_sub(1)]);
+    points.extend_from_slice(&pass);
+    points.extend_from_slice(second.points.get(1..).unwrap_or_default());
+
+    RoutedEdge {
+        points,
+        label: Some(label),
+        reversed: first.reversed,
+        ..RoutedEdge::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node() -> Size {
+        Size::new(100.0, 40.0)
+    }
+
+    fn contains(outer: &Rect, inner: &Rect) -> bool {
+        inner.x >= outer.x
+            && inner.y >= outer.y
+            && inner.x + inner.width <= outer.x + outer.width
+            && inner.y + inner.height <= outer.y + outer.height
+    }
+
+    #[test]
+    fn test_cluster_contains_members() {
+        let mut graph = GraphLayout::new(SugiyamaConfig::default());
+        let outer = graph.add_cluster(GraphCluster::new(None).with_header(20.0));
+        let nested = graph.add_cluster(GraphCluster::new(Some(outer)));
+        let a = graph.add_node_in(node(), outer);
+        let b = graph.add_node_in(node(), nested);
+        let c = graph.add_node(node());
+        graph.add_edge(GraphEdge::new(a, b));
+        graph.add_edge(GraphEdge::new(b, c));
+
+        let result = graph.layout();
+        let (outer_rect, nested_rect) = (result.clusters[outer], result.clusters[nested]);
+        assert!(contains(&outer_rect, &result.nodes[a]));
+        assert!(contains(&nested_rect, &result.nodes[b]));
+        assert!(contains(&outer_rect, &nested_rect));
+        assert!(!contains(&outer_rect, &result.nodes[c]));
+        assert!(result.nodes[a].y >= outer_rect.y + 20.0);
+    }
+
+    #[test]
+    fn test_edge_endpoints_on_real_nodes() {
+        let mut graph = GraphLayout::new(SugiyamaConfig::default());
+        let package = graph.add_cluster(GraphCluster::new(None));
+        let a = graph.add_node(node());
+        let b = graph.add_node_in(node(), package);
+        graph.add_edge(GraphEdge::new(a, b));
+
+        let result = graph.layout();
+        let points = &result.edges[0].points;
+        let end = points[points.len() - 1];
+        assert!((end.y - result.nodes[b].y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_label_reserves_space() {
+        let mut graph = GraphLayout::new(SugiyamaConfig::default());
+        let a = graph.add_node(node());
+        let b = graph.add_node(node());
+        graph.add_edge(GraphEdge::new(a, b).with_label(Size::new(80.0, 14.0)));
+
+        let result = graph.layout();
+        let label = result.edges[0].label.expect("label placed");
+        let (ra, rb) = (result.nodes[a], result.nodes[b]);
+        assert!(label.y >= ra.y + ra.height);
+        assert!(label.y + label.height <= rb.y);
+        // Линия проходит вдоль левого края метки
+        assert!(result.edges[0]
+            .points
+            .iter()
+            .any(|p| (p.x - (label.x - LABEL_GAP)).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_edge_to_cluster() {
+        let mut graph = GraphLayout::new(SugiyamaConfig::default());
+        let a = graph.add_node(node());
+        let composite = graph.add_cluster(GraphCluster::new(None));
+        graph.add_node_in(node(), composite);
+        graph.add_edge(GraphEdge::between(
+            Endpoint::Node(a),
+            Endpoint::Cluster(composite),
+        ));
+
+        let result = graph.layout();
+        let cluster = result.clusters[composite];
+        let points = &result.edges[0].points;
+        assert!(result.nodes[a].y + result.nodes[a].height <= cluster.y);
+        assert!((points[points.len() - 1].y - cluster.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ports() {
+        let mut graph = GraphLayout::new(SugiyamaConfig::default());
+        let a = graph.add_node(node());
+        let b = graph.add_node(node());
+        graph.add_edge(GraphEdge::new(a, b).with_ports(Port::Right, Port::Right));
+
+        let result = graph.layout();
+        let points = &result.edges[0].points;
+        assert_eq!(points[0], result.nodes[a].right_center());
+        assert_eq!(points[points.len() - 1], result.nodes[b].right_center());
+    }
+
+    #[test]
+    fn test_self_loop_with_label() {
+        let mut graph = GraphLayout::new(SugiyamaConfig::default());
+        let a = graph.add_node(node());
+        graph.add_edge(GraphEdge::new(a, a).with_label(Size::new(40.0, 14.0)));
+
+        let result = graph.layout();
+        let label = result.edges[0].label.expect("label placed");
+        assert!(label.x > result.nodes[a].x + result.nodes[a].width);
+        assert!(result.width >= label.x + label.width);
+    }
+}
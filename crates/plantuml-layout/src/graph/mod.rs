@@ -0,0 +1,52 @@
+//! # Graph Layout
+//!
+//! Переиспользуемый иерархический layout направленных графов (алгоритм Sugiyama).
+//!
+//! ## Этапы
+//!
+//! 1. **Удаление циклов** - обращение обратных рёбер
+//! 2. **Присвоение слоёв** - longest path
+//! 3. **Фиктивные узлы** - длинные рёбра разбиваются по слоям
+//! 4. **Минимизация пересечений** - медиана/барицентр + transpose
+//! 5. **Координаты** - Brandes-Köpf
+//! 6. **Маршрутизация рёбер** - ломаные/сплайны через фиктивные узлы
+//! 7. **Обход узлов** - [`EdgeRouter`]: spline, polyline или ortho
+//!    (`skinparam linetype`), разведение параллельных рёбер, размещение подписей
+//!
+//! Поверх алгоритма построен [`GraphLayout`] — общая модель графовых диаграмм
+//! с кластерами, портами и метками рёбер, в которую отображаются движки
+//! классов, компонентов, прецедентов, объектов, ER и состояний.
+//!
+//! ## Пример
+//!
+//! ```
+//! use plantuml_layout::graph::{LayeredGraph, SugiyamaLayout};
+//! use plantuml_layout::Size;
+//!
+//! let mut graph = LayeredGraph::new();
+//! let a = graph.add_node(Size::new(100.0, 40.0));
+//! let b = graph.add_node(Size::new(80.0, 40.0));
+//! graph.add_edge(a, b);
+//!
+//! let result = SugiyamaLayout::default().layout(&graph);
+//! assert!(result.nodes[a].bounds.y < result.nodes[b].bounds.y);
+//! ```
+
+pub mod brandes_kopf;
+pub mod config;
+pub mod layered;
+pub mod layout;
+pub mod ordering;
+pub mod router;
+pub mod routing;
+pub mod sugiyama;
+
+pub use config::{LineType, RankDir, SugiyamaConfig};
+pub use layered::{LayeredGraph, ProperGraph};
+pub use layout::{
+    label_size, Endpoint, GraphCluster, GraphEdge, GraphLayout, GraphLayoutResult, GraphNode,
+    Port, RoutedEdge,
+};
+pub use router::{EdgeRouter, Route, RouteRequest};
+pub use routing::EdgeRoute;
+pub use sugiyama::{NodePlacement, SugiyamaLayout, SugiyamaResult};
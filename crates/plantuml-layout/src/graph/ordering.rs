@@ -0,0 +1,280 @@
+//! Минимизация пересечений рёбер.
+//!
+//! Итеративные проходы сверху вниз и снизу вверх: узлы слоя сортируются по
+//! взвешенной медиане позиций соседей (при равенстве — по барицентру), после
+//! каждого прохода выполняются локальные перестановки соседних узлов (transpose).
+//! Сохраняется лучший найденный порядок.
+
+use super::layered::ProperGraph;
+
+/// Упорядочивает узлы внутри слоёв, минимизируя количество пересечений
+pub fn minimize_crossings(graph: &mut ProperGraph, max_sweeps: usize, transpose: bool) {
+    if graph.layers.len() < 2 {
+        return;
+    }
+
+    initial_order(graph);
+    if transpose {
+        transpose_layers(graph);
+    }
+
+    let mut best = graph.layers.clone();
+    let mut best_crossings = count_crossings(graph);
+
+    for sweep in 0..max_sweeps {
+        if best_crossings == 0 {
+            break;
+        }
+
+        if sweep % 2 == 0 {
+            // Сверху вниз: ориентируемся на верхних соседей
+            for layer in 1..graph.layers.len() {
+                reorder_layer(graph, layer, true);
+            }
+        } else {
+            // Снизу вверх: ориентируемся на нижних соседей
+            for layer in (0..graph.layers.len() - 1).rev() {
+                reorder_layer(graph, layer, false);
+            }
+        }
+
+        if transpose {
+            transpose_layers(graph);
+        }
+
+        let crossings = count_crossings(graph);
+        if crossings < best_crossings {
+            best_crossings = crossings;
+            best = graph.layers.clone();
+        }
+    }
+
+    graph.layers = best;
+}
+
+/// Начальный порядок: обход в глубину от узлов верхнего слоя
+///
+/// Узлы, достижимые друг из друга, оказываются рядом, что уменьшает
+/// количество итераций до сходимости.
+fn initial_order(graph: &mut ProperGraph) {
+    let n = graph.node_count();
+    let mut visited = vec![false; n];
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); graph.layers.len()];
+
+    let mut roots: Vec<usize> = graph.layers.iter().flatten().copied().collect();
+    roots.sort_by_key(|&v| (graph.layer[v], v));
+
+    for root in roots {
+        if visited[root] {
+            continue;
+        }
+        let mut stack = vec![root];
+        while let Some(v) = stack.pop() {
+            if visited[v] {
+                continue;
+            }
+            visited[v] = true;
+            layers[graph.layer[v]].push(v);
+            for &w in graph.succs[v].iter().rev() {
+                if !visited[w] {
+                    stack.push(w);
+                }
+            }
+        }
+    }
+
+    graph.layers = layers;
+}
+
+/// Переупорядочивает один слой по медиане позиций соседей
+fn reorder_layer(graph: &mut ProperGraph, layer: usize, use_upper: bool) {
+    let pos = graph.positions();
+    let nodes = graph.layers[layer].clone();
+
+    // Узлы без соседей остаются на своих местах
+    let mut movable: Vec<(usize, f64, f64)> = Vec::new();
+    let mut fixed = vec![false; nodes.len()];
+
+    for (i, &v) in nodes.iter().enumerate() {
+        let neighbors = if use_upper { &graph.preds[v] } else { &graph.succs[v] };
+        if neighbors.is_empty() {
+            fixed[i] = true;
+            continue;
+        }
+        let mut positions: Vec<usize> = neighbors.iter().map(|&w| pos[w]).collect();
+        positions.sort_unstable();
+        movable.push((v, weighted_median(&positions), barycenter(&positions)));
+    }
+
+    movable.sort_by(|a, b| {
+        a.1.partial_cmp(&b.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    let mut movable = movable.into_iter().map(|(v, _, _)| v);
+    let ordered: Vec<usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| if fixed[i] { v } else { movable.next().unwrap_or(v) })
+        .collect();
+
+    graph.layers[layer] = ordered;
+}
+
+/// Взвешенная медиана (Gansner et al.) по отсортированным позициям
+fn weighted_median(positions: &[usize]) -> f64 {
+    let len = positions.len();
+    let m = len / 2;
+    if len % 2 == 1 {
+        return positions[m] as f64;
+    }
+    if len == 2 {
+        return (positions[0] + positions[1]) as f64 / 2.0;
+    }
+
+    let left = (positions[m - 1] - positions[0]) as f64;
+    let right = (positions[len - 1] - positions[m]) as f64;
+    if left + right == 0.0 {
+        return (positions[m - 1] + positions[m]) as f64 / 2.0;
+    }
+    (positions[m - 1] as f64 * right + positions[m] as f64 * left) / (left + right)
+}
+
+/// Барицентр (среднее арифметическое) позиций
+fn barycenter(positions: &[usize]) -> f64 {
+    positions.iter().sum::<usize>() as f64 / positions.len() as f64
+}
+
+/// Локальные перестановки соседних узлов, пока они уменьшают пересечения
+fn transpose_layers(graph: &mut ProperGraph) {
+    let max_rounds = graph.node_count().max(1);
+
+    for _ in 0..max_rounds {
+        let mut improved = false;
+        let mut pos = graph.positions();
+
+        for layer in 0..graph.layers.len() {
+            for i in 1..graph.layers[layer].len() {
+                let v = graph.layers[layer][i - 1];
+                let w = graph.layers[layer][i];
+
+                let current = pair_crossings(graph, &pos, v, w);
+                let swapped = pair_crossings(graph, &pos, w, v);
+                if swapped < current {
+                    graph.layers[layer].swap(i - 1, i);
+                    pos.swap(v, w);
+                    improved = true;
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Количество пересечений рёбер узлов `left` и `right`, если `left` стоит левее
+fn pair_crossings(graph: &ProperGraph, pos: &[usize], left: usize, right: usize) -> usize {
+    let count = |a: &[usize], b: &[usize]| {
+        let mut c = 0;
+        for &x in a {
+            for &y in b {
+                if pos[x] > pos[y] {
+                    c += 1;
+                }
+            }
+        }
+        c
+    };
+
+    count(&graph.preds[left], &graph.preds[right]) + count(&graph.succs[left], &graph.succs[right])
+}
+
+/// Общее количество пересечений между всеми парами соседних слоёв
+pub fn count_crossings(graph: &ProperGraph) -> usize {
+    let pos = graph.positions();
+    (1..graph.layers.len())
+        .map(|layer| bilayer_crossings(graph, &pos, layer - 1))
+        .sum()
+}
+
+/// Пересечения между слоем `upper` и следующим за ним
+///
+/// Рёбра сортируются по позиции верхнего конца, после чего количество
+/// пересечений равно числу инверсий позиций нижних концов (дерево Фенвика).
+fn bilayer_crossings(graph: &ProperGraph, pos: &[usize], upper: usize) -> usize {
+    let lower_len = graph.layers.get(upper + 1).map_or(0, |l| l.len());
+    if lower_len == 0 {
+        return 0;
+    }
+
+    let mut ends: Vec<(usize, usize)> = Vec::new();
+    for &v in &graph.layers[upper] {
+        for &w in &graph.succs[v] {
+            ends.push((pos[v], pos[w]));
+        }
+    }
+    ends.sort_unstable();
+
+    let mut tree = vec![0usize; lower_len + 1];
+    let mut crossings = 0;
+    for (inserted, &(_, south)) in ends.iter().enumerate() {
+        // Количество уже вставленных концов с позицией <= south
+        let mut not_greater = 0;
+        let mut i = south + 1;
+        while i > 0 {
+            not_greater += tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        crossings += inserted - not_greater;
+
+        let mut i = south + 1;
+        while i <= lower_len {
+            tree[i] += 1;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    crossings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Два узла сверху, два снизу, рёбра крест-накрест
+    fn crossed_graph() -> ProperGraph {
+        let mut graph = ProperGraph::with_layers(2);
+        let a = graph.add_node(0, 10.0, 10.0, false);
+        let b = graph.add_node(0, 10.0, 10.0, false);
+        let c = graph.add_node(1, 10.0, 10.0, false);
+        let d = graph.add_node(1, 10.0, 10.0, false);
+        graph.connect(a, d);
+        graph.connect(b, c);
+        graph
+    }
+
+    #[test]
+    fn test_count_crossings() {
+        let graph = crossed_graph();
+        assert_eq!(count_crossings(&graph), 1);
+    }
+
+    #[test]
+    fn test_minimize_removes_crossing() {
+        let mut graph = crossed_graph();
+        minimize_crossings(&mut graph, 8, true);
+        assert_eq!(count_crossings(&graph), 0);
+    }
+
+    #[test]
+    fn test_weighted_median() {
+        assert_eq!(weighted_median(&[1, 2, 3]), 2.0);
+        assert_eq!(weighted_median(&[0, 4]), 2.0);
+        // Левая группа плотнее — медиана смещается к ней
+        let m = weighted_median(&[0, 1, 2, 10]);
+        assert!(m > 1.0 && m < 2.0);
+    }
+}
@@ -0,0 +1,923 @@
+//! Маршрутизация рёбер с обходом узлов.
+//!
+//! [`EdgeRouter`] строит окончательную геометрию рёбер по начальным маршрутам
+//! layered layout (ломаным через фиктивные узлы) в зависимости от [`LineType`]:
+//!
+//! - **polyline** — ломаная; отрезки, задевающие чужие узлы, огибают их по углам;
+//! - **spline** — та же ломаная, сглаженная кубическими кривыми Безье;
+//! - **ortho** — кратчайший ортогональный путь со штрафом за изгибы по сетке,
+//!   построенной по границам узлов (узлы расширены на `clearance`).
+//!
+//! Параллельные рёбра между одной парой узлов разводятся на `spacing`, петли
+//! одного узла вкладываются друг в друга. Метки и кардинальности размещаются
+//! вдоль маршрута в местах, где они не перекрывают узлы, другие подписи и линии.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use plantuml_model::{Point, Rect, Size};
+
+use super::config::LineType;
+use super::routing;
+
+/// Зазор между линией ребра и подписью
+pub(crate) const LABEL_GAP: f64 = 4.0;
+
+/// Штраф ортогонального маршрута за каждый изгиб (в пикселях длины)
+const BEND_COST: f64 = 30.0;
+
+/// Запрос на маршрутизацию одного ребра
+#[derive(Debug, Clone, Default)]
+pub struct RouteRequest {
+    /// Прямоугольник исходного конца
+    pub from: Rect,
+    /// Прямоугольник целевого конца
+    pub to: Rect,
+    /// Точки излома из layered layout (без концов)
+    pub waypoints: Vec<Point>,
+    /// Фиксированная точка крепления у исходного конца (порт)
+    pub from_anchor: Option<Point>,
+    /// Фиксированная точка крепления у целевого конца
+    pub to_anchor: Option<Point>,
+    /// Размер метки
+    pub label: Option<Size>,
+    /// Место, зарезервированное под метку при layout
+    pub reserved_label: Option<Rect>,
+    /// Размер подписи у исходного конца (кардинальность)
+    pub from_label: Option<Size>,
+    /// Размер подписи у целевого конца
+    pub to_label: Option<Size>,
+}
+
+impl RouteRequest {
+    /// Создаёт запрос для ребра между двумя прямоугольниками
+    pub fn new(from: Rect, to: Rect) -> Self {
+        Self {
+            from,
+            to,
+            ..Self::default()
+        }
+    }
+
+    /// Петля (ребро узла в самого себя)
+    fn is_loop(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Построенный маршрут ребра
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Route {
+    /// Точки ломаной от исходного конца к целевому
+    pub points: Vec<Point>,
+    /// Контрольные точки кривых Безье `[p0, c1, c2, p1, ...]` (пусто — ломаная)
+    pub curve: Vec<Point>,
+    /// Область метки
+    pub label: Option<Rect>,
+    /// Область подписи у исходного конца
+    pub from_label: Option<Rect>,
+    /// Область подписи у целевого конца
+    pub to_label: Option<Rect>,
+}
+
+/// Маршрутизатор рёбер
+#[derive(Debug, Clone)]
+pub struct EdgeRouter {
+    line_type: LineType,
+    clearance: f64,
+    spacing: f64,
+}
+
+impl EdgeRouter {
+    /// Создаёт маршрутизатор для заданной формы линий
+    pub fn new(line_type: LineType) -> Self {
+        Self {
+            line_type,
+            clearance: 10.0,
+            spacing: 20.0,
+        }
+    }
+
+    /// Устанавливает минимальное расстояние от линий до чужих узлов
+    pub fn with_clearance(mut self, clearance: f64) -> Self {
+        self.clearance = clearance;
+        self
+    }
+
+    /// Устанавливает расстояние между параллельными рёбрами
+    pub fn with_spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Строит маршруты рёбер в обход `obstacles` (прямоугольников узлов)
+    pub fn route(&self, obstacles: &[Rect], requests: &[RouteRequest]) -> Vec<Route> {
+        let bundles = parallel_bundles(requests);
+
+        let mut routes: Vec<Route> = requests
+            .iter()
+            .zip(&bundles)
+            .map(|(request, &(rank, count, flipped))| {
+                let skip = endpoint_obstacles(obstacles, request);
+                let points = if request.is_loop() {
+                    routing::self_loop(&request.from, self.spacing * (rank + 1) as f64)
+                } else {
+                    let offset = (rank as f64 - (count - 1) as f64 / 2.0) * self.spacing;
+                    let offset = if flipped { -offset } else { offset };
+                    match self.line_type {
+                        LineType::Ortho => self
+                            .ortho_path(request, offset, obstacles, &skip)
+                            .unwrap_or_else(|| {
+                                self.polyline_path(request, offset, obstacles, &skip)
+                            }),
+                        LineType::Polyline | LineType::Spline => {
+                            self.polyline_path(request, offset, obstacles, &skip)
+                        }
+                    }
+                };
+                let curve = match self.line_type {
+                    LineType::Spline => routing::to_bezier(&points),
+                    LineType::Polyline | LineType::Ortho => Vec::new(),
+                };
+                Route {
+                    points,
+                    curve,
+                    ..Route::default()
+                }
+            })
+            .collect();
+
+        self.place_labels(obstacles, requests, &mut routes);
+        routes
+    }
+
+    /// Ломаная через точки layered layout с обходом чужих узлов
+    fn polyline_path(
+        &self,
+        request: &RouteRequest,
+        offset: f64,
+        obstacles: &[Rect],
+        skip: &[bool],
+    ) -> Vec<Point> {
+        let mut points = if request.waypoints.is_empty() && offset != 0.0 {
+            shifted_line(&request.from, &request.to, offset)
+        } else {
+            routing::polyline(&request.from, &request.waypoints, &request.to)
+        };
+        if let Some(anchor) = request.from_anchor {
+            points[0] = anchor;
+        }
+        if let Some(anchor) = request.to_anchor {
+            let last = points.len() - 1;
+            points[last] = anchor;
+        }
+        self.avoid_obstacles(points, obstacles, skip)
+    }
+
+    /// Заменяет отрезки, пересекающие чужие узлы, обходом по углам узла
+    fn avoid_obstacles(&self, points: Vec<Point>, obstacles: &[Rect], skip: &[bool]) -> Vec<Point> {
+        let mut result = vec![points[0]];
+        let mut pending: Vec<Point> = points[1..].iter().rev().copied().collect();
+        let mut budget = 4 * obstacles.len() + points.len();
+
+        while let Some(next) = pending.pop() {
+            let from = result[result.len() - 1];
+            let hit = (budget > 0)
+                .then(|| {
+                    obstacles
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i, _)| !skip[i])
+                        .filter_map(|(_, rect)| {
+                            let rect = inflate(rect, self.clearance / 2.0);
+                            segment_entry(from, next, &rect).map(|t| (t, rect))
+                        })
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                })
+                .flatten();
+
+            match hit
+                .and_then(|(_, rect)| detour(from, next, &inflate(&rect, self.clearance / 2.0)))
+            {
+                Some(corners) => {
+                    budget -= 1;
+                    pending.push(next);
+                    pending.extend(corners.into_iter().rev());
+                }
+                None => result.push(next),
+            }
+        }
+        result
+    }
+
+    /// Ортогональный маршрут по сетке видимости (`None`, если пути нет)
+    fn ortho_path(
+        &self,
+        request: &RouteRequest,
+        offset: f64,
+        obstacles: &[Rect],
+        skip: &[bool],
+    ) -> Option<Vec<Point>> {
+        let sources = self.ports(&request.from, request.from_anchor, offset);
+        let targets = self.ports(&request.to, request.to_anchor, offset);
+
+        let mut blocks: Vec<Rect> = obstacles
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !skip[i])
+            .map(|(_, rect)| inflate(rect, self.clearance))
+            .collect();
+        blocks.push(inflate(&request.from, self.clearance));
+        blocks.push(inflate(&request.to, self.clearance));
+
+        let stubs = sources.iter().chain(&targets).map(|p| p.stub);
+        let grid = Grid::new(&blocks, stubs);
+
+        let source_states: Vec<(usize, Dir)> = sources
+            .iter()
+            .filter_map(|p| grid.index(p.stub).map(|v| (v, p.dir)))
+            .collect();
+        let target_states: HashMap<usize, Vec<(usize, Dir)>> =
+            targets
+                .iter()
+                .enumerate()
+                .fold(HashMap::new(), |mut map, (i, p)| {
+                    if let Some(v) = grid.index(p.stub) {
+                        map.entry(v)
+                            .or_insert_with(Vec::new)
+                            .push((i, p.dir.opposite()));
+                    }
+                    map
+                });
+
+        let (path, target) = grid.shortest_path(&blocks, &source_states, &target_states)?;
+        let source = sources
+            .iter()
+            .find(|p| grid.index(p.stub) == path.first().copied())?;
+
+        let mut points = vec![source.anchor];
+        points.extend(path.iter().map(|&v| grid.point(v)));
+        points.push(targets[target].anchor);
+        Some(simplify(points))
+    }
+
+    /// Возможные точки выхода ребра из прямоугольника для ортогонального маршрута
+    fn ports(&self, rect: &Rect, anchor: Option<Point>, offset: f64) -> Vec<OrthoPort> {
+        let along_x = offset.clamp(-rect.width * 0.4, rect.width * 0.4);
+        let along_y = offset.clamp(-rect.height * 0.4, rect.height * 0.4);
+        let all = [
+            (Point::new(rect.center().x + along_x, rect.y), Dir::Up),
+            (
+                Point::new(rect.center().x + along_x, rect.y + rect.height),
+                Dir::Down,
+            ),
+            (Point::new(rect.x, rect.center().y + along_y), Dir::Left),
+            (
+                Point::new(rect.x + rect.width, rect.center().y + along_y),
+                Dir::Right,
+            ),
+        ];
+        let side = anchor.map(|a| {
+            all.iter()
+                .min_by(|x, y| distance(x.0, a).total_cmp(&distance(y.0, a)))
+                .map(|&(_, dir)| dir)
+                .unwrap_or(Dir::Down)
+        });
+
+        all.iter()
+            .filter(|&&(_, dir)| side.map_or(true, |s| s == dir))
+            .map(|&(point, dir)| {
+                let anchor = match (anchor, side) {
+                    (Some(a), Some(_)) => a,
+                    _ => point,
+                };
+                OrthoPort {
+                    anchor,
+                    stub: dir.step(anchor, self.clearance),
+                    dir,
+                }
+            })
+            .collect()
+    }
+
+    /// Размещает метки и подписи концов рёбер в свободных местах
+    fn place_labels(&self, obstacles: &[Rect], requests: &[RouteRequest], routes: &mut [Route]) {
+        let mut placed: Vec<Rect> = Vec::new();
+
+        for i in 0..routes.len() {
+            let request = &requests[i];
+            if routes[i].points.len() < 2 {
+                continue;
+            }
+            let score = |rect: &Rect, placed: &[Rect]| {
+                let nodes = obstacles.iter().filter(|o| overlaps(o, rect)).count();
+                let labels = placed.iter().filter(|o| overlaps(o, rect)).count();
+                let lines = routes
+                    .iter()
+                    .flat_map(|r| r.points.windows(2))
+                    .filter(|s| segment_entry(s[0], s[1], rect).is_some())
+                    .count();
+                (nodes * 1000 + labels * 500 + lines * 10) as f64
+            };
+            let best = |candidates: Vec<Rect>, placed: &[Rect]| {
+                candidates
+                    .into_iter()
+                    .enumerate()
+                    .map(|(order, rect)| (score(&rect, placed) + order as f64, rect))
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .map(|(_, rect)| rect)
+            };
+
+            let points = &routes[i].points;
+            let label = request.label.and_then(|size| {
+                let mut candidates = Vec::new();
+                if let Some(reserved) = request.reserved_label {
+                    let near = inflate(&reserved, LABEL_GAP + 1.0);
+                    if points
+                        .windows(2)
+                        .any(|s| segment_entry(s[0], s[1], &near).is_some())
+                    {
+                        candidates.push(reserved);
+                    }
+                }
+                candidates.extend(along_route(points, size));
+                best(candidates, &placed)
+            });
+            placed.extend(label);
+
+            let from_label = request
+                .from_label
+                .and_then(|size| best(near_end(points[0], points[1], size), &placed));
+            placed.extend(from_label);
+
+            let n = points.len();
+            let to_label = request
+                .to_label
+                .and_then(|size| best(near_end(points[n - 1], points[n - 2], size), &placed));
+            placed.extend(to_label);
+
+            let route = &mut routes[i];
+            route.label = label;
+            route.from_label = from_label;
+            route.to_label = to_label;
+        }
+    }
+}
+
+impl Default for EdgeRouter {
+    fn default() -> Self {
+        Self::new(LineType::default())
+    }
+}
+
+/// Направление движения по ортогональной сетке
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Dir {
+    const ALL: [Dir; 4] = [Dir::Up, Dir::Down, Dir::Left, Dir::Right];
+
+    fn opposite(self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+
+    fn step(self, p: Point, length: f64) -> Point {
+        match self {
+            Self::Up => Point::new(p.x, p.y - length),
+            Self::Down => Point::new(p.x, p.y + length),
+            Self::Left => Point::new(p.x - length, p.y),
+            Self::Right => Point::new(p.x + length, p.y),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Точка выхода ортогонального маршрута из узла
+#[derive(Debug, Clone, Copy)]
+struct OrthoPort {
+    /// Точка на границе узла
+    anchor: Point,
+    /// Конец короткого отрезка наружу (на границе расширенного узла)
+    stub: Point,
+    /// Направление выхода наружу
+    dir: Dir,
+}
+
+/// Сетка ортогональной маршрутизации: линии по границам препятствий и между ними
+struct Grid {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+}
+
+impl Grid {
+    fn new(blocks: &[Rect], extra: impl Iterator<Item = Point>) -> Self {
+        let mut xs = Vec::new();
+        let mut ys = Vec::new();
+        for rect in blocks {
+            xs.extend([rect.x, rect.x + rect.width]);
+            ys.extend([rect.y, rect.y + rect.height]);
+        }
+        for p in extra {
+            xs.push(p.x);
+            ys.push(p.y);
+        }
+        Self {
+            xs: with_midlines(xs),
+            ys: with_midlines(ys),
+        }
+    }
+
+    fn index(&self, p: Point) -> Option<usize> {
+        let i = self.xs.iter().position(|&x| (x - p.x).abs() < 1e-6)?;
+        let j = self.ys.iter().position(|&y| (y - p.y).abs() < 1e-6)?;
+        Some(j * self.xs.len() + i)
+    }
+
+    fn point(&self, v: usize) -> Point {
+        Point::new(self.xs[v % self.xs.len()], self.ys[v / self.xs.len()])
+    }
+
+    fn neighbor(&self, v: usize, dir: Dir) -> Option<usize> {
+        let (i, j) = (v % self.xs.len(), v / self.xs.len());
+        let (i, j) = match dir {
+            Dir::Up => (i, j.checked_sub(1)?),
+            Dir::Down => (i, j + 1),
+            Dir::Left => (i.checked_sub(1)?, j),
+            Dir::Right => (i + 1, j),
+        };
+        (i < self.xs.len() && j < self.ys.len()).then_some(j * self.xs.len() + i)
+    }
+
+    /// Дейкстра по состояниям (узел сетки, направление) со штрафом за изгибы
+    ///
+    /// Возвращает узлы пути и индекс достигнутой цели.
+    fn shortest_path(
+        &self,
+        blocks: &[Rect],
+        sources: &[(usize, Dir)],
+        targets: &HashMap<usize, Vec<(usize, Dir)>>,
+    ) -> Option<(Vec<usize>, usize)> {
+        let state = |v: usize, dir: Dir| v * 4 + dir.index();
+        let mut cost = vec![f64::INFINITY; self.xs.len() * self.ys.len() * 4];
+        let mut prev: Vec<Option<usize>> = vec![None; cost.len()];
+        let mut heap = BinaryHeap::new();
+        for &(v, dir) in sources {
+            cost[state(v, dir)] = 0.0;
+            heap.push(Visit(0.0, state(v, dir)));
+        }
+
+        let mut best: Option<(f64, usize, usize)> = None;
+        while let Some(Visit(c, s)) = heap.pop() {
+            if c > cost[s] || best.is_some_and(|(b, _, _)| c >= b) {
+                continue;
+            }
+            let (v, dir) = (s / 4, Dir::ALL[s % 4]);
+            if let Some(ends) = targets.get(&v) {
+                for &(target, inward) in ends {
+                    let total = c + if inward == dir { 0.0 } else { BEND_COST };
+                    if best.map_or(true, |(b, _, _)| total < b) {
+                        best = Some((total, s, target));
+                    }
+                }
+            }
+            for next_dir in Dir::ALL {
+                if next_dir == dir.opposite() {
+                    continue;
+                }
+                let Some(u) = self.neighbor(v, next_dir) else {
+                    continue;
+                };
+                let (a, b) = (self.point(v), self.point(u));
+                let mid = Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+                if blocks.iter().any(|r| contains_strictly(r, mid)) {
+                    continue;
+                }
+                let bend = if next_dir == dir { 0.0 } else { BEND_COST };
+                let next_cost = c + distance(a, b) + bend;
+                let t = state(u, next_dir);
+                if next_cost < cost[t] {
+                    cost[t] = next_cost;
+                    prev[t] = Some(s);
+                    heap.push(Visit(next_cost, t));
+                }
+            }
+        }
+
+        let (_, mut s, target) = best?;
+        let mut path = vec![s / 4];
+        while let Some(p) = prev[s] {
+            s = p;
+            path.push(s / 4);
+        }
+        path.reverse();
+        path.dedup();
+        Some((path, target))
+    }
+}
+
+/// Элемент очереди Дейкстры (минимальная стоимость — наверху)
+struct Visit(f64, usize);
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0)
+    }
+}
+
+/// Ранг ребра в пучке параллельных, размер пучка и обратная ориентация
+///
+/// Пучок — рёбра между одной парой прямоугольников в любом направлении.
+fn parallel_bundles(requests: &[RouteRequest]) -> Vec<(usize, usize, bool)> {
+    let key = |r: &Rect| [r.x, r.y, r.width, r.height].map(f64::to_bits);
+    let mut counts: HashMap<_, usize> = HashMap::new();
+    let mut result = Vec::with_capacity(requests.len());
+    for request in requests {
+        let (a, b) = (key(&request.from), key(&request.to));
+        let flipped = a > b;
+        let pair = if flipped { (b, a) } else { (a, b) };
+        let rank = counts.entry(pair).or_insert(0);
+        result.push((*rank, 0, flipped));
+        *rank += 1;
+    }
+    for (request, entry) in requests.iter().zip(&mut result) {
+        let (a, b) = (key(&request.from), key(&request.to));
+        let pair = if a > b { (b, a) } else { (a, b) };
+        entry.1 = counts[&pair];
+    }
+    result
+}
+
+/// Препятствия, совпадающие с концами ребра или лежащие внутри них
+fn endpoint_obstacles(obstacles: &[Rect], request: &RouteRequest) -> Vec<bool> {
+    obstacles
+        .iter()
+        .map(|o| contains(&request.from, o) || contains(&request.to, o))
+        .collect()
+}
+
+/// Прямая между центрами прямоугольников, сдвинутая перпендикулярно на `offset`
+fn shifted_line(from: &Rect, to: &Rect, offset: f64) -> Vec<Point> {
+    let (a, b) = (from.center(), to.center());
+    let length = distance(a, b);
+    if length < f64::EPSILON {
+        return routing::polyline(from, &[], to);
+    }
+    let d = Point::new((b.x - a.x) / length, (b.y - a.y) / length);
+    let n = Point::new(-d.y, d.x);
+    let half = |r: &Rect| n.x.abs() * r.width / 2.0 + n.y.abs() * r.height / 2.0;
+    let limit = half(from).min(half(to)) * 0.8;
+    let offset = offset.clamp(-limit, limit);
+
+    let start = Point::new(a.x + n.x * offset, a.y + n.y * offset);
+    let end = Point::new(b.x + n.x * offset, b.y + n.y * offset);
+    vec![
+        exit_point(from, start, d),
+        exit_point(to, end, Point::new(-d.x, -d.y)),
+    ]
+}
+
+/// Точка выхода луча из `origin` (внутри прямоугольника) в направлении `dir`
+fn exit_point(rect: &Rect, origin: Point, dir: Point) -> Point {
+    let along = |o: f64, d: f64, lo: f64, hi: f64| {
+        if d > f64::EPSILON {
+            (hi - o) / d
+        } else if d < -f64::EPSILON {
+            (lo - o) / d
+        } else {
+            f64::INFINITY
+        }
+    };
+    let t = along(origin.x, dir.x, rect.x, rect.x + rect.width)
+        .min(along(origin.y, dir.y, rect.y, rect.y + rect.height))
+        .max(0.0);
+    Point::new(origin.x + dir.x * t, origin.y + dir.y * t)
+}
+
+/// Обход прямоугольника: кратчайший путь через один или два его угла
+fn detour(from: Point, to: Point, rect: &Rect) -> Option<Vec<Point>> {
+    let (x0, y0) = (rect.x, rect.y);
+    let (x1, y1) = (rect.x + rect.width, rect.y + rect.height);
+    let corners = [
+        Point::new(x0, y0),
+        Point::new(x1, y0),
+        Point::new(x1, y1),
+        Point::new(x0, y1),
+    ];
+
+    let mut options: Vec<Vec<Point>> = corners.iter().map(|&c| vec![c]).collect();
+    for i in 0..4 {
+        options.push(vec![corners[i], corners[(i + 1) % 4]]);
+        options.push(vec![corners[(i + 1) % 4], corners[i]]);
+    }
+
+    options
+        .into_iter()
+        .filter(|via| {
+            let mut path = vec![from];
+            path.extend(via);
+            path.push(to);
+            path.windows(2)
+                .all(|s| segment_entry(s[0], s[1], rect).is_none())
+        })
+        .min_by(|a, b| {
+            let length = |via: &Vec<Point>| {
+                let mut path = vec![from];
+                path.extend(via);
+                path.push(to);
+                path.windows(2).map(|s| distance(s[0], s[1])).sum::<f64>()
+            };
+            length(a).total_cmp(&length(b))
+        })
+}
+
+/// Кандидаты на место метки вдоль маршрута (от середины к краям)
+fn along_route(points: &[Point], size: Size) -> Vec<Rect> {
+    let lengths: Vec<f64> = points.windows(2).map(|s| distance(s[0], s[1])).collect();
+    let total: f64 = lengths.iter().sum();
+
+    let mut spots: Vec<(f64, Point, Point)> = Vec::new();
+    let mut start = 0.0;
+    for (s, &length) in points.windows(2).zip(&lengths) {
+        for t in [0.5, 0.3, 0.7] {
+            let at = Point::new(
+                s[0].x + (s[1].x - s[0].x) * t,
+                s[0].y + (s[1].y - s[0].y) * t,
+            );
+            let position = start + length * t;
+            spots.push((
+                (position - total / 2.0).abs(),
+                at,
+                Point::new(s[1].x - s[0].x, s[1].y - s[0].y),
+            ));
+        }
+        start += length;
+    }
+    spots.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let (w, h) = (size.width, size.height);
+    spots
+        .into_iter()
+        .flat_map(|(_, at, d)| {
+            if d.y.abs() >= d.x.abs() {
+                [
+                    Rect::new(at.x + LABEL_GAP, at.y - h / 2.0, w, h),
+                    Rect::new(at.x - LABEL_GAP - w, at.y - h / 2.0, w, h),
+                ]
+            } else {
+                [
+                    Rect::new(at.x - w / 2.0, at.y - LABEL_GAP - h, w, h),
+                    Rect::new(at.x - w / 2.0, at.y + LABEL_GAP, w, h),
+                ]
+            }
+        })
+        .collect()
+}
+
+/// Кандидаты на место подписи у конца ребра `end` (линия уходит к `next`)
+fn near_end(end: Point, next: Point, size: Size) -> Vec<Rect> {
+    let (w, h) = (size.width, size.height);
+    let gap = LABEL_GAP * 2.0;
+    if (next.y - end.y).abs() >= (next.x - end.x).abs() {
+        let y = if next.y > end.y {
+            end.y + gap
+        } else {
+            end.y - gap - h
+        };
+        vec![
+            Rect::new(end.x - gap - w, y, w, h),
+            Rect::new(end.x + gap, y, w, h),
+        ]
+    } else {
+        let x = if next.x > end.x {
+            end.x + gap
+        } else {
+            end.x - gap - w
+        };
+        vec![
+            Rect::new(x, end.y - gap - h, w, h),
+            Rect::new(x, end.y + gap, w, h),
+        ]
+    }
+}
+
+/// Параметр входа отрезка во внутренность прямоугольника (`None`, если не пересекает)
+fn segment_entry(a: Point, b: Point, rect: &Rect) -> Option<f64> {
+    const EPS: f64 = 1e-6;
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    let d = Point::new(b.x - a.x, b.y - a.y);
+    let clips = [
+        (-d.x, a.x - rect.x),
+        (d.x, rect.x + rect.width - a.x),
+        (-d.y, a.y - rect.y),
+        (d.y, rect.y + rect.height - a.y),
+    ];
+    for (p, q) in clips {
+        if p.abs() < EPS {
+            if q <= EPS {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    (t1 - t0 > EPS).then_some(t0)
+}
+
+/// Прямоугольник, расширенный на `by` со всех сторон
+fn inflate(rect: &Rect, by: f64) -> Rect {
+    Rect::new(
+        rect.x - by,
+        rect.y - by,
+        rect.width + by * 2.0,
+        rect.height + by * 2.0,
+    )
+}
+
+/// `inner` целиком внутри `outer`
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width <= outer.x + outer.width
+        && inner.y + inner.height <= outer.y + outer.height
+}
+
+/// Точка строго внутри прямоугольника
+fn contains_strictly(rect: &Rect, p: Point) -> bool {
+    p.x > rect.x + 1e-6
+        && p.x < rect.x + rect.width - 1e-6
+        && p.y > rect.y + 1e-6
+        && p.y < rect.y + rect.height - 1e-6
+}
+
+/// Прямоугольники перекрываются (касание не считается)
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+/// Сортирует координаты, убирает дубли и добавляет середины между соседними
+fn with_midlines(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(f64::total_cmp);
+    values.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+    let mids: Vec<f64> = values.windows(2).map(|w| (w[0] + w[1]) / 2.0).collect();
+    values.extend(mids);
+    values.sort_by(f64::total_cmp);
+    values
+}
+
+/// Убирает промежуточные точки на одной прямой
+fn simplify(points: Vec<Point>) -> Vec<Point> {
+    let mut result: Vec<Point> = Vec::with_capacity(points.len());
+    for p in points {
+        if result.last().is_some_and(|&q| distance(p, q) < 1e-6) {
+            continue;
+        }
+        if result.len() >= 2 {
+            let a = result[result.len() - 2];
+            let b = result[result.len() - 1];
+            let cross = (b.x - a.x) * (p.y - b.y) - (b.y - a.y) * (p.x - b.x);
+            if cross.abs() < 1e-6 {
+                result.pop();
+            }
+        }
+        result.push(p);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crosses_any(points: &[Point], rect: &Rect) -> bool {
+        points
+            .windows(2)
+            .any(|s| segment_entry(s[0], s[1], rect).is_some())
+    }
+
+    #[test]
+    fn test_line_type_parse() {
+        assert_eq!(LineType::parse("ortho"), Some(LineType::Ortho));
+        assert_eq!(LineType::parse("Polyline"), Some(LineType::Polyline));
+        assert_eq!(LineType::parse("splines"), Some(LineType::Spline));
+        assert_eq!(LineType::parse("curved"), None);
+    }
+
+    #[test]
+    fn test_polyline_avoids_obstacle() {
+        let from = Rect::new(0.0, 0.0, 40.0, 40.0);
+        let to = Rect::new(0.0, 300.0, 40.0, 40.0);
+        let blocker = Rect::new(-20.0, 150.0, 80.0, 40.0);
+        let obstacles = [from, to, blocker];
+
+        let routes =
+            EdgeRouter::new(LineType::Polyline).route(&obstacles, &[RouteRequest::new(from, to)]);
+        let points = &routes[0].points;
+        assert!(points.len() > 2);
+        assert!(!crosses_any(points, &blocker));
+        assert!(routes[0].curve.is_empty());
+    }
+
+    #[test]
+    fn test_ortho_route_is_orthogonal_and_avoids_obstacle() {
+        let from = Rect::new(0.0, 0.0, 60.0, 40.0);
+        let to = Rect::new(200.0, 300.0, 60.0, 40.0);
+        let blocker = Rect::new(60.0, 120.0, 140.0, 60.0);
+        let obstacles = [from, to, blocker];
+
+        let routes =
+            EdgeRouter::new(LineType::Ortho).route(&obstacles, &[RouteRequest::new(from, to)]);
+        let points = &routes[0].points;
+        assert!(points.len() >= 3);
+        for s in points.windows(2) {
+            assert!((s[0].x - s[1].x).abs() < 1e-6 || (s[0].y - s[1].y).abs() < 1e-6);
+        }
+        assert!(!crosses_any(points, &blocker));
+    }
+
+    #[test]
+    fn test_spline_has_bezier_control_points() {
+        let from = Rect::new(0.0, 0.0, 40.0, 40.0);
+        let to = Rect::new(100.0, 200.0, 40.0, 40.0);
+        let mut request = RouteRequest::new(from, to);
+        request.waypoints = vec![Point::new(60.0, 100.0)];
+
+        let routes = EdgeRouter::new(LineType::Spline).route(&[from, to], &[request]);
+        let route = &routes[0];
+        assert_eq!(route.curve.len(), 3 * (route.points.len() - 1) + 1);
+        assert_eq!(route.curve[0], route.points[0]);
+    }
+
+    #[test]
+    fn test_parallel_edges_are_spread() {
+        let a = Rect::new(0.0, 0.0, 100.0, 40.0);
+        let b = Rect::new(0.0, 200.0, 100.0, 40.0);
+        let requests = [RouteRequest::new(a, b), RouteRequest::new(b, a)];
+
+        let routes = EdgeRouter::new(LineType::Polyline).route(&[a, b], &requests);
+        let x0 = routes[0].points[0].x;
+        let x1 = routes[1].points[routes[1].points.len() - 1].x;
+        assert!((x0 - x1).abs() >= 19.0);
+    }
+
+    #[test]
+    fn test_labels_do_not_overlap() {
+        let a = Rect::new(0.0, 0.0, 100.0, 40.0);
+        let b = Rect::new(0.0, 200.0, 100.0, 40.0);
+        let mut first = RouteRequest::new(a, b);
+        first.label = Some(Size::new(60.0, 16.0));
+        first.from_label = Some(Size::new(7.0, 16.0));
+        first.to_label = Some(Size::new(7.0, 16.0));
+        let mut second = first.clone();
+        second.from = b;
+        second.to = a;
+
+        let routes = EdgeRouter::new(LineType::Polyline).route(&[a, b], &[first, second]);
+        let rects: Vec<Rect> = routes
+            .iter()
+            .flat_map(|r| [r.label, r.from_label, r.to_label])
+            .map(Option::unwrap)
+            .collect();
+        for (i, r) in rects.iter().enumerate() {
+            assert!(!overlaps(r, &a) && !overlaps(r, &b));
+            for other in &rects[i + 1..] {
+                assert!(!overlaps(r, other));
+            }
+        }
+    }
+}
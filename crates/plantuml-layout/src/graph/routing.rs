@@ -0,0 +1,143 @@
+//! Маршрутизация рёбер через позиции фиктивных узлов.
+//!
+//! Ломаная проходит через каждый фиктивный узел вертикальным отрезком на всю
+//! высоту его слоя, поэтому длинные рёбра не пересекают узлы промежуточных
+//! слоёв. Концы ломаной обрезаются по границам исходного и целевого узлов.
+
+use plantuml_model::{Point, Rect};
+
+/// Маршрут ребра
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EdgeRoute {
+    /// Точки ломаной от исходного узла к целевому
+    pub points: Vec<Point>,
+    /// Ребро было обращено при удалении циклов
+    pub reversed: bool,
+}
+
+impl EdgeRoute {
+    /// Создаёт маршрут
+    pub fn new(points: Vec<Point>, reversed: bool) -> Self {
+        Self { points, reversed }
+    }
+
+    /// Контрольные точки кубических сплайнов Безье через точки ломаной
+    ///
+    /// Формат: `[p0, c1, c2, p1, c1, c2, p2, ...]` — `3 * (n - 1) + 1` точек.
+    pub fn spline(&self) -> Vec<Point> {
+        to_bezier(&self.points)
+    }
+}
+
+/// Точка пересечения луча из центра прямоугольника к `toward` с его границей
+pub fn clip_to_rect(rect: &Rect, toward: Point) -> Point {
+    let center = rect.center();
+    let dx = toward.x - center.x;
+    let dy = toward.y - center.y;
+    if dx.abs() < f64::EPSILON && dy.abs() < f64::EPSILON {
+        return center;
+    }
+
+    let half_w = rect.width / 2.0;
+    let half_h = rect.height / 2.0;
+    let scale_x = if dx.abs() < f64::EPSILON { f64::INFINITY } else { half_w / dx.abs() };
+    let scale_y = if dy.abs() < f64::EPSILON { f64::INFINITY } else { half_h / dy.abs() };
+    let scale = scale_x.min(scale_y).min(1.0);
+
+    Point::new(center.x + dx * scale, center.y + dy * scale)
+}
+
+/// Строит ломаную между двумя узлами через промежуточные точки
+///
+/// `waypoints` — точки излома (обычно верх и низ каждого фиктивного узла).
+pub fn polyline(from: &Rect, waypoints: &[Point], to: &Rect) -> Vec<Point> {
+    let first_target = waypoints.first().copied().unwrap_or_else(|| to.center());
+    let last_source = waypoints.last().copied().unwrap_or_else(|| from.center());
+
+    let mut points = Vec::with_capacity(waypoints.len() + 2);
+    points.push(clip_to_rect(from, first_target));
+    points.extend_from_slice(waypoints);
+    points.push(clip_to_rect(to, last_source));
+    points
+}
+
+/// Петля для ребра узла в самого себя (справа от узла)
+pub fn self_loop(rect: &Rect, size: f64) -> Vec<Point> {
+    let right = rect.x + rect.width;
+    let cy = rect.y + rect.height / 2.0;
+    let dy = (rect.height / 4.0).min(size);
+    vec![
+        Point::new(right, cy - dy),
+        Point::new(right + size, cy - dy),
+        Point::new(right + size, cy + dy),
+        Point::new(right, cy + dy),
+    ]
+}
+
+/// Преобразует ломаную в кубические сплайны Безье (Catmull-Rom)
+///
+/// Кривая проходит через все точки ломаной; касательные в точках
+/// определяются соседними точками.
+pub fn to_bezier(points: &[Point]) -> Vec<Point> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+
+    let n = points.len();
+    let at = |i: isize| points[i.clamp(0, n as isize - 1) as usize];
+
+    let mut result = Vec::with_capacity(3 * (n - 1) + 1);
+    result.push(points[0]);
+    for i in 0..n - 1 {
+        let i = i as isize;
+        let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+        result.push(Point::new(
+            p1.x + (p2.x - p0.x) / 6.0,
+            p1.y + (p2.y - p0.y) / 6.0,
+        ));
+        result.push(Point::new(
+            p2.x - (p3.x - p1.x) / 6.0,
+            p2.y - (p3.y - p1.y) / 6.0,
+        ));
+        result.push(p2);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_to_rect_vertical() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let p = clip_to_rect(&rect, Point::new(50.0, 200.0));
+        assert_eq!(p, Point::new(50.0, 50.0));
+    }
+
+    #[test]
+    fn test_polyline_clips_endpoints() {
+        let from = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let to = Rect::new(0.0, 300.0, 100.0, 50.0);
+        let waypoints = [Point::new(50.0, 130.0), Point::new(50.0, 220.0)];
+
+        let points = polyline(&from, &waypoints, &to);
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[0], Point::new(50.0, 50.0));
+        assert_eq!(points[3], Point::new(50.0, 300.0));
+    }
+
+    #[test]
+    fn test_to_bezier_passes_through_points() {
+        let points = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 50.0),
+            Point::new(0.0, 100.0),
+        ];
+        let bezier = to_bezier(&points);
+        assert_eq!(bezier.len(), 7);
+        assert_eq!(bezier[0], points[0]);
+        assert_eq!(bezier[3], points[1]);
+        assert_eq!(bezier[6], points[2]);
+    }
+}
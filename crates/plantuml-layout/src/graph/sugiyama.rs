@@ -0,0 +1,548 @@
+//! Иерархический layout графа (алгоритм Sugiyama).
+//!
+//! 1. Удаление циклов — обращение обратных рёбер (DFS)
+//! 2. Присвоение слоёв — longest path с опусканием источников, затем
+//!    выравнивание пар узлов одного слоя
+//! 3. Вставка фиктивных узлов для рёбер, пересекающих несколько слоёв
+//! 4. Минимизация пересечений — см. [`super::ordering`]
+//! 5. Горизонтальные координаты — см. [`super::brandes_kopf`]
+//! 6. Маршрутизация рёбер через фиктивные узлы — см. [`super::routing`]
+
+use std::collections::VecDeque;
+
+use plantuml_model::{Point, Rect};
+
+use super::brandes_kopf::{self, Separation};
+use super::config::{RankDir, SugiyamaConfig};
+use super::layered::{LayeredGraph, ProperGraph};
+use super::ordering;
+use super::routing::{self, EdgeRoute};
+
+/// Размещение узла после layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodePlacement {
+    /// Слой узла
+    pub layer: usize,
+    /// Позиция внутри слоя
+    pub order: usize,
+    /// Bounding box узла
+    pub bounds: Rect,
+}
+
+/// Результат иерархического layout
+#[derive(Debug, Clone, Default)]
+pub struct SugiyamaResult {
+    /// Размещения узлов (в порядке индексов входного графа)
+    pub nodes: Vec<NodePlacement>,
+    /// Маршруты рёбер (в порядке индексов входного графа)
+    pub edges: Vec<EdgeRoute>,
+    /// Ширина диаграммы (с отступами)
+    pub width: f64,
+    /// Высота диаграммы (с отступами)
+    pub height: f64,
+}
+
+/// Алгоритм Sugiyama
+#[derive(Debug, Clone, Default)]
+pub struct SugiyamaLayout {
+    config: SugiyamaConfig,
+}
+
+impl SugiyamaLayout {
+    /// Создаёт алгоритм с заданной конфигурацией
+    pub fn new(config: SugiyamaConfig) -> Self {
+        Self { config }
+    }
+
+    /// Выполняет layout графа
+    pub fn layout(&self, graph: &LayeredGraph) -> SugiyamaResult {
+        let n = graph.node_count();
+        if n == 0 {
+            return SugiyamaResult::default();
+        }
+
+        let horizontal = self.config.rank_dir.is_horizontal();
+        // Для горизонтальных направлений раскладываем транспонированные узлы
+        let widths: Vec<f64> = graph
+            .sizes
+            .iter()
+            .map(|s| if horizontal { s.height } else { s.width })
+            .collect();
+        let heights: Vec<f64> = graph
+            .sizes
+            .iter()
+            .map(|s| if horizontal { s.width } else { s.height })
+            .collect();
+
+        // Шаг 1: удаление циклов (петли не участвуют в layout)
+        let reversed = remove_cycles(n, &graph.edges);
+        let acyclic: Vec<Option<(usize, usize)>> = graph
+            .edges
+            .iter()
+            .zip(&reversed)
+            .map(|(&(from, to), &rev)| match (from == to, rev) {
+                (true, _) => None,
+                (false, true) => Some((to, from)),
+                (false, false) => Some((from, to)),
+            })
+            .collect();
+
+        // Шаг 2: присвоение слоёв
+        let edges: Vec<(usize, usize)> = acyclic.iter().flatten().copied().collect();
+        let mut layers = assign_layers(n, &edges);
+        align_layers(&mut layers, &edges, &graph.same_rank);
+
+        // Шаг 3: фиктивные узлы
+        let (mut proper, chains) = build_proper_graph(&layers, &widths, &heights, &acyclic);
+
+        // Шаг 4: минимизация пересечений, затем порядок пар одного слоя
+        ordering::minimize_crossings(&mut proper, self.config.max_sweeps, self.config.transpose);
+        for &(left, right) in &graph.same_rank {
+            let pos = proper.positions();
+            if layers[left] == layers[right] && pos[left] > pos[right] {
+                proper.layers[layers[left]].swap(pos[left], pos[right]);
+            }
+        }
+
+        // Шаг 5: координаты
+        let xs = brandes_kopf::assign_x(
+            &proper,
+            Separation {
+                node_spacing: self.config.node_spacing,
+                edge_spacing: self.config.edge_spacing,
+            },
+        );
+        let (layer_top, layer_height) = self.layer_bands(&proper);
+
+        let min_x = (0..proper.node_count())
+            .map(|v| xs[v] - proper.widths[v] / 2.0)
+            .fold(f64::INFINITY, f64::min);
+        let offset_x = self.config.margin - min_x;
+        let center_x = |v: usize| xs[v] + offset_x;
+        let center_y = |v: usize| {
+            let layer = proper.layer[v];
+            layer_top[layer] + layer_height[layer] / 2.0
+        };
+
+        let pos = proper.positions();
+        let mut nodes: Vec<NodePlacement> = (0..n)
+            .map(|v| NodePlacement {
+                layer: layers[v],
+                order: pos[v],
+                bounds: Rect::new(
+                    center_x(v) - widths[v] / 2.0,
+                    center_y(v) - heights[v] / 2.0,
+                    widths[v],
+                    heights[v],
+                ),
+            })
+            .collect();
+
+        // Шаг 6: маршрутизация
+        let loop_size = self.config.edge_spacing.max(10.0);
+        let mut edges: Vec<EdgeRoute> = graph
+            .edges
+            .iter()
+            .enumerate()
+            .map(|(idx, &(from, _))| {
+                let Some(chain) = &chains[idx] else {
+                    return EdgeRoute::new(routing::self_loop(&nodes[from].bounds, loop_size), false);
+                };
+                let upper = chain[0];
+                let lower = chain[chain.len() - 1];
+
+                let mut waypoints = Vec::new();
+                for &d in &chain[1..chain.len() - 1] {
+                    let layer = proper.layer[d];
+                    let top = layer_top[layer];
+                    let bottom = top + layer_height[layer];
+                    waypoints.push(Point::new(center_x(d), top));
+                    if bottom > top {
+                        waypoints.push(Point::new(center_x(d), bottom));
+                    }
+                }
+
+                let mut points =
+                    routing::polyline(&nodes[upper].bounds, &waypoints, &nodes[lower].bounds);
+                if reversed[idx] {
+                    points.reverse();
+                }
+                EdgeRoute::new(points, reversed[idx])
+            })
+            .collect();
+
+        let mut width = nodes
+            .iter()
+            .map(|p| p.bounds.x + p.bounds.width)
+            .chain(edges.iter().flat_map(|e| e.points.iter().map(|p| p.x)))
+            .fold(0.0, f64::max)
+            + self.config.margin;
+        let mut height = layer_top.last().copied().unwrap_or(0.0)
+            + layer_height.last().copied().unwrap_or(0.0)
+            + self.config.margin;
+
+        if self.config.rank_dir != RankDir::TopToBottom {
+            self.transform(&mut nodes, &mut edges, &mut width, &mut height);
+        }
+
+        SugiyamaResult {
+            nodes,
+            edges,
+            width,
+            height,
+        }
+    }
+
+    /// Вертикальные полосы слоёв: (верх, высота) для каждого слоя
+    fn layer_bands(&self, proper: &ProperGraph) -> (Vec<f64>, Vec<f64>) {
+        let heights: Vec<f64> = proper
+            .layers
+            .iter()
+            .map(|layer| layer.iter().map(|&v| proper.heights[v]).fold(0.0, f64::max))
+            .collect();
+
+        let mut tops = Vec::with_capacity(heights.len());
+        let mut y = self.config.margin;
+        for &h in &heights {
+            tops.push(y);
+            y += h + self.config.layer_spacing;
+        }
+        (tops, heights)
+    }
+
+    /// Поворачивает/отражает результат согласно `rank_dir`
+    fn transform(
+        &self,
+        nodes: &mut [NodePlacement],
+        edges: &mut [EdgeRoute],
+        width: &mut f64,
+        height: &mut f64,
+    ) {
+        let rank_dir = self.config.rank_dir;
+        let (w, h) = (*width, *height);
+
+        let map_point = |p: Point| match rank_dir {
+            RankDir::TopToBottom => p,
+            RankDir::BottomToTop => Point::new(p.x, h - p.y),
+            RankDir::LeftToRight => Point::new(p.y, p.x),
+            RankDir::RightToLeft => Point::new(h - p.y, p.x),
+        };
+
+        for node in nodes.iter_mut() {
+            let b = node.bounds;
+            let a = map_point(Point::new(b.x, b.y));
+            let c = map_point(Point::new(b.x + b.width, b.y + b.height));
+            node.bounds = Rect::from_points(a, c);
+        }
+        for edge in edges.iter_mut() {
+            for p in &mut edge.points {
+                *p = map_point(*p);
+            }
+        }
+
+        if rank_dir.is_horizontal() {
+            *width = h;
+            *height = w;
+        }
+    }
+}
+
+/// Находит рёбра, которые нужно обратить, чтобы граф стал ацикличным
+///
+/// Обратные рёбра ищутся обходом в глубину; петли (`from == to`) не обращаются.
+pub fn remove_cycles(node_count: usize, edges: &[(usize, usize)]) -> Vec<bool> {
+    let mut adjacency = vec![Vec::new(); node_count];
+    for (idx, &(from, to)) in edges.iter().enumerate() {
+        if from != to {
+            adjacency[from].push(idx);
+        }
+    }
+
+    let mut reversed = vec![false; edges.len()];
+    let mut visited = vec![false; node_count];
+    let mut in_stack = vec![false; node_count];
+
+    for start in 0..node_count {
+        if visited[start] {
+            continue;
+        }
+        // Итеративный DFS: (узел, индекс следующего исходящего ребра)
+        let mut stack = vec![(start, 0usize)];
+        visited[start] = true;
+        in_stack[start] = true;
+
+        while let Some(top) = stack.last_mut() {
+            let (node, next) = *top;
+            if let Some(&edge_idx) = adjacency[node].get(next) {
+                top.1 += 1;
+                let target = edges[edge_idx].1;
+                if !visited[target] {
+                    visited[target] = true;
+                    in_stack[target] = true;
+                    stack.push((target, 0));
+                } else if in_stack[target] {
+                    reversed[edge_idx] = true;
+                }
+            } else {
+                in_stack[node] = false;
+                stack.pop();
+            }
+        }
+    }
+
+    reversed
+}
+
+/// Присваивает слои ацикличному графу
+///
+/// Longest path: слой узла — длина самого длинного пути от источника.
+/// Затем источники опускаются как можно ниже, к своим потомкам, чтобы
+/// не растягивать рёбра через всю диаграмму.
+pub fn assign_layers(node_count: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut outgoing = vec![Vec::new(); node_count];
+    let mut in_degree = vec![0usize; node_count];
+    for &(from, to) in edges {
+        outgoing[from].push(to);
+        in_degree[to] += 1;
+    }
+
+    // Топологическая сортировка (Kahn)
+    let mut remaining = in_degree.clone();
+    let mut queue: VecDeque<usize> = (0..node_count).filter(|&v| remaining[v] == 0).collect();
+    let mut topo = Vec::with_capacity(node_count);
+    while let Some(v) = queue.pop_front() {
+        topo.push(v);
+        for &w in &outgoing[v] {
+            remaining[w] -= 1;
+            if remaining[w] == 0 {
+                queue.push_back(w);
+            }
+        }
+    }
+    // Граф должен быть ацикличным; на всякий случай добавляем оставшиеся узлы
+    if topo.len() < node_count {
+        let mut seen = vec![false; node_count];
+        for &v in &topo {
+            seen[v] = true;
+        }
+        topo.extend((0..node_count).filter(|&v| !seen[v]));
+    }
+
+    let mut layers = vec![0usize; node_count];
+    for &v in &topo {
+        for &w in &outgoing[v] {
+            layers[w] = layers[w].max(layers[v] + 1);
+        }
+    }
+
+    // Опускаем источники к ближайшему потомку
+    for &v in topo.iter().rev() {
+        if in_degree[v] == 0 && !outgoing[v].is_empty() {
+            let min_child = outgoing[v].iter().map(|&w| layers[w]).min().unwrap_or(1);
+            layers[v] = min_child.saturating_sub(1);
+        }
+    }
+
+    layers
+}
+
+/// Ставит пары узлов `same` в один слой
+///
+/// Оба узла пары получают больший из слоёв, потомки опускаются ниже,
+/// опустевшие слои удаляются. Противоречивая пара (узлы на одном пути)
+/// остаётся в разных слоях.
+pub fn align_layers(layers: &mut [usize], edges: &[(usize, usize)], same: &[(usize, usize)]) {
+    if same.is_empty() {
+        return;
+    }
+    for _ in 0..=layers.len() {
+        let mut changed = false;
+        for &(a, b) in same {
+            let layer = layers[a].max(layers[b]);
+            if layers[a] != layer || layers[b] != layer {
+                layers[a] = layer;
+                layers[b] = layer;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        // Рёбра снова идут сверху вниз
+        let mut relaxed = true;
+        while relaxed {
+            relaxed = false;
+            for &(from, to) in edges {
+                if layers[to] <= layers[from] {
+                    layers[to] = layers[from] + 1;
+                    relaxed = true;
+                }
+            }
+        }
+    }
+
+    let mut used: Vec<usize> = layers.to_vec();
+    used.sort_unstable();
+    used.dedup();
+    for layer in layers.iter_mut() {
+        *layer = used.binary_search(layer).unwrap_or(*layer);
+    }
+}
+
+/// Строит граф с фиктивными узлами
+///
+/// Возвращает граф и для каждого ребра цепочку узлов сверху вниз
+/// (`None` для петель).
+fn build_proper_graph(
+    layers: &[usize],
+    widths: &[f64],
+    heights: &[f64],
+    edges: &[Option<(usize, usize)>],
+) -> (ProperGraph, Vec<Option<Vec<usize>>>) {
+    let layer_count = layers.iter().max().map_or(0, |&m| m + 1);
+    let mut proper = ProperGraph::with_layers(layer_count);
+
+    for v in 0..layers.len() {
+        proper.add_node(layers[v], widths[v], heights[v], false);
+    }
+
+    let chains = edges
+        .iter()
+        .map(|edge| {
+            let (upper, lower) = (*edge)?;
+            let mut chain = vec![upper];
+            let mut prev = upper;
+            for layer in layers[upper] + 1..layers[lower] {
+                let dummy = proper.add_node(layer, 0.0, 0.0, true);
+                proper.connect(prev, dummy);
+                chain.push(dummy);
+                prev = dummy;
+            }
+            proper.connect(prev, lower);
+            chain.push(lower);
+            Some(chain)
+        })
+        .collect();
+
+    (proper, chains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_model::Size;
+
+    fn node() -> Size {
+        Size::new(100.0, 40.0)
+    }
+
+    #[test]
+    fn test_remove_cycles() {
+        let edges = [(0, 1), (1, 2), (2, 0)];
+        let reversed = remove_cycles(3, &edges);
+        assert_eq!(reversed.iter().filter(|&&r| r).count(), 1);
+    }
+
+    #[test]
+    fn test_assign_layers_pulls_sources_down() {
+        // 0 -> 1 -> 2, 3 -> 2: источник 3 должен оказаться рядом с 2
+        let layers = assign_layers(4, &[(0, 1), (1, 2), (3, 2)]);
+        assert_eq!(layers, vec![0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_align_layers_same_rank() {
+        // 0 -> 1 -> 2 и 3 -> 4: 3 должен встать в слой 2, 4 — ниже
+        let edges = [(0, 1), (1, 2), (3, 4)];
+        let mut layers = assign_layers(5, &edges);
+        align_layers(&mut layers, &edges, &[(2, 3)]);
+        assert_eq!(layers, vec![0, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn test_same_rank_keeps_order() {
+        let mut graph = LayeredGraph::new();
+        let a = graph.add_node(node());
+        let b = graph.add_node(node());
+        graph.add_same_rank(b, a);
+
+        let result = SugiyamaLayout::default().layout(&graph);
+        assert_eq!(result.nodes[a].bounds.y, result.nodes[b].bounds.y);
+        assert!(result.nodes[b].bounds.x < result.nodes[a].bounds.x);
+    }
+
+    #[test]
+    fn test_long_edge_avoids_middle_node() {
+        // a -> b -> c и длинное ребро a -> c
+        let mut graph = LayeredGraph::new();
+        let a = graph.add_node(node());
+        let b = graph.add_node(node());
+        let c = graph.add_node(node());
+        graph.add_edge(a, b);
+        graph.add_edge(b, c);
+        let long = graph.add_edge(a, c);
+
+        let result = SugiyamaLayout::default().layout(&graph);
+        let route = &result.edges[long];
+        // Проходит через фиктивный узел: верх и низ его слоя
+        assert_eq!(route.points.len(), 4);
+
+        let middle = result.nodes[b].bounds;
+        for p in &route.points[1..3] {
+            let inside = p.x > middle.x && p.x < middle.x + middle.width;
+            assert!(!inside, "long edge crosses the middle node");
+        }
+    }
+
+    #[test]
+    fn test_nodes_do_not_overlap() {
+        let mut graph = LayeredGraph::new();
+        let root = graph.add_node(node());
+        for _ in 0..5 {
+            let child = graph.add_node(node());
+            graph.add_edge(root, child);
+        }
+
+        let result = SugiyamaLayout::default().layout(&graph);
+        let mut children: Vec<Rect> = result.nodes[1..].iter().map(|p| p.bounds).collect();
+        children.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+        for pair in children.windows(2) {
+            assert!(pair[0].x + pair[0].width <= pair[1].x);
+        }
+    }
+
+    #[test]
+    fn test_reversed_edge_keeps_direction() {
+        let mut graph = LayeredGraph::new();
+        let a = graph.add_node(node());
+        let b = graph.add_node(node());
+        graph.add_edge(a, b);
+        let back = graph.add_edge(b, a);
+
+        let result = SugiyamaLayout::default().layout(&graph);
+        let route = &result.edges[back];
+        assert!(route.reversed);
+        // Начало у узла b, конец у узла a
+        let first = route.points[0];
+        let last = route.points[route.points.len() - 1];
+        assert!((first.y - result.nodes[b].bounds.y).abs() < 1e-6);
+        assert!((last.y - (result.nodes[a].bounds.y + 40.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_left_to_right() {
+        let mut graph = LayeredGraph::new();
+        let a = graph.add_node(Size::new(100.0, 40.0));
+        let b = graph.add_node(Size::new(100.0, 40.0));
+        graph.add_edge(a, b);
+
+        let config = SugiyamaConfig::default().with_rank_dir(RankDir::LeftToRight);
+        let result = SugiyamaLayout::new(config).layout(&graph);
+
+        let (ra, rb) = (result.nodes[a].bounds, result.nodes[b].bounds);
+        assert!(ra.x + ra.width <= rb.x);
+        assert_eq!(ra.width, 100.0);
+        assert_eq!(ra.height, 40.0);
+        assert!(result.width > result.height);
+    }
+}
@@ -3,29 +3,38 @@
 //! JSON визуализируется как дерево с вложенными блоками.
 //! Объекты и массивы отображаются как контейнеры с заголовками.
 
+use std::sync::Arc;
+
 use plantuml_ast::json::{JsonDiagram, JsonNode, JsonValue};
 use plantuml_model::{Rect, Size};
 
 use crate::json::config::JsonLayoutConfig;
+use crate::text::{FixedMetrics, TextMetrics};
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::{ElementType, LayoutConfig, LayoutElement};
 
 /// Layout engine для JSON диаграмм
 pub struct JsonLayoutEngine {
     config: JsonLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl JsonLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: JsonLayoutConfig::default(),
-        }
+        Self::with_config(JsonLayoutConfig::default())
     }
 
     /// Создаёт engine с указанной конфигурацией
     pub fn with_config(config: JsonLayoutConfig) -> Self {
-        Self { config }
+        let metrics = Arc::new(FixedMetrics::new(8.0, config.font_size));
+        Self { config, metrics }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Вычисляет layout для JSON узла
@@ -262,7 +271,7 @@ impl JsonLayoutEngine {
             value_text
         };
 
-        let text_width = display_text.len() as f64 * 8.0 + 20.0;
+        let text_width = self.metrics.text_width(&display_text, self.config.font_size) + 20.0;
         let width = text_width.max(self.config.min_key_width);
 
         let element = LayoutElement {
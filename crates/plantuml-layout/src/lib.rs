@@ -6,15 +6,19 @@ pub mod activity;
 pub mod class;
 pub mod component;
 pub mod config;
+pub mod dot;
 pub mod er;
 pub mod gantt;
+pub mod graph;
 pub mod json;
 pub mod mindmap;
 pub mod network;
 pub mod object;
 pub mod salt;
 pub mod sequence;
+pub mod sprites;
 pub mod state;
+pub mod text;
 pub mod timing;
 pub mod traits;
 pub mod usecase;
@@ -25,6 +29,7 @@ pub use activity::{ActivityLayoutConfig, ActivityLayoutEngine};
 pub use class::{ClassLayoutConfig, ClassLayoutEngine};
 pub use component::{ComponentLayoutConfig, ComponentLayoutEngine};
 pub use config::LayoutConfig;
+pub use dot::{DotLayoutConfig, DotLayoutEngine};
 pub use er::{ErLayoutConfig, ErLayoutEngine};
 pub use gantt::{GanttLayoutConfig, GanttLayoutEngine};
 pub use json::{JsonLayoutConfig, JsonLayoutEngine};
@@ -35,6 +40,7 @@ pub use plantuml_model::{Point, Rect, Size};
 pub use salt::{SaltLayoutConfig, SaltLayoutEngine};
 pub use sequence::{SequenceLayoutConfig, SequenceLayoutEngine};
 pub use state::{StateLayoutConfig, StateLayoutEngine};
+pub use text::{EstimatedMetrics, FixedMetrics, TextMetrics};
 pub use timing::{TimingLayoutConfig, TimingLayoutEngine};
 pub use traits::{LayoutEngine, LayoutResult};
 pub use usecase::{UseCaseLayoutConfig, UseCaseLayoutEngine};
@@ -103,6 +109,10 @@ pub struct LayoutElement {
     /// Текст элемента (опционально)
     pub text: Option<String>,
     /// Дополнительные свойства
+    ///
+    /// Для интерактивного SVG: `class` — семантический CSS-класс,
+    /// `link`/`tooltip` — гиперссылка `[[url подсказка]]`, `line` — строка
+    /// объявления в исходном коде.
     pub properties: std::collections::HashMap<String, String>,
 }
 
@@ -129,6 +139,25 @@ impl LayoutElement {
         self.properties.insert(key.into(), value.into());
         self
     }
+
+    /// Добавляет гиперссылку элемента (свойства `link` и `tooltip`)
+    pub fn with_link(mut self, link: Option<&plantuml_ast::common::Link>) -> Self {
+        if let Some(link) = link {
+            self.properties.insert("link".to_string(), link.url.clone());
+            if let Some(tooltip) = &link.tooltip {
+                self.properties.insert("tooltip".to_string(), tooltip.clone());
+            }
+        }
+        self
+    }
+
+    /// Добавляет строку исходного кода (свойство `line`), если она известна
+    pub fn with_span(mut self, span: &plantuml_ast::common::Span) -> Self {
+        if span.line > 0 {
+            self.properties.insert("line".to_string(), span.line.to_string());
+        }
+        self
+    }
 }
 
 impl Default for ElementType {
@@ -160,6 +189,19 @@ pub enum EdgeType {
     Link,
 }
 
+/// Положения подписей ребра, рассчитанные layout (центры текстовых блоков)
+///
+/// `None` — положение выбирает рендерер.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EdgeLabelPositions {
+    /// Центр метки
+    pub label: Option<Point>,
+    /// Центр кардинальности у начальной точки
+    pub from_cardinality: Option<Point>,
+    /// Центр кардинальности у конечной точки
+    pub to_cardinality: Option<Point>,
+}
+
 /// Тип элемента layout
 #[derive(Debug, Clone, PartialEq)]
 pub enum ElementType {
@@ -205,6 +247,11 @@ pub enum ElementType {
         from_cardinality: Option<String>,
         /// Кардинальность у конечной точки (например "*")
         to_cardinality: Option<String>,
+        /// Контрольные точки кубических кривых Безье `[p0, c1, c2, p1, ...]`
+        /// (пусто — ломаная по `points`)
+        curve: Vec<Point>,
+        /// Положения метки и кардинальностей
+        label_positions: EdgeLabelPositions,
     },
     /// SVG Path (для кривых Безье, etc.)
     Path,
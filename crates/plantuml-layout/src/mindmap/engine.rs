@@ -4,17 +4,20 @@
 //! Линии соединяют края узлов кривыми Безье.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use plantuml_ast::mindmap::{MindMapDiagram, MindMapNode, NodeStyle};
 use plantuml_model::{Point, Rect};
 
 use super::MindMapLayoutConfig;
+use crate::text::{EstimatedMetrics, TextMetrics};
 use crate::traits::LayoutResult;
 use crate::{ElementType, LayoutElement};
 
 /// Layout engine для MindMap диаграмм
 pub struct MindMapLayoutEngine {
     config: MindMapLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 /// Информация о размещении узла
@@ -26,14 +29,24 @@ struct NodeLayout {
 impl MindMapLayoutEngine {
     /// Создаёт новый layout engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: MindMapLayoutConfig::default(),
-        }
+        Self::with_config(MindMapLayoutConfig::default())
     }
 
     /// Создаёт layout engine с заданной конфигурацией
     pub fn with_config(config: MindMapLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics {
+                char_width: 0.6,
+                wide_char_width: 0.6,
+            }),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout для диаграммы
@@ -160,10 +173,7 @@ impl MindMapLayoutEngine {
 
     /// Вычисляет ширину узла по тексту
     fn calculate_node_width(&self, text: &str) -> f64 {
-        // Считаем символы Unicode правильно
-        let char_count = text.chars().count();
-        let char_width = self.config.font_size * 0.6;
-        let text_width = char_count as f64 * char_width;
+        let text_width = self.metrics.text_width(text, self.config.font_size);
         (text_width + self.config.node_padding_x * 2.0).max(self.config.min_node_width)
     }
 
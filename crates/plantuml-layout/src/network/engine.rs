@@ -4,30 +4,40 @@
 //! Серверы, принадлежащие нескольким сетям, соединяются вертикальными линиями.
 
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use plantuml_ast::network::{DeviceType, NetworkDiagram, Server};
 use plantuml_model::{Point, Rect};
 
 use crate::network::config::NetworkLayoutConfig;
+use crate::text::{EstimatedMetrics, TextMetrics};
 use crate::traits::{LayoutEngine, LayoutResult};
-use crate::{EdgeType, ElementType, LayoutConfig, LayoutElement};
+use crate::{EdgeLabelPositions, EdgeType, ElementType, LayoutConfig, LayoutElement};
 
 /// Layout engine для Network диаграмм
 pub struct NetworkLayoutEngine {
     config: NetworkLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl NetworkLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: NetworkLayoutConfig::default(),
-        }
+        Self::with_config(NetworkLayoutConfig::default())
     }
 
     /// Создаёт engine с указанной конфигурацией
     pub fn with_config(config: NetworkLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics::default()),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Собирает информацию о серверах: в каких сетях они присутствуют
@@ -365,6 +375,7 @@ impl NetworkLayoutEngine {
                             arrow_end: false,
                             dashed: false,
                             edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                            curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                         },
                         bounds: Rect::from_points(from, to),
                         text: None,
@@ -449,6 +460,22 @@ impl LayoutEngine for NetworkLayoutEngine {
         let server_networks = self.collect_server_networks(diagram);
         let server_order = self.collect_unique_servers(diagram);
 
+        // Серверы шире конфигурации, если не помещается самое длинное имя
+        let server_width = server_order
+            .iter()
+            .map(|name| self.metrics.text_width(name, self.config.font_size) + 10.0)
+            .fold(self.config.server_width, f64::max);
+        if server_width > self.config.server_width {
+            let engine = Self {
+                config: NetworkLayoutConfig {
+                    server_width,
+                    ..self.config.clone()
+                },
+                metrics: self.metrics.clone(),
+            };
+            return engine.layout(diagram, _config);
+        }
+
         // Размещаем сети
         let (width, height) = self.layout_networks(diagram, &server_order, &mut elements);
 
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Object Diagrams
 
+use crate::graph::LineType;
+
 /// Конфигурация layout для Object Diagrams
 #[derive(Debug, Clone)]
 pub struct ObjectLayoutConfig {
@@ -15,6 +17,12 @@ pub struct ObjectLayoutConfig {
     pub vertical_spacing: f64,
     /// Отступ от края диаграммы
     pub padding: f64,
+    /// Размер шрифта заголовка объекта
+    pub font_size: f64,
+    /// Размер шрифта полей
+    pub field_font_size: f64,
+    /// Форма линий рёбер (`skinparam linetype`)
+    pub line_type: LineType,
 }
 
 impl Default for ObjectLayoutConfig {
@@ -26,6 +34,9 @@ impl Default for ObjectLayoutConfig {
             horizontal_spacing: 60.0,
             vertical_spacing: 50.0,
             padding: 30.0,
+            font_size: 14.0,
+            field_font_size: 12.0,
+            line_type: LineType::Spline,
         }
     }
 }
@@ -2,36 +2,50 @@
 //!
 //! Рендерит UI wireframes с кнопками, текстовыми полями, чекбоксами и т.д.
 
+use std::sync::Arc;
+
 use plantuml_ast::salt::{BorderStyle, Container, SaltDiagram, SaltWidget, SeparatorType};
 use plantuml_model::{Point, Rect};
 
 use crate::salt::config::SaltLayoutConfig;
+use crate::text::{FixedMetrics, TextMetrics};
 use crate::traits::{LayoutEngine, LayoutResult};
-use crate::{EdgeType, ElementType, LayoutConfig, LayoutElement};
+use crate::{EdgeLabelPositions, EdgeType, ElementType, LayoutConfig, LayoutElement};
 
 /// Layout engine для Salt диаграмм
 pub struct SaltLayoutEngine {
     config: SaltLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
     element_id: usize,
 }
 
 impl SaltLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: SaltLayoutConfig::default(),
-            element_id: 0,
-        }
+        Self::with_config(SaltLayoutConfig::default())
     }
 
     /// Создаёт engine с указанной конфигурацией
     pub fn with_config(config: SaltLayoutConfig) -> Self {
+        let metrics = Arc::new(FixedMetrics::new(8.0, config.font_size));
         Self {
             config,
+            metrics,
             element_id: 0,
         }
     }
 
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Ширина строки текста
+    fn text_width(&self, text: &str) -> f64 {
+        self.metrics.text_width(text, self.config.font_size)
+    }
+
     /// Генерирует уникальный ID элемента
     fn next_id(&mut self, prefix: &str) -> String {
         self.element_id += 1;
@@ -188,7 +202,7 @@ impl SaltLayoutEngine {
 
     /// Рендерит текст
     fn render_text(&mut self, text: &str, x: f64, y: f64, elements: &mut Vec<LayoutElement>) -> (f64, f64) {
-        let width = text.len() as f64 * 8.0 + self.config.cell_padding;
+        let width = self.text_width(text) + self.config.cell_padding;
         let height = self.config.row_height;
 
         let text_elem = LayoutElement {
@@ -216,7 +230,7 @@ impl SaltLayoutEngine {
         y: f64,
         elements: &mut Vec<LayoutElement>,
     ) -> (f64, f64) {
-        let width = label.len() as f64 * 8.0 + self.config.cell_padding * 2.0;
+        let width = self.text_width(label) + self.config.cell_padding * 2.0;
         let width = width.max(self.config.min_cell_width);
         let height = self.config.button_height;
 
@@ -267,7 +281,7 @@ impl SaltLayoutEngine {
         y: f64,
         elements: &mut Vec<LayoutElement>,
     ) -> (f64, f64) {
-        let width = text.len() as f64 * 8.0 + self.config.cell_padding * 2.0;
+        let width = self.text_width(text) + self.config.cell_padding * 2.0;
         let width = width.max(self.config.min_cell_width);
         let height = self.config.textfield_height;
 
@@ -368,7 +382,7 @@ impl SaltLayoutEngine {
                     .collect(),
             };
             elements.push(label_elem);
-            label.len() as f64 * 8.0
+            self.text_width(label)
         } else {
             0.0
         };
@@ -431,7 +445,7 @@ impl SaltLayoutEngine {
                     .collect(),
             };
             elements.push(label_elem);
-            label.len() as f64 * 8.0
+            self.text_width(label)
         } else {
             0.0
         };
@@ -449,7 +463,7 @@ impl SaltLayoutEngine {
         elements: &mut Vec<LayoutElement>,
     ) -> (f64, f64) {
         let text = items.first().map(|s| s.as_str()).unwrap_or("Select...");
-        let width = text.len() as f64 * 8.0 + 30.0;
+        let width = self.text_width(text) + 30.0;
         let height = self.config.textfield_height;
 
         // Фон
@@ -531,6 +545,7 @@ impl SaltLayoutEngine {
                 arrow_end: false,
                 dashed: dasharray.is_some(),
                 edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
             bounds: Rect::new(x, y, width, 20.0),
             text: None,
@@ -561,6 +576,7 @@ impl SaltLayoutEngine {
                     arrow_end: false,
                     dashed: false,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                    curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                 },
                 bounds: Rect::new(x, y, width, 20.0),
                 text: None,
@@ -600,7 +616,7 @@ impl SaltLayoutEngine {
             if !node.text.is_empty() {
                 let prefix = if node.level > 0 { "├─ " } else { "" };
                 let text = format!("{}{}", prefix, node.text);
-                let width = text.len() as f64 * 8.0 + indent;
+                let width = engine.text_width(&text) + indent;
 
                 let text_elem = LayoutElement {
                     id: engine.next_id("tree_node"),
@@ -643,7 +659,7 @@ impl SaltLayoutEngine {
         let tab_height = 25.0;
 
         for (i, item) in items.iter().enumerate() {
-            let width = item.len() as f64 * 8.0 + 20.0;
+            let width = self.text_width(item) + 20.0;
             let is_selected = i == selected;
 
             // Фон вкладки
@@ -705,7 +721,7 @@ impl SaltLayoutEngine {
         let menu_height = 22.0;
 
         // Фон меню
-        let total_width = items.iter().map(|i| i.text.len() as f64 * 8.0 + 20.0).sum::<f64>();
+        let total_width = items.iter().map(|i| self.text_width(&i.text) + 20.0).sum::<f64>();
         let bg = LayoutElement {
             id: self.next_id("menu_bg"),
             element_type: ElementType::Rectangle {
@@ -728,7 +744,7 @@ impl SaltLayoutEngine {
                 continue;
             }
 
-            let width = item.text.len() as f64 * 8.0 + 20.0;
+            let width = self.text_width(&item.text) + 20.0;
 
             let text = LayoutElement {
                 id: self.next_id("menu_item"),
@@ -899,7 +915,12 @@ impl LayoutEngine for SaltLayoutEngine {
     type Input = SaltDiagram;
 
     fn layout(&self, diagram: &Self::Input, _config: &LayoutConfig) -> LayoutResult {
-        let mut engine = SaltLayoutEngine::new();
+        // Счётчик id — у копии engine, layout не меняет `self`
+        let mut engine = SaltLayoutEngine {
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            element_id: 0,
+        };
         let mut elements = Vec::new();
 
         let (width, height) = engine.render_widget(
@@ -7,32 +7,57 @@ use plantuml_ast::sequence::{
     Activation, ActivationType, AutonumberCommand, Delay, Divider, Fragment, FragmentType, 
     Message, ParticipantType, Return, SequenceDiagram, SequenceElement,
 };
+use std::sync::Arc;
+
+use plantuml_ast::SpriteRegistry;
 use plantuml_model::{Point, Rect};
 
 use super::config::SequenceLayoutConfig;
 use super::metrics::{DiagramMetrics, ParticipantMetrics};
-use crate::{EdgeType, ElementType, FragmentSection, LayoutConfig, LayoutElement, LayoutResult};
+use crate::sprites;
+use crate::text::{FixedMetrics, TextMetrics};
+use crate::{EdgeLabelPositions, EdgeType, ElementType, FragmentSection, LayoutConfig, LayoutElement, LayoutResult};
 
 /// Layout engine для sequence diagrams
 pub struct SequenceLayoutEngine {
     config: SequenceLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl SequenceLayoutEngine {
     /// Создаёт новый engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: SequenceLayoutConfig::default(),
-        }
+        Self::with_config(SequenceLayoutConfig::default())
     }
 
-    /// Создаёт engine с заданной конфигурацией
+    /// Создаёт engine с заданной конфигурацией; ширина текста оценивается
+    /// по `char_width`, пока не заданы метрики
     pub fn with_config(config: SequenceLayoutConfig) -> Self {
-        Self { config }
+        let metrics = Arc::new(FixedMetrics::new(config.char_width, config.font_size));
+        Self { config, metrics }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &SequenceDiagram) -> LayoutResult {
+        // Спрайты в подписях участников увеличивают высоту заголовков
+        let header_height = self.participant_header_height(diagram);
+        if header_height > self.config.participant_height {
+            let engine = Self {
+                config: SequenceLayoutConfig {
+                    participant_height: header_height,
+                    ..self.config.clone()
+                },
+                metrics: self.metrics.clone(),
+            };
+            return engine.layout(diagram);
+        }
+
         let mut metrics = DiagramMetrics::new();
         let mut elements = Vec::new();
 
@@ -149,7 +174,7 @@ impl SequenceLayoutEngine {
                     if let Some(pm) = metrics.participants.get(&msg.from) {
                         let loop_width = 40.0;
                         let text_offset = 5.0; // отступ от петли до текста
-                        let text_width = self.config.message_label_width(&msg.label);
+                        let text_width = self.message_label_width(&msg.label);
                         let right_edge = pm.center_x + loop_width + text_offset + text_width;
                         *max_right = max_right.max(right_edge);
                     }
@@ -160,7 +185,7 @@ impl SequenceLayoutEngine {
                     let to_x = metrics.lifeline_x(&msg.to, &self.config);
                     let left_x = from_x.min(to_x);
                     let text_start = left_x + 5.0; // отступ от lifeline
-                    let text_width = self.config.message_label_width(&msg.label);
+                    let text_width = self.message_label_width(&msg.label);
                     let text_end = text_start + text_width;
 
                     // Проверяем overflow вправо
@@ -185,7 +210,7 @@ impl SequenceLayoutEngine {
             SequenceElement::Return(ret) => {
                 // Return тоже может иметь label
                 if let Some(label) = &ret.label {
-                    let text_width = self.config.message_label_width(label);
+                    let text_width = self.message_label_width(label);
                     // Return обычно идёт справа налево, текст над стрелкой
                     // Просто добавляем к max_right для безопасности
                     let current_max_x = metrics.participants.values()
@@ -311,7 +336,7 @@ impl SequenceLayoutEngine {
             .iter()
             .map(|name| {
                 let display_name = participant_names.get(name).unwrap_or(name);
-                self.config.participant_width_for_name(display_name)
+                self.participant_width(display_name, &diagram.metadata.sprites)
             })
             .collect();
         
@@ -356,7 +381,20 @@ impl SequenceLayoutEngine {
             );
 
             // Создаём визуальный элемент
-            let element = self.create_participant_element(name, display_name, &bounds, ptype);
+            let declaration = diagram.participants.iter().find(|p| {
+                p.id.alias.as_ref().unwrap_or(&p.id.name) == name
+            });
+            let mut element = self
+                .create_participant_element(name, display_name, &bounds, ptype)
+                .with_property("class", "participant")
+                .with_link(declaration.and_then(|p| p.link.as_ref()))
+                .with_span(&declaration.map(|p| p.span).unwrap_or_default());
+            if let Some(sprite) = declaration
+                .and_then(|p| p.stereotype.as_ref())
+                .and_then(|s| s.sprite())
+            {
+                element = element.with_property("sprite", sprite);
+            }
             elements.push(element);
 
             // Расстояние до следующего участника
@@ -375,6 +413,56 @@ impl SequenceLayoutEngine {
         metrics.max_x = x;
     }
 
+    /// Ширина участника; подпись со спрайтами `<$name>` измеряется вместе с ними
+    fn participant_width(&self, name: &str, sprites: &SpriteRegistry) -> f64 {
+        if !sprites.is_used_in(name) {
+            return self.participant_width_for_name(name);
+        }
+        let label = sprites::label_size(
+            name,
+            sprites,
+            self.config.font_size,
+            self.metrics.as_ref(),
+        );
+        self.config.participant_width.max(label.width + 20.0)
+    }
+
+    /// Ширина участника по имени: не меньше `participant_width`
+    fn participant_width_for_name(&self, name: &str) -> f64 {
+        let text_width = self.metrics.text_width(name, self.config.font_size) + 20.0;
+        self.config.participant_width.max(text_width)
+    }
+
+    /// Ширина подписи сообщения с отступами (по самой длинной строке)
+    fn message_label_width(&self, label: &str) -> f64 {
+        let processed = label.replace("\\n", "\n");
+        let max_line_width = processed
+            .split('\n')
+            .map(|line| self.metrics.text_width(line, self.config.font_size))
+            .fold(0.0_f64, f64::max);
+        max_line_width + 16.0
+    }
+
+    /// Высота заголовков участников: не меньше подписи со спрайтами
+    fn participant_header_height(&self, diagram: &SequenceDiagram) -> f64 {
+        let sprites = &diagram.metadata.sprites;
+        diagram
+            .participants
+            .iter()
+            .filter(|p| sprites.is_used_in(&p.id.name))
+            .map(|p| {
+                sprites::label_size(
+                    &p.id.name,
+                    sprites,
+                    self.config.font_size,
+                    self.metrics.as_ref(),
+                )
+                .height
+                    + 10.0
+            })
+            .fold(self.config.participant_height, f64::max)
+    }
+
     /// Собирает порядок участников из сообщений диаграммы
     fn collect_participants_order(&self, diagram: &SequenceDiagram, order: &mut Vec<String>) {
         for element in &diagram.elements {
@@ -530,7 +618,7 @@ impl SequenceLayoutEngine {
     ) {
         match element {
             SequenceElement::Message(msg) => {
-                let text_width = self.config.message_label_width(&msg.label);
+                let text_width = self.message_label_width(&msg.label);
                 let autonumber_width = if has_autonumber { 45.0 } else { 0.0 };
                 let total_width = text_width + autonumber_width;
                 
@@ -552,7 +640,7 @@ impl SequenceLayoutEngine {
             SequenceElement::Return(ret) => {
                 // Return тоже влияет на spacing
                 if let Some(label) = &ret.label {
-                    let text_width = self.config.message_label_width(label);
+                    let text_width = self.message_label_width(label);
                     // Return не имеет autonumber
                     
                     // Для return нужно знать caller и callee
@@ -759,10 +847,11 @@ impl SequenceLayoutEngine {
                     arrow_end: true,
                     dashed: true,
                     edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
+                    curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                 },
             };
 
-            elements.push(edge);
+            elements.push(edge.with_property("class", "message return"));
             metrics.advance_y(self.config.message_spacing);
         }
     }
@@ -820,7 +909,7 @@ impl SequenceLayoutEngine {
         let label_width = if label.is_empty() {
             0.0
         } else {
-            self.config.message_label_width(&label)
+            self.message_label_width(&label)
         };
 
         let points = if is_self_message {
@@ -892,8 +981,13 @@ impl SequenceLayoutEngine {
                 edge_type: EdgeType::Association, // стандартные стрелки sequence diagram
                 from_cardinality: None,
                 to_cardinality: None,
+                curve: Vec::new(),
+                label_positions: EdgeLabelPositions::default(),
             },
-        };
+        }
+        .with_property("class", "message")
+        .with_link(msg.link.as_ref())
+        .with_span(&msg.span);
 
         elements.push(edge);
 
@@ -1082,7 +1176,7 @@ impl SequenceLayoutEngine {
             },
         };
 
-        elements.push(note_elem);
+        elements.push(note_elem.with_property("class", "note"));
         metrics.advance_y(self.config.note_height + 10.0);
     }
 
@@ -1122,7 +1216,7 @@ impl SequenceLayoutEngine {
             },
         };
 
-        elements.push(divider);
+        elements.push(divider.with_property("class", "divider"));
         metrics.advance_y(self.config.divider_height);
     }
 
@@ -1151,7 +1245,7 @@ impl SequenceLayoutEngine {
             },
         };
 
-        elements.push(delay_elem);
+        elements.push(delay_elem.with_property("class", "delay"));
         metrics.advance_y(self.config.delay_height);
     }
 
@@ -1212,9 +1306,11 @@ impl SequenceLayoutEngine {
                     edge_type: EdgeType::Link, // линия без маркеров
                     from_cardinality: None,
                     to_cardinality: None,
+                    curve: Vec::new(),
+                    label_positions: EdgeLabelPositions::default(),
                 },
             };
-            elements.push(lifeline);
+            elements.push(lifeline.with_property("class", "lifeline"));
         }
     }
 
@@ -1239,7 +1335,7 @@ impl SequenceLayoutEngine {
                     corner_radius: 2.5, // PlantUML style
                 },
             };
-            elements.push(footer);
+            elements.push(footer.with_property("class", "participant footer"));
         }
     }
 }
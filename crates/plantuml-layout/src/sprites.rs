@@ -0,0 +1,98 @@
+//! Размеры подписей со спрайтами `<$name>`
+//!
+//! Спрайт в строке подписи занимает свой размер с учётом масштаба, высота
+//! строки — наибольшая из высоты текста и спрайтов. Рендерер раскладывает
+//! строки по тем же правилам, поэтому блок совпадает с размером из layout.
+
+use plantuml_ast::sprite::{LabelPart, SpriteRef, SpriteRegistry};
+use plantuml_model::Size;
+
+use crate::text::TextMetrics;
+
+/// Строки подписи (разделители — `\n` и литерал `\\n`)
+pub fn label_lines(label: &str) -> Vec<String> {
+    label
+        .replace("\\n", "\n")
+        .split('\n')
+        .map(str::to_string)
+        .collect()
+}
+
+/// Размер спрайта по ссылке; `None` — спрайт не определён
+pub fn sprite_size(sprites: &SpriteRegistry, sprite: &SpriteRef) -> Option<Size> {
+    sprites.get(&sprite.name).map(|s| {
+        Size::new(
+            f64::from(s.width) * sprite.scale,
+            f64::from(s.height) * sprite.scale,
+        )
+    })
+}
+
+/// Размер одной строки; неизвестные спрайты не занимают места, строка из
+/// одних спрайтов высотой с наибольший из них
+pub fn line_size(
+    line: &str,
+    sprites: &SpriteRegistry,
+    font_size: f64,
+    metrics: &dyn TextMetrics,
+) -> Size {
+    let mut size = Size::new(0.0, 0.0);
+    let mut has_text = false;
+    for part in LabelPart::split(line) {
+        match part {
+            LabelPart::Text(text) => {
+                has_text |= !text.trim().is_empty();
+                size.width += metrics.text_width(text, font_size);
+            }
+            LabelPart::Sprite(sprite) => {
+                if let Some(sprite) = sprite_size(sprites, &sprite) {
+                    size.width += sprite.width;
+                    size.height = size.height.max(sprite.height);
+                }
+            }
+        }
+    }
+    if has_text || size.height == 0.0 {
+        size.height = size.height.max(metrics.line_height(font_size));
+    }
+    size
+}
+
+/// Размер многострочной подписи со спрайтами
+pub fn label_size(
+    label: &str,
+    sprites: &SpriteRegistry,
+    font_size: f64,
+    metrics: &dyn TextMetrics,
+) -> Size {
+    label_lines(label)
+        .iter()
+        .map(|line| line_size(line, sprites, font_size, metrics))
+        .fold(Size::new(0.0, 0.0), |total, line| {
+            Size::new(total.width.max(line.width), total.height + line.height)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use plantuml_ast::Sprite;
+
+    use super::*;
+    use crate::text::EstimatedMetrics;
+
+    #[test]
+    fn test_label_size() {
+        let mut sprites = SpriteRegistry::new();
+        sprites.insert("icon", Sprite::gray(32, 40, 16, Vec::new()).unwrap());
+        let metrics = EstimatedMetrics::default();
+
+        let size = label_size("<$icon{scale=0.5}>\\nWeb", &sprites, 10.0, &metrics);
+        // Спрайт 16×20 над строкой текста высотой 12
+        assert_eq!(size.height, 20.0 + 12.0);
+        assert_eq!(size.width, 16.5);
+
+        let line = line_size("<$icon> ab <$unknown>", &sprites, 10.0, &metrics);
+        assert_eq!(line.height, 40.0);
+        assert!((line.width - (32.0 + 4.0 * 5.5)).abs() < 1e-9);
+    }
+}
@@ -1,5 +1,8 @@
 //! Конфигурация layout для State Diagrams
 
+use crate::graph::LineType;
+use crate::text::FixedMetrics;
+
 /// Конфигурация State Layout Engine
 #[derive(Debug, Clone)]
 pub struct StateLayoutConfig {
@@ -27,6 +30,16 @@ pub struct StateLayoutConfig {
     pub arrow_size: f64,
     /// Отступ текста внутри состояния
     pub text_padding: f64,
+    /// Внутренний отступ composite состояния
+    pub composite_padding: f64,
+    /// Высота заголовка composite состояния
+    pub composite_header_height: f64,
+    /// Размер шрифта подписей
+    pub font_size: f64,
+    /// Ширина символа (приблизительно, если не заданы метрики шрифтов)
+    pub char_width: f64,
+    /// Форма линий рёбер (`skinparam linetype`)
+    pub line_type: LineType,
 }
 
 impl Default for StateLayoutConfig {
@@ -44,6 +57,18 @@ impl Default for StateLayoutConfig {
             bar_height: 6.0,
             arrow_size: 8.0,
             text_padding: 10.0,
+            composite_padding: 15.0,
+            composite_header_height: 30.0,
+            font_size: 14.0,
+            char_width: 9.0,
+            line_type: LineType::Spline,
         }
     }
 }
+
+impl StateLayoutConfig {
+    /// Оценка метрик текста по `char_width`
+    pub fn estimated_metrics(&self) -> FixedMetrics {
+        FixedMetrics::new(self.char_width, self.font_size)
+    }
+}
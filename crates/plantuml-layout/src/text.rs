@@ -0,0 +1,141 @@
+//! Метрики текста для layout
+//!
+//! Layout оценивает размеры подписей до рендеринга. По умолчанию используется
+//! [`EstimatedMetrics`] — приближение по числу символов. Рендерер с реальными
+//! шрифтами (`plantuml_renderer::fonts::FontSet`) реализует тот же трейт,
+//! поэтому размеры блоков совпадают с тем, что нарисует PNG.
+
+use plantuml_model::Size;
+
+/// Источник метрик текста
+pub trait TextMetrics: Send + Sync {
+    /// Ширина одной строки текста
+    fn text_width(&self, text: &str, font_size: f64) -> f64;
+
+    /// Высота строки
+    fn line_height(&self, font_size: f64) -> f64 {
+        (font_size * 1.2).round()
+    }
+
+    /// Размер многострочного текста (разделители — `\n` и литерал `\\n`)
+    fn text_size(&self, text: &str, font_size: f64) -> Size {
+        let text = text.replace("\\n", "\n");
+        let lines = text.lines().count().max(1);
+        let width = text
+            .lines()
+            .map(|line| self.text_width(line, font_size))
+            .fold(0.0, f64::max);
+        Size::new(width, lines as f64 * self.line_height(font_size))
+    }
+}
+
+/// Оценка метрик без шрифтов: средняя ширина символа пропорциональна кеглю
+#[derive(Debug, Clone, Copy)]
+pub struct EstimatedMetrics {
+    /// Ширина обычного символа в долях кегля
+    pub char_width: f64,
+    /// Ширина широкого символа (CJK, полноширинные формы) в долях кегля
+    pub wide_char_width: f64,
+}
+
+impl Default for EstimatedMetrics {
+    fn default() -> Self {
+        Self {
+            char_width: 0.55,
+            wide_char_width: 1.0,
+        }
+    }
+}
+
+impl TextMetrics for EstimatedMetrics {
+    fn text_width(&self, text: &str, font_size: f64) -> f64 {
+        text.chars()
+            .map(|c| {
+                if is_wide_char(c) {
+                    self.wide_char_width
+                } else {
+                    self.char_width
+                }
+            })
+            .sum::<f64>()
+            * font_size
+    }
+}
+
+/// Постоянная ширина символа `char_width` точек при кегле `font_size` —
+/// прежнее приближение engine'ов без заданных метрик
+#[derive(Debug, Clone, Copy)]
+pub struct FixedMetrics {
+    /// Ширина символа в точках
+    pub char_width: f64,
+    /// Кегль, для которого задана ширина
+    pub font_size: f64,
+}
+
+impl FixedMetrics {
+    /// Создаёт метрики с шириной символа `char_width` при кегле `font_size`
+    pub fn new(char_width: f64, font_size: f64) -> Self {
+        Self {
+            char_width,
+            font_size,
+        }
+    }
+}
+
+impl TextMetrics for FixedMetrics {
+    fn text_width(&self, text: &str, font_size: f64) -> f64 {
+        // Деление последним: при исходном кегле ширина точная
+        text.chars().count() as f64 * self.char_width * font_size / self.font_size
+    }
+}
+
+/// Символ занимает полную ширину (иероглифы, кана, хангыль)
+pub fn is_wide_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x20000..=0x2FFFD
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimated_width() {
+        let metrics = EstimatedMetrics::default();
+        let latin = metrics.text_width("abcd", 10.0);
+        let cyrillic = metrics.text_width("абвг", 10.0);
+        let cjk = metrics.text_width("漢字漢字", 10.0);
+        assert!((latin - 22.0).abs() < 1e-9);
+        assert_eq!(latin, cyrillic);
+        assert!(cjk > latin);
+    }
+
+    #[test]
+    fn test_fixed_width() {
+        let metrics = FixedMetrics::new(8.0, 14.0);
+        assert_eq!(metrics.text_width("abcd", 14.0), 32.0);
+        assert_eq!(metrics.text_width("漢字", 14.0), 16.0);
+        assert_eq!(metrics.text_width("abcd", 7.0), 16.0);
+    }
+
+    #[test]
+    fn test_text_size_multiline() {
+        let metrics = EstimatedMetrics::default();
+        let size = metrics.text_size("short\\nlonger line", 10.0);
+        assert_eq!(size.height, 24.0);
+        assert_eq!(size.width, metrics.text_width("longer line", 10.0));
+    }
+}
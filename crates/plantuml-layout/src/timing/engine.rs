@@ -3,36 +3,54 @@
 //! Создаёт горизонтальную временную шкалу с вертикальными lanes для участников.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use plantuml_ast::timing::{ParticipantType, StateChange, TimeValue, TimingDiagram};
 use plantuml_model::{Point, Rect};
 
 use super::TimingLayoutConfig;
+use crate::text::{EstimatedMetrics, TextMetrics};
 use crate::traits::LayoutResult;
-use crate::{EdgeType, ElementType, LayoutElement};
+use crate::{EdgeLabelPositions, EdgeType, ElementType, LayoutElement};
 
 /// Layout engine для Timing Diagrams
 pub struct TimingLayoutEngine {
     config: TimingLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl TimingLayoutEngine {
     /// Создаёт новый layout engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: TimingLayoutConfig::default(),
-        }
+        Self::with_config(TimingLayoutConfig::default())
     }
 
     /// Создаёт layout engine с заданной конфигурацией
     pub fn with_config(config: TimingLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics::default()),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &TimingDiagram) -> LayoutResult {
         let mut elements = Vec::new();
 
+        // Колонка подписей участников шире конфигурации, если подпись не помещается
+        let label_width = diagram
+            .participants
+            .iter()
+            .map(|p| p.alias.as_deref().unwrap_or(&p.name))
+            .map(|label| self.metrics.text_width(label, self.config.label_font_size) + 10.0)
+            .fold(self.config.participant_label_width, f64::max);
+
         // 1. Собираем все времена для определения масштаба
         let (min_time, max_time) = self.calculate_time_range(diagram);
         let time_range = (max_time - min_time).max(100.0);
@@ -75,7 +93,7 @@ impl TimingLayoutEngine {
         }
 
         // 4. Рисуем участников и их lanes
-        let timeline_start_x = self.config.padding + self.config.participant_label_width;
+        let timeline_start_x = self.config.padding + label_width;
         let timeline_width = time_range * self.config.time_scale;
 
         for (i, participant) in diagram.participants.iter().enumerate() {
@@ -88,7 +106,7 @@ impl TimingLayoutEngine {
                 bounds: Rect::new(
                     self.config.padding,
                     lane_y,
-                    self.config.participant_label_width - 10.0,
+                    label_width - 10.0,
                     self.config.lane_height,
                 ),
                 text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
@@ -283,6 +301,7 @@ impl TimingLayoutEngine {
                 arrow_end: false,
                 dashed: false,
                 edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
         });
 
@@ -305,6 +324,7 @@ impl TimingLayoutEngine {
                         arrow_end: false,
                         dashed: false,
                         edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                        curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                     },
                 });
 
@@ -360,6 +380,7 @@ impl TimingLayoutEngine {
                 arrow_end: false,
                 dashed: false,
                 edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
         });
     }
@@ -388,6 +409,7 @@ impl TimingLayoutEngine {
                 arrow_end: true,
                 dashed: false,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
+                curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
             },
         });
 
@@ -410,6 +432,7 @@ impl TimingLayoutEngine {
                     arrow_end: false,
                     dashed: false,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
+                    curve: Vec::new(), label_positions: EdgeLabelPositions::default(),
                 },
             });
 
@@ -1,5 +1,8 @@
 //! Конфигурация layout для Use Case Diagrams
 
+use crate::graph::LineType;
+use crate::text::FixedMetrics;
+
 /// Конфигурация Use Case Layout Engine
 #[derive(Debug, Clone)]
 pub struct UseCaseLayoutConfig {
@@ -21,6 +24,12 @@ pub struct UseCaseLayoutConfig {
     pub package_padding: f64,
     /// Высота заголовка пакета
     pub package_header_height: f64,
+    /// Размер шрифта подписей
+    pub font_size: f64,
+    /// Ширина символа (приблизительно, если не заданы метрики шрифтов)
+    pub char_width: f64,
+    /// Форма линий рёбер (`skinparam linetype`)
+    pub line_type: LineType,
 }
 
 impl Default for UseCaseLayoutConfig {
@@ -35,6 +44,17 @@ impl Default for UseCaseLayoutConfig {
             horizontal_spacing: 80.0, // Increased for actor labels
             package_padding: 25.0,
             package_header_height: 30.0,
+            // Кириллица занимает примерно 9 пикселей на символ (font-size 14)
+            font_size: 14.0,
+            char_width: 9.0,
+            line_type: LineType::Spline,
         }
     }
 }
+
+impl UseCaseLayoutConfig {
+    /// Оценка метрик текста по `char_width`
+    pub fn estimated_metrics(&self) -> FixedMetrics {
+        FixedMetrics::new(self.char_width, self.font_size)
+    }
+}
@@ -4,30 +4,43 @@
 //! в отличие от MindMap который горизонтальный.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use plantuml_ast::wbs::{WbsDiagram, WbsNode, WbsNodeStyle};
 use plantuml_model::{Point, Rect};
 
 use super::WbsLayoutConfig;
+use crate::text::{EstimatedMetrics, TextMetrics};
 use crate::traits::LayoutResult;
 use crate::{ElementType, LayoutElement};
 
 /// Layout engine для WBS диаграмм
 pub struct WbsLayoutEngine {
     config: WbsLayoutConfig,
+    metrics: Arc<dyn TextMetrics>,
 }
 
 impl WbsLayoutEngine {
     /// Создаёт новый layout engine с конфигурацией по умолчанию
     pub fn new() -> Self {
-        Self {
-            config: WbsLayoutConfig::default(),
-        }
+        Self::with_config(WbsLayoutConfig::default())
     }
 
     /// Создаёт layout engine с заданной конфигурацией
     pub fn with_config(config: WbsLayoutConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: Arc::new(EstimatedMetrics {
+                char_width: 0.6,
+                wide_char_width: 0.6,
+            }),
+        }
+    }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Выполняет layout для диаграммы
@@ -141,8 +154,7 @@ impl WbsLayoutEngine {
 
     /// Вычисляет ширину узла
     fn calculate_node_width(&self, text: &str) -> f64 {
-        let char_width = self.config.font_size * 0.6;
-        let text_width = text.len() as f64 * char_width;
+        let text_width = self.metrics.text_width(text, self.config.font_size);
         (text_width + self.config.node_padding_x * 2.0).max(self.config.min_node_width)
     }
 
@@ -2,10 +2,13 @@
 //!
 //! Переиспользует JSON layout engine, адаптируя его для YamlDiagram.
 
+use std::sync::Arc;
+
 use plantuml_ast::json::JsonDiagram;
 use plantuml_ast::yaml::YamlDiagram;
 
 use crate::json::{JsonLayoutConfig, JsonLayoutEngine};
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::LayoutConfig;
 
@@ -28,6 +31,12 @@ impl YamlLayoutEngine {
             json_engine: JsonLayoutEngine::with_config(config),
         }
     }
+
+    /// Задаёт метрики шрифтов для размеров подписей (например, шрифты PNG рендерера)
+    pub fn with_metrics(mut self, metrics: Arc<dyn TextMetrics>) -> Self {
+        self.json_engine = self.json_engine.with_metrics(metrics);
+        self
+    }
 }
 
 impl Default for YamlLayoutEngine {
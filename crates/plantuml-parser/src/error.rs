@@ -41,3 +41,27 @@ pub enum ParseError {
     #[error("ошибка грамматики: {0}")]
     GrammarError(String),
 }
+
+impl ParseError {
+    /// Номер строки разобранного текста (с 1), если ошибка к ней привязана
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedToken { line, .. } | Self::SyntaxError { line, .. } if *line > 0 => {
+                Some(*line)
+            }
+            _ => None,
+        }
+    }
+
+    /// Текст ошибки без номера строки: номер разобранного текста после
+    /// препроцессора не совпадает со строкой исходника
+    pub fn message(&self) -> String {
+        match self {
+            Self::UnexpectedToken { token, column, .. } => {
+                format!("неожиданный токен '{}', позиция {}", token, column)
+            }
+            Self::SyntaxError { message, .. } => format!("синтаксическая ошибка: {}", message),
+            other => other.to_string(),
+        }
+    }
+}
@@ -9,12 +9,13 @@
 pub mod error;
 pub mod lexer;
 pub mod parsers;
+pub mod sprite;
 
 pub use error::ParseError;
 pub use parsers::{
-    parse_activity, parse_class, parse_component, parse_er, parse_gantt, parse_json, parse_mindmap,
-    parse_network, parse_object, parse_salt, parse_sequence, parse_state, parse_timing, parse_usecase,
-    parse_wbs, parse_yaml,
+    parse_activity, parse_class, parse_component, parse_dot, parse_er, parse_gantt, parse_json,
+    parse_mindmap, parse_network, parse_object, parse_salt, parse_sequence, parse_state,
+    parse_timing, parse_usecase, parse_wbs, parse_yaml,
 };
 pub use plantuml_ast::Diagram;
 
@@ -40,10 +41,14 @@ pub type Result<T> = std::result::Result<T, ParseError>;
 /// assert!(diagram.is_ok());
 /// ```
 pub fn parse(source: &str) -> Result<Diagram> {
+    // Спрайты общие для всех диаграмм и не входят в грамматики
+    let (source, sprites) = sprite::extract_sprites(source)?;
+    let source = source.as_str();
+
     // Определяем тип диаграммы
     let diagram_type = detect_diagram_type(source)?;
 
-    match diagram_type {
+    let mut diagram = match diagram_type {
         DiagramKind::Sequence => parse_sequence_diagram(source),
         DiagramKind::Class => parse_class_diagram(source),
         DiagramKind::Activity => parse_activity_diagram(source),
@@ -62,8 +67,11 @@ pub fn parse(source: &str) -> Result<Diagram> {
         DiagramKind::Network => parse_network_diagram(source),
         DiagramKind::Salt => parse_salt_diagram(source),
         DiagramKind::Archimate => parse_archimate_diagram(source),
+        DiagramKind::Dot => parse_dot_diagram(source),
         DiagramKind::Unknown => Err(ParseError::UnknownDiagramType),
-    }
+    }?;
+    diagram.metadata_mut().sprites = sprites;
+    Ok(diagram)
 }
 
 /// Тип диаграммы
@@ -87,6 +95,7 @@ pub enum DiagramKind {
     Network,
     Salt,
     Archimate,
+    Dot,
     Unknown,
 }
 
@@ -199,10 +208,30 @@ fn has_paren_usecase_pattern(source: &str) -> bool {
     false
 }
 
+/// Проверяет, что первая значимая строка открывает DOT граф
+/// (`digraph G {`, `graph {`, `strict digraph {`)
+fn has_dot_graph_header(source: &str) -> bool {
+    let Some(first) = source
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('@') && !line.starts_with("//"))
+    else {
+        return false;
+    };
+    let first = first.to_lowercase();
+    let header = first.strip_prefix("strict ").map(str::trim_start).unwrap_or(&first);
+    header.starts_with("digraph") || (header.starts_with("graph") && header.contains('{'))
+}
+
 /// Определяет тип диаграммы по содержимому
 pub fn detect_diagram_type(source: &str) -> Result<DiagramKind> {
     let source_lower = source.to_lowercase();
 
+    // DOT граф — проверяем по @startdot или заголовку digraph/graph
+    if source_lower.contains("@startdot") || has_dot_graph_header(source) {
+        return Ok(DiagramKind::Dot);
+    }
+
     // Salt Diagram — проверяем по @startsalt или salt keyword
     if source_lower.contains("@startsalt") || (source_lower.contains("salt") && source_lower.contains("{")) {
         return Ok(DiagramKind::Salt);
@@ -336,10 +365,12 @@ pub fn detect_diagram_type(source: &str) -> Result<DiagramKind> {
     }
 
     // database в сочетании с sequence-паттернами (-> или -->) — это sequence
+    // (rectangle в sequence не бывает: database рядом с ним — компонент, как в C4)
     if source_lower.contains("database ")
         && (source_lower.contains(" -> ") || source_lower.contains(" --> "))
         && !source_lower.contains("component ")
         && !source_lower.contains("package ")
+        && !source_lower.contains("rectangle ")
     {
         return Ok(DiagramKind::Sequence);
     }
@@ -360,6 +391,7 @@ pub fn detect_diagram_type(source: &str) -> Result<DiagramKind> {
         || source_lower.contains("cloud ")
         || source_lower.contains("storage ")
         || source_lower.contains("artifact ")
+        || source_lower.contains("rectangle ")
         || source_lower.contains("interface ")
         || (source_lower.contains("package ") && !source_lower.contains("class "))
     {
@@ -481,6 +513,11 @@ fn parse_salt_diagram(source: &str) -> Result<Diagram> {
     Ok(Diagram::Salt(diagram))
 }
 
+fn parse_dot_diagram(source: &str) -> Result<Diagram> {
+    let diagram = parsers::parse_dot(source)?;
+    Ok(Diagram::Dot(diagram))
+}
+
 fn parse_archimate_diagram(source: &str) -> Result<Diagram> {
     // Archimate использует тот же синтаксис что и Component
     let diagram = parsers::parse_component(source)?;
@@ -528,6 +565,9 @@ mod tests {
     fn test_detect_component() {
         let source = "@startuml\ncomponent API\n@enduml";
         assert_eq!(detect_diagram_type(source).unwrap(), DiagramKind::Component);
+
+        let source2 = "@startuml\nrectangle \"Store\" as store\n@enduml";
+        assert_eq!(detect_diagram_type(source2).unwrap(), DiagramKind::Component);
     }
 
     #[test]
@@ -602,6 +642,26 @@ mod tests {
         assert_eq!(detect_diagram_type(source).unwrap(), DiagramKind::Archimate);
     }
 
+    #[test]
+    fn test_detect_dot() {
+        let source = "@startdot\ndigraph G {\n  a -> b\n}\n@enddot";
+        assert_eq!(detect_diagram_type(source).unwrap(), DiagramKind::Dot);
+        let source2 = "@startuml\nstrict graph {\n  a -- b\n}\n@enduml";
+        assert_eq!(detect_diagram_type(source2).unwrap(), DiagramKind::Dot);
+        // "graph" внутри обычной диаграммы не считается DOT
+        let source3 = "@startuml\nclass graph\n@enduml";
+        assert_ne!(detect_diagram_type(source3).unwrap(), DiagramKind::Dot);
+    }
+
+    #[test]
+    fn test_parse_dot_diagram() {
+        let source = "@startdot\ndigraph {\n  a -> b\n}\n@enddot";
+        match parse(source).unwrap() {
+            Diagram::Dot(diagram) => assert_eq!(diagram.edges.len(), 1),
+            other => panic!("ожидалась DOT диаграмма, получено {:?}", other.diagram_type()),
+        }
+    }
+
     #[test]
     fn test_parse_returns_diagram() {
         let source = "@startuml\nAlice -> Bob\n@enduml";
@@ -619,4 +679,26 @@ React -> React: test
 @enduml"#;
         assert_eq!(detect_diagram_type(source).unwrap(), DiagramKind::Sequence);
     }
+
+    #[test]
+    fn test_parse_sprites() {
+        let source = "@startuml\nsprite $db [2x2/16] {\nF0\n0F\n}\ncomponent \"<$db>\\nStore\" as store <<$db>>\n@enduml";
+        let diagram = parse(source).unwrap();
+        let Diagram::Component(component) = &diagram else {
+            panic!("ожидалась диаграмма компонентов");
+        };
+        assert!(diagram.metadata().sprites.get("db").is_some());
+        let store = &component.components[0];
+        assert_eq!(store.name, "<$db>\\nStore");
+        assert_eq!(store.stereotype.as_ref().and_then(|s| s.sprite()), Some("db"));
+    }
+
+    #[test]
+    fn test_syntax_error_line() {
+        let error = parse("@startuml\nA -> B\n!!!\n@enduml").unwrap_err();
+        assert_eq!(error.line(), Some(3));
+
+        let error = parse("@startuml\nclass A\nclass B {{{\n@enduml").unwrap_err();
+        assert_eq!(error.line(), Some(3));
+    }
 }
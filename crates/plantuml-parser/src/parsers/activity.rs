@@ -11,7 +11,8 @@ use plantuml_ast::activity::{
 };
 use plantuml_ast::common::{Color, Note, NotePosition};
 
-use crate::{ParseError, Result};
+use super::pest_error;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/activity.pest"]
@@ -20,10 +21,7 @@ pub struct ActivityParser;
 /// Парсит activity diagram из исходного кода
 pub fn parse_activity(source: &str) -> Result<ActivityDiagram> {
     let pairs =
-        ActivityParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        })?;
+        ActivityParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = ActivityDiagram::new();
 
@@ -9,9 +9,10 @@ use plantuml_ast::class::{
     ClassDiagram, Classifier, ClassifierType, Member, Package, Relationship, RelationshipType,
     Visibility,
 };
-use plantuml_ast::common::{Color, LineStyle, Stereotype};
+use plantuml_ast::common::{Color, LineStyle, Link, Stereotype};
 
-use crate::{ParseError, Result};
+use super::{pest_error, span_of};
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/class.pest"]
@@ -19,10 +20,7 @@ pub struct ClassParser;
 
 /// Парсит class diagram из исходного кода
 pub fn parse_class(source: &str) -> Result<ClassDiagram> {
-    let pairs = ClassParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-        line: e.line().to_string().parse().unwrap_or(0),
-        message: e.to_string(),
-    })?;
+    let pairs = ClassParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = ClassDiagram::new();
     let mut package_stack: Vec<Package> = Vec::new();
@@ -209,6 +207,8 @@ fn parse_class_decl_with_inheritance(
     let mut methods: Vec<Member> = Vec::new();
     let mut extends: Option<String> = None;
     let mut implements: Vec<String> = Vec::new();
+    let mut link: Option<Link> = None;
+    let span = span_of(&pair);
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -229,6 +229,9 @@ fn parse_class_decl_with_inheritance(
             Rule::color => {
                 color = Some(Color::from_hex(inner.as_str()));
             }
+            Rule::link => {
+                link = Link::parse(inner.as_str());
+            }
             Rule::generic_params => {
                 generics = Some(inner.as_str().to_string());
             }
@@ -269,6 +272,8 @@ fn parse_class_decl_with_inheritance(
             background_color: color,
             border_color: None,
             generics,
+            link,
+            span,
         },
         extends,
         implements,
@@ -662,6 +667,24 @@ fn extract_name(pair: pest::iterators::Pair<Rule>) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_class_link() {
+        let source = r#"@startuml
+class User [[https://example.com/user]] {
+    -id: Long
+}
+class Order
+@enduml"#;
+
+        let diagram = parse_class(source).unwrap();
+        let user = &diagram.classifiers[0];
+        assert_eq!(user.link.as_ref().unwrap().url, "https://example.com/user");
+        assert_eq!(user.span.line, 2);
+        assert_eq!(user.fields.len(), 1);
+        assert!(diagram.classifiers[1].link.is_none());
+        assert_eq!(diagram.classifiers[1].span.line, 5);
+    }
+
     #[test]
     fn test_parse_simple_class() {
         let source = r#"@startuml
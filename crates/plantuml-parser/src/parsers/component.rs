@@ -8,9 +8,10 @@ use pest_derive::Parser;
 use plantuml_ast::component::{
     Component, ComponentDiagram, ComponentPackage, ComponentType, Connection, PackageType,
 };
-use plantuml_ast::common::{Color, Note, NotePosition, Stereotype};
+use plantuml_ast::common::{Color, Direction, Note, NotePosition, Stereotype};
 
-use crate::{ParseError, Result};
+use super::pest_error;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/component.pest"]
@@ -18,12 +19,7 @@ pub struct ComponentParser;
 
 /// Парсит component diagram из исходного кода
 pub fn parse_component(source: &str) -> Result<ComponentDiagram> {
-    let pairs = ComponentParser::parse(Rule::diagram, source).map_err(|e| {
-        ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        }
-    })?;
+    let pairs = ComponentParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = ComponentDiagram::new();
 
@@ -69,11 +65,29 @@ fn parse_body(pair: pest::iterators::Pair<Rule>, diagram: &mut ComponentDiagram)
                     diagram.notes.push(note);
                 }
             }
+            Rule::legend_stmt => {
+                diagram.metadata.legend = parse_legend(inner);
+            }
             _ => {}
         }
     }
 }
 
+/// Парсит легенду: непустые строки тела без отступов
+fn parse_legend(pair: pest::iterators::Pair<Rule>) -> Option<String> {
+    let body = pair
+        .into_inner()
+        .find(|inner| inner.as_rule() == Rule::legend_body)?;
+    let text = body
+        .as_str()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (!text.is_empty()).then_some(text)
+}
+
 /// Парсит определение компонента (простой, без тела)
 fn parse_component_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
     let mut name = String::new();
@@ -243,6 +257,8 @@ fn parse_connection(pair: pest::iterators::Pair<Rule>) -> Option<Connection> {
     let mut to = String::new();
     let mut label: Option<String> = None;
     let mut dashed = false;
+    let mut hidden = false;
+    let mut direction = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -255,9 +271,16 @@ fn parse_connection(pair: pest::iterators::Pair<Rule>) -> Option<Connection> {
             Rule::arrow => {
                 // Проверяем тип стрелки
                 for arrow_inner in inner.into_inner() {
-                    if arrow_inner.as_rule() == Rule::arrow_dashed {
-                        dashed = true;
+                    match arrow_inner.as_rule() {
+                        Rule::arrow_dashed => dashed = true,
+                        Rule::arrow_hidden => hidden = true,
+                        _ => {}
                     }
+                    direction = arrow_inner
+                        .into_inner()
+                        .find(|p| p.as_rule() == Rule::arrow_direction)
+                        .map(|p| parse_arrow_direction(p.as_str()))
+                        .or(direction);
                 }
             }
             Rule::connection_label => {
@@ -278,9 +301,21 @@ fn parse_connection(pair: pest::iterators::Pair<Rule>) -> Option<Connection> {
         label,
         color: None,
         dashed,
+        hidden,
+        direction,
     })
 }
 
+/// Направление стрелки: куда относительно источника стоит цель
+fn parse_arrow_direction(s: &str) -> Direction {
+    match s {
+        "up" | "u" => Direction::BottomToTop,
+        "left" | "l" => Direction::RightToLeft,
+        "right" | "r" => Direction::LeftToRight,
+        _ => Direction::TopToBottom,
+    }
+}
+
 /// Извлекает endpoint связи
 fn extract_connection_endpoint(pair: pest::iterators::Pair<Rule>) -> String {
     for inner in pair.into_inner() {
@@ -615,4 +650,35 @@ node "Outer" {
         assert_eq!(diagram.packages[0].packages[0].name, "Inner");
         assert_eq!(diagram.packages[0].packages[0].components.len(), 1, "Should have 1 component");
     }
+
+    #[test]
+    fn test_parse_hidden_connection_and_legend() {
+        let source = r#"
+@startuml
+[A]
+[B]
+[C]
+A -[hidden]-> B
+B -[hidden]right- C
+A --up-> C
+legend right
+  Первая строка
+  Вторая строка
+endlegend
+@enduml
+"#;
+
+        let diagram = parse_component(source).unwrap();
+        assert_eq!(diagram.connections.len(), 3);
+        assert!(diagram.connections[0].hidden);
+        assert_eq!(diagram.connections[0].direction, None);
+        assert!(diagram.connections[1].hidden);
+        assert_eq!(diagram.connections[1].direction, Some(Direction::LeftToRight));
+        assert!(!diagram.connections[2].hidden);
+        assert_eq!(diagram.connections[2].direction, Some(Direction::BottomToTop));
+        assert_eq!(
+            diagram.metadata.legend.as_deref(),
+            Some("Первая строка\nВторая строка")
+        );
+    }
 }
@@ -0,0 +1,491 @@
+//! Парсер Graphviz DOT графов
+//!
+//! Разбирает `digraph`/`graph` внутри `@startdot ... @enddot` (или
+//! `@startuml`). Значения по умолчанию `node [...]`/`edge [...]` действуют
+//! до конца текущего подграфа и применяются к узлам и рёбрам сразу.
+
+use std::collections::HashMap;
+
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser;
+
+use plantuml_ast::dot::{DotAttributes, DotDiagram, DotEdge, DotNode, DotSubgraph};
+
+use crate::error::ParseError;
+
+#[derive(Parser)]
+#[grammar = "grammars/dot.pest"]
+struct DotParser;
+
+/// Парсит DOT граф
+pub fn parse_dot(source: &str) -> crate::Result<DotDiagram> {
+    let content = extract_dot_content(source);
+
+    let pairs = DotParser::parse(Rule::dot_diagram, &content)
+        .map_err(|e| ParseError::GrammarError(format!("Ошибка парсинга DOT: {}", e)))?;
+
+    let mut builder = Builder::default();
+    for pair in pairs.flatten() {
+        if pair.as_rule() == Rule::graph {
+            builder.parse_graph(pair);
+            break;
+        }
+    }
+
+    Ok(builder.diagram)
+}
+
+/// Убирает строки `@startdot`/`@enddot` (`@startuml`/`@enduml`)
+fn extract_dot_content(source: &str) -> String {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('@'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Значения по умолчанию текущей области видимости
+#[derive(Debug, Clone, Default)]
+struct Defaults {
+    node: DotAttributes,
+    edge: DotAttributes,
+}
+
+/// Конец ребра: узел с необязательным портом
+struct EndPoint {
+    id: String,
+    port: Option<String>,
+}
+
+/// Собирает диаграмму при обходе дерева разбора
+#[derive(Default)]
+struct Builder {
+    diagram: DotDiagram,
+    index: HashMap<String, usize>,
+}
+
+impl Builder {
+    /// Разбирает граф верхнего уровня
+    fn parse_graph(&mut self, pair: Pair<Rule>) {
+        let mut root = DotSubgraph::new(None);
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::strict_kw => self.diagram.strict = true,
+                Rule::graph_kind => {
+                    self.diagram.directed = inner.as_str().eq_ignore_ascii_case("digraph");
+                }
+                Rule::id => root.name = Some(id_value(inner)),
+                Rule::stmt_list => {
+                    let mut defaults = Defaults::default();
+                    self.parse_stmt_list(inner, &mut defaults, &mut root);
+                }
+                _ => {}
+            }
+        }
+        self.diagram.name = root.name;
+        self.diagram.attributes = root.attributes;
+        self.diagram.subgraphs = root.subgraphs;
+    }
+
+    /// Разбирает операторы подграфа (или графа)
+    fn parse_stmt_list(
+        &mut self,
+        pair: Pair<Rule>,
+        defaults: &mut Defaults,
+        scope: &mut DotSubgraph,
+    ) {
+        for stmt in pair.into_inner() {
+            match stmt.as_rule() {
+                Rule::attr_stmt => {
+                    let mut inner = stmt.into_inner();
+                    let target = inner.next().map(|p| p.as_str().to_ascii_lowercase());
+                    let attributes = inner.next().map(attr_list).unwrap_or_default();
+                    match target.as_deref() {
+                        Some("node") => defaults.node.extend(attributes),
+                        Some("edge") => defaults.edge.extend(attributes),
+                        _ => scope.attributes.extend(attributes),
+                    }
+                }
+                Rule::id_assignment => {
+                    let mut inner = stmt.into_inner();
+                    if let (Some(name), Some(value)) = (inner.next(), inner.next()) {
+                        scope.attributes.insert(id_value(name), id_value(value));
+                    }
+                }
+                Rule::node_stmt => {
+                    let mut inner = stmt.into_inner();
+                    let Some(node) = inner.next().map(node_id) else {
+                        continue;
+                    };
+                    let attributes = inner.next().map(attr_list).unwrap_or_default();
+                    self.add_node(&node.id, defaults, attributes, scope);
+                }
+                Rule::edge_stmt => self.parse_edge_stmt(stmt, defaults, scope),
+                Rule::subgraph => {
+                    self.parse_subgraph(stmt, defaults, scope);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Разбирает подграф, добавляет его в `scope` и возвращает все его узлы
+    fn parse_subgraph(
+        &mut self,
+        pair: Pair<Rule>,
+        defaults: &Defaults,
+        scope: &mut DotSubgraph,
+    ) -> Vec<String> {
+        let mut subgraph = DotSubgraph::new(None);
+        // Значения по умолчанию подграфа не выходят за его пределы
+        let mut defaults = defaults.clone();
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::id => subgraph.name = Some(id_value(inner)),
+                Rule::stmt_list => self.parse_stmt_list(inner, &mut defaults, &mut subgraph),
+                _ => {}
+            }
+        }
+
+        let nodes = all_nodes(&subgraph);
+        scope.subgraphs.push(subgraph);
+        nodes
+    }
+
+    /// Разбирает цепочку рёбер `a -> b -> { c d }`
+    fn parse_edge_stmt(&mut self, pair: Pair<Rule>, defaults: &Defaults, scope: &mut DotSubgraph) {
+        let mut operands: Vec<Vec<EndPoint>> = Vec::new();
+        let mut attributes = DotAttributes::new();
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::node_id => {
+                    let node = node_id(inner);
+                    self.add_node(&node.id, defaults, DotAttributes::new(), scope);
+                    operands.push(vec![node]);
+                }
+                Rule::subgraph => {
+                    let nodes = self.parse_subgraph(inner, defaults, scope);
+                    operands.push(
+                        nodes
+                            .into_iter()
+                            .map(|id| EndPoint { id, port: None })
+                            .collect(),
+                    );
+                }
+                Rule::attr_list => attributes = attr_list(inner),
+                _ => {}
+            }
+        }
+
+        for pair in operands.windows(2) {
+            for from in &pair[0] {
+                for to in &pair[1] {
+                    let mut edge = DotEdge::new(from.id.as_str(), to.id.as_str());
+                    edge.attributes = defaults.edge.clone();
+                    if let Some(port) = &from.port {
+                        edge.attributes.insert("tailport".to_string(), port.clone());
+                    }
+                    if let Some(port) = &to.port {
+                        edge.attributes.insert("headport".to_string(), port.clone());
+                    }
+                    edge.attributes.extend(attributes.clone());
+                    self.push_edge(edge);
+                }
+            }
+        }
+    }
+
+    /// Добавляет ребро; в `strict` графе повтор дополняет атрибуты существующего
+    fn push_edge(&mut self, edge: DotEdge) {
+        if self.diagram.strict {
+            let directed = self.diagram.directed;
+            let existing = self.diagram.edges.iter_mut().find(|e| {
+                (e.from == edge.from && e.to == edge.to)
+                    || (!directed && e.from == edge.to && e.to == edge.from)
+            });
+            if let Some(existing) = existing {
+                existing.attributes.extend(edge.attributes);
+                return;
+            }
+        }
+        self.diagram.edges.push(edge);
+    }
+
+    /// Добавляет узел (или дополняет атрибуты существующего) и отмечает его в `scope`
+    fn add_node(
+        &mut self,
+        id: &str,
+        defaults: &Defaults,
+        attributes: DotAttributes,
+        scope: &mut DotSubgraph,
+    ) {
+        let index = match self.index.get(id) {
+            Some(&index) => index,
+            None => {
+                let mut node = DotNode::new(id);
+                node.attributes = defaults.node.clone();
+                self.diagram.nodes.push(node);
+                self.index
+                    .insert(id.to_string(), self.diagram.nodes.len() - 1);
+                self.diagram.nodes.len() - 1
+            }
+        };
+        self.diagram.nodes[index].attributes.extend(attributes);
+        if !scope.nodes.iter().any(|n| n == id) {
+            scope.nodes.push(id.to_string());
+        }
+    }
+}
+
+/// Все узлы подграфа, включая вложенные
+fn all_nodes(subgraph: &DotSubgraph) -> Vec<String> {
+    let mut nodes = subgraph.nodes.clone();
+    for child in &subgraph.subgraphs {
+        for id in all_nodes(child) {
+            if !nodes.contains(&id) {
+                nodes.push(id);
+            }
+        }
+    }
+    nodes
+}
+
+/// Разбирает `node_id` (идентификатор с портом)
+fn node_id(pair: Pair<Rule>) -> EndPoint {
+    let mut id = String::new();
+    let mut port = None;
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::id => id = id_value(inner),
+            Rule::port => {
+                // node:port:compass — для layout важна сторона (compass)
+                port = inner.into_inner().last().map(id_value);
+            }
+            _ => {}
+        }
+    }
+    EndPoint { id, port }
+}
+
+/// Разбирает список атрибутов `[a=b, c=d][e=f]`
+fn attr_list(pair: Pair<Rule>) -> DotAttributes {
+    let mut attributes = DotAttributes::new();
+    for attribute in pair.into_inner() {
+        let mut inner = attribute.into_inner();
+        if let (Some(name), Some(value)) = (inner.next(), inner.next()) {
+            attributes.insert(id_value(name), id_value(value));
+        }
+    }
+    attributes
+}
+
+/// Значение идентификатора: строки раскавычиваются, из HTML убираются теги
+fn id_value(pair: Pair<Rule>) -> String {
+    let Some(inner) = pair.into_inner().next() else {
+        return String::new();
+    };
+    match inner.as_rule() {
+        Rule::quoted => inner
+            .into_inner()
+            .filter_map(|part| part.into_inner().next())
+            .map(|content| unescape(content.as_str()))
+            .collect(),
+        Rule::html => inner
+            .into_inner()
+            .next()
+            .map(|content| strip_html(content.as_str()))
+            .unwrap_or_default(),
+        _ => inner.as_str().to_string(),
+    }
+}
+
+/// Обрабатывает escape-последовательности строки DOT
+///
+/// `\"` → `"`; переводы строк `\l`, `\r` приводятся к `\n`, который
+/// понимают layout и рендерер.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => result.push('"'),
+            Some('l') | Some('r') | Some('n') => result.push_str("\\n"),
+            // Продолжение строки
+            Some('\n') => {}
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Превращает HTML-метку в текст: `<br/>` — перевод строки, прочие теги удаляются
+fn strip_html(html: &str) -> String {
+    let mut result = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_ascii_lowercase();
+        if tag.starts_with("br") {
+            result.push_str("\\n");
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_ast::dot::DotShape;
+
+    #[test]
+    fn test_parse_simple_digraph() {
+        let source = r#"@startdot
+digraph G {
+  a -> b -> c;
+  b -> d
+}
+@enddot"#;
+
+        let diagram = parse_dot(source).unwrap();
+        assert!(diagram.directed);
+        assert_eq!(diagram.name.as_deref(), Some("G"));
+        assert_eq!(diagram.nodes.len(), 4);
+        assert_eq!(diagram.edges.len(), 3);
+        assert_eq!(diagram.edges[1].from, "b");
+        assert_eq!(diagram.edges[1].to, "c");
+    }
+
+    #[test]
+    fn test_parse_attributes_and_defaults() {
+        let source = r##"@startdot
+strict graph {
+  rankdir=LR
+  node [shape=box, color="#336699"]
+  a [label="Start\nhere"]
+  edge [style=dashed]
+  a -- b [label=<<b>next</b>>]
+  c [shape=diamond]
+}
+@enddot"##;
+
+        let diagram = parse_dot(source).unwrap();
+        assert!(diagram.strict);
+        assert!(!diagram.directed);
+        assert_eq!(
+            diagram.attributes.get("rankdir").map(String::as_str),
+            Some("LR")
+        );
+
+        let a = diagram.find_node("a").unwrap();
+        assert_eq!(a.shape(), DotShape::Box);
+        assert_eq!(a.label(), "Start\\nhere");
+        assert_eq!(
+            a.attributes.get("color").map(String::as_str),
+            Some("#336699")
+        );
+
+        // Умолчания применяются и к узлам, созданным ребром
+        assert_eq!(diagram.find_node("b").unwrap().shape(), DotShape::Box);
+        assert_eq!(diagram.find_node("c").unwrap().shape(), DotShape::Diamond);
+
+        let edge = &diagram.edges[0];
+        assert!(edge.has_style("dashed"));
+        assert_eq!(edge.label(), Some("next"));
+    }
+
+    #[test]
+    fn test_parse_clusters() {
+        let source = r#"@startdot
+digraph {
+  subgraph cluster_front {
+    label="Frontend"
+    node [shape=box]
+    ui -> api
+    subgraph cluster_inner { widget }
+  }
+  api -> { db cache }
+  db
+}
+@enddot"#;
+
+        let diagram = parse_dot(source).unwrap();
+        assert_eq!(diagram.subgraphs.len(), 2);
+
+        let front = &diagram.subgraphs[0];
+        assert!(front.is_cluster());
+        assert_eq!(front.label(), Some("Frontend"));
+        assert_eq!(front.nodes, vec!["ui", "api"]);
+        assert_eq!(front.subgraphs[0].nodes, vec!["widget"]);
+
+        // Умолчания кластера не действуют снаружи
+        assert_eq!(diagram.find_node("db").unwrap().shape(), DotShape::Ellipse);
+
+        // Ребро к анонимному подграфу разворачивается в рёбра к каждому узлу
+        let targets: Vec<&str> = diagram
+            .edges
+            .iter()
+            .filter(|e| e.from == "api")
+            .map(|e| e.to.as_str())
+            .collect();
+        assert_eq!(targets, vec!["db", "cache"]);
+    }
+
+    #[test]
+    fn test_parse_ports_and_comments() {
+        let source = r#"@startdot
+digraph {
+  // комментарий
+  /* блочный
+     комментарий */
+  a:s -> b:port:n
+}
+@enddot"#;
+
+        let diagram = parse_dot(source).unwrap();
+        let edge = &diagram.edges[0];
+        assert_eq!(
+            edge.attributes.get("tailport").map(String::as_str),
+            Some("s")
+        );
+        assert_eq!(
+            edge.attributes.get("headport").map(String::as_str),
+            Some("n")
+        );
+    }
+
+    #[test]
+    fn test_parse_strict_merges_edges() {
+        let source = "@startdot\nstrict graph {\n  a -- b\n  b -- a [color=red]\n}\n@enddot";
+        let diagram = parse_dot(source).unwrap();
+        assert_eq!(diagram.edges.len(), 1);
+        assert_eq!(
+            diagram.edges[0].attributes.get("color").map(String::as_str),
+            Some("red")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_dot("@startdot\ndigraph {\n  a -> \n@enddot").is_err());
+    }
+}
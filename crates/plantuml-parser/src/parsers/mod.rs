@@ -3,6 +3,7 @@
 pub mod activity;
 pub mod class;
 pub mod component;
+pub mod dot;
 pub mod er;
 pub mod gantt;
 pub mod json;
@@ -21,6 +22,7 @@ pub use activity::parse_activity;
 pub use class::parse_class;
 pub use er::parse_er;
 pub use component::parse_component;
+pub use dot::parse_dot;
 pub use gantt::parse_gantt;
 pub use json::parse_json;
 pub use mindmap::parse_mindmap;
@@ -33,3 +35,25 @@ pub use network::parse_network;
 pub use salt::parse_salt;
 pub use wbs::parse_wbs;
 pub use yaml::parse_yaml;
+
+use plantuml_ast::common::Span;
+
+/// Позиция правила в исходном коде (для `data-line` в SVG и сообщений об ошибках)
+pub(crate) fn span_of<R: pest::RuleType>(pair: &pest::iterators::Pair<'_, R>) -> Span {
+    let span = pair.as_span();
+    let (line, column) = span.start_pos().line_col();
+    Span::new(span.start(), span.end(), line, column)
+}
+
+/// Ошибка pest с номером строки, в которой она обнаружена; в тексте только
+/// ожидание pest и позиция в строке, без фрагмента исходника с номером строки
+pub(crate) fn pest_error<R: pest::RuleType>(error: pest::error::Error<R>) -> crate::ParseError {
+    let (line, column) = match error.line_col {
+        pest::error::LineColLocation::Pos(position)
+        | pest::error::LineColLocation::Span(position, _) => position,
+    };
+    crate::ParseError::SyntaxError {
+        line,
+        message: format!("{}, позиция {}", error.variant.message(), column),
+    }
+}
@@ -8,7 +8,8 @@ use pest_derive::Parser;
 use plantuml_ast::common::{Note, NotePosition, Stereotype};
 use plantuml_ast::object::{Object, ObjectDiagram, ObjectField, ObjectLink, ObjectLinkType};
 
-use crate::{ParseError, Result};
+use super::pest_error;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/object.pest"]
@@ -16,12 +17,7 @@ pub struct ObjectParser;
 
 /// Парсит object diagram из исходного кода
 pub fn parse_object(source: &str) -> Result<ObjectDiagram> {
-    let pairs = ObjectParser::parse(Rule::diagram, source).map_err(|e| {
-        ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        }
-    })?;
+    let pairs = ObjectParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = ObjectDiagram::new();
 
@@ -5,14 +5,15 @@
 use pest::Parser;
 use pest_derive::Parser;
 
-use plantuml_ast::common::{Color, LineStyle, Note, NotePosition, Stereotype};
+use plantuml_ast::common::{Color, LineStyle, Link, Note, NotePosition, Stereotype};
 use plantuml_ast::sequence::{
     Activation, ActivationType, ArrowType, AutonumberCommand, AutonumberStart, Delay, Divider,
     Fragment, FragmentSection, FragmentType, Message, Participant, ParticipantBox, ParticipantType,
     Return, SequenceDiagram, SequenceElement,
 };
 
-use crate::{ParseError, Result};
+use super::{pest_error, span_of};
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/sequence.pest"]
@@ -27,10 +28,7 @@ type BoxState = (Option<String>, Option<Color>, Vec<String>);
 /// Парсит sequence diagram из исходного кода
 pub fn parse_sequence(source: &str) -> Result<SequenceDiagram> {
     let pairs =
-        SequenceParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        })?;
+        SequenceParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = SequenceDiagram::new();
     let mut fragment_stack: Vec<FragmentStackEntry> = Vec::new();
@@ -243,12 +241,14 @@ fn process_rule(
 
 /// Парсит объявление участника
 fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<Participant> {
+    let span = span_of(&pair);
     let mut participant_type = ParticipantType::Participant;
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut stereotype: Option<Stereotype> = None;
     let mut color: Option<Color> = None;
     let mut order: Option<i32> = None;
+    let mut link: Option<Link> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -290,6 +290,9 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<Participant> {
                     order = Some(n);
                 }
             }
+            Rule::link => {
+                link = Link::parse(inner.as_str());
+            }
             _ => {}
         }
     }
@@ -305,6 +308,8 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<Participant> {
     participant.stereotype = stereotype;
     participant.color = color;
     participant.order = order;
+    participant.link = link;
+    participant.span = span;
 
     Some(participant)
 }
@@ -332,6 +337,7 @@ fn extract_name(pair: pest::iterators::Pair<Rule>) -> String {
 
 /// Парсит сообщение
 fn parse_message(pair: pest::iterators::Pair<Rule>) -> Option<Message> {
+    let span = span_of(&pair);
     let mut from = String::new();
     let mut to = String::new();
     let mut label = String::new();
@@ -377,7 +383,11 @@ fn parse_message(pair: pest::iterators::Pair<Rule>) -> Option<Message> {
         return None;
     }
 
+    // Ссылка в тексте сообщения: A -> B : запрос [[/api/login]]
+    let (label, link) = Link::extract(&label);
     let mut message = Message::new(from, to, label);
+    message.link = link;
+    message.span = span;
     message.line_style = line_style;
     message.arrow_type = arrow_type;
     message.activate = activate;
@@ -778,6 +788,28 @@ fn parse_return(pair: pest::iterators::Pair<Rule>) -> Return {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_links_and_lines() {
+        let source = r#"@startuml
+participant API [[https://example.com/api Документация]]
+Alice -> API: запрос [[/login{вход}]]
+@enduml"#;
+
+        let diagram = parse_sequence(source).unwrap();
+        let api = &diagram.participants[0];
+        assert_eq!(api.span.line, 2);
+        let link = api.link.as_ref().unwrap();
+        assert_eq!(link.url, "https://example.com/api");
+        assert_eq!(link.tooltip.as_deref(), Some("Документация"));
+
+        let SequenceElement::Message(message) = &diagram.elements[0] else {
+            panic!("ожидалось сообщение");
+        };
+        assert_eq!(message.label, "запрос");
+        assert_eq!(message.span.line, 3);
+        assert_eq!(message.link.as_ref().unwrap().url, "/login");
+    }
+
     #[test]
     fn test_parse_basic_sequence() {
         let source = r#"@startuml
@@ -6,9 +6,10 @@ use pest::Parser;
 use pest_derive::Parser;
 
 use plantuml_ast::state::{State, StateDiagram, StateType, Transition};
-use plantuml_ast::common::{Note, NotePosition};
+use plantuml_ast::common::{Link, Note, NotePosition, Span};
 
-use crate::{ParseError, Result};
+use super::{pest_error, span_of};
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/state.pest"]
@@ -17,10 +18,7 @@ pub struct StateParser;
 /// Парсит state diagram из исходного кода
 pub fn parse_state(source: &str) -> Result<StateDiagram> {
     let pairs =
-        StateParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        })?;
+        StateParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = StateDiagram::new();
 
@@ -63,6 +61,12 @@ fn parse_body(pair: pest::iterators::Pair<Rule>, diagram: &mut StateDiagram) {
 
 /// Парсит определение состояния
 fn parse_state_def(pair: pest::iterators::Pair<Rule>) -> Option<State> {
+    let span = span_of(&pair);
+    parse_state_kind(pair).map(|state| State { span, ..state })
+}
+
+/// Парсит состояние по виду объявления
+fn parse_state_kind(pair: pest::iterators::Pair<Rule>) -> Option<State> {
     for inner in pair.into_inner() {
         match inner.as_rule() {
             Rule::state_composite => return parse_state_composite(inner),
@@ -97,6 +101,7 @@ fn parse_state_composite(pair: pest::iterators::Pair<Rule>) -> Option<State> {
     let mut alias: Option<String> = None;
     let mut substates = Vec::new();
     let mut internal_transitions = Vec::new();
+    let mut link: Option<Link> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -106,6 +111,9 @@ fn parse_state_composite(pair: pest::iterators::Pair<Rule>) -> Option<State> {
             Rule::state_alias_part => {
                 alias = extract_alias(inner);
             }
+            Rule::state_link_part => {
+                link = extract_link(inner);
+            }
             Rule::body => {
                 // Парсим вложенное тело
                 let mut sub_diagram = StateDiagram::new();
@@ -134,6 +142,8 @@ fn parse_state_composite(pair: pest::iterators::Pair<Rule>) -> Option<State> {
         entry_action: None,
         exit_action: None,
         do_action: None,
+        link,
+        span: Span::empty(),
     })
 }
 
@@ -142,6 +152,7 @@ fn parse_state_simple(pair: pest::iterators::Pair<Rule>) -> Option<State> {
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut description: Option<String> = None;
+    let mut link: Option<Link> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -151,6 +162,9 @@ fn parse_state_simple(pair: pest::iterators::Pair<Rule>) -> Option<State> {
             Rule::state_alias_part => {
                 alias = extract_alias(inner);
             }
+            Rule::state_link_part => {
+                link = extract_link(inner);
+            }
             Rule::state_description_part => {
                 description = extract_description(inner);
             }
@@ -175,6 +189,8 @@ fn parse_state_simple(pair: pest::iterators::Pair<Rule>) -> Option<State> {
         entry_action: None,
         exit_action: None,
         do_action: None,
+        link,
+        span: Span::empty(),
     })
 }
 
@@ -214,6 +230,8 @@ fn parse_state_alias(pair: pest::iterators::Pair<Rule>) -> Option<State> {
         entry_action: None,
         exit_action: None,
         do_action: None,
+        link: None,
+        span: Span::empty(),
     })
 }
 
@@ -244,6 +262,8 @@ fn parse_state_special(pair: pest::iterators::Pair<Rule>, state_type: StateType)
         entry_action: None,
         exit_action: None,
         do_action: None,
+        link: None,
+        span: Span::empty(),
     })
 }
 
@@ -450,6 +470,13 @@ fn extract_alias(pair: pest::iterators::Pair<Rule>) -> Option<String> {
     None
 }
 
+/// Извлекает ссылку `[[url]]`
+fn extract_link(pair: pest::iterators::Pair<Rule>) -> Option<Link> {
+    pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::link)
+        .and_then(|inner| Link::parse(inner.as_str()))
+}
+
 /// Извлекает описание
 fn extract_description(pair: pest::iterators::Pair<Rule>) -> Option<String> {
     for inner in pair.into_inner() {
@@ -477,6 +504,22 @@ fn extract_quoted_string(pair: pest::iterators::Pair<Rule>) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_state_link() {
+        let source = r#"@startuml
+state Idle [[https://example.com/idle ожидание]] : ждёт запрос
+[*] --> Idle
+@enduml"#;
+
+        let diagram = parse_state(source).unwrap();
+        let idle = &diagram.states[0];
+        assert_eq!(idle.span.line, 2);
+        assert_eq!(idle.description.as_deref(), Some("ждёт запрос"));
+        let link = idle.link.as_ref().unwrap();
+        assert_eq!(link.url, "https://example.com/idle");
+        assert_eq!(link.tooltip.as_deref(), Some("ожидание"));
+    }
+
     #[test]
     fn test_parse_simple_transition() {
         let source = r#"
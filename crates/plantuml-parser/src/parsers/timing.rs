@@ -9,7 +9,8 @@ use plantuml_ast::timing::{
     ParticipantType, StateChange, TimeConstraint, TimeValue, TimingDiagram, TimingParticipant,
 };
 
-use crate::{ParseError, Result};
+use super::pest_error;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/timing.pest"]
@@ -17,12 +18,7 @@ pub struct TimingParser;
 
 /// Парсит timing diagram из исходного кода
 pub fn parse_timing(source: &str) -> Result<TimingDiagram> {
-    let pairs = TimingParser::parse(Rule::diagram, source).map_err(|e| {
-        ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        }
-    })?;
+    let pairs = TimingParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = TimingDiagram::new();
     let mut current_time: Option<TimeValue> = None;
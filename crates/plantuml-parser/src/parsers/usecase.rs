@@ -11,7 +11,8 @@ use plantuml_ast::usecase::{
     UseCaseRelationship,
 };
 
-use crate::{ParseError, Result};
+use super::pest_error;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/usecase.pest"]
@@ -19,10 +20,7 @@ pub struct UseCaseParser;
 
 /// Парсит use case diagram из исходного кода
 pub fn parse_usecase(source: &str) -> Result<UseCaseDiagram> {
-    let pairs = UseCaseParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-        line: e.line().to_string().parse().unwrap_or(0),
-        message: e.to_string(),
-    })?;
+    let pairs = UseCaseParser::parse(Rule::diagram, source).map_err(pest_error)?;
 
     let mut diagram = UseCaseDiagram::new();
 
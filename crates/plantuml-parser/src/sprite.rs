@@ -0,0 +1,412 @@
+//! Определения спрайтов
+//!
+//! `sprite` — общая для всех диаграмм команда, поэтому определения
+//! извлекаются из текста до выбора грамматики. Поддерживаются форматы:
+//!
+//! - `sprite $name [WxH/16] { ... }` — шестнадцатеричная строка на строку пикселей
+//! - `[WxH/8]` и `[WxH/4]` — символ `0-9A-Za-z-_` кодирует 2 или 3 пикселя по вертикали
+//! - `[WxH/16z] ...` (`/8z`, `/4z`) — пиксели, сжатые deflate, в той же 64-символьной кодировке
+//! - `sprite $name <svg ...>...</svg>` — встроенный SVG
+//!
+//! Строки определений заменяются пустыми, чтобы номера строк не сдвигались.
+//! Внутри многострочных заметок, легенд, заголовков и подвалов `sprite` —
+//! обычный текст, а размер монохромного спрайта ограничен
+//! [`MAX_SPRITE_PIXELS`].
+
+use std::io::Read;
+
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use plantuml_ast::sprite::MAX_SPRITE_PIXELS;
+use plantuml_ast::{Sprite, SpriteRegistry};
+
+use crate::{ParseError, Result};
+
+/// Извлекает спрайты: возвращает текст без определений и реестр спрайтов
+pub fn extract_sprites(source: &str) -> Result<(String, SpriteRegistry)> {
+    let mut registry = SpriteRegistry::new();
+    if !source.contains("sprite") {
+        return Ok((source.to_string(), registry));
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut output = Vec::with_capacity(lines.len());
+    // Концы открытого многострочного блока (заметки, легенды и т.п.)
+    let mut block: Option<&[&str]> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let trimmed = lines[i].trim();
+        let definition = match block {
+            Some(ends) => {
+                if ends.iter().any(|end| trimmed.eq_ignore_ascii_case(end)) {
+                    block = None;
+                }
+                None
+            }
+            None => {
+                block = block_ends(trimmed);
+                trimmed
+                    .strip_prefix("sprite ")
+                    .and_then(split_name)
+                    .filter(|(_, rest)| is_definition(rest))
+            }
+        };
+        let Some((name, rest)) = definition else {
+            output.push(lines[i]);
+            i += 1;
+            continue;
+        };
+        let line = i + 1;
+
+        // Тело: до `</svg>` для SVG или до `}` для блока строк
+        let mut body = vec![rest];
+        if rest.starts_with("<svg") {
+            while !body.iter().any(|part| part.contains("</svg>")) {
+                i += 1;
+                let part = lines.get(i).ok_or_else(|| error(line, "нет </svg>"))?;
+                body.push(part.trim());
+            }
+        } else if rest.ends_with('{') {
+            loop {
+                i += 1;
+                let part = lines
+                    .get(i)
+                    .ok_or_else(|| error(line, "нет закрывающей }"))?;
+                if part.trim() == "}" {
+                    break;
+                }
+                body.push(part.trim());
+            }
+        }
+        registry.insert(
+            name,
+            parse_sprite(&body).map_err(|message| error(line, &message))?,
+        );
+
+        // Определение занимает строки line..=i
+        output.extend(std::iter::repeat("").take(i + 2 - line));
+        i += 1;
+    }
+
+    let mut text = output.join("\n");
+    if source.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok((text, registry))
+}
+
+/// Остаток строки после имени начинает определение: `[WxH/N]`, `<svg` или `{`
+fn is_definition(rest: &str) -> bool {
+    rest.starts_with('[') || rest.starts_with("<svg") || rest.starts_with('{')
+}
+
+/// Многострочный блок, внутри которого `sprite` — обычный текст: строки,
+/// закрывающие блок, открытый строкой `line`
+fn block_ends(line: &str) -> Option<&'static [&'static str]> {
+    let first = line.split_whitespace().next()?.to_ascii_lowercase();
+    let alone = |keyword: &str| {
+        line.split_whitespace()
+            .filter(|word| !matches!(*word, "left" | "right" | "center"))
+            .eq([keyword])
+    };
+    match first.as_str() {
+        // Однострочные заметки содержат `:` или текст в кавычках
+        "note" | "hnote" | "rnote" if !line.contains(':') && !line.contains('"') => Some(&[
+            "end note",
+            "endnote",
+            "end hnote",
+            "endhnote",
+            "end rnote",
+            "endrnote",
+        ]),
+        "legend" => Some(&["endlegend", "end legend"]),
+        _ if alone("header") => Some(&["endheader", "end header"]),
+        _ if alone("footer") => Some(&["endfooter", "end footer"]),
+        _ if alone("title") => Some(&["endtitle", "end title"]),
+        _ => None,
+    }
+}
+
+/// Имя `$name` (или `name`) и остаток строки
+fn split_name(definition: &str) -> Option<(&str, &str)> {
+    let definition = definition.trim_start();
+    let name = definition.strip_prefix('$').unwrap_or(definition);
+    let end = name
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(name.len());
+    (end > 0).then(|| (&name[..end], name[end..].trim()))
+}
+
+/// Разбирает тело: первая часть — остаток строки `sprite` после имени
+fn parse_sprite(body: &[&str]) -> std::result::Result<Sprite, String> {
+    let header = body[0];
+    if header.starts_with("<svg") {
+        return Ok(Sprite::svg(body.join("\n")));
+    }
+
+    // Формат [WxH/N] или [WxH/Nz]; без него — 16 уровней и размер по данным
+    let (size, rest) = match header.strip_prefix('[') {
+        Some(inner) => {
+            let (format, rest) = inner.split_once(']').ok_or("нет закрывающей ]")?;
+            (Some(parse_format(format)?), rest.trim())
+        }
+        None => (None, header),
+    };
+    let mut rows: Vec<&str> = body[1..]
+        .iter()
+        .copied()
+        .filter(|r| !r.is_empty())
+        .collect();
+    let inline = rest.trim_end_matches('{').trim();
+    if !inline.is_empty() {
+        rows.insert(0, inline);
+    }
+
+    let (width, height, levels, compressed) = match size {
+        Some(size) => size,
+        None => {
+            let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+            let width = u32::try_from(width).unwrap_or(u32::MAX);
+            let height = u32::try_from(rows.len()).unwrap_or(u32::MAX);
+            (width, height, 16, false)
+        }
+    };
+    let count = Sprite::pixel_count(width, height).ok_or_else(|| {
+        format!(
+            "спрайт {}x{} больше {} пикселей",
+            width, height, MAX_SPRITE_PIXELS
+        )
+    })?;
+
+    let pixels = if compressed {
+        decompress(&rows.concat(), count)?
+    } else {
+        gray_pixels(&rows, width, height, levels, count)?
+    };
+    Sprite::gray(width, height, levels, pixels).ok_or_else(|| "некорректный размер спрайта".into())
+}
+
+/// `WxH/N` или `WxH/Nz` → (ширина, высота, уровни, сжатие)
+fn parse_format(format: &str) -> std::result::Result<(u32, u32, u8, bool), String> {
+    let invalid = || format!("некорректный формат спрайта [{}]", format);
+    let (size, levels) = format.split_once('/').unwrap_or((format, "16"));
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let (levels, compressed) = match levels.strip_suffix('z') {
+        Some(levels) => (levels, true),
+        None => (levels, false),
+    };
+    let levels: u8 = levels.trim().parse().map_err(|_| invalid())?;
+    if !matches!(levels, 4 | 8 | 16) {
+        return Err(format!(
+            "поддерживаются 4, 8 и 16 уровней серого: [{}]",
+            format
+        ));
+    }
+    let width = width.trim().parse().map_err(|_| invalid())?;
+    let height = height.trim().parse().map_err(|_| invalid())?;
+    Ok((width, height, levels, compressed))
+}
+
+/// Несжатые строки: символ кодирует 1 (16 уровней), 2 (8) или 3 (4) пикселя
+/// по вертикали
+fn gray_pixels(
+    rows: &[&str],
+    width: u32,
+    height: u32,
+    levels: u8,
+    count: usize,
+) -> std::result::Result<Vec<u8>, String> {
+    let per_char: u32 = match levels {
+        16 => 1,
+        8 => 2,
+        _ => 3,
+    };
+    let mut pixels = vec![0; count];
+    for (row, line) in rows.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            let value = if levels == 16 {
+                c.to_digit(16)
+            } else {
+                decode6bit(c).map(u32::from)
+            }
+            .ok_or_else(|| format!("недопустимый символ спрайта '{}'", c))?;
+
+            for k in 0..per_char {
+                let shift = levels.trailing_zeros() * (per_char - 1 - k);
+                let gray = (value >> shift) % u32::from(levels);
+                let y = row * per_char as usize + k as usize;
+                if x < width as usize && y < height as usize {
+                    pixels[y * width as usize + x] = gray as u8;
+                }
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+/// Сжатые пиксели: 64-символьная кодировка PlantUML поверх deflate, байт на
+/// пиксель; распаковывается не больше `count` байт
+fn decompress(data: &str, count: usize) -> std::result::Result<Vec<u8>, String> {
+    let chars: Vec<u8> = data
+        .chars()
+        .map(|c| decode6bit(c).ok_or_else(|| format!("недопустимый символ спрайта '{}'", c)))
+        .collect::<std::result::Result<_, _>>()?;
+
+    let mut bytes = Vec::with_capacity(chars.len() * 3 / 4);
+    for group in chars.chunks(4) {
+        let value = group
+            .iter()
+            .chain(std::iter::repeat(&0))
+            .take(4)
+            .fold(0u32, |acc, &c| (acc << 6) | u32::from(c));
+        bytes.extend_from_slice(&value.to_be_bytes()[1..]);
+    }
+
+    let mut pixels = Vec::new();
+    let limit = count as u64;
+    if DeflateDecoder::new(bytes.as_slice())
+        .take(limit)
+        .read_to_end(&mut pixels)
+        .is_ok()
+    {
+        return Ok(pixels);
+    }
+    pixels.clear();
+    ZlibDecoder::new(bytes.as_slice())
+        .take(limit)
+        .read_to_end(&mut pixels)
+        .map_err(|e| format!("не удалось распаковать спрайт: {}", e))?;
+    Ok(pixels)
+}
+
+/// Символ кодировки `0-9A-Za-z-_` → 6 бит
+fn decode6bit(c: char) -> Option<u8> {
+    match c {
+        '0'..='9' => Some(c as u8 - b'0'),
+        'A'..='Z' => Some(c as u8 - b'A' + 10),
+        'a'..='z' => Some(c as u8 - b'a' + 36),
+        '-' => Some(62),
+        '_' => Some(63),
+        _ => None,
+    }
+}
+
+fn error(line: usize, message: &str) -> ParseError {
+    ParseError::SyntaxError {
+        line,
+        message: format!("sprite: {}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use plantuml_ast::sprite::SpriteData;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_hex_sprite() {
+        let source = "@startuml\nsprite $dot [3x2/16] {\n0F0\nF8F\n}\nAlice -> Bob\n@enduml\n";
+        let (text, sprites) = extract_sprites(source).unwrap();
+        // Номер строки сообщения не изменился
+        assert_eq!(text.lines().nth(5), Some("Alice -> Bob"));
+        assert_eq!(text.lines().count(), source.lines().count());
+
+        let sprite = sprites.get("dot").unwrap();
+        assert_eq!((sprite.width, sprite.height), (3, 2));
+        assert_eq!(sprite.pixel(1, 0), Some(15));
+        assert_eq!(sprite.pixel(1, 1), Some(8));
+        assert_eq!(sprite.pixel(0, 0), Some(0));
+    }
+
+    #[test]
+    fn test_gray_levels() {
+        // 'Z' = 35: для 8 уровней — 4 и 3, для 4 уровней — 2, 0 и 3
+        let (_, sprites) =
+            extract_sprites("sprite $a [1x2/8] {\nZ\n}\nsprite $b [1x3/4] Z").unwrap();
+        assert_eq!(sprites.get("a").unwrap().pixel(0, 0), Some(4));
+        assert_eq!(sprites.get("a").unwrap().pixel(0, 1), Some(3));
+        let b = sprites.get("b").unwrap();
+        assert_eq!(
+            [b.pixel(0, 0), b.pixel(0, 1), b.pixel(0, 2)],
+            [Some(2), Some(0), Some(3)]
+        );
+
+        // Без формата размер определяется по данным
+        let (_, sprites) = extract_sprites("sprite foo {\nFF\nF\n}").unwrap();
+        let foo = sprites.get("$foo").unwrap();
+        assert_eq!((foo.width, foo.height), (2, 2));
+    }
+
+    #[test]
+    fn test_compressed_sprite() {
+        let pixels: Vec<u8> = (0..16).collect();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&pixels).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let alphabet = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+        let mut encoded = String::new();
+        for chunk in compressed.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let value = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+            for shift in [18, 12, 6, 0] {
+                encoded.push(alphabet[((value >> shift) & 63) as usize] as char);
+            }
+        }
+
+        let source = format!("sprite $z [4x4/16z] {{\n{}\n}}", encoded);
+        let (_, sprites) = extract_sprites(&source).unwrap();
+        let sprite = sprites.get("z").unwrap();
+        assert_eq!(sprite.pixel(3, 3), Some(15));
+        assert_eq!(sprite.pixel(1, 2), Some(9));
+    }
+
+    #[test]
+    fn test_svg_sprite() {
+        let source = "sprite $logo <svg viewBox=\"0 0 20 10\">\n<circle r=\"5\"/>\n</svg>\nrest";
+        let (text, sprites) = extract_sprites(source).unwrap();
+        assert_eq!(text, "\n\n\nrest");
+        let logo = sprites.get("logo").unwrap();
+        assert_eq!((logo.width, logo.height), (20, 10));
+        assert!(matches!(&logo.data, SpriteData::Svg(svg) if svg.contains("<circle")));
+    }
+
+    #[test]
+    fn test_sprite_word_in_text() {
+        let source = "@startuml\nnote over Bob\nsprite sheet loaded\nend note\n\
+                      sprite -> Bob : hi\nlegend\nsprite $x [1x1/16] F\nendlegend\n@enduml\n";
+        let (text, sprites) = extract_sprites(source).unwrap();
+        assert_eq!(text, source);
+        assert!(sprites.is_empty());
+
+        // После конца заметки определения снова распознаются
+        let (_, sprites) =
+            extract_sprites("note left: sprite\nsprite $a [1x1/16] F\n").unwrap();
+        assert!(sprites.get("a").is_some());
+    }
+
+    #[test]
+    fn test_sprite_size_limit() {
+        let err = extract_sprites("sprite $a [65536x65536/16] {\n}").unwrap_err();
+        assert!(matches!(err, ParseError::SyntaxError { line: 1, ref message }
+            if message.contains("65536x65536")));
+        assert!(extract_sprites("sprite $a [4294967295x4294967295/16z] AA").is_err());
+        assert!(extract_sprites("sprite $a [4096x4096/16] {\n}").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_sprites() {
+        let err = extract_sprites("@startuml\nsprite $a [4x4/3] {\n}\n").unwrap_err();
+        assert!(matches!(err, ParseError::SyntaxError { line: 2, .. }));
+        assert!(extract_sprites("sprite $a [2x1/16] {\nFG\n}").is_err());
+        assert!(extract_sprites("sprite $a [2x1/16] {\nFF\n").is_err());
+        assert!(extract_sprites("sprite $a [2x1/16z] !!").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Чтение zip-архивов для `!import`
+//!
+//! Поддерживаются записи без сжатия (stored) и со сжатием deflate — этого
+//! достаточно для архивов, собранных обычными инструментами. Двоичные
+//! (не UTF-8) записи пропускаются: из архива включаются только исходники.
+
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+use indexmap::IndexMap;
+
+use crate::{PreprocessError, Result};
+
+/// Сигнатура записи центрального каталога
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+/// Сигнатура локального заголовка записи
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+/// Сигнатура конца центрального каталога
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Текстовые файлы zip-архива
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+    entries: IndexMap<String, String>,
+}
+
+impl Archive {
+    /// Разбирает архив; `name` используется в сообщениях об ошибках
+    pub fn parse(name: &str, data: &[u8]) -> Result<Self> {
+        let error = |reason: &str| PreprocessError::FileReadError(format!("{}: {}", name, reason));
+
+        // Конец центрального каталога ищется с конца: после него может идти комментарий
+        let end = (0..data.len().saturating_sub(21))
+            .rev()
+            .find(|&i| read_u32(data, i) == Some(END_OF_DIRECTORY))
+            .ok_or_else(|| error("не zip-архив"))?;
+        let count = read_u16(data, end + 10).ok_or_else(|| error("повреждён каталог"))?;
+        let mut offset =
+            read_u32(data, end + 16).ok_or_else(|| error("повреждён каталог"))? as usize;
+
+        let mut entries = IndexMap::new();
+        for _ in 0..count {
+            if read_u32(data, offset) != Some(CENTRAL_HEADER) {
+                return Err(error("повреждён каталог"));
+            }
+            let field = |at: usize| read_u16(data, offset + at).map(usize::from);
+            let (method, name_len, extra_len, comment_len) =
+                match (field(10), field(28), field(30), field(32)) {
+                    (Some(m), Some(n), Some(e), Some(c)) => (m, n, e, c),
+                    _ => return Err(error("повреждён каталог")),
+                };
+            let compressed =
+                read_u32(data, offset + 20).ok_or_else(|| error("повреждён каталог"))?;
+            let local = read_u32(data, offset + 42).ok_or_else(|| error("повреждён каталог"))?;
+            let path = data
+                .get(offset + 46..offset + 46 + name_len)
+                .ok_or_else(|| error("повреждён каталог"))?;
+            let path = String::from_utf8_lossy(path).into_owned();
+            offset += 46 + name_len + extra_len + comment_len;
+
+            if path.ends_with('/') {
+                continue;
+            }
+            let raw = entry_data(data, local as usize, compressed as usize)
+                .ok_or_else(|| error(&format!("повреждена запись {}", path)))?;
+            let bytes = match method {
+                0 => raw.to_vec(),
+                8 => {
+                    let mut inflated = Vec::new();
+                    DeflateDecoder::new(raw)
+                        .read_to_end(&mut inflated)
+                        .map_err(|e| error(&format!("{}: {}", path, e)))?;
+                    inflated
+                }
+                other => {
+                    return Err(error(&format!(
+                        "{}: неподдерживаемый метод сжатия {}",
+                        path, other
+                    )))
+                }
+            };
+            if let Ok(text) = String::from_utf8(bytes) {
+                entries.insert(path, text);
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Содержимое файла архива (`./` в начале пути не учитывается)
+    pub fn get(&self, path: &str) -> Option<&str> {
+        let path = path.trim_start_matches("./");
+        self.entries.get(path).map(String::as_str)
+    }
+}
+
+/// Данные записи по смещению её локального заголовка
+fn entry_data(data: &[u8], local: usize, size: usize) -> Option<&[u8]> {
+    if read_u32(data, local)? != LOCAL_HEADER {
+        return None;
+    }
+    let start = local
+        + 30
+        + usize::from(read_u16(data, local + 26)?)
+        + usize::from(read_u16(data, local + 28)?);
+    data.get(start..start + size)
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Собирает zip-архив из файлов (deflate, без контрольных сумм) для тестов
+#[cfg(test)]
+pub(crate) fn build_zip(files: &[(&str, &str)]) -> Vec<u8> {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut data = Vec::new();
+    let mut directory = Vec::new();
+    for (path, content) in files {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let header = |signature: u32, central: bool| {
+            let mut h = signature.to_le_bytes().to_vec();
+            if central {
+                h.extend_from_slice(&20u16.to_le_bytes());
+            }
+            h.extend_from_slice(&[20, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            h.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            h.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            h.extend_from_slice(&(path.len() as u16).to_le_bytes());
+            h.extend_from_slice(&0u16.to_le_bytes());
+            h
+        };
+
+        let mut central = header(CENTRAL_HEADER, true);
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        central.extend_from_slice(path.as_bytes());
+        directory.extend(central);
+
+        data.extend(header(LOCAL_HEADER, false));
+        data.extend_from_slice(path.as_bytes());
+        data.extend(compressed);
+    }
+
+    let offset = data.len() as u32;
+    let size = directory.len() as u32;
+    data.extend(directory);
+    data.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    data.extend_from_slice(&size.to_le_bytes());
+    data.extend_from_slice(&offset.to_le_bytes());
+    data.extend_from_slice(&[0; 2]);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_archive() {
+        let data = build_zip(&[("lib/common.puml", "!$x = 1\n"), ("main.puml", "A -> B\n")]);
+        let archive = Archive::parse("lib.zip", &data).unwrap();
+
+        assert_eq!(archive.get("lib/common.puml"), Some("!$x = 1\n"));
+        assert_eq!(archive.get("./lib/common.puml"), Some("!$x = 1\n"));
+        assert_eq!(archive.get("main.puml"), Some("A -> B\n"));
+        assert!(archive.get("missing.puml").is_none());
+
+        let err = Archive::parse("bad.zip", b"not an archive at all").unwrap_err();
+        assert!(matches!(err, PreprocessError::FileReadError(_)));
+    }
+}
@@ -1,314 +1,468 @@
 //! Встроенные функции препроцессора PlantUML
 //!
+//! Вызовы `%name(...)` вычисляются движком выражений, поэтому аргументами
+//! могут быть переменные и вложенные вызовы: `%upper(%substr($x, 0, 3))`.
+//!
 //! Поддерживаемые функции:
-//! - Дата/время: `%date()`, `%time()`
-//! - Метаданные: `%version()`, `%filename()`, `%dirpath()`
-//! - Логические: `%true()`, `%false()`, `%not(expr)`
-//! - Строковые: `%strlen(s)`, `%substr(s, start, len)`, `%upper(s)`, `%lower(s)`,
-//!              `%strpos(s, needle)`, `%string(x)`, `%newline()`
-//! - Числовые: `%intval(s)`, `%floor(x)`, `%ceil(x)`, `%abs(x)`
-
-use regex::Regex;
-use std::sync::LazyLock;
-
-/// Регулярные выражения для парсинга функций с аргументами
-static RE_STRLEN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r#"%strlen\("([^"]*)"\)"#).unwrap());
-static RE_UPPER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"%upper\("([^"]*)"\)"#).unwrap());
-static RE_LOWER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"%lower\("([^"]*)"\)"#).unwrap());
-static RE_SUBSTR: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r#"%substr\("([^"]*)",\s*(\d+)(?:,\s*(\d+))?\)"#).unwrap());
-static RE_STRPOS: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r#"%strpos\("([^"]*)",\s*"([^"]*)"\)"#).unwrap());
-static RE_STRING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%string\(([^)]+)\)").unwrap());
-static RE_INTVAL: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r#"%intval\("?([^")]+)"?\)"#).unwrap());
-static RE_FLOOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%floor\(([^)]+)\)").unwrap());
-static RE_CEIL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%ceil\(([^)]+)\)").unwrap());
-static RE_ABS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%abs\(([^)]+)\)").unwrap());
-static RE_NOT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%not\(([^)]+)\)").unwrap());
-
-/// Обрабатывает builtin функции в строке
-pub fn process_builtins(line: &str) -> String {
-    let mut result = line.to_string();
-
-    // === Простые функции без аргументов ===
-
-    // %date()
-    if result.contains("%date()") {
-        let date = get_current_date();
-        result = result.replace("%date()", &date);
-    }
-
-    // %time()
-    if result.contains("%time()") {
-        let time = get_current_time();
-        result = result.replace("%time()", &time);
-    }
-
-    // %version()
-    if result.contains("%version()") {
-        result = result.replace("%version()", env!("CARGO_PKG_VERSION"));
-    }
-
-    // %true()
-    result = result.replace("%true()", "true");
-
-    // %false()
-    result = result.replace("%false()", "false");
-
-    // %newline()
-    result = result.replace("%newline()", "\n");
-
-    // %tab()
-    result = result.replace("%tab()", "\t");
-
-    // === Строковые функции ===
-
-    // %strlen("string")
-    result = process_strlen(&result);
-
-    // %upper("string")
-    result = process_upper(&result);
-
-    // %lower("string")
-    result = process_lower(&result);
-
-    // %substr("string", start, len)
-    result = process_substr(&result);
-
-    // %strpos("string", "needle")
-    result = process_strpos(&result);
-
-    // %string(value)
-    result = process_string(&result);
-
-    // === Числовые функции ===
-
-    // %intval("42")
-    result = process_intval(&result);
-
-    // %floor(3.7)
-    result = process_floor(&result);
-
-    // %ceil(3.2)
-    result = process_ceil(&result);
-
-    // %abs(-5)
-    result = process_abs(&result);
-
-    // === Логические функции ===
-
-    // %not(expr)
-    result = process_not(&result);
-
-    result
+//! - Дата/время: `%date([fmt[, ts]])`, `%time()`, `%random([max] | [min, max])`
+//! - Окружение: `%version()`, `%filename()`, `%dirpath()`, `%getenv(name)` (только
+//!   переменные из [`Preprocessor::allow_env`](crate::Preprocessor::allow_env)), `%feature(name)`
+//! - Логические: `%true()`, `%false()`, `%not(x)`, `%boolval(x)`
+//! - Строковые: `%strlen(s)`, `%substr(s, start[, len])`, `%upper(s)`, `%lower(s)`,
+//!   `%strpos(s, needle)`, `%string(x)`, `%splitstr(s, sep)`, `%chr(n)`, `%ord(s)`,
+//!   `%newline()`, `%tab()`
+//! - Числовые: `%intval(s)`, `%floor(x)`, `%ceil(x)`, `%abs(x)`, `%dec2hex(n)`, `%hex2dec(s)`
+//! - JSON: `%str2json(s)`, `%load_json(path)`, `%size(x)`, `%get_json_keys(obj)`,
+//!   `%get_json_type(x)`, `%json_key_exists(obj, key)`, `%json_set(json, key, value)`,
+//!   `%json_add(json, [key,] value)`, `%json_remove(json, key)`
+//! - Переменные и функции: `%get_variable_value(name)`, `%set_variable_value(name, value)`,
+//!   `%variable_exists(name)`, `%function_exists(name)`, `%invoke_procedure(name, ...)`,
+//!   `%call_user_func(name, ...)`
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::expression::Value;
+use crate::{PreprocessError, Result};
+
+/// Имена встроенных функций (без `%`)
+pub const BUILTINS: &[&str] = &[
+    "abs",
+    "boolval",
+    "call_user_func",
+    "ceil",
+    "chr",
+    "date",
+    "dec2hex",
+    "dirpath",
+    "false",
+    "feature",
+    "filename",
+    "floor",
+    "function_exists",
+    "get_json_keys",
+    "get_json_type",
+    "get_variable_value",
+    "getenv",
+    "hex2dec",
+    "intval",
+    "invoke_procedure",
+    "json_add",
+    "json_key_exists",
+    "json_remove",
+    "json_set",
+    "load_json",
+    "lower",
+    "newline",
+    "not",
+    "ord",
+    "random",
+    "set_variable_value",
+    "size",
+    "splitstr",
+    "str2json",
+    "string",
+    "strlen",
+    "strpos",
+    "substr",
+    "tab",
+    "time",
+    "true",
+    "upper",
+    "variable_exists",
+    "version",
+];
+
+/// Возможности, о которых сообщает `%feature(name)`
+const FEATURES: &[&str] = &["style", "theme"];
+
+/// Состояние препроцессора, доступное встроенным функциям
+pub trait BuiltinContext {
+    /// Значение переменной
+    fn get_variable(&self, name: &str) -> Option<Value>;
+
+    /// Устанавливает глобальную переменную
+    fn set_variable(&mut self, name: &str, value: Value);
+
+    /// Определена ли пользовательская функция или процедура
+    fn callable_exists(&self, name: &str) -> bool;
+
+    /// Вызывает пользовательскую функцию (значение `!return`) или процедуру (вывод)
+    fn call_user(&mut self, name: &str, args: Vec<Value>) -> Result<Value>;
+
+    /// Читает файл через `FileResolver`
+    fn read_file(&self, path: &str) -> Result<String>;
+
+    /// Путь обрабатываемого файла
+    fn current_file(&self) -> Option<&str>;
+
+    /// Переменная окружения, если она разрешена препроцессору
+    fn env_var(&self, name: &str) -> Option<String>;
 }
 
-// === Строковые функции ===
-
-/// %strlen("string") -> длина строки
-fn process_strlen(input: &str) -> String {
-    RE_STRLEN
-        .replace_all(input, |caps: &regex::Captures| {
-            let s = &caps[1];
-            s.chars().count().to_string()
-        })
-        .to_string()
-}
-
-/// %upper("string") -> СТРОКА В ВЕРХНЕМ РЕГИСТРЕ
-fn process_upper(input: &str) -> String {
-    RE_UPPER
-        .replace_all(input, |caps: &regex::Captures| caps[1].to_uppercase())
-        .to_string()
+/// Проверяет, является ли `%name` встроенной функцией
+pub fn is_builtin(name: &str) -> bool {
+    name.strip_prefix('%')
+        .is_some_and(|name| BUILTINS.contains(&name))
 }
 
-/// %lower("STRING") -> строка в нижнем регистре
-fn process_lower(input: &str) -> String {
-    RE_LOWER
-        .replace_all(input, |caps: &regex::Captures| caps[1].to_lowercase())
-        .to_string()
-}
-
-/// %substr("string", start, len) -> подстрока
-fn process_substr(input: &str) -> String {
-    RE_SUBSTR
-        .replace_all(input, |caps: &regex::Captures| {
-            let s = &caps[1];
-            let start: usize = caps[2].parse().unwrap_or(0);
-            let len: Option<usize> = caps.get(3).and_then(|m| m.as_str().parse().ok());
-
-            let chars: Vec<char> = s.chars().collect();
-            if start >= chars.len() {
-                return String::new();
+/// Вызывает встроенную функцию `%name` с вычисленными аргументами
+pub fn call_builtin(name: &str, args: &[Value], ctx: &mut dyn BuiltinContext) -> Result<Value> {
+    let args = Args { name, args };
+    let value = match name.trim_start_matches('%') {
+        // === Дата/время ===
+        "date" => {
+            let timestamp = match args.get(1) {
+                Some(ts) => args.int(ts)?,
+                None => now(),
+            };
+            match args.get(0) {
+                Some(format) => Value::Str(format_date(&format.to_text(), timestamp)),
+                None => Value::Str(format_date("yyyy-MM-dd", timestamp)),
             }
-
-            match len {
-                Some(l) => chars.iter().skip(start).take(l).collect(),
-                None => chars.iter().skip(start).collect(),
+        }
+        "time" => Value::Str(format_date("HH:mm:ss", now())),
+        "random" => {
+            let (min, max) = match args.args {
+                [] => (0, 2),
+                [max] => (0, args.int(max)?),
+                [min, max, ..] => (args.int(min)?, args.int(max)?),
+            };
+            if max <= min {
+                return Err(args.error("пустой диапазон"));
             }
-        })
-        .to_string()
-}
-
-/// %strpos("haystack", "needle") -> позиция или -1
-fn process_strpos(input: &str) -> String {
-    RE_STRPOS
-        .replace_all(input, |caps: &regex::Captures| {
-            let haystack = &caps[1];
-            let needle = &caps[2];
-
-            match haystack.find(needle) {
-                Some(pos) => pos.to_string(),
-                None => "-1".to_string(),
+            // Ширина диапазона до 2^64 - 1 помещается только в u64
+            let offset = random() % max.abs_diff(min);
+            Value::Int((i128::from(min) + i128::from(offset)) as i64)
+        }
+
+        // === Окружение ===
+        "version" => Value::Str(env!("CARGO_PKG_VERSION").to_string()),
+        "filename" => Value::Str(
+            ctx.current_file()
+                .map(|path| path.rsplit(['/', '\\']).next().unwrap_or(path))
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        "dirpath" => Value::Str(
+            ctx.current_file()
+                .and_then(|path| path.rsplit_once(['/', '\\']))
+                .map(|(dir, _)| dir)
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        "getenv" => Value::Str(ctx.env_var(&args.text(0)?).unwrap_or_default()),
+        "feature" => Value::Bool(FEATURES.contains(&args.text(0)?.to_lowercase().as_str())),
+
+        // === Логические ===
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "not" => Value::Bool(!args.value(0)?.is_truthy()),
+        "boolval" => Value::Bool(args.value(0)?.is_truthy()),
+
+        // === Строковые ===
+        "strlen" => Value::Int(args.text(0)?.chars().count() as i64),
+        "upper" => Value::Str(args.text(0)?.to_uppercase()),
+        "lower" => Value::Str(args.text(0)?.to_lowercase()),
+        "substr" => {
+            let text = args.text(0)?;
+            let start = args.int(args.value(1)?)?.max(0) as usize;
+            let chars = text.chars().skip(start);
+            Value::Str(match args.get(2) {
+                Some(len) => chars.take(args.int(len)?.max(0) as usize).collect(),
+                None => chars.collect(),
+            })
+        }
+        "strpos" => {
+            let (text, needle) = (args.text(0)?, args.text(1)?);
+            Value::Int(
+                text.find(&needle)
+                    .map_or(-1, |pos| text[..pos].chars().count() as i64),
+            )
+        }
+        "string" => Value::Str(args.text(0)?),
+        "splitstr" => {
+            let (text, separator) = (args.text(0)?, args.text(1)?);
+            let parts: Vec<serde_json::Value> = if separator.is_empty() {
+                text.chars().map(|c| c.to_string().into()).collect()
+            } else {
+                text.split(separator.as_str()).map(|part| part.into()).collect()
+            };
+            Value::Json(parts.into())
+        }
+        "chr" => {
+            let code = args.int(args.value(0)?)?;
+            let c = u32::try_from(code)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| args.error("некорректный код символа"))?;
+            Value::Str(c.to_string())
+        }
+        "ord" => Value::Int(args.text(0)?.chars().next().map_or(0, |c| c as i64)),
+        "newline" => Value::Str("\n".to_string()),
+        "tab" => Value::Str("\t".to_string()),
+
+        // === Числовые ===
+        "intval" => {
+            let value = args.value(0)?;
+            let int = value
+                .as_int()
+                .or_else(|| value.as_float().map(|f| f.trunc() as i64));
+            Value::Int(int.unwrap_or(0))
+        }
+        "floor" => Value::Int(args.float(0)?.floor() as i64),
+        "ceil" => Value::Int(args.float(0)?.ceil() as i64),
+        "abs" => match args.value(0)? {
+            Value::Float(f) => Value::Float(f.abs()),
+            value => match value.as_int() {
+                Some(n) => Value::Int(
+                    n.checked_abs()
+                        .ok_or_else(|| args.error("переполнение целого"))?,
+                ),
+                None => Value::Float(args.float(0)?.abs()),
+            },
+        },
+        "dec2hex" => Value::Str(format!("{:x}", args.int(args.value(0)?)?)),
+        "hex2dec" => {
+            let text = args.text(0)?;
+            let digits = text.trim().trim_start_matches("0x").trim_start_matches('#');
+            Value::Int(i64::from_str_radix(digits, 16).unwrap_or(0))
+        }
+
+        // === JSON ===
+        "str2json" => {
+            let json = serde_json::from_str(&args.text(0)?)
+                .map_err(|e| args.error(&format!("некорректный JSON: {}", e)))?;
+            Value::from_json(json)
+        }
+        "load_json" => {
+            let path = args.text(0)?;
+            let content = ctx.read_file(&path)?;
+            let json = serde_json::from_str(&content)
+                .map_err(|e| args.error(&format!("{}: некорректный JSON: {}", path, e)))?;
+            Value::from_json(json)
+        }
+        "size" => match args.value(0)?.to_json() {
+            serde_json::Value::Array(items) => Value::Int(items.len() as i64),
+            serde_json::Value::Object(fields) => Value::Int(fields.len() as i64),
+            _ => Value::Int(args.text(0)?.chars().count() as i64),
+        },
+        "get_json_keys" => match args.json(0)? {
+            serde_json::Value::Object(fields) => {
+                Value::Json(fields.keys().cloned().map(serde_json::Value::String).collect())
             }
-        })
-        .to_string()
+            _ => Value::Json(serde_json::Value::Array(Vec::new())),
+        },
+        "get_json_type" => Value::Str(
+            match args.json(0)? {
+                serde_json::Value::Null => "null",
+                serde_json::Value::Bool(_) => "boolean",
+                serde_json::Value::Number(_) => "number",
+                serde_json::Value::String(_) => "string",
+                serde_json::Value::Array(_) => "array",
+                serde_json::Value::Object(_) => "object",
+            }
+            .to_string(),
+        ),
+        "json_key_exists" => {
+            let key = args.text(1)?;
+            Value::Bool(matches!(args.json(0)?, serde_json::Value::Object(fields) if fields.contains_key(&key)))
+        }
+        "json_set" => {
+            let mut json = args.json(0)?;
+            let value = args.value(2)?.to_json();
+            match &mut json {
+                serde_json::Value::Object(fields) => {
+                    fields.insert(args.text(1)?, value);
+                }
+                serde_json::Value::Array(items) => {
+                    let index = args.int(args.value(1)?)?;
+                    let slot = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| items.get_mut(index))
+                        .ok_or_else(|| args.error("индекс вне массива"))?;
+                    *slot = value;
+                }
+                _ => return Err(args.error("ожидается объект или массив")),
+            }
+            Value::from_json(json)
+        }
+        "json_add" => {
+            let mut json = args.json(0)?;
+            match &mut json {
+                serde_json::Value::Array(items) => items.push(args.value(1)?.to_json()),
+                serde_json::Value::Object(fields) => {
+                    fields.insert(args.text(1)?, args.value(2)?.to_json());
+                }
+                _ => return Err(args.error("ожидается объект или массив")),
+            }
+            Value::from_json(json)
+        }
+        "json_remove" => {
+            let mut json = args.json(0)?;
+            match &mut json {
+                serde_json::Value::Object(fields) => {
+                    fields.remove(&args.text(1)?);
+                }
+                serde_json::Value::Array(items) => {
+                    let index = args.int(args.value(1)?)?;
+                    if let Some(index) = usize::try_from(index).ok().filter(|&i| i < items.len()) {
+                        items.remove(index);
+                    }
+                }
+                _ => return Err(args.error("ожидается объект или массив")),
+            }
+            Value::from_json(json)
+        }
+
+        // === Переменные и функции ===
+        "get_variable_value" => ctx.get_variable(&args.text(0)?).unwrap_or_default(),
+        "set_variable_value" => {
+            ctx.set_variable(&args.text(0)?, args.value(1)?.clone());
+            Value::default()
+        }
+        "variable_exists" => Value::Bool(ctx.get_variable(&args.text(0)?).is_some()),
+        "function_exists" => {
+            let name = args.text(0)?;
+            Value::Bool(ctx.callable_exists(&name) || is_builtin(&name))
+        }
+        "invoke_procedure" | "call_user_func" => {
+            let callee = args.text(0)?;
+            ctx.call_user(&callee, args.args[1..].to_vec())?
+        }
+
+        _ => {
+            return Err(PreprocessError::ExpressionError(format!(
+                "неизвестная функция: {}",
+                name
+            )))
+        }
+    };
+    Ok(value)
 }
 
-/// %string(value) -> преобразование в строку
-fn process_string(input: &str) -> String {
-    RE_STRING
-        .replace_all(input, |caps: &regex::Captures| {
-            let value = caps[1].trim();
-            // Убираем кавычки если есть
-            let value = value.trim_matches('"');
-            format!("\"{}\"", value)
-        })
-        .to_string()
+/// Аргументы вызова с проверкой количества и типов
+struct Args<'a> {
+    name: &'a str,
+    args: &'a [Value],
 }
 
-// === Числовые функции ===
+impl Args<'_> {
+    fn get(&self, index: usize) -> Option<&Value> {
+        self.args.get(index)
+    }
 
-/// %intval("42") -> 42
-fn process_intval(input: &str) -> String {
-    RE_INTVAL
-        .replace_all(input, |caps: &regex::Captures| {
-            let value = caps[1].trim().trim_matches('"');
-            value.parse::<i64>().unwrap_or(0).to_string()
-        })
-        .to_string()
-}
+    fn value(&self, index: usize) -> Result<&Value> {
+        self.get(index)
+            .ok_or_else(|| self.error(&format!("нет аргумента {}", index + 1)))
+    }
 
-/// %floor(3.7) -> 3
-fn process_floor(input: &str) -> String {
-    RE_FLOOR
-        .replace_all(input, |caps: &regex::Captures| {
-            let value = caps[1].trim();
-            value
-                .parse::<f64>()
-                .map(|v| v.floor() as i64)
-                .unwrap_or(0)
-                .to_string()
-        })
-        .to_string()
-}
+    fn text(&self, index: usize) -> Result<String> {
+        self.value(index).map(Value::to_text)
+    }
 
-/// %ceil(3.2) -> 4
-fn process_ceil(input: &str) -> String {
-    RE_CEIL
-        .replace_all(input, |caps: &regex::Captures| {
-            let value = caps[1].trim();
-            value
-                .parse::<f64>()
-                .map(|v| v.ceil() as i64)
-                .unwrap_or(0)
-                .to_string()
-        })
-        .to_string()
-}
+    fn int(&self, value: &Value) -> Result<i64> {
+        value
+            .as_int()
+            .ok_or_else(|| self.error(&format!("ожидается целое число, получено '{}'", value)))
+    }
 
-/// %abs(-5) -> 5
-fn process_abs(input: &str) -> String {
-    RE_ABS
-        .replace_all(input, |caps: &regex::Captures| {
-            let value = caps[1].trim();
-            // Пробуем как целое, потом как дробное
-            if let Ok(v) = value.parse::<i64>() {
-                v.abs().to_string()
-            } else if let Ok(v) = value.parse::<f64>() {
-                v.abs().to_string()
-            } else {
-                "0".to_string()
-            }
-        })
-        .to_string()
-}
+    fn float(&self, index: usize) -> Result<f64> {
+        let value = self.value(index)?;
+        value
+            .as_float()
+            .ok_or_else(|| self.error(&format!("ожидается число, получено '{}'", value)))
+    }
 
-// === Логические функции ===
-
-/// %not(expr) -> инвертирование логического значения
-fn process_not(input: &str) -> String {
-    RE_NOT
-        .replace_all(input, |caps: &regex::Captures| {
-            let value = caps[1].trim().to_lowercase();
-            match value.as_str() {
-                "true" | "1" => "false".to_string(),
-                "false" | "0" | "" => "true".to_string(),
-                _ => "false".to_string(), // непустые значения считаются true
+    /// JSON аргумент; строка разбирается как JSON, если это возможно
+    fn json(&self, index: usize) -> Result<serde_json::Value> {
+        Ok(match self.value(index)? {
+            Value::Str(text) => {
+                serde_json::from_str(text).unwrap_or_else(|_| serde_json::Value::String(text.clone()))
             }
+            value => value.to_json(),
         })
-        .to_string()
+    }
+
+    fn error(&self, message: &str) -> PreprocessError {
+        PreprocessError::ExpressionError(format!("{}: {}", self.name, message))
+    }
 }
 
 // === Вспомогательные функции ===
 
-/// Возвращает текущую дату в формате YYYY-MM-DD
-fn get_current_date() -> String {
+/// Текущее время в секундах с эпохи Unix
+fn now() -> i64 {
     // Для WASM-совместимости используем cfg
     #[cfg(target_arch = "wasm32")]
     {
-        // В WASM возвращаем placeholder (можно заменить на js_sys::Date)
-        "2024-01-01".to_string()
+        // В WASM возвращаем placeholder: 2024-01-01 12:00:00 UTC
+        1_704_110_400
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     {
         use std::time::SystemTime;
 
-        // Получаем время с эпохи Unix
-        let now = SystemTime::now()
+        SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default();
-
-        // Простой расчёт даты (без учёта часовых поясов)
-        let days = now.as_secs() / 86400;
-        let (year, month, day) = days_to_ymd(days);
-
-        format!("{:04}-{:02}-{:02}", year, month, day)
+            .map_or(0, |d| d.as_secs() as i64)
     }
 }
 
-/// Возвращает текущее время в формате HH:MM:SS
-fn get_current_time() -> String {
-    #[cfg(target_arch = "wasm32")]
-    {
-        "12:00:00".to_string()
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        use std::time::SystemTime;
-
-        let now = SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default();
-
-        let secs = now.as_secs() % 86400;
-        let hours = secs / 3600;
-        let minutes = (secs % 3600) / 60;
-        let seconds = secs % 60;
-
-        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+/// Форматирует время по шаблону Java `SimpleDateFormat` (без учёта часовых поясов)
+///
+/// Поддерживаются `yyyy`, `yy`, `MM`, `dd`, `HH`, `mm`, `ss` и текст в `'...'`.
+fn format_date(format: &str, timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86400);
+    let secs = timestamp.rem_euclid(86400);
+    let (year, month, day) = days_to_ymd(days);
+
+    let mut result = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // Текст в кавычках; '' — сама кавычка
+            if chars.peek() == Some(&'\'') {
+                chars.next();
+                result.push('\'');
+                continue;
+            }
+            for c in chars.by_ref() {
+                if c == '\'' {
+                    break;
+                }
+                result.push(c);
+            }
+            continue;
+        }
+
+        let mut count = 1;
+        while chars.peek() == Some(&c) {
+            chars.next();
+            count += 1;
+        }
+        let value = match c {
+            'y' if count == 2 => year.rem_euclid(100),
+            'y' => year,
+            'M' => i64::from(month),
+            'd' => i64::from(day),
+            'H' => secs / 3600,
+            'm' => secs % 3600 / 60,
+            's' => secs % 60,
+            _ => {
+                result.extend(std::iter::repeat(c).take(count));
+                continue;
+            }
+        };
+        result.push_str(&format!("{:0width$}", value, width = count));
     }
+    result
 }
 
 /// Преобразует количество дней с эпохи Unix в (year, month, day)
-#[cfg(not(target_arch = "wasm32"))]
-fn days_to_ymd(days: u64) -> (u32, u32, u32) {
+fn days_to_ymd(days: i64) -> (i64, u32, u32) {
     // Алгоритм из Howard Hinnant's date algorithms
-    let z = days as i64 + 719468;
+    let z = days + 719468;
     let era = if z >= 0 {
         z / 146097
     } else {
@@ -323,12 +477,33 @@ fn days_to_ymd(days: u64) -> (u32, u32, u32) {
     let m = if mp < 10 { mp + 3 } else { mp - 9 };
     let y = if m <= 2 { y + 1 } else { y };
 
-    (y as u32, m, d)
+    (y, m, d)
+}
+
+/// Псевдослучайное число (xorshift64*); засевается временем при первом вызове
+fn random() -> u64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+
+    let mut x = STATE.load(Ordering::Relaxed);
+    if x == 0 {
+        x = (now() as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+    }
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    STATE.store(x, Ordering::Relaxed);
+    x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    /// Обрабатывает одну строку препроцессором
+    fn process_builtins(line: &str) -> String {
+        crate::preprocess(line)
+            .unwrap()
+            .trim_end_matches('\n')
+            .to_string()
+    }
 
     #[test]
     fn test_version() {
@@ -424,6 +599,13 @@ mod tests {
 
         let result = process_builtins("val = %abs(-2.5)");
         assert_eq!(result, "val = 2.5");
+
+        let result = process_builtins("val = %abs(-9223372036854775807)");
+        assert_eq!(result, "val = 9223372036854775807");
+        assert!(matches!(
+            crate::preprocess("val = %abs(-9223372036854775808)"),
+            Err(crate::PreprocessError::ExpressionError(m)) if m.contains("переполнение")
+        ));
     }
 
     #[test]
@@ -459,6 +641,10 @@ mod tests {
         assert!(date_part.len() == 10);
         assert!(date_part.chars().nth(4) == Some('-'));
         assert!(date_part.chars().nth(7) == Some('-'));
+
+        // 2021-03-04 05:06:07 UTC
+        let result = process_builtins(r#"%date("dd.MM.yy 'at' HH:mm:ss", 1614834367)"#);
+        assert_eq!(result, "04.03.21 at 05:06:07");
     }
 
     #[test]
@@ -476,4 +662,87 @@ mod tests {
         let result = process_builtins(r#"result = %upper("test") + %strlen("hello")"#);
         assert_eq!(result, "result = TEST + 5");
     }
+
+    #[test]
+    fn test_nested_calls_with_variables() {
+        let source = "!$name = \"alice\"\n%upper(%substr($name, 0, 3)) %strlen(%string($name))";
+        assert_eq!(crate::preprocess(source).unwrap(), "ALI 5\n");
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(process_builtins("%chr(65)%ord(\"a\")"), "A97");
+        assert_eq!(process_builtins("%dec2hex(255) %hex2dec(\"ff\")"), "ff 255");
+        assert_eq!(process_builtins("%boolval(\"false\") %boolval(2)"), "false true");
+        let n: i64 = process_builtins("%random(5, 8)").parse().unwrap();
+        assert!((5..8).contains(&n));
+        // Крайние границы не переполняют ширину диапазона
+        let n: i64 = process_builtins("%random(-9223372036854775808, 9223372036854775807)")
+            .parse()
+            .unwrap();
+        assert!(n < i64::MAX);
+        let n: i64 = process_builtins("%random(9223372036854775806, 9223372036854775807)")
+            .parse()
+            .unwrap();
+        assert_eq!(n, i64::MAX - 1);
+        assert_eq!(
+            process_builtins("%random(-9223372036854775808, -9223372036854775807)"),
+            "-9223372036854775808"
+        );
+        assert!(crate::preprocess("%random(9223372036854775807, -9223372036854775808)").is_err());
+        assert!(crate::preprocess("%chr(-1)").is_err());
+    }
+
+    #[test]
+    fn test_json_builtins() {
+        assert_eq!(process_builtins(r#"%splitstr("a,b,c", ",")"#), r#"["a","b","c"]"#);
+        assert_eq!(process_builtins(r#"%size(%splitstr("a,b,c", ","))"#), "3");
+
+        let source = r#"!$obj = %str2json('{"name": "web", "port": 80}')
+%get_json_keys($obj) %get_json_type($obj) %json_key_exists($obj, "port")
+%json_set($obj, "port", 443)
+%json_remove(%json_add(["x"], "y"), 0)"#;
+        let result = crate::preprocess(source).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines[0], r#"["name","port"] object true"#);
+        assert_eq!(lines[1], r#"{"name":"web","port":443}"#);
+        assert_eq!(lines[2], r#"["y"]"#);
+        assert!(crate::preprocess(r#"%str2json("{bad")"#).is_err());
+    }
+
+    #[test]
+    fn test_variable_and_function_builtins() {
+        let source = r#"!procedure $hello($who)
+Hello $who
+!endprocedure
+!function $twice($x)
+!return $x * 2
+!endfunction
+!$dummy = %set_variable_value("$v", 42)
+%get_variable_value("$v") %variable_exists("$v") %variable_exists("$none")
+%function_exists("$twice") %function_exists("%strlen") %function_exists("$none")
+%call_user_func("$twice", 21)
+%invoke_procedure("$hello", "Bob")"#;
+        let result = crate::preprocess(source).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines[0], "42 true false");
+        assert_eq!(lines[1], "true true false");
+        assert_eq!(lines[2], "42");
+        assert_eq!(lines[3], "Hello Bob");
+    }
+
+    #[test]
+    fn test_environment_builtins() {
+        assert_eq!(process_builtins("%getenv(\"PLANTUML_RS_UNSET_VARIABLE\")"), "");
+        // Без разрешения переменные окружения недоступны
+        assert_eq!(process_builtins("[%getenv(\"PATH\")]"), "[]");
+        let allowed = crate::Preprocessor::new().allow_env(["PATH"]);
+        assert_eq!(
+            allowed.process("%getenv(\"PATH\")").unwrap().trim_end(),
+            std::env::var("PATH").unwrap()
+        );
+        assert_eq!(process_builtins("%feature(\"theme\") %feature(\"x\")"), "true false");
+        // Без файла путь пустой
+        assert_eq!(process_builtins("[%filename()][%dirpath()]"), "[][]");
+    }
 }
@@ -0,0 +1,102 @@
+//! Цепочка FileResolver'ов
+//!
+//! Файл читается первым resolver'ом, у которого он есть: например, сначала
+//! файлы в памяти, затем диск, затем URL.
+
+use crate::{FileResolver, PreprocessError, Result};
+
+/// FileResolver, опрашивающий вложенные resolver'ы по порядку
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_preprocessor::{ChainResolver, FileResolver, MemoryFileResolver};
+///
+/// let resolver = ChainResolver::new()
+///     .with(MemoryFileResolver::new().with_file("a.puml", "A"))
+///     .with(MemoryFileResolver::new().with_file("b.puml", "B"));
+/// assert_eq!(resolver.read_file("b.puml").unwrap(), "B");
+/// ```
+#[derive(Default)]
+pub struct ChainResolver {
+    resolvers: Vec<Box<dyn FileResolver>>,
+}
+
+impl ChainResolver {
+    /// Создаёт пустую цепочку
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет resolver в конец цепочки (builder)
+    pub fn with(mut self, resolver: impl FileResolver + 'static) -> Self {
+        self.push(resolver);
+        self
+    }
+
+    /// Добавляет resolver в конец цепочки
+    pub fn push(&mut self, resolver: impl FileResolver + 'static) {
+        self.resolvers.push(Box::new(resolver));
+    }
+
+    /// Число resolver'ов в цепочке
+    pub fn len(&self) -> usize {
+        self.resolvers.len()
+    }
+
+    /// Пуста ли цепочка
+    pub fn is_empty(&self) -> bool {
+        self.resolvers.is_empty()
+    }
+
+    /// Первый resolver, у которого есть файл
+    fn find(&self, path: &str) -> Result<&dyn FileResolver> {
+        self.resolvers
+            .iter()
+            .find(|resolver| resolver.file_exists(path))
+            .map(|resolver| resolver.as_ref())
+            .ok_or_else(|| PreprocessError::FileNotFound(path.to_string()))
+    }
+}
+
+impl FileResolver for ChainResolver {
+    fn read_file(&self, path: &str) -> Result<String> {
+        self.find(path)?.read_file(path)
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        self.find(path).is_ok()
+    }
+
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        self.find(path)?.read_bytes(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryFileResolver, NoopFileResolver};
+
+    #[test]
+    fn test_chain_order() {
+        let resolver = ChainResolver::new()
+            .with(NoopFileResolver)
+            .with(MemoryFileResolver::new().with_file("common.puml", "first"))
+            .with(
+                MemoryFileResolver::new()
+                    .with_file("common.puml", "second")
+                    .with_file("extra.puml", "extra"),
+            );
+
+        assert_eq!(resolver.len(), 3);
+        assert_eq!(resolver.read_file("common.puml").unwrap(), "first");
+        assert_eq!(resolver.read_file("extra.puml").unwrap(), "extra");
+        assert!(!resolver.file_exists("missing.puml"));
+        assert!(matches!(
+            resolver.read_file("missing.puml"),
+            Err(PreprocessError::FileNotFound(_))
+        ));
+        assert!(ChainResolver::new().is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! Диагностические сообщения препроцессора (`!log`, `!dump_memory`, предупреждения)
+
+use std::fmt;
+
+/// Источник диагностического сообщения
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Сообщение `!log`
+    Log,
+    /// Снимок переменных и функций `!dump_memory`
+    MemoryDump,
+    /// Предупреждение о пропущенной строке (например, `!endfunction` без `!function`)
+    Warning,
+}
+
+/// Диагностическое сообщение, собранное во время препроцессинга
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Источник сообщения
+    pub kind: DiagnosticKind,
+    /// Текст сообщения (после подстановки переменных)
+    pub message: String,
+    /// Файл, в котором встретилась директива (`None` — основной исходник)
+    pub file: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+        f.write_str(&self.message)
+    }
+}
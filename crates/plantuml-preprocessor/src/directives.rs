@@ -28,38 +28,77 @@ pub fn handle_undef(name: &str, ctx: &mut PreprocessContext) {
     }
 }
 
+/// Обрабатывает `!pragma key [value]`
+pub fn handle_pragma(rest: &str, ctx: &mut PreprocessContext) {
+    if !ctx.should_output() {
+        return;
+    }
+
+    let rest = rest.trim();
+    let (key, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    ctx.pragmas
+        .insert(key.to_lowercase(), value.trim().to_string());
+}
+
 /// Обрабатывает !ifdef / !ifndef
 pub fn handle_ifdef(name: &str, ctx: &mut PreprocessContext, is_ifdef: bool) {
     let defined = ctx.is_defined(name);
     let condition = if is_ifdef { defined } else { !defined };
+    handle_if(condition, ctx);
+}
 
+/// Открывает условный блок (`!if`, `!ifdef`, `!ifndef`)
+pub fn handle_if(condition: bool, ctx: &mut PreprocessContext) {
     // Если мы уже внутри ложного условия, вложенное условие тоже ложно
     let effective = ctx.should_output() && condition;
 
     ctx.condition_stack.push(effective);
+    ctx.branch_taken.push(effective);
     ctx.condition_depth += 1;
 }
 
-/// Обрабатывает !else
-pub fn handle_else(ctx: &mut PreprocessContext) -> Result<()> {
-    if ctx.condition_stack.is_empty() {
-        return Err(PreprocessError::UnbalancedCondition);
-    }
+/// Нужно ли вычислять условие `!elseif`: родительский блок активен и
+/// ни одна ветвь ещё не выбрана
+pub fn elseif_pending(ctx: &PreprocessContext) -> Result<bool> {
+    let taken = ctx
+        .branch_taken
+        .last()
+        .ok_or(PreprocessError::UnbalancedCondition)?;
+    Ok(parent_active(ctx) && !taken)
+}
 
-    // Проверяем родительское условие до мутации
-    let len = ctx.condition_stack.len();
-    let parent_ok = len <= 1 || ctx.condition_stack[..len - 1].iter().all(|&b| b);
+/// Обрабатывает !elseif с вычисленным условием (ложным, если ветвь не ожидается)
+pub fn handle_elseif(condition: bool, ctx: &mut PreprocessContext) -> Result<()> {
+    let active = elseif_pending(ctx)? && condition;
+    if let (Some(last), Some(taken)) = (ctx.condition_stack.last_mut(), ctx.branch_taken.last_mut())
+    {
+        *last = active;
+        *taken |= active;
+    }
+    Ok(())
+}
 
-    // Инвертируем текущее условие если родитель true
-    if parent_ok {
-        if let Some(last) = ctx.condition_stack.last_mut() {
-            *last = !*last;
-        }
+/// Обрабатывает !else
+pub fn handle_else(ctx: &mut PreprocessContext) -> Result<()> {
+    // Ветвь активна, если родитель активен и предыдущие ветви не выбраны
+    let active = elseif_pending(ctx)?;
+    if let (Some(last), Some(taken)) = (ctx.condition_stack.last_mut(), ctx.branch_taken.last_mut())
+    {
+        *last = active;
+        *taken = true;
     }
 
     Ok(())
 }
 
+/// Активны ли все условия, кроме текущего
+fn parent_active(ctx: &PreprocessContext) -> bool {
+    let len = ctx.condition_stack.len();
+    ctx.condition_stack[..len.saturating_sub(1)]
+        .iter()
+        .all(|&b| b)
+}
+
 /// Обрабатывает !endif
 pub fn handle_endif(ctx: &mut PreprocessContext) -> Result<()> {
     if ctx.condition_stack.is_empty() {
@@ -67,20 +106,123 @@ pub fn handle_endif(ctx: &mut PreprocessContext) -> Result<()> {
     }
 
     ctx.condition_stack.pop();
+    ctx.branch_taken.pop();
     ctx.condition_depth = ctx.condition_depth.saturating_sub(1);
 
     Ok(())
 }
 
+/// Отделяет селектор блока от пути: `file.puml!2`, `file.puml!PART`
+pub fn split_selector(spec: &str) -> (&str, Option<&str>) {
+    match spec.rsplit_once('!') {
+        Some((path, selector))
+            if !path.is_empty()
+                && !selector.is_empty()
+                && !selector.contains(['/', '\\', '"', '>']) =>
+        {
+            (path, Some(selector))
+        }
+        _ => (spec, None),
+    }
+}
+
+/// Выбирает из файла блок `@start...`/`@end...` по номеру (с нуля) или
+/// по `id` из `@startuml(id=NAME)`; строки `@start`/`@end` не включаются.
+/// Возвращает номер первой строки блока в файле и его текст
+pub fn select_block(content: &str, selector: &str) -> Option<(usize, String)> {
+    let index = selector.parse::<usize>().ok();
+    let mut block = 0;
+    let mut first_line = 1;
+    let mut current: Option<(bool, String)> = None;
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        match &mut current {
+            None if trimmed.starts_with("@start") => {
+                let id = trimmed
+                    .split_once("(id=")
+                    .map(|(_, rest)| rest.trim_end_matches(')').trim());
+                let selected = index == Some(block) || id == Some(selector);
+                current = Some((selected, String::new()));
+                first_line = number + 2;
+                block += 1;
+            }
+            Some((selected, body)) if trimmed.starts_with("@end") => {
+                if *selected {
+                    return Some((first_line, std::mem::take(body)));
+                }
+                current = None;
+            }
+            Some((true, body)) => {
+                body.push_str(line);
+                body.push('\n');
+            }
+            _ => {}
+        }
+    }
+
+    // Незакрытый выбранный блок включается до конца файла
+    current.and_then(|(selected, body)| selected.then_some((first_line, body)))
+}
+
+/// Собирает строки всех секций `!startsub NAME` ... `!endsub` с заданным именем.
+/// Возвращает номер первой строки первой секции в файле и текст секций
+pub fn extract_sub(content: &str, name: &str) -> Option<(usize, String)> {
+    let mut body = String::new();
+    let mut first_line = None;
+    let mut open: Vec<&str> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(part) = trimmed.strip_prefix("!startsub ") {
+            let part = part.trim();
+            if part == name && first_line.is_none() {
+                first_line = Some(number + 2);
+            }
+            open.push(part);
+        } else if trimmed == "!endsub" {
+            open.pop();
+        } else if open.contains(&name) {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    first_line.map(|line| (line, body))
+}
+
+/// Делит `!assert` на условие и сообщение по первому `:` вне строк и скобок
+pub fn split_assert(rest: &str) -> (&str, Option<&str>) {
+    let mut depth = 0usize;
+    let mut quote = None;
+
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth = depth.saturating_sub(1),
+            (None, ':') if depth == 0 => {
+                return (rest[..i].trim(), Some(rest[i + 1..].trim()));
+            }
+            _ => {}
+        }
+    }
+
+    (rest.trim(), None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Value;
 
     #[test]
     fn test_define() {
         let mut ctx = PreprocessContext::new();
         handle_define("DEBUG true", &mut ctx).unwrap();
-        assert_eq!(ctx.get_variable("DEBUG"), Some(&"true".to_string()));
+        assert_eq!(ctx.get_variable("DEBUG"), Some(&Value::from("true")));
     }
 
     #[test]
@@ -118,4 +260,67 @@ mod tests {
 
         handle_endif(&mut ctx).unwrap();
     }
+
+    #[test]
+    fn test_elseif_chain() {
+        let mut ctx = PreprocessContext::new();
+
+        handle_if(false, &mut ctx);
+        assert!(elseif_pending(&ctx).unwrap());
+        handle_elseif(true, &mut ctx).unwrap();
+        assert!(ctx.should_output());
+
+        // После выбранной ветви остальные не выполняются
+        assert!(!elseif_pending(&ctx).unwrap());
+        handle_elseif(false, &mut ctx).unwrap();
+        assert!(!ctx.should_output());
+        handle_else(&mut ctx).unwrap();
+        assert!(!ctx.should_output());
+
+        handle_endif(&mut ctx).unwrap();
+        assert!(handle_else(&mut ctx).is_err());
+    }
+
+    #[test]
+    fn test_split_selector() {
+        assert_eq!(split_selector("file.puml!2"), ("file.puml", Some("2")));
+        assert_eq!(split_selector("lib.puml!PART"), ("lib.puml", Some("PART")));
+        assert_eq!(split_selector("file.puml"), ("file.puml", None));
+        assert_eq!(
+            split_selector("<C4/C4_Container>"),
+            ("<C4/C4_Container>", None)
+        );
+    }
+
+    #[test]
+    fn test_select_block() {
+        let content = "@startuml\nA -> B\n@enduml\n@startuml(id=SECOND)\nC -> D\n@enduml\n";
+        assert_eq!(select_block(content, "0"), Some((2, "A -> B\n".to_string())));
+        assert_eq!(select_block(content, "1"), Some((5, "C -> D\n".to_string())));
+        assert_eq!(select_block(content, "SECOND"), Some((5, "C -> D\n".to_string())));
+        assert!(select_block(content, "2").is_none());
+    }
+
+    #[test]
+    fn test_extract_sub() {
+        let content = "header\n!startsub BASIC\nA -> B\n!startsub INNER\nB -> C\n!endsub\n!endsub\n!startsub BASIC\nC -> D\n!endsub\n";
+        assert_eq!(
+            extract_sub(content, "BASIC"),
+            Some((3, "A -> B\nB -> C\nC -> D\n".to_string()))
+        );
+        assert_eq!(extract_sub(content, "INNER"), Some((5, "B -> C\n".to_string())));
+        assert!(extract_sub(content, "MISSING").is_none());
+    }
+
+    #[test]
+    fn test_split_assert() {
+        assert_eq!(
+            split_assert("$x > 1 : x too small"),
+            ("$x > 1", Some("x too small"))
+        );
+        assert_eq!(
+            split_assert("%strlen(\"a:b\") == 3"),
+            ("%strlen(\"a:b\") == 3", None)
+        );
+    }
 }
@@ -36,4 +36,12 @@ pub enum PreprocessError {
     /// Ошибка вычисления выражения
     #[error("ошибка вычисления выражения: {0}")]
     ExpressionError(String),
+
+    /// Не выполнено условие `!assert`
+    #[error("!assert: {0}")]
+    AssertionFailed(String),
+
+    /// URL не входит в allowlist
+    #[error("URL не разрешён: {0}")]
+    UrlNotAllowed(String),
 }
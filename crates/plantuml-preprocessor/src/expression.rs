@@ -0,0 +1,796 @@
+//! Выражения препроцессора
+//!
+//! Используются в `!if`, `!elseif`, `!while`, `!foreach`, `!return` и в
+//! присваиваниях переменных. Поддерживаются:
+//! - литералы: строки `"..."`/`'...'`, целые и дробные числа, `true`/`false`,
+//!   JSON (`{...}`, `[...]`)
+//! - переменные `$name` и вызовы `$func(...)`, `%builtin(...)`
+//! - доступ к JSON: `$data.servers[0].name`, `$data["key"]`
+//! - операторы (по убыванию приоритета): `!` и унарный `-`; `*` `/` `%`; `+` `-`;
+//!   `<` `<=` `>` `>=`; `==` `!=`; `&&`; `||`
+
+use std::fmt;
+
+use crate::{PreprocessError, Result};
+
+/// Значение выражения
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Строка
+    Str(String),
+    /// Целое число
+    Int(i64),
+    /// Дробное число (`%floor`, `%ceil`, `%abs`)
+    Float(f64),
+    /// Логическое значение
+    Bool(bool),
+    /// JSON массив, объект или `null`
+    Json(serde_json::Value),
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Str(String::new())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl Value {
+    /// Значение из текста переменной: целое число, дробное число или строка
+    pub fn from_text(text: &str) -> Self {
+        let trimmed = text.trim();
+        if let Ok(n) = trimmed.parse::<i64>() {
+            return Value::Int(n);
+        }
+        match decimal(trimmed) {
+            Some(f) => Value::Float(f),
+            None => Value::Str(text.to_string()),
+        }
+    }
+
+    /// Значение из JSON: скаляры становятся строками, числами и логическими значениями
+    pub fn from_json(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::String(s) => Value::Str(s),
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) if n.is_i64() => Value::Int(n.as_i64().unwrap_or(0)),
+            serde_json::Value::Number(n) => Value::Float(n.as_f64().unwrap_or(0.0)),
+            other => Value::Json(other),
+        }
+    }
+
+    /// Значение как JSON
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Str(s) => serde_json::Value::String(s.clone()),
+            Value::Int(n) => serde_json::Value::from(*n),
+            Value::Float(f) => serde_json::Value::from(*f),
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Json(json) => json.clone(),
+        }
+    }
+
+    /// Истинность значения в условиях
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            Value::Int(n) => *n != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::Str(s) => !(s.is_empty() || s == "0" || s == "false"),
+            Value::Json(json) => match json {
+                serde_json::Value::Null => false,
+                serde_json::Value::Array(items) => !items.is_empty(),
+                serde_json::Value::Object(fields) => !fields.is_empty(),
+                _ => true,
+            },
+        }
+    }
+
+    /// Целое число, если значение его представляет
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::Bool(b) => Some(i64::from(*b)),
+            Value::Str(s) => s.trim().parse().ok(),
+            Value::Float(_) | Value::Json(_) => None,
+        }
+    }
+
+    /// Число с плавающей точкой, если значение числовое
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            Value::Str(s) => decimal(s.trim()).or_else(|| self.as_int().map(|n| n as f64)),
+            _ => self.as_int().map(|n| n as f64),
+        }
+    }
+
+    /// Поле JSON объекта: `value.name`
+    pub fn field(&self, name: &str) -> Result<Value> {
+        match self {
+            Value::Json(serde_json::Value::Object(fields)) => fields
+                .get(name)
+                .cloned()
+                .map(Value::from_json)
+                .ok_or_else(|| error(format!("нет поля '{}' в {}", name, self))),
+            _ => Err(error(format!("'{}' не является JSON объектом", self))),
+        }
+    }
+
+    /// Элемент JSON массива по номеру или поле объекта по ключу: `value[index]`
+    pub fn index(&self, index: &Value) -> Result<Value> {
+        match (self, index.as_int()) {
+            (Value::Json(serde_json::Value::Array(items)), Some(i)) => usize::try_from(i)
+                .ok()
+                .and_then(|i| items.get(i))
+                .cloned()
+                .map(Value::from_json)
+                .ok_or_else(|| error(format!("индекс {} вне массива {}", i, self))),
+            (Value::Json(serde_json::Value::Object(_)), _) => self.field(&index.to_text()),
+            _ => Err(error(format!("'{}' нельзя индексировать '{}'", self, index))),
+        }
+    }
+
+    /// Текстовое представление для подстановки в диаграмму
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => f.write_str(s),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Json(json) => write!(f, "{}", json),
+        }
+    }
+}
+
+/// Окружение вычисления: переменные и вызовы функций
+pub trait Environment {
+    /// Значение переменной (`$name` или имени из `!define`)
+    fn variable(&self, name: &str) -> Option<Value>;
+
+    /// Вызов пользовательской (`$name`) или встроенной (`%name`) функции
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value>;
+}
+
+/// Разобранное выражение
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Литерал
+    Literal(Value),
+    /// Переменная
+    Variable(String),
+    /// Вызов функции
+    Call(String, Vec<Expr>),
+    /// Поле объекта `expr.name`
+    Field(Box<Expr>, String),
+    /// Элемент `expr[index]`
+    Index(Box<Expr>, Box<Expr>),
+    /// Унарный оператор (`!`, `-`)
+    Unary(&'static str, Box<Expr>),
+    /// Бинарный оператор
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Разбирает выражение из строки
+    pub fn parse(source: &str) -> Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.parse_binary(0)?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(error(format!("лишний токен {:?} в '{}'", token, source))),
+        }
+    }
+
+    /// Вычисляет выражение
+    pub fn evaluate(&self, env: &mut dyn Environment) -> Result<Value> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Variable(name) => env
+                .variable(name)
+                .ok_or_else(|| PreprocessError::UnknownVariable(name.clone())),
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(env))
+                    .collect::<Result<Vec<_>>>()?;
+                env.call(name, args)
+            }
+            Expr::Field(object, name) => object.evaluate(env)?.field(name),
+            Expr::Index(object, index) => object.evaluate(env)?.index(&index.evaluate(env)?),
+            Expr::Unary(op, operand) => {
+                let value = operand.evaluate(env)?;
+                match (*op, value) {
+                    ("!", value) => Ok(Value::Bool(!value.is_truthy())),
+                    (_, Value::Float(f)) => Ok(Value::Float(-f)),
+                    (op, value) => integer(&value, op)?
+                        .checked_neg()
+                        .map(Value::Int)
+                        .ok_or_else(|| overflow(op)),
+                }
+            }
+            // Логические операторы вычисляются сокращённо
+            Expr::Binary("&&", lhs, rhs) => Ok(Value::Bool(
+                lhs.evaluate(env)?.is_truthy() && rhs.evaluate(env)?.is_truthy(),
+            )),
+            Expr::Binary("||", lhs, rhs) => Ok(Value::Bool(
+                lhs.evaluate(env)?.is_truthy() || rhs.evaluate(env)?.is_truthy(),
+            )),
+            Expr::Binary(op, lhs, rhs) => binary(op, lhs.evaluate(env)?, rhs.evaluate(env)?),
+        }
+    }
+}
+
+/// Разбирает и вычисляет выражение
+pub fn evaluate(source: &str, env: &mut dyn Environment) -> Result<Value> {
+    Expr::parse(source)?.evaluate(env)
+}
+
+/// Максимальная глубина дерева выражения: скобки, индексы, аргументы,
+/// унарные операторы и цепочки бинарных операторов
+const MAX_EXPRESSION_DEPTH: usize = 256;
+
+/// Бинарные операторы по уровням приоритета (от низшего)
+const PRECEDENCE: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+fn binary(op: &str, lhs: Value, rhs: Value) -> Result<Value> {
+    let value = match op {
+        "==" => Value::Bool(equals(&lhs, &rhs)),
+        "!=" => Value::Bool(!equals(&lhs, &rhs)),
+        "<" | "<=" | ">" | ">=" => {
+            let ordering = match (lhs.as_int(), rhs.as_int()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => match (lhs.as_float(), rhs.as_float()) {
+                    (Some(a), Some(b)) => a.total_cmp(&b),
+                    _ => lhs.to_text().cmp(&rhs.to_text()),
+                },
+            };
+            Value::Bool(match op {
+                "<" => ordering.is_lt(),
+                "<=" => ordering.is_le(),
+                ">" => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+        // `+` складывает числа и склеивает всё остальное
+        "+" => match (&lhs, &rhs) {
+            (Value::Int(a), Value::Int(b)) => Value::Int(a.checked_add(*b).ok_or_else(|| overflow(op))?),
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                Value::Float(float(&lhs, op)? + float(&rhs, op)?)
+            }
+            _ => Value::Str(format!("{}{}", lhs, rhs)),
+        },
+        _ if matches!(lhs, Value::Float(_)) || matches!(rhs, Value::Float(_)) => {
+            let (a, b) = (float(&lhs, op)?, float(&rhs, op)?);
+            Value::Float(match op {
+                "-" => a - b,
+                "*" => a * b,
+                _ if b == 0.0 => return Err(error("деление на ноль".to_string())),
+                "/" => a / b,
+                _ => a % b,
+            })
+        }
+        _ => {
+            let (a, b) = (integer(&lhs, op)?, integer(&rhs, op)?);
+            let result = match op {
+                "-" => a.checked_sub(b),
+                "*" => a.checked_mul(b),
+                _ if b == 0 => return Err(error("деление на ноль".to_string())),
+                "/" => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            Value::Int(result.ok_or_else(|| overflow(op))?)
+        }
+    };
+    Ok(value)
+}
+
+/// Равенство: числа сравниваются как числа, остальное — по тексту
+fn equals(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => a == b,
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+            lhs.as_float() == rhs.as_float()
+        }
+        (Value::Json(a), Value::Json(b)) => a == b,
+        _ => lhs.to_text() == rhs.to_text(),
+    }
+}
+
+fn integer(value: &Value, op: &str) -> Result<i64> {
+    value
+        .as_int()
+        .ok_or_else(|| error(format!("'{}' ожидает число, получено '{}'", op, value)))
+}
+
+fn float(value: &Value, op: &str) -> Result<f64> {
+    value
+        .as_float()
+        .ok_or_else(|| error(format!("'{}' ожидает число, получено '{}'", op, value)))
+}
+
+/// Дробное число вида `12.5` (без экспоненты, `inf` и `NaN`)
+fn decimal(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (int, frac) = digits.split_once('.')?;
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if is_digits(int) && is_digits(frac) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn error(message: String) -> PreprocessError {
+    PreprocessError::ExpressionError(message)
+}
+
+fn overflow(op: &str) -> PreprocessError {
+    error(format!("переполнение целого в '{}'", op))
+}
+
+/// Токен выражения
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(Value),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    Comma,
+}
+
+/// Операторы; двухсимвольные проверяются первыми
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = match c {
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            ',' => (Token::Comma, 1),
+            '.' => (Token::Dot, 1),
+            ']' => (Token::RBracket, 1),
+            '"' | '\'' => string_literal(rest, c)?,
+            // `[` после значения — индекс, иначе начало JSON массива
+            '[' if matches!(
+                tokens.last(),
+                Some(
+                    Token::Ident(_)
+                        | Token::RParen
+                        | Token::RBracket
+                        | Token::Literal(Value::Json(_))
+                )
+            ) =>
+            {
+                (Token::LBracket, 1)
+            }
+            '{' | '[' => json_literal(rest)?,
+            '0'..='9' => number_literal(rest)?,
+            // Минус перед числом вне позиции операнда — часть литерала,
+            // иначе `-9223372036854775808` не помещается в i64
+            '-' if rest[1..].starts_with(|c: char| c.is_ascii_digit())
+                && !matches!(
+                    tokens.last(),
+                    Some(
+                        Token::Literal(_) | Token::Ident(_) | Token::RParen | Token::RBracket
+                    )
+                ) =>
+            {
+                number_literal(rest)?
+            }
+            // `%` перед именем — встроенная функция, иначе остаток от деления
+            '$' | '%' | '_' | 'a'..='z' | 'A'..='Z'
+                if c != '%' || rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) =>
+            {
+                let len = 1 + rest[1..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len() - 1);
+                let token = match &rest[..len] {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    name => Token::Ident(name.to_string()),
+                };
+                (token, len)
+            }
+            _ => {
+                let op = OPERATORS
+                    .iter()
+                    .find(|op| rest.starts_with(**op))
+                    .ok_or_else(|| error(format!("неожиданный символ '{}' в '{}'", c, source)))?;
+                (Token::Op(op), op.len())
+            }
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Целое или дробное число, возможно со знаком `-`
+fn number_literal(rest: &str) -> Result<(Token, usize)> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let sign = usize::from(rest.starts_with('-'));
+    let mut len = sign + digits(&rest[sign..]);
+    if rest[len..].starts_with('.') && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+        len += 1 + digits(&rest[len + 1..]);
+        let f = rest[..len].parse().map_err(|_| error(format!("некорректное число {}", &rest[..len])))?;
+        return Ok((Token::Literal(Value::Float(f)), len));
+    }
+    let n = rest[..len]
+        .parse()
+        .map_err(|_| error(format!("слишком большое число {}", &rest[..len])))?;
+    Ok((Token::Literal(Value::Int(n)), len))
+}
+
+/// Строка в кавычках; `\` экранирует кавычку, прочие `\` (`\n` в подписях)
+/// сохраняются как есть
+fn string_literal(rest: &str, quote: char) -> Result<(Token, usize)> {
+    let mut value = String::new();
+    let mut chars = rest.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, escaped)) if escaped == quote => {
+                    value.push(escaped);
+                    chars.next();
+                }
+                _ => value.push(c),
+            },
+            c if c == quote => return Ok((Token::Literal(Value::Str(value)), i + 1)),
+            c => value.push(c),
+        }
+    }
+    Err(error(format!("незакрытая строка {}", rest)))
+}
+
+/// JSON объект или массив: длина определяется самим разбором JSON
+fn json_literal(rest: &str) -> Result<(Token, usize)> {
+    let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<serde_json::Value>();
+    match stream.next() {
+        Some(Ok(json)) => Ok((Token::Literal(Value::from_json(json)), stream.byte_offset())),
+        Some(Err(e)) => Err(error(format!("некорректный JSON: {}", e))),
+        None => Err(error("ожидается JSON".to_string())),
+    }
+}
+
+/// Рекурсивный спуск по уровням `PRECEDENCE`
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Текущая глубина дерева, ограничена `MAX_EXPRESSION_DEPTH`
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Углубляется на `levels` уровней дерева
+    fn enter(&mut self, levels: usize) -> Result<()> {
+        self.depth += levels;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(error(format!(
+                "слишком глубокая вложенность выражения (больше {})",
+                MAX_EXPRESSION_DEPTH
+            )));
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(error(format!("ожидается {:?}, получено {:?}", expected, other))),
+        }
+    }
+
+    fn parse_binary(&mut self, level: usize) -> Result<Expr> {
+        let Some(operators) = PRECEDENCE.get(level) else {
+            return self.parse_unary();
+        };
+        let mut lhs = self.parse_binary(level + 1)?;
+        // Каждый оператор цепочки углубляет левую ветвь
+        let mut chain = 0;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if !operators.contains(&op) {
+                break;
+            }
+            self.pos += 1;
+            self.enter(1)?;
+            chain += 1;
+            let rhs = self.parse_binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        self.depth -= chain;
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        self.enter(1)?;
+        let expr = match self.peek() {
+            Some(&Token::Op(op @ ("!" | "-"))) => {
+                self.pos += 1;
+                Expr::Unary(op, Box::new(self.parse_unary()?))
+            }
+            _ => self.parse_postfix()?,
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    /// Значение с цепочкой `.field` и `[index]`
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_primary()?;
+        let mut chain = 0;
+        loop {
+            if matches!(self.peek(), Some(Token::Dot | Token::LBracket)) {
+                self.enter(1)?;
+                chain += 1;
+            }
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Ident(name)) => expr = Expr::Field(Box::new(expr), name),
+                        other => {
+                            return Err(error(format!("ожидается имя поля, получено {:?}", other)))
+                        }
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    let index = self.parse_binary(0)?;
+                    self.expect(Token::RBracket)?;
+                    expr = Expr::Index(Box::new(expr), Box::new(index));
+                }
+                _ => {
+                    self.depth -= chain;
+                    return Ok(expr);
+                }
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(Expr::Literal(value)),
+            Some(Token::LParen) => {
+                let expr = self.parse_binary(0)?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Expr::Variable(name));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() == Some(&Token::RParen) {
+                    self.pos += 1;
+                    return Ok(Expr::Call(name, args));
+                }
+                loop {
+                    args.push(self.parse_binary(0)?);
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::RParen) => return Ok(Expr::Call(name, args)),
+                        other => {
+                            return Err(error(format!(
+                                "ожидается ',' или ')' в вызове {}, получено {:?}",
+                                name, other
+                            )))
+                        }
+                    }
+                }
+            }
+            other => Err(error(format!("ожидается значение, получено {:?}", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+
+    /// Окружение с переменными и функцией `$twice`
+    struct TestEnv(IndexMap<String, Value>);
+
+    impl Environment for TestEnv {
+        fn variable(&self, name: &str) -> Option<Value> {
+            self.0.get(name).cloned()
+        }
+
+        fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+            match name {
+                "$twice" => Ok(Value::Int(args[0].as_int().unwrap() * 2)),
+                _ => Err(error(format!("неизвестная функция {}", name))),
+            }
+        }
+    }
+
+    fn eval(source: &str) -> Result<Value> {
+        let mut vars = IndexMap::new();
+        vars.insert("$env".to_string(), Value::Str("prod".to_string()));
+        vars.insert("$i".to_string(), Value::Int(3));
+        evaluate(source, &mut TestEnv(vars))
+    }
+
+    #[test]
+    fn test_arithmetic_precedence() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), Value::Int(7));
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), Value::Int(9));
+        assert_eq!(eval("-$i + 10 % 4").unwrap(), Value::Int(-1));
+        assert_eq!(eval("$twice($i + 1) / 3").unwrap(), Value::Int(2));
+        assert!(eval("1 / 0").is_err());
+        assert_eq!(eval("1.5 * 2").unwrap(), Value::Float(3.0));
+        assert_eq!(eval("-2.5 < 1").unwrap(), Value::Bool(true));
+        assert_eq!(eval("-9223372036854775808").unwrap(), Value::Int(i64::MIN));
+        assert_eq!(eval("3-1").unwrap(), Value::Int(2));
+        assert_eq!(eval("$i -1").unwrap(), Value::Int(2));
+        assert_eq!(eval("(1)-1 * -2").unwrap(), Value::Int(3));
+        assert_eq!(Value::from_text("12.50"), Value::Float(12.5));
+        assert_eq!(Value::from_text("inf"), Value::Str("inf".to_string()));
+    }
+
+    #[test]
+    fn test_comparison_and_logic() {
+        assert_eq!(eval("$env == \"prod\"").unwrap(), Value::Bool(true));
+        assert_eq!(eval("$env != 'prod' || $i < 5").unwrap(), Value::Bool(true));
+        assert_eq!(eval("!($i >= 3 && true)").unwrap(), Value::Bool(false));
+        // Правый операнд не вычисляется при сокращённом вычислении
+        assert_eq!(eval("false && $missing").unwrap(), Value::Bool(false));
+        assert_eq!(eval("\"10\" == 10").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(eval("\"a\" + $i").unwrap(), Value::Str("a3".to_string()));
+        assert_eq!(
+            eval(r#""say \"hi\"""#).unwrap(),
+            Value::Str("say \"hi\"".to_string())
+        );
+        // `\n` остаётся в тексте: его переводят в строку подписи
+        assert_eq!(eval(r#""a\nb""#).unwrap(), Value::Str("a\\nb".to_string()));
+        assert!(eval("\"abc\" - 1").is_err());
+    }
+
+    #[test]
+    fn test_json_values() {
+        let value = eval(r#"["a", "b"]"#).unwrap();
+        assert_eq!(value.to_text(), r#"["a","b"]"#);
+        assert!(value.is_truthy());
+        assert_eq!(eval(r#"{"k": 1} == {"k": 1}"#).unwrap(), Value::Bool(true));
+        assert!(!eval("[]").unwrap().is_truthy());
+    }
+
+    #[test]
+    fn test_json_access() {
+        let mut vars = IndexMap::new();
+        let data = r#"{"servers": [{"name": "web", "ports": [80, 443]}], "env": "prod"}"#;
+        vars.insert(
+            "$data".to_string(),
+            Value::from_json(serde_json::from_str(data).unwrap()),
+        );
+        let mut env = TestEnv(vars);
+
+        let name = evaluate("$data.servers[0].name", &mut env).unwrap();
+        assert_eq!(name, Value::Str("web".to_string()));
+        let port = evaluate("$data.servers[0].ports[$twice(1) - 1] + 1", &mut env).unwrap();
+        assert_eq!(port, Value::Int(444));
+        assert_eq!(evaluate("$data[\"env\"]", &mut env).unwrap(), Value::from("prod"));
+        // Литерал массива после оператора — не индекс
+        assert_eq!(evaluate("[1][0]", &mut env).unwrap(), Value::Int(1));
+
+        assert!(evaluate("$data.servers[5]", &mut env).is_err());
+        assert!(evaluate("$data.missing", &mut env).is_err());
+        assert!(evaluate("$data.env.x", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            eval("$missing"),
+            Err(PreprocessError::UnknownVariable(name)) if name == "$missing"
+        ));
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("(1").is_err());
+        assert!(Expr::parse("Hello_$name").is_err());
+        assert!(Expr::parse("\"open").is_err());
+
+        // Арифметические ошибки не превращаются в текст и не переполняются
+        for source in ["1 / 0", "5 % 0", "1.5 / 0", "\"a\" * 3", "\"a\" - 1"] {
+            assert!(
+                matches!(eval(source), Err(PreprocessError::ExpressionError(_))),
+                "{}",
+                source
+            );
+        }
+        for source in [
+            "9223372036854775807 + 1",
+            "0 - 9223372036854775807 - 2",
+            "4611686018427387904 * 2",
+            "(0 - 9223372036854775807 - 1) / (0 - 1)",
+            "(0 - 9223372036854775807 - 1) % (0 - 1)",
+            "-(0 - 9223372036854775807 - 1)",
+        ] {
+            assert!(
+                matches!(eval(source), Err(PreprocessError::ExpressionError(m)) if m.contains("переполнение")),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        let nested = |open: &str, inner: &str, close: &str, n: usize| {
+            format!("{}{}{}", open.repeat(n), inner, close.repeat(n))
+        };
+        assert_eq!(eval(&nested("(", "1", ")", 100)).unwrap(), Value::Int(1));
+        assert_eq!(eval(&nested("-", "1", "", 100)).unwrap(), Value::Int(1));
+
+        // Глубокая вложенность — ошибка разбора, а не переполнение стека
+        let deep = [
+            nested("(", "1", ")", 100_000),
+            nested("!", "1", "", 100_000),
+            nested("$f(", "1", ")", 100_000),
+            nested("$data[", "0", "]", 100_000),
+            format!("1{}", " + 1".repeat(100_000)),
+            format!("$data{}", "[0]".repeat(100_000)),
+        ];
+        for source in &deep {
+            assert!(
+                matches!(Expr::parse(source), Err(PreprocessError::ExpressionError(m)) if m.contains("вложенность")),
+                "{}",
+                &source[..20]
+            );
+        }
+    }
+}
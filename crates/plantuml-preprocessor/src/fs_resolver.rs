@@ -6,11 +6,10 @@
 //! - Путей поиска (search paths)
 //! - Стандартной библиотеки PlantUML (`<stdlib/...>`)
 
-use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::{FileResolver, PreprocessError, Result};
+use crate::{FileResolver, PreprocessError, Result, MAX_INCLUDE_DEPTH};
 
 /// FileResolver для файловой системы
 ///
@@ -26,7 +25,6 @@ use crate::{FileResolver, PreprocessError, Result};
 /// let result = preprocessor.process(source)?;
 /// ```
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct FsFileResolver {
     /// Базовая директория для относительных путей
     base_dir: PathBuf,
@@ -34,10 +32,6 @@ pub struct FsFileResolver {
     search_paths: Vec<PathBuf>,
     /// Максимальная глубина включений (защита от рекурсии)
     max_depth: usize,
-    /// Текущая глубина включений
-    current_depth: usize,
-    /// Уже включённые файлы (для обнаружения циклов)
-    included_files: HashSet<PathBuf>,
 }
 
 impl FsFileResolver {
@@ -56,9 +50,7 @@ impl FsFileResolver {
         Self {
             base_dir: base_dir.as_ref().to_path_buf(),
             search_paths: Vec::new(),
-            max_depth: 10,
-            current_depth: 0,
-            included_files: HashSet::new(),
+            max_depth: MAX_INCLUDE_DEPTH,
         }
     }
 
@@ -82,9 +74,9 @@ impl FsFileResolver {
         self
     }
 
-    /// Устанавливает максимальную глубину включений
+    /// Устанавливает максимальную глубину вложенных `!include`
     ///
-    /// По умолчанию: 10
+    /// По умолчанию: [`MAX_INCLUDE_DEPTH`]
     pub fn with_max_depth(mut self, depth: usize) -> Self {
         self.max_depth = depth;
         self
@@ -137,10 +129,11 @@ impl FsFileResolver {
         None
     }
 
-    /// Разрешает путь к стандартной библиотеке
+    /// Разрешает путь к стандартной библиотеке на диске
+    ///
+    /// Встроенную библиотеку читает `StdlibResolver` из `plantuml-stdlib`,
+    /// здесь ищутся локальные копии в search_paths.
     fn resolve_stdlib_path(&self, stdlib_path: &str) -> Option<PathBuf> {
-        // TODO: Интеграция с plantuml-stdlib
-        // Пока ищем в search_paths
         for search_path in &self.search_paths {
             let candidate = search_path.join(stdlib_path);
             if candidate.exists() {
@@ -162,42 +155,6 @@ impl FsFileResolver {
 
         None
     }
-
-    /// Проверяет на циклическое включение
-    #[allow(dead_code)]
-    fn check_recursion(&mut self, path: &Path) -> Result<()> {
-        // Проверка глубины
-        if self.current_depth >= self.max_depth {
-            return Err(PreprocessError::RecursiveInclude(format!(
-                "превышена максимальная глубина включений ({})",
-                self.max_depth
-            )));
-        }
-
-        // Канонический путь для сравнения
-        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-
-        // Проверка на уже включённый файл (цикл)
-        if self.included_files.contains(&canonical) {
-            return Err(PreprocessError::RecursiveInclude(format!(
-                "{}",
-                path.display()
-            )));
-        }
-
-        self.included_files.insert(canonical);
-        self.current_depth += 1;
-
-        Ok(())
-    }
-
-    /// Сбрасывает состояние после включения файла
-    #[allow(dead_code)]
-    fn finish_include(&mut self) {
-        if self.current_depth > 0 {
-            self.current_depth -= 1;
-        }
-    }
 }
 
 impl FileResolver for FsFileResolver {
@@ -207,11 +164,7 @@ impl FileResolver for FsFileResolver {
             PreprocessError::FileNotFound(format!("{} (base_dir: {})", path, self.base_dir.display()))
         })?;
 
-        // Проверяем рекурсию (используем clone для мутабельности)
-        // NOTE: В реальности нужен RefCell или другой механизм
-        // Пока просто читаем файл без проверки рекурсии на уровне resolver
-
-        // Читаем файл
+        // Циклы и глубину включений проверяет препроцессор (`max_include_depth`)
         fs::read_to_string(&resolved_path).map_err(|e| {
             PreprocessError::FileReadError(format!("{}: {}", resolved_path.display(), e))
         })
@@ -220,6 +173,23 @@ impl FileResolver for FsFileResolver {
     fn file_exists(&self, path: &str) -> bool {
         self.resolve_path(path).is_some()
     }
+
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        let resolved_path = self.resolve_path(path).ok_or_else(|| {
+            PreprocessError::FileNotFound(format!(
+                "{} (base_dir: {})",
+                path,
+                self.base_dir.display()
+            ))
+        })?;
+        fs::read(&resolved_path).map_err(|e| {
+            PreprocessError::FileReadError(format!("{}: {}", resolved_path.display(), e))
+        })
+    }
+
+    fn max_include_depth(&self) -> usize {
+        self.max_depth
+    }
 }
 
 /// Создаёт FsFileResolver из пути к файлу (использует родительскую директорию)
@@ -3,9 +3,13 @@
 //! Поддержка:
 //! - `!function $name($args)` ... `!endfunction`
 //! - `!procedure $name($args)` ... `!endprocedure`
+//! - `!unquoted procedure Name($args)`: имя без `$`, аргументы без кавычек
+//!   (`Person(user, "Пользователь")`) передаются как текст
 //! - `!return value`
+//! - параметры со значениями по умолчанию: `$name($a, $b = "x")`
 
-use indexmap::IndexMap;
+use crate::expression::Value;
+use crate::{PreprocessContext, Preprocessor};
 
 /// Тип callable: функция или процедура
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,28 +29,44 @@ pub struct UserCallable {
     pub kind: CallableKind,
     /// Список параметров (имена с $)
     pub parameters: Vec<String>,
+    /// Выражения значений по умолчанию для параметров
+    pub defaults: Vec<Option<String>>,
     /// Тело функции (строки между !function и !endfunction)
     pub body: Vec<String>,
+    /// Файл определения (`None` — основной исходник)
+    pub file: Option<String>,
+    /// Строка первой строки тела в файле определения (с 1)
+    pub line: usize,
 }
 
 impl UserCallable {
     /// Создаёт новую функцию
     pub fn function(name: impl Into<String>, parameters: Vec<String>) -> Self {
-        Self {
-            name: name.into(),
-            kind: CallableKind::Function,
-            parameters,
-            body: Vec::new(),
-        }
+        Self::new(name.into(), CallableKind::Function, parameters)
     }
 
     /// Создаёт новую процедуру
     pub fn procedure(name: impl Into<String>, parameters: Vec<String>) -> Self {
+        Self::new(name.into(), CallableKind::Procedure, parameters)
+    }
+
+    /// Разделяет параметры `$name = default` на имена и значения по умолчанию
+    fn new(name: String, kind: CallableKind, parameters: Vec<String>) -> Self {
+        let (parameters, defaults) = parameters
+            .iter()
+            .map(|param| match param.split_once('=') {
+                Some((name, default)) => (name.trim().to_string(), Some(default.trim().to_string())),
+                None => (param.trim().to_string(), None),
+            })
+            .unzip();
         Self {
-            name: name.into(),
-            kind: CallableKind::Procedure,
+            name,
+            kind,
             parameters,
+            defaults,
             body: Vec::new(),
+            file: None,
+            line: 1,
         }
     }
 
@@ -55,67 +75,23 @@ impl UserCallable {
         self.body.push(line.into());
     }
 
-    /// Вызывает функцию/процедуру с аргументами
+    /// Вызывает функцию/процедуру с аргументами в отдельном контексте
     ///
+    /// Тело выполняется препроцессором без доступа к другим функциям.
     /// Возвращает (output_lines, return_value)
     pub fn call(&self, args: &[String]) -> (Vec<String>, Option<String>) {
-        // Создаём локальный контекст переменных
-        let mut local_vars: IndexMap<String, String> = IndexMap::new();
-
-        // Связываем параметры с аргументами
-        for (i, param) in self.parameters.iter().enumerate() {
-            let value = args.get(i).cloned().unwrap_or_default();
-            local_vars.insert(param.clone(), value);
+        let mut ctx = PreprocessContext::new();
+        let args = args.iter().map(|arg| Value::from_text(arg)).collect();
+        match Preprocessor::new().invoke(self, args, &mut ctx) {
+            Ok((output, returned)) => (
+                output.lines().map(str::to_string).collect(),
+                returned.map(|value| value.to_text()),
+            ),
+            Err(_) => (Vec::new(), None),
         }
-
-        let mut output = Vec::new();
-        let mut return_value: Option<String> = None;
-
-        for line in &self.body {
-            let trimmed = line.trim();
-
-            // Обработка !return
-            if let Some(rest) = trimmed.strip_prefix("!return ") {
-                let value = substitute_local(rest.trim(), &local_vars);
-                return_value = Some(value);
-                break; // !return завершает выполнение
-            }
-
-            // Обработка локального присваивания !$var = value
-            if trimmed.starts_with("!$") {
-                if let Some((var, val)) = trimmed[1..].split_once('=') {
-                    let var_name = var.trim().to_string();
-                    let val_str = substitute_local(val.trim().trim_matches('"'), &local_vars);
-                    local_vars.insert(var_name, val_str);
-                    continue;
-                }
-            }
-
-            // Подстановка переменных и вывод
-            let processed = substitute_local(line, &local_vars);
-            output.push(processed);
-        }
-
-        (output, return_value)
     }
 }
 
-/// Подставляет локальные переменные в строку
-fn substitute_local(line: &str, vars: &IndexMap<String, String>) -> String {
-    let mut result = line.to_string();
-
-    for (name, value) in vars {
-        // Подстановка $name
-        result = result.replace(name, value);
-
-        // Подстановка ${name}
-        let braced = format!("${{{}}}", name.trim_start_matches('$'));
-        result = result.replace(&braced, value);
-    }
-
-    result
-}
-
 /// Парсит определение функции/процедуры
 ///
 /// Формат: `$name($arg1, $arg2, ...)` или `$name()`
@@ -148,15 +124,11 @@ pub fn parse_callable_definition(def: &str) -> Option<(String, Vec<String>)> {
 
 /// Парсит вызов функции/процедуры
 ///
-/// Формат: `$name(arg1, arg2, ...)` или `$name()`
+/// Формат: `$name(arg1, arg2, ...)`, `%name(...)`, `Name(...)` или `$name()`.
+/// Аргументы возвращаются как текст, кавычки сохраняются
 pub fn parse_callable_call(call: &str) -> Option<(String, Vec<String>)> {
     let call = call.trim();
 
-    // Должно начинаться с $
-    if !call.starts_with('$') {
-        return None;
-    }
-
     let paren_start = call.find('(')?;
     let paren_end = call.rfind(')')?;
 
@@ -165,59 +137,104 @@ pub fn parse_callable_call(call: &str) -> Option<(String, Vec<String>)> {
     }
 
     let name = call[..paren_start].trim().to_string();
+    if name.is_empty() {
+        return None;
+    }
     let args_str = &call[paren_start + 1..paren_end];
 
     let args: Vec<String> = if args_str.trim().is_empty() {
         Vec::new()
     } else {
-        // Простой парсинг - по запятым (не учитывает вложенные вызовы)
-        args_str
-            .split(',')
-            .map(|s| s.trim().trim_matches('"').to_string())
-            .collect()
+        split_arguments(args_str)
     };
 
     Some((name, args))
 }
 
-/// Ищет вызовы функций в строке и возвращает позиции
-pub fn find_function_calls(line: &str) -> Vec<(usize, usize, String, Vec<String>)> {
+/// Делит список аргументов по запятым вне строк и скобок
+fn split_arguments(args: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in args.char_indices() {
+        match (c, quote) {
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('"' | '\'', None) => quote = Some(c),
+            ('(' | '[' | '{', None) => depth += 1,
+            (')' | ']' | '}', None) => depth = depth.saturating_sub(1),
+            (',', None) if depth == 0 => {
+                result.push(args[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(args[start..].trim().to_string());
+    result
+}
+
+/// Ищет вызовы пользовательских (`$name(...)`) и встроенных (`%name(...)`)
+/// функций в строке и возвращает байтовые позиции. Имена без `$` (`Rel(...)`)
+/// считаются вызовами, если их принимает `is_macro`
+///
+/// Вложенные вызовы входят в аргументы внешнего; скобки внутри строк в
+/// кавычках не учитываются.
+pub fn find_function_calls(
+    line: &str,
+    is_macro: impl Fn(&str) -> bool,
+) -> Vec<(usize, usize, String, Vec<String>)> {
     let mut calls = Vec::new();
-    let chars: Vec<char> = line.chars().collect();
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let offset = |i: usize| chars.get(i).map_or(line.len(), |&(pos, _)| pos);
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
     let mut i = 0;
 
     while i < chars.len() {
-        if chars[i] == '$' {
+        let c = chars[i].1;
+        let bare = (c.is_alphabetic() || c == '_')
+            && (i == 0 || !(is_word(chars[i - 1].1) || matches!(chars[i - 1].1, '$' | '%' | '.')));
+        if c == '$' || c == '%' || bare {
             // Потенциальный вызов функции
             let start = i;
-            i += 1;
+            let mut name = String::new();
+            if !bare {
+                name.push(c);
+                i += 1;
+            }
 
             // Читаем имя
-            let mut name = String::from("$");
-            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
-                name.push(chars[i]);
+            while i < chars.len() && is_word(chars[i].1) {
+                name.push(chars[i].1);
                 i += 1;
             }
 
             // Проверяем наличие (
-            if i < chars.len() && chars[i] == '(' {
+            let named = if bare { is_macro(&name) } else { name.len() > 1 };
+            if named && i < chars.len() && chars[i].1 == '(' {
                 let mut depth = 1;
+                let mut quote = None;
                 i += 1;
 
                 // Ищем закрывающую скобку
                 while i < chars.len() && depth > 0 {
-                    if chars[i] == '(' {
-                        depth += 1;
-                    } else if chars[i] == ')' {
-                        depth -= 1;
+                    match (chars[i].1, quote) {
+                        (c, Some(q)) if c == q => quote = None,
+                        (_, Some(_)) => {}
+                        ('"' | '\'', None) => quote = Some(chars[i].1),
+                        ('(', None) => depth += 1,
+                        (')', None) => depth -= 1,
+                        _ => {}
                     }
                     i += 1;
                 }
 
                 if depth == 0 {
-                    let call_str: String = chars[start..i].iter().collect();
-                    if let Some((_, args)) = parse_callable_call(&call_str) {
-                        calls.push((start, i, name, args));
+                    let (start, end) = (offset(start), offset(i));
+                    if let Some((_, args)) = parse_callable_call(&line[start..end]) {
+                        calls.push((start, end, name, args));
                     }
                 }
             }
@@ -252,6 +269,18 @@ mod tests {
         let (name, args) = parse_callable_call("$add(1, 2)").unwrap();
         assert_eq!(name, "$add");
         assert_eq!(args, vec!["1", "2"]);
+
+        // Запятые внутри строк и вложенных вызовов не делят аргументы
+        let (name, args) = parse_callable_call(r#"Rel(a, b, "HTTP, JSON", $f(1, 2))"#).unwrap();
+        assert_eq!(name, "Rel");
+        assert_eq!(args, vec!["a", "b", "\"HTTP, JSON\"", "$f(1, 2)"]);
+    }
+
+    #[test]
+    fn test_parse_default_parameters() {
+        let func = UserCallable::function("$f", vec!["$a".to_string(), "$b = \"x\"".to_string()]);
+        assert_eq!(func.parameters, vec!["$a", "$b"]);
+        assert_eq!(func.defaults, vec![None, Some("\"x\"".to_string())]);
     }
 
     #[test]
@@ -261,7 +290,7 @@ mod tests {
 
         let (output, ret) = func.call(&["10".to_string(), "20".to_string()]);
         assert!(output.is_empty());
-        assert_eq!(ret, Some("10 + 20".to_string()));
+        assert_eq!(ret, Some("30".to_string()));
     }
 
     #[test]
@@ -278,10 +307,26 @@ mod tests {
 
     #[test]
     fn test_find_function_calls() {
-        let calls = find_function_calls("result = $add(1, 2) + $mul(3, 4)");
+        let calls = find_function_calls("result = $add(1, 2) + $mul(3, 4)", |_| false);
         assert_eq!(calls.len(), 2);
         assert_eq!(calls[0].2, "$add");
         assert_eq!(calls[1].2, "$mul");
+
+        // Встроенные функции; вложенный вызов и скобка в строке входят во внешний
+        let calls = find_function_calls(r#"%upper(%substr(")", 0)) 50%(x)"#, |_| false);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].2, "%upper");
+
+        // Позиции байтовые: перед вызовом может быть не-ASCII текст
+        let line = "итог: $f(1)";
+        let calls = find_function_calls(line, |_| false);
+        assert_eq!(&line[calls[0].0..calls[0].1], "$f(1)");
+
+        // Имена без `$` — только известные макросы и только с начала слова
+        let calls = find_function_calls("Rel(a, b) foo(1) xRel(2) $x.Rel(3)", |name| name == "Rel");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].2, "Rel");
+        assert_eq!(calls[0].3, vec!["a", "b"]);
     }
 
     #[test]
@@ -1,28 +1,56 @@
 //! # plantuml-preprocessor
 //!
 //! Препроцессор PlantUML для обработки директив:
-//! - `!include` / `!include_once`
+//! - `!include` / `!include_once` (`file!2` — выбор блока), `!includesub file!PART`,
+//!   `!includedef`, `!import archive.zip`, `!includeurl`
 //! - `!define` / `!undef`
-//! - `!ifdef` / `!ifndef` / `!else` / `!endif`
-//! - `!$variable = value`
-//! - `!function` / `!procedure`
-//! - `!theme`
-//! - `%date()`, `%version()` и другие builtin функции
-
+//! - `!ifdef` / `!ifndef` / `!if` / `!elseif` / `!else` / `!endif`
+//! - `!while` / `!endwhile`, `!foreach $item in ...` / `!endfor`
+//! - `!$variable = value` (строки, числа, JSON), `!local`, `!global`
+//! - `!function` / `!procedure` / `!return`
+//! - `!theme`, `!pragma`
+//! - `!assert`, `!log`, `!dump_memory`
+//! - `%date()`, `%version()`, `%splitstr()` и другие builtin функции
+
+mod archive;
 mod builtins;
+mod chain_resolver;
+mod diagnostics;
 mod directives;
 mod error;
+mod expression;
 mod fs_resolver;
 mod functions;
+mod memory_resolver;
+mod source_map;
+#[cfg(feature = "url")]
+mod url_resolver;
 mod variables;
 
+pub use chain_resolver::ChainResolver;
+pub use diagnostics::{Diagnostic, DiagnosticKind};
 pub use error::PreprocessError;
+pub use expression::{Environment, Expr, Value};
 pub use fs_resolver::FsFileResolver;
 pub use functions::{CallableKind, UserCallable};
+pub use memory_resolver::MemoryFileResolver;
+pub use source_map::{MacroCall, SourceLocation, SourceMap};
+#[cfg(feature = "url")]
+pub use url_resolver::{Fetcher, UrlResolver};
 pub use plantuml_themes::{SkinParams, Theme};
 
+use std::borrow::Cow;
+
 use indexmap::IndexMap;
 
+use variables::Scope;
+
+/// Максимальная глубина вложенных вызовов функций
+const MAX_CALL_DEPTH: usize = 64;
+
+/// Максимальное число итераций одного цикла `!while`
+const MAX_LOOP_ITERATIONS: usize = 10_000;
+
 /// Обрабатывает PlantUML исходный код (без поддержки !include)
 ///
 /// Это удобная обёртка над `Preprocessor::new().process(source)`.
@@ -43,6 +71,9 @@ pub fn preprocess(source: &str) -> Result<String> {
 /// Результат препроцессинга
 pub type Result<T> = std::result::Result<T, PreprocessError>;
 
+/// Наибольшая глубина вложенных `!include` по умолчанию
+pub const MAX_INCLUDE_DEPTH: usize = 10;
+
 /// Трейт для разрешения путей файлов
 pub trait FileResolver {
     /// Читает содержимое файла по пути
@@ -50,6 +81,34 @@ pub trait FileResolver {
 
     /// Проверяет существование файла
     fn file_exists(&self, path: &str) -> bool;
+
+    /// Читает двоичное содержимое файла (архивы `!import`)
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        self.read_file(path).map(String::into_bytes)
+    }
+
+    /// Наибольшая глубина вложенных `!include`
+    fn max_include_depth(&self) -> usize {
+        MAX_INCLUDE_DEPTH
+    }
+}
+
+impl<R: FileResolver + ?Sized> FileResolver for &R {
+    fn read_file(&self, path: &str) -> Result<String> {
+        (**self).read_file(path)
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        (**self).file_exists(path)
+    }
+
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        (**self).read_bytes(path)
+    }
+
+    fn max_include_depth(&self) -> usize {
+        (**self).max_include_depth()
+    }
 }
 
 /// Заглушка для FileResolver (не поддерживает !include)
@@ -80,14 +139,24 @@ enum DefiningCallable {
 /// Контекст препроцессора
 #[derive(Debug)]
 pub struct PreprocessContext {
-    /// Переменные
-    pub variables: IndexMap<String, String>,
+    /// Глобальные переменные и определения `!define`
+    pub variables: IndexMap<String, Value>,
     /// Уже включённые файлы (для !include_once)
     pub included_files: Vec<String>,
+    /// Файлы обрабатываемых `!include` от внешнего к внутреннему
+    include_stack: Vec<String>,
     /// Текущий уровень вложенности условий
     pub condition_depth: usize,
     /// Активные условия (true = выполнять код)
     pub condition_stack: Vec<bool>,
+    /// Выбрана ли уже ветвь в каждом условном блоке (для `!elseif`/`!else`)
+    branch_taken: Vec<bool>,
+    /// Локальные переменные вызовов функций (верхний — текущий вызов)
+    frames: Vec<IndexMap<String, Value>>,
+    /// Значение `!return` текущего вызова
+    returned: Option<Value>,
+    /// Путь обрабатываемого файла (для `%filename()` и `%dirpath()`)
+    pub current_file: Option<String>,
     /// Пользовательские функции и процедуры
     pub callables: IndexMap<String, functions::UserCallable>,
     /// Текущее определение функции/процедуры
@@ -96,6 +165,24 @@ pub struct PreprocessContext {
     pub theme: Theme,
     /// SkinParam параметры
     pub skin_params: SkinParams,
+    /// Значения `!pragma` (ключи в нижнем регистре)
+    pub pragmas: IndexMap<String, String>,
+    /// Сообщения `!log` и `!dump_memory`
+    pub diagnostics: Vec<Diagnostic>,
+    /// Архивы `!import`, в которых сначала ищутся включаемые файлы
+    archives: Vec<archive::Archive>,
+    /// Положения строк вывода в исходных файлах (дополняется при каждой обработке)
+    pub source_map: SourceMap,
+    /// Положение строки, обработка которой завершилась ошибкой
+    pub error_location: Option<SourceLocation>,
+    /// Файл обрабатываемых строк (внутри макроса — файл его определения)
+    origin_file: Option<String>,
+    /// Номер обрабатываемой строки в `origin_file` (с 1)
+    line: usize,
+    /// Активные вызовы макросов
+    call_stack: Vec<MacroCall>,
+    /// Положения строк вывода процедур, вызванных в текущей строке
+    expanded: Vec<SourceLocation>,
 }
 
 impl Default for PreprocessContext {
@@ -103,12 +190,26 @@ impl Default for PreprocessContext {
         Self {
             variables: IndexMap::new(),
             included_files: Vec::new(),
+            include_stack: Vec::new(),
             condition_depth: 0,
             condition_stack: Vec::new(),
+            branch_taken: Vec::new(),
+            frames: Vec::new(),
+            returned: None,
+            current_file: None,
             callables: IndexMap::new(),
             defining: DefiningCallable::None,
             theme: Theme::default(),
             skin_params: SkinParams::new(),
+            pragmas: IndexMap::new(),
+            diagnostics: Vec::new(),
+            archives: Vec::new(),
+            source_map: SourceMap::new(),
+            error_location: None,
+            origin_file: None,
+            line: 0,
+            call_stack: Vec::new(),
+            expanded: Vec::new(),
         }
     }
 }
@@ -120,18 +221,45 @@ impl PreprocessContext {
     }
 
     /// Устанавливает переменную
-    pub fn set_variable(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    pub fn set_variable(&mut self, name: impl Into<String>, value: impl Into<Value>) {
         self.variables.insert(name.into(), value.into());
     }
 
-    /// Получает значение переменной
-    pub fn get_variable(&self, name: &str) -> Option<&String> {
-        self.variables.get(name)
+    /// Получает значение переменной (локальные переменные вызова важнее глобальных)
+    pub fn get_variable(&self, name: &str) -> Option<&Value> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.get(name))
+            .or_else(|| self.variables.get(name))
     }
 
     /// Проверяет, определена ли переменная
     pub fn is_defined(&self, name: &str) -> bool {
-        self.variables.contains_key(name)
+        self.get_variable(name).is_some()
+    }
+
+    /// Присваивает переменную: `Auto` внутри функции пишет в локальные переменные
+    fn assign(&mut self, name: String, value: Value, scope: Scope) {
+        match (scope, self.frames.last_mut()) {
+            (Scope::Auto | Scope::Local, Some(frame)) => {
+                frame.insert(name, value);
+            }
+            _ => {
+                self.variables.insert(name, value);
+            }
+        }
+    }
+
+    /// Переменные, видимые в текущем вызове
+    fn visible_variables(&self) -> Cow<'_, IndexMap<String, Value>> {
+        match self.frames.last() {
+            Some(frame) if !frame.is_empty() => {
+                let mut variables = self.variables.clone();
+                variables.extend(frame.iter().map(|(k, v)| (k.clone(), v.clone())));
+                Cow::Owned(variables)
+            }
+            _ => Cow::Borrowed(&self.variables),
+        }
     }
 
     /// Проверяет, нужно ли выполнять текущий код
@@ -173,19 +301,42 @@ impl PreprocessContext {
     pub fn apply_skin_params(&mut self) {
         self.skin_params.apply_to(&mut self.theme);
     }
+
+    /// Значение `!pragma` по имени (без учёта регистра)
+    pub fn pragma(&self, name: &str) -> Option<&str> {
+        self.pragmas.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    /// Положение обрабатываемой строки
+    fn location(&self) -> SourceLocation {
+        SourceLocation {
+            file: self.origin_file.clone(),
+            line: self.line,
+            macro_stack: self.call_stack.clone(),
+        }
+    }
+
+    /// Добавляет диагностическое сообщение от текущего файла
+    fn diagnose(&mut self, kind: DiagnosticKind, message: String) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            message,
+            file: self.current_file.clone(),
+        });
+    }
 }
 
 /// Препроцессор PlantUML
 pub struct Preprocessor<R: FileResolver = NoopFileResolver> {
     resolver: R,
+    /// Переменные окружения, доступные `%getenv`
+    allowed_env: Vec<String>,
 }
 
 impl Preprocessor<NoopFileResolver> {
     /// Создаёт препроцессор без поддержки !include
     pub fn new() -> Self {
-        Self {
-            resolver: NoopFileResolver,
-        }
+        Self::with_resolver(NoopFileResolver)
     }
 }
 
@@ -198,7 +349,23 @@ impl Default for Preprocessor<NoopFileResolver> {
 impl<R: FileResolver> Preprocessor<R> {
     /// Создаёт препроцессор с заданным resolver'ом
     pub fn with_resolver(resolver: R) -> Self {
-        Self { resolver }
+        Self {
+            resolver,
+            allowed_env: Vec::new(),
+        }
+    }
+
+    /// Разрешает `%getenv` читать перечисленные переменные окружения.
+    ///
+    /// По умолчанию список пуст и `%getenv` возвращает пустую строку, чтобы
+    /// диаграмма не могла вывести секреты процесса
+    pub fn allow_env<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_env.extend(names.into_iter().map(Into::into));
+        self
     }
 
     /// Обрабатывает исходный код PlantUML
@@ -208,19 +375,56 @@ impl<R: FileResolver> Preprocessor<R> {
     }
 
     /// Обрабатывает исходный код с заданным контекстом
+    ///
+    /// Положения строк результата добавляются в `ctx.source_map`: строка N
+    /// вывода этого вызова — запись с номером `ctx.source_map.len()` до вызова + N.
     pub fn process_with_context(
         &self,
         source: &str,
         ctx: &mut PreprocessContext,
+    ) -> Result<String> {
+        let lines: Vec<&str> = source.lines().collect();
+        self.process_lines(&lines, ctx.current_file.clone(), 1, ctx)
+    }
+
+    /// Обрабатывает строки файла `file`, начиная со строки `first_line`
+    fn process_lines(
+        &self,
+        lines: &[&str],
+        file: Option<String>,
+        first_line: usize,
+        ctx: &mut PreprocessContext,
+    ) -> Result<String> {
+        let parent_file = std::mem::replace(&mut ctx.origin_file, file);
+        let parent_line = ctx.line;
+        let result = self.process_each_line(lines, first_line, ctx);
+        if result.is_err() && ctx.error_location.is_none() {
+            ctx.error_location = Some(ctx.location());
+        }
+        ctx.origin_file = parent_file;
+        ctx.line = parent_line;
+        result
+    }
+
+    /// Обрабатывает строки по одной; останавливается после `!return`
+    fn process_each_line(
+        &self,
+        lines: &[&str],
+        first_line: usize,
+        ctx: &mut PreprocessContext,
     ) -> Result<String> {
         let mut output = String::new();
+        let mut i = 0;
 
-        for line in source.lines() {
+        while i < lines.len() {
+            let line = lines[i];
             let trimmed = line.trim();
+            ctx.line = first_line + i;
+            i += 1;
 
             // Если мы определяем функцию/процедуру, собираем тело
             if ctx.is_defining_callable() {
-                if trimmed == "!endfunction" || trimmed == "!endprocedure" {
+                if matches!(trimmed, "!endfunction" | "!endprocedure" | "!enddefinelong") {
                     // Завершаем определение
                     self.finish_callable_definition(ctx)?;
                 } else {
@@ -230,43 +434,214 @@ impl<R: FileResolver> Preprocessor<R> {
                 continue;
             }
 
-            // Обработка директив препроцессора
-            if trimmed.starts_with('!') {
-                let included_content = self.process_directive_with_output(trimmed, ctx)?;
+            // Циклы: тело до парной !endwhile/!endfor выполняется целиком
+            if loop_header(trimmed).is_some() {
+                let end = find_loop_end(lines, i - 1)?;
+                if ctx.should_output() {
+                    let body = &lines[i..end];
+                    output.push_str(&self.run_loop(trimmed, body, first_line + i, ctx)?);
+                }
+                i = end + 1;
+            } else if is_directive(trimmed) {
+                // Присваивание многострочного JSON собирается в одну директиву
+                let directive = match variables::json_continuation(lines, i - 1, ctx.line)? {
+                    Some((directive, end)) => {
+                        i = end + 1;
+                        Cow::Owned(directive)
+                    }
+                    None => Cow::Borrowed(trimmed),
+                };
+
+                // Обработка директив препроцессора; вывод процедур в директивах не попадает в текст
+                let mark = ctx.expanded.len();
+                let included_content = self.process_directive_with_output(&directive, ctx)?;
+                ctx.expanded.truncate(mark);
                 if let Some(content) = included_content {
                     output.push_str(&content);
                 }
-                continue;
-            }
+            } else if ctx.should_output() {
+                // Обработка skinparam (строка применяется к теме и пропускается)
+                if trimmed.starts_with("skinparam ") {
+                    self.handle_skinparam(trimmed, ctx);
+                    continue;
+                }
 
-            // Пропускаем строки, если мы внутри ложного условия
-            if !ctx.should_output() {
-                continue;
+                let location = ctx.location();
+                let mark = ctx.expanded.len();
+                let expanded = self.expand_line(line, ctx)?;
+                let from_macros = ctx.expanded.split_off(mark);
+
+                // Строки из тела процедуры указывают на определение, остальные — на эту строку
+                let count = expanded.split('\n').count();
+                if from_macros.len() == count {
+                    ctx.source_map.extend(from_macros);
+                } else {
+                    ctx.source_map.extend(std::iter::repeat(location).take(count));
+                }
+                output.push_str(&expanded);
+                output.push('\n');
             }
 
-            // Обработка skinparam
-            if trimmed.starts_with("skinparam ") {
-                self.handle_skinparam(trimmed, ctx);
-                // Пропускаем строку (она применена к теме)
-                continue;
+            if ctx.returned.is_some() {
+                break;
             }
+        }
+
+        Ok(output)
+    }
 
-            // Подстановка переменных
-            let processed = self.substitute_variables(line, ctx);
+    /// Подставляет в строку вызовы функций (пользовательских и builtin) и переменные
+    fn expand_line(&self, line: &str, ctx: &mut PreprocessContext) -> Result<String> {
+        // Аргументы вызовов вычисляются до подстановки переменных
+        let processed = self.process_function_calls(line, ctx)?;
 
-            // Обработка вызовов пользовательских функций
-            let processed = self.process_function_calls(&processed, ctx);
+        // Подстановка переменных
+        Ok(self.substitute_variables(&processed, ctx))
+    }
 
-            // Обработка builtin функций
-            let processed = builtins::process_builtins(&processed);
+    /// Вычисляет выражение в текущем контексте
+    fn evaluate(&self, source: &str, ctx: &mut PreprocessContext) -> Result<Value> {
+        expression::evaluate(
+            source,
+            &mut Evaluation {
+                preprocessor: self,
+                ctx,
+            },
+        )
+    }
+
+    /// Вычисляет значение присваивания или `!return`; текст, не являющийся
+    /// выражением (`Hello_$name`, `Foo bar`), подставляется как строка.
+    /// Ошибки вычисления разобранного выражения (`1 / 0`, `"a" * 3`,
+    /// переполнение) возвращаются
+    fn evaluate_value(&self, source: &str, ctx: &mut PreprocessContext) -> Result<Value> {
+        let parsed = Expr::parse(source).map(|expr| {
+            expr.evaluate(&mut Evaluation {
+                preprocessor: self,
+                ctx,
+            })
+        });
+        match parsed {
+            Ok(Err(PreprocessError::UnknownVariable(_))) | Err(_) => {
+                let text = self.expand_line(source, ctx)?;
+                Ok(Value::Str(text.trim_matches('"').to_string()))
+            }
+            Ok(result) => result,
+        }
+    }
 
-            output.push_str(&processed);
-            output.push('\n');
+    /// Выполняет `!while cond` или `!foreach $item in list` над телом цикла
+    fn run_loop(
+        &self,
+        header: &str,
+        body: &[&str],
+        first_line: usize,
+        ctx: &mut PreprocessContext,
+    ) -> Result<String> {
+        let mut output = String::new();
+
+        match loop_header(header) {
+            Some(Loop::While(condition)) => {
+                let mut iterations = 0;
+                while self.evaluate(condition, ctx)?.is_truthy() {
+                    iterations += 1;
+                    if iterations > MAX_LOOP_ITERATIONS {
+                        return Err(PreprocessError::ExpressionError(format!(
+                            "!while {}: превышено {} итераций",
+                            condition, MAX_LOOP_ITERATIONS
+                        )));
+                    }
+                    output.push_str(&self.process_lines(body, ctx.origin_file.clone(), first_line, ctx)?);
+                    if ctx.returned.is_some() {
+                        break;
+                    }
+                }
+            }
+            Some(Loop::Foreach(spec)) => {
+                let (name, list) = spec.split_once(" in ").ok_or_else(|| {
+                    PreprocessError::SyntaxError(format!(
+                        "ожидается !foreach $item in ...: {}",
+                        spec
+                    ))
+                })?;
+                let name = format!("${}", name.trim().trim_start_matches('$'));
+                for item in foreach_items(self.evaluate(list, ctx)?)? {
+                    ctx.assign(name.clone(), item, Scope::Auto);
+                    output.push_str(&self.process_lines(body, ctx.origin_file.clone(), first_line, ctx)?);
+                    if ctx.returned.is_some() {
+                        break;
+                    }
+                }
+            }
+            None => {}
         }
 
         Ok(output)
     }
 
+    /// Выполняет тело функции/процедуры с локальными параметрами
+    ///
+    /// Возвращает вывод тела и значение `!return`.
+    fn invoke(
+        &self,
+        callable: &functions::UserCallable,
+        args: Vec<Value>,
+        ctx: &mut PreprocessContext,
+    ) -> Result<(String, Option<Value>)> {
+        if ctx.frames.len() >= MAX_CALL_DEPTH {
+            return Err(PreprocessError::ExpressionError(format!(
+                "{}: превышена глубина вызовов ({})",
+                callable.name, MAX_CALL_DEPTH
+            )));
+        }
+        if args.len() > callable.parameters.len() {
+            return Err(PreprocessError::ExpressionError(format!(
+                "{} ожидает не более {} аргументов, передано {}",
+                callable.name,
+                callable.parameters.len(),
+                args.len()
+            )));
+        }
+
+        // Значения по умолчанию вычисляются в контексте вызывающего
+        let mut frame = IndexMap::new();
+        let mut args = args.into_iter();
+        for (param, default) in callable.parameters.iter().zip(&callable.defaults) {
+            let value = match (args.next(), default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.evaluate_value(default, ctx)?,
+                (None, None) => Value::default(),
+            };
+            frame.insert(param.clone(), value);
+        }
+
+        // Незакрытые в теле условия не должны влиять на вызывающий код
+        let depth = ctx.condition_stack.len();
+        ctx.frames.push(frame);
+        ctx.call_stack.push(MacroCall {
+            name: callable.name.clone(),
+            file: ctx.origin_file.clone(),
+            line: ctx.line,
+        });
+        let mark = ctx.source_map.len();
+        let lines: Vec<&str> = callable.body.iter().map(String::as_str).collect();
+        let output = self.process_lines(&lines, callable.file.clone(), callable.line, ctx);
+
+        // Строки вывода процедуры подставляются в строку вызова
+        let body_locations = ctx.source_map.split_off(mark);
+        if callable.kind == functions::CallableKind::Procedure {
+            ctx.expanded.extend(body_locations);
+        }
+        ctx.call_stack.pop();
+        ctx.frames.pop();
+        ctx.condition_stack.truncate(depth);
+        ctx.branch_taken.truncate(depth);
+        ctx.condition_depth = depth;
+        let returned = ctx.returned.take();
+
+        Ok((output?, returned))
+    }
+
     /// Обрабатывает директиву препроцессора (без возврата контента)
     #[allow(dead_code)]
     fn process_directive(&self, line: &str, ctx: &mut PreprocessContext) -> Result<()> {
@@ -290,57 +665,274 @@ impl<R: FileResolver> Preprocessor<R> {
             directives::handle_ifdef(rest.trim(), ctx, true);
         } else if let Some(rest) = directive.strip_prefix("ifndef ") {
             directives::handle_ifdef(rest.trim(), ctx, false);
+        } else if let Some(rest) = directive.strip_prefix("if ") {
+            let condition = ctx.should_output() && self.evaluate(rest, ctx)?.is_truthy();
+            directives::handle_if(condition, ctx);
+        } else if let Some(rest) = directive.strip_prefix("elseif ") {
+            let condition =
+                directives::elseif_pending(ctx)? && self.evaluate(rest, ctx)?.is_truthy();
+            directives::handle_elseif(condition, ctx)?;
         } else if directive == "else" {
             directives::handle_else(ctx)?;
         } else if directive == "endif" {
             directives::handle_endif(ctx)?;
+        } else if matches!(
+            directive,
+            "endwhile" | "endfor" | "endfunction" | "endprocedure" | "enddefinelong"
+        ) {
+            // Лишняя закрывающая директива пропускается с предупреждением
+            if ctx.should_output() {
+                let message = format!("!{} без открывающей директивы пропущена", directive);
+                ctx.diagnose(DiagnosticKind::Warning, message);
+            }
+        } else if directive == "return" || directive.starts_with("return ") {
+            self.handle_return(directive["return".len()..].trim(), ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("local ") {
+            self.handle_assignment(rest, Scope::Local, ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("global ") {
+            self.handle_assignment(rest, Scope::Global, ctx)?;
         } else if let Some(rest) = directive.strip_prefix("include ") {
-            return self.handle_include(rest.trim(), ctx, false);
+            return self.handle_include(rest.trim(), ctx, Inclusion::Always);
+        } else if let Some(rest) = directive.strip_prefix("include_many ") {
+            return self.handle_include(rest.trim(), ctx, Inclusion::Always);
         } else if let Some(rest) = directive.strip_prefix("include_once ") {
-            return self.handle_include(rest.trim(), ctx, true);
+            return self.handle_include(rest.trim(), ctx, Inclusion::Once);
+        } else if let Some(rest) = directive.strip_prefix("includeurl ") {
+            return self.handle_include(rest.trim(), ctx, Inclusion::Always);
+        } else if let Some(rest) = directive.strip_prefix("includesub ") {
+            return self.handle_include(rest.trim(), ctx, Inclusion::Sub);
+        } else if let Some(rest) = directive.strip_prefix("includedef ") {
+            return self.handle_include(rest.trim(), ctx, Inclusion::Definitions);
+        } else if let Some(rest) = directive.strip_prefix("import ") {
+            self.handle_import(rest.trim(), ctx)?;
+        } else if directive.starts_with("startsub ") || directive == "endsub" {
+            // Границы секций для !includesub; в самом файле ничего не выводят
+        } else if let Some(rest) = directive.strip_prefix("assert ") {
+            self.handle_assert(rest, ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("log ") {
+            if ctx.should_output() {
+                let message = self.expand_line(rest.trim(), ctx)?;
+                ctx.diagnose(DiagnosticKind::Log, message);
+            }
+        } else if directive == "dump_memory" || directive.starts_with("dump_memory ") {
+            self.handle_dump_memory(directive["dump_memory".len()..].trim(), ctx);
+        } else if let Some(rest) = directive.strip_prefix("pragma ") {
+            directives::handle_pragma(rest, ctx);
         } else if let Some(rest) = directive.strip_prefix("function ") {
             self.start_function_definition(rest.trim(), ctx)?;
         } else if let Some(rest) = directive.strip_prefix("procedure ") {
             self.start_procedure_definition(rest.trim(), ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("definelong ") {
+            // Устаревший многострочный макрос: процедура до !enddefinelong
+            self.start_procedure_definition(rest.trim(), ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("unquoted ") {
+            // Аргументы без кавычек принимаются любыми вызовами, отдельного режима нет
+            let rest = rest.trim_start();
+            if let Some(def) = rest.strip_prefix("function ") {
+                self.start_function_definition(def.trim(), ctx)?;
+            } else if let Some(def) = rest.strip_prefix("procedure ") {
+                self.start_procedure_definition(def.trim(), ctx)?;
+            } else {
+                return Err(PreprocessError::SyntaxError(format!(
+                    "ожидается !unquoted function или !unquoted procedure: !{}",
+                    directive
+                )));
+            }
         } else if let Some(rest) = directive.strip_prefix("theme ") {
             self.handle_theme(rest.trim(), ctx)?;
         } else if directive.starts_with('$') {
             // Переменная: !$var = value
-            variables::handle_variable_assignment(directive, ctx)?;
+            self.handle_assignment(directive, Scope::Auto, ctx)?;
+        } else if ctx.should_output() {
+            return Err(PreprocessError::SyntaxError(format!(
+                "неизвестная директива: !{}",
+                directive
+            )));
         }
 
         Ok(None)
     }
 
-    /// Обрабатывает !include и возвращает обработанный контент
+    /// Обрабатывает `!include`, `!include_once`, `!includesub` и `!includedef`;
+    /// возвращает обработанный контент
     fn handle_include(
         &self,
-        path: &str,
+        spec: &str,
         ctx: &mut PreprocessContext,
-        once: bool,
+        inclusion: Inclusion,
     ) -> Result<Option<String>> {
         if !ctx.should_output() {
             return Ok(None);
         }
 
-        let path = path.trim_matches(|c| c == '<' || c == '>' || c == '"');
+        // Угловые скобки сохраняются: по ним resolver отличает стандартную библиотеку
+        let (path, selector) = directives::split_selector(spec);
+        let path = path.trim_matches('"');
+        let key = match selector {
+            Some(selector) => format!("{}!{}", path, selector),
+            None => path.to_string(),
+        };
 
-        if once && ctx.included_files.contains(&path.to_string()) {
+        if inclusion == Inclusion::Once && ctx.included_files.contains(&key) {
             return Ok(None);
         }
 
-        let content = self.resolver.read_file(path)?;
-        ctx.included_files.push(path.to_string());
+        // Файл, который уже обрабатывается, включил бы себя бесконечно
+        let max_depth = self.resolver.max_include_depth();
+        let cycle = ctx.include_stack.contains(&key);
+        if cycle || ctx.include_stack.len() >= max_depth {
+            let mut chain = ctx.include_stack.join(" -> ");
+            chain.push_str(if chain.is_empty() { "" } else { " -> " });
+            chain.push_str(&key);
+            return Err(PreprocessError::RecursiveInclude(if cycle {
+                chain
+            } else {
+                format!("превышена глубина включений {}: {}", max_depth, chain)
+            }));
+        }
+
+        let content = self.read_source(path, ctx)?;
+        let (first_line, content) = match (inclusion, selector) {
+            (Inclusion::Sub, None) => {
+                return Err(PreprocessError::SyntaxError(format!(
+                    "!includesub {}: ожидается file!PART",
+                    spec
+                )))
+            }
+            (Inclusion::Sub, Some(part)) => directives::extract_sub(&content, part)
+                .ok_or_else(|| PreprocessError::FileNotFound(format!("{} (!startsub)", key)))?,
+            (_, Some(block)) => directives::select_block(&content, block)
+                .ok_or_else(|| PreprocessError::FileNotFound(format!("{} (блок)", key)))?,
+            (_, None) => (1, content),
+        };
+        ctx.included_files.push(key.clone());
 
         // Рекурсивная обработка включённого файла
-        let processed = self.process_with_context(&content, ctx)?;
+        let parent = ctx.current_file.replace(path.to_string());
+        let mark = ctx.source_map.len();
+        let lines: Vec<&str> = content.lines().collect();
+        ctx.include_stack.push(key);
+        let processed = self.process_lines(&lines, Some(path.to_string()), first_line, ctx);
+        ctx.include_stack.pop();
+        ctx.current_file = parent;
+
+        // !includedef оставляет только определения, текст файла отбрасывается
+        match inclusion {
+            Inclusion::Definitions => {
+                ctx.source_map.truncate(mark);
+                processed.map(|_| None)
+            }
+            _ => processed.map(Some),
+        }
+    }
+
+    /// Читает включаемый файл: сначала из архивов `!import`, затем через resolver
+    fn read_source(&self, path: &str, ctx: &PreprocessContext) -> Result<String> {
+        match ctx.archives.iter().find_map(|archive| archive.get(path)) {
+            Some(content) => Ok(content.to_string()),
+            None => self.resolver.read_file(path),
+        }
+    }
+
+    /// Обрабатывает `!import archive.zip`: файлы архива становятся доступны для `!include`
+    fn handle_import(&self, path: &str, ctx: &mut PreprocessContext) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+
+        let path = path.trim_matches('"');
+        let data = self.resolver.read_bytes(path)?;
+        ctx.archives.push(archive::Archive::parse(path, &data)?);
+
+        Ok(())
+    }
+
+    /// Обрабатывает `!assert condition : message`
+    fn handle_assert(&self, rest: &str, ctx: &mut PreprocessContext) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+
+        let (condition, message) = directives::split_assert(rest);
+        if self.evaluate(condition, ctx)?.is_truthy() {
+            return Ok(());
+        }
+        let message = match message {
+            Some(message) => self.evaluate_value(message, ctx)?.to_string(),
+            None => condition.to_string(),
+        };
+
+        Err(PreprocessError::AssertionFailed(message))
+    }
+
+    /// Обрабатывает `!dump_memory [label]`: снимок переменных и функций в диагностику
+    fn handle_dump_memory(&self, label: &str, ctx: &mut PreprocessContext) {
+        if !ctx.should_output() {
+            return;
+        }
+
+        let variables: serde_json::Map<String, serde_json::Value> = ctx
+            .visible_variables()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_json()))
+            .collect();
+        let dump = serde_json::json!({
+            "variables": variables,
+            "functions": ctx.callables.keys().collect::<Vec<_>>(),
+        });
+        let message = if label.is_empty() {
+            dump.to_string()
+        } else {
+            format!("{}: {}", label, dump)
+        };
+        ctx.diagnose(DiagnosticKind::MemoryDump, message);
+    }
+
+    /// Обрабатывает присваивание `$var = value` в заданной области видимости
+    fn handle_assignment(
+        &self,
+        directive: &str,
+        scope: Scope,
+        ctx: &mut PreprocessContext,
+    ) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
 
-        Ok(Some(processed))
+        let assignment = variables::parse_assignment(directive)?;
+        if assignment.if_undefined && ctx.is_defined(&assignment.name) {
+            return Ok(());
+        }
+        let value = self.evaluate_value(assignment.value, ctx)?;
+        ctx.assign(assignment.name, value, scope);
+
+        Ok(())
+    }
+
+    /// Обрабатывает `!return value`: завершает текущий вызов
+    fn handle_return(&self, value: &str, ctx: &mut PreprocessContext) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+        if ctx.frames.is_empty() {
+            return Err(PreprocessError::SyntaxError(
+                "!return вне функции или процедуры".to_string(),
+            ));
+        }
+
+        let value = if value.is_empty() {
+            Value::default()
+        } else {
+            self.evaluate_value(value, ctx)?
+        };
+        ctx.returned = Some(value);
+
+        Ok(())
     }
 
     /// Подставляет переменные в строку
     fn substitute_variables(&self, line: &str, ctx: &PreprocessContext) -> String {
-        variables::substitute(line, &ctx.variables)
+        variables::substitute(line, &ctx.visible_variables())
     }
 
     /// Обрабатывает !theme
@@ -385,7 +977,9 @@ impl<R: FileResolver> Preprocessor<R> {
             PreprocessError::SyntaxError(format!("неверный формат определения функции: {}", def))
         })?;
 
-        let callable = functions::UserCallable::function(name, params);
+        let mut callable = functions::UserCallable::function(name, params);
+        callable.file = ctx.origin_file.clone();
+        callable.line = ctx.line + 1;
         ctx.defining = DefiningCallable::Function(callable);
 
         Ok(())
@@ -401,7 +995,9 @@ impl<R: FileResolver> Preprocessor<R> {
             PreprocessError::SyntaxError(format!("неверный формат определения процедуры: {}", def))
         })?;
 
-        let callable = functions::UserCallable::procedure(name, params);
+        let mut callable = functions::UserCallable::procedure(name, params);
+        callable.file = ctx.origin_file.clone();
+        callable.line = ctx.line + 1;
         ctx.defining = DefiningCallable::Procedure(callable);
 
         Ok(())
@@ -437,37 +1033,209 @@ impl<R: FileResolver> Preprocessor<R> {
         Ok(())
     }
 
-    /// Обрабатывает вызовы пользовательских функций в строке
-    fn process_function_calls(&self, line: &str, ctx: &PreprocessContext) -> String {
-        let calls = functions::find_function_calls(line);
+    /// Обрабатывает вызовы пользовательских и builtin функций в строке
+    fn process_function_calls(&self, line: &str, ctx: &mut PreprocessContext) -> Result<String> {
+        let calls = functions::find_function_calls(line, |name| ctx.get_callable(name).is_some());
 
         if calls.is_empty() {
-            return line.to_string();
+            return Ok(line.to_string());
         }
 
         let mut result = line.to_string();
 
         // Обрабатываем вызовы в обратном порядке (чтобы не сбивались индексы)
         for (start, end, name, args) in calls.into_iter().rev() {
-            if let Some(callable) = ctx.get_callable(&name) {
-                let (output_lines, return_value) = callable.call(&args);
-
-                let replacement = match callable.kind {
-                    functions::CallableKind::Function => {
-                        // Функция: подставляем возвращённое значение
-                        return_value.unwrap_or_default()
-                    }
-                    functions::CallableKind::Procedure => {
-                        // Процедура: подставляем вывод
-                        output_lines.join("\n")
+            if builtins::is_builtin(&name) {
+                let value = self.evaluate(&line[start..end], ctx)?;
+                result.replace_range(start..end, &value.to_text());
+                continue;
+            }
+            let Some(callable) = ctx.get_callable(&name).cloned() else {
+                continue;
+            };
+
+            // Аргументы — выражения; не вычисляемые аргументы (`user`, `Hello world`)
+            // передаются как текст
+            let args = args
+                .iter()
+                .map(|arg| match self.evaluate(arg, ctx) {
+                    Err(PreprocessError::ExpressionError(_) | PreprocessError::UnknownVariable(_)) => {
+                        Ok(Value::from_text(arg.trim_matches('"')))
                     }
-                };
+                    result => result,
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let (output, return_value) = self.invoke(&callable, args, ctx)?;
+
+            let replacement = match callable.kind {
+                functions::CallableKind::Function => {
+                    // Функция: подставляем возвращённое значение
+                    return_value.unwrap_or_default().to_text()
+                }
+                functions::CallableKind::Procedure => {
+                    // Процедура: подставляем вывод
+                    output.trim_end_matches('\n').to_string()
+                }
+            };
+
+            result.replace_range(start..end, &replacement);
+        }
+
+        Ok(result)
+    }
+}
+
+/// Строка-директива: `!` и имя (`!include`, `!$var = ...`); прочие строки
+/// с `!` в начале (`! текст`, `!= x`) обрабатываются как обычный текст
+fn is_directive(line: &str) -> bool {
+    line.strip_prefix('!')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+}
+
+/// Вариант директивы включения файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inclusion {
+    /// `!include`, `!includeurl`
+    Always,
+    /// `!include_once`
+    Once,
+    /// `!includesub file!PART`
+    Sub,
+    /// `!includedef`: только определения, без текста
+    Definitions,
+}
+
+/// Заголовок цикла
+enum Loop<'a> {
+    /// `!while условие`
+    While(&'a str),
+    /// `!foreach $item in список`
+    Foreach(&'a str),
+}
+
+fn loop_header(line: &str) -> Option<Loop<'_>> {
+    if let Some(condition) = line.strip_prefix("!while ") {
+        Some(Loop::While(condition.trim()))
+    } else {
+        line.strip_prefix("!foreach ")
+            .map(|spec| Loop::Foreach(spec.trim()))
+    }
+}
 
-                result = format!("{}{}{}", &result[..start], replacement, &result[end..]);
+/// Индекс строки с парной `!endwhile`/`!endfor` для цикла в строке `start`
+fn find_loop_end(lines: &[&str], start: usize) -> Result<usize> {
+    let mut expected = Vec::new();
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let trimmed = line.trim();
+        match loop_header(trimmed) {
+            Some(Loop::While(_)) => expected.push("!endwhile"),
+            Some(Loop::Foreach(_)) => expected.push("!endfor"),
+            None if trimmed == "!endwhile" || trimmed == "!endfor" => {
+                if expected.pop() != Some(trimmed) {
+                    return Err(PreprocessError::SyntaxError(format!(
+                        "{} не соответствует открытому циклу",
+                        trimmed
+                    )));
+                }
+                if expected.is_empty() {
+                    return Ok(i);
+                }
             }
+            None => {}
         }
+    }
+    Err(PreprocessError::SyntaxError(format!(
+        "нет закрывающей {} для '{}'",
+        expected.last().copied().unwrap_or("!endwhile"),
+        lines[start].trim()
+    )))
+}
 
-        result
+/// Элементы `!foreach`: JSON массив или список через запятую
+fn foreach_items(list: Value) -> Result<Vec<Value>> {
+    match list {
+        Value::Json(serde_json::Value::Array(items)) => {
+            Ok(items.into_iter().map(Value::from_json).collect())
+        }
+        Value::Json(other) => Err(PreprocessError::ExpressionError(format!(
+            "!foreach ожидает массив, получено {}",
+            other
+        ))),
+        Value::Str(text) if text.trim().is_empty() => Ok(Vec::new()),
+        Value::Str(text) => Ok(text
+            .split(',')
+            .map(|item| Value::from_text(item.trim()))
+            .collect()),
+        value => Ok(vec![value]),
+    }
+}
+
+/// Окружение вычисления выражений поверх контекста препроцессора
+struct Evaluation<'a, R: FileResolver> {
+    preprocessor: &'a Preprocessor<R>,
+    ctx: &'a mut PreprocessContext,
+}
+
+impl<R: FileResolver> Environment for Evaluation<'_, R> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.ctx.get_variable(name).cloned()
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        if let Some(callable) = self.ctx.get_callable(name).cloned() {
+            let (output, returned) = self.preprocessor.invoke(&callable, args, self.ctx)?;
+            return Ok(match callable.kind {
+                functions::CallableKind::Function => returned.unwrap_or_default(),
+                functions::CallableKind::Procedure => {
+                    Value::Str(output.trim_end_matches('\n').to_string())
+                }
+            });
+        }
+        builtins::call_builtin(name, &args, self)
+    }
+}
+
+impl<R: FileResolver> builtins::BuiltinContext for Evaluation<'_, R> {
+    fn get_variable(&self, name: &str) -> Option<Value> {
+        self.variable(name)
+            .or_else(|| self.variable(&format!("${}", name)))
+    }
+
+    fn set_variable(&mut self, name: &str, value: Value) {
+        self.ctx.assign(name.to_string(), value, Scope::Global);
+    }
+
+    fn callable_exists(&self, name: &str) -> bool {
+        self.ctx.get_callable(name).is_some()
+    }
+
+    fn call_user(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        if !self.callable_exists(name) {
+            return Err(PreprocessError::ExpressionError(format!(
+                "неизвестная функция: {}",
+                name
+            )));
+        }
+        self.call(name, args)
+    }
+
+    fn read_file(&self, path: &str) -> Result<String> {
+        self.preprocessor.resolver.read_file(path)
+    }
+
+    fn current_file(&self) -> Option<&str> {
+        self.ctx.current_file.as_deref()
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.preprocessor
+            .allowed_env
+            .iter()
+            .any(|allowed| allowed == name)
+            .then(|| std::env::var(name).ok())
+            .flatten()
     }
 }
 
@@ -543,6 +1311,30 @@ $box("MyBox")
         assert!(result.contains("rectangle \"MyBox\""));
     }
 
+    #[test]
+    fn test_unquoted_procedure() {
+        let result = preprocess("!unquoted procedure Foo($a)\nX $a\n!endprocedure\nFoo(1)\n").unwrap();
+        assert_eq!(result, "X 1\n");
+
+        // Аргументы без кавычек — текст, запятые в строках не делят аргументы;
+        // имя без `$` совпадает только целым словом
+        let source = r#"
+!unquoted procedure Rel($from, $to, $label="")
+$from --> $to : $label
+!endprocedure
+Rel(user, web, "HTTP, JSON")
+xRel(a, b)
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("user --> web : HTTP, JSON"));
+        assert!(result.contains("xRel(a, b)"));
+
+        assert!(matches!(
+            preprocess("!unquoted Foo($a)\n"),
+            Err(PreprocessError::SyntaxError(_))
+        ));
+    }
+
     #[test]
     fn test_function_with_multiple_params() {
         let preprocessor = Preprocessor::new();
@@ -556,6 +1348,470 @@ output: $format("[", "test", "]")
         assert!(result.contains("output: [test]"));
     }
 
+    #[test]
+    fn test_if_elseif_else() {
+        let source = r#"
+!$env = "staging"
+!if $env == "prod"
+PROD
+!elseif $env == "staging" && %strlen($env) > 3
+STAGING
+!else
+OTHER
+!endif
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("STAGING"));
+        assert!(!result.contains("PROD"));
+        assert!(!result.contains("OTHER"));
+    }
+
+    #[test]
+    fn test_if_skips_inactive_branch_errors() {
+        // Условие !elseif после выбранной ветви не вычисляется
+        let source = "!if 1
+A
+!elseif $undefined > 1
+B
+!endif
+";
+        assert_eq!(
+            preprocess(source).unwrap(),
+            "A
+"
+        );
+
+        let err = preprocess(
+            "!if $undefined
+!endif
+",
+        )
+        .unwrap_err();
+        assert!(matches!(err, PreprocessError::UnknownVariable(_)));
+    }
+
+    #[test]
+    fn test_unknown_directive() {
+        let err = preprocess("A -> B\n!frobnicate x\n").unwrap_err();
+        assert!(matches!(err, PreprocessError::SyntaxError(m) if m.contains("!frobnicate x")));
+        assert!(preprocess("!frobnicate\n").is_err());
+        assert!(preprocess("!_x\n").is_err());
+
+        // В неактивной ветви директива не проверяется
+        assert_eq!(preprocess("!if 0\n!frobnicate\n!endif\nA\n").unwrap(), "A\n");
+
+        // `!` без имени директивы — обычный текст
+        assert_eq!(
+            preprocess("! not a directive\n!= x\n!\n").unwrap(),
+            "! not a directive\n!= x\n!\n"
+        );
+
+        let files = MemoryFileResolver::new().with_file("a.puml", "A");
+        let result = Preprocessor::with_resolver(files)
+            .process("!include_many a.puml\n!include_many a.puml\n")
+            .unwrap();
+        assert_eq!(result.matches('A').count(), 2);
+    }
+
+    #[test]
+    fn test_stray_end_directives_warn() {
+        let source = "A\n!endfunction\n!endprocedure\n!enddefinelong\n!endwhile\n!endfor\nB\n!if 0\n!endfor\n!endif\n";
+        let mut ctx = PreprocessContext::new();
+        let result = Preprocessor::new()
+            .process_with_context(source, &mut ctx)
+            .unwrap();
+        assert_eq!(result, "A\nB\n");
+        let warnings: Vec<_> = ctx
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (DiagnosticKind::Warning, "!endfunction без открывающей директивы пропущена"),
+                (DiagnosticKind::Warning, "!endprocedure без открывающей директивы пропущена"),
+                (DiagnosticKind::Warning, "!enddefinelong без открывающей директивы пропущена"),
+                (DiagnosticKind::Warning, "!endwhile без открывающей директивы пропущена"),
+                (DiagnosticKind::Warning, "!endfor без открывающей директивы пропущена"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_definelong() {
+        let source = r#"
+!definelong CONNECT(from, to)
+from -> to : request
+to --> from : response
+!enddefinelong
+CONNECT(Alice, Bob)
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("Alice -> Bob : request\nBob --> Alice : response\n"));
+        assert!(!result.contains("definelong"));
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let source = r#"
+!$i = 0
+!while $i < 3
+node_$i
+!$i = $i + 1
+!endwhile
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("node_0\nnode_1\nnode_2\n"));
+        assert!(!result.contains("node_3"));
+
+        let err = preprocess(
+            "!while 1
+!endwhile
+",
+        )
+        .unwrap_err();
+        assert!(matches!(err, PreprocessError::ExpressionError(_)));
+        assert!(preprocess(
+            "!while 1
+A
+"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_foreach_loop() {
+        let source = r#"
+!foreach $name in ["Alice", "Bob"]
+!foreach $n in "1, 2"
+participant $name$n
+!endfor
+!endfor
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("participant Alice1\nparticipant Alice2\nparticipant Bob1"));
+        assert!(result.contains("participant Bob2"));
+    }
+
+    #[test]
+    fn test_function_return_expression() {
+        let source = r#"
+!function $fact($n)
+!if $n <= 1
+!return 1
+!endif
+!return $n * $fact($n - 1)
+!endfunction
+!function $label($text, $suffix = "!")
+!return %upper($text) + $suffix
+!endfunction
+value: $fact(5) $label("ok")
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("value: 120 OK!"));
+    }
+
+    #[test]
+    fn test_arithmetic_errors() {
+        for source in ["!$x = 1 / 0\n", "!$x = 5 % 0\n", "!$x = \"a\" * 3\n"] {
+            assert!(
+                matches!(preprocess(source), Err(PreprocessError::ExpressionError(_))),
+                "{}",
+                source
+            );
+        }
+        let err = preprocess("!function $big($n)\n!return $n * $n\n!endfunction\n$big(4294967296)\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("переполнение"));
+
+        // Текст, не являющийся выражением, по-прежнему подставляется как строка
+        let result = preprocess("!$x = Foo bar\n!$y = Hi $x\n$y\n").unwrap();
+        assert_eq!(result.trim(), "Hi Foo bar");
+    }
+
+    #[test]
+    fn test_local_and_global() {
+        let source = r#"
+!$count = 0
+!$tmp = "global"
+!procedure $inc()
+!local $tmp = "local"
+!global $count = $count + 1
+!endprocedure
+$inc()
+$inc()
+count: $count tmp: $tmp
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("count: 2 tmp: global"));
+
+        assert!(preprocess(
+            "!return 1
+"
+        )
+        .is_err());
+        assert!(preprocess(
+            "!$x ?= 1
+!$x ?= 2
+x=$x
+"
+        )
+        .unwrap()
+        .contains("x=1"));
+    }
+
+    #[test]
+    fn test_json_variable_foreach() {
+        let source = r#"
+!$inventory = {
+  "env": "prod",
+  "servers": [
+    {"name": "web", "port": 80},
+    {"name": "db", "port": 5432}
+  ]
+}
+!foreach $server in $inventory.servers
+!if $server.port > 1000
+node "$server.name:$server.port" <<internal>>
+!else
+node "$server.name:$server.port"
+!endif
+!endfor
+note: $inventory.servers[1].name in $inventory.env
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("node \"web:80\"\n"));
+        assert!(result.contains("node \"db:5432\" <<internal>>"));
+        assert!(result.contains("note: db in prod"));
+
+        let mut ctx = PreprocessContext::new();
+        Preprocessor::new()
+            .process_with_context("!$list = [1, 2]\n!$n = 3\n", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.get_variable("$n"), Some(&Value::Int(3)));
+        assert!(matches!(ctx.get_variable("$list"), Some(Value::Json(_))));
+
+        let err = preprocess("A -> B\n!$a = [1,\nC -> D\n").unwrap_err();
+        assert!(matches!(err, PreprocessError::SyntaxError(m) if m.starts_with("строка 2:")));
+    }
+
+    #[test]
+    fn test_load_json() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("hosts.json"),
+            r#"{"hosts": ["alpha", "beta"]}"#,
+        )
+        .unwrap();
+
+        let source = r#"
+!$data = %load_json("hosts.json")
+!foreach $host in $data.hosts
+node $host
+!endfor
+count: %size($data.hosts)
+"#;
+        let preprocessor = Preprocessor::with_resolver(FsFileResolver::new(temp_dir.path()));
+        let result = preprocessor.process(source).unwrap();
+        assert!(result.contains("node alpha\nnode beta\n"));
+        assert!(result.contains("count: 2"));
+
+        let err = preprocessor
+            .process("!$x = %load_json(\"missing.json\")")
+            .unwrap_err();
+        assert!(matches!(err, PreprocessError::FileNotFound(_)));
+    }
+
+    #[test]
+    fn test_assert_log_and_dump_memory() {
+        let source = r#"
+!$count = 2
+!assert $count == 2 : "count is " + $count
+!log processing $count items
+!function $twice($x)
+!return $x * 2
+!endfunction
+!dump_memory state
+!if 0
+!assert 0 : skipped
+!log skipped
+!endif
+"#;
+        let mut ctx = PreprocessContext::new();
+        Preprocessor::new()
+            .process_with_context(source, &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.diagnostics.len(), 2);
+        assert_eq!(ctx.diagnostics[0].kind, DiagnosticKind::Log);
+        assert_eq!(ctx.diagnostics[0].message, "processing 2 items");
+        assert_eq!(ctx.diagnostics[1].kind, DiagnosticKind::MemoryDump);
+        assert!(ctx.diagnostics[1].message.starts_with("state: {"));
+        assert!(ctx.diagnostics[1].message.contains("\"$count\":2"));
+        assert!(ctx.diagnostics[1].message.contains("\"$twice\""));
+
+        let err = preprocess("!$count = 3\n!assert $count == 2 : \"count is \" + $count\n")
+            .unwrap_err();
+        assert!(matches!(err, PreprocessError::AssertionFailed(ref m) if m == "count is 3"));
+        let err = preprocess("!assert %strlen(\"ab\") > 5\n").unwrap_err();
+        assert_eq!(err.to_string(), "!assert: %strlen(\"ab\") > 5");
+    }
+
+    #[test]
+    fn test_pragma() {
+        let mut ctx = PreprocessContext::new();
+        let result = Preprocessor::new()
+            .process_with_context("!pragma teoz true\n!pragma Layout elk\n!pragma flag\nA -> B\n", &mut ctx)
+            .unwrap();
+        assert_eq!(result, "A -> B\n");
+        assert_eq!(ctx.pragma("teoz"), Some("true"));
+        assert_eq!(ctx.pragma("layout"), Some("elk"));
+        assert_eq!(ctx.pragma("flag"), Some(""));
+        assert_eq!(ctx.pragma("missing"), None);
+    }
+
+    #[test]
+    fn test_include_block_sub_and_def() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("multi.puml"),
+            "@startuml\nA -> B\n@enduml\n@startuml(id=extra)\nC -> D\n@enduml\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("parts.puml"),
+            "!startsub NODES\nnode web\n!endsub\nnode other\n!startsub NODES\nnode db\n!endsub\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("defs.puml"),
+            "!$color = \"red\"\nignored text\n",
+        )
+        .unwrap();
+
+        let preprocessor = Preprocessor::with_resolver(FsFileResolver::new(temp_dir.path()));
+        let result = preprocessor
+            .process("!include multi.puml!1\n!include multi.puml!0\n!include_once multi.puml!1\n!include multi.puml!extra\n")
+            .unwrap();
+        assert_eq!(result, "C -> D\nA -> B\nC -> D\n");
+
+        let result = preprocessor.process("!includesub parts.puml!NODES\n").unwrap();
+        assert_eq!(result, "node web\nnode db\n");
+
+        let result = preprocessor
+            .process("!includedef defs.puml\ncolor: $color\n")
+            .unwrap();
+        assert_eq!(result, "color: red\n");
+
+        assert!(matches!(
+            preprocessor.process("!include multi.puml!5"),
+            Err(PreprocessError::FileNotFound(_))
+        ));
+        assert!(matches!(
+            preprocessor.process("!includesub parts.puml"),
+            Err(PreprocessError::SyntaxError(_))
+        ));
+    }
+
+    #[test]
+    fn test_import_archive() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let data = archive::build_zip(&[
+            ("lib/style.puml", "!$shape = \"node\"\n"),
+            ("lib/parts.puml", "!startsub MAIN\n$shape main\n!endsub\n"),
+        ]);
+        std::fs::write(temp_dir.path().join("lib.zip"), data).unwrap();
+
+        let preprocessor = Preprocessor::with_resolver(FsFileResolver::new(temp_dir.path()));
+        let result = preprocessor
+            .process("!import lib.zip\n!include lib/style.puml\n!includesub lib/parts.puml!MAIN\n")
+            .unwrap();
+        assert_eq!(result, "node main\n");
+
+        assert!(preprocessor.process("!import missing.zip").is_err());
+    }
+
+    #[test]
+    fn test_memory_and_chain_resolvers() {
+        let memory = MemoryFileResolver::new()
+            .with_file("lib/common.puml", "!include_once style.puml\nA -> B")
+            .with_file("lib/style.puml", "!$color = \"red\"")
+            .with_base_dir("lib");
+        let fallback = MemoryFileResolver::new().with_file("extra.puml", "C -> D");
+        let preprocessor =
+            Preprocessor::with_resolver(ChainResolver::new().with(memory).with(fallback));
+
+        let result = preprocessor
+            .process("!include common.puml\n!include extra.puml\ncolor: $color\n")
+            .unwrap();
+        assert_eq!(result, "A -> B\nC -> D\ncolor: red\n");
+
+        // Resolver можно передать по ссылке
+        let memory = MemoryFileResolver::new().with_file("a.puml", "A");
+        assert_eq!(Preprocessor::with_resolver(&memory).process("!include a.puml").unwrap(), "A\n");
+    }
+
+    #[test]
+    fn test_source_map() {
+        let resolver = MemoryFileResolver::new()
+            .with_file(
+                "common.puml",
+                "' общий файл\n!procedure $pair($a, $b)\n$a -> $b\n$b -> $a\n!endprocedure\nX -> Y",
+            )
+            .with_file("multi.puml", "@startuml\nA -> B\n@enduml\n@startuml\nC -> D\nE -> F\n@enduml");
+        let preprocessor = Preprocessor::with_resolver(resolver);
+        let mut ctx = PreprocessContext::new();
+        let source = "!include common.puml\n!foreach $n in [\"P\", \"Q\"]\n$n -> R\n!endfor\n$pair(M, N)\n!include multi.puml!1\n";
+        let result = preprocessor.process_with_context(source, &mut ctx).unwrap();
+        assert_eq!(result, "' общий файл\nX -> Y\nP -> R\nQ -> R\nM -> N\nN -> M\nC -> D\nE -> F\n");
+
+        let positions: Vec<String> = ctx.source_map.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            positions,
+            [
+                "в common.puml:1",
+                "в common.puml:6",
+                "в строке 3",
+                "в строке 3",
+                "в common.puml:3, макрос $pair вызван в строке 5",
+                "в common.puml:4, макрос $pair вызван в строке 5",
+                "в multi.puml:5",
+                "в multi.puml:6",
+            ]
+        );
+
+        let mut ctx = PreprocessContext::new();
+        let source = "!procedure $fail()\n!assert 1 == 2 : сломано\n!endprocedure\nA -> B\n$fail()\n";
+        assert!(preprocessor.process_with_context(source, &mut ctx).is_err());
+        assert_eq!(
+            ctx.error_location.map(|location| location.to_string()).as_deref(),
+            Some("в строке 2, макрос $fail вызван в строке 5")
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_includeurl() {
+        let resolver = UrlResolver::new(|url: &str| Ok(format!("' from {}", url).into_bytes()))
+            .allow("https://example.com/");
+        let preprocessor = Preprocessor::with_resolver(resolver);
+
+        let result = preprocessor
+            .process("!includeurl https://example.com/a.puml\n!include https://example.com/b.puml\n")
+            .unwrap();
+        assert_eq!(result, "' from https://example.com/a.puml\n' from https://example.com/b.puml\n");
+        assert!(matches!(
+            preprocessor.process("!includeurl https://evil.org/a.puml"),
+            Err(PreprocessError::UrlNotAllowed(_))
+        ));
+    }
+
     #[test]
     fn test_theme_directive() {
         let preprocessor = Preprocessor::new();
@@ -675,7 +1931,10 @@ BODY
 
         // HEADER_LINE должен появиться только один раз
         let count = result.matches("HEADER_LINE").count();
-        assert_eq!(count, 1, "!include_once должен включать файл только один раз");
+        assert_eq!(
+            count, 1,
+            "!include_once должен включать файл только один раз"
+        );
     }
 
     #[test]
@@ -697,6 +1956,24 @@ BODY
         assert!(matches!(err, PreprocessError::FileNotFound(_)));
     }
 
+    #[test]
+    fn test_include_stdlib_path() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let lib_dir = temp_dir.path().join("lib");
+        std::fs::create_dir_all(lib_dir.join("mylib")).unwrap();
+        std::fs::write(lib_dir.join("mylib/common.puml"), "STDLIB_LINE\n").unwrap();
+
+        // Путь в угловых скобках передаётся resolver'у как есть
+        let source = "!include <mylib/common>\nBODY\n";
+        let resolver = FsFileResolver::new(temp_dir.path()).with_search_path(&lib_dir);
+        let preprocessor = Preprocessor::with_resolver(resolver);
+        let result = preprocessor.process(source).unwrap();
+
+        assert!(result.contains("STDLIB_LINE"));
+    }
+
     #[test]
     fn test_nested_include() {
         use std::io::Write;
@@ -732,5 +2009,34 @@ MAIN_END
         assert!(result.contains("LEVEL2_CONTENT"));
         assert!(result.contains("LEVEL1_END"));
         assert!(result.contains("MAIN_END"));
+
+        // Глубина ограничена resolver'ом
+        let resolver = FsFileResolver::new(temp_dir.path()).with_max_depth(1);
+        let err = Preprocessor::with_resolver(resolver).process(source).unwrap_err();
+        assert!(matches!(err, PreprocessError::RecursiveInclude(m)
+            if m.ends_with("level1.puml -> level2.puml")));
+    }
+
+    #[test]
+    fn test_recursive_include() {
+        let files = MemoryFileResolver::new()
+            .with_file("self.puml", "A\n!include self.puml")
+            .with_file("a.puml", "!include b.puml")
+            .with_file("b.puml", "!include a.puml")
+            .with_file("twice.puml", "!include c.puml\n!include c.puml")
+            .with_file("c.puml", "C");
+        let preprocessor = Preprocessor::with_resolver(files);
+
+        let err = preprocessor.process("!include self.puml\n").unwrap_err();
+        assert!(matches!(err, PreprocessError::RecursiveInclude(m) if m == "self.puml -> self.puml"));
+        let err = preprocessor.process("!include a.puml\n").unwrap_err();
+        assert!(matches!(err, PreprocessError::RecursiveInclude(m)
+            if m == "a.puml -> b.puml -> a.puml"));
+
+        // Повторное включение одного файла подряд — не цикл; стек очищается после ошибки
+        let mut ctx = PreprocessContext::new();
+        assert!(preprocessor.process_with_context("!include self.puml\n", &mut ctx).is_err());
+        let result = preprocessor.process_with_context("!include twice.puml\n", &mut ctx).unwrap();
+        assert_eq!(result, "C\nC\n");
     }
 }
@@ -0,0 +1,176 @@
+//! FileResolver поверх файлов в памяти
+//!
+//! Виртуальная файловая система для окружений без диска (WASM) и для тестов:
+//! - Пути нормализуются (`./`, `..`, `\`, ведущий `/`)
+//! - Относительные пути ищутся сначала в базовой директории, затем от корня
+//! - Стандартная библиотека (`<C4/C4_Container>`) ищется как `C4/C4_Container`
+//!   с расширениями `.puml` и `.iuml`
+
+use indexmap::IndexMap;
+
+use crate::{FileResolver, PreprocessError, Result};
+
+/// FileResolver с файлами в памяти
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_preprocessor::{MemoryFileResolver, Preprocessor};
+///
+/// let resolver = MemoryFileResolver::new()
+///     .with_file("lib/style.puml", "!$color = \"red\"")
+///     .with_base_dir("lib");
+/// let result = Preprocessor::with_resolver(resolver)
+///     .process("!include style.puml\ncolor: $color")
+///     .unwrap();
+/// assert_eq!(result, "color: red\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileResolver {
+    /// Содержимое файлов по нормализованным путям
+    files: IndexMap<String, Vec<u8>>,
+    /// Базовая директория для относительных путей
+    base_dir: String,
+}
+
+impl MemoryFileResolver {
+    /// Создаёт пустую файловую систему
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет файл (builder)
+    pub fn with_file(mut self, path: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.add_file(path, content);
+        self
+    }
+
+    /// Устанавливает базовую директорию для относительных путей
+    pub fn with_base_dir(mut self, dir: &str) -> Self {
+        self.base_dir = normalize(dir);
+        self
+    }
+
+    /// Добавляет или заменяет файл
+    pub fn add_file(&mut self, path: &str, content: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(path), content.into());
+    }
+
+    /// Удаляет файл; возвращает `true`, если он был
+    pub fn remove_file(&mut self, path: &str) -> bool {
+        self.files.shift_remove(&normalize(path)).is_some()
+    }
+
+    /// Удаляет все файлы
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    /// Пути файлов в директории и её поддиректориях
+    pub fn files_in<'a>(&'a self, dir: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let prefix = match normalize(dir) {
+            dir if dir.is_empty() => dir,
+            dir => dir + "/",
+        };
+        self.files
+            .keys()
+            .filter(move |path| path.starts_with(&prefix))
+            .map(String::as_str)
+    }
+
+    /// Есть ли в файловой системе директория с таким путём
+    pub fn is_dir(&self, dir: &str) -> bool {
+        self.files_in(dir).next().is_some()
+    }
+
+    /// Находит файл: в базовой директории, от корня, для `<...>` — с расширениями
+    fn resolve(&self, path: &str) -> Option<&Vec<u8>> {
+        let path = path.trim();
+        if let Some(stdlib) = path.strip_prefix('<').and_then(|p| p.strip_suffix('>')) {
+            let stdlib = normalize(stdlib);
+            return ["", ".puml", ".iuml"]
+                .iter()
+                .find_map(|ext| self.files.get(&format!("{}{}", stdlib, ext)));
+        }
+
+        let path = path.trim_matches('"');
+        let relative = !path.starts_with('/') && !self.base_dir.is_empty();
+        relative
+            .then(|| self.files.get(&normalize(&format!("{}/{}", self.base_dir, path))))
+            .flatten()
+            .or_else(|| self.files.get(&normalize(path)))
+    }
+
+    fn not_found(&self, path: &str) -> PreprocessError {
+        PreprocessError::FileNotFound(format!("{} (в памяти: {} файлов)", path, self.files.len()))
+    }
+}
+
+impl FileResolver for MemoryFileResolver {
+    fn read_file(&self, path: &str) -> Result<String> {
+        let content = self.resolve(path).ok_or_else(|| self.not_found(path))?;
+        String::from_utf8(content.clone())
+            .map_err(|e| PreprocessError::FileReadError(format!("{}: {}", path, e)))
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        self.resolve(path)
+            .cloned()
+            .ok_or_else(|| self.not_found(path))
+    }
+}
+
+/// Нормализует путь: `/` как разделитель, без `.`, `..` и пустых сегментов
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_and_directories() {
+        let mut resolver = MemoryFileResolver::new()
+            .with_file("/project/common.puml", "common")
+            .with_file("project\\styles\\dark.puml", "dark")
+            .with_file("C4/C4_Container.puml", "c4")
+            .with_base_dir("./project/");
+
+        assert_eq!(resolver.read_file("common.puml").unwrap(), "common");
+        assert_eq!(resolver.read_file("\"styles/../common.puml\"").unwrap(), "common");
+        assert_eq!(resolver.read_file("/project/styles/dark.puml").unwrap(), "dark");
+        assert_eq!(resolver.read_file("<C4/C4_Container>").unwrap(), "c4");
+        assert!(resolver.is_dir("project"));
+        assert!(!resolver.is_dir("project/common.puml"));
+        assert_eq!(resolver.files_in("project/styles").collect::<Vec<_>>(), ["project/styles/dark.puml"]);
+
+        assert!(resolver.remove_file("project/common.puml"));
+        assert!(!resolver.file_exists("common.puml"));
+        assert!(matches!(
+            resolver.read_file("common.puml"),
+            Err(PreprocessError::FileNotFound(_))
+        ));
+
+        resolver.add_file("bin.dat", vec![0xff, 0xfe]);
+        assert_eq!(resolver.read_bytes("bin.dat").unwrap(), [0xff, 0xfe]);
+        assert!(matches!(
+            resolver.read_file("bin.dat"),
+            Err(PreprocessError::FileReadError(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Карта строк вывода препроцессора на исходные файлы
+//!
+//! После `!include`, циклов и вызовов процедур строка N результата может
+//! происходить из другого файла или из тела макроса. Карта хранит для каждой
+//! строки вывода файл, строку и стек вызовов макросов, чтобы ошибки парсера
+//! указывали на исходный текст.
+
+use std::fmt;
+
+/// Вызов макроса (процедуры или функции), через который получена строка
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroCall {
+    /// Имя вызванного макроса (с `$`)
+    pub name: String,
+    /// Файл вызова (`None` — основной исходник)
+    pub file: Option<String>,
+    /// Строка вызова (с 1)
+    pub line: usize,
+}
+
+/// Исходное положение строки вывода
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Файл (`None` — основной исходник)
+    pub file: Option<String>,
+    /// Строка в файле (с 1)
+    pub line: usize,
+    /// Вызовы макросов от внешнего к внутреннему (пусто вне макросов)
+    pub macro_stack: Vec<MacroCall>,
+}
+
+impl SourceLocation {
+    /// Сдвигает строки основного исходника (положение и вызовы макросов)
+    pub fn shift_main_lines(&mut self, offset: usize) {
+        if self.file.is_none() {
+            self.line += offset;
+        }
+        for call in &mut self.macro_stack {
+            if call.file.is_none() {
+                call.line += offset;
+            }
+        }
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_position(f, self.file.as_deref(), self.line)?;
+        for call in self.macro_stack.iter().rev() {
+            write!(f, ", макрос {} вызван ", call.name)?;
+            write_position(f, call.file.as_deref(), call.line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Положение с предлогом: `в common.puml:3` или `в строке 3` основного исходника
+fn write_position(f: &mut fmt::Formatter<'_>, file: Option<&str>, line: usize) -> fmt::Result {
+    match file {
+        Some(file) => write!(f, "в {}:{}", file, line),
+        None => write!(f, "в строке {}", line),
+    }
+}
+
+/// Карта строк вывода препроцессора
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    lines: Vec<SourceLocation>,
+}
+
+impl SourceMap {
+    /// Создаёт пустую карту
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Положение строки вывода `line` (с 1)
+    pub fn get(&self, line: usize) -> Option<&SourceLocation> {
+        line.checked_sub(1).and_then(|index| self.lines.get(index))
+    }
+
+    /// Число строк вывода
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Пуста ли карта
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Положения строк вывода по порядку
+    pub fn iter(&self) -> impl Iterator<Item = &SourceLocation> {
+        self.lines.iter()
+    }
+
+    /// Сдвигает строки основного исходника, например после обрезки пустых
+    /// строк в его начале
+    pub fn shift_main_lines(&mut self, offset: usize) {
+        for location in &mut self.lines {
+            location.shift_main_lines(offset);
+        }
+    }
+
+    pub(crate) fn extend(&mut self, locations: impl IntoIterator<Item = SourceLocation>) {
+        self.lines.extend(locations);
+    }
+
+    pub(crate) fn split_off(&mut self, at: usize) -> Vec<SourceLocation> {
+        self.lines.split_off(at)
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.lines.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_display_and_shift() {
+        let mut map = SourceMap::new();
+        map.extend([SourceLocation {
+            file: Some("common.puml".to_string()),
+            line: 4,
+            macro_stack: vec![MacroCall {
+                name: "$box".to_string(),
+                file: None,
+                line: 2,
+            }],
+        }]);
+
+        assert_eq!(map.get(1).unwrap().to_string(), "в common.puml:4, макрос $box вызван в строке 2");
+        assert!(map.get(0).is_none());
+        assert!(map.get(2).is_none());
+
+        map.shift_main_lines(3);
+        assert_eq!(map.get(1).unwrap().line, 4);
+        assert_eq!(map.get(1).unwrap().macro_stack[0].line, 5);
+    }
+}
@@ -0,0 +1,316 @@
+//! FileResolver для `!includeurl` и `!include https://...` (feature `url`)
+//!
+//! Сеть не используется напрямую: содержимое загружает [`Fetcher`] вызывающей
+//! стороны (HTTP-клиент, `fetch` в браузере, заглушка в тестах). Загружаются
+//! только URL из allowlist; пустой allowlist запрещает все URL. Загруженные
+//! файлы можно кешировать на диске: запись хранит свой URL и устаревает через
+//! заданное время.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{FileResolver, PreprocessError, Result};
+
+/// Загрузчик содержимого по URL
+pub trait Fetcher {
+    /// Загружает содержимое; ошибка — текст для сообщения пользователю
+    fn fetch(&self, url: &str) -> std::result::Result<Vec<u8>, String>;
+}
+
+impl<F> Fetcher for F
+where
+    F: Fn(&str) -> std::result::Result<Vec<u8>, String>,
+{
+    fn fetch(&self, url: &str) -> std::result::Result<Vec<u8>, String> {
+        self(url)
+    }
+}
+
+/// FileResolver для http(s) URL с allowlist и кешем на диске
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_preprocessor::{FileResolver, UrlResolver};
+///
+/// let resolver = UrlResolver::new(|url: &str| Ok(format!("' {}", url).into_bytes()))
+///     .allow("https://example.com/plantuml/");
+/// assert!(resolver.read_file("https://example.com/plantuml/a.puml").is_ok());
+/// assert!(resolver.read_file("https://other.org/a.puml").is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct UrlResolver<F: Fetcher> {
+    /// Загрузчик содержимого
+    fetcher: F,
+    /// Разрешённые префиксы URL
+    allowlist: Vec<String>,
+    /// Директория кеша (без неё каждый URL загружается заново)
+    cache_dir: Option<PathBuf>,
+    /// Время жизни записи кеша (None — без ограничения)
+    cache_ttl: Option<Duration>,
+}
+
+impl<F: Fetcher> UrlResolver<F> {
+    /// Создаёт resolver с пустым allowlist (все URL запрещены)
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            allowlist: Vec::new(),
+            cache_dir: None,
+            cache_ttl: None,
+        }
+    }
+
+    /// Разрешает URL с заданным префиксом (`https://host/` или `https://host/path`)
+    pub fn allow(mut self, prefix: impl Into<String>) -> Self {
+        self.allowlist.push(prefix.into());
+        self
+    }
+
+    /// Включает кеш загруженных файлов в директории
+    pub fn with_cache_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cache_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Задаёт время жизни записей кеша: более старые загружаются заново
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Разрешён ли URL: http(s), без сегментов `.`/`..` (в том числе
+    /// `%2e%2e`) и с префиксом из allowlist по границе сегмента
+    pub fn is_allowed(&self, url: &str) -> bool {
+        is_url(url)
+            && !has_dot_segments(url)
+            && self.allowlist.iter().any(|prefix| {
+                url.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+                    rest.is_empty()
+                        || prefix.ends_with('/')
+                        || rest.starts_with(['/', '?', '#'])
+                })
+            })
+    }
+
+    /// Путь файла кеша для URL
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{:016x}.cache", fnv1a(url))))
+    }
+
+    /// Содержимое записи кеша, если она не устарела и сохранена для `url`:
+    /// запись начинается строкой с URL, поэтому коллизия хеша — промах
+    fn read_cache(&self, path: &Path, url: &str) -> Option<Vec<u8>> {
+        if let Some(ttl) = self.cache_ttl {
+            let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+            if age > ttl {
+                return None;
+            }
+        }
+        let entry = fs::read(path).ok()?;
+        let content = entry.strip_prefix(url.as_bytes())?.strip_prefix(b"\n")?;
+        Some(content.to_vec())
+    }
+
+    /// Загружает URL через кеш и fetcher
+    fn load(&self, path: &str) -> Result<Vec<u8>> {
+        let url = path.trim().trim_matches('"');
+        if !is_url(url) {
+            return Err(PreprocessError::FileNotFound(url.to_string()));
+        }
+        if !self.is_allowed(url) {
+            return Err(PreprocessError::UrlNotAllowed(url.to_string()));
+        }
+
+        let cache = self.cache_path(url);
+        if let Some(content) = cache.as_ref().and_then(|path| self.read_cache(path, url)) {
+            return Ok(content);
+        }
+
+        let content = self
+            .fetcher
+            .fetch(url)
+            .map_err(|e| PreprocessError::FileReadError(format!("{}: {}", url, e)))?;
+
+        // Кеш не обязателен: ошибка записи не мешает включению
+        if let Some(cache) = cache {
+            if let Some(dir) = cache.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let entry = [url.as_bytes(), b"\n", &content].concat();
+            let _ = fs::write(cache, entry);
+        }
+
+        Ok(content)
+    }
+}
+
+impl<F: Fetcher> FileResolver for UrlResolver<F> {
+    fn read_file(&self, path: &str) -> Result<String> {
+        String::from_utf8(self.load(path)?)
+            .map_err(|e| PreprocessError::FileReadError(format!("{}: {}", path, e)))
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        self.is_allowed(path.trim().trim_matches('"'))
+    }
+
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        self.load(path)
+    }
+}
+
+/// Является ли путь http(s) URL
+fn is_url(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+/// Есть ли в пути URL сегменты `.` или `..`: сервер мог бы нормализовать их и
+/// выйти за разрешённый префикс. Учитываются `%2e` и разделители `%2f`, `%5c`, `\`
+fn has_dot_segments(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_ascii_lowercase();
+    let path = path
+        .replace("%2e", ".")
+        .replace("%2f", "/")
+        .replace("%5c", "/")
+        .replace('\\', "/");
+    path.split('/').any(|segment| segment == "." || segment == "..")
+}
+
+/// Стабильный хеш FNV-1a для имён файлов кеша
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_allowlist() {
+        let resolver = UrlResolver::new(|_: &str| Ok(b"A -> B".to_vec()))
+            .allow("https://example.com")
+            .allow("https://cdn.org/lib/");
+
+        assert!(resolver.is_allowed("https://example.com/a.puml"));
+        assert!(resolver.is_allowed("https://cdn.org/lib/c4.puml"));
+        assert!(!resolver.is_allowed("https://example.com.evil.org/a.puml"));
+        assert!(!resolver.is_allowed("https://cdn.org/other.puml"));
+        assert!(!resolver.is_allowed("file:///etc/passwd"));
+
+        // Сегменты `..` не выводят за разрешённый префикс
+        for url in [
+            "https://cdn.org/lib/../secret.puml",
+            "https://cdn.org/lib/%2e%2e/secret.puml",
+            "https://cdn.org/lib/%2E./secret.puml",
+            "https://cdn.org/lib/..%2fsecret.puml",
+            "https://cdn.org/lib/..\\secret.puml",
+            "https://cdn.org/lib/./c4.puml",
+            "https://cdn.org/lib/a/..",
+        ] {
+            assert!(!resolver.is_allowed(url), "{}", url);
+        }
+        assert!(resolver.is_allowed("https://cdn.org/lib/v1..2/c4.puml?x=../y"));
+
+        assert_eq!(resolver.read_file("\"https://example.com/a.puml\"").unwrap(), "A -> B");
+        assert!(matches!(
+            resolver.read_file("https://other.org/a.puml"),
+            Err(PreprocessError::UrlNotAllowed(_))
+        ));
+        assert!(matches!(
+            resolver.read_file("local.puml"),
+            Err(PreprocessError::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let fetches = Cell::new(0);
+        let fetcher = |url: &str| {
+            fetches.set(fetches.get() + 1);
+            if url.ends_with("missing.puml") {
+                Err("404".to_string())
+            } else {
+                Ok(b"cached".to_vec())
+            }
+        };
+        let resolver = UrlResolver::new(fetcher)
+            .allow("https://example.com/")
+            .with_cache_dir(temp_dir.path().join("cache"));
+
+        assert_eq!(resolver.read_file("https://example.com/a.puml").unwrap(), "cached");
+        assert_eq!(resolver.read_file("https://example.com/a.puml").unwrap(), "cached");
+        assert_eq!(fetches.get(), 1);
+
+        let err = resolver.read_file("https://example.com/missing.puml").unwrap_err();
+        assert!(matches!(err, PreprocessError::FileReadError(ref m) if m.contains("404")));
+    }
+
+    #[test]
+    fn test_cache_entry_checks_url() {
+        let temp_dir = TempDir::new().unwrap();
+        let fetches = Cell::new(0);
+        let fetcher = |url: &str| {
+            fetches.set(fetches.get() + 1);
+            Ok(format!("' {}", url).into_bytes())
+        };
+        let resolver = UrlResolver::new(fetcher)
+            .allow("https://example.com/")
+            .with_cache_dir(temp_dir.path());
+
+        // Запись другого URL под тем же именем (коллизия хеша) не используется
+        let url = "https://example.com/a.puml";
+        let path = resolver.cache_path(url).unwrap();
+        fs::write(&path, "https://example.com/b.puml\n' b").unwrap();
+        assert_eq!(resolver.read_file(url).unwrap(), format!("' {}", url));
+        assert_eq!(fetches.get(), 1);
+
+        // Перезаписанная запись хранит свой URL
+        assert!(fs::read_to_string(&path).unwrap().starts_with("https://example.com/a.puml\n"));
+        assert_eq!(resolver.read_file(url).unwrap(), format!("' {}", url));
+        assert_eq!(fetches.get(), 1);
+
+        // Запись старого формата без URL — промах
+        fs::write(&path, "' stale").unwrap();
+        assert_eq!(resolver.read_file(url).unwrap(), format!("' {}", url));
+        assert_eq!(fetches.get(), 2);
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let temp_dir = TempDir::new().unwrap();
+        let fetches = Cell::new(0);
+        let fetcher = |_: &str| {
+            fetches.set(fetches.get() + 1);
+            Ok(format!("' v{}", fetches.get()).into_bytes())
+        };
+        let resolver = UrlResolver::new(fetcher)
+            .allow("https://example.com/")
+            .with_cache_dir(temp_dir.path())
+            .with_cache_ttl(Duration::from_secs(60));
+
+        let url = "https://example.com/a.puml";
+        assert_eq!(resolver.read_file(url).unwrap(), "' v1");
+        assert_eq!(resolver.read_file(url).unwrap(), "' v1");
+
+        // Запись старше TTL загружается заново
+        let hour_ago = std::time::SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(resolver.cache_path(url).unwrap())
+            .unwrap()
+            .set_modified(hour_ago)
+            .unwrap();
+        assert_eq!(resolver.read_file(url).unwrap(), "' v2");
+        assert_eq!(resolver.read_file(url).unwrap(), "' v2");
+        assert_eq!(fetches.get(), 2);
+    }
+}
@@ -2,45 +2,212 @@
 
 use indexmap::IndexMap;
 
-use crate::{PreprocessContext, PreprocessError, Result};
+use crate::expression::{self, Environment, Value};
+use crate::{PreprocessError, Result};
 
-/// Обрабатывает присваивание переменной: !$var = value
-pub fn handle_variable_assignment(directive: &str, ctx: &mut PreprocessContext) -> Result<()> {
-    if !ctx.should_output() {
-        return Ok(());
-    }
+/// Область видимости присваивания
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Локальная внутри функции, иначе глобальная
+    Auto,
+    /// `!local`
+    Local,
+    /// `!global`
+    Global,
+}
+
+/// Разобранное присваивание `$name = value` / `$name ?= value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment<'a> {
+    /// Имя переменной (с $)
+    pub name: String,
+    /// Текст выражения значения
+    pub value: &'a str,
+    /// `?=`: присвоить, только если переменная не определена
+    pub if_undefined: bool,
+}
+
+/// Разбирает присваивание переменной: `$var = value`
+pub fn parse_assignment(directive: &str) -> Result<Assignment<'_>> {
+    let (name, value) = directive.split_once('=').ok_or_else(|| {
+        PreprocessError::SyntaxError(format!("неверный формат присваивания: {}", directive))
+    })?;
+    let (name, if_undefined) = match name.trim_end().strip_suffix('?') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
 
-    // Формат: $name = value
-    let parts: Vec<&str> = directive.splitn(2, '=').collect();
-    if parts.len() != 2 {
+    let name = name.trim().trim_start_matches('$');
+    if name.is_empty() {
         return Err(PreprocessError::SyntaxError(format!(
             "неверный формат присваивания: {}",
             directive
         )));
     }
 
-    let name = parts[0].trim().trim_start_matches('$');
-    let value = parts[1].trim().trim_matches('"');
-
-    ctx.set_variable(format!("${}", name), value.to_string());
-
-    Ok(())
+    Ok(Assignment {
+        name: format!("${}", name),
+        value: value.trim(),
+        if_undefined,
+    })
 }
 
 /// Подставляет переменные в строку
-pub fn substitute(line: &str, variables: &IndexMap<String, String>) -> String {
+///
+/// Имена из `!define` заменяются как текст. Для `$name` и `${name}` берётся
+/// самое длинное определённое имя; JSON значение с цепочкой доступа
+/// (`$data.servers[0].name`) заменяется выбранным элементом, а цепочка к
+/// несуществующему элементу остаётся без изменений.
+pub fn substitute(line: &str, variables: &IndexMap<String, Value>) -> String {
     let mut result = line.to_string();
+    for (name, value) in variables.iter().filter(|(name, _)| !name.starts_with('$')) {
+        result = result.replace(name.as_str(), &value.to_text());
+    }
+
+    let mut output = String::with_capacity(result.len());
+    let mut rest = result.as_str();
+    while let Some(pos) = rest.find('$') {
+        output.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        let (text, len) = substitute_at(rest, variables).unwrap_or_else(|| ("$".to_string(), 1));
+        output.push_str(&text);
+        rest = &rest[len..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Подстановка переменной в начале `text` (начинается с `$`): текст и длина замены
+fn substitute_at(text: &str, variables: &IndexMap<String, Value>) -> Option<(String, usize)> {
+    // ${name}
+    if let Some(inner) = text.strip_prefix("${") {
+        let end = inner.find('}')?;
+        let value = variables.get(&format!("${}", &inner[..end]))?;
+        return Some((value.to_text(), end + 3));
+    }
+
+    let ident_len = 1 + text[1..]
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len() - 1);
+    let (len, value) = text[..ident_len]
+        .char_indices()
+        .rev()
+        .take_while(|&(i, _)| i > 0)
+        .map(|(i, c)| i + c.len_utf8())
+        .find_map(|end| variables.get(&text[..end]).map(|value| (end, value)))?;
+
+    if len == ident_len && matches!(value, Value::Json(_)) {
+        let chain = len + accessor_len(&text[len..]);
+        if chain > len {
+            // Несуществующий элемент не подставляется: текст остаётся как есть
+            let mut env = Variables(variables);
+            let text = match expression::evaluate(&text[..chain], &mut env) {
+                Ok(item) => item.to_text(),
+                Err(_) => text[..chain].to_string(),
+            };
+            return Some((text, chain));
+        }
+    }
+    Some((value.to_text(), len))
+}
+
+/// Длина цепочки `.field` / `[index]` в начале строки
+fn accessor_len(text: &str) -> usize {
+    let mut len = 0;
+    loop {
+        let rest = &text[len..];
+        if let Some(field) = rest.strip_prefix('.') {
+            let name = field
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(field.len());
+            if name == 0 || !field.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                return len;
+            }
+            len += 1 + name;
+        } else if rest.starts_with('[') {
+            match rest.find(']') {
+                Some(end) => len += end + 1,
+                None => return len,
+            }
+        } else {
+            return len;
+        }
+    }
+}
+
+/// Окружение только с переменными (для цепочек доступа в тексте)
+struct Variables<'a>(&'a IndexMap<String, Value>);
+
+impl Environment for Variables<'_> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.0.get(name).cloned()
+    }
 
-    for (name, value) in variables {
-        // Подстановка $name и ${name}
-        result = result.replace(name, value);
+    fn call(&mut self, name: &str, _args: Vec<Value>) -> Result<Value> {
+        Err(PreprocessError::ExpressionError(format!(
+            "вызов {} в цепочке доступа",
+            name
+        )))
+    }
+}
+
+/// Присваивание с многострочным JSON значением (`!$data = {` ... `}`)
+///
+/// Возвращает директиву, склеенную до закрытия всех скобок, и индекс её
+/// последней строки; `None` — присваивание однострочное. Если скобки не
+/// закрыты до конца текста — ошибка с номером `line` первой строки.
+pub fn json_continuation(
+    lines: &[&str],
+    start: usize,
+    line: usize,
+) -> Result<Option<(String, usize)>> {
+    let first = lines[start].trim();
+    let Some((_, value)) = first.split_once('=') else {
+        return Ok(None);
+    };
+    let is_assignment = ["!$", "!local ", "!global "]
+        .iter()
+        .any(|prefix| first.starts_with(prefix));
+    if !is_assignment || !value.trim_start().starts_with(['{', '[']) {
+        return Ok(None);
+    }
 
-        // Также подстановка ${name}
-        let braced = format!("${{{}}}", name.trim_start_matches('$'));
-        result = result.replace(&braced, value);
+    let mut depth = bracket_depth(value, 0);
+    if depth <= 0 {
+        return Ok(None);
+    }
+    let mut directive = first.to_string();
+    let mut end = start;
+    while depth > 0 && end + 1 < lines.len() {
+        end += 1;
+        directive.push('\n');
+        directive.push_str(lines[end]);
+        depth = bracket_depth(lines[end], depth);
+    }
+    if depth > 0 {
+        return Err(PreprocessError::SyntaxError(format!(
+            "строка {}: JSON не закрыт до конца файла: {}",
+            line, first
+        )));
     }
+    Ok(Some((directive, end)))
+}
 
-    result
+/// Глубина вложенности скобок JSON после `text` (строки в кавычках пропускаются)
+fn bracket_depth(text: &str, mut depth: i32) -> i32 {
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' | '[' if !in_string => depth += 1,
+            '}' | ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
 }
 
 #[cfg(test)]
@@ -49,18 +216,67 @@ mod tests {
 
     #[test]
     fn test_variable_assignment() {
-        let mut ctx = PreprocessContext::new();
-        handle_variable_assignment("$name = \"Alice\"", &mut ctx).unwrap();
-        assert_eq!(ctx.get_variable("$name"), Some(&"Alice".to_string()));
+        let assignment = parse_assignment("$name = \"Alice\"").unwrap();
+        assert_eq!(assignment.name, "$name");
+        assert_eq!(assignment.value, "\"Alice\"");
+        assert!(!assignment.if_undefined);
+
+        let assignment = parse_assignment("$n ?= 1 + 2").unwrap();
+        assert_eq!((assignment.name.as_str(), assignment.value), ("$n", "1 + 2"));
+        assert!(assignment.if_undefined);
+
+        assert!(parse_assignment("$name").is_err());
     }
 
     #[test]
     fn test_substitute() {
         let mut vars = IndexMap::new();
-        vars.insert("$name".to_string(), "Alice".to_string());
-        vars.insert("$color".to_string(), "#FF0000".to_string());
+        vars.insert("$name".to_string(), Value::from("Alice"));
+        vars.insert("$color".to_string(), Value::from("#FF0000"));
 
         let result = substitute("participant $name #$color", &vars);
         assert_eq!(result, "participant Alice ##FF0000");
+
+        // Самое длинное имя, ${name}, неизвестные переменные не меняются
+        vars.insert("$name2".to_string(), Value::Int(2));
+        let result = substitute("$name2 $names ${name}_x $other $", &vars);
+        assert_eq!(result, "2 Alices Alice_x $other $");
+    }
+
+    #[test]
+    fn test_substitute_json_access() {
+        let mut vars = IndexMap::new();
+        let json = serde_json::json!({"servers": [{"name": "web"}], "env": "prod"});
+        vars.insert("$data".to_string(), Value::Json(json));
+        vars.insert("$file".to_string(), Value::from("readme"));
+
+        let result = substitute("node $data.servers[0].name ($data.env). $file.txt", &vars);
+        assert_eq!(result, "node web (prod). readme.txt");
+        // Несуществующие элементы остаются текстом
+        assert_eq!(substitute("$data.missing", &vars), "$data.missing");
+        assert_eq!(substitute("[$data.servers[5]]", &vars), "[$data.servers[5]]");
+    }
+
+    #[test]
+    fn test_json_continuation() {
+        let lines = [
+            "!$data = {",
+            r#"  "name": "a}", "items": ["#,
+            "    1, 2",
+            "  ]",
+            "}",
+            "after",
+        ];
+        let (directive, end) = json_continuation(&lines, 0, 1).unwrap().unwrap();
+        assert_eq!(end, 4);
+        assert!(directive.ends_with("]\n}"));
+
+        assert!(json_continuation(&["!$a = [1, 2]"], 0, 1).unwrap().is_none());
+        assert!(json_continuation(&["!$a = 1"], 0, 1).unwrap().is_none());
+        assert!(json_continuation(&["note {"], 0, 1).unwrap().is_none());
+
+        // Незакрытый JSON не поглощает остаток файла
+        let err = json_continuation(&["A -> B", "!$a = [1,", "C -> D"], 1, 2).unwrap_err();
+        assert!(matches!(err, PreprocessError::SyntaxError(m) if m.starts_with("строка 2:")));
     }
 }
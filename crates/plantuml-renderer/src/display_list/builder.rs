@@ -0,0 +1,1251 @@
+//! Построение display list из результата layout
+//!
+//! Геометрия повторяет [`SvgRenderer`](crate::SvgRenderer): те же размеры,
+//! отступы, цвета темы и наконечники, чтобы backend'ы рисовали одинаково.
+//! В рукописном режиме (`skinparam handwritten`) контуры дрожат, как в SVG.
+//! Спрайты подписей и стереотипов становятся командами [`DrawCommand::Image`].
+
+use plantuml_ast::sprite::{LabelPart, Sprite, SpriteData, SpriteRef, SpriteRegistry};
+use plantuml_layout::sprites::{label_lines, label_size, line_size, sprite_size};
+use plantuml_layout::{EstimatedMetrics, TextMetrics};
+
+use super::{
+    ClipRect, DisplayList, DrawCommand, Font, ImageData, Link, Marker, MarkerKind, PathSegment,
+    Style, TextAnchor, TextBaseline,
+};
+use crate::interactive::{element_class, ElementIds};
+use crate::sketch::Sketch;
+use crate::sprites::standalone_svg;
+use crate::svg_renderer::{cardinality_anchor, edge_label_anchor};
+use crate::{
+    ClassMember, ClassifierKind, EdgeLabelPositions, EdgeType, ElementType, FragmentSection,
+    LayoutElement, LayoutResult, MemberVisibility, Point, Rect, RenderOptions, Renderer, ZLayer,
+};
+use plantuml_themes::Theme;
+
+/// Рендерер в display list
+#[derive(Debug, Clone, Default)]
+pub struct DisplayListRenderer {
+    options: RenderOptions,
+    sprites: SpriteRegistry,
+}
+
+impl DisplayListRenderer {
+    /// Создаёт рендерер с опциями по умолчанию
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Создаёт рендерер с опциями (масштаб применяют backend'ы)
+    pub fn with_options(options: RenderOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Задаёт спрайты диаграммы (`DiagramMetadata::sprites`)
+    pub fn with_sprites(mut self, sprites: SpriteRegistry) -> Self {
+        self.sprites = sprites;
+        self
+    }
+}
+
+impl Renderer for DisplayListRenderer {
+    type Output = DisplayList;
+
+    fn render(&self, layout: &LayoutResult, theme: &Theme) -> DisplayList {
+        let bounds = &layout.bounds;
+        let margin = 5.0; // как в SvgRenderer
+
+        let mut list = DisplayList::new(
+            bounds.x - margin,
+            bounds.y - margin,
+            bounds.width + margin * 2.0,
+            bounds.height + margin * 2.0,
+        );
+        list.background = self.options.background_color.clone();
+
+        let mut sorted_elements: Vec<_> = layout.elements.iter().collect();
+        sorted_elements.sort_by_key(|e| ZLayer::from_element(e));
+
+        let mut ids = ElementIds::default();
+        list.commands = sorted_elements
+            .into_iter()
+            .map(|element| element_group(element, theme, &self.sprites, &mut ids))
+            .collect();
+        list
+    }
+}
+
+/// Группа команд элемента с `id`, классом, строкой и ссылкой
+fn element_group(
+    element: &LayoutElement,
+    theme: &Theme,
+    sprites: &SpriteRegistry,
+    ids: &mut ElementIds,
+) -> DrawCommand {
+    let mut painter = Painter::new(theme, sprites);
+    painter.element(element, ids);
+
+    // Спрайт стереотипа <<$name>> — иконка в правом верхнем углу
+    if let Some((name, sprite)) = element
+        .properties
+        .get("sprite")
+        .and_then(|name| Some((name, sprites.get(name)?)))
+    {
+        let x = element.bounds.x + element.bounds.width - f64::from(sprite.width) - 4.0;
+        let y = element.bounds.y + 4.0;
+        painter.sprite(sprite, &SpriteRef::new(name.as_str()), x, y);
+    }
+
+    let link = element.properties.get("link").map(|url| Link {
+        url: url.clone(),
+        tooltip: element.properties.get("tooltip").cloned(),
+    });
+    DrawCommand::Group {
+        id: Some(ids.unique(&element.id)),
+        class: Some(element_class(element)),
+        line: element
+            .properties
+            .get("line")
+            .and_then(|line| line.parse().ok()),
+        link,
+        clip: None,
+        commands: painter.commands,
+    }
+}
+
+/// Накопитель команд одного элемента
+struct Painter<'a> {
+    theme: &'a Theme,
+    sprites: &'a SpriteRegistry,
+    commands: Vec<DrawCommand>,
+}
+
+impl<'a> Painter<'a> {
+    fn new(theme: &'a Theme, sprites: &'a SpriteRegistry) -> Self {
+        Self {
+            theme,
+            sprites,
+            commands: Vec::new(),
+        }
+    }
+
+    fn path(&mut self, segments: Vec<PathSegment>, style: Style) {
+        self.commands.push(DrawCommand::Path {
+            segments: self.outline(segments),
+            style,
+            marker_start: None,
+            marker_end: None,
+        });
+    }
+
+    /// Контур фигуры: в рукописном режиме — дрожащий
+    fn outline(&self, segments: Vec<PathSegment>) -> Vec<PathSegment> {
+        if self.theme.handwritten {
+            sketched(&segments)
+        } else {
+            segments
+        }
+    }
+
+    fn rect(&mut self, bounds: &Rect, radius: f64, style: Style) {
+        let segments = PathSegment::rect(bounds.x, bounds.y, bounds.width, bounds.height, radius);
+        self.path(segments, style);
+    }
+
+    fn ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64, style: Style) {
+        self.path(PathSegment::ellipse(cx, cy, rx, ry), style);
+    }
+
+    /// Отрезок цветом границ узлов
+    fn line(&mut self, from: Point, to: Point, width: f64) {
+        let style = Style::stroke(self.theme.node_border.to_css(), width);
+        self.path(
+            PathSegment::polyline(&[(from.x, from.y), (to.x, to.y)]),
+            style,
+        );
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: impl Into<String>, font: Font, anchor: TextAnchor) {
+        self.styled_text(x, y, text, font, anchor, TextBaseline::Alphabetic);
+    }
+
+    fn styled_text(
+        &mut self,
+        x: f64,
+        y: f64,
+        text: impl Into<String>,
+        font: Font,
+        anchor: TextAnchor,
+        baseline: TextBaseline,
+    ) {
+        self.colored_text(
+            x,
+            y,
+            text,
+            font,
+            anchor,
+            baseline,
+            self.theme.text_color.to_css(),
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn colored_text(
+        &mut self,
+        x: f64,
+        y: f64,
+        text: impl Into<String>,
+        font: Font,
+        anchor: TextAnchor,
+        baseline: TextBaseline,
+        color: String,
+    ) {
+        self.commands.push(DrawCommand::Text {
+            x,
+            y,
+            text: text.into(),
+            font,
+            anchor,
+            baseline,
+            color,
+        });
+    }
+
+    /// Спрайт с левым верхним углом в `(x, y)`; монохромный — цветом текста
+    fn sprite(&mut self, sprite: &Sprite, reference: &SpriteRef, x: f64, y: f64) {
+        let image = match &sprite.data {
+            SpriteData::Gray { levels, pixels } => ImageData::Gray {
+                width: sprite.width,
+                height: sprite.height,
+                levels: *levels,
+                pixels: pixels.clone(),
+                color: reference
+                    .color
+                    .clone()
+                    .unwrap_or_else(|| self.theme.text_color.to_css()),
+            },
+            SpriteData::Svg(source) => ImageData::Svg {
+                source: standalone_svg(source),
+            },
+        };
+        self.commands.push(DrawCommand::Image {
+            x,
+            y,
+            width: f64::from(sprite.width) * reference.scale,
+            height: f64::from(sprite.height) * reference.scale,
+            image,
+        });
+    }
+
+    /// Подпись со спрайтами: строки сверху вниз от `top`, каждая строка
+    /// выравнивается относительно `x` по `anchor` (как в SVG)
+    fn sprite_label(&mut self, label: &str, x: f64, top: f64, anchor: TextAnchor, font_size: f64) {
+        let metrics = EstimatedMetrics::default();
+        let sprites = self.sprites;
+        let mut line_top = top;
+        for line in label_lines(label) {
+            let size = line_size(&line, sprites, font_size, &metrics);
+            let mut cursor = match anchor {
+                TextAnchor::Start => x,
+                TextAnchor::End => x - size.width,
+                TextAnchor::Middle => x - size.width / 2.0,
+            };
+            for part in LabelPart::split(&line) {
+                match part {
+                    LabelPart::Text(text) => {
+                        if !text.trim().is_empty() {
+                            let font = self.font(font_size);
+                            self.styled_text(
+                                cursor,
+                                line_top + size.height / 2.0,
+                                text,
+                                font,
+                                TextAnchor::Start,
+                                TextBaseline::Middle,
+                            );
+                        }
+                        cursor += metrics.text_width(text, font_size);
+                    }
+                    LabelPart::Sprite(reference) => {
+                        let (Some(sprite), Some(sprite_size)) = (
+                            sprites.get(&reference.name),
+                            sprite_size(sprites, &reference),
+                        ) else {
+                            continue;
+                        };
+                        let y = line_top + (size.height - sprite_size.height) / 2.0;
+                        self.sprite(sprite, &reference, cursor, y);
+                        cursor += sprite_size.width;
+                    }
+                }
+            }
+            line_top += size.height;
+        }
+    }
+
+    /// Подпись со спрайтами, центрированная в `(cx, cy)`
+    fn centered_sprite_label(&mut self, label: &str, cx: f64, cy: f64) {
+        let font_size = self.theme.font_size;
+        let metrics = EstimatedMetrics::default();
+        let height = label_size(label, self.sprites, font_size, &metrics).height;
+        self.sprite_label(label, cx, cy - height / 2.0, TextAnchor::Middle, font_size);
+    }
+
+    /// Шрифт темы заданного кегля
+    fn font(&self, size: f64) -> Font {
+        Font::new(self.theme.font_family.as_str(), size)
+    }
+
+    /// Узел диаграммы: цвета темы и тень
+    fn node_style(&self, stroke_width: f64) -> Style {
+        Style::fill_and_stroke(
+            self.theme.node_background.to_css(),
+            self.theme.node_border.to_css(),
+            stroke_width,
+        )
+        .with_shadow(self.theme.shadow)
+    }
+
+    fn element(&mut self, element: &LayoutElement, ids: &mut ElementIds) {
+        let theme = self.theme;
+        let bounds = &element.bounds;
+        match &element.element_type {
+            ElementType::Rectangle {
+                label,
+                corner_radius,
+            } => self.labeled_rect(bounds, label, *corner_radius),
+            ElementType::RoundedRectangle => {
+                self.labeled_rect(bounds, element.text.as_deref().unwrap_or(""), 8.0)
+            }
+            ElementType::Ellipse { label } => {
+                let Point { x: cx, y: cy } = bounds.center();
+                self.ellipse(
+                    cx,
+                    cy,
+                    bounds.width / 2.0,
+                    bounds.height / 2.0,
+                    self.node_style(1.0),
+                );
+                if let Some(label) = label.as_deref().filter(|l| self.sprites.is_used_in(l)) {
+                    self.centered_sprite_label(label, cx, cy);
+                } else if let Some(label) = label {
+                    let font = self.font(theme.font_size);
+                    self.styled_text(
+                        cx,
+                        cy,
+                        label.as_str(),
+                        font,
+                        TextAnchor::Middle,
+                        TextBaseline::Middle,
+                    );
+                }
+            }
+            ElementType::InitialState => {
+                let Point { x: cx, y: cy } = bounds.center();
+                let r = bounds.width.min(bounds.height) / 2.0;
+                self.ellipse(cx, cy, r, r, Style::fill(theme.node_border.to_css()));
+            }
+            ElementType::FinalState => {
+                let Point { x: cx, y: cy } = bounds.center();
+                let r = bounds.width.min(bounds.height) / 2.0;
+                let outer = Style::fill_and_stroke(
+                    theme.background_color.to_css(),
+                    theme.node_border.to_css(),
+                    1.5,
+                );
+                self.ellipse(cx, cy, r, r, outer);
+                self.ellipse(
+                    cx,
+                    cy,
+                    r * 0.6,
+                    r * 0.6,
+                    Style::fill(theme.node_border.to_css()),
+                );
+            }
+            ElementType::State { name, description } => {
+                self.state(bounds, name, description.as_deref())
+            }
+            ElementType::CompositeState {
+                name,
+                header_height,
+            } => {
+                self.rect(bounds, 10.0, self.node_style(1.5));
+                let font = self.font(theme.font_size + 1.0).bold();
+                self.styled_text(
+                    bounds.x + bounds.width / 2.0,
+                    bounds.y + header_height / 2.0 + 2.0,
+                    name.as_str(),
+                    font,
+                    TextAnchor::Middle,
+                    TextBaseline::Middle,
+                );
+                let separator_y = bounds.y + header_height;
+                self.line(
+                    Point::new(bounds.x, separator_y),
+                    Point::new(bounds.x + bounds.width, separator_y),
+                    1.0,
+                );
+            }
+            ElementType::Actor { label } => self.actor(bounds, label),
+            ElementType::System { title } => {
+                let style = Style::fill_and_stroke(
+                    theme.node_background.to_css(),
+                    theme.node_border.to_css(),
+                    1.0,
+                );
+                self.rect(bounds, 0.0, style);
+                let font = self.font(theme.font_size + 1.0).bold();
+                self.text(
+                    bounds.x + bounds.width / 2.0,
+                    bounds.y + 25.0 / 2.0 + 5.0,
+                    title.as_str(),
+                    font,
+                    TextAnchor::Middle,
+                );
+            }
+            ElementType::Edge {
+                points,
+                label,
+                arrow_start,
+                arrow_end,
+                dashed,
+                edge_type,
+                from_cardinality,
+                to_cardinality,
+                curve,
+                label_positions,
+            } => {
+                let edge = Edge {
+                    points,
+                    curve,
+                    label: label.as_deref(),
+                    autonumber: element.properties.get("autonumber").map(|s| s.as_str()),
+                    arrow_start: *arrow_start,
+                    arrow_end: *arrow_end,
+                    dashed: *dashed,
+                    edge_type: *edge_type,
+                    from_cardinality: from_cardinality.as_deref(),
+                    to_cardinality: to_cardinality.as_deref(),
+                    label_positions,
+                };
+                self.edge(&edge);
+            }
+            ElementType::Text { text, font_size } => {
+                let font = self.font(*font_size);
+                self.text(
+                    bounds.x,
+                    bounds.y + font_size,
+                    text.as_str(),
+                    font,
+                    TextAnchor::Start,
+                );
+            }
+            ElementType::Group { label, children } => {
+                self.rect(bounds, 0.0, Style::stroke(theme.node_border.to_css(), 1.0));
+                if let Some(label) = label {
+                    let header = Rect::new(bounds.x, bounds.y, bounds.width, 20.0);
+                    self.rect(&header, 0.0, Style::fill(theme.node_background.to_css()));
+                    let font = self.font(theme.font_size).bold();
+                    self.text(
+                        bounds.x + 5.0,
+                        bounds.y + 14.0,
+                        label.as_str(),
+                        font,
+                        TextAnchor::Start,
+                    );
+                }
+                for child in children {
+                    self.commands
+                        .push(element_group(child, theme, self.sprites, ids));
+                }
+            }
+            ElementType::Fragment {
+                fragment_type,
+                sections,
+            } => self.fragment(bounds, fragment_type, sections, ids),
+            ElementType::Activation => {
+                let style = Style::fill_and_stroke(
+                    theme.background_color.to_css(),
+                    theme.node_border.to_css(),
+                    1.0,
+                );
+                self.rect(bounds, 0.0, style);
+            }
+            ElementType::Path => {
+                if let Some(path_data) = element.properties.get("path") {
+                    let style = Style::stroke(theme.node_border.to_css(), 1.0);
+                    self.path(PathSegment::parse_svg(path_data), style);
+                }
+            }
+            ElementType::ClassBox {
+                classifier_type,
+                name,
+                stereotype,
+                fields,
+                methods,
+            } => self.class_box(
+                bounds,
+                *classifier_type,
+                name,
+                stereotype.as_deref(),
+                fields,
+                methods,
+            ),
+            ElementType::ParticipantBox => {
+                let fill = element
+                    .properties
+                    .get("color")
+                    .map_or("#EEEEEE", |s| s.as_str());
+                self.rect(
+                    bounds,
+                    0.0,
+                    Style::fill_and_stroke(fill, theme.node_border.to_css(), 1.0),
+                );
+                if let Some(title) = element.text.as_deref() {
+                    let font = self.font(theme.font_size + 1.0).bold();
+                    self.text(
+                        bounds.x + bounds.width / 2.0,
+                        bounds.y + 16.0,
+                        title,
+                        font,
+                        TextAnchor::Middle,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Прямоугольник с подписью по центру
+    fn labeled_rect(&mut self, bounds: &Rect, label: &str, corner_radius: f64) {
+        self.rect(bounds, corner_radius, self.node_style(0.5));
+        if self.sprites.is_used_in(label) {
+            let Point { x: cx, y: cy } = bounds.center();
+            self.centered_sprite_label(label, cx, cy);
+            return;
+        }
+        let font = self.font(self.theme.font_size);
+        self.styled_text(
+            bounds.x + bounds.width / 2.0,
+            bounds.y + bounds.height / 2.0,
+            label,
+            font,
+            TextAnchor::Middle,
+            TextBaseline::Middle,
+        );
+    }
+
+    /// Состояние: скруглённый прямоугольник, название, разделитель, описание
+    fn state(&mut self, bounds: &Rect, name: &str, description: Option<&str>) {
+        let header_height = 25.0;
+        self.rect(bounds, 10.0, self.node_style(1.0));
+        let font = self.font(self.theme.font_size).bold();
+        self.styled_text(
+            bounds.x + bounds.width / 2.0,
+            bounds.y + header_height / 2.0 + 5.0,
+            name,
+            font,
+            TextAnchor::Middle,
+            TextBaseline::Middle,
+        );
+        let separator_y = bounds.y + header_height;
+        self.line(
+            Point::new(bounds.x, separator_y),
+            Point::new(bounds.x + bounds.width, separator_y),
+            0.5,
+        );
+        if let Some(description) = description {
+            let font = self.font(self.theme.font_size - 2.0);
+            self.text(
+                bounds.x + 5.0,
+                separator_y + 15.0,
+                description,
+                font,
+                TextAnchor::Start,
+            );
+        }
+    }
+
+    /// Человечек актёра с именем внизу
+    fn actor(&mut self, bounds: &Rect, label: &str) {
+        let theme = self.theme;
+        let cx = bounds.x + bounds.width / 2.0;
+        let (head_radius, body_length, arm_width, leg_length, leg_spread) =
+            (8.0, 20.0, 18.0, 15.0, 10.0);
+
+        let head_cy = bounds.y + head_radius + 2.0;
+        let neck_y = head_cy + head_radius;
+        let waist_y = neck_y + body_length;
+        let arms_y = neck_y + body_length * 0.3;
+        let feet_y = waist_y + leg_length;
+
+        let head = Style::fill_and_stroke(
+            theme.node_background.to_css(),
+            theme.node_border.to_css(),
+            1.5,
+        );
+        self.ellipse(cx, head_cy, head_radius, head_radius, head);
+        self.line(Point::new(cx, neck_y), Point::new(cx, waist_y), 1.5);
+        self.line(
+            Point::new(cx - arm_width / 2.0, arms_y),
+            Point::new(cx + arm_width / 2.0, arms_y),
+            1.5,
+        );
+        self.line(
+            Point::new(cx, waist_y),
+            Point::new(cx - leg_spread, feet_y),
+            1.5,
+        );
+        self.line(
+            Point::new(cx, waist_y),
+            Point::new(cx + leg_spread, feet_y),
+            1.5,
+        );
+
+        let font = self.font(theme.font_size);
+        self.text(cx, feet_y + 15.0, label, font, TextAnchor::Middle);
+    }
+
+    /// Линия со стрелками, подписью, номером и кардинальностями
+    fn edge(&mut self, edge: &Edge<'_>) {
+        let theme = self.theme;
+        let points = edge.points;
+        if points.len() < 2 {
+            return;
+        }
+
+        // Кривые Безье от роутера: p0, затем тройки (c1, c2, p1)
+        let curve = edge.curve;
+        let segments = if curve.len() >= 4 && (curve.len() - 1) % 3 == 0 {
+            let mut segments = vec![PathSegment::MoveTo {
+                x: curve[0].x,
+                y: curve[0].y,
+            }];
+            segments.extend(curve[1..].chunks(3).map(|c| PathSegment::CubicTo {
+                x1: c[0].x,
+                y1: c[0].y,
+                x2: c[1].x,
+                y2: c[1].y,
+                x: c[2].x,
+                y: c[2].y,
+            }));
+            segments
+        } else {
+            let points: Vec<(f64, f64)> = points.iter().map(|p| (p.x, p.y)).collect();
+            PathSegment::polyline(&points)
+        };
+
+        // Сообщения толще lifelines; пунктир 2,2 у сообщений и 5,5 у lifelines
+        let has_arrow = edge.arrow_end || edge.arrow_start;
+        let mut style = Style::stroke(
+            theme.arrow_color.to_css(),
+            if has_arrow { 1.0 } else { 0.5 },
+        );
+        if edge.dashed {
+            style = style.with_dash(if has_arrow { &[2.0, 2.0] } else { &[5.0, 5.0] });
+        }
+
+        let marker = |kind| {
+            Marker::new(
+                kind,
+                theme.arrow_color.to_css(),
+                theme.background_color.to_css(),
+            )
+        };
+        let marker_end = edge
+            .arrow_end
+            .then_some(edge.edge_type)
+            .and_then(|edge_type| match edge_type {
+                EdgeType::Inheritance | EdgeType::Realization => {
+                    Some(marker(MarkerKind::Inheritance))
+                }
+                EdgeType::Dependency => Some(marker(MarkerKind::OpenArrow)),
+                EdgeType::Link => None,
+                _ => Some(marker(MarkerKind::Arrow)),
+            });
+        let marker_start = edge.arrow_start.then(|| match edge.edge_type {
+            EdgeType::Composition => marker(MarkerKind::Composition),
+            EdgeType::Aggregation => marker(MarkerKind::Aggregation),
+            _ => marker(MarkerKind::Arrow),
+        });
+        self.commands.push(DrawCommand::Path {
+            segments: self.outline(segments),
+            style,
+            marker_start,
+            marker_end,
+        });
+
+        // PlantUML использует font-size 13 для сообщений
+        let font_size = 13.0;
+        if edge.label.is_some() || edge.autonumber.is_some() {
+            let (base_x, text_y, anchor) =
+                edge_label_anchor(points, edge.label, edge.label_positions);
+            let anchor = text_anchor(anchor);
+            let mut text_x = base_x;
+            if let Some(number) = edge.autonumber {
+                let font = self.font(font_size);
+                self.text(base_x, text_y, number, font, anchor);
+                // ~7px на символ + отступ 3px
+                text_x += number.len() as f64 * 7.0 + 3.0;
+            }
+            if let Some(label) = edge.label.filter(|label| self.sprites.is_used_in(label)) {
+                // Нижний край подписи на уровне базовой линии последней строки
+                let metrics = EstimatedMetrics::default();
+                let height = label_size(label, self.sprites, font_size, &metrics).height;
+                let top = text_y + font_size * 0.3 - height;
+                self.sprite_label(label, text_x, top, anchor, font_size);
+            } else if let Some(label) = edge.label {
+                // Многострочная подпись: последняя строка на text_y, остальные выше
+                let label = label.replace("\\n", "\n");
+                let lines: Vec<&str> = label.split('\n').collect();
+                let line_height = font_size + 2.0;
+                let top_y = text_y - (lines.len() as f64 - 1.0) * line_height;
+                for (i, line) in lines.iter().enumerate() {
+                    let font = self.font(font_size);
+                    self.text(text_x, top_y + i as f64 * line_height, *line, font, anchor);
+                }
+            }
+        }
+
+        let cardinalities = [
+            (
+                edge.from_cardinality,
+                true,
+                edge.label_positions.from_cardinality,
+            ),
+            (
+                edge.to_cardinality,
+                false,
+                edge.label_positions.to_cardinality,
+            ),
+        ];
+        for (cardinality, at_start, center) in cardinalities {
+            if let Some(cardinality) = cardinality {
+                let (x, y, anchor) = cardinality_anchor(points, at_start, center);
+                let font = self.font(font_size);
+                self.styled_text(
+                    x,
+                    y,
+                    cardinality,
+                    font,
+                    text_anchor(anchor),
+                    TextBaseline::Middle,
+                );
+            }
+        }
+    }
+
+    /// Combined fragment: рамка, пятиугольный заголовок, секции
+    fn fragment(
+        &mut self,
+        bounds: &Rect,
+        fragment_type: &str,
+        sections: &[FragmentSection],
+        ids: &mut ElementIds,
+    ) {
+        let theme = self.theme;
+        self.rect(bounds, 0.0, Style::stroke(theme.node_border.to_css(), 1.5));
+
+        let label_width = (fragment_type.len() as f64 * 8.0 + 16.0).max(40.0);
+        let (label_height, notch_size) = (20.0, 8.0);
+        let pentagon = PathSegment::polygon(&[
+            (bounds.x, bounds.y),
+            (bounds.x + label_width, bounds.y),
+            (bounds.x + label_width, bounds.y + label_height - notch_size),
+            (bounds.x + label_width - notch_size, bounds.y + label_height),
+            (bounds.x, bounds.y + label_height),
+        ]);
+        let style = Style::fill_and_stroke(
+            theme.node_background.to_css(),
+            theme.node_border.to_css(),
+            1.5,
+        );
+        self.path(pentagon, style);
+
+        let font = self.font(theme.font_size).bold();
+        self.text(
+            bounds.x + 5.0,
+            bounds.y + 14.0,
+            fragment_type,
+            font,
+            TextAnchor::Start,
+        );
+
+        if let Some(condition) = sections.first().and_then(|s| s.condition.as_deref()) {
+            let font = self.font(theme.font_size);
+            self.text(
+                bounds.x + label_width + 10.0,
+                bounds.y + 14.0,
+                format!("[{}]", condition),
+                font,
+                TextAnchor::Start,
+            );
+        }
+
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 {
+                // Разделитель над первым сообщением секции, условие — над ним
+                let separator_y = section.start_y - 28.0;
+                let line = PathSegment::polyline(&[
+                    (bounds.x, separator_y),
+                    (bounds.x + bounds.width, separator_y),
+                ]);
+                let style = Style::stroke(theme.node_border.to_css(), 1.0).with_dash(&[5.0, 3.0]);
+                self.path(line, style);
+
+                let condition = section.condition.as_deref().unwrap_or("else");
+                let font = self.font(theme.font_size - 1.0);
+                self.text(
+                    bounds.x + 5.0,
+                    separator_y - 5.0,
+                    format!("[{}]", condition),
+                    font,
+                    TextAnchor::Start,
+                );
+            }
+            for child in &section.children {
+                self.commands
+                    .push(element_group(child, theme, self.sprites, ids));
+            }
+        }
+    }
+
+    /// Класс: рамка, иконка, стереотип, имя, поля и методы
+    fn class_box(
+        &mut self,
+        bounds: &Rect,
+        classifier_type: ClassifierKind,
+        name: &str,
+        stereotype: Option<&str>,
+        fields: &[ClassMember],
+        methods: &[ClassMember],
+    ) {
+        let theme = self.theme;
+        let (padding, line_height, icon_size) = (5.0, 16.0, 11.0);
+
+        self.rect(bounds, 2.5, self.node_style(0.5));
+        let mut current_y = bounds.y + padding;
+
+        let icon_x = bounds.x + padding + icon_size;
+        let icon_y = current_y + icon_size;
+        let (icon_fill, icon_letter) = match classifier_type {
+            ClassifierKind::Class => ("#ADD1B2", "C"),
+            ClassifierKind::Interface => ("#B4A7E5", "I"),
+            ClassifierKind::AbstractClass => ("#A9DCDF", "A"),
+            ClassifierKind::Enum => ("#EB937F", "E"),
+            ClassifierKind::Annotation => ("#FFDD8C", "@"),
+            ClassifierKind::Entity => ("#CCCCCC", "E"),
+        };
+        let icon = Style::fill_and_stroke(icon_fill, theme.node_border.to_css(), 1.0);
+        self.ellipse(icon_x, icon_y, icon_size, icon_size, icon);
+        let font = self.font(12.0).bold();
+        self.colored_text(
+            icon_x,
+            icon_y + 4.0,
+            icon_letter,
+            font,
+            TextAnchor::Middle,
+            TextBaseline::Alphabetic,
+            "#000000".to_string(),
+        );
+
+        let name_x = icon_x + icon_size + 5.0;
+        if let Some(stereotype) = stereotype {
+            let font = self.font(10.0);
+            self.text(
+                name_x,
+                current_y + 10.0,
+                format!("«{}»", stereotype),
+                font,
+                TextAnchor::Start,
+            );
+            current_y += 12.0;
+        }
+
+        let font = self.font(theme.font_size).bold();
+        self.text(
+            name_x,
+            current_y + line_height - 2.0,
+            name,
+            font,
+            TextAnchor::Start,
+        );
+        current_y += line_height + padding;
+
+        for members in [fields, methods] {
+            self.line(
+                Point::new(bounds.x + 1.0, current_y),
+                Point::new(bounds.x + bounds.width - 1.0, current_y),
+                0.5,
+            );
+            current_y += padding;
+            for member in members {
+                self.class_member(bounds.x + padding, current_y, member);
+                current_y += line_height;
+            }
+        }
+    }
+
+    /// Член класса с иконкой видимости
+    fn class_member(&mut self, x: f64, y: f64, member: &ClassMember) {
+        let icon_radius = 3.0;
+        let icon_x = x + icon_radius;
+        let (fill, stroke) = match member.visibility {
+            MemberVisibility::Public => ("#84BE84", "#038048"),
+            MemberVisibility::Private => ("#C82829", "#C80000"),
+            MemberVisibility::Protected => ("#FFCC00", "#B38600"),
+            MemberVisibility::Package => ("#66CCFF", "#0099CC"),
+        };
+        let style = Style::fill_and_stroke(fill, stroke, 1.0);
+        self.ellipse(icon_x, y + 8.0, icon_radius, icon_radius, style);
+
+        let mut font = self.font(self.theme.font_size);
+        if member.is_static {
+            font = font.underline();
+        }
+        if member.is_abstract {
+            font = font.italic();
+        }
+        self.text(
+            icon_x + icon_radius + 5.0,
+            y + 12.0,
+            member.text.as_str(),
+            font,
+            TextAnchor::Start,
+        );
+    }
+}
+
+/// Параметры линии для [`Painter::edge`]
+struct Edge<'a> {
+    points: &'a [Point],
+    curve: &'a [Point],
+    label: Option<&'a str>,
+    autonumber: Option<&'a str>,
+    arrow_start: bool,
+    arrow_end: bool,
+    dashed: bool,
+    edge_type: EdgeType,
+    from_cardinality: Option<&'a str>,
+    to_cardinality: Option<&'a str>,
+    label_positions: &'a EdgeLabelPositions,
+}
+
+/// Контур с дрожанием [`Sketch`]: подконтуры из отрезков — дрожащие ломаные,
+/// с кривыми — дрожащие кривые; seed — координаты контура, как в SVG
+fn sketched(segments: &[PathSegment]) -> Vec<PathSegment> {
+    let seed: Vec<f64> = segments
+        .iter()
+        .filter_map(PathSegment::end_point)
+        .flat_map(|(x, y)| [x, y])
+        .collect();
+    let mut sketch = Sketch::seeded(&seed);
+
+    // Текущий подконтур: начало и куски (c1, c2, конец); отрезок — кусок
+    // с контрольными точками на концах
+    let mut start: Option<Point> = None;
+    let mut pieces: Vec<[Point; 3]> = Vec::new();
+    let mut curved = false;
+    let mut d = String::new();
+    let mut flush = |start: &mut Option<Point>,
+                     pieces: &mut Vec<[Point; 3]>,
+                     curved: bool,
+                     closed: bool| {
+        let Some(first) = start.take() else {
+            return;
+        };
+        let sub = if curved {
+            let mut curve = vec![first];
+            curve.extend(pieces.iter().flatten());
+            let mut sub = sketch.curve(&curve);
+            if closed {
+                sub.push_str(" Z");
+            }
+            sub
+        } else {
+            let mut points = vec![first];
+            points.extend(pieces.iter().map(|piece| piece[2]));
+            if closed {
+                // Замыкающий отрезок polygon добавит сам
+                if points.len() > 1 && points.last() == Some(&first) {
+                    points.pop();
+                }
+                sketch.polygon(&points)
+            } else {
+                sketch.polyline(&points)
+            }
+        };
+        pieces.clear();
+        d.push_str(&sub);
+        d.push(' ');
+    };
+
+    for segment in segments {
+        let from = pieces.last().map(|piece| piece[2]).or(start);
+        match *segment {
+            PathSegment::MoveTo { x, y } => {
+                flush(&mut start, &mut pieces, curved, false);
+                start = Some(Point::new(x, y));
+                curved = false;
+            }
+            PathSegment::LineTo { x, y } => {
+                let to = Point::new(x, y);
+                pieces.push([from.unwrap_or(to), to, to]);
+            }
+            PathSegment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                pieces.push([Point::new(x1, y1), Point::new(x2, y2), Point::new(x, y)]);
+                curved = true;
+            }
+            PathSegment::Close => flush(&mut start, &mut pieces, curved, true),
+        }
+    }
+    flush(&mut start, &mut pieces, curved, false);
+    PathSegment::parse_svg(&d)
+}
+
+/// Значение CSS `text-anchor` в [`TextAnchor`]
+fn text_anchor(anchor: &str) -> TextAnchor {
+    match anchor {
+        "middle" => TextAnchor::Middle,
+        "end" => TextAnchor::End,
+        _ => TextAnchor::Start,
+    }
+}
+
+/// Область отсечения по границам элемента
+impl From<&Rect> for ClipRect {
+    fn from(rect: &Rect) -> Self {
+        Self {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn layout(elements: Vec<LayoutElement>) -> LayoutResult {
+        let mut layout = LayoutResult::empty();
+        layout.elements = elements;
+        layout.calculate_bounds();
+        layout
+    }
+
+    fn edge(edge_type: EdgeType, arrow_start: bool) -> LayoutElement {
+        LayoutElement::new(
+            "e",
+            Rect::new(0.0, 0.0, 100.0, 0.0),
+            ElementType::Edge {
+                points: vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0)],
+                label: Some("hello".to_string()),
+                arrow_start,
+                arrow_end: true,
+                dashed: true,
+                edge_type,
+                from_cardinality: None,
+                to_cardinality: Some("1..*".to_string()),
+                curve: Vec::new(),
+                label_positions: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_rectangle_commands() {
+        let mut element = LayoutElement::new(
+            "participant_Alice",
+            Rect::new(10.0, 10.0, 80.0, 30.0),
+            ElementType::Rectangle {
+                label: "Alice".to_string(),
+                corner_radius: 0.0,
+            },
+        );
+        element.properties = HashMap::from([
+            ("class".to_string(), "participant".to_string()),
+            ("line".to_string(), "2".to_string()),
+            ("link".to_string(), "https://example.com".to_string()),
+        ]);
+        let list = DisplayListRenderer::new().render(&layout(vec![element]), &Theme::default());
+
+        assert_eq!(list.commands.len(), 1);
+        let DrawCommand::Group {
+            id,
+            class,
+            line,
+            link,
+            commands,
+            ..
+        } = &list.commands[0]
+        else {
+            panic!("ожидалась группа");
+        };
+        assert_eq!(id.as_deref(), Some("participant_Alice"));
+        assert_eq!(class.as_deref(), Some("participant"));
+        assert_eq!(*line, Some(2));
+        assert_eq!(
+            link.as_ref().map(|l| l.url.as_str()),
+            Some("https://example.com")
+        );
+        assert!(matches!(
+            &commands[0],
+            DrawCommand::Path { style, .. } if style.fill.is_some() && style.stroke.is_some()
+        ));
+        assert!(matches!(
+            &commands[1],
+            DrawCommand::Text { text, anchor: TextAnchor::Middle, .. } if text == "Alice"
+        ));
+    }
+
+    #[test]
+    fn test_sprite_label_images() {
+        let element = LayoutElement::new(
+            "web",
+            Rect::new(0.0, 0.0, 80.0, 40.0),
+            ElementType::Rectangle {
+                label: "<$logo>\\nWeb".to_string(),
+                corner_radius: 0.0,
+            },
+        );
+        let mut sprites = SpriteRegistry::new();
+        sprites.insert("logo", Sprite::svg("<svg width=\"20\" height=\"10\"/>"));
+        let list = DisplayListRenderer::new()
+            .with_sprites(sprites)
+            .render(&layout(vec![element]), &Theme::default());
+
+        let mut images = Vec::new();
+        let mut texts = Vec::new();
+        list.visit(&mut |command| match command {
+            DrawCommand::Image {
+                x,
+                width,
+                height,
+                image,
+                ..
+            } => images.push((*x, *width, *height, image.clone())),
+            DrawCommand::Text { text, .. } => texts.push(text.clone()),
+            _ => {}
+        });
+        assert_eq!(images.len(), 1);
+        let (x, width, height, image) = &images[0];
+        // Спрайт центрирован над текстом, как в SVG
+        assert_eq!((*x, *width, *height), (30.0, 20.0, 10.0));
+        assert!(matches!(image, ImageData::Svg { source } if source.contains("xmlns")));
+        assert_eq!(texts, vec!["Web"]);
+    }
+
+    #[test]
+    fn test_handwritten_outlines() {
+        let element = LayoutElement::new(
+            "box",
+            Rect::new(10.0, 10.0, 80.0, 30.0),
+            ElementType::Rectangle {
+                label: "Box".to_string(),
+                corner_radius: 0.0,
+            },
+        );
+        let layout = layout(vec![element, edge(EdgeType::Association, false)]);
+        let theme = Theme {
+            handwritten: true,
+            ..Theme::default()
+        };
+        let paths = |theme: &Theme| {
+            let list = DisplayListRenderer::new().render(&layout, theme);
+            let mut paths = Vec::new();
+            list.visit(&mut |command| {
+                if let DrawCommand::Path { segments, .. } = command {
+                    paths.push(segments.clone());
+                }
+            });
+            paths
+        };
+
+        let sketched = paths(&theme);
+        let plain = paths(&Theme::default());
+        assert_eq!(sketched.len(), plain.len());
+        for (sketched, plain) in sketched.iter().zip(&plain) {
+            assert_ne!(sketched, plain);
+            assert!(sketched
+                .iter()
+                .any(|segment| matches!(segment, PathSegment::CubicTo { .. })));
+        }
+        // Прямоугольник остаётся замкнутым, дрожание детерминировано
+        assert!(sketched[0].ends_with(&[PathSegment::Close]));
+        assert_eq!(sketched, paths(&theme));
+    }
+
+    #[test]
+    fn test_edge_markers_and_labels() {
+        let theme = Theme::default();
+        let list = DisplayListRenderer::new()
+            .render(&layout(vec![edge(EdgeType::Composition, true)]), &theme);
+        let mut markers = Vec::new();
+        let mut texts = Vec::new();
+        list.visit(&mut |command| match command {
+            DrawCommand::Path {
+                style,
+                marker_start,
+                marker_end,
+                ..
+            } => {
+                assert_eq!(style.stroke.as_ref().unwrap().dash, vec![2.0, 2.0]);
+                markers.push((marker_start.clone(), marker_end.clone()));
+            }
+            DrawCommand::Text { text, .. } => texts.push(text.clone()),
+            DrawCommand::Group { .. } | DrawCommand::Image { .. } => {}
+        });
+        let (start, end) = &markers[0];
+        assert_eq!(start.as_ref().unwrap().kind, MarkerKind::Composition);
+        assert_eq!(end.as_ref().unwrap().kind, MarkerKind::Arrow);
+        assert_eq!(texts, vec!["hello", "1..*"]);
+    }
+
+    #[test]
+    fn test_nested_fragment_children() {
+        let fragment = LayoutElement::new(
+            "frag",
+            Rect::new(0.0, 0.0, 200.0, 100.0),
+            ElementType::Fragment {
+                fragment_type: "alt".to_string(),
+                sections: vec![
+                    FragmentSection {
+                        condition: Some("ok".to_string()),
+                        start_y: 30.0,
+                        end_y: 60.0,
+                        children: vec![edge(EdgeType::Association, false)],
+                    },
+                    FragmentSection {
+                        condition: None,
+                        start_y: 70.0,
+                        end_y: 100.0,
+                        children: vec![edge(EdgeType::Association, false)],
+                    },
+                ],
+            },
+        );
+        let list = DisplayListRenderer::new().render(&layout(vec![fragment]), &Theme::default());
+        let mut ids = Vec::new();
+        let mut texts = Vec::new();
+        list.visit(&mut |command| match command {
+            DrawCommand::Group { id, .. } => ids.push(id.clone().unwrap()),
+            DrawCommand::Text { text, .. } => texts.push(text.clone()),
+            DrawCommand::Path { .. } | DrawCommand::Image { .. } => {}
+        });
+        assert_eq!(ids, vec!["frag", "e", "e-2"]);
+        assert!(texts.contains(&"[ok]".to_string()));
+        assert!(texts.contains(&"[else]".to_string()));
+    }
+}
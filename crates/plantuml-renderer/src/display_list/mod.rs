@@ -0,0 +1,1025 @@
+//! Display list: независимый от backend список команд рисования
+//!
+//! [`DisplayListRenderer`] переводит [`LayoutResult`](crate::LayoutResult) в
+//! плоский список [`DrawCommand`]: контуры с заливкой, обводкой и маркерами,
+//! текст со шрифтом, изображения спрайтов и группы с отсечением. По этому списку рисуют все
+//! backend'ы, кроме SVG: WASM canvas, нативный GUI, растеризация PNG, JPEG и
+//! WebP (`RasterRenderer`, `PngRenderer::render_display_list`), EPS и
+//! LaTeX/TikZ. SVG строит [`SvgRenderer`](crate::SvgRenderer) с той же
+//! геометрией: кроме фигур, он выводит `<marker>`, CSS и скрипты
+//! интерактивного режима, которых в display list нет.
+//!
+//! Список сериализуется через serde (`"type"` — тег команды, `"op"` — тег
+//! сегмента контура) и передаётся в JavaScript как JSON. Рукописный режим
+//! (`skinparam handwritten`) уже учтён в контурах команд.
+
+mod builder;
+
+#[cfg(feature = "png")]
+pub(crate) mod raster;
+
+use serde::{Deserialize, Serialize};
+
+pub use builder::DisplayListRenderer;
+
+/// Список команд рисования диаграммы
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayList {
+    /// Левый край видимой области в координатах диаграммы
+    pub x: f64,
+    /// Верхний край видимой области
+    pub y: f64,
+    /// Ширина видимой области
+    pub width: f64,
+    /// Высота видимой области
+    pub height: f64,
+    /// Цвет фона (None = прозрачный)
+    pub background: Option<String>,
+    /// Команды в порядке рисования (снизу вверх)
+    pub commands: Vec<DrawCommand>,
+}
+
+impl DisplayList {
+    /// Пустой список с заданной видимой областью
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            background: None,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Обходит команды в порядке рисования, включая вложенные в группы
+    pub fn visit(&self, f: &mut impl FnMut(&DrawCommand)) {
+        fn walk(commands: &[DrawCommand], f: &mut impl FnMut(&DrawCommand)) {
+            for command in commands {
+                f(command);
+                if let DrawCommand::Group { commands, .. } = command {
+                    walk(commands, f);
+                }
+            }
+        }
+        walk(&self.commands, f);
+    }
+}
+
+/// Команда рисования
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DrawCommand {
+    /// Контур с заливкой и/или обводкой
+    Path {
+        /// Сегменты контура
+        segments: Vec<PathSegment>,
+        /// Заливка, обводка и тень
+        style: Style,
+        /// Маркер в начале контура
+        marker_start: Option<Marker>,
+        /// Маркер в конце контура
+        marker_end: Option<Marker>,
+    },
+    /// Строка текста
+    Text {
+        /// Точка привязки по горизонтали
+        x: f64,
+        /// Точка привязки по вертикали
+        y: f64,
+        /// Текст
+        text: String,
+        /// Шрифт
+        font: Font,
+        /// Выравнивание относительно `x`
+        anchor: TextAnchor,
+        /// Базовая линия относительно `y`
+        baseline: TextBaseline,
+        /// Цвет текста
+        color: String,
+    },
+    /// Изображение (спрайт), растянутое на прямоугольник
+    Image {
+        /// Левый край
+        x: f64,
+        /// Верхний край
+        y: f64,
+        /// Ширина
+        width: f64,
+        /// Высота
+        height: f64,
+        /// Содержимое
+        image: ImageData,
+    },
+    /// Группа команд элемента диаграммы
+    Group {
+        /// Уникальный id элемента
+        id: Option<String>,
+        /// Семантический CSS-класс (`participant`, `message`, ...)
+        class: Option<String>,
+        /// Строка объявления в исходном коде
+        line: Option<u32>,
+        /// Гиперссылка `[[url подсказка]]`
+        link: Option<Link>,
+        /// Область отсечения
+        clip: Option<ClipRect>,
+        /// Вложенные команды
+        commands: Vec<DrawCommand>,
+    },
+}
+
+/// Содержимое изображения
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ImageData {
+    /// Монохромный спрайт: уровни серого по строкам (`0` — прозрачный
+    /// пиксель, `levels - 1` — непрозрачный `color`)
+    Gray {
+        /// Ширина в пикселях
+        width: u32,
+        /// Высота в пикселях
+        height: u32,
+        /// Количество уровней
+        levels: u8,
+        /// Уровни пикселей
+        pixels: Vec<u8>,
+        /// Цвет (CSS)
+        color: String,
+    },
+    /// Документ SVG (с `xmlns`)
+    Svg {
+        /// Исходный текст
+        source: String,
+    },
+}
+
+impl ImageData {
+    /// Пиксели изображения: монохромный спрайт — в своём размере, SVG
+    /// растеризуется в `width`×`height`, если доступен resvg (features `png`
+    /// или `pdf`), иначе `None`
+    #[cfg_attr(not(any(feature = "png", feature = "pdf")), allow(unused_variables))]
+    pub fn pixels(&self, width: u32, height: u32) -> Option<Pixels> {
+        match self {
+            ImageData::Gray {
+                width: w,
+                height: h,
+                levels,
+                pixels,
+                color,
+            } => {
+                let [r, g, b] = parse_rgb(color)?;
+                let max = u32::from(levels.saturating_sub(1).max(1));
+                let rgba = pixels
+                    .iter()
+                    .map(|&level| {
+                        let alpha = (u32::from(level).min(max) * 255 / max) as u8;
+                        [r, g, b, alpha]
+                    })
+                    .collect();
+                Some(Pixels {
+                    width: *w,
+                    height: *h,
+                    rgba,
+                })
+            }
+            #[cfg(any(feature = "png", feature = "pdf"))]
+            ImageData::Svg { source } => rasterize_svg(source, width, height),
+            #[cfg(not(any(feature = "png", feature = "pdf")))]
+            ImageData::Svg { .. } => None,
+        }
+    }
+}
+
+/// Растеризует SVG в `width`×`height` пикселей
+#[cfg(any(feature = "png", feature = "pdf"))]
+fn rasterize_svg(source: &str, width: u32, height: u32) -> Option<Pixels> {
+    use resvg::{tiny_skia, usvg};
+
+    let tree = usvg::Tree::from_str(source, &usvg::Options::default()).ok()?;
+    let mut pixmap = tiny_skia::Pixmap::new(width.max(1), height.max(1))?;
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        pixmap.width() as f32 / size.width(),
+        pixmap.height() as f32 / size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Some(Pixels {
+        width: pixmap.width(),
+        height: pixmap.height(),
+        rgba: pixmap
+            .pixels()
+            .iter()
+            .map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect(),
+    })
+}
+
+/// Прямоугольник (x, y, ширина, высота)
+pub type Bounds = (f64, f64, f64, f64);
+
+/// Пиксели RGBA (без premultiply) построчно
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pixels {
+    /// Ширина
+    pub width: u32,
+    /// Высота
+    pub height: u32,
+    /// Цвета пикселей
+    pub rgba: Vec<[u8; 4]>,
+}
+
+impl Pixels {
+    /// Прямоугольники отрезков [`runs`](Self::runs) изображения, растянутого
+    /// на `bounds` (x, y, ширина, высота): `(прямоугольник, цвет)`
+    pub fn rects(&self, bounds: Bounds) -> Vec<(Bounds, [u8; 4])> {
+        let (x, y, width, height) = bounds;
+        let sx = width / f64::from(self.width.max(1));
+        let sy = height / f64::from(self.height.max(1));
+        self.runs()
+            .into_iter()
+            .map(|(px, py, len, rgba)| {
+                let rect = (
+                    x + f64::from(px) * sx,
+                    y + f64::from(py) * sy,
+                    f64::from(len) * sx,
+                    sy,
+                );
+                (rect, rgba)
+            })
+            .collect()
+    }
+
+    /// Отрезки строк из одинаковых непрозрачных пикселей:
+    /// `(x, y, длина, цвет)` — для backend'ов без растровых изображений
+    pub fn runs(&self) -> Vec<(u32, u32, u32, [u8; 4])> {
+        let mut runs = Vec::new();
+        for (y, row) in self.rgba.chunks(self.width.max(1) as usize).enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let start = x;
+                while x < row.len() && row[x] == row[start] {
+                    x += 1;
+                }
+                if row[start][3] > 0 {
+                    runs.push((start as u32, y as u32, (x - start) as u32, row[start]));
+                }
+            }
+        }
+        runs
+    }
+}
+
+/// Сегмент контура в абсолютных координатах
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PathSegment {
+    /// Начало подконтура
+    MoveTo { x: f64, y: f64 },
+    /// Отрезок
+    LineTo { x: f64, y: f64 },
+    /// Кубическая кривая Безье
+    CubicTo {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x: f64,
+        y: f64,
+    },
+    /// Замыкание подконтура
+    Close,
+}
+
+/// Коэффициент контрольных точек для четверти эллипса кривой Безье
+const KAPPA: f64 = 0.552_284_749_8;
+
+impl PathSegment {
+    /// Ломаная через точки
+    pub fn polyline(points: &[(f64, f64)]) -> Vec<PathSegment> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| {
+                if i == 0 {
+                    PathSegment::MoveTo { x, y }
+                } else {
+                    PathSegment::LineTo { x, y }
+                }
+            })
+            .collect()
+    }
+
+    /// Замкнутый многоугольник
+    pub fn polygon(points: &[(f64, f64)]) -> Vec<PathSegment> {
+        let mut segments = Self::polyline(points);
+        segments.push(PathSegment::Close);
+        segments
+    }
+
+    /// Прямоугольник, при `radius > 0` — со скруглёнными углами
+    pub fn rect(x: f64, y: f64, width: f64, height: f64, radius: f64) -> Vec<PathSegment> {
+        let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+        if r == 0.0 {
+            return Self::polygon(&[
+                (x, y),
+                (x + width, y),
+                (x + width, y + height),
+                (x, y + height),
+            ]);
+        }
+        let k = r * (1.0 - KAPPA);
+        let (right, bottom) = (x + width, y + height);
+        vec![
+            PathSegment::MoveTo { x: x + r, y },
+            PathSegment::LineTo { x: right - r, y },
+            PathSegment::CubicTo {
+                x1: right - k,
+                y1: y,
+                x2: right,
+                y2: y + k,
+                x: right,
+                y: y + r,
+            },
+            PathSegment::LineTo {
+                x: right,
+                y: bottom - r,
+            },
+            PathSegment::CubicTo {
+                x1: right,
+                y1: bottom - k,
+                x2: right - k,
+                y2: bottom,
+                x: right - r,
+                y: bottom,
+            },
+            PathSegment::LineTo {
+                x: x + r,
+                y: bottom,
+            },
+            PathSegment::CubicTo {
+                x1: x + k,
+                y1: bottom,
+                x2: x,
+                y2: bottom - k,
+                x,
+                y: bottom - r,
+            },
+            PathSegment::LineTo { x, y: y + r },
+            PathSegment::CubicTo {
+                x1: x,
+                y1: y + k,
+                x2: x + k,
+                y2: y,
+                x: x + r,
+                y,
+            },
+            PathSegment::Close,
+        ]
+    }
+
+    /// Эллипс из четырёх кривых Безье
+    pub fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> Vec<PathSegment> {
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        vec![
+            PathSegment::MoveTo { x: cx + rx, y: cy },
+            PathSegment::CubicTo {
+                x1: cx + rx,
+                y1: cy + ky,
+                x2: cx + kx,
+                y2: cy + ry,
+                x: cx,
+                y: cy + ry,
+            },
+            PathSegment::CubicTo {
+                x1: cx - kx,
+                y1: cy + ry,
+                x2: cx - rx,
+                y2: cy + ky,
+                x: cx - rx,
+                y: cy,
+            },
+            PathSegment::CubicTo {
+                x1: cx - rx,
+                y1: cy - ky,
+                x2: cx - kx,
+                y2: cy - ry,
+                x: cx,
+                y: cy - ry,
+            },
+            PathSegment::CubicTo {
+                x1: cx + kx,
+                y1: cy - ry,
+                x2: cx + rx,
+                y2: cy - ky,
+                x: cx + rx,
+                y: cy,
+            },
+            PathSegment::Close,
+        ]
+    }
+
+    /// Разбирает SVG path data (`M`, `L`, `H`, `V`, `C`, `Q`, `Z`,
+    /// в том числе относительные); квадратичные кривые повышаются до кубических
+    pub fn parse_svg(d: &str) -> Vec<PathSegment> {
+        let mut segments = Vec::new();
+        let mut tokens = SvgPathTokens::new(d);
+        let (mut cx, mut cy) = (0.0, 0.0);
+        let (mut sx, mut sy) = (0.0, 0.0);
+        let mut command = None;
+
+        while let Some(token) = tokens.peek() {
+            if let SvgPathToken::Command(c) = token {
+                tokens.next();
+                command = Some(c);
+                if c.eq_ignore_ascii_case(&'z') {
+                    segments.push(PathSegment::Close);
+                    (cx, cy) = (sx, sy);
+                    command = None;
+                }
+                continue;
+            }
+            let Some(c) = command else {
+                // Число без команды — некорректные данные
+                break;
+            };
+            let relative = c.is_ascii_lowercase();
+            let (ox, oy) = if relative { (cx, cy) } else { (0.0, 0.0) };
+            match c.to_ascii_uppercase() {
+                'M' | 'L' => {
+                    let Some([x, y]) = tokens.numbers() else {
+                        break;
+                    };
+                    (cx, cy) = (ox + x, oy + y);
+                    if c.eq_ignore_ascii_case(&'m') {
+                        segments.push(PathSegment::MoveTo { x: cx, y: cy });
+                        (sx, sy) = (cx, cy);
+                        // Следующие пары после M — неявные L
+                        command = Some(if relative { 'l' } else { 'L' });
+                    } else {
+                        segments.push(PathSegment::LineTo { x: cx, y: cy });
+                    }
+                }
+                'H' => {
+                    let Some([x]) = tokens.numbers() else {
+                        break;
+                    };
+                    cx = ox + x;
+                    segments.push(PathSegment::LineTo { x: cx, y: cy });
+                }
+                'V' => {
+                    let Some([y]) = tokens.numbers() else {
+                        break;
+                    };
+                    cy = oy + y;
+                    segments.push(PathSegment::LineTo { x: cx, y: cy });
+                }
+                'C' => {
+                    let Some([x1, y1, x2, y2, x, y]) = tokens.numbers() else {
+                        break;
+                    };
+                    segments.push(PathSegment::CubicTo {
+                        x1: ox + x1,
+                        y1: oy + y1,
+                        x2: ox + x2,
+                        y2: oy + y2,
+                        x: ox + x,
+                        y: oy + y,
+                    });
+                    (cx, cy) = (ox + x, oy + y);
+                }
+                'Q' => {
+                    let Some([qx, qy, x, y]) = tokens.numbers() else {
+                        break;
+                    };
+                    let (qx, qy, x, y) = (ox + qx, oy + qy, ox + x, oy + y);
+                    segments.push(PathSegment::CubicTo {
+                        x1: cx + (qx - cx) * 2.0 / 3.0,
+                        y1: cy + (qy - cy) * 2.0 / 3.0,
+                        x2: x + (qx - x) * 2.0 / 3.0,
+                        y2: y + (qy - y) * 2.0 / 3.0,
+                        x,
+                        y,
+                    });
+                    (cx, cy) = (x, y);
+                }
+                // Дуги и сглаженные кривые в layout не встречаются
+                _ => break,
+            }
+        }
+        segments
+    }
+
+    /// Конечная точка сегмента
+    pub fn end_point(&self) -> Option<(f64, f64)> {
+        match *self {
+            PathSegment::MoveTo { x, y }
+            | PathSegment::LineTo { x, y }
+            | PathSegment::CubicTo { x, y, .. } => Some((x, y)),
+            PathSegment::Close => None,
+        }
+    }
+}
+
+/// Лексема SVG path data
+#[derive(Debug, Clone, Copy)]
+enum SvgPathToken {
+    Command(char),
+    Number(f64),
+}
+
+/// Разбиение SVG path data на команды и числа
+struct SvgPathTokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> SvgPathTokens<'a> {
+    fn new(d: &'a str) -> Self {
+        Self { rest: d }
+    }
+
+    /// Лексема в начале остатка и её длина в байтах
+    fn scan(&mut self) -> Option<(SvgPathToken, usize)> {
+        self.rest = self
+            .rest
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let c = self.rest.chars().next()?;
+        if c.is_ascii_alphabetic() && c != 'e' && c != 'E' {
+            return Some((SvgPathToken::Command(c), c.len_utf8()));
+        }
+        // Число: знак в начале или после экспоненты, цифры, точка, экспонента
+        let bytes = self.rest.as_bytes();
+        let mut end = 0;
+        while end < bytes.len() {
+            let b = bytes[end];
+            let sign =
+                (b == b'-' || b == b'+') && (end == 0 || matches!(bytes[end - 1], b'e' | b'E'));
+            if !(b.is_ascii_digit() || b == b'.' || b == b'e' || b == b'E' || sign) {
+                break;
+            }
+            end += 1;
+        }
+        let number = self.rest[..end].parse().ok()?;
+        Some((SvgPathToken::Number(number), end))
+    }
+
+    fn peek(&mut self) -> Option<SvgPathToken> {
+        self.scan().map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<SvgPathToken> {
+        let (token, len) = self.scan()?;
+        self.rest = &self.rest[len..];
+        Some(token)
+    }
+
+    /// Следующие `N` чисел
+    fn numbers<const N: usize>(&mut self) -> Option<[f64; N]> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            match self.next()? {
+                SvgPathToken::Number(n) => *value = n,
+                SvgPathToken::Command(_) => return None,
+            }
+        }
+        Some(values)
+    }
+}
+
+/// Заливка и обводка контура
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Style {
+    /// Цвет заливки (CSS), None — без заливки
+    pub fill: Option<String>,
+    /// Обводка, None — без обводки
+    pub stroke: Option<Stroke>,
+    /// Тень (`skinparam shadowing`)
+    pub shadow: bool,
+}
+
+impl Style {
+    /// Только заливка
+    pub fn fill(color: impl Into<String>) -> Self {
+        Self {
+            fill: Some(color.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Только обводка
+    pub fn stroke(color: impl Into<String>, width: f64) -> Self {
+        Self {
+            stroke: Some(Stroke::new(color, width)),
+            ..Self::default()
+        }
+    }
+
+    /// Заливка и обводка
+    pub fn fill_and_stroke(fill: impl Into<String>, stroke: impl Into<String>, width: f64) -> Self {
+        Self {
+            fill: Some(fill.into()),
+            stroke: Some(Stroke::new(stroke, width)),
+            shadow: false,
+        }
+    }
+
+    /// Включает тень
+    pub fn with_shadow(mut self, shadow: bool) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    /// Пунктир обводки
+    pub fn with_dash(mut self, dash: &[f64]) -> Self {
+        if let Some(stroke) = &mut self.stroke {
+            stroke.dash = dash.to_vec();
+        }
+        self
+    }
+}
+
+/// Обводка контура
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stroke {
+    /// Цвет (CSS)
+    pub color: String,
+    /// Толщина линии
+    pub width: f64,
+    /// Пунктир: длины штрихов и промежутков, пустой — сплошная линия
+    pub dash: Vec<f64>,
+}
+
+impl Stroke {
+    /// Сплошная обводка
+    pub fn new(color: impl Into<String>, width: f64) -> Self {
+        Self {
+            color: color.into(),
+            width,
+            dash: Vec::new(),
+        }
+    }
+}
+
+/// Шрифт текста
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Font {
+    /// CSS `font-family`
+    pub family: String,
+    /// Кегль
+    pub size: f64,
+    /// Полужирный
+    pub bold: bool,
+    /// Курсив
+    pub italic: bool,
+    /// Подчёркивание
+    pub underline: bool,
+}
+
+impl Font {
+    /// Обычное начертание
+    pub fn new(family: impl Into<String>, size: f64) -> Self {
+        Self {
+            family: family.into(),
+            size,
+            bold: false,
+            italic: false,
+            underline: false,
+        }
+    }
+
+    /// Полужирное начертание
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Курсив
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    /// Подчёркивание
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+}
+
+/// Выравнивание текста по горизонтали (CSS `text-anchor`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAnchor {
+    /// `x` — левый край
+    #[default]
+    Start,
+    /// `x` — центр
+    Middle,
+    /// `x` — правый край
+    End,
+}
+
+/// Базовая линия текста (CSS `dominant-baseline`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextBaseline {
+    /// `y` — алфавитная базовая линия
+    #[default]
+    Alphabetic,
+    /// `y` — середина строчных букв
+    Middle,
+}
+
+/// Гиперссылка элемента
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    /// Адрес
+    pub url: String,
+    /// Подсказка (None — показывается адрес)
+    pub tooltip: Option<String>,
+}
+
+/// Прямоугольная область отсечения
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClipRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Наконечник линии
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    /// Форма наконечника
+    pub kind: MarkerKind,
+    /// Цвет обводки и закрашенных наконечников
+    pub color: String,
+    /// Заливка полых наконечников (цвет фона темы)
+    pub fill: String,
+}
+
+/// Форма наконечника (как маркеры SVG рендерера)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerKind {
+    /// Закрашенная стрелка с вырезом (ассоциации, сообщения)
+    Arrow,
+    /// Открытая стрелка (зависимости, асинхронные сообщения)
+    OpenArrow,
+    /// Полый треугольник (наследование, реализация)
+    Inheritance,
+    /// Закрашенный ромб (композиция)
+    Composition,
+    /// Полый ромб (агрегация)
+    Aggregation,
+}
+
+impl MarkerKind {
+    /// Контур в собственных координатах, точка привязки и замкнутость
+    fn shape(self) -> (&'static [(f64, f64)], (f64, f64), bool) {
+        const ARROW: &[(f64, f64)] = &[(0.0, 0.0), (10.0, 4.0), (0.0, 8.0), (4.0, 4.0)];
+        const OPEN_ARROW: &[(f64, f64)] = &[(0.0, 0.0), (10.0, 4.0), (0.0, 8.0)];
+        const TRIANGLE: &[(f64, f64)] = &[(0.0, 0.0), (20.0, 10.0), (0.0, 20.0)];
+        const DIAMOND: &[(f64, f64)] = &[(0.0, 6.0), (6.0, 0.0), (12.0, 6.0), (6.0, 12.0)];
+        match self {
+            MarkerKind::Arrow => (ARROW, (10.0, 4.0), true),
+            MarkerKind::OpenArrow => (OPEN_ARROW, (10.0, 4.0), false),
+            MarkerKind::Inheritance => (TRIANGLE, (20.0, 10.0), true),
+            MarkerKind::Composition | MarkerKind::Aggregation => (DIAMOND, (0.0, 6.0), true),
+        }
+    }
+}
+
+impl Marker {
+    /// Наконечник заданной формы
+    pub fn new(kind: MarkerKind, color: impl Into<String>, fill: impl Into<String>) -> Self {
+        Self {
+            kind,
+            color: color.into(),
+            fill: fill.into(),
+        }
+    }
+
+    /// Контур наконечника в точке `at`, повёрнутый по направлению `angle`
+    /// (радианы, как `orient="auto"` в SVG) и готовый к рисованию
+    pub fn place(&self, at: (f64, f64), angle: f64) -> (Vec<PathSegment>, Style) {
+        let (points, (rx, ry), closed) = self.kind.shape();
+        let (sin, cos) = angle.sin_cos();
+        let placed: Vec<(f64, f64)> = points
+            .iter()
+            .map(|&(x, y)| {
+                let (dx, dy) = (x - rx, y - ry);
+                (at.0 + dx * cos - dy * sin, at.1 + dx * sin + dy * cos)
+            })
+            .collect();
+        let style = match self.kind {
+            MarkerKind::Arrow | MarkerKind::Composition => Style::fill(self.color.clone()),
+            MarkerKind::OpenArrow => Style::stroke(self.color.clone(), 1.0),
+            MarkerKind::Inheritance | MarkerKind::Aggregation => {
+                Style::fill_and_stroke(self.fill.clone(), self.color.clone(), 1.0)
+            }
+        };
+        let segments = if closed {
+            PathSegment::polygon(&placed)
+        } else {
+            PathSegment::polyline(&placed)
+        };
+        (segments, style)
+    }
+}
+
+/// Конец контура: точка и направление касательной (радианы)
+pub(crate) type PathEnd = ((f64, f64), f64);
+
+/// Начало и конец контура для маркеров
+pub(crate) fn path_ends(segments: &[PathSegment]) -> Option<(PathEnd, PathEnd)> {
+    let mut points = Vec::new();
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo { x, y } | PathSegment::LineTo { x, y } => points.push((x, y)),
+            PathSegment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                points.push((x1, y1));
+                points.push((x2, y2));
+                points.push((x, y));
+            }
+            PathSegment::Close => {}
+        }
+    }
+    points.dedup();
+    if points.len() < 2 {
+        return None;
+    }
+    let angle = |from: (f64, f64), to: (f64, f64)| (to.1 - from.1).atan2(to.0 - from.0);
+    let n = points.len();
+    Some((
+        (points[0], angle(points[0], points[1])),
+        (points[n - 1], angle(points[n - 2], points[n - 1])),
+    ))
+}
+
+/// Контуры и стили маркеров в начале и конце контура
+pub(crate) fn placed_markers(
+    segments: &[PathSegment],
+    marker_start: Option<&Marker>,
+    marker_end: Option<&Marker>,
+) -> Vec<(Vec<PathSegment>, Style)> {
+    let ends = path_ends(segments);
+    [
+        marker_start.zip(ends.map(|(start, _)| start)),
+        marker_end.zip(ends.map(|(_, end)| end)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(marker, (at, angle))| marker.place(at, angle))
+    .collect()
+}
+
+/// Цвет CSS (`#RGB`, `#RRGGBB` или базовое имя) в RGB; прозрачный — None
+pub(crate) fn parse_rgb(css: &str) -> Option<[u8; 3]> {
+    let css = css.trim();
+    if let Some(hex) = css.strip_prefix('#') {
+        let channel = |range: std::ops::Range<usize>| u8::from_str_radix(hex.get(range)?, 16).ok();
+        return match hex.len() {
+            6 => Some([channel(0..2)?, channel(2..4)?, channel(4..6)?]),
+            3 => Some([channel(0..1)? * 17, channel(1..2)? * 17, channel(2..3)? * 17]),
+            _ => None,
+        };
+    }
+    match css.to_lowercase().as_str() {
+        "white" => Some([255, 255, 255]),
+        "black" => Some([0, 0, 0]),
+        "red" => Some([255, 0, 0]),
+        "green" => Some([0, 128, 0]),
+        "blue" => Some([0, 0, 255]),
+        "yellow" => Some([255, 255, 0]),
+        _ => None,
+    }
+}
+
+/// Число с точностью до тысячных без лишних нулей
+pub(crate) fn num(value: f64) -> String {
+    let rounded = (value * 1000.0).round() / 1000.0;
+    if rounded == 0.0 {
+        "0".to_string()
+    } else {
+        rounded.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_svg_path() {
+        let segments = PathSegment::parse_svg("M10,20 L30,20 C35,20 40,25 40,30 Z");
+        assert_eq!(
+            segments,
+            vec![
+                PathSegment::MoveTo { x: 10.0, y: 20.0 },
+                PathSegment::LineTo { x: 30.0, y: 20.0 },
+                PathSegment::CubicTo {
+                    x1: 35.0,
+                    y1: 20.0,
+                    x2: 40.0,
+                    y2: 25.0,
+                    x: 40.0,
+                    y: 30.0
+                },
+                PathSegment::Close,
+            ]
+        );
+
+        // Относительные команды, H/V и неявные L после M
+        let segments = PathSegment::parse_svg("m1 1 2 0 v3 h-2.5e0 z");
+        assert_eq!(
+            segments,
+            vec![
+                PathSegment::MoveTo { x: 1.0, y: 1.0 },
+                PathSegment::LineTo { x: 3.0, y: 1.0 },
+                PathSegment::LineTo { x: 3.0, y: 4.0 },
+                PathSegment::LineTo { x: 0.5, y: 4.0 },
+                PathSegment::Close,
+            ]
+        );
+        assert_eq!(PathSegment::parse_svg("M-1-2").len(), 1);
+    }
+
+    #[test]
+    fn test_marker_place() {
+        let marker = Marker::new(MarkerKind::Arrow, "#000", "#fff");
+        // Стрелка вправо: кончик в точке привязки
+        let (segments, style) = marker.place((100.0, 50.0), 0.0);
+        assert_eq!(segments[1], PathSegment::LineTo { x: 100.0, y: 50.0 });
+        assert_eq!(style.fill.as_deref(), Some("#000"));
+
+        // Стрелка вниз: основание выше кончика
+        let (segments, _) = marker.place((0.0, 0.0), std::f64::consts::FRAC_PI_2);
+        let (_, y) = segments[0].end_point().unwrap();
+        assert!(y < -9.0);
+
+        let hollow = Marker::new(MarkerKind::Inheritance, "#000", "#fff");
+        let (_, style) = hollow.place((0.0, 0.0), 0.0);
+        assert_eq!(style.fill.as_deref(), Some("#fff"));
+        assert!(style.stroke.is_some());
+    }
+
+    #[test]
+    fn test_gray_image_runs() {
+        let image = ImageData::Gray {
+            width: 3,
+            height: 2,
+            levels: 4,
+            pixels: vec![3, 3, 0, 0, 1, 3],
+            color: "#FF0000".to_string(),
+        };
+        let pixels = image.pixels(3, 2).unwrap();
+        assert_eq!(pixels.rgba[4], [255, 0, 0, 85]);
+        assert_eq!(
+            pixels.runs(),
+            vec![
+                (0, 0, 2, [255, 0, 0, 255]),
+                (1, 1, 1, [255, 0, 0, 85]),
+                (2, 1, 1, [255, 0, 0, 255]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_list_json() {
+        let mut list = DisplayList::new(0.0, 0.0, 100.0, 50.0);
+        list.commands.push(DrawCommand::Group {
+            id: Some("a".to_string()),
+            class: Some("participant".to_string()),
+            line: Some(2),
+            link: None,
+            clip: None,
+            commands: vec![DrawCommand::Path {
+                segments: PathSegment::rect(0.0, 0.0, 10.0, 10.0, 0.0),
+                style: Style::fill("#FEFECE"),
+                marker_start: None,
+                marker_end: None,
+            }],
+        });
+
+        let json = serde_json::to_string(&list).unwrap();
+        assert!(json.contains(r#""type":"group""#));
+        assert!(json.contains(r#""op":"move_to""#));
+        let back: DisplayList = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, list);
+
+        let mut count = 0;
+        list.visit(&mut |_| count += 1);
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,504 @@
+//! Растровый backend display list (tiny-skia)
+//!
+//! Контуры рисуются напрямую, текст — глифами ab_glyph из шрифтов
+//! [`FontSet`] без промежуточного SVG, спрайты — растровыми изображениями.
+
+use ab_glyph::{point, Font as _, FontRef, GlyphId, PxScale, ScaleFont};
+use fontdb::{Family, Query, Style as FontStyle, Weight, ID};
+use tiny_skia::{
+    ColorU8, FillRule, FilterQuality, IntSize, Mask, Paint, PathBuilder, Pixmap, PixmapPaint,
+    PremultipliedColorU8, Stroke as SkiaStroke, StrokeDash, Transform,
+};
+
+use super::{
+    placed_markers, DisplayList, DisplayListRenderer, DrawCommand, Font, ImageData, PathSegment,
+    Style, TextAnchor, TextBaseline,
+};
+use crate::fonts::{query_families, FontSet};
+use crate::png_renderer::{parse_css_color, PngError, PngOptions, PngRenderer};
+use crate::{LayoutResult, Renderer, Theme};
+
+impl PngRenderer {
+    /// Растеризует display list в PNG без разбора SVG
+    pub fn render_display_list(&self, list: &DisplayList) -> Result<Vec<u8>, PngError> {
+        rasterize(list, &self.options, self.fonts()?)?
+            .encode_png()
+            .map_err(|e| PngError::EncodingError(e.to_string()))
+    }
+}
+
+/// PNG через display list: layout рисуется без промежуточного SVG
+impl Renderer for PngRenderer {
+    type Output = Result<Vec<u8>, PngError>;
+
+    fn render(&self, layout: &LayoutResult, theme: &Theme) -> Self::Output {
+        let list = DisplayListRenderer::new().render(layout, theme);
+        self.render_display_list(&list)
+    }
+}
+
+/// Растеризует display list в pixmap с масштабом и фоном из опций
+pub(crate) fn rasterize(
+    list: &DisplayList,
+    options: &PngOptions,
+    fonts: &FontSet,
+) -> Result<Pixmap, PngError> {
+    let scale = options.scale;
+    let width = (list.width as f32 * scale).ceil() as u32;
+    let height = (list.height as f32 * scale).ceil() as u32;
+    if width == 0 || height == 0 {
+        return Err(PngError::InvalidDimensions);
+    }
+
+    let mut pixmap =
+        Pixmap::new(width, height).ok_or(PngError::PixmapCreationError { width, height })?;
+    if let Some(background) = options.background_color {
+        pixmap.fill(background);
+    }
+
+    let transform =
+        Transform::from_scale(scale, scale).pre_translate(-list.x as f32, -list.y as f32);
+    let mut canvas = Canvas {
+        pixmap,
+        transform,
+        fonts,
+    };
+    if let Some(background) = list.background.as_deref().and_then(parse_css_color) {
+        let rect = PathSegment::rect(list.x, list.y, list.width, list.height, 0.0);
+        canvas.fill(&rect, background, None);
+    }
+    for command in &list.commands {
+        canvas.draw(command, None);
+    }
+    Ok(canvas.pixmap)
+}
+
+/// Холст растеризации
+struct Canvas<'a> {
+    pixmap: Pixmap,
+    transform: Transform,
+    fonts: &'a FontSet,
+}
+
+impl Canvas<'_> {
+    fn draw(&mut self, command: &DrawCommand, mask: Option<&Mask>) {
+        match command {
+            DrawCommand::Path {
+                segments,
+                style,
+                marker_start,
+                marker_end,
+            } => {
+                self.path(segments, style, mask);
+                let markers = placed_markers(segments, marker_start.as_ref(), marker_end.as_ref());
+                for (segments, style) in markers {
+                    self.path(&segments, &style, mask);
+                }
+            }
+            DrawCommand::Text {
+                x,
+                y,
+                text,
+                font,
+                anchor,
+                baseline,
+                color,
+            } => {
+                if let Some(color) = parse_css_color(color) {
+                    self.text(*x, *y, text, font, *anchor, *baseline, color, mask);
+                }
+            }
+            DrawCommand::Image {
+                x,
+                y,
+                width,
+                height,
+                image,
+            } => self.image(*x, *y, *width, *height, image, mask),
+            DrawCommand::Group { clip, commands, .. } => {
+                let clip_mask = clip.and_then(|clip| {
+                    let mut clip_mask = Mask::new(self.pixmap.width(), self.pixmap.height())?;
+                    let rect = PathSegment::rect(clip.x, clip.y, clip.width, clip.height, 0.0);
+                    clip_mask.fill_path(
+                        &build_path(&rect)?,
+                        FillRule::Winding,
+                        true,
+                        self.transform,
+                    );
+                    Some(clip_mask)
+                });
+                // Вложенное отсечение заменяет внешнее
+                let mask = clip_mask.as_ref().or(mask);
+                for command in commands {
+                    self.draw(command, mask);
+                }
+            }
+        }
+    }
+
+    /// Изображение, растянутое на прямоугольник; SVG растеризуется в размер на холсте
+    fn image(
+        &mut self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        image: &ImageData,
+        mask: Option<&Mask>,
+    ) {
+        let target_width = (width as f32 * self.transform.sx).ceil() as u32;
+        let target_height = (height as f32 * self.transform.sy).ceil() as u32;
+        let Some(pixels) = image.pixels(target_width, target_height) else {
+            return;
+        };
+        let data = pixels
+            .rgba
+            .iter()
+            .flat_map(|&[r, g, b, a]| {
+                let c = ColorU8::from_rgba(r, g, b, a).premultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect();
+        let Some(source) = IntSize::from_wh(pixels.width, pixels.height)
+            .and_then(|size| Pixmap::from_vec(data, size))
+        else {
+            return;
+        };
+        let transform = self.transform.pre_translate(x as f32, y as f32).pre_scale(
+            width as f32 / pixels.width as f32,
+            height as f32 / pixels.height as f32,
+        );
+        let paint = PixmapPaint {
+            quality: FilterQuality::Nearest,
+            ..PixmapPaint::default()
+        };
+        self.pixmap
+            .draw_pixmap(0, 0, source.as_ref(), &paint, transform, mask);
+    }
+
+    fn path(&mut self, segments: &[PathSegment], style: &Style, mask: Option<&Mask>) {
+        let Some(path) = build_path(segments) else {
+            return;
+        };
+        if let Some(fill) = style.fill.as_deref().and_then(parse_css_color) {
+            if style.shadow {
+                // Тень без размытия: полупрозрачная копия со сдвигом
+                let shadow = tiny_skia::Color::from_rgba8(0, 0, 0, 89);
+                let transform = self.transform.pre_translate(3.0, 3.0);
+                self.pixmap
+                    .fill_path(&path, &paint(shadow), FillRule::Winding, transform, mask);
+            }
+            self.pixmap
+                .fill_path(&path, &paint(fill), FillRule::Winding, self.transform, mask);
+        }
+        if let Some(stroke) = &style.stroke {
+            let Some(color) = parse_css_color(&stroke.color) else {
+                return;
+            };
+            let mut skia_stroke = SkiaStroke {
+                width: stroke.width as f32,
+                ..SkiaStroke::default()
+            };
+            if !stroke.dash.is_empty() {
+                let dash = stroke.dash.iter().map(|&d| d as f32).collect();
+                skia_stroke.dash = StrokeDash::new(dash, 0.0);
+            }
+            self.pixmap
+                .stroke_path(&path, &paint(color), &skia_stroke, self.transform, mask);
+        }
+    }
+
+    fn fill(&mut self, segments: &[PathSegment], color: tiny_skia::Color, mask: Option<&Mask>) {
+        if let Some(path) = build_path(segments) {
+            self.pixmap.fill_path(
+                &path,
+                &paint(color),
+                FillRule::Winding,
+                self.transform,
+                mask,
+            );
+        }
+    }
+
+    /// Текст глифами ab_glyph; символы без глифа берутся из запасных шрифтов
+    ///
+    /// Глифы растеризуются в пикселях устройства, поэтому масштаб
+    /// применяется к размеру шрифта, а не к готовому изображению.
+    #[allow(clippy::too_many_arguments)]
+    fn text(
+        &mut self,
+        x: f64,
+        y: f64,
+        text: &str,
+        font: &Font,
+        anchor: TextAnchor,
+        baseline: TextBaseline,
+        color: tiny_skia::Color,
+        mask: Option<&Mask>,
+    ) {
+        let Some(primary) = self.face(font) else {
+            return;
+        };
+        let px = font.size as f32 * self.transform.sy;
+
+        // Позиции глифов вдоль строки с кернингом внутри одного шрифта
+        let mut glyphs: Vec<(ID, GlyphId, f32)> = Vec::new();
+        let mut pen = 0.0;
+        let mut previous: Option<(ID, GlyphId)> = None;
+        for c in text.chars().filter(|c| !c.is_control()) {
+            let has_glyph = self
+                .with_font(primary, |face| face.glyph_id(c).0 != 0)
+                .unwrap_or(false);
+            let id = if has_glyph {
+                primary
+            } else {
+                self.fonts.face_for_char(c, &[primary]).unwrap_or(primary)
+            };
+            let metrics = self.with_font(id, |face| {
+                let scaled = face.as_scaled(px_scale(face, px));
+                let glyph = scaled.glyph_id(c);
+                let kern = previous
+                    .filter(|&(previous_id, _)| previous_id == id)
+                    .map_or(0.0, |(_, previous_glyph)| {
+                        scaled.kern(previous_glyph, glyph)
+                    });
+                (glyph, scaled.h_advance(glyph), kern)
+            });
+            let (glyph, advance, kern) = metrics.unwrap_or((GlyphId(0), px * 0.6, 0.0));
+            pen += kern;
+            glyphs.push((id, glyph, pen));
+            pen += advance;
+            previous = Some((id, glyph));
+        }
+        let width = pen;
+
+        let origin_x = x as f32 * self.transform.sx + self.transform.tx
+            - match anchor {
+                TextAnchor::Start => 0.0,
+                TextAnchor::Middle => width / 2.0,
+                TextAnchor::End => width,
+            };
+        let baseline_y = y as f32 * self.transform.sy
+            + self.transform.ty
+            + match baseline {
+                TextBaseline::Alphabetic => 0.0,
+                // Середина строчных букв: половина высоты «x»
+                TextBaseline::Middle => self
+                    .with_font(primary, |face| {
+                        let scaled = face.as_scaled(px_scale(face, px));
+                        let outlined = scaled.outline_glyph(scaled.scaled_glyph('x'))?;
+                        Some(-outlined.px_bounds().min.y / 2.0)
+                    })
+                    .flatten()
+                    .unwrap_or(px * 0.25),
+            };
+
+        for &(id, glyph, offset) in &glyphs {
+            let position = point(origin_x + offset, baseline_y);
+            let pixmap = &mut self.pixmap;
+            let fonts = self.fonts;
+            fonts.database().with_face_data(id, |data, index| {
+                let Ok(face) = FontRef::try_from_slice_and_index(data, index) else {
+                    return;
+                };
+                let glyph = glyph.with_scale_and_position(px_scale(&face, px), position);
+                let Some(outlined) = face.outline_glyph(glyph) else {
+                    return;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, coverage| {
+                    let pixel_x = bounds.min.x as i32 + gx as i32;
+                    let pixel_y = bounds.min.y as i32 + gy as i32;
+                    blend(pixmap, mask, pixel_x, pixel_y, color, coverage);
+                });
+            });
+        }
+
+        if font.underline {
+            let top = baseline_y + px * 0.1;
+            let thickness = (px / 14.0).max(0.5);
+            if let Some(rect) = tiny_skia::Rect::from_xywh(origin_x, top, width, thickness) {
+                self.pixmap
+                    .fill_rect(rect, &paint(color), Transform::identity(), mask);
+            }
+        }
+    }
+
+    /// Грань шрифта для семейства и начертания
+    fn face(&self, font: &Font) -> Option<ID> {
+        let mut families = query_families(&font.family);
+        families.push(Family::SansSerif);
+        let query = Query {
+            families: &families,
+            weight: if font.bold {
+                Weight::BOLD
+            } else {
+                Weight::NORMAL
+            },
+            style: if font.italic {
+                FontStyle::Italic
+            } else {
+                FontStyle::Normal
+            },
+            ..Query::default()
+        };
+        self.fonts
+            .database()
+            .query(&query)
+            .or_else(|| self.fonts.query(&font.family))
+    }
+
+    fn with_font<T>(&self, id: ID, f: impl FnOnce(&FontRef<'_>) -> T) -> Option<T> {
+        self.fonts
+            .database()
+            .with_face_data(id, |data, index| {
+                FontRef::try_from_slice_and_index(data, index)
+                    .ok()
+                    .map(|face| f(&face))
+            })
+            .flatten()
+    }
+}
+
+/// Масштаб ab_glyph, при котором em шрифта равен `px` пикселям
+///
+/// `PxScale` задаёт высоту от нижнего выносного до верхнего элемента,
+/// а размер шрифта в SVG — это em.
+fn px_scale(face: &FontRef<'_>, px: f32) -> PxScale {
+    let units_per_em = face.units_per_em().unwrap_or(1000.0);
+    PxScale::from(px * face.height_unscaled() / units_per_em)
+}
+
+/// Смешивает цвет с пикселем с учётом покрытия глифа и маски отсечения
+fn blend(
+    pixmap: &mut Pixmap,
+    mask: Option<&Mask>,
+    x: i32,
+    y: i32,
+    color: tiny_skia::Color,
+    coverage: f32,
+) {
+    let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+    if x < 0 || y < 0 || x >= width || y >= height {
+        return;
+    }
+    let index = (y * width + x) as usize;
+    let clip = mask.map_or(1.0, |mask| f32::from(mask.data()[index]) / 255.0);
+    let alpha = color.alpha() * coverage.min(1.0) * clip;
+    if alpha <= 0.0 {
+        return;
+    }
+
+    let pixel = &mut pixmap.pixels_mut()[index];
+    let over = |source: f32, destination: u8| {
+        (source * alpha * 255.0 + f32::from(destination) * (1.0 - alpha)).round() as u8
+    };
+    let a = over(1.0, pixel.alpha());
+    // Премультиплицированные каналы не превышают альфу
+    let r = over(color.red(), pixel.red()).min(a);
+    let g = over(color.green(), pixel.green()).min(a);
+    let b = over(color.blue(), pixel.blue()).min(a);
+    if let Some(blended) = PremultipliedColorU8::from_rgba(r, g, b, a) {
+        *pixel = blended;
+    }
+}
+
+fn build_path(segments: &[PathSegment]) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo { x, y } => builder.move_to(x as f32, y as f32),
+            PathSegment::LineTo { x, y } => builder.line_to(x as f32, y as f32),
+            PathSegment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => builder.cubic_to(
+                x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32,
+            ),
+            PathSegment::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+fn paint(color: tiny_skia::Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElementType, LayoutElement, Rect};
+
+    #[test]
+    fn test_render_layout_without_svg() {
+        let mut layout = LayoutResult::empty();
+        layout.elements.push(LayoutElement::new(
+            "a",
+            Rect::new(0.0, 0.0, 80.0, 30.0),
+            ElementType::Rectangle {
+                label: "Alice".to_string(),
+                corner_radius: 0.0,
+            },
+        ));
+        layout.calculate_bounds();
+
+        let renderer = PngRenderer::new(PngOptions::default());
+        let png = renderer.render(&layout, &Theme::default()).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!(pixmap.width(), 90);
+        // Рамка, заливка и текст: в изображении есть тёмные пиксели
+        let dark = pixmap
+            .pixels()
+            .iter()
+            .filter(|p| p.red() < 100 && p.alpha() > 200)
+            .count();
+        assert!(dark > 20, "тёмных пикселей: {}", dark);
+    }
+
+    #[test]
+    fn test_render_image_command() {
+        let mut list = DisplayList::new(0.0, 0.0, 20.0, 20.0);
+        list.commands.push(DrawCommand::Image {
+            x: 0.0,
+            y: 0.0,
+            width: 20.0,
+            height: 20.0,
+            image: ImageData::Gray {
+                width: 2,
+                height: 1,
+                levels: 2,
+                pixels: vec![1, 0],
+                color: "#FF0000".to_string(),
+            },
+        });
+        let renderer = PngRenderer::new(PngOptions::default());
+        let png = renderer.render_display_list(&list).unwrap();
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let left = pixmap.pixel(width / 4, height / 2).unwrap();
+        let right = pixmap.pixel(width * 3 / 4, height / 2).unwrap();
+        // Левая половина закрашена цветом спрайта, правая остаётся фоном
+        assert_eq!((left.red(), left.green(), left.alpha()), (255, 0, 255));
+        assert_ne!(right.green(), 0);
+    }
+
+    #[test]
+    fn test_render_empty_display_list() {
+        let renderer = PngRenderer::new(PngOptions::default());
+        let list = DisplayList::new(0.0, 0.0, 0.0, 0.0);
+        assert!(matches!(
+            renderer.render_display_list(&list),
+            Err(PngError::InvalidDimensions)
+        ));
+    }
+}
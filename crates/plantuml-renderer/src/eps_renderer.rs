@@ -0,0 +1,453 @@
+//! EPS рендеринг (Encapsulated PostScript, аналог `-teps`)
+//!
+//! Layout переводится в [`DisplayList`] и выводится операторами PostScript
+//! Level 2: контуры `moveto`/`lineto`/`curveto`, текст стандартными шрифтами
+//! Helvetica, Times и Courier, перекодированными в ISO Latin-1. Символы вне
+//! Latin-1 заменяются на `?`, гиперссылки не выводятся, тень рисуется
+//! серым без прозрачности. Спрайты выводятся прямоугольниками из строк
+//! пикселей, полупрозрачные пиксели смешиваются с белым; SVG спрайты
+//! растеризуются только с features `png` или `pdf`.
+//!
+//! # Пример
+//!
+//! ```rust,ignore
+//! use plantuml_renderer::{EpsRenderer, Renderer};
+//!
+//! let eps = EpsRenderer::new().render(&layout, &theme);
+//! std::fs::write("diagram.eps", eps)?;
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use crate::display_list::{
+    num, parse_rgb, placed_markers, Bounds, DisplayList, DrawCommand, Font, ImageData, PathSegment,
+    Style, TextAnchor, TextBaseline,
+};
+use crate::{DisplayListRenderer, LayoutResult, RenderOptions, Renderer, SpriteRegistry, Theme};
+
+/// Процедуры пролога: перекодировка шрифтов и вывод выровненного текста
+const PROLOG: &str = "/reencode {
+  findfont dup length dict begin
+  { 1 index /FID ne { def } { pop pop } ifelse } forall
+  /Encoding ISOLatin1Encoding def
+  currentdict end definefont pop
+} bind def
+/AS {
+  1 index stringwidth pop mul neg 0 rmoveto show
+} bind def
+/ASU {
+  4 dict begin /t exch def /o exch def /f exch def /s exch def
+  s stringwidth pop f mul neg 0 rmoveto
+  gsave currentpoint newpath moveto 0 o neg rmoveto
+  s stringwidth pop 0 rlineto t setlinewidth stroke grestore
+  s show
+  end
+} bind def
+";
+
+/// Сдвиг базовой линии `middle` вниз в долях кегля (половина высоты «x» Helvetica)
+const MIDDLE_BASELINE_SHIFT: f64 = 0.26;
+
+/// EPS рендерер
+#[derive(Debug, Clone, Default)]
+pub struct EpsRenderer {
+    options: RenderOptions,
+    sprites: SpriteRegistry,
+}
+
+impl EpsRenderer {
+    /// Создаёт рендерер с опциями по умолчанию
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Создаёт рендерер с опциями (масштаб и фон)
+    pub fn with_options(options: RenderOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Задаёт спрайты диаграммы (`DiagramMetadata::sprites`)
+    pub fn with_sprites(mut self, sprites: SpriteRegistry) -> Self {
+        self.sprites = sprites;
+        self
+    }
+
+    /// Выводит display list в EPS
+    pub fn render_display_list(&self, list: &DisplayList) -> String {
+        let scale = self.options.scale;
+        let (width, height) = (list.width * scale, list.height * scale);
+
+        let mut fonts = BTreeSet::new();
+        list.visit(&mut |command| {
+            if let DrawCommand::Text { font, .. } = command {
+                fonts.insert(postscript_font(font));
+            }
+        });
+
+        let mut out = String::new();
+        out.push_str("%!PS-Adobe-3.0 EPSF-3.0\n");
+        out.push_str("%%Creator: plantuml-rs\n");
+        let _ = writeln!(
+            out,
+            "%%BoundingBox: 0 0 {} {}",
+            width.ceil() as i64,
+            height.ceil() as i64
+        );
+        let _ = writeln!(
+            out,
+            "%%HiResBoundingBox: 0 0 {} {}",
+            num(width),
+            num(height)
+        );
+        out.push_str("%%LanguageLevel: 2\n%%Pages: 1\n%%EndComments\n");
+        out.push_str("%%BeginProlog\n");
+        out.push_str(PROLOG);
+        for font in &fonts {
+            let _ = writeln!(out, "/PU-{} /{} reencode", font, font);
+        }
+        out.push_str("%%EndProlog\n%%Page: 1 1\ngsave\n");
+        // Ось Y PostScript направлена вверх: переворачиваем систему координат
+        let _ = writeln!(
+            out,
+            "0 {} translate {} {} scale {} {} translate",
+            num(height),
+            num(scale),
+            num(-scale),
+            num(-list.x),
+            num(-list.y)
+        );
+
+        if let Some(rgb) = list.background.as_deref().and_then(parse_rgb) {
+            let _ = writeln!(
+                out,
+                "{} setrgbcolor {} {} {} {} rectfill",
+                color(rgb),
+                num(list.x),
+                num(list.y),
+                num(list.width),
+                num(list.height)
+            );
+        }
+        for command in &list.commands {
+            write_command(&mut out, command);
+        }
+
+        out.push_str("grestore\nshowpage\n%%EOF\n");
+        out
+    }
+}
+
+impl Renderer for EpsRenderer {
+    type Output = String;
+
+    fn render(&self, layout: &LayoutResult, theme: &Theme) -> Self::Output {
+        let list = DisplayListRenderer::with_options(self.options.clone())
+            .with_sprites(self.sprites.clone())
+            .render(layout, theme);
+        self.render_display_list(&list)
+    }
+}
+
+fn write_command(out: &mut String, command: &DrawCommand) {
+    match command {
+        DrawCommand::Path {
+            segments,
+            style,
+            marker_start,
+            marker_end,
+        } => {
+            write_path(out, segments, style);
+            for (segments, style) in
+                placed_markers(segments, marker_start.as_ref(), marker_end.as_ref())
+            {
+                write_path(out, &segments, &style);
+            }
+        }
+        DrawCommand::Text {
+            x,
+            y,
+            text,
+            font,
+            anchor,
+            baseline,
+            color: text_color,
+        } => {
+            let Some(rgb) = parse_rgb(text_color) else {
+                return;
+            };
+            let shift = match baseline {
+                TextBaseline::Alphabetic => 0.0,
+                TextBaseline::Middle => font.size * MIDDLE_BASELINE_SHIFT,
+            };
+            let fraction = match anchor {
+                TextAnchor::Start => "0",
+                TextAnchor::Middle => "0.5",
+                TextAnchor::End => "1",
+            };
+            let _ = writeln!(
+                out,
+                "gsave {} {} translate 1 -1 scale /PU-{} {} selectfont {} setrgbcolor",
+                num(*x),
+                num(*y),
+                postscript_font(font),
+                num(font.size),
+                color(rgb)
+            );
+            let _ = write!(
+                out,
+                "0 {} moveto {} {}",
+                num(-shift),
+                ps_string(text),
+                fraction
+            );
+            if font.underline {
+                let _ = writeln!(
+                    out,
+                    " {} {} ASU grestore",
+                    num(font.size * 0.1),
+                    num((font.size / 14.0).max(0.5))
+                );
+            } else {
+                out.push_str(" AS grestore\n");
+            }
+        }
+        DrawCommand::Image {
+            x,
+            y,
+            width,
+            height,
+            image,
+        } => write_image(out, (*x, *y, *width, *height), image),
+        DrawCommand::Group { clip, commands, .. } => {
+            if let Some(clip) = clip {
+                let _ = writeln!(
+                    out,
+                    "gsave {} {} {} {} rectclip",
+                    num(clip.x),
+                    num(clip.y),
+                    num(clip.width),
+                    num(clip.height)
+                );
+            }
+            for command in commands {
+                write_command(out, command);
+            }
+            if clip.is_some() {
+                out.push_str("grestore\n");
+            }
+        }
+    }
+}
+
+/// Изображение строками пикселей; прозрачность смешивается с белым
+fn write_image(out: &mut String, bounds: Bounds, image: &ImageData) {
+    let Some(pixels) = image.pixels(bounds.2.ceil() as u32, bounds.3.ceil() as u32) else {
+        return;
+    };
+    for ((x, y, width, height), [r, g, b, a]) in pixels.rects(bounds) {
+        let blend =
+            |c: u8| ((u32::from(c) * u32::from(a) + 255 * (255 - u32::from(a))) / 255) as u8;
+        let _ = writeln!(
+            out,
+            "{} setrgbcolor {} {} {} {} rectfill",
+            color([blend(r), blend(g), blend(b)]),
+            num(x),
+            num(y),
+            num(width),
+            num(height)
+        );
+    }
+}
+
+fn write_path(out: &mut String, segments: &[PathSegment], style: &Style) {
+    let fill = style.fill.as_deref().and_then(parse_rgb);
+    let stroke = style
+        .stroke
+        .as_ref()
+        .and_then(|stroke| Some((stroke, parse_rgb(&stroke.color)?)));
+    if fill.is_none() && stroke.is_none() {
+        return;
+    }
+
+    if fill.is_some() && style.shadow {
+        out.push_str("gsave 3 3 translate ");
+        write_segments(out, segments);
+        out.push_str(" 0.75 setgray fill grestore\n");
+    }
+    write_segments(out, segments);
+    out.push('\n');
+    if let Some(rgb) = fill {
+        let _ = writeln!(out, "gsave {} setrgbcolor fill grestore", color(rgb));
+    }
+    match stroke {
+        Some((stroke, rgb)) => {
+            let dash: Vec<String> = stroke.dash.iter().map(|&d| num(d)).collect();
+            let _ = writeln!(
+                out,
+                "{} setlinewidth [{}] 0 setdash {} setrgbcolor stroke",
+                num(stroke.width),
+                dash.join(" "),
+                color(rgb)
+            );
+        }
+        None => out.push_str("newpath\n"),
+    }
+}
+
+fn write_segments(out: &mut String, segments: &[PathSegment]) {
+    out.push_str("newpath");
+    for segment in segments {
+        let _ = match *segment {
+            PathSegment::MoveTo { x, y } => write!(out, " {} {} moveto", num(x), num(y)),
+            PathSegment::LineTo { x, y } => write!(out, " {} {} lineto", num(x), num(y)),
+            PathSegment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => write!(
+                out,
+                " {} {} {} {} {} {} curveto",
+                num(x1),
+                num(y1),
+                num(x2),
+                num(y2),
+                num(x),
+                num(y)
+            ),
+            PathSegment::Close => write!(out, " closepath"),
+        };
+    }
+}
+
+/// Стандартный шрифт PostScript для семейства и начертания
+fn postscript_font(font: &Font) -> &'static str {
+    const FAMILIES: [[&str; 4]; 3] = [
+        [
+            "Helvetica",
+            "Helvetica-Bold",
+            "Helvetica-Oblique",
+            "Helvetica-BoldOblique",
+        ],
+        [
+            "Times-Roman",
+            "Times-Bold",
+            "Times-Italic",
+            "Times-BoldItalic",
+        ],
+        [
+            "Courier",
+            "Courier-Bold",
+            "Courier-Oblique",
+            "Courier-BoldOblique",
+        ],
+    ];
+    let family = font.family.to_lowercase();
+    let index = if family.contains("mono") || family.contains("courier") {
+        2
+    } else if family.contains("times") || (family.contains("serif") && !family.contains("sans")) {
+        1
+    } else {
+        0
+    };
+    FAMILIES[index][usize::from(font.bold) + 2 * usize::from(font.italic)]
+}
+
+/// Строка PostScript в кодировке ISO Latin-1
+fn ps_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{A0}'..='\u{FF}' => {
+                let _ = write!(out, "\\{:03o}", c as u32);
+            }
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+fn color([r, g, b]: [u8; 3]) -> String {
+    let channel = |c: u8| num(f64::from(c) / 255.0);
+    format!("{} {} {}", channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_list::{Marker, MarkerKind};
+    use crate::{ElementType, LayoutElement, Rect};
+
+    #[test]
+    fn test_render_layout() {
+        let mut layout = LayoutResult::empty();
+        layout.elements.push(LayoutElement::new(
+            "a",
+            Rect::new(0.0, 0.0, 80.0, 30.0),
+            ElementType::Rectangle {
+                label: "Alice (A)".to_string(),
+                corner_radius: 0.0,
+            },
+        ));
+        layout.calculate_bounds();
+
+        let eps = EpsRenderer::new().render(&layout, &Theme::default());
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+        assert!(eps.contains("%%BoundingBox: 0 0 90 40\n"));
+        assert!(eps.contains("/PU-Helvetica /Helvetica reencode"));
+        assert!(eps.contains("(Alice \\(A\\)) 0.5 AS"));
+        assert!(eps.contains("rectfill") || eps.contains("fill grestore"));
+        assert!(eps.ends_with("showpage\n%%EOF\n"));
+    }
+
+    #[test]
+    fn test_path_and_markers() {
+        let mut list = DisplayList::new(0.0, 0.0, 100.0, 40.0);
+        list.commands.push(DrawCommand::Path {
+            segments: PathSegment::polyline(&[(0.0, 20.0), (100.0, 20.0)]),
+            style: Style::stroke("#FF0000", 1.5).with_dash(&[5.0, 5.0]),
+            marker_start: None,
+            marker_end: Some(Marker::new(MarkerKind::Arrow, "#FF0000", "#FF0000")),
+        });
+
+        let eps = EpsRenderer::new().render_display_list(&list);
+        assert!(eps.contains("newpath 0 20 moveto 100 20 lineto\n"));
+        assert!(eps.contains("1.5 setlinewidth [5 5] 0 setdash 1 0 0 setrgbcolor stroke"));
+        // Наконечник — закрашенный контур от конца линии
+        assert!(eps.contains("100 20 lineto 90 24 lineto"));
+        assert!(eps.contains("gsave 1 0 0 setrgbcolor fill grestore"));
+    }
+
+    #[test]
+    fn test_ps_string_latin1() {
+        assert_eq!(ps_string("a\\b"), "(a\\\\b)");
+        assert_eq!(ps_string("café"), "(caf\\351)");
+        assert_eq!(ps_string("Привет"), "(??????)");
+    }
+
+    #[test]
+    fn test_postscript_font() {
+        assert_eq!(postscript_font(&Font::new("sans-serif", 12.0)), "Helvetica");
+        assert_eq!(
+            postscript_font(&Font::new("monospace", 12.0).bold()),
+            "Courier-Bold"
+        );
+        assert_eq!(
+            postscript_font(&Font::new("Times New Roman", 12.0).bold().italic()),
+            "Times-BoldItalic"
+        );
+    }
+}
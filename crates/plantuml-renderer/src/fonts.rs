@@ -0,0 +1,431 @@
+//! Управление шрифтами для PNG/PDF рендеринга и метрик layout
+//!
+//! Этот модуль доступен при включённом feature `fonts` (включается `png` и `pdf`).
+//!
+//! [`FontSet`] собирает базу шрифтов из нескольких источников:
+//!
+//! - встроенный запасной шрифт DejaVu Sans (feature `embedded-font`) —
+//!   латиница, кириллица, греческий; гарантирует подписи в PNG без системных шрифтов;
+//! - системные шрифты (feature `system-fonts`, флаг [`FontOptions::system_fonts`]);
+//! - файлы и каталоги шрифтов пользователя ([`FontOptions::font_files`],
+//!   [`FontOptions::font_dirs`]).
+//!
+//! Символы, которых нет в основном шрифте (например, CJK), берутся из первого
+//! подходящего семейства [`FALLBACK_FAMILIES`]. Тот же порядок используется
+//! и при растеризации, и в [`TextMetrics`], поэтому размеры блоков в layout
+//! совпадают с тем, что рисует PNG.
+//!
+//! # Пример
+//!
+//! ```rust,ignore
+//! use plantuml_renderer::fonts::{FontOptions, FontSet};
+//! use plantuml_layout::{DotLayoutEngine, TextMetrics};
+//! use std::sync::Arc;
+//!
+//! let fonts = FontSet::new(&FontOptions::default().with_font_dir("./fonts"))?;
+//! let width = fonts.text_width("Привет", 14.0);
+//! let engine = DotLayoutEngine::new().with_metrics(Arc::new(fonts.clone()));
+//! ```
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fontdb::{Database, Family, Query, ID};
+use plantuml_layout::{EstimatedMetrics, TextMetrics};
+use thiserror::Error;
+
+/// Встроенный запасной шрифт (DejaVu Sans, лицензия Bitstream Vera)
+#[cfg(feature = "embedded-font")]
+pub const EMBEDDED_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+/// Семейство встроенного шрифта
+pub const EMBEDDED_FONT_FAMILY: &str = "DejaVu Sans";
+
+/// Порядок поиска семейств для символов, отсутствующих в основном шрифте
+pub const FALLBACK_FAMILIES: &[&str] = &[
+    // Латиница и кириллица
+    "DejaVu Sans",
+    "Noto Sans",
+    "Liberation Sans",
+    "Arial",
+    "Helvetica",
+    // CJK
+    "Noto Sans CJK SC",
+    "Noto Sans CJK JP",
+    "Noto Sans CJK KR",
+    "Source Han Sans SC",
+    "WenQuanYi Micro Hei",
+    "Microsoft YaHei",
+    "PingFang SC",
+    "Hiragino Sans",
+    "Malgun Gothic",
+];
+
+/// Ошибки загрузки шрифтов
+#[derive(Error, Debug)]
+pub enum FontError {
+    /// Не удалось прочитать файл шрифта
+    #[error("не удалось загрузить шрифт {}: {message}", .path.display())]
+    LoadError { path: PathBuf, message: String },
+
+    /// Каталог шрифтов не найден
+    #[error("каталог шрифтов не найден: {}", .0.display())]
+    DirNotFound(PathBuf),
+}
+
+/// Источники шрифтов
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontOptions {
+    /// Файлы шрифтов (TTF, OTF, TTC)
+    pub font_files: Vec<PathBuf>,
+    /// Каталоги со шрифтами (обходятся рекурсивно)
+    pub font_dirs: Vec<PathBuf>,
+    /// Загружать системные шрифты (требует feature `system-fonts`)
+    pub system_fonts: bool,
+    /// Основное семейство (None = первое доступное из [`FALLBACK_FAMILIES`])
+    pub default_family: Option<String>,
+}
+
+impl FontOptions {
+    /// Добавляет файл шрифта
+    pub fn with_font_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.font_files.push(path.into());
+        self
+    }
+
+    /// Добавляет каталог шрифтов
+    pub fn with_font_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.font_dirs.push(path.into());
+        self
+    }
+
+    /// Включает поиск системных шрифтов
+    pub fn with_system_fonts(mut self) -> Self {
+        self.system_fonts = true;
+        self
+    }
+
+    /// Задаёт основное семейство
+    pub fn with_default_family(mut self, family: impl Into<String>) -> Self {
+        self.default_family = Some(family.into());
+        self
+    }
+}
+
+/// Набор загруженных шрифтов с порядком fallback
+#[derive(Clone)]
+pub struct FontSet {
+    database: Arc<Database>,
+    default_family: Option<String>,
+    /// Грани в порядке поиска символа: основное семейство, [`FALLBACK_FAMILIES`], остальные
+    fallback: Vec<ID>,
+}
+
+impl FontSet {
+    /// Загружает шрифты из указанных источников
+    pub fn new(options: &FontOptions) -> Result<Self, FontError> {
+        let mut database = Database::new();
+
+        #[cfg(feature = "embedded-font")]
+        database.load_font_data(EMBEDDED_FONT.to_vec());
+
+        #[cfg(feature = "system-fonts")]
+        if options.system_fonts {
+            database.load_system_fonts();
+        }
+
+        for dir in &options.font_dirs {
+            if !dir.is_dir() {
+                return Err(FontError::DirNotFound(dir.clone()));
+            }
+            database.load_fonts_dir(dir);
+        }
+
+        for file in &options.font_files {
+            database
+                .load_font_file(file)
+                .map_err(|e| FontError::LoadError {
+                    path: file.clone(),
+                    message: e.to_string(),
+                })?;
+        }
+
+        Ok(Self::from_database(
+            database,
+            options.default_family.as_deref(),
+        ))
+    }
+
+    /// Набор только со встроенным шрифтом (пустой без feature `embedded-font`)
+    pub fn embedded() -> Self {
+        #[allow(unused_mut)]
+        let mut database = Database::new();
+        #[cfg(feature = "embedded-font")]
+        database.load_font_data(EMBEDDED_FONT.to_vec());
+        Self::from_database(database, None)
+    }
+
+    /// Создаёт набор из готовой базы fontdb
+    pub fn from_database(mut database: Database, default_family: Option<&str>) -> Self {
+        let default_family = default_family
+            .filter(|family| has_family(&database, family))
+            .or_else(|| {
+                FALLBACK_FAMILIES
+                    .iter()
+                    .copied()
+                    .find(|family| has_family(&database, family))
+            })
+            .map(str::to_string)
+            .or_else(|| {
+                database
+                    .faces()
+                    .next()
+                    .and_then(|face| face.families.first())
+                    .map(|(name, _)| name.clone())
+            });
+
+        // Обобщённые семейства CSS указывают на основной шрифт, если их
+        // значения fontdb по умолчанию (Times New Roman, Arial, ...) не загружены
+        if let Some(family) = &default_family {
+            database.set_sans_serif_family(family.clone());
+            let generics: [(Family, SetFamily); 4] = [
+                (Family::Serif, |db, f| db.set_serif_family(f)),
+                (Family::Monospace, |db, f| db.set_monospace_family(f)),
+                (Family::Cursive, |db, f| db.set_cursive_family(f)),
+                (Family::Fantasy, |db, f| db.set_fantasy_family(f)),
+            ];
+            for (generic, set_family) in generics {
+                if !has_family(&database, database.family_name(&generic)) {
+                    set_family(&mut database, family.clone());
+                }
+            }
+        }
+
+        let mut fallback: Vec<ID> = Vec::new();
+        let families = default_family
+            .iter()
+            .map(String::as_str)
+            .chain(FALLBACK_FAMILIES.iter().copied());
+        for family in families {
+            for face in database.faces() {
+                if face_has_family(face, family) && !fallback.contains(&face.id) {
+                    fallback.push(face.id);
+                }
+            }
+        }
+        for face in database.faces() {
+            if !fallback.contains(&face.id) {
+                fallback.push(face.id);
+            }
+        }
+
+        Self {
+            database: Arc::new(database),
+            default_family,
+            fallback,
+        }
+    }
+
+    /// База шрифтов (для `usvg::Options::fontdb`)
+    pub fn database(&self) -> Arc<Database> {
+        Arc::clone(&self.database)
+    }
+
+    /// Основное семейство
+    pub fn default_family(&self) -> Option<&str> {
+        self.default_family.as_deref()
+    }
+
+    /// Нет ни одного шрифта
+    pub fn is_empty(&self) -> bool {
+        self.database.is_empty()
+    }
+
+    /// Загружено ли семейство
+    pub fn has_family(&self, family: &str) -> bool {
+        has_family(&self.database, family)
+    }
+
+    /// Подбирает грань для CSS `font-family` (как при растеризации SVG)
+    pub fn query(&self, font_family: &str) -> Option<ID> {
+        let mut families = query_families(font_family);
+        families.push(Family::SansSerif);
+        self.database.query(&Query {
+            families: &families,
+            ..Query::default()
+        })
+    }
+
+    /// Первая грань (кроме `exclude`), содержащая символ, в порядке fallback
+    pub fn face_for_char(&self, c: char, exclude: &[ID]) -> Option<ID> {
+        self.fallback
+            .iter()
+            .copied()
+            .filter(|id| !exclude.contains(id))
+            .find(|&id| self.advance(id, c).is_some())
+    }
+
+    /// Опции usvg с этим набором шрифтов (общие для PNG и PDF)
+    ///
+    /// Символы без глифа в выбранном шрифте ищутся в том же порядке,
+    /// что и в метриках `FontSet`.
+    #[cfg(any(feature = "png", feature = "pdf"))]
+    pub(crate) fn usvg_options(&self) -> resvg::usvg::Options<'static> {
+        use resvg::usvg::FontResolver;
+
+        let fallback = self.clone();
+        resvg::usvg::Options {
+            fontdb: self.database(),
+            font_family: self
+                .default_family()
+                .unwrap_or(EMBEDDED_FONT_FAMILY)
+                .to_string(),
+            font_resolver: FontResolver {
+                select_font: FontResolver::default_font_selector(),
+                select_fallback: Box::new(move |c, exclude, _| fallback.face_for_char(c, exclude)),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Ширина символа в долях кегля
+    fn advance(&self, id: ID, c: char) -> Option<f64> {
+        self.database
+            .with_face_data(id, |data, index| {
+                let face = ttf_parser::Face::parse(data, index).ok()?;
+                let glyph = face.glyph_index(c)?;
+                let advance = face.glyph_hor_advance(glyph)?;
+                Some(f64::from(advance) / f64::from(face.units_per_em()))
+            })
+            .flatten()
+    }
+}
+
+impl Default for FontSet {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
+
+impl TextMetrics for FontSet {
+    fn text_width(&self, text: &str, font_size: f64) -> f64 {
+        let estimated = EstimatedMetrics::default();
+        text.chars()
+            .filter(|c| !c.is_control())
+            .map(|c| {
+                self.face_for_char(c, &[])
+                    .and_then(|id| self.advance(id, c))
+                    .map(|advance| advance * font_size)
+                    .unwrap_or_else(|| estimated.text_width(c.encode_utf8(&mut [0; 4]), font_size))
+            })
+            .sum()
+    }
+}
+
+/// Установка обобщённого семейства в базе fontdb
+type SetFamily = fn(&mut Database, String);
+
+/// Есть ли в базе грань семейства (без учёта регистра)
+fn has_family(database: &Database, family: &str) -> bool {
+    database.faces().any(|face| face_has_family(face, family))
+}
+
+fn face_has_family(face: &fontdb::FaceInfo, family: &str) -> bool {
+    face.families
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(family))
+}
+
+/// Разбирает CSS `font-family` в запрос fontdb
+pub fn query_families(font_family: &str) -> Vec<Family<'_>> {
+    font_family
+        .split(',')
+        .map(|name| name.trim().trim_matches(|c| c == '"' || c == '\''))
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.to_ascii_lowercase().as_str() {
+            "serif" => Family::Serif,
+            "sans-serif" => Family::SansSerif,
+            "monospace" => Family::Monospace,
+            "cursive" => Family::Cursive,
+            "fantasy" => Family::Fantasy,
+            _ => Family::Name(name),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_options_builders() {
+        let options = FontOptions::default()
+            .with_font_file("a.ttf")
+            .with_font_dir("fonts")
+            .with_system_fonts()
+            .with_default_family("Noto Sans");
+        assert_eq!(options.font_files, vec![PathBuf::from("a.ttf")]);
+        assert_eq!(options.font_dirs, vec![PathBuf::from("fonts")]);
+        assert!(options.system_fonts);
+        assert_eq!(options.default_family.as_deref(), Some("Noto Sans"));
+    }
+
+    #[test]
+    fn test_missing_sources() {
+        let options = FontOptions::default().with_font_dir("/nonexistent/fonts");
+        assert!(matches!(
+            FontSet::new(&options),
+            Err(FontError::DirNotFound(_))
+        ));
+
+        let options = FontOptions::default().with_font_file("/nonexistent/font.ttf");
+        assert!(matches!(
+            FontSet::new(&options),
+            Err(FontError::LoadError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_query_families() {
+        let families = query_families("Helvetica, 'Segoe UI', sans-serif");
+        assert_eq!(families.len(), 3);
+        assert_eq!(families[0], Family::Name("Helvetica"));
+        assert_eq!(families[1], Family::Name("Segoe UI"));
+        assert_eq!(families[2], Family::SansSerif);
+    }
+
+    #[cfg(feature = "embedded-font")]
+    #[test]
+    fn test_embedded_font() {
+        let fonts = FontSet::embedded();
+        assert!(!fonts.is_empty());
+        assert_eq!(fonts.default_family(), Some(EMBEDDED_FONT_FAMILY));
+        assert!(fonts.query("sans-serif").is_some());
+        assert!(fonts.query("Helvetica, Arial, sans-serif").is_some());
+        // Кириллица есть во встроенном шрифте
+        assert!(fonts.face_for_char('Ж', &[]).is_some());
+    }
+
+    #[cfg(feature = "embedded-font")]
+    #[test]
+    fn test_font_metrics() {
+        let fonts = FontSet::embedded();
+        let narrow = fonts.text_width("iiii", 14.0);
+        let wide = fonts.text_width("WWWW", 14.0);
+        assert!(narrow > 0.0 && narrow < wide);
+        // Ширина пропорциональна кеглю
+        let double = fonts.text_width("WWWW", 28.0);
+        assert!((double - wide * 2.0).abs() < 1e-6);
+        // Символы без глифа оцениваются приближённо
+        assert!(fonts.text_width("漢", 14.0) > 0.0);
+    }
+
+    #[test]
+    fn test_without_fonts_falls_back_to_estimate() {
+        let fonts = FontSet::from_database(Database::new(), None);
+        assert!(fonts.is_empty());
+        assert_eq!(fonts.default_family(), None);
+        let estimated = EstimatedMetrics::default().text_width("abc", 12.0);
+        assert_eq!(fonts.text_width("abc", 12.0), estimated);
+    }
+}
@@ -0,0 +1,181 @@
+//! Интерактивный SVG: стабильные `id`, семантические CSS-классы и ссылки
+//!
+//! Каждый элемент диаграммы выводится группой `<g id="..." class="...">`,
+//! чтобы диаграммы можно было стилизовать и скриптовать на страницах
+//! документации:
+//!
+//! - `id` строится из [`LayoutElement::id`]: недопустимые в XML символы
+//!   заменяются на `_`, повторы получают суффикс `-2`, `-3`, ...
+//! - `class` — свойство `class` из layout (`participant`, `message`, ...)
+//!   либо выводится из типа элемента (`class`, `edge inheritance`, `state`)
+//! - `data-line` — строка объявления в исходном коде
+//! - ссылка `[[url подсказка]]` — `<a xlink:href>` и `<title>`
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{ClassifierKind, EdgeType, ElementType, LayoutElement};
+
+/// Пространство имён XLink для `xlink:href`
+pub(crate) const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Выдаёт уникальные `id` в порядке вывода элементов
+#[derive(Debug, Default)]
+pub(crate) struct ElementIds {
+    used: HashSet<String>,
+    /// Следующий номер суффикса для базового id
+    next: HashMap<String, usize>,
+}
+
+impl ElementIds {
+    /// Уникальный допустимый в XML id для элемента
+    pub(crate) fn unique(&mut self, id: &str) -> String {
+        let base = sanitize_id(id);
+        let mut n = self.next.get(&base).copied().unwrap_or(1);
+        let mut candidate = if n == 1 {
+            base.clone()
+        } else {
+            format!("{}-{}", base, n)
+        };
+        // Суффикс может совпасть с другим исходным id (`a-2`)
+        while self.used.contains(&candidate) {
+            n += 1;
+            candidate = format!("{}-{}", base, n);
+        }
+        self.next.insert(base, n + 1);
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// Заменяет недопустимые символы; id начинается с буквы или `_`
+fn sanitize_id(id: &str) -> String {
+    let mut out: String = id
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !out.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// Семантический CSS-класс элемента
+pub(crate) fn element_class(element: &LayoutElement) -> String {
+    if let Some(class) = element.properties.get("class") {
+        return class.clone();
+    }
+    match &element.element_type {
+        ElementType::Rectangle { .. } | ElementType::RoundedRectangle => "rectangle".to_string(),
+        ElementType::Ellipse { .. } => "ellipse".to_string(),
+        ElementType::InitialState => "state initial".to_string(),
+        ElementType::FinalState => "state final".to_string(),
+        ElementType::State { .. } => "state".to_string(),
+        ElementType::CompositeState { .. } => "state composite".to_string(),
+        ElementType::Actor { .. } => "actor".to_string(),
+        ElementType::System { .. } => "system".to_string(),
+        ElementType::Edge { edge_type, .. } => format!("edge {}", edge_class(*edge_type)),
+        ElementType::Text { .. } => "text".to_string(),
+        ElementType::Group { .. } => "group".to_string(),
+        ElementType::Fragment { .. } => "fragment".to_string(),
+        ElementType::Activation => "activation".to_string(),
+        ElementType::Path => "path".to_string(),
+        ElementType::ClassBox {
+            classifier_type, ..
+        } => classifier_class(*classifier_type).to_string(),
+        ElementType::ParticipantBox => "box".to_string(),
+    }
+}
+
+fn edge_class(edge_type: EdgeType) -> &'static str {
+    match edge_type {
+        EdgeType::Association => "association",
+        EdgeType::Inheritance => "inheritance",
+        EdgeType::Realization => "realization",
+        EdgeType::Composition => "composition",
+        EdgeType::Aggregation => "aggregation",
+        EdgeType::Dependency => "dependency",
+        EdgeType::Link => "link",
+    }
+}
+
+fn classifier_class(kind: ClassifierKind) -> &'static str {
+    match kind {
+        ClassifierKind::Class => "class",
+        ClassifierKind::Interface => "interface",
+        ClassifierKind::AbstractClass => "class abstract",
+        ClassifierKind::Enum => "enum",
+        ClassifierKind::Annotation => "annotation",
+        ClassifierKind::Entity => "entity",
+    }
+}
+
+/// Есть ли в диаграмме ссылки (нужно объявить пространство имён XLink)
+pub(crate) fn has_links(elements: &[LayoutElement]) -> bool {
+    elements.iter().any(|element| {
+        element.properties.contains_key("link")
+            || match &element.element_type {
+                ElementType::Group { children, .. } => has_links(children),
+                ElementType::Fragment { sections, .. } => {
+                    sections.iter().any(|section| has_links(&section.children))
+                }
+                _ => false,
+            }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rect;
+
+    #[test]
+    fn test_unique_ids() {
+        let mut ids = ElementIds::default();
+        assert_eq!(ids.unique("msg_A_B"), "msg_A_B");
+        assert_eq!(ids.unique("msg_A_B"), "msg_A_B-2");
+        assert_eq!(ids.unique("msg_A_B"), "msg_A_B-3");
+        assert_eq!(
+            ids.unique("participant_Web Server"),
+            "participant_Web_Server"
+        );
+        assert_eq!(ids.unique("[*]_initial"), "____initial");
+        assert_eq!(ids.unique("1st"), "_1st");
+        // Явный id, совпадающий с уже выданным суффиксом
+        assert_eq!(ids.unique("msg_A_B-2"), "msg_A_B-2-2");
+    }
+
+    #[test]
+    fn test_element_class() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let edge = LayoutElement::new(
+            "e",
+            rect,
+            ElementType::Edge {
+                points: Vec::new(),
+                label: None,
+                arrow_start: false,
+                arrow_end: true,
+                dashed: false,
+                edge_type: EdgeType::Inheritance,
+                from_cardinality: None,
+                to_cardinality: None,
+                curve: Vec::new(),
+                label_positions: Default::default(),
+            },
+        );
+        assert_eq!(element_class(&edge), "edge inheritance");
+
+        let message = edge.clone().with_property("class", "message");
+        assert_eq!(element_class(&message), "message");
+        assert!(!has_links(std::slice::from_ref(&message)));
+        assert!(has_links(&[
+            message.with_property("link", "https://example.com")
+        ]));
+    }
+}
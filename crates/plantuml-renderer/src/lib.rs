@@ -1,10 +1,21 @@
 //! # plantuml-renderer
 //!
-//! SVG и PNG рендеринг диаграмм PlantUML.
+//! SVG, PNG, JPEG, WebP, PDF, EPS и LaTeX/TikZ рендеринг диаграмм PlantUML.
 //!
 //! ## Features
 //!
-//! - `png` - включает PNG рендеринг через resvg (требует дополнительные зависимости)
+//! - `png` - включает PNG рендеринг через resvg и прямой растровый `RasterRenderer`
+//!   (tiny-skia + ab_glyph, без промежуточного SVG)
+//! - `jpeg`, `webp` - JPEG и WebP без потерь из `RasterRenderer` (включают `png`)
+//! - `pdf` - векторный PDF со встроенными шрифтами
+//! - `fonts` - загрузка шрифтов и метрики текста для layout (включается `png` и `pdf`)
+//! - `embedded-font` - встроенный запасной шрифт DejaVu Sans (включается `png` и `pdf`)
+//! - `system-fonts` - поиск системных шрифтов
+//!
+//! Кроме SVG строки, layout можно перевести в независимый от backend
+//! [`DisplayList`] команд рисования (для canvas в WASM и нативного GUI);
+//! PNG рендерер растеризует его напрямую, а [`EpsRenderer`] и
+//! [`TikzRenderer`] выводят по нему EPS и LaTeX/TikZ.
 //!
 //! ## Пример
 //!
@@ -23,22 +34,50 @@
 //! }
 //! ```
 
+pub mod display_list;
+pub mod eps_renderer;
+mod interactive;
 pub mod shapes;
+pub mod sketch;
+mod sprites;
 pub mod svg_renderer;
+pub mod tikz_renderer;
+
+#[cfg(feature = "fonts")]
+pub mod fonts;
 
 #[cfg(feature = "png")]
 pub mod png_renderer;
 
+#[cfg(feature = "png")]
+pub mod raster_renderer;
+
+#[cfg(feature = "pdf")]
+pub mod pdf_renderer;
+
 pub use plantuml_layout::{
-    ClassMember, ClassifierKind, EdgeType, ElementType, FragmentSection, 
+    ClassMember, ClassifierKind, EdgeLabelPositions, EdgeType, ElementType, FragmentSection, 
     LayoutElement, LayoutResult, MemberVisibility, Point, Rect, ZLayer,
 };
+pub use display_list::{DisplayList, DisplayListRenderer, DrawCommand, ImageData};
+pub use plantuml_ast::{Sprite, SpriteRegistry};
 pub use plantuml_themes::Theme;
+pub use eps_renderer::EpsRenderer;
 pub use svg_renderer::SvgRenderer;
+pub use tikz_renderer::TikzRenderer;
+
+#[cfg(feature = "fonts")]
+pub use fonts::{FontError, FontOptions, FontSet};
 
 #[cfg(feature = "png")]
 pub use png_renderer::{PngError, PngOptions, PngRenderer};
 
+#[cfg(feature = "png")]
+pub use raster_renderer::RasterRenderer;
+
+#[cfg(feature = "pdf")]
+pub use pdf_renderer::{PageSize, PdfError, PdfOptions, PdfRenderer};
+
 /// Трейт для рендереров
 pub trait Renderer {
     /// Тип выходных данных
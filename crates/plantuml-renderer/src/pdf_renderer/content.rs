@@ -0,0 +1,371 @@
+//! Перевод дерева usvg в операторы потока содержимого PDF
+//!
+//! Поддерживается то, что выдаёт `SvgRenderer`: группы с трансформациями и
+//! прозрачностью, контуры с заливкой и обводкой (штрихи, концы, соединения),
+//! текст встроенными шрифтами. Градиенты заменяются цветом первой
+//! остановки. Фильтры (тени), маски и обрезка не переносятся — в PDF они
+//! требуют растеризации, а диаграмма остаётся читаемой и без них.
+
+use resvg::usvg::tiny_skia_path::{PathSegment, Transform};
+use resvg::usvg::{
+    self, FillRule, Group, LineCap, LineJoin, Node, Paint, PaintOrder, Path, Text, Tree,
+};
+
+use super::font::FontResources;
+use super::writer::num;
+
+/// Ресурсы, общие для всех страниц документа
+pub(super) struct Resources {
+    pub(super) fonts: FontResources,
+    /// Пары прозрачности (заливка, обводка) в тысячных; индекс — имя `GS{n}`
+    pub(super) ext_gstates: Vec<(u16, u16)>,
+}
+
+impl Resources {
+    pub(super) fn new(fonts: FontResources) -> Self {
+        Self {
+            fonts,
+            ext_gstates: Vec::new(),
+        }
+    }
+
+    /// Имя ExtGState для прозрачности; `None`, если объект непрозрачен
+    fn ext_gstate(&mut self, fill: f32, stroke: f32) -> Option<String> {
+        let key = (alpha_key(fill), alpha_key(stroke));
+        if key == (1000, 1000) {
+            return None;
+        }
+        let index = match self.ext_gstates.iter().position(|&k| k == key) {
+            Some(index) => index,
+            None => {
+                self.ext_gstates.push(key);
+                self.ext_gstates.len() - 1
+            }
+        };
+        Some(format!("GS{}", index))
+    }
+}
+
+fn alpha_key(alpha: f32) -> u16 {
+    (alpha.clamp(0.0, 1.0) * 1000.0).round() as u16
+}
+
+/// Поток содержимого одного SVG-документа в координатах SVG
+pub(super) fn draw_tree(tree: &Tree, resources: &mut Resources) -> Vec<u8> {
+    let mut content = Content {
+        ops: String::new(),
+        resources,
+    };
+    content.group(tree.root(), 1.0);
+    content.ops.into_bytes()
+}
+
+struct Content<'a> {
+    ops: String,
+    resources: &'a mut Resources,
+}
+
+impl Content<'_> {
+    fn op(&mut self, op: &str) {
+        self.ops.push_str(op);
+        self.ops.push('\n');
+    }
+
+    fn transform(&mut self, ts: Transform) {
+        if !ts.is_identity() {
+            let op = format!(
+                "{} {} {} {} {} {} cm",
+                num(ts.sx),
+                num(ts.ky),
+                num(ts.kx),
+                num(ts.sy),
+                num(ts.tx),
+                num(ts.ty)
+            );
+            self.op(&op);
+        }
+    }
+
+    /// Группа; прозрачность группы умножается на прозрачность потомков
+    fn group(&mut self, group: &Group, opacity: f32) {
+        let opacity = opacity * group.opacity().get();
+        self.op("q");
+        self.transform(group.transform());
+        for child in group.children() {
+            match child {
+                Node::Group(group) => self.group(group, opacity),
+                Node::Path(path) => self.path(path, opacity),
+                Node::Text(text) => self.text(text, opacity),
+                // Растровые изображения SvgRenderer не создаёт
+                Node::Image(_) => {}
+            }
+        }
+        self.op("Q");
+    }
+
+    fn path(&mut self, path: &Path, opacity: f32) {
+        if !path.is_visible() {
+            return;
+        }
+        match path.paint_order() {
+            PaintOrder::FillAndStroke => self.path_with(path, true, true, opacity),
+            PaintOrder::StrokeAndFill => {
+                self.path_with(path, false, true, opacity);
+                self.path_with(path, true, false, opacity);
+            }
+        }
+    }
+
+    /// Рисует контур с заливкой и/или обводкой
+    fn path_with(&mut self, path: &Path, use_fill: bool, use_stroke: bool, opacity: f32) {
+        let fill = path
+            .fill()
+            .filter(|_| use_fill)
+            .and_then(|fill| Some((paint_color(fill.paint())?, fill)));
+        let stroke = path
+            .stroke()
+            .filter(|_| use_stroke)
+            .and_then(|stroke| Some((paint_color(stroke.paint())?, stroke)));
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+
+        self.op("q");
+        let fill_alpha = fill.as_ref().map_or(1.0, |(_, f)| f.opacity().get()) * opacity;
+        let stroke_alpha = stroke.as_ref().map_or(1.0, |(_, s)| s.opacity().get()) * opacity;
+        if let Some(gs) = self.resources.ext_gstate(fill_alpha, stroke_alpha) {
+            self.op(&format!("/{} gs", gs));
+        }
+        if let Some((color, _)) = &fill {
+            self.op(&format!("{} rg", color));
+        }
+        if let Some((color, stroke)) = &stroke {
+            self.op(&format!("{} RG", color));
+            self.op(&format!("{} w", num(stroke.width().get())));
+            self.op(&format!(
+                "{} J",
+                match stroke.linecap() {
+                    LineCap::Butt => 0,
+                    LineCap::Round => 1,
+                    LineCap::Square => 2,
+                }
+            ));
+            self.op(&format!(
+                "{} j",
+                match stroke.linejoin() {
+                    LineJoin::Miter | LineJoin::MiterClip => 0,
+                    LineJoin::Round => 1,
+                    LineJoin::Bevel => 2,
+                }
+            ));
+            self.op(&format!("{} M", num(stroke.miterlimit().get())));
+            if let Some(dashes) = stroke.dasharray() {
+                let dashes: Vec<String> = dashes.iter().map(|&d| num(d)).collect();
+                self.op(&format!(
+                    "[{}] {} d",
+                    dashes.join(" "),
+                    num(stroke.dashoffset())
+                ));
+            }
+        }
+
+        self.segments(path.data());
+
+        let even_odd = fill
+            .as_ref()
+            .is_some_and(|(_, f)| f.rule() == FillRule::EvenOdd);
+        self.op(match (fill.is_some(), stroke.is_some(), even_odd) {
+            (true, true, false) => "B",
+            (true, true, true) => "B*",
+            (true, false, false) => "f",
+            (true, false, true) => "f*",
+            _ => "S",
+        });
+        self.op("Q");
+    }
+
+    /// Сегменты контура; квадратичные кривые переводятся в кубические
+    fn segments(&mut self, data: &usvg::tiny_skia_path::Path) {
+        let mut current = (0.0, 0.0);
+        let mut start = (0.0, 0.0);
+        for segment in data.segments() {
+            match segment {
+                PathSegment::MoveTo(p) => {
+                    self.op(&format!("{} {} m", num(p.x), num(p.y)));
+                    current = (p.x, p.y);
+                    start = current;
+                }
+                PathSegment::LineTo(p) => {
+                    self.op(&format!("{} {} l", num(p.x), num(p.y)));
+                    current = (p.x, p.y);
+                }
+                PathSegment::QuadTo(c, p) => {
+                    let c1 = (
+                        current.0 + 2.0 / 3.0 * (c.x - current.0),
+                        current.1 + 2.0 / 3.0 * (c.y - current.1),
+                    );
+                    let c2 = (p.x + 2.0 / 3.0 * (c.x - p.x), p.y + 2.0 / 3.0 * (c.y - p.y));
+                    self.op(&format!(
+                        "{} {} {} {} {} {} c",
+                        num(c1.0),
+                        num(c1.1),
+                        num(c2.0),
+                        num(c2.1),
+                        num(p.x),
+                        num(p.y)
+                    ));
+                    current = (p.x, p.y);
+                }
+                PathSegment::CubicTo(c1, c2, p) => {
+                    self.op(&format!(
+                        "{} {} {} {} {} {} c",
+                        num(c1.x),
+                        num(c1.y),
+                        num(c2.x),
+                        num(c2.y),
+                        num(p.x),
+                        num(p.y)
+                    ));
+                    current = (p.x, p.y);
+                }
+                PathSegment::Close => {
+                    self.op("h");
+                    current = start;
+                }
+            }
+        }
+    }
+
+    /// Текст глифами встроенных шрифтов; если шрифт встроить нельзя — контурами
+    fn text(&mut self, text: &Text, opacity: f32) {
+        let spans = text.layouted();
+        let embeddable = spans
+            .iter()
+            .flat_map(|span| &span.positioned_glyphs)
+            .all(|glyph| self.resources.fonts.embeddable(glyph.font));
+        if !embeddable {
+            self.group(text.flattened(), opacity);
+            return;
+        }
+
+        for span in spans.iter().filter(|span| span.visible) {
+            for decoration in [&span.underline, &span.overline].into_iter().flatten() {
+                self.path(decoration, opacity);
+            }
+
+            if let Some((color, fill)) = span
+                .fill
+                .as_ref()
+                .and_then(|fill| Some((paint_color(fill.paint())?, fill)))
+            {
+                self.op("q");
+                if let Some(gs) = self
+                    .resources
+                    .ext_gstate(fill.opacity().get() * opacity, 1.0)
+                {
+                    self.op(&format!("/{} gs", gs));
+                }
+                self.op(&format!("{} rg", color));
+                self.op("BT");
+                let mut current_font = None;
+                for glyph in &span.positioned_glyphs {
+                    let Some((resource, units_per_em)) =
+                        self.resources
+                            .fonts
+                            .use_glyph(glyph.font, glyph.id.0, &glyph.text)
+                    else {
+                        continue;
+                    };
+                    let resource = resource.to_string();
+                    if current_font.as_ref() != Some(&resource) {
+                        self.op(&format!("/{} 1 Tf", resource));
+                        current_font = Some(resource);
+                    }
+                    // Единица текстового пространства — em: контур глифа
+                    // масштабируется на units per em
+                    let upem = f32::from(units_per_em);
+                    let ts = glyph.outline_transform().pre_scale(upem, upem);
+                    let op = format!(
+                        "{} {} {} {} {} {} Tm <{:04X}> Tj",
+                        num(ts.sx),
+                        num(ts.ky),
+                        num(ts.kx),
+                        num(ts.sy),
+                        num(ts.tx),
+                        num(ts.ty),
+                        glyph.id.0
+                    );
+                    self.op(&op);
+                }
+                self.op("ET");
+                self.op("Q");
+            }
+
+            if let Some(decoration) = &span.line_through {
+                self.path(decoration, opacity);
+            }
+        }
+    }
+}
+
+/// Цвет заливки/обводки в виде операндов `rg`/`RG`
+fn paint_color(paint: &Paint) -> Option<String> {
+    let color = match paint {
+        Paint::Color(color) => *color,
+        Paint::LinearGradient(gradient) => gradient.stops().first()?.color(),
+        Paint::RadialGradient(gradient) => gradient.stops().first()?.color(),
+        Paint::Pattern(_) => return None,
+    };
+    Some(format!(
+        "{} {} {}",
+        num(f32::from(color.red) / 255.0),
+        num(f32::from(color.green) / 255.0),
+        num(f32::from(color.blue) / 255.0)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::FontSet;
+
+    fn draw(svg: &str) -> (String, Resources) {
+        let fonts = FontSet::embedded();
+        let tree = Tree::from_str(svg, &fonts.usvg_options()).unwrap();
+        let mut resources = Resources::new(FontResources::new(fonts.database()));
+        let ops = draw_tree(&tree, &mut resources);
+        (String::from_utf8(ops).unwrap(), resources)
+    }
+
+    #[test]
+    fn test_path_operators() {
+        let (ops, resources) = draw(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <rect x="10" y="10" width="50" height="20" fill="#FF0000" stroke="black"
+                      stroke-dasharray="4 2" fill-opacity="0.5"/>
+            </svg>"##,
+        );
+        assert!(ops.contains("1 0 0 rg"));
+        assert!(ops.contains("0 0 0 RG"));
+        assert!(ops.contains("[4 2] 0 d"));
+        assert!(ops.contains("10 10 m"));
+        assert!(ops.contains("\nB\n"));
+        assert_eq!(resources.ext_gstates, vec![(500, 1000)]);
+    }
+
+    #[test]
+    fn test_text_uses_embedded_font() {
+        let (ops, resources) = draw(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="40">
+                <text x="5" y="20" font-size="14">Hi</text>
+            </svg>"#,
+        );
+        assert!(ops.contains("BT"));
+        assert!(ops.contains("/F0 1 Tf"));
+        assert_eq!(ops.matches(" Tj").count(), 2);
+
+        // Оба глифа из одной грани — один шрифт в документе
+        let mut writer = super::super::writer::PdfWriter::new(false);
+        assert_eq!(resources.fonts.write(&mut writer).len(), 1);
+    }
+}
@@ -0,0 +1,246 @@
+//! Встраивание шрифтов TrueType в PDF
+//!
+//! Каждая использованная грань встраивается целиком (`FontFile2`) как
+//! составной шрифт `Type0`/`CIDFontType2` с кодировкой `Identity-H`: код
+//! символа в строке — номер глифа. Ширины (`/W`) и таблица `ToUnicode`
+//! строятся только по использованным глифам, поэтому текст в PDF можно
+//! искать и копировать.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use fontdb::{Database, ID};
+
+use super::writer::{name, num, PdfWriter, Ref};
+
+/// Грань, используемая в документе
+struct EmbeddedFace {
+    /// Имя ресурса (`F0`, `F1`, ...)
+    resource: String,
+    id: ID,
+    units_per_em: u16,
+    /// Номер глифа → исходный текст кластера
+    glyphs: BTreeMap<u16, String>,
+}
+
+/// Шрифты документа
+pub(super) struct FontResources {
+    database: Arc<Database>,
+    faces: Vec<EmbeddedFace>,
+    /// Грани, которые нельзя встроить (коллекции TTC, CFF)
+    unsupported: Vec<ID>,
+}
+
+impl FontResources {
+    pub(super) fn new(database: Arc<Database>) -> Self {
+        Self {
+            database,
+            faces: Vec::new(),
+            unsupported: Vec::new(),
+        }
+    }
+
+    /// Можно ли вывести глифы грани текстом (иначе — контурами)
+    pub(super) fn embeddable(&mut self, id: ID) -> bool {
+        if self.faces.iter().any(|face| face.id == id) {
+            return true;
+        }
+        if self.unsupported.contains(&id) {
+            return false;
+        }
+
+        let units_per_em = self
+            .database
+            .with_face_data(id, |data, index| {
+                // FontFile2 — одиночный TrueType с таблицей glyf
+                if index != 0 || data.starts_with(b"ttcf") {
+                    return None;
+                }
+                let face = ttf_parser::Face::parse(data, index).ok()?;
+                face.tables().glyf?;
+                Some(face.units_per_em())
+            })
+            .flatten();
+
+        match units_per_em {
+            Some(units_per_em) => {
+                self.faces.push(EmbeddedFace {
+                    resource: format!("F{}", self.faces.len()),
+                    id,
+                    units_per_em,
+                    glyphs: BTreeMap::new(),
+                });
+                true
+            }
+            None => {
+                self.unsupported.push(id);
+                false
+            }
+        }
+    }
+
+    /// Отмечает глиф как использованный; возвращает имя ресурса и units per em
+    pub(super) fn use_glyph(&mut self, id: ID, glyph: u16, text: &str) -> Option<(&str, u16)> {
+        if !self.embeddable(id) {
+            return None;
+        }
+        let face = self.faces.iter_mut().find(|face| face.id == id)?;
+        face.glyphs.entry(glyph).or_insert_with(|| text.to_string());
+        Some((&face.resource, face.units_per_em))
+    }
+
+    /// Записывает шрифты; возвращает пары (имя ресурса, ссылка на шрифт)
+    pub(super) fn write(&self, writer: &mut PdfWriter) -> Vec<(String, Ref)> {
+        self.faces
+            .iter()
+            .filter_map(|face| {
+                let font = self.write_face(writer, face)?;
+                Some((face.resource.clone(), font))
+            })
+            .collect()
+    }
+
+    fn write_face(&self, writer: &mut PdfWriter, face: &EmbeddedFace) -> Option<Ref> {
+        let info = self.database.face(face.id)?;
+        let base_font = name(&info.post_script_name);
+
+        let (data, descriptor, widths) = self
+            .database
+            .with_face_data(face.id, |data, index| {
+                let ttf = ttf_parser::Face::parse(data, index).ok()?;
+                let scale = 1000.0 / f32::from(ttf.units_per_em());
+                let bbox = ttf.global_bounding_box();
+
+                let mut flags = 32; // Nonsymbolic
+                if ttf.is_monospaced() {
+                    flags |= 1;
+                }
+                if ttf.is_italic() {
+                    flags |= 64;
+                }
+                let ascent = f32::from(ttf.ascender()) * scale;
+                let descriptor = format!(
+                    "/Flags {} /FontBBox [{} {} {} {}] /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV 80",
+                    flags,
+                    num(f32::from(bbox.x_min) * scale),
+                    num(f32::from(bbox.y_min) * scale),
+                    num(f32::from(bbox.x_max) * scale),
+                    num(f32::from(bbox.y_max) * scale),
+                    num(ttf.italic_angle().unwrap_or(0.0)),
+                    num(ascent),
+                    num(f32::from(ttf.descender()) * scale),
+                    num(ttf.capital_height().map_or(ascent, |h| f32::from(h) * scale)),
+                );
+
+                let widths: Vec<String> = face
+                    .glyphs
+                    .keys()
+                    .map(|&glyph| {
+                        let advance = ttf
+                            .glyph_hor_advance(ttf_parser::GlyphId(glyph))
+                            .unwrap_or(0);
+                        format!("{} [{}]", glyph, num(f32::from(advance) * scale))
+                    })
+                    .collect();
+
+                Some((data.to_vec(), descriptor, widths.join(" ")))
+            })
+            .flatten()?;
+
+        let font = writer.alloc();
+        let cid_font = writer.alloc();
+        let descriptor_ref = writer.alloc();
+        let file = writer.alloc();
+        let to_unicode = writer.alloc();
+
+        writer.stream(file, &format!("/Length1 {}", data.len()), &data);
+        writer.object(
+            descriptor_ref,
+            &format!(
+                "<< /Type /FontDescriptor /FontName /{} {} /FontFile2 {} >>",
+                base_font, descriptor, file
+            ),
+        );
+        writer.object(
+            cid_font,
+            &format!(
+                "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} \
+                 /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+                 /FontDescriptor {} /CIDToGIDMap /Identity /DW 1000 /W [{}] >>",
+                base_font, descriptor_ref, widths
+            ),
+        );
+        writer.stream(to_unicode, "", to_unicode_cmap(&face.glyphs).as_bytes());
+        writer.object(
+            font,
+            &format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+                 /DescendantFonts [{}] /ToUnicode {} >>",
+                base_font, cid_font, to_unicode
+            ),
+        );
+        Some(font)
+    }
+}
+
+/// CMap `ToUnicode`: номер глифа → текст в UTF-16BE
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, String>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n\
+         12 dict begin\n\
+         begincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n\
+         /CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+
+    let entries: Vec<(u16, &String)> = glyphs
+        .iter()
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(&glyph, text)| (glyph, text))
+        .collect();
+    // Не больше 100 записей в блоке bfchar
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (glyph, text) in chunk {
+            let utf16: String = text
+                .encode_utf16()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, utf16));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::FontSet;
+
+    #[test]
+    fn test_embedded_font_is_embeddable() {
+        let fonts = FontSet::embedded();
+        let id = fonts.query("sans-serif").unwrap();
+        let mut resources = FontResources::new(fonts.database());
+        assert!(resources.embeddable(id));
+        let (resource, units_per_em) = resources.use_glyph(id, 36, "A").unwrap();
+        assert_eq!(resource, "F0");
+        assert_eq!(units_per_em, 2048);
+    }
+
+    #[test]
+    fn test_to_unicode_cmap() {
+        let mut glyphs = BTreeMap::new();
+        glyphs.insert(36, "A".to_string());
+        glyphs.insert(600, "Я".to_string());
+        let cmap = to_unicode_cmap(&glyphs);
+        assert!(cmap.contains("2 beginbfchar"));
+        assert!(cmap.contains("<0024> <0041>"));
+        assert!(cmap.contains("<0258> <042F>"));
+    }
+}
@@ -0,0 +1,597 @@
+//! Векторный PDF рендеринг
+//!
+//! Этот модуль доступен только при включённом feature `pdf`.
+//!
+//! SVG разбирается usvg (те же шрифты [`FontSet`], что и для PNG), после чего
+//! контуры переводятся в операторы PDF, а текст выводится глифами
+//! встроенных шрифтов TrueType — его можно выделять и искать.
+//!
+//! Каждый SVG-документ записывается один раз как Form XObject. Страница
+//! размещает его целиком ([`PageSize::Fit`] или вписывание в формат) либо,
+//! с [`PdfOptions::paginated`], показывает один фрагмент большой диаграммы:
+//! диаграмма, не помещающаяся на лист, разбивается на несколько страниц.
+//!
+//! # Пример
+//!
+//! ```rust,ignore
+//! use plantuml_renderer::{PageSize, PdfOptions, PdfRenderer};
+//!
+//! let options = PdfOptions::default()
+//!     .with_page_size(PageSize::A4)
+//!     .paginated();
+//! let renderer = PdfRenderer::new(options);
+//! let pdf_bytes = renderer.render_svg(svg_content)?;
+//! std::fs::write("diagram.pdf", pdf_bytes)?;
+//! ```
+
+mod content;
+mod font;
+mod writer;
+
+use std::path::PathBuf;
+
+use resvg::usvg;
+use thiserror::Error;
+
+use crate::fonts::{FontOptions, FontSet};
+
+use self::content::Resources;
+use self::font::FontResources;
+use self::writer::{num, text_string, PdfWriter};
+
+/// Ошибки PDF рендеринга
+#[derive(Error, Debug)]
+pub enum PdfError {
+    /// Ошибка парсинга SVG
+    #[error("ошибка парсинга SVG: {0}")]
+    SvgParseError(String),
+
+    /// Некорректные размеры диаграммы или страницы
+    #[error("некорректные размеры страницы")]
+    InvalidDimensions,
+
+    /// Нет ни одной диаграммы
+    #[error("пустой документ")]
+    EmptyDocument,
+
+    /// Ошибка загрузки шрифтов
+    #[error("ошибка шрифтов: {0}")]
+    FontError(String),
+
+    /// Ошибка записи файла
+    #[error("ошибка записи PDF: {0}")]
+    IoError(String),
+}
+
+/// Размер страницы (в пунктах, 1 pt = 1/72 дюйма)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    /// Страница по размеру диаграммы
+    #[default]
+    Fit,
+    /// A4 (595 × 842 pt)
+    A4,
+    /// A3 (842 × 1191 pt)
+    A3,
+    /// US Letter (612 × 792 pt)
+    Letter,
+    /// Произвольный размер
+    Custom { width: f32, height: f32 },
+}
+
+impl PageSize {
+    /// Размер (ширина, высота); `None` для [`PageSize::Fit`]
+    pub fn dimensions(self) -> Option<(f32, f32)> {
+        match self {
+            PageSize::Fit => None,
+            PageSize::A4 => Some((595.0, 842.0)),
+            PageSize::A3 => Some((842.0, 1191.0)),
+            PageSize::Letter => Some((612.0, 792.0)),
+            PageSize::Custom { width, height } => Some((width, height)),
+        }
+    }
+}
+
+/// Опции PDF рендеринга
+#[derive(Debug, Clone)]
+pub struct PdfOptions {
+    /// Размер страницы
+    pub page_size: PageSize,
+    /// Альбомная ориентация (для фиксированного размера страницы)
+    pub landscape: bool,
+    /// Поля страницы (pt)
+    pub margin: f32,
+    /// Масштаб: 1.0 — один пиксель SVG равен одному пункту
+    pub scale: f32,
+    /// Разбивать большую диаграмму на страницы вместо уменьшения
+    pub paginate: bool,
+    /// Сжимать потоки (`/FlateDecode`)
+    pub compress: bool,
+    /// Заголовок документа (`/Title`)
+    pub title: Option<String>,
+    /// Источники шрифтов для текста
+    pub fonts: FontOptions,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::Fit,
+            landscape: false,
+            margin: 0.0,
+            scale: 1.0,
+            paginate: false,
+            compress: true,
+            title: None,
+            fonts: FontOptions::default(),
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Устанавливает размер страницы
+    pub fn with_page_size(mut self, page_size: PageSize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Альбомная ориентация
+    pub fn landscape(mut self) -> Self {
+        self.landscape = true;
+        self
+    }
+
+    /// Устанавливает поля страницы (pt)
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Устанавливает масштаб
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Разбивает диаграмму, не помещающуюся на страницу, на несколько страниц
+    pub fn paginated(mut self) -> Self {
+        self.paginate = true;
+        self
+    }
+
+    /// Отключает сжатие потоков (удобно для отладки)
+    pub fn uncompressed(mut self) -> Self {
+        self.compress = false;
+        self
+    }
+
+    /// Устанавливает заголовок документа
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Добавляет файл шрифта
+    pub fn with_font_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fonts = self.fonts.with_font_file(path);
+        self
+    }
+
+    /// Добавляет каталог шрифтов
+    pub fn with_font_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fonts = self.fonts.with_font_dir(path);
+        self
+    }
+
+    /// Включает системные шрифты (требует feature `system-fonts`)
+    pub fn with_system_fonts(mut self) -> Self {
+        self.fonts = self.fonts.with_system_fonts();
+        self
+    }
+
+    /// Задаёт основное семейство шрифтов
+    pub fn with_font_family(mut self, family: impl Into<String>) -> Self {
+        self.fonts = self.fonts.with_default_family(family);
+        self
+    }
+}
+
+/// Страница: размер и положение диаграммы на ней
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Page {
+    width: f32,
+    height: f32,
+    /// Масштаб диаграммы
+    scale: f32,
+    /// Смещение левого верхнего угла диаграммы от левого верхнего угла страницы
+    offset_x: f32,
+    offset_y: f32,
+    /// Область обрезки (x, y сверху, ширина, высота) для фрагментов
+    clip: Option<(f32, f32, f32, f32)>,
+}
+
+/// PDF рендерер
+pub struct PdfRenderer {
+    options: PdfOptions,
+    /// Шрифты загружаются один раз; ошибка загрузки возвращается из `render_svg`
+    fonts: Result<FontSet, String>,
+}
+
+impl PdfRenderer {
+    /// Создаёт новый PDF рендерер и загружает шрифты из опций
+    pub fn new(options: PdfOptions) -> Self {
+        let fonts = FontSet::new(&options.fonts).map_err(|e| e.to_string());
+        Self { options, fonts }
+    }
+
+    /// Создаёт рендерер с готовым набором шрифтов (общим с метриками layout)
+    pub fn with_fonts(options: PdfOptions, fonts: FontSet) -> Self {
+        Self {
+            options,
+            fonts: Ok(fonts),
+        }
+    }
+
+    /// Набор шрифтов рендерера
+    pub fn fonts(&self) -> Result<&FontSet, PdfError> {
+        self.fonts
+            .as_ref()
+            .map_err(|e| PdfError::FontError(e.clone()))
+    }
+
+    /// Рендерит SVG строку в PDF
+    pub fn render_svg(&self, svg_content: &str) -> Result<Vec<u8>, PdfError> {
+        self.render_svgs(&[svg_content])
+    }
+
+    /// Рендерит несколько SVG в один PDF: каждая диаграмма с новой страницы
+    pub fn render_svgs(&self, svg_contents: &[&str]) -> Result<Vec<u8>, PdfError> {
+        if svg_contents.is_empty() {
+            return Err(PdfError::EmptyDocument);
+        }
+
+        let fonts = self.fonts()?;
+        let usvg_options = fonts.usvg_options();
+        let trees = svg_contents
+            .iter()
+            .map(|svg| {
+                usvg::Tree::from_str(svg, &usvg_options)
+                    .map_err(|e| PdfError::SvgParseError(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut writer = PdfWriter::new(self.options.compress);
+        let catalog = writer.alloc();
+        let info = writer.alloc();
+        let pages_ref = writer.alloc();
+        let resources_ref = writer.alloc();
+
+        let mut resources = Resources::new(FontResources::new(fonts.database()));
+        let mut xobjects = Vec::new();
+        let mut page_refs = Vec::new();
+
+        for (index, tree) in trees.iter().enumerate() {
+            let size = tree.size();
+            let pages = self.pages(size.width(), size.height())?;
+
+            // Диаграмма — Form XObject, страницы-фрагменты ссылаются на неё
+            let form = writer.alloc();
+            let body = content::draw_tree(tree, &mut resources);
+            writer.stream(
+                form,
+                &format!(
+                    "/Type /XObject /Subtype /Form /BBox [0 0 {} {}] /Resources {}",
+                    num(size.width()),
+                    num(size.height()),
+                    resources_ref
+                ),
+                &body,
+            );
+            let form_name = format!("X{}", index);
+            xobjects.push((form_name.clone(), form));
+
+            for page in pages {
+                let page_ref = writer.alloc();
+                let contents = writer.alloc();
+                writer.stream(contents, "", page_content(&page, &form_name).as_bytes());
+                writer.object(
+                    page_ref,
+                    &format!(
+                        "<< /Type /Page /Parent {} /MediaBox [0 0 {} {}] /Resources {} /Contents {} >>",
+                        pages_ref,
+                        num(page.width),
+                        num(page.height),
+                        resources_ref,
+                        contents
+                    ),
+                );
+                page_refs.push(page_ref);
+            }
+        }
+
+        // Общий словарь ресурсов: шрифты, прозрачность, диаграммы
+        let font_entries: Vec<String> = resources
+            .fonts
+            .write(&mut writer)
+            .into_iter()
+            .map(|(name, font)| format!("/{} {}", name, font))
+            .collect();
+        let gs_entries: Vec<String> = resources
+            .ext_gstates
+            .iter()
+            .enumerate()
+            .map(|(index, &(fill, stroke))| {
+                format!(
+                    "/GS{} << /Type /ExtGState /ca {} /CA {} >>",
+                    index,
+                    num(f32::from(fill) / 1000.0),
+                    num(f32::from(stroke) / 1000.0)
+                )
+            })
+            .collect();
+        let xobject_entries: Vec<String> = xobjects
+            .iter()
+            .map(|(name, form)| format!("/{} {}", name, form))
+            .collect();
+        writer.object(
+            resources_ref,
+            &format!(
+                "<< /ProcSet [/PDF /Text] /Font << {} >> /ExtGState << {} >> /XObject << {} >> >>",
+                font_entries.join(" "),
+                gs_entries.join(" "),
+                xobject_entries.join(" ")
+            ),
+        );
+
+        let kids: Vec<String> = page_refs.iter().map(|r| r.to_string()).collect();
+        writer.object(
+            pages_ref,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                page_refs.len()
+            ),
+        );
+        writer.object(
+            catalog,
+            &format!("<< /Type /Catalog /Pages {} >>", pages_ref),
+        );
+        let title = self
+            .options
+            .title
+            .as_deref()
+            .map(|title| format!(" /Title {}", text_string(title)))
+            .unwrap_or_default();
+        writer.object(
+            info,
+            &format!("<< /Producer {}{} >>", text_string("plantuml-rs"), title),
+        );
+
+        Ok(writer.finish(catalog, info))
+    }
+
+    /// Рендерит SVG и сохраняет в файл
+    pub fn render_to_file(
+        &self,
+        svg_content: &str,
+        path: &std::path::Path,
+    ) -> Result<(), PdfError> {
+        let pdf_data = self.render_svg(svg_content)?;
+        std::fs::write(path, pdf_data)
+            .map_err(|e| PdfError::IoError(format!("не удалось записать файл: {}", e)))
+    }
+
+    /// Раскладка диаграммы размером `width`×`height` (px) по страницам
+    fn pages(&self, width: f32, height: f32) -> Result<Vec<Page>, PdfError> {
+        let options = &self.options;
+        let content_width = width * options.scale;
+        let content_height = height * options.scale;
+        if !(content_width > 0.0 && content_height > 0.0) {
+            return Err(PdfError::InvalidDimensions);
+        }
+        let margin = options.margin.max(0.0);
+
+        let Some((mut page_width, mut page_height)) = options.page_size.dimensions() else {
+            return Ok(vec![Page {
+                width: content_width + 2.0 * margin,
+                height: content_height + 2.0 * margin,
+                scale: options.scale,
+                offset_x: margin,
+                offset_y: margin,
+                clip: None,
+            }]);
+        };
+        if options.landscape && page_width < page_height {
+            std::mem::swap(&mut page_width, &mut page_height);
+        }
+        let area_width = page_width - 2.0 * margin;
+        let area_height = page_height - 2.0 * margin;
+        if !(area_width > 0.0 && area_height > 0.0) {
+            return Err(PdfError::InvalidDimensions);
+        }
+
+        let fits = content_width <= area_width && content_height <= area_height;
+        if !options.paginate || fits {
+            // Уменьшаем до размера листа, но не увеличиваем
+            let fit = (area_width / content_width)
+                .min(area_height / content_height)
+                .min(1.0);
+            return Ok(vec![Page {
+                width: page_width,
+                height: page_height,
+                scale: options.scale * fit,
+                offset_x: margin,
+                offset_y: margin,
+                clip: None,
+            }]);
+        }
+
+        // Фрагменты по строкам: слева направо, сверху вниз
+        let columns = (content_width / area_width).ceil() as usize;
+        let rows = (content_height / area_height).ceil() as usize;
+        let mut pages = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                pages.push(Page {
+                    width: page_width,
+                    height: page_height,
+                    scale: options.scale,
+                    offset_x: margin - column as f32 * area_width,
+                    offset_y: margin - row as f32 * area_height,
+                    clip: Some((margin, margin, area_width, area_height)),
+                });
+            }
+        }
+        Ok(pages)
+    }
+}
+
+impl Default for PdfRenderer {
+    fn default() -> Self {
+        Self::new(PdfOptions::default())
+    }
+}
+
+/// Поток страницы: обрезка фрагмента и вывод диаграммы
+fn page_content(page: &Page, form_name: &str) -> String {
+    let mut ops = String::from("q\n");
+    if let Some((x, y, width, height)) = page.clip {
+        ops.push_str(&format!(
+            "{} {} {} {} re W n\n",
+            num(x),
+            num(page.height - y - height),
+            num(width),
+            num(height)
+        ));
+    }
+    // Ось Y в SVG направлена вниз, в PDF — вверх
+    ops.push_str(&format!(
+        "{} 0 0 {} {} {} cm\n/{} Do\nQ\n",
+        num(page.scale),
+        num(-page.scale),
+        num(page.offset_x),
+        num(page.height - page.offset_y),
+        form_name
+    ));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300">
+        <rect x="10" y="10" width="380" height="280" fill="#FEFECE" stroke="#A80036"/>
+        <text x="20" y="40" font-family="sans-serif" font-size="14">Привет, PDF</text>
+    </svg>"##;
+
+    fn count(pdf: &[u8], needle: &str) -> usize {
+        pdf.windows(needle.len())
+            .filter(|w| *w == needle.as_bytes())
+            .count()
+    }
+
+    #[test]
+    fn test_page_size_dimensions() {
+        assert_eq!(PageSize::Fit.dimensions(), None);
+        assert_eq!(PageSize::A4.dimensions(), Some((595.0, 842.0)));
+        let custom = PageSize::Custom {
+            width: 100.0,
+            height: 50.0,
+        };
+        assert_eq!(custom.dimensions(), Some((100.0, 50.0)));
+    }
+
+    #[test]
+    fn test_render_single_page() {
+        let renderer = PdfRenderer::new(PdfOptions::default().uncompressed());
+        let pdf = renderer.render_svg(SVG).unwrap();
+
+        assert!(pdf.starts_with(b"%PDF-1.7"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert_eq!(count(&pdf, "/Type /Page "), 1);
+        assert_eq!(count(&pdf, "/MediaBox [0 0 400 300]"), 1);
+        // Текст — встроенным шрифтом с таблицей ToUnicode
+        assert_eq!(count(&pdf, "/FontFile2"), 1);
+        assert_eq!(count(&pdf, "/ToUnicode"), 1);
+        assert!(count(&pdf, " Tj") > 0);
+    }
+
+    #[test]
+    fn test_fit_to_page() {
+        let options = PdfOptions::default()
+            .with_page_size(PageSize::Custom {
+                width: 200.0,
+                height: 200.0,
+            })
+            .with_margin(10.0);
+        let pages = PdfRenderer::new(options).pages(400.0, 300.0).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!((pages[0].scale - 0.45).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_paginated_tiles() {
+        let options = PdfOptions::default()
+            .with_page_size(PageSize::Custom {
+                width: 200.0,
+                height: 200.0,
+            })
+            .paginated()
+            .uncompressed();
+        let renderer = PdfRenderer::new(options);
+        assert_eq!(renderer.pages(400.0, 300.0).unwrap().len(), 4);
+
+        // Фрагменты ссылаются на одну диаграмму, шрифт встроен один раз
+        let pdf = renderer.render_svg(SVG).unwrap();
+        assert_eq!(count(&pdf, "/Type /Page "), 4);
+        assert_eq!(count(&pdf, "/Count 4"), 1);
+        assert_eq!(count(&pdf, "/Subtype /Form"), 1);
+        assert_eq!(count(&pdf, "re W n"), 4);
+        assert_eq!(count(&pdf, "/FontFile2"), 1);
+    }
+
+    #[test]
+    fn test_landscape() {
+        let options = PdfOptions::default()
+            .with_page_size(PageSize::A4)
+            .landscape();
+        let pages = PdfRenderer::new(options).pages(100.0, 100.0).unwrap();
+        assert_eq!((pages[0].width, pages[0].height), (842.0, 595.0));
+    }
+
+    #[test]
+    fn test_render_multiple_documents() {
+        let renderer = PdfRenderer::new(PdfOptions::default().with_title("Диаграммы"));
+        let pdf = renderer.render_svgs(&[SVG, SVG]).unwrap();
+        assert_eq!(count(&pdf, "/Type /Page "), 2);
+        assert_eq!(count(&pdf, "/Title <FEFF"), 1);
+        assert!(matches!(
+            renderer.render_svgs(&[]),
+            Err(PdfError::EmptyDocument)
+        ));
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let renderer = PdfRenderer::default();
+        assert!(matches!(
+            renderer.render_svg("not valid svg"),
+            Err(PdfError::SvgParseError(_))
+        ));
+
+        let options = PdfOptions::default().with_font_file("/nonexistent/font.ttf");
+        assert!(matches!(
+            PdfRenderer::new(options).render_svg(SVG),
+            Err(PdfError::FontError(_))
+        ));
+
+        let options = PdfOptions::default()
+            .with_page_size(PageSize::A4)
+            .with_margin(400.0);
+        assert!(matches!(
+            PdfRenderer::new(options).render_svg(SVG),
+            Err(PdfError::InvalidDimensions)
+        ));
+    }
+}
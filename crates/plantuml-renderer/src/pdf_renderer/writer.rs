@@ -0,0 +1,190 @@
+//! Запись объектов PDF и таблицы перекрёстных ссылок
+
+use std::fmt;
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+/// Ссылка на косвенный объект
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Ref(usize);
+
+impl fmt::Display for Ref {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} 0 R", self.0)
+    }
+}
+
+/// Документ PDF: объекты добавляются в любом порядке, номера выдаются заранее
+pub(super) struct PdfWriter {
+    /// Тела объектов; индекс 0 соответствует объекту 1
+    objects: Vec<Option<Vec<u8>>>,
+    compress: bool,
+}
+
+impl PdfWriter {
+    pub(super) fn new(compress: bool) -> Self {
+        Self {
+            objects: Vec::new(),
+            compress,
+        }
+    }
+
+    /// Резервирует номер объекта
+    pub(super) fn alloc(&mut self) -> Ref {
+        self.objects.push(None);
+        Ref(self.objects.len())
+    }
+
+    /// Записывает объект (словарь, массив, число)
+    pub(super) fn object(&mut self, id: Ref, body: &str) {
+        self.objects[id.0 - 1] = Some(body.as_bytes().to_vec());
+    }
+
+    /// Записывает поток; `dict` — записи словаря без `<<`/`>>` и `/Length`
+    pub(super) fn stream(&mut self, id: Ref, dict: &str, data: &[u8]) {
+        let (data, filter) = if self.compress {
+            (deflate(data), " /Filter /FlateDecode")
+        } else {
+            (data.to_vec(), "")
+        };
+        let mut body =
+            format!("<< {} /Length {}{} >>\nstream\n", dict, data.len(), filter).into_bytes();
+        body.extend_from_slice(&data);
+        body.extend_from_slice(b"\nendstream");
+        self.objects[id.0 - 1] = Some(body);
+    }
+
+    /// Собирает файл: заголовок, объекты, xref и trailer
+    pub(super) fn finish(self, catalog: Ref, info: Ref) -> Vec<u8> {
+        // Бинарный комментарий после заголовка — признак двоичного файла
+        let mut out = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (index, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            out.extend_from_slice(body.as_deref().unwrap_or(b"null"));
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n", self.objects.len() + 1).as_bytes());
+        out.extend_from_slice(b"0000000000 65535 f \n");
+        for offset in offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root {} /Info {} >>\nstartxref\n{}\n%%EOF\n",
+                self.objects.len() + 1,
+                catalog,
+                info,
+                xref
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+/// Сжатие потока (zlib, `/FlateDecode`)
+pub(super) fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Запись в Vec не завершается ошибкой
+    encoder.write_all(data).expect("запись в память");
+    encoder.finish().expect("запись в память")
+}
+
+/// Число без лишних нулей: `1`, `0.5`, `-12.125`
+pub(super) fn num(value: f32) -> String {
+    if !value.is_finite() {
+        return "0".to_string();
+    }
+    let rounded = (value * 1000.0).round() / 1000.0;
+    if rounded == rounded.trunc() {
+        format!("{}", rounded as i64)
+    } else {
+        let s = format!("{:.3}", rounded);
+        s.trim_end_matches('0').to_string()
+    }
+}
+
+/// Строка для словаря Info: литерал для ASCII, UTF-16BE с BOM иначе
+pub(super) fn text_string(value: &str) -> String {
+    if value.is_ascii() {
+        let mut out = String::from("(");
+        for c in value.chars() {
+            match c {
+                '(' | ')' | '\\' => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                c if c.is_ascii_control() => {}
+                c => out.push(c),
+            }
+        }
+        out.push(')');
+        out
+    } else {
+        let mut out = String::from("<FEFF");
+        for unit in value.encode_utf16() {
+            out.push_str(&format!("{:04X}", unit));
+        }
+        out.push('>');
+        out
+    }
+}
+
+/// Имя PDF без пробелов и служебных символов
+pub(super) fn name(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '.'))
+        .collect();
+    if name.is_empty() {
+        "Font".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_num() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(-0.5), "-0.5");
+        assert_eq!(num(12.1254), "12.125");
+        assert_eq!(num(f32::NAN), "0");
+    }
+
+    #[test]
+    fn test_text_string() {
+        assert_eq!(text_string("a(b)"), "(a\\(b\\))");
+        assert_eq!(text_string("Я"), "<FEFF042F>");
+        assert_eq!(name("DejaVu Sans"), "DejaVuSans");
+    }
+
+    #[test]
+    fn test_xref_offsets() {
+        let mut writer = PdfWriter::new(false);
+        let catalog = writer.alloc();
+        let info = writer.alloc();
+        writer.object(catalog, "<< /Type /Catalog >>");
+        writer.object(info, "<< >>");
+        let pdf = writer.finish(catalog, info);
+
+        // Смещение из xref указывает на начало объекта 1 (в байтах)
+        let xref = pdf.windows(5).position(|w| w == b"xref\n").unwrap();
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        let first = table.lines().nth(3).unwrap();
+        let offset: usize = first[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(b"1 0 obj"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+    }
+}
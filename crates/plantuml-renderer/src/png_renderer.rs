@@ -2,6 +2,10 @@
 //!
 //! Этот модуль доступен только при включённом feature `png`.
 //!
+//! Текст растеризуется шрифтами [`FontSet`]: встроенным DejaVu Sans,
+//! файлами и каталогами из [`PngOptions::fonts`] и, с feature `system-fonts`,
+//! системными шрифтами.
+//!
 //! # Пример
 //!
 //! ```rust,ignore
@@ -13,8 +17,13 @@
 //! std::fs::write("diagram.png", png_bytes)?;
 //! ```
 
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::display_list::parse_rgb;
+use crate::fonts::{FontOptions, FontSet};
+
 /// Ошибки PNG рендеринга
 #[derive(Error, Debug)]
 pub enum PngError {
@@ -33,6 +42,10 @@ pub enum PngError {
     /// Некорректные размеры
     #[error("некорректные размеры изображения")]
     InvalidDimensions,
+
+    /// Ошибка загрузки шрифтов
+    #[error("ошибка шрифтов: {0}")]
+    FontError(String),
 }
 
 /// Опции PNG рендеринга
@@ -42,6 +55,10 @@ pub struct PngOptions {
     pub scale: f32,
     /// Цвет фона (None = прозрачный)
     pub background_color: Option<tiny_skia::Color>,
+    /// Источники шрифтов для текста
+    pub fonts: FontOptions,
+    /// Качество JPEG (1–100) для `RasterRenderer::render_jpeg`
+    pub quality: u8,
 }
 
 impl Default for PngOptions {
@@ -49,6 +66,8 @@ impl Default for PngOptions {
         Self {
             scale: 1.0,
             background_color: Some(tiny_skia::Color::WHITE),
+            fonts: FontOptions::default(),
+            quality: 90,
         }
     }
 }
@@ -75,23 +94,70 @@ impl PngOptions {
         self.background_color = parse_css_color(color);
         self
     }
+
+    /// Добавляет файл шрифта
+    pub fn with_font_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fonts = self.fonts.with_font_file(path);
+        self
+    }
+
+    /// Добавляет каталог шрифтов
+    pub fn with_font_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fonts = self.fonts.with_font_dir(path);
+        self
+    }
+
+    /// Включает системные шрифты (требует feature `system-fonts`)
+    pub fn with_system_fonts(mut self) -> Self {
+        self.fonts = self.fonts.with_system_fonts();
+        self
+    }
+
+    /// Задаёт качество JPEG (ограничивается диапазоном 1–100)
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Задаёт основное семейство шрифтов
+    pub fn with_font_family(mut self, family: impl Into<String>) -> Self {
+        self.fonts = self.fonts.with_default_family(family);
+        self
+    }
 }
 
 /// PNG рендерер
 pub struct PngRenderer {
-    options: PngOptions,
+    pub(crate) options: PngOptions,
+    /// Шрифты загружаются один раз; ошибка загрузки возвращается из `render_svg`
+    fonts: Result<FontSet, String>,
 }
 
 impl PngRenderer {
-    /// Создаёт новый PNG рендерер с опциями по умолчанию
+    /// Создаёт новый PNG рендерер и загружает шрифты из опций
     pub fn new(options: PngOptions) -> Self {
-        Self { options }
+        let fonts = FontSet::new(&options.fonts).map_err(|e| e.to_string());
+        Self { options, fonts }
+    }
+
+    /// Создаёт рендерер с готовым набором шрифтов (общим с метриками layout)
+    pub fn with_fonts(options: PngOptions, fonts: FontSet) -> Self {
+        Self {
+            options,
+            fonts: Ok(fonts),
+        }
+    }
+
+    /// Набор шрифтов рендерера
+    pub fn fonts(&self) -> Result<&FontSet, PngError> {
+        self.fonts
+            .as_ref()
+            .map_err(|e| PngError::FontError(e.clone()))
     }
 
     /// Рендерит SVG строку в PNG байты
     pub fn render_svg(&self, svg_content: &str) -> Result<Vec<u8>, PngError> {
-        // Настройки парсинга SVG (без fontdb для WASM-совместимости)
-        let usvg_options = resvg::usvg::Options::default();
+        let usvg_options = self.fonts()?.usvg_options();
 
         // Парсим SVG
         let tree = resvg::usvg::Tree::from_str(svg_content, &usvg_options)
@@ -147,37 +213,12 @@ impl Default for PngRenderer {
 }
 
 /// Парсит CSS цвет в tiny_skia::Color
-fn parse_css_color(css: &str) -> Option<tiny_skia::Color> {
-    let css = css.trim();
-
-    // Формат #RRGGBB или #RGB
-    if let Some(hex) = css.strip_prefix('#') {
-        let hex = hex.to_uppercase();
-
-        if hex.len() == 6 {
-            let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-            return Some(tiny_skia::Color::from_rgba8(r, g, b, 255));
-        } else if hex.len() == 3 {
-            let r = u8::from_str_radix(&hex[0..1], 16).ok()? * 17;
-            let g = u8::from_str_radix(&hex[1..2], 16).ok()? * 17;
-            let b = u8::from_str_radix(&hex[2..3], 16).ok()? * 17;
-            return Some(tiny_skia::Color::from_rgba8(r, g, b, 255));
-        }
-    }
-
-    // Именованные цвета
-    match css.to_lowercase().as_str() {
-        "white" => Some(tiny_skia::Color::WHITE),
-        "black" => Some(tiny_skia::Color::BLACK),
-        "transparent" => Some(tiny_skia::Color::TRANSPARENT),
-        "red" => Some(tiny_skia::Color::from_rgba8(255, 0, 0, 255)),
-        "green" => Some(tiny_skia::Color::from_rgba8(0, 128, 0, 255)),
-        "blue" => Some(tiny_skia::Color::from_rgba8(0, 0, 255, 255)),
-        "yellow" => Some(tiny_skia::Color::from_rgba8(255, 255, 0, 255)),
-        _ => None,
+pub(crate) fn parse_css_color(css: &str) -> Option<tiny_skia::Color> {
+    if css.trim().eq_ignore_ascii_case("transparent") {
+        return Some(tiny_skia::Color::TRANSPARENT);
     }
+    let [r, g, b] = parse_rgb(css)?;
+    Some(tiny_skia::Color::from_rgba8(r, g, b, 255))
 }
 
 #[cfg(test)]
@@ -212,6 +253,8 @@ mod tests {
         let opts = PngOptions::default();
         assert_eq!(opts.scale, 1.0);
         assert!(opts.background_color.is_some());
+        assert_eq!(opts.quality, 90);
+        assert_eq!(opts.with_quality(0).quality, 1);
     }
 
     #[test]
@@ -258,4 +301,40 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(result, Err(PngError::SvgParseError(_))));
     }
+
+    #[test]
+    fn test_png_options_fonts() {
+        let opts = PngOptions::default()
+            .with_font_file("custom.ttf")
+            .with_font_dir("fonts")
+            .with_font_family("Noto Sans");
+        assert_eq!(opts.fonts.font_files.len(), 1);
+        assert_eq!(opts.fonts.font_dirs.len(), 1);
+        assert_eq!(opts.fonts.default_family.as_deref(), Some("Noto Sans"));
+    }
+
+    #[test]
+    fn test_render_missing_font_file() {
+        let opts = PngOptions::default().with_font_file("/nonexistent/font.ttf");
+        let renderer = PngRenderer::new(opts);
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#;
+        assert!(matches!(
+            renderer.render_svg(svg),
+            Err(PngError::FontError(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_text_draws_glyphs() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="120" height="40">
+            <text x="5" y="28" font-family="sans-serif" font-size="20" fill="black">Текст</text>
+        </svg>"#;
+
+        let renderer = PngRenderer::new(PngOptions::transparent());
+
+        // С встроенным шрифтом подпись растеризуется в непрозрачные пиксели
+        let png = renderer.render_svg(svg).unwrap();
+        let pixmap = tiny_skia::Pixmap::decode_png(&png).unwrap();
+        assert!(pixmap.pixels().iter().any(|p| p.alpha() > 0));
+    }
 }
@@ -0,0 +1,239 @@
+//! Прямой растровый рендеринг layout (tiny-skia + ab_glyph)
+//!
+//! Этот модуль доступен только при включённом feature `png`.
+//!
+//! В отличие от [`PngRenderer::render_svg`](crate::PngRenderer::render_svg),
+//! диаграмма не сериализуется в SVG и не разбирается usvg: элементы layout
+//! переводятся в команды [`DisplayList`](crate::DisplayList) в памяти и сразу
+//! рисуются на [`Pixmap`]. Геометрия фигур совпадает с SVG рендерером,
+//! включая рукописный режим (`handwritten`).
+//!
+//! Кроме PNG, изображение кодируется в JPEG (feature `jpeg`, прозрачность
+//! смешивается с белым) и WebP без потерь (feature `webp`).
+//!
+//! # Пример
+//!
+//! ```rust,ignore
+//! use plantuml_renderer::{PngOptions, RasterRenderer, Renderer};
+//!
+//! let renderer = RasterRenderer::new(PngOptions::default());
+//! let pixmap = renderer.render(&layout, &theme)?;
+//! let png_bytes = renderer.render_png(&layout, &theme)?;
+//! ```
+
+use tiny_skia::Pixmap;
+
+use crate::display_list::raster::rasterize;
+use crate::fonts::FontSet;
+use crate::png_renderer::{PngError, PngOptions};
+use crate::{DisplayListRenderer, LayoutResult, RenderOptions, Renderer, SpriteRegistry, Theme};
+
+/// Растровый рендерер layout без промежуточного SVG
+pub struct RasterRenderer {
+    options: PngOptions,
+    render_options: RenderOptions,
+    sprites: SpriteRegistry,
+    /// Шрифты загружаются один раз; ошибка загрузки возвращается из `render`
+    fonts: Result<FontSet, String>,
+}
+
+impl RasterRenderer {
+    /// Создаёт рендерер и загружает шрифты из опций
+    pub fn new(options: PngOptions) -> Self {
+        let fonts = FontSet::new(&options.fonts).map_err(|e| e.to_string());
+        Self {
+            options,
+            render_options: RenderOptions::default(),
+            sprites: SpriteRegistry::new(),
+            fonts,
+        }
+    }
+
+    /// Создаёт рендерер с готовым набором шрифтов (общим с метриками layout)
+    pub fn with_fonts(options: PngOptions, fonts: FontSet) -> Self {
+        Self {
+            options,
+            render_options: RenderOptions::default(),
+            sprites: SpriteRegistry::new(),
+            fonts: Ok(fonts),
+        }
+    }
+
+    /// Устанавливает опции построения диаграммы (фон холста диаграммы)
+    pub fn with_render_options(mut self, render_options: RenderOptions) -> Self {
+        self.render_options = render_options;
+        self
+    }
+
+    /// Задаёт спрайты диаграммы (`DiagramMetadata::sprites`)
+    pub fn with_sprites(mut self, sprites: SpriteRegistry) -> Self {
+        self.sprites = sprites;
+        self
+    }
+
+    /// Набор шрифтов рендерера
+    pub fn fonts(&self) -> Result<&FontSet, PngError> {
+        self.fonts
+            .as_ref()
+            .map_err(|e| PngError::FontError(e.clone()))
+    }
+
+    /// Рендерит layout и кодирует результат в PNG
+    pub fn render_png(&self, layout: &LayoutResult, theme: &Theme) -> Result<Vec<u8>, PngError> {
+        self.render(layout, theme)?
+            .encode_png()
+            .map_err(|e| PngError::EncodingError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "jpeg")]
+impl RasterRenderer {
+    /// Рендерит layout в JPEG с качеством [`PngOptions::quality`]
+    pub fn render_jpeg(&self, layout: &LayoutResult, theme: &Theme) -> Result<Vec<u8>, PngError> {
+        encode_jpeg(&self.render(layout, theme)?, self.options.quality)
+    }
+}
+
+#[cfg(feature = "webp")]
+impl RasterRenderer {
+    /// Рендерит layout в WebP без потерь
+    pub fn render_webp(&self, layout: &LayoutResult, theme: &Theme) -> Result<Vec<u8>, PngError> {
+        encode_webp(&self.render(layout, theme)?)
+    }
+}
+
+impl Default for RasterRenderer {
+    fn default() -> Self {
+        Self::new(PngOptions::default())
+    }
+}
+
+impl Renderer for RasterRenderer {
+    type Output = Result<Pixmap, PngError>;
+
+    fn render(&self, layout: &LayoutResult, theme: &Theme) -> Self::Output {
+        let list = DisplayListRenderer::with_options(self.render_options.clone())
+            .with_sprites(self.sprites.clone())
+            .render(layout, theme);
+        rasterize(&list, &self.options, self.fonts()?)
+    }
+}
+
+/// Кодирует pixmap в JPEG; полупрозрачные пиксели смешиваются с белым
+#[cfg(feature = "jpeg")]
+fn encode_jpeg(pixmap: &Pixmap, quality: u8) -> Result<Vec<u8>, PngError> {
+    let width = u16::try_from(pixmap.width()).map_err(|_| PngError::InvalidDimensions)?;
+    let height = u16::try_from(pixmap.height()).map_err(|_| PngError::InvalidDimensions)?;
+    // Каналы премультиплицированы: наложение на белый — прибавка (255 - альфа)
+    let rgb: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let white = 255 - p.alpha();
+            [p.red() + white, p.green() + white, p.blue() + white]
+        })
+        .collect();
+
+    let mut jpeg = Vec::new();
+    jpeg_encoder::Encoder::new(&mut jpeg, quality)
+        .encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| PngError::EncodingError(e.to_string()))?;
+    Ok(jpeg)
+}
+
+/// Кодирует pixmap в WebP без потерь (VP8L)
+#[cfg(feature = "webp")]
+fn encode_webp(pixmap: &Pixmap) -> Result<Vec<u8>, PngError> {
+    let rgba: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+
+    let mut webp = Vec::new();
+    image_webp::WebPEncoder::new(&mut webp)
+        .encode(
+            &rgba,
+            pixmap.width(),
+            pixmap.height(),
+            image_webp::ColorType::Rgba8,
+        )
+        .map_err(|e| PngError::EncodingError(e.to_string()))?;
+    Ok(webp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElementType, LayoutElement, Rect};
+
+    fn layout() -> LayoutResult {
+        let mut layout = LayoutResult::empty();
+        layout.elements.push(LayoutElement::new(
+            "a",
+            Rect::new(0.0, 0.0, 80.0, 30.0),
+            ElementType::Rectangle {
+                label: "Alice".to_string(),
+                corner_radius: 0.0,
+            },
+        ));
+        layout.calculate_bounds();
+        layout
+    }
+
+    #[test]
+    fn test_render_pixmap() {
+        let pixmap = RasterRenderer::default()
+            .render(&layout(), &Theme::default())
+            .unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (90, 40));
+        // Текст нарисован глифами: внутри прямоугольника есть тёмные пиксели
+        let dark = pixmap
+            .pixels()
+            .iter()
+            .filter(|p| p.red() < 100 && p.alpha() > 200)
+            .count();
+        assert!(dark > 20, "тёмных пикселей: {}", dark);
+    }
+
+    #[test]
+    fn test_render_scaled_png() {
+        let renderer = RasterRenderer::new(PngOptions::with_scale(2.0));
+        let png = renderer.render_png(&layout(), &Theme::default()).unwrap();
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (180, 80));
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn test_render_jpeg() {
+        let renderer = RasterRenderer::new(PngOptions::transparent().with_quality(80));
+        let jpeg = renderer.render_jpeg(&layout(), &Theme::default()).unwrap();
+        // SOI и EOI маркеры JPEG
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+        assert_eq!(&jpeg[jpeg.len() - 2..], &[0xFF, 0xD9]);
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_render_webp() {
+        let webp = RasterRenderer::default()
+            .render_webp(&layout(), &Theme::default())
+            .unwrap();
+        assert_eq!(&webp[..4], b"RIFF");
+        assert_eq!(&webp[8..16], b"WEBPVP8L");
+    }
+
+    #[test]
+    fn test_render_empty_layout() {
+        let pixmap = RasterRenderer::new(PngOptions::default().with_background("#336699"))
+            .render(&LayoutResult::empty(), &Theme::default())
+            .unwrap();
+        // Пустая диаграмма — только поля, залитые фоном
+        assert_eq!((pixmap.width(), pixmap.height()), (10, 10));
+        assert!(pixmap.pixels().iter().all(|p| p.blue() == 0x99));
+    }
+}
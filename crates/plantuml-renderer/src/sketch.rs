@@ -0,0 +1,263 @@
+//! Рукописный стиль (`skinparam handwritten true`)
+//!
+//! Линии и контуры фигур рисуются с небольшим дрожанием, как от руки.
+//! Дрожание детерминировано: генератор инициализируется координатами фигуры,
+//! поэтому одна и та же диаграмма всегда даёт одинаковый SVG.
+
+use crate::Point;
+
+/// Максимальное смещение точек по умолчанию (px)
+const DEFAULT_ROUGHNESS: f64 = 1.2;
+
+/// Генератор дрожащих контуров
+#[derive(Debug, Clone)]
+pub struct Sketch {
+    state: u64,
+    roughness: f64,
+}
+
+impl Sketch {
+    /// Создаёт генератор с заданным seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            roughness: DEFAULT_ROUGHNESS,
+        }
+    }
+
+    /// Создаёт генератор, seed которого получен из координат фигуры
+    pub fn seeded(values: &[f64]) -> Self {
+        // FNV-1a по битам координат
+        let seed = values.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, value| {
+            value.to_bits().to_le_bytes().iter().fold(hash, |h, &b| {
+                (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+            })
+        });
+        Self::new(seed)
+    }
+
+    /// Задаёт максимальное смещение точек
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    /// Следующее псевдослучайное число в диапазоне [-1, 1] (splitmix64)
+    fn next(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+
+    /// Случайное смещение не больше `roughness * scale`
+    fn jitter(&mut self, scale: f64) -> f64 {
+        self.next() * self.roughness * scale
+    }
+
+    /// Точка со смещением
+    fn shake(&mut self, p: Point) -> Point {
+        Point::new(p.x + self.jitter(1.0), p.y + self.jitter(1.0))
+    }
+
+    /// Добавляет к пути изогнутый отрезок до `to` (квадратичная кривая)
+    fn segment(&mut self, d: &mut String, from: Point, to: Point) {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length < f64::EPSILON {
+            return;
+        }
+        // Изгиб перпендикулярно отрезку, растёт с длиной, но ограничен
+        let bow = self.jitter((length / 60.0).min(1.5));
+        let control = Point::new(
+            from.x + dx * (0.5 + self.jitter(0.05)) - dy / length * bow,
+            from.y + dy * (0.5 + self.jitter(0.05)) + dx / length * bow,
+        );
+        let end = self.shake(to);
+        d.push_str(&format!(
+            " Q{},{} {},{}",
+            round(control.x),
+            round(control.y),
+            round(end.x),
+            round(end.y)
+        ));
+    }
+
+    /// Дрожащая линия
+    pub fn line(&mut self, from: Point, to: Point) -> String {
+        self.polyline(&[from, to])
+    }
+
+    /// Дрожащая ломаная
+    pub fn polyline(&mut self, points: &[Point]) -> String {
+        let Some(&first) = points.first() else {
+            return String::new();
+        };
+        let start = self.shake(first);
+        let mut d = format!("M{},{}", round(start.x), round(start.y));
+        for pair in points.windows(2) {
+            self.segment(&mut d, pair[0], pair[1]);
+        }
+        d
+    }
+
+    /// Дрожащий замкнутый многоугольник
+    pub fn polygon(&mut self, points: &[Point]) -> String {
+        if points.is_empty() {
+            return String::new();
+        }
+        let mut closed = points.to_vec();
+        closed.push(points[0]);
+        let mut d = self.polyline(&closed);
+        d.push_str(" Z");
+        d
+    }
+
+    /// Дрожащий прямоугольник
+    pub fn rectangle(&mut self, x: f64, y: f64, width: f64, height: f64) -> String {
+        self.polygon(&[
+            Point::new(x, y),
+            Point::new(x + width, y),
+            Point::new(x + width, y + height),
+            Point::new(x, y + height),
+        ])
+    }
+
+    /// Дрожащий эллипс (четыре кубические дуги с немного разными радиусами)
+    pub fn ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64) -> String {
+        // Коэффициент аппроксимации четверти окружности кубической кривой
+        const K: f64 = 0.552_284_75;
+        let scale = (rx.min(ry) / 10.0).min(1.0);
+        let mut radius = |r: f64| r + self.jitter(scale);
+        let (r1, r2, r3, r4) = (radius(rx), radius(ry), radius(rx), radius(ry));
+
+        let right = Point::new(cx + r1, cy);
+        let bottom = Point::new(cx, cy + r2);
+        let left = Point::new(cx - r3, cy);
+        let top = Point::new(cx, cy - r4);
+        // Конец чуть заходит за начало, как у незамкнутого от руки овала
+        let end = Point::new(cx + r1, cy + self.jitter(scale));
+
+        // Четверти: (c1, c2, конец) по часовой стрелке от правой точки
+        let quarters = [
+            (
+                Point::new(cx + r1, cy + r2 * K),
+                Point::new(cx + r3 * K, cy + r2),
+                bottom,
+            ),
+            (
+                Point::new(cx - r3 * K, cy + r2),
+                Point::new(cx - r3, cy + r2 * K),
+                left,
+            ),
+            (
+                Point::new(cx - r3, cy - r4 * K),
+                Point::new(cx - r1 * K, cy - r4),
+                top,
+            ),
+            (
+                Point::new(cx + r1 * K, cy - r4),
+                Point::new(cx + r1, cy - r4 * K),
+                end,
+            ),
+        ];
+
+        let mut d = format!("M{},{}", round(right.x), round(right.y));
+        for (c1, c2, to) in quarters {
+            d.push_str(&format!(
+                " C{},{} {},{} {},{}",
+                round(c1.x),
+                round(c1.y),
+                round(c2.x),
+                round(c2.y),
+                round(to.x),
+                round(to.y)
+            ));
+        }
+        d
+    }
+
+    /// Дрожащая кривая Безье: p0, затем тройки (c1, c2, p1)
+    pub fn curve(&mut self, curve: &[Point]) -> String {
+        let Some(&first) = curve.first() else {
+            return String::new();
+        };
+        let start = self.shake(first);
+        let mut d = format!("M{},{}", round(start.x), round(start.y));
+        for segment in curve[1..].chunks(3) {
+            if segment.len() < 3 {
+                break;
+            }
+            let points: Vec<Point> = segment.iter().map(|&p| self.shake(p)).collect();
+            d.push_str(&format!(
+                " C{},{} {},{} {},{}",
+                round(points[0].x),
+                round(points[0].y),
+                round(points[1].x),
+                round(points[1].y),
+                round(points[2].x),
+                round(points[2].y)
+            ));
+        }
+        d
+    }
+}
+
+/// Округление координат до сотых (компактный и стабильный SVG)
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let a = Sketch::seeded(&[10.0, 20.0, 100.0, 50.0]).rectangle(10.0, 20.0, 100.0, 50.0);
+        let b = Sketch::seeded(&[10.0, 20.0, 100.0, 50.0]).rectangle(10.0, 20.0, 100.0, 50.0);
+        let c = Sketch::seeded(&[11.0, 20.0, 100.0, 50.0]).rectangle(10.0, 20.0, 100.0, 50.0);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.starts_with('M') && a.ends_with('Z'));
+    }
+
+    #[test]
+    fn test_jitter_is_bounded() {
+        let mut sketch = Sketch::new(42);
+        for _ in 0..1000 {
+            let v = sketch.jitter(1.0);
+            assert!(v.abs() <= DEFAULT_ROUGHNESS);
+        }
+    }
+
+    #[test]
+    fn test_line_stays_near_endpoints() {
+        let d = Sketch::new(7).line(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        let numbers: Vec<f64> = d
+            .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .filter_map(|s| s.parse().ok())
+            .collect();
+        // M x,y Q cx,cy x,y
+        assert_eq!(numbers.len(), 6);
+        assert!(numbers[0].abs() <= DEFAULT_ROUGHNESS);
+        assert!((numbers[4] - 100.0).abs() <= DEFAULT_ROUGHNESS);
+    }
+
+    #[test]
+    fn test_ellipse_and_curve() {
+        let ellipse = Sketch::new(1).ellipse(50.0, 50.0, 20.0, 10.0);
+        assert_eq!(ellipse.matches('C').count(), 4);
+
+        let curve = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(20.0, 10.0),
+            Point::new(30.0, 10.0),
+        ];
+        assert_eq!(Sketch::new(1).curve(&curve).matches('C').count(), 1);
+        assert!(Sketch::new(1).polyline(&[]).is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! Спрайты в SVG
+//!
+//! Монохромный спрайт рисуется `<path>` на каждый уровень серого (прозрачность
+//! пропорциональна уровню), SVG спрайт — `<image>` с data URI. Подписи со
+//! ссылками `<$name>` раскладываются по строкам так же, как их измеряет
+//! layout (`plantuml_layout::sprites`).
+
+use plantuml_ast::sprite::{LabelPart, Sprite, SpriteData, SpriteRef, SpriteRegistry};
+use plantuml_layout::sprites::{label_lines, line_size, sprite_size};
+use plantuml_layout::{EstimatedMetrics, TextMetrics};
+use svg::node::element::{Group, Image, Path, Text};
+
+use plantuml_themes::Theme;
+
+/// Рисует спрайт с левым верхним углом в `(x, y)`
+pub(crate) fn sprite_node(
+    sprite: &Sprite,
+    reference: &SpriteRef,
+    x: f64,
+    y: f64,
+    color: &str,
+) -> Group {
+    let group = Group::new().set("class", format!("sprite sprite-{}", reference.name));
+    match &sprite.data {
+        SpriteData::Svg(source) => group.add(
+            Image::new()
+                .set("x", x)
+                .set("y", y)
+                .set("width", f64::from(sprite.width) * reference.scale)
+                .set("height", f64::from(sprite.height) * reference.scale)
+                .set(
+                    "href",
+                    format!(
+                        "data:image/svg+xml;base64,{}",
+                        base64(standalone_svg(source).as_bytes())
+                    ),
+                ),
+        ),
+        SpriteData::Gray { levels, .. } => {
+            let color = reference.color.as_deref().unwrap_or(color);
+            let mut group = group
+                .set(
+                    "transform",
+                    format!("translate({} {}) scale({})", x, y, reference.scale),
+                )
+                .set("shape-rendering", "crispEdges");
+            let max = levels.saturating_sub(1).max(1);
+            for level in 1..=max {
+                let d = level_path(sprite, level);
+                if d.is_empty() {
+                    continue;
+                }
+                let mut path = Path::new().set("d", d).set("fill", color);
+                if level < max {
+                    path = path.set("fill-opacity", f64::from(level) / f64::from(max));
+                }
+                group = group.add(path);
+            }
+            group
+        }
+    }
+}
+
+/// SVG спрайта как отдельный документ: без `xmlns` изображение не отображается
+pub(crate) fn standalone_svg(source: &str) -> String {
+    if source.contains("xmlns=") {
+        source.to_string()
+    } else {
+        source.replacen("<svg", "<svg xmlns=\"http://www.w3.org/2000/svg\"", 1)
+    }
+}
+
+/// Контур пикселей одного уровня: горизонтальные отрезки подряд идущих пикселей
+fn level_path(sprite: &Sprite, level: u8) -> String {
+    let mut d = String::new();
+    for y in 0..sprite.height {
+        let mut x = 0;
+        while x < sprite.width {
+            if sprite.pixel(x, y) != Some(level) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < sprite.width && sprite.pixel(x, y) == Some(level) {
+                x += 1;
+            }
+            d.push_str(&format!("M{} {}h{}v1h-{}z", start, y, x - start, x - start));
+        }
+    }
+    d
+}
+
+/// Высота подписи со спрайтами
+pub(crate) fn label_height(sprites: &SpriteRegistry, label: &str, font_size: f64) -> f64 {
+    plantuml_layout::sprites::label_size(label, sprites, font_size, &EstimatedMetrics::default())
+        .height
+}
+
+/// Рисует подпись со спрайтами: строки сверху вниз от `top`, каждая строка
+/// выравнивается относительно `x` по `anchor` (`start`, `middle`, `end`)
+pub(crate) fn label_node(
+    sprites: &SpriteRegistry,
+    label: &str,
+    x: f64,
+    top: f64,
+    anchor: &str,
+    font_size: f64,
+    theme: &Theme,
+) -> Group {
+    let metrics = EstimatedMetrics::default();
+    let color = theme.text_color.to_css();
+    let mut group = Group::new();
+    let mut line_top = top;
+
+    for line in label_lines(label) {
+        let size = line_size(&line, sprites, font_size, &metrics);
+        let mut cursor = match anchor {
+            "start" => x,
+            "end" => x - size.width,
+            _ => x - size.width / 2.0,
+        };
+        for part in LabelPart::split(&line) {
+            match part {
+                LabelPart::Text(text) => {
+                    if !text.trim().is_empty() {
+                        group = group.add(
+                            Text::new(text)
+                                .set("x", cursor)
+                                .set("y", line_top + size.height / 2.0)
+                                .set("dominant-baseline", "middle")
+                                .set("font-family", theme.font_family.as_str())
+                                .set("font-size", font_size)
+                                .set("fill", color.as_str()),
+                        );
+                    }
+                    cursor += metrics.text_width(text, font_size);
+                }
+                LabelPart::Sprite(reference) => {
+                    let (Some(sprite), Some(sprite_size)) = (
+                        sprites.get(&reference.name),
+                        sprite_size(sprites, &reference),
+                    ) else {
+                        continue;
+                    };
+                    let y = line_top + (size.height - sprite_size.height) / 2.0;
+                    group = group.add(sprite_node(sprite, &reference, cursor, y, &color));
+                    cursor += sprite_size.width;
+                }
+            }
+        }
+        line_top += size.height;
+    }
+    group
+}
+
+/// Base64 (RFC 4648) для data URI
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let value = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (u32::from(b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((value >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"<svg/>"), "PHN2Zy8+");
+    }
+
+    #[test]
+    fn test_gray_sprite_paths() {
+        // Два уровня из трёх: полупрозрачный и непрозрачный
+        let sprite = Sprite::gray(3, 2, 4, vec![3, 3, 0, 0, 1, 3]).unwrap();
+        let svg = sprite_node(&sprite, &SpriteRef::new("s"), 10.0, 20.0, "#000").to_string();
+        assert!(svg.contains("translate(10 20) scale(1)"));
+        assert!(svg.contains("M0 0h2v1h-2z"));
+        assert!(svg.contains("M2 1h1v1h-1z"));
+        assert!(svg.contains("fill-opacity=\"0.3333333333333333\""));
+        assert_eq!(svg.matches("<path").count(), 2);
+    }
+
+    #[test]
+    fn test_label_node() {
+        let mut sprites = SpriteRegistry::new();
+        sprites.insert("logo", Sprite::svg("<svg width=\"20\" height=\"10\"/>"));
+        let theme = Theme::default();
+        let svg =
+            label_node(&sprites, "<$logo>\\nWeb", 50.0, 0.0, "middle", 10.0, &theme).to_string();
+        assert!(svg.contains("<image"));
+        assert!(svg.contains("x=\"40\""));
+        let encoded =
+            base64(b"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"10\"/>");
+        assert!(svg.contains(&format!("data:image/svg+xml;base64,{}", encoded)));
+        assert!(svg.contains("<text") && svg.contains("Web"));
+        assert_eq!(label_height(&sprites, "<$logo>\\nWeb", 10.0), 10.0 + 12.0);
+    }
+}
@@ -1,30 +1,48 @@
 //! SVG рендерер
 
-use svg::node::element::{Definitions, Group, Marker, Path, Rectangle};
-use svg::Document;
+use svg::node::element::{
+    Anchor, Definitions, Filter, FilterEffectColorMatrix, FilterEffectGaussianBlur,
+    FilterEffectMerge, FilterEffectMergeNode, FilterEffectOffset, Group, Marker, Path, Rectangle,
+    Title,
+};
+use svg::{Document, Node};
+
+use crate::interactive::{element_class, has_links, ElementIds, XLINK_NAMESPACE};
+use crate::sketch::Sketch;
+use crate::sprites;
 
 use crate::{
-    ClassMember, ClassifierKind, EdgeType, ElementType, FragmentSection, LayoutElement, LayoutResult, 
+    ClassMember, ClassifierKind, EdgeLabelPositions, EdgeType, ElementType, FragmentSection, LayoutElement, LayoutResult, 
     MemberVisibility, Point, Rect, RenderOptions, Renderer, ZLayer,
 };
+use plantuml_ast::SpriteRegistry;
 use plantuml_themes::Theme;
 
 /// SVG рендерер
 pub struct SvgRenderer {
-    options: RenderOptions,
+    pub(crate) options: RenderOptions,
+    /// Спрайты для `<$name>` в подписях и стереотипов `<<$name>>`
+    sprites: SpriteRegistry,
 }
 
 impl SvgRenderer {
     /// Создаёт новый рендерер
     pub fn new() -> Self {
-        Self {
-            options: RenderOptions::default(),
-        }
+        Self::with_options(RenderOptions::default())
     }
 
     /// Создаёт рендерер с опциями
     pub fn with_options(options: RenderOptions) -> Self {
-        Self { options }
+        Self {
+            options,
+            sprites: SpriteRegistry::new(),
+        }
+    }
+
+    /// Задаёт спрайты диаграммы (`DiagramMetadata::sprites`)
+    pub fn with_sprites(mut self, sprites: SpriteRegistry) -> Self {
+        self.sprites = sprites;
+        self
     }
 
     /// Рендерит в строку
@@ -54,6 +72,9 @@ impl SvgRenderer {
                 ),
             )
             .set("xmlns", "http://www.w3.org/2000/svg");
+        if has_links(&layout.elements) {
+            doc = doc.set("xmlns:xlink", XLINK_NAMESPACE);
+        }
 
         // PlantUML по умолчанию НЕ добавляет фон и рамку вокруг диаграммы
         // Фон добавляется только если явно указан через skinparam backgroundColor
@@ -80,6 +101,21 @@ impl SvgRenderer {
     fn create_definitions(&self, theme: &Theme) -> Definitions {
         let arrow_color = theme.arrow_color.to_css();
 
+        // В рукописном режиме наконечники тоже рисуются от руки
+        let marker_path = |plain: &str, points: &[(f64, f64)], closed: bool| {
+            if !theme.handwritten {
+                return plain.to_string();
+            }
+            let points: Vec<Point> = points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+            let seed: Vec<f64> = points.iter().flat_map(|p| [p.x, p.y]).collect();
+            let mut sketch = Sketch::seeded(&seed).with_roughness(0.6);
+            if closed {
+                sketch.polygon(&points)
+            } else {
+                sketch.polyline(&points)
+            }
+        };
+
         // Маркер стрелки в стиле PlantUML (ромб с вырезом) - для ассоциаций и сообщений
         let arrow_marker = Marker::new()
             .set("id", "arrow")
@@ -92,7 +128,14 @@ impl SvgRenderer {
             .add(
                 Path::new()
                     // PlantUML style: ромб с вырезом
-                    .set("d", "M0,0 L10,4 L0,8 L4,4 Z")
+                    .set(
+                        "d",
+                        marker_path(
+                            "M0,0 L10,4 L0,8 L4,4 Z",
+                            &[(0.0, 0.0), (10.0, 4.0), (0.0, 8.0), (4.0, 4.0)],
+                            true,
+                        ),
+                    )
                     .set("fill", arrow_color.as_str()),
             );
 
@@ -107,7 +150,14 @@ impl SvgRenderer {
             .set("markerUnits", "userSpaceOnUse")
             .add(
                 Path::new()
-                    .set("d", "M0,0 L10,4 L0,8")
+                    .set(
+                        "d",
+                        marker_path(
+                            "M0,0 L10,4 L0,8",
+                            &[(0.0, 0.0), (10.0, 4.0), (0.0, 8.0)],
+                            false,
+                        ),
+                    )
                     .set("fill", "none")
                     .set("stroke", arrow_color.as_str())
                     .set("stroke-width", 1),
@@ -126,7 +176,14 @@ impl SvgRenderer {
             .add(
                 Path::new()
                     // Треугольник: верх, кончик, низ
-                    .set("d", "M0,0 L20,10 L0,20 Z")
+                    .set(
+                        "d",
+                        marker_path(
+                            "M0,0 L20,10 L0,20 Z",
+                            &[(0.0, 0.0), (20.0, 10.0), (0.0, 20.0)],
+                            true,
+                        ),
+                    )
                     .set("fill", theme.background_color.to_css()) // белый внутри
                     .set("stroke", arrow_color.as_str())
                     .set("stroke-width", 1),
@@ -144,7 +201,14 @@ impl SvgRenderer {
             .add(
                 Path::new()
                     // Ромб: лево, верх, право, низ
-                    .set("d", "M0,6 L6,0 L12,6 L6,12 Z")
+                    .set(
+                        "d",
+                        marker_path(
+                            "M0,6 L6,0 L12,6 L6,12 Z",
+                            &[(0.0, 6.0), (6.0, 0.0), (12.0, 6.0), (6.0, 12.0)],
+                            true,
+                        ),
+                    )
                     .set("fill", arrow_color.as_str()),
             );
 
@@ -159,23 +223,60 @@ impl SvgRenderer {
             .set("markerUnits", "userSpaceOnUse")
             .add(
                 Path::new()
-                    .set("d", "M0,6 L6,0 L12,6 L6,12 Z")
+                    .set(
+                        "d",
+                        marker_path(
+                            "M0,6 L6,0 L12,6 L6,12 Z",
+                            &[(0.0, 6.0), (6.0, 0.0), (12.0, 6.0), (6.0, 12.0)],
+                            true,
+                        ),
+                    )
                     .set("fill", theme.background_color.to_css()) // белый внутри
                     .set("stroke", arrow_color.as_str())
                     .set("stroke-width", 1),
             );
 
-        Definitions::new()
+        let mut defs = Definitions::new()
             .add(arrow_marker)
             .add(open_arrow_marker)
             .add(inheritance_marker)
             .add(composition_marker)
-            .add(aggregation_marker)
+            .add(aggregation_marker);
+
+        // Тень узлов (skinparam shadowing): размытая полупрозрачная копия силуэта со сдвигом
+        if theme.shadow {
+            let shadow = Filter::new()
+                .set("id", "shadow")
+                .set("x", "-10%")
+                .set("y", "-10%")
+                .set("width", "130%")
+                .set("height", "130%")
+                .add(
+                    FilterEffectGaussianBlur::new()
+                        .set("in", "SourceAlpha")
+                        .set("stdDeviation", 2),
+                )
+                .add(FilterEffectOffset::new().set("dx", 3).set("dy", 3))
+                .add(
+                    FilterEffectColorMatrix::new()
+                        .set("type", "matrix")
+                        .set("values", "0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0.35 0")
+                        .set("result", "shadow"),
+                )
+                .add(
+                    FilterEffectMerge::new()
+                        .add(FilterEffectMergeNode::new().set("in", "shadow"))
+                        .add(FilterEffectMergeNode::new().set("in", "SourceGraphic")),
+                );
+            defs = defs.add(shadow);
+        }
+
+        defs
     }
 
-    /// Рендерит элемент
-    fn render_element(&self, element: &LayoutElement, theme: &Theme) -> Group {
-        let mut group = Group::new().set("id", element.id.as_str());
+    /// Рендерит элемент: группа с `id`, `class` и `data-line`, ссылка — `<a>`
+    fn render_element(&self, element: &LayoutElement, theme: &Theme, ids: &mut ElementIds) -> Group {
+        let mut group = Group::new();
 
         match &element.element_type {
             ElementType::Rectangle {
@@ -214,10 +315,13 @@ impl SvgRenderer {
                 edge_type,
                 from_cardinality,
                 to_cardinality,
+                curve,
+                label_positions,
             } => {
                 let autonumber = element.properties.get("autonumber").map(|s| s.as_str());
                 group = self.render_edge(
                     points,
+                    curve,
                     label.as_deref(),
                     autonumber,
                     *arrow_start,
@@ -226,6 +330,7 @@ impl SvgRenderer {
                     *edge_type,
                     from_cardinality.as_deref(),
                     to_cardinality.as_deref(),
+                    label_positions,
                     theme,
                     group,
                 );
@@ -234,15 +339,27 @@ impl SvgRenderer {
                 group = self.render_text(&element.bounds, text, *font_size, theme, group);
             }
             ElementType::Group { label, children } => {
-                group =
-                    self.render_group(&element.bounds, label.as_deref(), children, theme, group);
+                group = self.render_group(
+                    &element.bounds,
+                    label.as_deref(),
+                    children,
+                    theme,
+                    ids,
+                    group,
+                );
             }
             ElementType::Fragment {
                 fragment_type,
                 sections,
             } => {
-                group =
-                    self.render_fragment(&element.bounds, fragment_type, sections, theme, group);
+                group = self.render_fragment(
+                    &element.bounds,
+                    fragment_type,
+                    sections,
+                    theme,
+                    ids,
+                    group,
+                );
             }
             ElementType::Activation => {
                 group = self.render_activation(&element.bounds, theme, group);
@@ -289,7 +406,43 @@ impl SvgRenderer {
             }
         }
 
-        group
+        // Спрайт стереотипа <<$name>> — иконка в правом верхнем углу
+        if let Some((name, sprite)) = element
+            .properties
+            .get("sprite")
+            .and_then(|name| Some((name, self.sprites.get(name)?)))
+        {
+            let x = element.bounds.x + element.bounds.width - f64::from(sprite.width) - 4.0;
+            let y = element.bounds.y + 4.0;
+            let reference = plantuml_ast::sprite::SpriteRef::new(name.as_str());
+            group = group.add(sprites::sprite_node(sprite, &reference, x, y, &theme.text_color.to_css()));
+        }
+
+        let id = ids.unique(&element.id);
+        let class = element_class(element);
+        let Some(url) = element.properties.get("link") else {
+            return Self::with_source_attributes(group, &id, &class, element);
+        };
+        // Подсказка — текст из [[url подсказка]], иначе сам адрес
+        let tooltip = element.properties.get("tooltip").unwrap_or(url);
+        let anchor = Anchor::new()
+            .set("href", url.as_str())
+            .set("xlink:href", url.as_str())
+            .set("xlink:title", tooltip.as_str())
+            .set("target", "_top")
+            .add(group);
+        Self::with_source_attributes(Group::new(), &id, &class, element)
+            .add(Title::new(tooltip.as_str()))
+            .add(anchor)
+    }
+
+    /// Атрибуты группы элемента: `id`, `class` и `data-line`
+    fn with_source_attributes(group: Group, id: &str, class: &str, element: &LayoutElement) -> Group {
+        let group = group.set("id", id).set("class", class);
+        match element.properties.get("line") {
+            Some(line) => group.set("data-line", line.as_str()),
+            None => group,
+        }
     }
 
     /// Рендерит прямоугольник
@@ -302,19 +455,19 @@ impl SvgRenderer {
         mut group: Group,
     ) -> Group {
         // PlantUML использует stroke-width: 0.5 для участников
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("rx", corner_radius)
-            .set("ry", corner_radius)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
+        let rect = self.rect_shape(
+            bounds,
+            Some(corner_radius),
+            &ShapeStyle::node(theme, 0.5),
+            theme,
+        );
 
         group = group.add(rect);
 
+        if self.sprites.is_used_in(label) {
+            return group.add(self.sprite_label(label, bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0, theme));
+        }
+
         // Текст по центру
         let text = svg::node::element::Text::new(label)
             .set("x", bounds.x + bounds.width / 2.0)
@@ -341,17 +494,14 @@ impl SvgRenderer {
         let rx = bounds.width / 2.0;
         let ry = bounds.height / 2.0;
 
-        let ellipse = svg::node::element::Ellipse::new()
-            .set("cx", cx)
-            .set("cy", cy)
-            .set("rx", rx)
-            .set("ry", ry)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        let ellipse = self.ellipse_shape(cx, cy, rx, ry, &ShapeStyle::node(theme, 1.0), theme);
 
         group = group.add(ellipse);
 
+        if let Some(label) = label.filter(|label| self.sprites.is_used_in(label)) {
+            return group.add(self.sprite_label(label, cx, cy, theme));
+        }
+
         if let Some(label) = label {
             let text = svg::node::element::Text::new(label)
                 .set("x", cx)
@@ -380,13 +530,14 @@ impl SvgRenderer {
         let r = bounds.width.min(bounds.height) / 2.0;
 
         // Заполненный чёрный круг (UML standard)
-        let circle = svg::node::element::Ellipse::new()
-            .set("cx", cx)
-            .set("cy", cy)
-            .set("rx", r)
-            .set("ry", r)
-            .set("fill", theme.node_border.to_css()) // чёрная заливка
-            .set("stroke", "none");
+        let circle = self.ellipse_shape(
+            cx,
+            cy,
+            r,
+            r,
+            &ShapeStyle::filled(theme.node_border.to_css()), // чёрная заливка
+            theme,
+        );
 
         group.add(circle)
     }
@@ -404,25 +555,30 @@ impl SvgRenderer {
         let inner_r = outer_r * 0.6; // внутренний круг 60% от внешнего
 
         // Внешний круг (пустой, с обводкой)
-        let outer_circle = svg::node::element::Ellipse::new()
-            .set("cx", cx)
-            .set("cy", cy)
-            .set("rx", outer_r)
-            .set("ry", outer_r)
-            .set("fill", theme.background_color.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let outer_circle = self.ellipse_shape(
+            cx,
+            cy,
+            outer_r,
+            outer_r,
+            &ShapeStyle::new(
+                theme.background_color.to_css(),
+                theme.node_border.to_css(),
+                1.5,
+            ),
+            theme,
+        );
 
         group = group.add(outer_circle);
 
         // Внутренний круг (заполненный чёрный)
-        let inner_circle = svg::node::element::Ellipse::new()
-            .set("cx", cx)
-            .set("cy", cy)
-            .set("rx", inner_r)
-            .set("ry", inner_r)
-            .set("fill", theme.node_border.to_css()) // чёрная заливка
-            .set("stroke", "none");
+        let inner_circle = self.ellipse_shape(
+            cx,
+            cy,
+            inner_r,
+            inner_r,
+            &ShapeStyle::filled(theme.node_border.to_css()), // чёрная заливка
+            theme,
+        );
 
         group.add(inner_circle)
     }
@@ -440,16 +596,12 @@ impl SvgRenderer {
         let header_height = 25.0;
         
         // 1. Основной прямоугольник со скруглёнными углами
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("rx", corner_radius)
-            .set("ry", corner_radius)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        let rect = self.rect_shape(
+            bounds,
+            Some(corner_radius),
+            &ShapeStyle::node(theme, 1.0),
+            theme,
+        );
 
         group = group.add(rect);
 
@@ -469,13 +621,12 @@ impl SvgRenderer {
 
         // 3. Горизонтальный разделитель (UML style)
         let separator_y = bounds.y + header_height;
-        let separator = svg::node::element::Line::new()
-            .set("x1", bounds.x)
-            .set("y1", separator_y)
-            .set("x2", bounds.x + bounds.width)
-            .set("y2", separator_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
+        let separator = self.line_shape(
+            Point::new(bounds.x, separator_y),
+            Point::new(bounds.x + bounds.width, separator_y),
+            0.5,
+            theme,
+        );
 
         group = group.add(separator);
 
@@ -507,16 +658,12 @@ impl SvgRenderer {
         let corner_radius = 10.0;
         
         // 1. Основной прямоугольник контейнера со скруглёнными углами
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("rx", corner_radius)
-            .set("ry", corner_radius)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let rect = self.rect_shape(
+            bounds,
+            Some(corner_radius),
+            &ShapeStyle::node(theme, 1.5),
+            theme,
+        );
 
         group = group.add(rect);
 
@@ -536,13 +683,12 @@ impl SvgRenderer {
 
         // 3. Горизонтальный разделитель под заголовком
         let separator_y = bounds.y + header_height;
-        let separator = svg::node::element::Line::new()
-            .set("x1", bounds.x)
-            .set("y1", separator_y)
-            .set("x2", bounds.x + bounds.width)
-            .set("y2", separator_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        let separator = self.line_shape(
+            Point::new(bounds.x, separator_y),
+            Point::new(bounds.x + bounds.width, separator_y),
+            1.0,
+            theme,
+        );
 
         group = group.add(separator);
 
@@ -575,54 +721,45 @@ impl SvgRenderer {
         let feet_y = waist_y + leg_length;
         
         // 1. Голова (круг)
-        let head = svg::node::element::Ellipse::new()
-            .set("cx", cx)
-            .set("cy", head_cy)
-            .set("rx", head_radius)
-            .set("ry", head_radius)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let head = self.ellipse_shape(
+            cx,
+            head_cy,
+            head_radius,
+            head_radius,
+            &ShapeStyle::new(theme.node_background.to_css(), theme.node_border.to_css(), 1.5),
+            theme,
+        );
         group = group.add(head);
         
         // 2. Тело (вертикальная линия)
-        let body = svg::node::element::Line::new()
-            .set("x1", cx)
-            .set("y1", neck_y)
-            .set("x2", cx)
-            .set("y2", waist_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let body = self.line_shape(Point::new(cx, neck_y), Point::new(cx, waist_y), 1.5, theme);
         group = group.add(body);
         
         // 3. Руки (горизонтальная линия)
-        let arms = svg::node::element::Line::new()
-            .set("x1", cx - arm_width / 2.0)
-            .set("y1", arms_y)
-            .set("x2", cx + arm_width / 2.0)
-            .set("y2", arms_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let arms = self.line_shape(
+            Point::new(cx - arm_width / 2.0, arms_y),
+            Point::new(cx + arm_width / 2.0, arms_y),
+            1.5,
+            theme,
+        );
         group = group.add(arms);
         
         // 4. Левая нога
-        let left_leg = svg::node::element::Line::new()
-            .set("x1", cx)
-            .set("y1", waist_y)
-            .set("x2", cx - leg_spread)
-            .set("y2", feet_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let left_leg = self.line_shape(
+            Point::new(cx, waist_y),
+            Point::new(cx - leg_spread, feet_y),
+            1.5,
+            theme,
+        );
         group = group.add(left_leg);
         
         // 5. Правая нога
-        let right_leg = svg::node::element::Line::new()
-            .set("x1", cx)
-            .set("y1", waist_y)
-            .set("x2", cx + leg_spread)
-            .set("y2", feet_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let right_leg = self.line_shape(
+            Point::new(cx, waist_y),
+            Point::new(cx + leg_spread, feet_y),
+            1.5,
+            theme,
+        );
         group = group.add(right_leg);
         
         // 6. Текст имени под человечком
@@ -649,15 +786,13 @@ impl SvgRenderer {
     ) -> Group {
         let header_height = 25.0;
         
-        // 1. Основной прямоугольник системы
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        // 1. Основной прямоугольник системы (контейнер — без тени)
+        let rect = self.rect_shape(
+            bounds,
+            None,
+            &ShapeStyle::new(theme.node_background.to_css(), theme.node_border.to_css(), 1.0),
+            theme,
+        );
         group = group.add(rect);
         
         // 2. Заголовок сверху по центру
@@ -679,6 +814,7 @@ impl SvgRenderer {
     fn render_edge(
         &self,
         points: &[Point],
+        curve: &[Point],
         label: Option<&str>,
         autonumber: Option<&str>,
         arrow_start: bool,
@@ -687,6 +823,7 @@ impl SvgRenderer {
         edge_type: EdgeType,
         from_cardinality: Option<&str>,
         to_cardinality: Option<&str>,
+        label_positions: &EdgeLabelPositions,
         theme: &Theme,
         mut group: Group,
     ) -> Group {
@@ -700,8 +837,28 @@ impl SvgRenderer {
             && (points[0].x - points[3].x).abs() < 1.0
             && (points[0].y - points[3].y).abs() > 1.0;
 
+        // Кривые Безье от роутера: p0, затем тройки (c1, c2, p1)
+        let is_curve = curve.len() >= 4 && (curve.len() - 1) % 3 == 0;
+
         // Строим путь
-        let d = if is_self_message {
+        let d = if theme.handwritten {
+            let seed: Vec<f64> = points.iter().flat_map(|p| [p.x, p.y]).collect();
+            let mut sketch = Sketch::seeded(&seed);
+            if is_curve {
+                sketch.curve(curve)
+            } else {
+                sketch.polyline(points)
+            }
+        } else if is_curve {
+            let mut d = format!("M{},{}", curve[0].x, curve[0].y);
+            for segment in curve[1..].chunks(3) {
+                d.push_str(&format!(
+                    " C{},{} {},{} {},{}",
+                    segment[0].x, segment[0].y, segment[1].x, segment[1].y, segment[2].x, segment[2].y
+                ));
+            }
+            d
+        } else if is_self_message {
             // PlantUML style self-message: прямые углы (3 линии)
             // points[0] = start (lifeline, top)
             // points[1] = right top
@@ -782,75 +939,7 @@ impl SvgRenderer {
         // По умолчанию в PlantUML: skinparam sequenceMessageAlign left
         // Если есть autonumber — рендерим его отдельно слева, текст справа от него
         if label.is_some() || autonumber.is_some() {
-            // Определяем тип линии
-            let dx = points[points.len() - 1].x - points[0].x;
-            let dy = points[points.len() - 1].y - points[0].y;
-            
-            let is_vertical = points.len() == 2 && dy.abs() > dx.abs() * 3.0;
-            let is_horizontal = points.len() == 2 && dx.abs() > dy.abs() * 3.0;
-            let is_diagonal = points.len() == 2 && !is_vertical && !is_horizontal;
-            
-            // Позиция текста зависит от типа линии
-            let (base_x, text_y, anchor) = if is_self_message {
-                // PlantUML: для self-message текст НАД верхней линией петли
-                let text_start = points[0].x + 5.0;
-                let top_y = points[0].y - 5.0;
-                (text_start, top_y, "start")
-            } else if points.len() == 4 {
-                // Ортогональный путь (4 точки): это обратный переход
-                // Структура: start_h -> corner1 -> corner2 -> end_h
-                // points[0]: начало горизонтального сегмента
-                // points[1]: угол (конец первого горизонтального сегмента)
-                // points[2]: угол (начало последнего горизонтального сегмента)  
-                // points[3]: конец горизонтального сегмента
-                
-                // Метка располагается на ПЕРВОМ горизонтальном сегменте (исходящем),
-                // справа от точки выхода, на уровне Y этого сегмента
-                let text_x = points[0].x + 5.0; // справа от точки выхода
-                let text_y = points[0].y - 5.0; // чуть выше линии
-                (text_x, text_y, "start")
-            } else if is_diagonal {
-                // Диагональная линия (state diagrams): текст РЯДОМ с линией
-                // PlantUML style: текст размещается вдоль стрелки, с внешней стороны
-                
-                // Позиция вдоль линии (40% от начала для лучшего разделения расходящихся стрелок)
-                let t = 0.40;
-                let text_x = points[0].x + dx * t;
-                let text_y = points[0].y + dy * t;
-                
-                // Смещаем текст в сторону от линии (перпендикулярно, на ВНЕШНЮЮ сторону)
-                // Для расходящихся из одной точки стрелок:
-                // - Линия влево-вниз — текст СЛЕВА от линии
-                // - Линия вправо-вниз — текст СПРАВА от линии
-                let offset = 8.0; // отступ от линии
-                if dx > 0.0 {
-                    // Линия идёт вправо-вниз — текст СПРАВА от линии (внешняя сторона)
-                    (text_x + offset, text_y, "start")
-                } else {
-                    // Линия идёт влево-вниз — текст СЛЕВА от линии (внешняя сторона)
-                    (text_x - offset, text_y, "end")
-                }
-            } else if is_vertical {
-                // Вертикальная линия: метка СПРАВА, ПОСЕРЕДИНЕ по высоте
-                let mid_y = (points[0].y + points[1].y) / 2.0;
-                let text_x = points[0].x + 5.0;
-                (text_x, mid_y, "start")
-            } else if is_horizontal {
-                // Горизонтальная стрелка (sequence diagrams)
-                let is_left_to_right = dx > 0.0;
-                let left_x = if is_left_to_right {
-                    points[0].x + 5.0
-                } else {
-                    points[1].x + 5.0
-                };
-                let top_y = points[0].y - 5.0;
-                (left_x, top_y, "start")
-            } else {
-                // Fallback: середина первого сегмента
-                let mid_x = (points[0].x + points[1].x) / 2.0;
-                let mid_y = (points[0].y + points[1].y) / 2.0;
-                (mid_x, mid_y - 5.0, "middle")
-            };
+            let (base_x, text_y, anchor) = edge_label_anchor(points, label, label_positions);
 
             // PlantUML не использует белый фон для текста — текст просто над стрелкой
             // PlantUML использует font-size 13 для сообщений
@@ -894,26 +983,15 @@ impl SvgRenderer {
         // близко к точкам соединения с классами
         if points.len() >= 2 {
             let font_size = 13.0; // PlantUML использует 13px
-            let horizontal_offset = 10.0; // отступ слева от линии
-            let vertical_offset = 12.0; // отступ от точки соединения вниз/вверх
-            
-            // Определяем, это вертикальная линия
-            let is_vertical = (points[1].y - points[0].y).abs() > (points[1].x - points[0].x).abs();
-            
+
             // Кардинальность у начальной точки (from)
             if let Some(card) = from_cardinality {
-                let p = &points[0];
-                let (text_x, text_y) = if is_vertical {
-                    // Вертикальная линия: текст СЛЕВА, чуть НИЖЕ точки соединения
-                    (p.x - horizontal_offset, p.y + vertical_offset)
-                } else {
-                    // Горизонтальная линия: текст сверху
-                    (p.x + vertical_offset, p.y - horizontal_offset / 2.0)
-                };
+                let (text_x, text_y, anchor) =
+                    cardinality_anchor(points, true, label_positions.from_cardinality);
                 let text_elem = svg::node::element::Text::new(card)
                     .set("x", text_x)
                     .set("y", text_y)
-                    .set("text-anchor", "end") // выравнивание по правому краю (к линии)
+                    .set("text-anchor", anchor) // по умолчанию — к линии
                     .set("dominant-baseline", "middle")
                     .set("font-family", theme.font_family.as_str())
                     .set("font-size", font_size)
@@ -923,18 +1001,12 @@ impl SvgRenderer {
             
             // Кардинальность у конечной точки (to)
             if let Some(card) = to_cardinality {
-                let p = &points[points.len() - 1];
-                let (text_x, text_y) = if is_vertical {
-                    // Вертикальная линия: текст СЛЕВА, чуть ВЫШЕ точки соединения
-                    (p.x - horizontal_offset, p.y - vertical_offset)
-                } else {
-                    // Горизонтальная линия: текст сверху
-                    (p.x - vertical_offset, p.y - horizontal_offset / 2.0)
-                };
+                let (text_x, text_y, anchor) =
+                    cardinality_anchor(points, false, label_positions.to_cardinality);
                 let text_elem = svg::node::element::Text::new(card)
                     .set("x", text_x)
                     .set("y", text_y)
-                    .set("text-anchor", "end") // выравнивание по правому краю (к линии)
+                    .set("text-anchor", anchor) // по умолчанию — к линии
                     .set("dominant-baseline", "middle")
                     .set("font-family", theme.font_family.as_str())
                     .set("font-size", font_size)
@@ -978,6 +1050,12 @@ impl SvgRenderer {
         theme: &Theme,
         mut group: Group,
     ) -> Group {
+        if self.sprites.is_used_in(label) {
+            // Нижний край подписи на уровне базовой линии последней строки
+            let top = y + font_size * 0.3 - sprites::label_height(&self.sprites, label, font_size);
+            return group.add(sprites::label_node(&self.sprites, label, x, top, anchor, font_size, theme));
+        }
+
         // Конвертируем escape-последовательность \n в реальные переносы строк
         let processed_label = label.replace("\\n", "\n");
         let lines: Vec<&str> = processed_label.split('\n').collect();
@@ -1023,6 +1101,12 @@ impl SvgRenderer {
         group
     }
 
+    /// Подпись со спрайтами, центрированная в `(cx, cy)`
+    fn sprite_label(&self, label: &str, cx: f64, cy: f64, theme: &Theme) -> Group {
+        let height = sprites::label_height(&self.sprites, label, theme.font_size);
+        sprites::label_node(&self.sprites, label, cx, cy - height / 2.0, "middle", theme.font_size, theme)
+    }
+
     /// Рендерит группу (устаревший, для совместимости)
     fn render_group(
         &self,
@@ -1030,17 +1114,16 @@ impl SvgRenderer {
         label: Option<&str>,
         children: &[LayoutElement],
         theme: &Theme,
+        ids: &mut ElementIds,
         mut group: Group,
     ) -> Group {
         // Рамка группы - СПЛОШНАЯ (как в PlantUML)
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("fill", "none")
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        let rect = self.rect_shape(
+            bounds,
+            None,
+            &ShapeStyle::new("none", theme.node_border.to_css(), 1.0),
+            theme,
+        );
 
         group = group.add(rect);
 
@@ -1066,7 +1149,7 @@ impl SvgRenderer {
 
         // Дочерние элементы
         for child in children {
-            group = group.add(self.render_element(child, theme));
+            group = group.add(self.render_element(child, theme, ids));
         }
 
         group
@@ -1079,18 +1162,17 @@ impl SvgRenderer {
         fragment_type: &str,
         sections: &[FragmentSection],
         theme: &Theme,
+        ids: &mut ElementIds,
         mut group: Group,
     ) -> Group {
         // 1. СПЛОШНАЯ рамка фрагмента (как в PlantUML)
         // PlantUML использует более толстую рамку для фрагментов (1.5px)
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("fill", "none")
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1.5);
+        let rect = self.rect_shape(
+            bounds,
+            None,
+            &ShapeStyle::new("none", theme.node_border.to_css(), 1.5),
+            theme,
+        );
 
         group = group.add(rect);
 
@@ -1102,7 +1184,16 @@ impl SvgRenderer {
         let notch_size = 8.0; // размер "зазубрины" пятиугольника
 
         // Пятиугольник: верхний левый угол рамки -> вправо -> вниз с зазубриной -> влево -> вверх
-        let pentagon_path = format!(
+        let pentagon_path = if theme.handwritten {
+            Sketch::seeded(&[bounds.x, bounds.y, label_width]).polygon(&[
+                Point::new(bounds.x, bounds.y),
+                Point::new(bounds.x + label_width, bounds.y),
+                Point::new(bounds.x + label_width, bounds.y + label_height - notch_size),
+                Point::new(bounds.x + label_width - notch_size, bounds.y + label_height),
+                Point::new(bounds.x, bounds.y + label_height),
+            ])
+        } else {
+            format!(
             "M{},{} L{},{} L{},{} L{},{} L{},{} Z",
             bounds.x,
             bounds.y, // верхний левый
@@ -1114,7 +1205,8 @@ impl SvgRenderer {
             bounds.y + label_height, // зазубрина
             bounds.x,
             bounds.y + label_height, // нижний левый
-        );
+            )
+        };
 
         let pentagon = Path::new()
             .set("d", pentagon_path)
@@ -1162,17 +1254,17 @@ impl SvgRenderer {
                 let separator_y = section.start_y - 28.0;
 
                 // Пунктирная линия
+                let (from, to) = (
+                    Point::new(bounds.x, separator_y),
+                    Point::new(bounds.x + bounds.width, separator_y),
+                );
+                let separator_d = if theme.handwritten {
+                    Sketch::seeded(&[from.x, from.y, to.x]).line(from, to)
+                } else {
+                    format!("M{},{} L{},{}", from.x, from.y, to.x, to.y)
+                };
                 let separator_line = Path::new()
-                    .set(
-                        "d",
-                        format!(
-                            "M{},{} L{},{}",
-                            bounds.x,
-                            separator_y,
-                            bounds.x + bounds.width,
-                            separator_y
-                        ),
-                    )
+                    .set("d", separator_d)
                     .set("fill", "none")
                     .set("stroke", theme.node_border.to_css())
                     .set("stroke-width", 1)
@@ -1199,7 +1291,7 @@ impl SvgRenderer {
 
             // Дочерние элементы секции
             for child in &section.children {
-                group = group.add(self.render_element(child, theme));
+                group = group.add(self.render_element(child, theme, ids));
             }
         }
 
@@ -1219,14 +1311,12 @@ impl SvgRenderer {
         let fill_color = color.unwrap_or("#EEEEEE");
         
         // Основной прямоугольник
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("fill", fill_color)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        let rect = self.rect_shape(
+            bounds,
+            None,
+            &ShapeStyle::new(fill_color, theme.node_border.to_css(), 1.0),
+            theme,
+        );
         group = group.add(rect);
 
         // Заголовок по центру сверху
@@ -1249,14 +1339,12 @@ impl SvgRenderer {
     /// Рендерит Activation box (белый фон, чёрная рамка)
     fn render_activation(&self, bounds: &Rect, theme: &Theme, group: Group) -> Group {
         // Activation box: белый фон (как в PlantUML)
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("fill", theme.background_color.to_css()) // белый фон
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
+        let rect = self.rect_shape(
+            bounds,
+            None,
+            &ShapeStyle::new(theme.background_color.to_css(), theme.node_border.to_css(), 1.0),
+            theme,
+        );
 
         group.add(rect)
     }
@@ -1279,16 +1367,7 @@ impl SvgRenderer {
         let icon_size = 11.0; // радиус иконки класса
         
         // 1. Рамка класса
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("rx", 2.5)
-            .set("ry", 2.5)
-            .set("fill", theme.node_background.to_css())
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
+        let rect = self.rect_shape(bounds, Some(2.5), &ShapeStyle::node(theme, 0.5), theme);
         group = group.add(rect);
 
         let mut current_y = bounds.y + padding;
@@ -1352,13 +1431,12 @@ impl SvgRenderer {
         current_y += line_height + padding;
 
         // 5. Разделитель после имени
-        let separator1 = svg::node::element::Line::new()
-            .set("x1", bounds.x + 1.0)
-            .set("y1", current_y)
-            .set("x2", bounds.x + bounds.width - 1.0)
-            .set("y2", current_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
+        let separator1 = self.line_shape(
+            Point::new(bounds.x + 1.0, current_y),
+            Point::new(bounds.x + bounds.width - 1.0, current_y),
+            0.5,
+            theme,
+        );
         group = group.add(separator1);
         current_y += padding;
 
@@ -1376,13 +1454,12 @@ impl SvgRenderer {
         }
 
         // 7. Разделитель между полями и методами
-        let separator2 = svg::node::element::Line::new()
-            .set("x1", bounds.x + 1.0)
-            .set("y1", current_y)
-            .set("x2", bounds.x + bounds.width - 1.0)
-            .set("y2", current_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
+        let separator2 = self.line_shape(
+            Point::new(bounds.x + 1.0, current_y),
+            Point::new(bounds.x + bounds.width - 1.0, current_y),
+            0.5,
+            theme,
+        );
         group = group.add(separator2);
         current_y += padding;
 
@@ -1455,6 +1532,232 @@ impl SvgRenderer {
 
         group.add(text)
     }
+
+    /// Прямоугольник: `<rect>`, в рукописном режиме — дрожащий контур
+    fn rect_shape(
+        &self,
+        bounds: &Rect,
+        corner_radius: Option<f64>,
+        style: &ShapeStyle,
+        theme: &Theme,
+    ) -> Box<dyn Node> {
+        if theme.handwritten {
+            let d = Sketch::seeded(&[bounds.x, bounds.y, bounds.width, bounds.height]).rectangle(
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height,
+            );
+            return Box::new(style.apply(Path::new().set("d", d)));
+        }
+
+        let mut rect = Rectangle::new()
+            .set("x", bounds.x)
+            .set("y", bounds.y)
+            .set("width", bounds.width)
+            .set("height", bounds.height);
+        if let Some(radius) = corner_radius {
+            rect = rect.set("rx", radius).set("ry", radius);
+        }
+        Box::new(style.apply(rect))
+    }
+
+    /// Эллипс: `<ellipse>`, в рукописном режиме — дрожащий контур
+    fn ellipse_shape(
+        &self,
+        cx: f64,
+        cy: f64,
+        rx: f64,
+        ry: f64,
+        style: &ShapeStyle,
+        theme: &Theme,
+    ) -> Box<dyn Node> {
+        if theme.handwritten {
+            let d = Sketch::seeded(&[cx, cy, rx, ry]).ellipse(cx, cy, rx, ry);
+            return Box::new(style.apply(Path::new().set("d", d)));
+        }
+
+        let ellipse = svg::node::element::Ellipse::new()
+            .set("cx", cx)
+            .set("cy", cy)
+            .set("rx", rx)
+            .set("ry", ry);
+        Box::new(style.apply(ellipse))
+    }
+
+    /// Отрезок цветом границ узлов: `<line>`, в рукописном режиме — дрожащий путь
+    fn line_shape(&self, from: Point, to: Point, stroke_width: f64, theme: &Theme) -> Box<dyn Node> {
+        let stroke = theme.node_border.to_css();
+        if theme.handwritten {
+            let d = Sketch::seeded(&[from.x, from.y, to.x, to.y]).line(from, to);
+            return Box::new(
+                Path::new()
+                    .set("d", d)
+                    .set("fill", "none")
+                    .set("stroke", stroke)
+                    .set("stroke-width", stroke_width),
+            );
+        }
+
+        Box::new(
+            svg::node::element::Line::new()
+                .set("x1", from.x)
+                .set("y1", from.y)
+                .set("x2", to.x)
+                .set("y2", to.y)
+                .set("stroke", stroke)
+                .set("stroke-width", stroke_width),
+        )
+    }
+}
+
+/// Позиция подписи линии: `(x, y, text-anchor)`
+///
+/// Общая для SVG и display list; `points` содержит не меньше двух точек.
+pub(crate) fn edge_label_anchor(
+    points: &[Point],
+    label: Option<&str>,
+    label_positions: &EdgeLabelPositions,
+) -> (f64, f64, &'static str) {
+    // Self-message: петля из 4 точек, начало и конец на одной вертикали
+    let is_self_message = points.len() == 4
+        && (points[0].x - points[3].x).abs() < 1.0
+        && (points[0].y - points[3].y).abs() > 1.0;
+
+    // Определяем тип линии
+    let dx = points[points.len() - 1].x - points[0].x;
+    let dy = points[points.len() - 1].y - points[0].y;
+
+    let is_vertical = points.len() == 2 && dy.abs() > dx.abs() * 3.0;
+    let is_horizontal = points.len() == 2 && dx.abs() > dy.abs() * 3.0;
+    let is_diagonal = points.len() == 2 && !is_vertical && !is_horizontal;
+
+    // Позиция текста зависит от типа линии
+    if let Some(center) = label_positions.label {
+        // Область подписи выбрана при layout: центрируем текст в ней,
+        // базовая линия последней строки — у нижнего края области
+        let lines = label.map_or(1, |l| l.replace("\\n", "\n").lines().count().max(1)) as f64;
+        (center.x, center.y + lines * 16.0 / 2.0 - 4.0, "middle")
+    } else if is_self_message {
+        // PlantUML: для self-message текст НАД верхней линией петли
+        (points[0].x + 5.0, points[0].y - 5.0, "start")
+    } else if points.len() == 4 {
+        // Ортогональный путь (4 точки): это обратный переход.
+        // Метка на ПЕРВОМ горизонтальном сегменте, справа от точки выхода
+        (points[0].x + 5.0, points[0].y - 5.0, "start")
+    } else if is_diagonal {
+        // Диагональная линия (state diagrams): текст вдоль стрелки на 40% длины,
+        // с внешней стороны — справа для линии вправо-вниз, слева для влево-вниз
+        let t = 0.40;
+        let text_x = points[0].x + dx * t;
+        let text_y = points[0].y + dy * t;
+        let offset = 8.0; // отступ от линии
+        if dx > 0.0 {
+            (text_x + offset, text_y, "start")
+        } else {
+            (text_x - offset, text_y, "end")
+        }
+    } else if is_vertical {
+        // Вертикальная линия: метка СПРАВА, ПОСЕРЕДИНЕ по высоте
+        let mid_y = (points[0].y + points[1].y) / 2.0;
+        (points[0].x + 5.0, mid_y, "start")
+    } else if is_horizontal {
+        // Горизонтальная стрелка (sequence diagrams): над линией у левого конца
+        let left_x = if dx > 0.0 {
+            points[0].x + 5.0
+        } else {
+            points[1].x + 5.0
+        };
+        (left_x, points[0].y - 5.0, "start")
+    } else {
+        // Fallback: середина первого сегмента
+        let mid_x = (points[0].x + points[1].x) / 2.0;
+        let mid_y = (points[0].y + points[1].y) / 2.0;
+        (mid_x, mid_y - 5.0, "middle")
+    }
+}
+
+/// Позиция кардинальности у начала (`at_start`) или конца линии
+///
+/// PlantUML: кардинальности СЛЕВА от вертикальной линии, близко к точкам
+/// соединения с классами; `center` — позиция, выбранная при layout.
+pub(crate) fn cardinality_anchor(
+    points: &[Point],
+    at_start: bool,
+    center: Option<Point>,
+) -> (f64, f64, &'static str) {
+    let horizontal_offset = 10.0; // отступ слева от линии
+    let vertical_offset = 12.0; // отступ от точки соединения вниз/вверх
+
+    if let Some(center) = center {
+        return (center.x, center.y, "middle");
+    }
+    let is_vertical = (points[1].y - points[0].y).abs() > (points[1].x - points[0].x).abs();
+    let (p, direction) = if at_start {
+        (points[0], 1.0)
+    } else {
+        (points[points.len() - 1], -1.0)
+    };
+    if is_vertical {
+        // Вертикальная линия: текст СЛЕВА, чуть отступив от точки соединения
+        (p.x - horizontal_offset, p.y + direction * vertical_offset, "end")
+    } else {
+        // Горизонтальная линия: текст сверху
+        (p.x + direction * vertical_offset, p.y - horizontal_offset / 2.0, "end")
+    }
+}
+
+/// Заливка и обводка фигуры
+struct ShapeStyle {
+    fill: String,
+    stroke: String,
+    /// None — атрибут `stroke-width` не выводится
+    stroke_width: Option<f64>,
+    /// Тень (`skinparam shadowing`), фильтр `#shadow` из определений
+    shadow: bool,
+}
+
+impl ShapeStyle {
+    /// Фигура без тени
+    fn new(fill: impl Into<String>, stroke: impl Into<String>, stroke_width: f64) -> Self {
+        Self {
+            fill: fill.into(),
+            stroke: stroke.into(),
+            stroke_width: Some(stroke_width),
+            shadow: false,
+        }
+    }
+
+    /// Залитая фигура без обводки
+    fn filled(fill: impl Into<String>) -> Self {
+        Self {
+            fill: fill.into(),
+            stroke: "none".to_string(),
+            stroke_width: None,
+            shadow: false,
+        }
+    }
+
+    /// Узел диаграммы: цвета темы и тень, если она включена
+    fn node(theme: &Theme, stroke_width: f64) -> Self {
+        Self {
+            shadow: theme.shadow,
+            ..Self::new(theme.node_background.to_css(), theme.node_border.to_css(), stroke_width)
+        }
+    }
+
+    /// Применяет стиль к элементу
+    fn apply<N: Node>(&self, mut node: N) -> N {
+        node.assign("fill", self.fill.as_str());
+        node.assign("stroke", self.stroke.as_str());
+        if let Some(width) = self.stroke_width {
+            node.assign("stroke-width", width);
+        }
+        if self.shadow {
+            node.assign("filter", "url(#shadow)");
+        }
+        node
+    }
 }
 
 impl Default for SvgRenderer {
@@ -1474,8 +1777,9 @@ impl Renderer for SvgRenderer {
         let mut sorted_elements: Vec<_> = layout.elements.iter().collect();
         sorted_elements.sort_by_key(|e| ZLayer::from_element(e));
 
+        let mut ids = ElementIds::default();
         for element in sorted_elements {
-            let rendered = self.render_element(element, theme);
+            let rendered = self.render_element(element, theme, &mut ids);
             doc = doc.add(rendered);
         }
 
@@ -1523,4 +1827,75 @@ mod tests {
         assert!(svg.contains("<rect"));
         assert!(svg.contains("Hello"));
     }
+
+    fn rectangle_layout() -> LayoutResult {
+        LayoutResult {
+            elements: vec![
+                LayoutElement::new(
+                    "node",
+                    Rect::new(10.0, 10.0, 100.0, 50.0),
+                    ElementType::Rectangle {
+                        label: "Hello".to_string(),
+                        corner_radius: 5.0,
+                    },
+                ),
+                LayoutElement::new(
+                    "edge",
+                    Rect::new(60.0, 60.0, 1.0, 40.0),
+                    ElementType::Edge {
+                        points: vec![Point::new(60.0, 60.0), Point::new(60.0, 100.0)],
+                        label: None,
+                        arrow_start: false,
+                        arrow_end: true,
+                        dashed: false,
+                        edge_type: EdgeType::Association,
+                        from_cardinality: None,
+                        to_cardinality: None,
+                        curve: Vec::new(),
+                        label_positions: EdgeLabelPositions::default(),
+                    },
+                ),
+            ],
+            bounds: Rect::new(0.0, 0.0, 120.0, 110.0),
+        }
+    }
+
+    #[test]
+    fn test_render_shadow() {
+        let renderer = SvgRenderer::new();
+        let layout = rectangle_layout();
+
+        let plain = renderer.render(&layout, &Theme::default());
+        assert!(!plain.contains("<filter"));
+        assert!(!plain.contains("url(#shadow)"));
+
+        let theme = Theme {
+            shadow: true,
+            ..Theme::default()
+        };
+        let svg = renderer.render(&layout, &theme);
+        assert!(svg.contains("<filter") && svg.contains("id=\"shadow\""));
+        assert!(svg.contains("feGaussianBlur"));
+        // Тень только у узла, не у ребра
+        assert_eq!(svg.matches("filter=\"url(#shadow)\"").count(), 1);
+    }
+
+    #[test]
+    fn test_render_handwritten() {
+        let renderer = SvgRenderer::new();
+        let layout = rectangle_layout();
+        let theme = Theme {
+            handwritten: true,
+            ..Theme::default()
+        };
+
+        let svg = renderer.render(&layout, &theme);
+        // Прямоугольник и линия заменены дрожащими путями
+        assert!(!svg.contains("<rect"));
+        assert!(svg.contains(" Q"));
+        // Вывод детерминирован
+        assert_eq!(svg, renderer.render(&layout, &theme));
+        // Без рукописного режима — обычные фигуры
+        assert!(renderer.render(&layout, &Theme::default()).contains("<rect"));
+    }
 }
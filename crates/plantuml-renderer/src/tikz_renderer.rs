@@ -0,0 +1,457 @@
+//! LaTeX/TikZ рендеринг (аналог `-tlatex` и `-tlatex:nopreamble`)
+//!
+//! Layout переводится в [`DisplayList`] и выводится командами TikZ: контуры
+//! `\path` с заливкой и обводкой, текст узлами `\node`, отсечение через
+//! `scope` и `\clip`. Ось Y перевёрнута опцией `y=-1pt`, поэтому координаты
+//! совпадают с SVG. Цвета объявляются через `\definecolor` (пакет xcolor
+//! подключается TikZ), гиперссылки не выводятся. Спрайты выводятся
+//! прямоугольниками из строк пикселей с `fill opacity`; SVG спрайты
+//! растеризуются только с features `png` или `pdf`.
+//!
+//! # Пример
+//!
+//! ```rust,ignore
+//! use plantuml_renderer::{Renderer, TikzRenderer};
+//!
+//! let tex = TikzRenderer::new().without_preamble().render(&layout, &theme);
+//! std::fs::write("diagram.tex", tex)?;
+//! ```
+
+use std::fmt::Write as _;
+
+use crate::display_list::{
+    num, parse_rgb, placed_markers, Bounds, DisplayList, DrawCommand, Font, ImageData, PathSegment,
+    Style, TextAnchor, TextBaseline,
+};
+use crate::{DisplayListRenderer, LayoutResult, RenderOptions, Renderer, SpriteRegistry, Theme};
+
+/// TikZ рендерер
+#[derive(Debug, Clone)]
+pub struct TikzRenderer {
+    options: RenderOptions,
+    /// Выводить полный документ `standalone`, а не только `tikzpicture`
+    preamble: bool,
+    sprites: SpriteRegistry,
+}
+
+impl Default for TikzRenderer {
+    fn default() -> Self {
+        Self {
+            options: RenderOptions::default(),
+            preamble: true,
+            sprites: SpriteRegistry::new(),
+        }
+    }
+}
+
+impl TikzRenderer {
+    /// Создаёт рендерер с опциями по умолчанию
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Создаёт рендерер с опциями (масштаб и фон)
+    pub fn with_options(options: RenderOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Только окружение `tikzpicture` для вставки в свой документ
+    pub fn without_preamble(mut self) -> Self {
+        self.preamble = false;
+        self
+    }
+
+    /// Задаёт спрайты диаграммы (`DiagramMetadata::sprites`)
+    pub fn with_sprites(mut self, sprites: SpriteRegistry) -> Self {
+        self.sprites = sprites;
+        self
+    }
+
+    /// Выводит display list в LaTeX
+    pub fn render_display_list(&self, list: &DisplayList) -> String {
+        let scale = self.options.scale;
+        let mut picture = Picture {
+            body: String::new(),
+            colors: Vec::new(),
+            scale,
+        };
+
+        if let Some(background) = list.background.as_deref().and_then(|c| picture.color(c)) {
+            let _ = writeln!(
+                picture.body,
+                "\\fill[{}] ({},{}) rectangle ({},{});",
+                background,
+                num(list.x),
+                num(list.y),
+                num(list.x + list.width),
+                num(list.y + list.height)
+            );
+        }
+        for command in &list.commands {
+            picture.command(command);
+        }
+
+        let mut out = String::new();
+        if self.preamble {
+            out.push_str("\\documentclass[tikz]{standalone}\n");
+            out.push_str("\\usepackage[utf8]{inputenc}\n");
+            out.push_str("\\begin{document}\n");
+        }
+        let _ = writeln!(
+            out,
+            "\\begin{{tikzpicture}}[x={}pt, y=-{}pt]",
+            num(scale),
+            num(scale)
+        );
+        for (name, [r, g, b]) in &picture.colors {
+            let _ = writeln!(out, "\\definecolor{{{}}}{{RGB}}{{{},{},{}}}", name, r, g, b);
+        }
+        out.push_str(&picture.body);
+        out.push_str("\\end{tikzpicture}\n");
+        if self.preamble {
+            out.push_str("\\end{document}\n");
+        }
+        out
+    }
+}
+
+impl Renderer for TikzRenderer {
+    type Output = String;
+
+    fn render(&self, layout: &LayoutResult, theme: &Theme) -> Self::Output {
+        let list = DisplayListRenderer::with_options(self.options.clone())
+            .with_sprites(self.sprites.clone())
+            .render(layout, theme);
+        self.render_display_list(&list)
+    }
+}
+
+/// Тело `tikzpicture` и объявленные цвета
+struct Picture {
+    body: String,
+    /// Имена цветов в порядке первого использования
+    colors: Vec<(String, [u8; 3])>,
+    scale: f64,
+}
+
+impl Picture {
+    /// Имя цвета TikZ; новый цвет объявляется перед рисунком
+    fn color(&mut self, css: &str) -> Option<String> {
+        Some(self.rgb_color(parse_rgb(css)?))
+    }
+
+    /// Имя цвета TikZ для RGB
+    fn rgb_color(&mut self, rgb: [u8; 3]) -> String {
+        if let Some((name, _)) = self.colors.iter().find(|(_, known)| *known == rgb) {
+            return name.clone();
+        }
+        let name = format!("pu{}", self.colors.len() + 1);
+        self.colors.push((name.clone(), rgb));
+        name
+    }
+
+    fn command(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Path {
+                segments,
+                style,
+                marker_start,
+                marker_end,
+            } => {
+                self.path(segments, style);
+                for (segments, style) in
+                    placed_markers(segments, marker_start.as_ref(), marker_end.as_ref())
+                {
+                    self.path(&segments, &style);
+                }
+            }
+            DrawCommand::Text {
+                x,
+                y,
+                text,
+                font,
+                anchor,
+                baseline,
+                color,
+            } => {
+                let Some(color) = self.color(color) else {
+                    return;
+                };
+                let side = match anchor {
+                    TextAnchor::Start => "west",
+                    TextAnchor::Middle => "",
+                    TextAnchor::End => "east",
+                };
+                let anchor = match (baseline, side) {
+                    (TextBaseline::Alphabetic, "") => "base".to_string(),
+                    (TextBaseline::Alphabetic, side) => format!("base {}", side),
+                    (TextBaseline::Middle, "") => "center".to_string(),
+                    (TextBaseline::Middle, side) => side.to_string(),
+                };
+                let mut text = latex_escape(text);
+                if font.underline {
+                    text = format!("\\underline{{{}}}", text);
+                }
+                let _ = writeln!(
+                    self.body,
+                    "\\node[anchor={}, inner sep=0pt, outer sep=0pt, text={}, font={}] at ({},{}) {{{}}};",
+                    anchor,
+                    color,
+                    font_commands(font, self.scale),
+                    num(*x),
+                    num(*y),
+                    text
+                );
+            }
+            DrawCommand::Image {
+                x,
+                y,
+                width,
+                height,
+                image,
+            } => self.image((*x, *y, *width, *height), image),
+            DrawCommand::Group {
+                id, clip, commands, ..
+            } => {
+                if let Some(id) = id {
+                    let _ = writeln!(self.body, "% {}", id);
+                }
+                if let Some(clip) = clip {
+                    let _ = writeln!(
+                        self.body,
+                        "\\begin{{scope}}\n\\clip ({},{}) rectangle ({},{});",
+                        num(clip.x),
+                        num(clip.y),
+                        num(clip.x + clip.width),
+                        num(clip.y + clip.height)
+                    );
+                }
+                for command in commands {
+                    self.command(command);
+                }
+                if clip.is_some() {
+                    self.body.push_str("\\end{scope}\n");
+                }
+            }
+        }
+    }
+
+    /// Изображение строками пикселей
+    fn image(&mut self, bounds: Bounds, image: &ImageData) {
+        let Some(pixels) = image.pixels(bounds.2.ceil() as u32, bounds.3.ceil() as u32) else {
+            return;
+        };
+        for ((x, y, width, height), [r, g, b, a]) in pixels.rects(bounds) {
+            let color = self.rgb_color([r, g, b]);
+            let opacity = if a == 255 {
+                String::new()
+            } else {
+                format!(", fill opacity={}", num(f64::from(a) / 255.0))
+            };
+            let _ = writeln!(
+                self.body,
+                "\\fill[{}{}] ({},{}) rectangle ({},{});",
+                color,
+                opacity,
+                num(x),
+                num(y),
+                num(x + width),
+                num(y + height)
+            );
+        }
+    }
+
+    fn path(&mut self, segments: &[PathSegment], style: &Style) {
+        let mut options = Vec::new();
+        let fill = style.fill.as_deref().and_then(|c| self.color(c));
+        if let Some(fill) = &fill {
+            options.push(format!("fill={}", fill));
+        }
+        if let Some(stroke) = &style.stroke {
+            if let Some(color) = self.color(&stroke.color) {
+                options.push(format!("draw={}", color));
+                options.push(format!("line width={}pt", num(stroke.width * self.scale)));
+                if !stroke.dash.is_empty() {
+                    let pattern: Vec<String> = stroke
+                        .dash
+                        .chunks(2)
+                        .map(|pair| {
+                            let off = pair.get(1).copied().unwrap_or(pair[0]);
+                            format!("on {}pt off {}pt", num(pair[0]), num(off))
+                        })
+                        .collect();
+                    options.push(format!("dash pattern={}", pattern.join(" ")));
+                }
+            }
+        }
+        if options.is_empty() {
+            return;
+        }
+
+        let path = path_data(segments);
+        if fill.is_some() && style.shadow {
+            let _ = writeln!(
+                self.body,
+                "\\fill[black, fill opacity=0.35, shift={{(3,3)}}] {};",
+                path
+            );
+        }
+        let _ = writeln!(self.body, "\\path[{}] {};", options.join(", "), path);
+    }
+}
+
+/// Контур в синтаксисе TikZ
+fn path_data(segments: &[PathSegment]) -> String {
+    let mut d = String::new();
+    for segment in segments {
+        let _ = match *segment {
+            PathSegment::MoveTo { x, y } => write!(d, " ({},{})", num(x), num(y)),
+            PathSegment::LineTo { x, y } => write!(d, " -- ({},{})", num(x), num(y)),
+            PathSegment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => write!(
+                d,
+                " .. controls ({},{}) and ({},{}) .. ({},{})",
+                num(x1),
+                num(y1),
+                num(x2),
+                num(y2),
+                num(x),
+                num(y)
+            ),
+            PathSegment::Close => write!(d, " -- cycle"),
+        };
+    }
+    d.trim_start().to_string()
+}
+
+/// Команды шрифта: кегль с учётом масштаба, семейство и начертание
+fn font_commands(font: &Font, scale: f64) -> String {
+    let size = font.size * scale;
+    let family = font.family.to_lowercase();
+    let family = if family.contains("mono") || family.contains("courier") {
+        "\\ttfamily"
+    } else if family.contains("serif") && !family.contains("sans") {
+        "\\rmfamily"
+    } else {
+        "\\sffamily"
+    };
+    let mut commands = format!(
+        "\\fontsize{{{}}}{{{}}}\\selectfont{}",
+        num(size),
+        num(size * 1.2),
+        family
+    );
+    if font.bold {
+        commands.push_str("\\bfseries");
+    }
+    if font.italic {
+        commands.push_str("\\itshape");
+    }
+    commands
+}
+
+/// Экранирует спецсимволы LaTeX
+fn latex_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_list::{ClipRect, Marker, MarkerKind};
+    use crate::{ElementType, LayoutElement, Rect};
+
+    #[test]
+    fn test_render_layout() {
+        let mut layout = LayoutResult::empty();
+        layout.elements.push(LayoutElement::new(
+            "a",
+            Rect::new(0.0, 0.0, 80.0, 30.0),
+            ElementType::Rectangle {
+                label: "A_1 & B".to_string(),
+                corner_radius: 0.0,
+            },
+        ));
+        layout.calculate_bounds();
+
+        let tex = TikzRenderer::new().render(&layout, &Theme::default());
+        assert!(tex.starts_with("\\documentclass[tikz]{standalone}\n"));
+        assert!(tex.contains("\\begin{tikzpicture}[x=1pt, y=-1pt]"));
+        assert!(tex.contains("\\definecolor{pu1}{RGB}"));
+        assert!(tex.contains("{A\\_1 \\& B};"));
+        assert!(tex.ends_with("\\end{document}\n"));
+
+        let body = TikzRenderer::new()
+            .without_preamble()
+            .render(&layout, &Theme::default());
+        assert!(body.starts_with("\\begin{tikzpicture}"));
+        assert!(body.ends_with("\\end{tikzpicture}\n"));
+    }
+
+    #[test]
+    fn test_paths_and_clip() {
+        let mut list = DisplayList::new(0.0, 0.0, 100.0, 40.0);
+        list.commands.push(DrawCommand::Group {
+            id: Some("edge".to_string()),
+            class: None,
+            line: None,
+            link: None,
+            clip: Some(ClipRect {
+                x: 0.0,
+                y: 0.0,
+                width: 50.0,
+                height: 40.0,
+            }),
+            commands: vec![DrawCommand::Path {
+                segments: PathSegment::polyline(&[(0.0, 20.0), (100.0, 20.0)]),
+                style: Style::stroke("#181818", 1.0).with_dash(&[2.0, 2.0]),
+                marker_start: None,
+                marker_end: Some(Marker::new(MarkerKind::Arrow, "#181818", "#181818")),
+            }],
+        });
+
+        let tex = TikzRenderer::new()
+            .without_preamble()
+            .render_display_list(&list);
+        assert!(tex.contains("% edge\n\\begin{scope}\n\\clip (0,0) rectangle (50,40);"));
+        assert!(tex.contains(
+            "\\path[draw=pu1, line width=1pt, dash pattern=on 2pt off 2pt] (0,20) -- (100,20);"
+        ));
+        // Один цвет объявляется один раз
+        assert_eq!(tex.matches("\\definecolor").count(), 1);
+        assert!(
+            tex.contains("\\path[fill=pu1] (90,16) -- (100,20) -- (90,24) -- (94,20) -- cycle;")
+        );
+    }
+
+    #[test]
+    fn test_font_commands() {
+        let font = Font::new("monospace", 10.0).bold().italic();
+        assert_eq!(
+            font_commands(&font, 2.0),
+            "\\fontsize{20}{24}\\selectfont\\ttfamily\\bfseries\\itshape"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Сжимает исходники стандартной библиотеки из `stdlib/` для встраивания
+//!
+//! Каждая библиотека — подкаталог `stdlib/<имя>` под своей cargo feature.
+//! Файлы `.puml`/`.iuml` включённых библиотек сжимаются raw deflate в
+//! `OUT_DIR`, а `stdlib_index.rs` перечисляет их по пути для `include_bytes!`.
+//! Включённая feature без каталога библиотеки — ошибка сборки.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+/// Каталог библиотеки и включающая её feature
+const LIBRARIES: &[(&str, &str)] = &[
+    ("C4", "c4"),
+    ("awslib", "awslib"),
+    ("azure", "azure"),
+    ("kubernetes", "kubernetes"),
+    ("material", "material"),
+    ("tupadr3", "tupadr3"),
+];
+
+fn main() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("stdlib");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    println!("cargo:rerun-if-changed=stdlib");
+
+    let mut libraries = Vec::new();
+    let mut files = Vec::new();
+    for (library, feature) in LIBRARIES {
+        if env::var_os(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_none() {
+            continue;
+        }
+        let dir = root.join(library);
+        if !dir.is_dir() {
+            panic!(
+                "библиотека {} включена feature `{}`, но не найдена в stdlib/ — загрузите её scripts/stdlib.sh",
+                library, feature
+            );
+        }
+        libraries.push(*library);
+        collect(&dir, library, &mut files);
+    }
+    files.sort();
+
+    let mut index = String::from("/// Встроенные файлы: путь и содержимое, сжатое deflate\n");
+    index.push_str("static FILES: &[(&str, &[u8])] = &[\n");
+    for (i, (path, source)) in files.iter().enumerate() {
+        let name = format!("stdlib_{}.deflate", i);
+        let data = fs::read(source).unwrap();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&data).unwrap();
+        fs::write(out_dir.join(&name), encoder.finish().unwrap()).unwrap();
+        index.push_str(&format!(
+            "    ({:?}, include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}\"))),\n",
+            path, name
+        ));
+    }
+    index.push_str("];\n\n/// Включённые библиотеки\n");
+    index.push_str(&format!(
+        "static LIBRARIES: &[&str] = &{:?};\n",
+        libraries.as_slice()
+    ));
+    fs::write(out_dir.join("stdlib_index.rs"), index).unwrap();
+}
+
+/// Собирает файлы `.puml`/`.iuml` каталога рекурсивно с путями от `stdlib/`
+fn collect(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) {
+    let mut entries: Vec<_> = fs::read_dir(dir).unwrap().flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() {
+            collect(&path, &format!("{}/{}", prefix, name), files);
+        } else if name.ends_with(".puml") || name.ends_with(".iuml") {
+            files.push((format!("{}/{}", prefix, name), path));
+        }
+    }
+}
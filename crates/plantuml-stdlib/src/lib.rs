@@ -2,32 +2,248 @@
 //!
 //! Стандартная библиотека PlantUML: иконки, спрайты, макросы.
 //!
-//! Включает:
-//! - AWS Architecture Icons
-//! - Azure Icons  
-//! - Kubernetes Icons
-//! - C4 Model
-//! - Material Design Icons
-//! - и другие
+//! Исходники библиотек встраиваются в бинарник в сжатом виде, поэтому
+//! `!include <C4/C4_Container>` работает и в WASM, без файловой системы.
+//! Каждая библиотека включается своей feature:
+//!
+//! - `c4` — C4 Model (по умолчанию, поставляется с крейтом)
+//! - `awslib` — AWS Architecture Icons
+//! - `azure` — Azure Icons
+//! - `kubernetes` — Kubernetes Icons
+//! - `material` — Material Design Icons
+//! - `tupadr3` — Font Awesome, Devicons и другие наборы tupadr3
+//! - `full` — все библиотеки
+//!
+//! В репозитории есть только `stdlib/C4`. Остальные библиотеки не
+//! поставляются: их исходники нужно загрузить из plantuml-stdlib скриптом
+//! `scripts/stdlib.sh` до сборки, иначе feature ничего не встраивает (сборка
+//! выводит предупреждение).
+
+use std::io::Read;
+use std::sync::OnceLock;
+
+use flate2::read::DeflateDecoder;
+use plantuml_preprocessor::{FileResolver, NoopFileResolver, PreprocessError, Result};
 
-// TODO: Реализовать загрузку и управление стандартной библиотекой
+include!(concat!(env!("OUT_DIR"), "/stdlib_index.rs"));
 
-/// Получить спрайт по имени
-pub fn get_sprite(_name: &str) -> Option<&'static str> {
-    // TODO: Реализовать
-    None
+/// Определение спрайта по имени (`$name` или `name`) из встроенных библиотек:
+/// строки от `sprite $name` до конца данных
+///
+/// В библиотеке C4 спрайтов нет; они появляются с библиотеками иконок,
+/// загруженными `scripts/stdlib.sh`.
+pub fn get_sprite(name: &str) -> Option<&'static str> {
+    let name = name.trim_start_matches('$');
+    sources()
+        .iter()
+        .find_map(|(_, source)| sprite_definition(source, name))
 }
 
-/// Получить макрос по имени
-pub fn get_macro(_name: &str) -> Option<&'static str> {
-    // TODO: Реализовать
-    None
+/// Определение `!procedure`/`!function` (в том числе `!unquoted`) по имени
+/// из встроенных библиотек, вместе с телом и закрывающей директивой
+///
+/// # Пример
+///
+/// ```rust
+/// let container = plantuml_stdlib::get_macro("Container").unwrap();
+/// assert!(container.starts_with("!unquoted procedure Container($alias"));
+/// ```
+pub fn get_macro(name: &str) -> Option<&'static str> {
+    sources()
+        .iter()
+        .find_map(|(_, source)| macro_definition(source, name))
 }
 
 /// Проверить, существует ли элемент в stdlib
-pub fn exists(_path: &str) -> bool {
-    // TODO: Реализовать
-    false
+///
+/// Путь указывается как в `!include <...>`: с расширением или без.
+pub fn exists(path: &str) -> bool {
+    find(path).is_some()
+}
+
+/// Читает файл стандартной библиотеки
+///
+/// # Пример
+///
+/// ```rust
+/// let source = plantuml_stdlib::read("<C4/C4_Container>").unwrap();
+/// assert!(source.contains("Container"));
+/// ```
+pub fn read(path: &str) -> Option<String> {
+    let (_, data) = find(path)?;
+    let mut source = String::new();
+    DeflateDecoder::new(*data)
+        .read_to_string(&mut source)
+        .ok()?;
+    Some(source)
+}
+
+/// Встроенные библиотеки
+pub fn libraries() -> &'static [&'static str] {
+    LIBRARIES
+}
+
+/// Пути всех встроенных файлов (с расширением)
+pub fn files() -> impl Iterator<Item = &'static str> {
+    FILES.iter().map(|(path, _)| *path)
+}
+
+/// Ищет файл по пути `<lib/file>`, `lib/file`, `lib/file.puml` или `stdlib/lib/file`
+fn find(path: &str) -> Option<&'static (&'static str, &'static [u8])> {
+    let path = path.trim().trim_start_matches('<').trim_end_matches('>');
+    let path = path.strip_prefix("stdlib/").unwrap_or(path);
+    [
+        path.to_string(),
+        format!("{}.puml", path),
+        format!("{}.iuml", path),
+    ]
+    .iter()
+    .find_map(|candidate| {
+        FILES
+            .binary_search_by(|(file, _)| (*file).cmp(candidate.as_str()))
+            .ok()
+            .map(|index| &FILES[index])
+    })
+}
+
+/// `FileResolver` для `!include <...>` из встроенной стандартной библиотеки
+///
+/// Пути в угловых скобках читаются из stdlib, остальные передаются
+/// вложенному resolver'у (по умолчанию `NoopFileResolver`).
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_preprocessor::{FsFileResolver, Preprocessor};
+/// use plantuml_stdlib::StdlibResolver;
+///
+/// let resolver = StdlibResolver::with_fallback(FsFileResolver::new("."));
+/// let preprocessor = Preprocessor::with_resolver(resolver);
+/// ```
+#[derive(Debug, Default)]
+pub struct StdlibResolver<R: FileResolver = NoopFileResolver> {
+    fallback: R,
+}
+
+impl StdlibResolver {
+    /// Создаёт resolver только для стандартной библиотеки
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<R: FileResolver> StdlibResolver<R> {
+    /// Создаёт resolver, передающий прочие пути в `fallback`
+    pub fn with_fallback(fallback: R) -> Self {
+        Self { fallback }
+    }
+
+    /// Вложенный resolver
+    pub fn fallback(&self) -> &R {
+        &self.fallback
+    }
+}
+
+impl<R: FileResolver> FileResolver for StdlibResolver<R> {
+    fn read_file(&self, path: &str) -> Result<String> {
+        if !is_stdlib_path(path) {
+            return self.fallback.read_file(path);
+        }
+        match read(path) {
+            Some(source) => Ok(source),
+            // Библиотека может лежать на диске в путях поиска
+            None if self.fallback.file_exists(path) => self.fallback.read_file(path),
+            None => Err(PreprocessError::FileNotFound(format!(
+                "{} (стандартная библиотека: {})",
+                path,
+                LIBRARIES.join(", ")
+            ))),
+        }
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        if is_stdlib_path(path) && exists(path) {
+            return true;
+        }
+        self.fallback.file_exists(path)
+    }
+
+    fn read_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        if is_stdlib_path(path) {
+            return self.read_file(path).map(String::into_bytes);
+        }
+        self.fallback.read_bytes(path)
+    }
+
+    fn max_include_depth(&self) -> usize {
+        self.fallback.max_include_depth()
+    }
+}
+
+/// Распакованные исходники всех встроенных файлов; распаковываются при
+/// первом обращении к `get_sprite`/`get_macro`
+fn sources() -> &'static [(&'static str, String)] {
+    static SOURCES: OnceLock<Vec<(&'static str, String)>> = OnceLock::new();
+    SOURCES.get_or_init(|| {
+        FILES
+            .iter()
+            .filter_map(|(path, _)| Some((*path, read(path)?)))
+            .collect()
+    })
+}
+
+/// Ищет `sprite $name ...`: данные в `{...}` или `<svg>...</svg>` могут
+/// занимать несколько строк
+fn sprite_definition<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    let (start, line) = lines_with_offsets(source).find(|(_, line)| {
+        line.trim_start()
+            .strip_prefix("sprite")
+            .and_then(|rest| rest.trim_start().strip_prefix('$'))
+            .and_then(|rest| rest.strip_prefix(name))
+            .is_some_and(|rest| rest.starts_with([' ', '\t', '[', '<', '{']))
+    })?;
+    let terminator = if line.contains("<svg") {
+        "</svg>"
+    } else if line.contains('{') {
+        "}"
+    } else {
+        return Some(line.trim_end());
+    };
+    let end = source[start..].find(terminator)? + start + terminator.len();
+    Some(&source[start..end])
+}
+
+/// Ищет `!procedure name(`/`!function name(` и возвращает определение до
+/// `!endprocedure`/`!endfunction`
+fn macro_definition<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    let (start, _) = lines_with_offsets(source).find(|(_, line)| {
+        let Some(line) = line.trim_start().strip_prefix('!') else {
+            return false;
+        };
+        let line = line.strip_prefix("unquoted ").unwrap_or(line).trim_start();
+        line.strip_prefix("procedure ")
+            .or_else(|| line.strip_prefix("function "))
+            .and_then(|rest| rest.trim_start().strip_prefix(name))
+            .is_some_and(|rest| rest.trim_start().starts_with('('))
+    })?;
+    let (end, line) = lines_with_offsets(&source[start..]).find(|(_, line)| {
+        matches!(line.trim(), "!endprocedure" | "!endfunction")
+    })?;
+    Some(&source[start..start + end + line.trim_end().len()])
+}
+
+/// Строки текста с байтовыми смещениями их начала
+fn lines_with_offsets(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+fn is_stdlib_path(path: &str) -> bool {
+    let path = path.trim();
+    path.starts_with('<') && path.ends_with('>')
 }
 
 #[cfg(test)]
@@ -35,7 +251,65 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_placeholder() {
-        assert!(get_sprite("aws/Compute").is_none());
+    fn test_get_macro() {
+        let container = get_macro("Container").unwrap();
+        assert!(container.starts_with("!unquoted procedure Container($alias, $label"));
+        assert!(container.ends_with("!endprocedure"));
+        assert!(!container.contains("ContainerDb"));
+
+        let label = get_macro("$c4_label").unwrap();
+        assert!(label.starts_with("!function $c4_label("));
+        assert!(label.ends_with("!endfunction"));
+        assert!(get_macro("Contain").is_none());
+        assert!(get_macro("Unknown").is_none());
+    }
+
+    #[test]
+    fn test_sprite_definition() {
+        let source = "' иконки\nsprite $dot [3x3/4] {\nFFF\n}\nsprite $logo <svg viewBox=\"0 0 4 4\">\n<circle r=\"2\"/>\n</svg>\nsprite $dots [3x3/4z] eJzLSg=\n";
+        assert_eq!(
+            sprite_definition(source, "dot"),
+            Some("sprite $dot [3x3/4] {\nFFF\n}")
+        );
+        assert_eq!(
+            sprite_definition(source, "logo"),
+            Some("sprite $logo <svg viewBox=\"0 0 4 4\">\n<circle r=\"2\"/>\n</svg>")
+        );
+        assert_eq!(
+            sprite_definition(source, "dots"),
+            Some("sprite $dots [3x3/4z] eJzLSg=")
+        );
+        assert!(sprite_definition(source, "do").is_none());
+        // В C4 спрайтов нет
+        assert!(get_sprite("$dot").is_none());
+    }
+
+    #[test]
+    fn test_read_c4() {
+        assert!(libraries().contains(&"C4"));
+        assert!(exists("C4/C4_Container"));
+        assert!(exists("<C4/C4_Context>"));
+        assert!(exists("stdlib/C4/C4_Component.puml"));
+        assert!(!exists("C4/Unknown"));
+
+        let source = read("C4/C4_Container").unwrap();
+        assert!(source.contains("!include <C4/C4_Context>"));
+        assert!(files().any(|path| path == "C4/C4.puml"));
+    }
+
+    #[test]
+    fn test_resolver() {
+        let resolver = StdlibResolver::new();
+        assert!(resolver.file_exists("<C4/C4>"));
+        assert!(!resolver.file_exists("C4/C4"));
+        assert!(resolver.read_file("<C4/C4_Component>").is_ok());
+        assert!(matches!(
+            resolver.read_file("<C4/Unknown>"),
+            Err(PreprocessError::FileNotFound(_))
+        ));
+        assert!(matches!(
+            resolver.read_file("common.puml"),
+            Err(PreprocessError::IncludeNotSupported(_))
+        ));
     }
 }
@@ -225,6 +225,16 @@ impl SkinParams {
         self.params.get(key)
     }
 
+    /// Получает параметр без учёта регистра ключа (`lineType` == `linetype`)
+    pub fn get_ignore_case(&self, key: &str) -> Option<&String> {
+        self.get(key).or_else(|| {
+            self.params
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v)
+        })
+    }
+
     /// Применяет параметры к теме
     pub fn apply_to(&self, theme: &mut Theme) {
         if let Some(v) = self.get("backgroundColor") {
@@ -274,4 +284,13 @@ mod tests {
 
         assert_eq!(theme.background_color.to_css(), "#FF0000");
     }
+
+    #[test]
+    fn test_skin_params_ignore_case() {
+        let mut params = SkinParams::new();
+        params.set("lineType", "ortho");
+
+        assert_eq!(params.get("linetype"), None);
+        assert_eq!(params.get_ignore_case("linetype").map(String::as_str), Some("ortho"));
+    }
 }
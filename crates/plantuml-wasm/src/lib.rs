@@ -5,7 +5,7 @@
 //! ## Использование в JavaScript
 //!
 //! ```javascript
-//! import init, { render, parse } from 'plantuml-wasm';
+//! import init, { render, render_display_list, encode, decode } from 'plantuml-wasm';
 //!
 //! async function main() {
 //!     await init();
@@ -18,6 +18,19 @@
 //!     
 //!     const svg = render(source);
 //!     document.getElementById('diagram').innerHTML = svg;
+//!
+//!     // Команды рисования для собственного canvas; спрайты приходят
+//!     // командами Image с пикселями `gray` или исходником `svg`
+//!     const list = JSON.parse(render_display_list(source));
+//!
+//!     // Ссылка на диаграмму в формате сервера PlantUML и обратно
+//!     const url = `https://www.plantuml.com/plantuml/svg/${encode(source)}`;
+//!     const restored = decode(encode(source));
+//!
+//!     // !include из файлов в памяти
+//!     const files = new MemoryFileResolver();
+//!     files.add_file('common.puml', 'skinparam monochrome true');
+//!     const withIncludes = files.render('@startuml\n!include common.puml\nA -> B\n@enduml');
 //! }
 //! ```
 
@@ -74,6 +87,91 @@ pub fn parse_to_json(source: &str) -> Result<String, JsValue> {
         .map_err(|e: serde_json::Error| JsValue::from_str(&e.to_string()))
 }
 
+/// Строит display list диаграммы для рисования на canvas
+///
+/// @param source - PlantUML исходный код
+/// @returns JSON: `{x, y, width, height, background, commands}`, команды с тегом
+/// `type` (`path`, `text`, `group`), сегменты контуров с тегом `op`
+#[wasm_bindgen]
+pub fn render_display_list(source: &str) -> Result<String, JsValue> {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let list = plantuml_core::render_display_list(source, &RenderOptions::default())
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    serde_json::to_string(&list).map_err(|e: serde_json::Error| JsValue::from_str(&e.to_string()))
+}
+
+/// Файлы в памяти для `!include` (виртуальная файловая система)
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct MemoryFileResolver {
+    inner: plantuml_core::MemoryFileResolver,
+}
+
+#[wasm_bindgen]
+impl MemoryFileResolver {
+    /// Создаёт пустую файловую систему
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет или заменяет файл
+    ///
+    /// @param path - путь (`lib/common.puml`, `./a.puml`)
+    /// @param content - текст файла
+    pub fn add_file(&mut self, path: &str, content: &str) {
+        self.inner.add_file(path, content);
+    }
+
+    /// Добавляет двоичный файл (zip-архив для `!import`)
+    pub fn add_binary_file(&mut self, path: &str, content: Vec<u8>) {
+        self.inner.add_file(path, content);
+    }
+
+    /// Удаляет файл; возвращает `true`, если он был
+    pub fn remove_file(&mut self, path: &str) -> bool {
+        self.inner.remove_file(path)
+    }
+
+    /// Удаляет все файлы
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Рендерит диаграмму в SVG, читая `!include` из этих файлов
+    ///
+    /// @param source - PlantUML исходный код
+    /// @returns SVG строка или ошибка
+    pub fn render(&self, source: &str) -> Result<String, JsValue> {
+        #[cfg(feature = "console_error_panic_hook")]
+        set_panic_hook();
+
+        plantuml_core::render_with_resolver(source, &self.inner, &RenderOptions::default())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Кодирует исходный код для URL сервера PlantUML
+///
+/// @param source - PlantUML исходный код
+/// @returns строка deflate + base64 PlantUML (`/svg/<encoded>`)
+#[wasm_bindgen]
+pub fn encode(source: &str) -> String {
+    plantuml_core::encode(source)
+}
+
+/// Декодирует строку из URL сервера PlantUML
+///
+/// @param encoded - закодированный текст (также с префиксами `~1` и `~h`)
+/// @returns исходный код или ошибка
+#[wasm_bindgen]
+pub fn decode(encoded: &str) -> Result<String, JsValue> {
+    plantuml_core::decode(encoded).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
 /// Возвращает версию библиотеки
 #[wasm_bindgen]
 pub fn version() -> String {
@@ -97,4 +195,30 @@ mod tests {
     fn test_version() {
         assert!(!version().is_empty());
     }
+
+    #[test]
+    fn test_render_display_list() {
+        let json = render_display_list("@startuml\nAlice -> Bob: Hello\n@enduml").unwrap();
+        let list: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(list["commands"][0]["type"], "group");
+    }
+
+    #[test]
+    fn test_memory_file_resolver() {
+        let mut files = MemoryFileResolver::new();
+        files.add_file("lib/target.puml", "!$target = \"Bob\"");
+        let svg = files
+            .render("@startuml\n!include lib/target.puml\nAlice -> $target\n@enduml")
+            .unwrap();
+        assert!(svg.contains("Bob"));
+
+        assert!(files.remove_file("lib/target.puml"));
+        assert!(!files.remove_file("lib/target.puml"));
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let source = "@startuml\nAlice -> Bob: Hello\n@enduml";
+        assert_eq!(decode(&encode(source)).unwrap(), source);
+    }
 }
@@ -0,0 +1,111 @@
+#!/bin/bash
+# ============================================================================
+# stdlib.sh — Загрузка библиотек plantuml-stdlib для встраивания в plantuml-stdlib
+# ============================================================================
+
+set -e
+
+# Цвета для вывода
+RED='\033[0;31m'
+GREEN='\033[0;32m'
+BLUE='\033[0;34m'
+NC='\033[0m' # No Color
+
+print_header() {
+    echo -e "${BLUE}============================================${NC}"
+    echo -e "${BLUE}  $1${NC}"
+    echo -e "${BLUE}============================================${NC}"
+}
+
+print_success() {
+    echo -e "${GREEN}✓ $1${NC}"
+}
+
+print_error() {
+    echo -e "${RED}✗ $1${NC}"
+}
+
+# Переход в корень проекта
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(dirname "$SCRIPT_DIR")"
+cd "$PROJECT_ROOT"
+
+STDLIB_REPO=${STDLIB_REPO:-https://github.com/plantuml/plantuml-stdlib.git}
+TARGET_DIR="$PROJECT_ROOT/crates/plantuml-stdlib/stdlib"
+# Закреплённый коммит plantuml-stdlib: полный SHA-1 в файле COMMIT
+PIN_FILE="$TARGET_DIR/COMMIT"
+
+if ! command -v git &> /dev/null; then
+    print_error "git не найден"
+    exit 1
+fi
+
+# --update [ref]: закрепить текущий коммит ветки или тега (по умолчанию master)
+if [ "$1" = "--update" ]; then
+    ref=${2:-master}
+    commit=$(git ls-remote "$STDLIB_REPO" "$ref" | head -n 1 | cut -f 1)
+    if [ -z "$commit" ]; then
+        print_error "$ref не найден в $STDLIB_REPO"
+        exit 1
+    fi
+    echo "$commit" > "$PIN_FILE"
+    print_success "закреплён $ref = $commit"
+    exit 0
+fi
+
+STDLIB_COMMIT=${STDLIB_COMMIT:-$(cat "$PIN_FILE" 2> /dev/null || true)}
+if ! [[ "$STDLIB_COMMIT" =~ ^[0-9a-f]{40}$ ]]; then
+    print_error "нет закреплённого коммита: запустите $0 --update [ref] или задайте STDLIB_COMMIT"
+    exit 1
+fi
+
+# Библиотеки по умолчанию (C4 поставляется в репозитории)
+LIBRARIES=("$@")
+if [ ${#LIBRARIES[@]} -eq 0 ]; then
+    LIBRARIES=(awslib azure kubernetes material tupadr3)
+fi
+
+print_header "Загрузка plantuml-stdlib ($STDLIB_COMMIT)"
+
+TMP_DIR="$(mktemp -d)"
+trap 'rm -rf "$TMP_DIR"' EXIT
+
+git init -q "$TMP_DIR/stdlib"
+git -C "$TMP_DIR/stdlib" fetch -q --depth 1 "$STDLIB_REPO" "$STDLIB_COMMIT"
+git -C "$TMP_DIR/stdlib" checkout -q FETCH_HEAD
+if [ "$(git -C "$TMP_DIR/stdlib" rev-parse HEAD)" != "$STDLIB_COMMIT" ]; then
+    print_error "получен не тот коммит: ожидался $STDLIB_COMMIT"
+    exit 1
+fi
+
+# Лицензия plantuml-stdlib распространяется вместе с библиотеками
+for license in LICENSE LICENSE.md LICENSE.txt; do
+    if [ -f "$TMP_DIR/stdlib/$license" ]; then
+        cp "$TMP_DIR/stdlib/$license" "$TARGET_DIR/LICENSE.plantuml-stdlib"
+        break
+    fi
+done
+
+for library in "${LIBRARIES[@]}"; do
+    # В зависимости от версии библиотеки лежат в корне или в stdlib/
+    source_dir=""
+    for candidate in "$TMP_DIR/stdlib/$library" "$TMP_DIR/stdlib/stdlib/$library"; do
+        if [ -d "$candidate" ]; then
+            source_dir="$candidate"
+            break
+        fi
+    done
+
+    if [ -z "$source_dir" ]; then
+        print_error "библиотека $library не найдена в $STDLIB_REPO@$STDLIB_COMMIT"
+        exit 1
+    fi
+
+    rm -rf "${TARGET_DIR:?}/$library"
+    cp -R "$source_dir" "$TARGET_DIR/$library"
+    print_success "$library"
+done
+
+echo ""
+echo "Соберите с нужными features, например:"
+echo "  cargo build -p plantuml-stdlib --features full"